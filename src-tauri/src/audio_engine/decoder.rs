@@ -2,47 +2,95 @@ use std::fs::File;
 use symphonia::core::audio::{AudioBufferRef, Signal};
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error as SymphoniaError;
-use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo, Track};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use symphonia::core::units::Time;
 
+use super::dsd::{is_dsd_path, DsdOutput, DsdStream};
+use super::error::{AudioError, AudioErrorCode};
 use super::http_source::HttpStreamSource;
 
 pub struct DecodedInfo {
     pub sample_rate: u32,
     pub channels: usize,
     pub duration_secs: f64,
+    /// Bit depth of lossless sources; `None` for lossy codecs, which decode to float
+    pub bits_per_sample: Option<u32>,
+    /// Samples are DoP frames that must reach the DAC untouched
+    pub dop: bool,
+}
+
+enum Backend {
+    Symphonia {
+        format_reader: Box<dyn FormatReader>,
+        decoder: Box<dyn symphonia::core::codecs::Decoder>,
+        track_id: u32,
+    },
+    Dsd(DsdStream),
 }
 
 pub struct AudioDecoder {
-    format_reader: Box<dyn FormatReader>,
-    decoder: Box<dyn symphonia::core::codecs::Decoder>,
-    track_id: u32,
+    backend: Backend,
     pub info: DecodedInfo,
+    /// Set when a decoded packet's sample rate/channel count differed from `info`.
+    spec_changed: bool,
+    /// HTTP source: I/O failures are reported as network errors.
+    remote: bool,
 }
 
 impl AudioDecoder {
-    /// Open a local file or HTTP URL for decoding.
-    pub fn open(source: &str) -> Result<Self, String> {
-        let mss = if source.starts_with("http://") || source.starts_with("https://") {
+    /// Open a local file or HTTP URL for decoding; DSD is converted to PCM.
+    pub fn open(source: &str) -> Result<Self, AudioError> {
+        Self::open_with(source, DsdOutput::Pcm, &[])
+    }
+
+    /// Open a source, choosing how local DSD files are delivered.
+    /// `headers` are sent with every request of an HTTP source.
+    pub fn open_with(source: &str, dsd_output: DsdOutput, headers: &[(String, String)]) -> Result<Self, AudioError> {
+        let remote = source.starts_with("http://") || source.starts_with("https://");
+        if !remote && is_dsd_path(source) {
+            let stream = DsdStream::open(source, dsd_output)?;
+            return Ok(Self {
+                info: DecodedInfo {
+                    sample_rate: stream.sample_rate(),
+                    channels: stream.channels,
+                    duration_secs: stream.duration_secs(),
+                    // DoP words are 24 bit; decimated DSD has float precision
+                    bits_per_sample: stream.is_dop().then_some(24),
+                    dop: stream.is_dop(),
+                },
+                backend: Backend::Dsd(stream),
+                spec_changed: false,
+                remote,
+            });
+        }
+
+        let remote = source.starts_with("http://") || source.starts_with("https://");
+        let mss = if remote {
             // HTTP source: stream via sequential reads (not full download)
-            let http_source = HttpStreamSource::open(source)?;
+            let http_source = HttpStreamSource::open(source, headers)?;
             MediaSourceStream::new(Box::new(http_source), Default::default())
         } else {
             // Local file
-            let file =
-                File::open(source).map_err(|e| format!("Failed to open file '{}': {}", source, e))?;
+            let file = File::open(source).map_err(|e| {
+                AudioError::from_io(&e, false, &format!("Failed to open file '{}'", source))
+            })?;
             MediaSourceStream::new(Box::new(file), Default::default())
         };
 
-        let mut hint = Hint::new();
         // Try to extract extension from source path
-        if let Some(ext) = std::path::Path::new(source)
+        let extension = std::path::Path::new(source)
             .extension()
-            .and_then(|e| e.to_str())
-        {
+            .and_then(|e| e.to_str());
+        Self::open_stream(mss, extension, remote)
+    }
+
+    /// Open an already prepared media source, e.g. the download buffer of an HTTP stream.
+    pub fn open_stream(mss: MediaSourceStream, extension: Option<&str>, remote: bool) -> Result<Self, AudioError> {
+        let mut hint = Hint::new();
+        if let Some(ext) = extension {
             hint.with_extension(ext);
         }
 
@@ -55,16 +103,15 @@ impl AudioDecoder {
 
         let probed = symphonia::default::get_probe()
             .format(&hint, mss, &format_opts, &metadata_opts)
-            .map_err(|e| format!("Failed to probe audio format: {}", e))?;
+            .map_err(|e| classify_error(&e, remote, "Failed to probe audio format"))?;
 
         let format_reader = probed.format;
 
         // Find the first audio track
-        let track = format_reader
-            .tracks()
-            .iter()
-            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-            .ok_or("No supported audio track found")?;
+        let track = select_track(format_reader.tracks())
+            .ok_or_else(|| {
+                AudioError::new(AudioErrorCode::UnsupportedFormat, "No supported audio track found")
+            })?;
 
         let track_id = track.id;
         let codec_params = &track.codec_params;
@@ -89,27 +136,36 @@ impl AudioDecoder {
             })
             .unwrap_or(0.0);
 
+        let bits_per_sample = codec_params.bits_per_sample;
+
         let decoder = symphonia::default::get_codecs()
             .make(codec_params, &decoder_opts)
-            .map_err(|e| format!("Failed to create decoder: {}", e))?;
+            .map_err(|e| codec_error(&e))?;
 
         Ok(Self {
-            format_reader,
-            decoder,
-            track_id,
+            backend: Backend::Symphonia { format_reader, decoder, track_id },
             info: DecodedInfo {
                 sample_rate,
                 channels,
                 duration_secs,
+                bits_per_sample,
+                dop: false,
             },
+            spec_changed: false,
+            remote,
         })
     }
 
     /// Decode the next packet into interleaved f32 samples.
     /// Returns None at end of stream.
-    pub fn decode_next(&mut self) -> Result<Option<Vec<f32>>, String> {
+    pub fn decode_next(&mut self) -> Result<Option<Vec<f32>>, AudioError> {
+        let (format_reader, decoder, track_id) = match &mut self.backend {
+            Backend::Symphonia { format_reader, decoder, track_id } => (format_reader, decoder, track_id),
+            Backend::Dsd(stream) => return stream.decode_next(),
+        };
+
         loop {
-            let packet = match self.format_reader.next_packet() {
+            let packet = match format_reader.next_packet() {
                 Ok(p) => p,
                 Err(SymphoniaError::IoError(ref e))
                     if e.kind() == std::io::ErrorKind::UnexpectedEof =>
@@ -117,46 +173,102 @@ impl AudioDecoder {
                     return Ok(None);
                 }
                 Err(SymphoniaError::ResetRequired) => {
-                    self.decoder.reset();
+                    // New logical stream (e.g. chained Ogg): track list and codec params may differ
+                    let track = select_track(format_reader.tracks()).ok_or_else(|| {
+                        AudioError::new(AudioErrorCode::UnsupportedFormat, "No supported audio track found")
+                    })?;
+                    *decoder = symphonia::default::get_codecs()
+                        .make(&track.codec_params, &DecoderOptions::default())
+                        .map_err(|e| codec_error(&e))?;
+                    *track_id = track.id;
+                    self.info.bits_per_sample = track.codec_params.bits_per_sample;
                     continue;
                 }
-                Err(e) => return Err(format!("Decode error: {}", e)),
+                Err(e) => return Err(classify_error(&e, self.remote, "Decode error")),
             };
 
-            if packet.track_id() != self.track_id {
+            if packet.track_id() != *track_id {
                 continue;
             }
 
-            match self.decoder.decode(&packet) {
+            match decoder.decode(&packet) {
                 Ok(decoded) => {
+                    // Chained Ogg streams / radio can switch format between packets
+                    let spec = *decoded.spec();
+                    let spec_channels = spec.channels.count();
+                    if spec.rate != self.info.sample_rate || spec_channels != self.info.channels {
+                        self.info.sample_rate = spec.rate;
+                        self.info.channels = spec_channels;
+                        self.spec_changed = true;
+                    }
                     let samples = audio_buf_to_f32(&decoded, self.info.channels);
                     return Ok(Some(samples));
                 }
                 Err(SymphoniaError::DecodeError(_)) => continue,
-                Err(e) => return Err(format!("Decode error: {}", e)),
+                Err(e) => return Err(classify_error(&e, self.remote, "Decode error")),
             }
         }
     }
 
+    /// Returns true (once) if the stream's sample rate or channel count changed
+    /// since the last call; `info` already holds the new values.
+    pub fn take_spec_change(&mut self) -> bool {
+        std::mem::take(&mut self.spec_changed)
+    }
+
     /// Seek to a position in seconds.
-    pub fn seek(&mut self, position_secs: f64) -> Result<(), String> {
+    pub fn seek(&mut self, position_secs: f64) -> Result<(), AudioError> {
         let clamped = if self.info.duration_secs > 0.0 {
             position_secs.clamp(0.0, (self.info.duration_secs - 0.1).max(0.0))
         } else {
             position_secs.max(0.0)
         };
-        let seek_to = SeekTo::Time {
-            time: Time::from(clamped),
-            track_id: Some(self.track_id),
-        };
-        self.format_reader
-            .seek(SeekMode::Accurate, seek_to)
-            .map_err(|e| format!("Seek failed: {}", e))?;
-        self.decoder.reset();
-        Ok(())
+        match &mut self.backend {
+            Backend::Symphonia { format_reader, decoder, track_id } => {
+                let seek_to = SeekTo::Time {
+                    time: Time::from(clamped),
+                    track_id: Some(*track_id),
+                };
+                format_reader
+                    .seek(SeekMode::Accurate, seek_to)
+                    .map_err(|e| classify_error(&e, self.remote, "Seek failed"))?;
+                decoder.reset();
+                Ok(())
+            }
+            Backend::Dsd(stream) => stream.seek(clamped),
+        }
     }
 }
 
+/// Map a symphonia error to an engine error code.
+fn classify_error(err: &SymphoniaError, remote: bool, context: &str) -> AudioError {
+    match err {
+        SymphoniaError::IoError(e) => AudioError::from_io(e, remote, context),
+        SymphoniaError::Unsupported(_) => {
+            AudioError::new(AudioErrorCode::UnsupportedFormat, format!("{}: {}", context, err))
+        }
+        _ => AudioError::new(AudioErrorCode::DecodeFailed, format!("{}: {}", context, err)),
+    }
+}
+
+/// First audio track with a registered decoder. Matroska rips often carry
+/// AC3/DTS next to a FLAC track; if none is decodable the first audio track is
+/// returned so opening it reports `UnsupportedCodec`.
+fn select_track(tracks: &[Track]) -> Option<&Track> {
+    let codecs = symphonia::default::get_codecs();
+    tracks
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && codecs.get_codec(t.codec_params.codec).is_some())
+        .or_else(|| tracks.iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL))
+}
+
+fn codec_error(err: &SymphoniaError) -> AudioError {
+    AudioError::new(
+        AudioErrorCode::UnsupportedCodec,
+        format!("Failed to create decoder: {}", err),
+    )
+}
+
 /// Convert any symphonia AudioBufferRef to interleaved f32 samples.
 fn audio_buf_to_f32(buf: &AudioBufferRef, channels: usize) -> Vec<f32> {
     let frames = buf.frames();
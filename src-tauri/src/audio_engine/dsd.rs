@@ -0,0 +1,477 @@
+//! DSD playback for DSF and DSDIFF (.dff) files.
+//!
+//! The 1-bit stream is either low-pass filtered and decimated to PCM (88.2 /
+//! 96 kHz), or packed into DoP (DSD over PCM) frames for DACs that unpack it
+//! themselves. DST-compressed DSDIFF is not supported.
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use super::error::{AudioError, AudioErrorCode};
+
+/// Lowest PCM rate DSD is decimated to; DSD64 becomes 88.2 kHz
+const MIN_PCM_RATE: u32 = 88_200;
+/// Filter taps per decimation step, in bits
+const TAPS_PER_RATIO: usize = 32;
+/// Passband edge relative to the PCM rate (~30 kHz at 88.2 kHz), above the
+/// audio band but below where DSD's shaped noise rises
+const CUTOFF_RATIO: f64 = 0.34;
+/// Bytes per channel read at a time from byte-interleaved (DSDIFF) data
+const DFF_READ_BYTES: usize = 4096;
+/// DSD idle pattern: as many ones as zeros, i.e. silence
+const DSD_SILENCE: u8 = 0x69;
+/// DoP marker bytes, alternating every frame
+const DOP_MARKERS: [u8; 2] = [0x05, 0xFA];
+
+/// How DSD reaches the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DsdOutput {
+    /// Convert to PCM; works with every device and the whole DSP chain
+    #[default]
+    Pcm,
+    /// DSD over PCM at 1/16 of the DSD rate; needs a DoP DAC and an untouched output path
+    Dop,
+}
+
+/// Whether `path` has a DSD file extension
+pub fn is_dsd_path(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("dsf") || e.eq_ignore_ascii_case("dff"))
+}
+
+/// How the DSD bytes are laid out in the file
+enum Layout {
+    /// DSF: `block_size` bytes of channel 0, then of channel 1, ...; LSB is the oldest bit
+    Dsf { block_size: usize },
+    /// DSDIFF: one byte per channel in turn; MSB is the oldest bit
+    Dff,
+}
+
+enum Conversion {
+    Pcm(PcmConverter),
+    Dop { marker: usize },
+}
+
+/// An open DSD file producing interleaved f32 frames
+pub struct DsdStream {
+    reader: BufReader<File>,
+    layout: Layout,
+    /// Offset of the first DSD byte
+    data_start: u64,
+    /// DSD bytes per channel
+    bytes_per_channel: u64,
+    /// Bytes per channel consumed so far (including skipped ones)
+    position: u64,
+    /// Bytes per channel to drop from the next read, after seeking into a DSF block
+    skip: usize,
+    pub dsd_rate: u32,
+    pub channels: usize,
+    conversion: Conversion,
+}
+
+impl DsdStream {
+    pub fn open(path: &str, output: DsdOutput) -> Result<Self, AudioError> {
+        let file = File::open(path)
+            .map_err(|e| AudioError::from_io(&e, false, &format!("Failed to open file '{}'", path)))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(header_io_error)?;
+        let header = match &magic {
+            b"DSD " => read_dsf_header(&mut reader)?,
+            b"FRM8" => read_dff_header(&mut reader)?,
+            _ => return Err(format_error("Not a DSF or DSDIFF file")),
+        };
+        if header.channels == 0 || header.dsd_rate == 0 {
+            return Err(format_error("Invalid DSD header"));
+        }
+
+        reader
+            .seek(SeekFrom::Start(header.data_start))
+            .map_err(header_io_error)?;
+
+        let conversion = match output {
+            DsdOutput::Pcm => Conversion::Pcm(PcmConverter::new(header.dsd_rate, header.channels)),
+            DsdOutput::Dop => Conversion::Dop { marker: 0 },
+        };
+
+        Ok(Self {
+            reader,
+            layout: header.layout,
+            data_start: header.data_start,
+            bytes_per_channel: header.bytes_per_channel,
+            position: 0,
+            skip: 0,
+            dsd_rate: header.dsd_rate,
+            channels: header.channels,
+            conversion,
+        })
+    }
+
+    pub fn is_dop(&self) -> bool {
+        matches!(self.conversion, Conversion::Dop { .. })
+    }
+
+    /// Rate of the produced frames
+    pub fn sample_rate(&self) -> u32 {
+        match &self.conversion {
+            Conversion::Pcm(converter) => self.dsd_rate / converter.ratio_bits,
+            Conversion::Dop { .. } => self.dsd_rate / 16,
+        }
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.bytes_per_channel as f64 * 8.0 / self.dsd_rate as f64
+    }
+
+    /// Next block of interleaved f32 frames; `None` at the end of the data
+    pub fn decode_next(&mut self) -> Result<Option<Vec<f32>>, AudioError> {
+        loop {
+            let Some(mut bytes) = self.read_bytes()? else {
+                return Ok(None);
+            };
+            if self.skip > 0 {
+                let skip = self.skip.min(bytes[0].len());
+                for channel in &mut bytes {
+                    channel.drain(..skip);
+                }
+                self.skip -= skip;
+            }
+            if bytes[0].is_empty() {
+                continue;
+            }
+
+            let samples = match &mut self.conversion {
+                Conversion::Pcm(converter) => converter.process(&bytes),
+                Conversion::Dop { marker } => pack_dop(&bytes, marker),
+            };
+            if !samples.is_empty() {
+                return Ok(Some(samples));
+            }
+        }
+    }
+
+    /// Seek to a position in seconds
+    pub fn seek(&mut self, position_secs: f64) -> Result<(), AudioError> {
+        // DoP frames take two bytes per channel; keep the byte pairs aligned
+        let target = ((position_secs.max(0.0) * self.dsd_rate as f64 / 8.0) as u64)
+            .min(self.bytes_per_channel)
+            & !1;
+        let channels = self.channels as u64;
+        let (offset, position, skip) = match self.layout {
+            Layout::Dsf { block_size } => {
+                let block_size = block_size as u64;
+                let block = target / block_size;
+                (block * block_size * channels, block * block_size, (target % block_size) as usize)
+            }
+            Layout::Dff => (target * channels, target, 0),
+        };
+
+        self.reader
+            .seek(SeekFrom::Start(self.data_start + offset))
+            .map_err(|e| AudioError::from_io(&e, false, "Seek failed"))?;
+        self.position = position;
+        self.skip = skip;
+        match &mut self.conversion {
+            Conversion::Pcm(converter) => converter.reset(),
+            Conversion::Dop { marker } => *marker = 0,
+        }
+        Ok(())
+    }
+
+    /// Read the next run of DSD bytes, split per channel with the oldest bit as MSB
+    fn read_bytes(&mut self) -> Result<Option<Vec<Vec<u8>>>, AudioError> {
+        let remaining = self.bytes_per_channel.saturating_sub(self.position);
+        if remaining == 0 {
+            return Ok(None);
+        }
+        let channels = self.channels;
+
+        let bytes = match self.layout {
+            Layout::Dsf { block_size } => {
+                // Blocks are always written in full; the tail of the last one is padding
+                let mut block = vec![0u8; block_size * channels];
+                if !read_full(&mut self.reader, &mut block)? {
+                    return Ok(None);
+                }
+                let valid = (remaining as usize).min(block_size);
+                self.position += block_size as u64;
+                block
+                    .chunks(block_size)
+                    .map(|channel| channel[..valid].iter().map(|b| b.reverse_bits()).collect())
+                    .collect()
+            }
+            Layout::Dff => {
+                let count = (remaining as usize).min(DFF_READ_BYTES);
+                let mut interleaved = vec![0u8; count * channels];
+                if !read_full(&mut self.reader, &mut interleaved)? {
+                    return Ok(None);
+                }
+                self.position += count as u64;
+                (0..channels)
+                    .map(|channel| interleaved.iter().skip(channel).step_by(channels).copied().collect())
+                    .collect()
+            }
+        };
+        Ok(Some(bytes))
+    }
+}
+
+/// Fill `buf`, returning false if the file ends first
+fn read_full(reader: &mut BufReader<File>, buf: &mut [u8]) -> Result<bool, AudioError> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(AudioError::from_io(&e, false, "Decode error")),
+    }
+}
+
+/// Pack two DSD bytes per channel into one 24-bit DoP sample, as f32
+fn pack_dop(bytes: &[Vec<u8>], marker: &mut usize) -> Vec<f32> {
+    let frames = bytes[0].len() / 2;
+    let mut out = Vec::with_capacity(frames * bytes.len());
+    for frame in 0..frames {
+        let marker_byte = DOP_MARKERS[*marker];
+        for channel in bytes {
+            let word = (marker_byte as u32) << 16
+                | (channel[frame * 2] as u32) << 8
+                | channel[frame * 2 + 1] as u32;
+            // Sign-extend the 24-bit word; f32 holds it exactly
+            let signed = ((word << 8) as i32) >> 8;
+            out.push(signed as f32 / 8_388_608.0);
+        }
+        *marker ^= 1;
+    }
+    out
+}
+
+/// FIR decimator working a byte (8 DSD bits) at a time through lookup tables
+struct PcmConverter {
+    /// `tables[k][byte]`: contribution of the k-th most recent byte
+    tables: Vec<[f32; 256]>,
+    /// Recent bytes per channel, newest at `head`
+    history: Vec<Vec<u8>>,
+    head: usize,
+    /// Decimation ratio in DSD bits per PCM sample
+    ratio_bits: u32,
+    /// Bytes since the last PCM sample
+    phase: usize,
+}
+
+impl PcmConverter {
+    fn new(dsd_rate: u32, channels: usize) -> Self {
+        let mut ratio_bits = 8;
+        while dsd_rate / (ratio_bits * 2) >= MIN_PCM_RATE {
+            ratio_bits *= 2;
+        }
+        let pcm_rate = dsd_rate / ratio_bits;
+
+        let taps = ratio_bits as usize * TAPS_PER_RATIO;
+        let cutoff = CUTOFF_RATIO * pcm_rate as f64 / dsd_rate as f64;
+        let center = (taps - 1) as f64 / 2.0;
+        let mut coefficients: Vec<f64> = (0..taps)
+            .map(|n| {
+                let x = n as f64 - center;
+                let sinc = if x == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * x).sin() / (PI * x) };
+                let phase = 2.0 * PI * n as f64 / (taps - 1) as f64;
+                let blackman = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * blackman
+            })
+            .collect();
+        // Unity gain at DC: a fully modulated stream maps to full scale
+        let sum: f64 = coefficients.iter().sum();
+        coefficients.iter_mut().for_each(|c| *c /= sum);
+
+        let tables = coefficients
+            .chunks(8)
+            .map(|taps| {
+                std::array::from_fn(|byte| {
+                    // Bit 0 is the newest sample of the byte
+                    taps.iter()
+                        .enumerate()
+                        .map(|(bit, tap)| if byte & (1 << bit) != 0 { *tap } else { -*tap })
+                        .sum::<f64>() as f32
+                })
+            })
+            .collect::<Vec<[f32; 256]>>();
+
+        let history = vec![vec![DSD_SILENCE; tables.len()]; channels];
+        Self { tables, history, head: 0, ratio_bits, phase: 0 }
+    }
+
+    fn reset(&mut self) {
+        for channel in &mut self.history {
+            channel.fill(DSD_SILENCE);
+        }
+        self.phase = 0;
+    }
+
+    fn process(&mut self, bytes: &[Vec<u8>]) -> Vec<f32> {
+        let bytes_per_sample = self.ratio_bits as usize / 8;
+        let len = self.tables.len();
+        let mut out = Vec::with_capacity(bytes[0].len() / bytes_per_sample * bytes.len() + bytes.len());
+
+        for index in 0..bytes[0].len() {
+            self.head = (self.head + 1) % len;
+            for (history, channel) in self.history.iter_mut().zip(bytes) {
+                history[self.head] = channel[index];
+            }
+            self.phase += 1;
+            if self.phase < bytes_per_sample {
+                continue;
+            }
+            self.phase = 0;
+
+            for history in &self.history {
+                // Newest byte first: back from `head`, then wrapping around from the end
+                let (up_to_head, after_head) = history.split_at(self.head + 1);
+                let recent = up_to_head.iter().rev().chain(after_head.iter().rev());
+                out.push(self.tables.iter().zip(recent).map(|(table, &byte)| table[byte as usize]).sum());
+            }
+        }
+        out
+    }
+}
+
+struct DsdHeader {
+    layout: Layout,
+    dsd_rate: u32,
+    channels: usize,
+    data_start: u64,
+    bytes_per_channel: u64,
+}
+
+/// DSF: "DSD " chunk, "fmt " chunk, then the "data" chunk (little-endian)
+fn read_dsf_header(reader: &mut BufReader<File>) -> Result<DsdHeader, AudioError> {
+    // Rest of the "DSD " chunk: size, file size, metadata pointer
+    let mut dsd_chunk = [0u8; 24];
+    reader.read_exact(&mut dsd_chunk).map_err(header_io_error)?;
+    let chunk_size = u64::from_le_bytes(dsd_chunk[0..8].try_into().unwrap_or_default());
+    reader
+        .seek(SeekFrom::Start(chunk_size.max(28)))
+        .map_err(header_io_error)?;
+
+    let mut fmt = [0u8; 52];
+    reader.read_exact(&mut fmt).map_err(header_io_error)?;
+    if &fmt[0..4] != b"fmt " {
+        return Err(format_error("DSF fmt chunk missing"));
+    }
+    let le_u32 = |at: usize| u32::from_le_bytes(fmt[at..at + 4].try_into().unwrap_or_default());
+    let fmt_size = u64::from_le_bytes(fmt[4..12].try_into().unwrap_or_default());
+    let channels = le_u32(24) as usize;
+    let dsd_rate = le_u32(28);
+    let bits_per_sample = le_u32(32);
+    let sample_count = u64::from_le_bytes(fmt[36..44].try_into().unwrap_or_default());
+    let block_size = le_u32(44) as usize;
+    if le_u32(16) != 0 {
+        return Err(format_error("Unsupported DSF format id"));
+    }
+    if bits_per_sample != 1 || block_size == 0 {
+        return Err(format_error("Unsupported DSF bit layout"));
+    }
+
+    let fmt_end = chunk_size.max(28) + fmt_size.max(52);
+    reader.seek(SeekFrom::Start(fmt_end)).map_err(header_io_error)?;
+    let mut data = [0u8; 12];
+    reader.read_exact(&mut data).map_err(header_io_error)?;
+    if &data[0..4] != b"data" {
+        return Err(format_error("DSF data chunk missing"));
+    }
+
+    Ok(DsdHeader {
+        layout: Layout::Dsf { block_size },
+        dsd_rate,
+        channels,
+        data_start: fmt_end + 12,
+        bytes_per_channel: sample_count / 8,
+    })
+}
+
+/// DSDIFF: "FRM8" form of big-endian chunks; "PROP" holds the format, "DSD " the data
+fn read_dff_header(reader: &mut BufReader<File>) -> Result<DsdHeader, AudioError> {
+    let mut form = [0u8; 12];
+    reader.read_exact(&mut form).map_err(header_io_error)?;
+    if &form[8..12] != b"DSD " {
+        return Err(format_error("Not a DSDIFF audio file"));
+    }
+
+    let mut dsd_rate = 0;
+    let mut channels = 0;
+    let mut offset = 16u64;
+    loop {
+        let (id, size) = read_dff_chunk_header(reader)?;
+        let body = offset + 12;
+        match &id {
+            b"PROP" => {
+                let mut prop_type = [0u8; 4];
+                reader.read_exact(&mut prop_type).map_err(header_io_error)?;
+                let mut sub = body + 4;
+                while sub < body + size {
+                    let (sub_id, sub_size) = read_dff_chunk_header(reader)?;
+                    let mut value = vec![0u8; sub_size.min(64) as usize];
+                    reader.read_exact(&mut value).map_err(header_io_error)?;
+                    match &sub_id {
+                        b"FS  " if value.len() >= 4 => {
+                            dsd_rate = u32::from_be_bytes(value[0..4].try_into().unwrap_or_default());
+                        }
+                        b"CHNL" if value.len() >= 2 => {
+                            channels = u16::from_be_bytes([value[0], value[1]]) as usize;
+                        }
+                        b"CMPR" if value.get(0..4) != Some(b"DSD ".as_slice()) => {
+                            return Err(AudioError::new(
+                                AudioErrorCode::UnsupportedCodec,
+                                "DST-compressed DSDIFF is not supported",
+                            ));
+                        }
+                        _ => {}
+                    }
+                    sub += 12 + sub_size + (sub_size & 1);
+                    reader.seek(SeekFrom::Start(sub)).map_err(header_io_error)?;
+                }
+            }
+            b"DSD " => {
+                return Ok(DsdHeader {
+                    layout: Layout::Dff,
+                    dsd_rate,
+                    channels,
+                    data_start: body,
+                    bytes_per_channel: size / channels.max(1) as u64,
+                });
+            }
+            b"DST " => {
+                return Err(AudioError::new(
+                    AudioErrorCode::UnsupportedCodec,
+                    "DST-compressed DSDIFF is not supported",
+                ));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        offset = body + size + (size & 1);
+        reader.seek(SeekFrom::Start(offset)).map_err(header_io_error)?;
+    }
+}
+
+fn read_dff_chunk_header(reader: &mut BufReader<File>) -> Result<([u8; 4], u64), AudioError> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header).map_err(header_io_error)?;
+    let id = [header[0], header[1], header[2], header[3]];
+    let size = u64::from_be_bytes(header[4..12].try_into().unwrap_or_default());
+    Ok((id, size))
+}
+
+fn header_io_error(err: std::io::Error) -> AudioError {
+    if err.kind() == std::io::ErrorKind::UnexpectedEof {
+        format_error("Truncated DSD header")
+    } else {
+        AudioError::from_io(&err, false, "Failed to read DSD header")
+    }
+}
+
+fn format_error(details: &str) -> AudioError {
+    AudioError::new(AudioErrorCode::UnsupportedFormat, details)
+}
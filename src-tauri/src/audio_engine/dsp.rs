@@ -1,15 +1,30 @@
-/// 10-band Biquad EQ filter.
-///
-/// Band 0 (80 Hz): lowshelf
-/// Bands 1-8 (100–8000 Hz): peaking, Q = 1.4
-/// Band 9 (16000 Hz): highshelf
-///
-/// Each channel gets independent filter state (stereo = 20 instances).
+//! Biquad graphic EQ filter, in one of two band layouts.
+//!
+//! 10 bands:
+//! Band 0 (80 Hz): lowshelf
+//! Bands 1-8 (100–8000 Hz): peaking, Q = 1.4
+//! Band 9 (16000 Hz): highshelf
+//!
+//! 31 bands: ISO 1/3-octave centers from 20 Hz to 20 kHz, all peaking
+//!
+//! Each channel gets independent filter state (stereo, 10 bands = 20 instances).
 
 const EQ_FREQUENCIES: [f32; 10] = [
     80.0, 100.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
 ];
 
+const EQ_31_FREQUENCIES: [f32; 31] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0, 500.0,
+    630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0, 8000.0,
+    10000.0, 12500.0, 16000.0, 20000.0,
+];
+
+/// Q of a 1/3-octave peaking band
+const THIRD_OCTAVE_Q: f64 = 4.32;
+
+/// Bands this close to Nyquist can't be realized and stay flat
+const MAX_BAND_NYQUIST_RATIO: f64 = 0.9;
+
 #[derive(Clone)]
 struct BiquadCoeffs {
     b0: f64,
@@ -49,6 +64,8 @@ enum FilterType {
     LowShelf,
     Peaking,
     HighShelf,
+    HighPass,
+    LowPass,
 }
 
 fn compute_coeffs(filter_type: FilterType, freq: f64, gain_db: f64, q: f64, sample_rate: f64) -> BiquadCoeffs {
@@ -92,6 +109,26 @@ fn compute_coeffs(filter_type: FilterType, freq: f64, gain_db: f64, q: f64, samp
             a1 = 2.0 * ((a - 1.0) - (a + 1.0) * cos_w0);
             a2 = (a + 1.0) - (a - 1.0) * cos_w0 - two_sqrt_a_alpha;
         }
+        FilterType::HighPass => {
+            let alpha = sin_w0 / (2.0 * q);
+
+            b0 = (1.0 + cos_w0) / 2.0;
+            b1 = -(1.0 + cos_w0);
+            b2 = (1.0 + cos_w0) / 2.0;
+            a0 = 1.0 + alpha;
+            a1 = -2.0 * cos_w0;
+            a2 = 1.0 - alpha;
+        }
+        FilterType::LowPass => {
+            let alpha = sin_w0 / (2.0 * q);
+
+            b0 = (1.0 - cos_w0) / 2.0;
+            b1 = 1.0 - cos_w0;
+            b2 = (1.0 - cos_w0) / 2.0;
+            a0 = 1.0 + alpha;
+            a1 = -2.0 * cos_w0;
+            a2 = 1.0 - alpha;
+        }
     }
 
     BiquadCoeffs {
@@ -103,11 +140,51 @@ fn compute_coeffs(filter_type: FilterType, freq: f64, gain_db: f64, q: f64, samp
     }
 }
 
-/// 10-band parametric EQ that processes interleaved f32 audio in-place.
+/// Band layout of the equalizer, as the frontend names it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum EqLayout {
+    #[default]
+    #[serde(rename = "10")]
+    Ten,
+    /// ISO graphic EQ, as in EasyEffects / foobar2000 presets
+    #[serde(rename = "31")]
+    ThirtyOne,
+}
+
+impl EqLayout {
+    pub fn band_count(self) -> usize {
+        self.frequencies().len()
+    }
+
+    fn frequencies(self) -> &'static [f32] {
+        match self {
+            EqLayout::Ten => &EQ_FREQUENCIES,
+            EqLayout::ThirtyOne => &EQ_31_FREQUENCIES,
+        }
+    }
+
+    /// Coefficients of band `index` at `gain_db`
+    fn coeffs(self, index: usize, gain_db: f64, sample_rate: f64) -> BiquadCoeffs {
+        let freq = self.frequencies()[index] as f64;
+        let (filter_type, q) = match self {
+            EqLayout::Ten if index == 0 => (FilterType::LowShelf, 0.707),
+            EqLayout::Ten if index == 9 => (FilterType::HighShelf, 0.707),
+            EqLayout::Ten => (FilterType::Peaking, 1.4),
+            EqLayout::ThirtyOne => (FilterType::Peaking, THIRD_OCTAVE_Q),
+        };
+        let limit = sample_rate / 2.0 * MAX_BAND_NYQUIST_RATIO;
+        // Bands past the limit (e.g. 20 kHz at 44.1 kHz) stay flat
+        let gain_db = if freq >= limit { 0.0 } else { gain_db };
+        compute_coeffs(filter_type, freq.min(limit), gain_db, q, sample_rate)
+    }
+}
+
+/// Graphic EQ that processes interleaved f32 audio in-place.
 pub struct Equalizer {
-    coeffs: Vec<BiquadCoeffs>,            // 10 bands
-    states: Vec<Vec<BiquadState>>,        // 10 bands × N channels
-    gains: [f32; 10],
+    layout: EqLayout,
+    coeffs: Vec<BiquadCoeffs>,            // one per band
+    states: Vec<Vec<BiquadState>>,        // bands × N channels
+    gains: Vec<f32>,
     enabled: bool,
     sample_rate: f64,
     channels: usize,
@@ -115,37 +192,37 @@ pub struct Equalizer {
 
 impl Equalizer {
     pub fn new(sample_rate: u32, channels: usize) -> Self {
-        let gains = [0.0f32; 10];
-        let sr = sample_rate as f64;
-
-        let mut coeffs = Vec::with_capacity(10);
-        let mut states = Vec::with_capacity(10);
-
-        for (i, &freq) in EQ_FREQUENCIES.iter().enumerate() {
-            let ft = if i == 0 {
-                FilterType::LowShelf
-            } else if i == 9 {
-                FilterType::HighShelf
-            } else {
-                FilterType::Peaking
-            };
-            let q = if ft == FilterType::Peaking { 1.4 } else { 0.707 };
-            coeffs.push(compute_coeffs(ft, freq as f64, 0.0, q, sr));
-            states.push(vec![BiquadState::new(); channels]);
-        }
-
-        Self {
-            coeffs,
-            states,
-            gains,
+        let mut eq = Self {
+            layout: EqLayout::Ten,
+            coeffs: Vec::new(),
+            states: Vec::new(),
+            gains: Vec::new(),
             enabled: true,
-            sample_rate: sr,
+            sample_rate: sample_rate as f64,
             channels,
+        };
+        eq.build_bands();
+        eq
+    }
+
+    /// Switch the band layout; gains don't carry over and start flat.
+    pub fn set_layout(&mut self, layout: EqLayout) {
+        if layout != self.layout {
+            self.layout = layout;
+            self.build_bands();
         }
     }
 
-    pub fn set_gains(&mut self, gains: &[f32; 10]) {
-        self.gains = *gains;
+    pub fn layout(&self) -> EqLayout {
+        self.layout
+    }
+
+    /// Gain per band in dB; ignored unless there is one gain for every band of the layout.
+    pub fn set_gains(&mut self, gains: &[f32]) {
+        if gains.len() != self.gains.len() {
+            return;
+        }
+        self.gains.copy_from_slice(gains);
         self.recompute_coeffs();
     }
 
@@ -157,8 +234,8 @@ impl Equalizer {
         self.enabled
     }
 
-    pub fn gains(&self) -> [f32; 10] {
-        self.gains
+    pub fn gains(&self) -> &[f32] {
+        &self.gains
     }
 
     pub fn reset(&mut self) {
@@ -183,8 +260,8 @@ impl Equalizer {
                 let idx = frame * channels + ch;
                 let mut sample = samples[idx] as f64;
 
-                for band in 0..10 {
-                    sample = self.states[band][ch].process(&self.coeffs[band], sample);
+                for (coeffs, band_states) in self.coeffs.iter().zip(self.states.iter_mut()) {
+                    sample = band_states[ch].process(coeffs, sample);
                 }
 
                 samples[idx] = sample as f32;
@@ -192,17 +269,466 @@ impl Equalizer {
         }
     }
 
+    /// Flat bands with fresh filter state for the current layout.
+    fn build_bands(&mut self) {
+        let bands = self.layout.band_count();
+        self.gains = vec![0.0; bands];
+        self.states = vec![vec![BiquadState::new(); self.channels]; bands];
+        self.coeffs = (0..bands)
+            .map(|i| self.layout.coeffs(i, 0.0, self.sample_rate))
+            .collect();
+    }
+
     fn recompute_coeffs(&mut self) {
-        for (i, &freq) in EQ_FREQUENCIES.iter().enumerate() {
-            let ft = if i == 0 {
-                FilterType::LowShelf
-            } else if i == 9 {
-                FilterType::HighShelf
+        for (i, &gain) in self.gains.iter().enumerate() {
+            self.coeffs[i] = self.layout.coeffs(i, gain as f64, self.sample_rate);
+        }
+    }
+}
+
+/// Lower/upper edge of the band treated as vocals; bass and cymbals panned
+/// to the center are left alone.
+const VOCAL_LOW_HZ: f64 = 150.0;
+const VOCAL_HIGH_HZ: f64 = 6000.0;
+
+/// Karaoke vocal reduction: subtracts the center (mid) signal, by default only
+/// its vocal band, from both channels of an interleaved stereo stream. Mono
+/// passes through.
+pub struct VocalReducer {
+    high_pass: BiquadCoeffs,
+    low_pass: BiquadCoeffs,
+    hp_state: BiquadState,
+    lp_state: BiquadState,
+    /// 0.0 = off, 1.0 = full center cut within the vocal band
+    strength: f32,
+    /// Cut only between [`VOCAL_LOW_HZ`] and [`VOCAL_HIGH_HZ`]; otherwise the whole center
+    band_limited: bool,
+    enabled: bool,
+    channels: usize,
+}
+
+impl VocalReducer {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let sr = sample_rate as f64;
+        // Keep the low-pass below Nyquist for low sample rates
+        let high_hz = VOCAL_HIGH_HZ.min(sr * 0.45);
+        Self {
+            high_pass: compute_coeffs(FilterType::HighPass, VOCAL_LOW_HZ, 0.0, 0.707, sr),
+            low_pass: compute_coeffs(FilterType::LowPass, high_hz, 0.0, 0.707, sr),
+            hp_state: BiquadState::new(),
+            lp_state: BiquadState::new(),
+            strength: 0.8,
+            band_limited: true,
+            enabled: false,
+            channels,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.reset();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_strength(&mut self, strength: f32) {
+        self.strength = strength.clamp(0.0, 1.0);
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    pub fn set_band_limited(&mut self, band_limited: bool) {
+        self.band_limited = band_limited;
+    }
+
+    pub fn is_band_limited(&self) -> bool {
+        self.band_limited
+    }
+
+    pub fn reset(&mut self) {
+        self.hp_state.reset();
+        self.lp_state.reset();
+    }
+
+    /// Process interleaved f32 samples in-place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if !self.enabled || self.channels != 2 || self.strength <= 0.0 {
+            return;
+        }
+
+        let strength = self.strength as f64;
+        for frame in samples.chunks_exact_mut(2) {
+            let left = frame[0] as f64;
+            let right = frame[1] as f64;
+            let mid = (left + right) * 0.5;
+
+            let cut = if self.band_limited {
+                let band = self.hp_state.process(&self.high_pass, mid);
+                self.lp_state.process(&self.low_pass, band) * strength
             } else {
-                FilterType::Peaking
+                mid * strength
             };
-            let q = if ft == FilterType::Peaking { 1.4 } else { 0.707 };
-            self.coeffs[i] = compute_coeffs(ft, freq as f64, self.gains[i] as f64, q, self.sample_rate);
+
+            frame[0] = (left - cut) as f32;
+            frame[1] = (right - cut) as f32;
+        }
+    }
+}
+
+/// Strength presets of night mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NightModePreset {
+    Light,
+    Medium,
+    Strong,
+}
+
+impl NightModePreset {
+    /// Threshold (dBFS) and ratio of the compressor
+    fn params(self) -> (f32, f32) {
+        match self {
+            NightModePreset::Light => (-20.0, 2.0),
+            NightModePreset::Medium => (-28.0, 3.0),
+            NightModePreset::Strong => (-36.0, 5.0),
+        }
+    }
+}
+
+const NIGHT_ATTACK_MS: f32 = 10.0;
+const NIGHT_RELEASE_MS: f32 = 300.0;
+/// Share of the gain reduction at full scale given back as makeup gain; the
+/// rest keeps loud passages quieter than before
+const NIGHT_MAKEUP_SHARE: f32 = 0.6;
+
+/// Night mode: a stereo-linked compressor that pulls loud peaks down and,
+/// with makeup gain, lifts quiet passages. Runs after the EQ; the limiter
+/// catches what slips past the attack.
+pub struct NightMode {
+    attack: f32,
+    release: f32,
+    /// Current gain reduction in dB
+    reduction_db: f32,
+    preset: NightModePreset,
+    enabled: bool,
+    channels: usize,
+}
+
+impl NightMode {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let coeff = |ms: f32| 1.0 - (-1.0 / (ms * 0.001 * sample_rate as f32).max(1.0)).exp();
+        Self {
+            attack: coeff(NIGHT_ATTACK_MS),
+            release: coeff(NIGHT_RELEASE_MS),
+            reduction_db: 0.0,
+            preset: NightModePreset::Medium,
+            enabled: false,
+            channels: channels.max(1),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.reset();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_preset(&mut self, preset: NightModePreset) {
+        self.preset = preset;
+    }
+
+    pub fn preset(&self) -> NightModePreset {
+        self.preset
+    }
+
+    pub fn reset(&mut self) {
+        self.reduction_db = 0.0;
+    }
+
+    /// Process interleaved f32 samples in-place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if !self.enabled {
+            return;
+        }
+
+        let (threshold_db, ratio) = self.preset.params();
+        let slope = 1.0 - 1.0 / ratio;
+        let makeup = 10f32.powf(-threshold_db * slope * NIGHT_MAKEUP_SHARE / 20.0);
+        for frame in samples.chunks_mut(self.channels) {
+            let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            let level_db = 20.0 * peak.max(1e-6).log10();
+            let target_db = (level_db - threshold_db).max(0.0) * slope;
+            let coeff = if target_db > self.reduction_db { self.attack } else { self.release };
+            self.reduction_db += (target_db - self.reduction_db) * coeff;
+
+            let gain = makeup * 10f32.powf(-self.reduction_db / 20.0);
+            for s in frame.iter_mut() {
+                *s *= gain;
+            }
+        }
+    }
+}
+
+/// Output ceiling of the limiter, just below full scale
+const LIMIT_CEILING: f32 = 0.98;
+const LIMIT_RELEASE_MS: f32 = 120.0;
+/// How far ahead the limiter sees peaks coming; also the delay it adds
+const LIMIT_LOOKAHEAD_MS: f32 = 2.0;
+
+/// Look-ahead peak limiter with a soft clipper behind it.
+///
+/// Runs after the EQ so band boosts, normalization gain and night mode never
+/// hard-clip. The input is delayed by the look-ahead so the gain is already
+/// down when a peak arrives; whatever slips past the attack is rounded off by
+/// the soft clipper instead of being cut at full scale.
+pub struct Limiter {
+    /// Current gain reduction, 1.0 = none
+    reduction: f32,
+    attack: f32,
+    release: f32,
+    channels: usize,
+    /// Look-ahead in frames
+    lookahead: usize,
+    /// Delayed interleaved input
+    delay: std::collections::VecDeque<f32>,
+    /// Gain each frame in the look-ahead window needs, with the frame number.
+    /// Gains increase from the front, so the front is the window minimum.
+    needed: std::collections::VecDeque<(u64, f32)>,
+    frame: u64,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let release_samples = LIMIT_RELEASE_MS * 0.001 * sample_rate as f32;
+        let lookahead = ((LIMIT_LOOKAHEAD_MS * 0.001 * sample_rate as f32) as usize).max(1);
+        Self {
+            reduction: 1.0,
+            // Close most of the way within the look-ahead; the soft clipper takes the rest
+            attack: 1.0 - (-4.0 / lookahead as f32).exp(),
+            release: 1.0 - (-1.0 / release_samples.max(1.0)).exp(),
+            channels,
+            lookahead,
+            delay: vec![0.0; lookahead * channels].into(),
+            needed: std::collections::VecDeque::with_capacity(lookahead + 1),
+            frame: 0,
+        }
+    }
+
+    /// Frames the output lags the input
+    pub fn latency(&self) -> usize {
+        self.lookahead
+    }
+
+    pub fn reset(&mut self) {
+        self.reduction = 1.0;
+        self.delay.iter_mut().for_each(|s| *s = 0.0);
+        self.needed.clear();
+    }
+
+    /// Limit interleaved samples in-place. The output lags the input by the
+    /// look-ahead, also while `active` is false, so switching never skips audio;
+    /// inactive, samples pass through unchanged once the gain has recovered.
+    pub fn process(&mut self, samples: &mut [f32], active: bool) {
+        for frame in samples.chunks_mut(self.channels) {
+            let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            let target = if active && peak > LIMIT_CEILING { LIMIT_CEILING / peak } else { 1.0 };
+            while self.needed.back().is_some_and(|&(_, gain)| gain >= target) {
+                self.needed.pop_back();
+            }
+            self.needed.push_back((self.frame, target));
+            while self
+                .needed
+                .front()
+                .is_some_and(|&(index, _)| index + (self.lookahead as u64) < self.frame)
+            {
+                self.needed.pop_front();
+            }
+            self.frame += 1;
+
+            let window_min = self.needed.front().map_or(1.0, |&(_, gain)| gain);
+            let coeff = if window_min < self.reduction { self.attack } else { self.release };
+            self.reduction += (window_min - self.reduction) * coeff;
+
+            for s in frame.iter_mut() {
+                self.delay.push_back(*s);
+                let delayed = self.delay.pop_front().unwrap_or(0.0) * self.reduction;
+                *s = if active { soft_clip(delayed) } else { delayed };
+            }
+        }
+    }
+}
+
+/// Leaves samples up to the ceiling alone and bends everything above it
+/// smoothly towards full scale
+fn soft_clip(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= LIMIT_CEILING {
+        return sample;
+    }
+    let headroom = 1.0 - LIMIT_CEILING;
+    let shaped = LIMIT_CEILING + headroom * ((level - LIMIT_CEILING) / headroom).tanh();
+    shaped.copysign(sample)
+}
+
+/// TPDF dither for integer output devices.
+///
+/// Adds triangular noise of ±1 LSB and rounds to the device's grid, so the
+/// system's f32 → integer conversion is exact and the requantization error of
+/// quiet passages and fades becomes a steady noise floor instead of distortion.
+pub struct Dither {
+    /// xorshift32 state, never zero
+    state: u32,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self { state: 0x9E37_79B9 }
+    }
+}
+
+impl Dither {
+    /// Uniform random value in [0, 1)
+    fn next_uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Dither and quantize interleaved samples in-place to `bits` (below 24).
+    pub fn process(&mut self, samples: &mut [f32], bits: u32) {
+        let scale = (1u32 << (bits - 1)) as f32;
+        let max = 1.0 - 1.0 / scale;
+        for s in samples.iter_mut() {
+            let noise = self.next_uniform() - self.next_uniform();
+            *s = ((*s * scale + noise).round() / scale).clamp(-1.0, max);
+        }
+    }
+}
+
+/// RMS level below which a packet counts as silence (-60 dBFS)
+const SILENCE_THRESHOLD: f32 = 0.001;
+/// Silence kept inside and at the end of a track; longer runs are cut to this
+const SILENCE_MAX_GAP_SECS: f64 = 2.0;
+
+/// Skip-silence gate: drops the silence before the first audible packet of a
+/// track, and any silence run (trailing silence, hidden-track gaps) beyond
+/// [`SILENCE_MAX_GAP_SECS`].
+#[derive(Default)]
+pub struct SilenceSkipper {
+    enabled: bool,
+    /// Nothing audible decoded yet in this track
+    leading: bool,
+    /// Length of the current silence run in seconds
+    run_secs: f64,
+}
+
+impl SilenceSkipper {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.run_secs = 0.0;
+        }
+    }
+
+    /// A new track starts: its leading silence is skipped
+    pub fn start_track(&mut self) {
+        self.leading = true;
+        self.run_secs = 0.0;
+    }
+
+    /// Whether to drop a decoded packet of `secs` seconds
+    pub fn should_skip(&mut self, samples: &[f32], secs: f64) -> bool {
+        if !self.enabled || samples.is_empty() {
+            return false;
+        }
+        let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        if mean_square.sqrt() >= SILENCE_THRESHOLD {
+            self.leading = false;
+            self.run_secs = 0.0;
+            return false;
+        }
+        if self.leading {
+            return true;
+        }
+        self.run_secs += secs;
+        self.run_secs > SILENCE_MAX_GAP_SECS
+    }
+}
+
+/// How the volume slider (0.0 - 1.0) maps to output gain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeCurve {
+    /// Gain equals the slider value
+    Linear,
+    /// Equal slider steps are equal steps in dB, which is how loudness is heard
+    #[default]
+    Logarithmic,
+}
+
+/// Range of the logarithmic curve: the slider's lower end sits this far below full scale
+const VOLUME_RANGE_DB: f32 = 60.0;
+
+impl VolumeCurve {
+    /// Linear output gain for a slider value
+    pub fn gain(self, volume: f32) -> f32 {
+        let volume = volume.clamp(0.0, 1.0);
+        match self {
+            VolumeCurve::Linear => volume,
+            VolumeCurve::Logarithmic if volume <= 0.0 => 0.0,
+            VolumeCurve::Logarithmic => 10f32.powf((volume - 1.0) * VOLUME_RANGE_DB / 20.0),
+        }
+    }
+}
+
+const VOLUME_RAMP_MS: f32 = 30.0;
+
+/// Output gain that glides to a new value over [`VOLUME_RAMP_MS`] instead of
+/// jumping, so dragging the volume slider doesn't produce zipper noise.
+pub struct VolumeRamp {
+    current: f32,
+    target: f32,
+    step: f32,
+}
+
+impl VolumeRamp {
+    pub fn new(gain: f32) -> Self {
+        Self { current: gain, target: gain, step: 0.0 }
+    }
+
+    /// Start ramping towards `gain`, sized for the interleaved output stream.
+    pub fn set_target(&mut self, gain: f32, sample_rate: u32, channels: usize) {
+        let samples = (VOLUME_RAMP_MS * 0.001 * sample_rate as f32 * channels.max(1) as f32).max(1.0);
+        self.target = gain;
+        self.step = (gain - self.current).abs() / samples;
+    }
+
+    /// Settled at unity gain: samples pass through unchanged
+    pub fn is_unity(&self) -> bool {
+        self.current == self.target && (self.target - 1.0).abs() <= f32::EPSILON
+    }
+
+    /// Gain for the next sample
+    #[inline]
+    pub fn next_gain(&mut self) -> f32 {
+        if self.current < self.target {
+            self.current = (self.current + self.step).min(self.target);
+        } else if self.current > self.target {
+            self.current = (self.current - self.step).max(self.target);
         }
+        self.current
     }
 }
@@ -1,23 +1,44 @@
 use crossbeam_channel::{Receiver, Sender};
-use ringbuf::traits::{Observer, Producer};
+use ringbuf::traits::Observer;
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
 use super::decoder::AudioDecoder;
-use super::dsp::Equalizer;
+use super::dsd::DsdOutput;
+use super::dsp::{
+    Dither, EqLayout, Equalizer, Limiter, NightMode, NightModePreset, SilenceSkipper, VocalReducer, VolumeCurve, VolumeRamp,
+};
+use super::error::{AudioError, AudioErrorCode};
+use super::export::DspSettings;
 use super::fft::FftProcessor;
-use super::output::AudioOutput;
-use super::resampler::AudioResampler;
+use super::levels::LevelMeter;
+use super::history::PlayHistory;
+use super::http_source::HttpHeaders;
+use super::output::{AudioOutput, OutputBackend, OutputOptions, OutputRate};
+use super::queue::PlaybackQueue;
+use super::resampler::{AudioResampler, ResamplerQuality};
+use super::sleep_inhibit::SleepInhibitor;
 
 const FADE_OUT_MS: f32 = 150.0;
 const FADE_IN_MS: f32 = 200.0;
+/// Widest layout the output is opened with (7.1).
+const MAX_OUTPUT_CHANNELS: usize = 8;
+/// Largest position step between time updates still counted as listening (more is a seek).
+const MAX_HEARD_STEP_SECS: f64 = 2.0;
 
 enum FadeAction {
     Pause,
     Stop,
-    PlayNext { source: String },
+    PlayNext {
+        source: String,
+        headers: HttpHeaders,
+        start_secs: f64,
+        end_secs: Option<f64>,
+        gain: f32,
+        chapters: Vec<f64>,
+    },
 }
 
 enum FadeState {
@@ -28,15 +49,98 @@ enum FadeState {
 
 /// Commands sent from IPC to the audio thread.
 pub enum AudioCommand {
-    Play { source: String },
+    /// Open a source and fade in; `start_secs` > 0 seeks before any audio is output.
+    /// `end_secs` (cue-out) ends the track early as if the stream had ended there.
+    /// `gain` is the track's linear normalization gain (1.0 = unchanged).
+    /// `headers` are sent with HTTP requests, e.g. an Authorization header.
+    /// `chapters` are the chapter start times (seconds) reported through `audio:chapter_changed`.
+    Play {
+        source: String,
+        headers: HttpHeaders,
+        start_secs: f64,
+        end_secs: Option<f64>,
+        gain: f32,
+        chapters: Vec<f64>,
+    },
     Pause,
     Resume,
     Stop,
     Seek { position_secs: f64 },
+    /// Seek to `offset_secs` into chapter `index` of the current track; ignored past the last chapter.
+    SeekChapter { index: usize, offset_secs: f64 },
     SetVolume { volume: f32 },
-    SetEqBands { gains: [f32; 10] },
+    /// Mapping from the volume slider to output gain.
+    SetVolumeCurve { curve: VolumeCurve },
+    /// Hard mute at the output stage; the user volume is left untouched.
+    SetMuted { muted: bool },
+    /// Gain per band in dB, one for each band of the current layout.
+    SetEqBands { gains: Vec<f32> },
+    /// 10-band or ISO 31-band equalizer; switching resets the gains to flat.
+    SetEqLayout { layout: EqLayout },
     SetEqEnabled { enabled: bool },
+    /// Karaoke center-channel cut; `strength` is 0.0 - 1.0. `band_limited` keeps
+    /// the cut to the vocal band, leaving centered bass and cymbals alone; None keeps the current mode.
+    SetVocalReduction { enabled: bool, strength: f32, band_limited: Option<bool> },
+    /// Night mode compressor after the EQ.
+    SetNightMode { enabled: bool, preset: NightModePreset },
+    /// Look-ahead limiter against clipping from EQ / vocal reduction boosts.
+    /// Normalization gain above 0 dB and night mode are always limited.
+    SetLimiter { enabled: bool },
+    /// TPDF dither when the output device has a lower bit depth than the stream.
+    SetDither { enabled: bool },
+    /// Skip leading silence and cut long silent stretches (trailing, mid-track).
+    SetSkipSilence { enabled: bool },
+    /// Fade out over the last `secs` of every track (or before its cue-out); 0 turns it off.
+    SetEndFade { secs: f64 },
     EnableVisualization { enabled: bool },
+    /// Per-channel peak/RMS `audio:levels` events (~20 Hz), without the FFT.
+    EnableLevels { enabled: bool },
+    /// Source to hand off to gaplessly when the current track ends naturally (None clears it),
+    /// with its HTTP headers, cue-in/cue-out points and chapter start times.
+    PreloadNext {
+        source: Option<String>,
+        headers: HttpHeaders,
+        start_secs: f64,
+        end_secs: Option<f64>,
+        gain: f32,
+        chapters: Vec<f64>,
+    },
+    /// Device buffer size / ring buffer depth; reopens the output if one is active.
+    SetOutputOptions { options: OutputOptions },
+    /// Audio server to output to (system default or JACK); reopens the output if one is active.
+    SetOutputBackend { backend: OutputBackend },
+    /// Rate the output is opened at (source rate or fixed); reopens the output if one is active.
+    SetOutputRate { rate: OutputRate },
+    /// Keep the source channel layout (5.1/7.1) instead of downmixing to stereo.
+    SetMultichannel { enabled: bool },
+    /// DSD as PCM or DoP, from the next track on.
+    SetDsdOutput { output: DsdOutput },
+    /// Resampler used when the device rate differs from the source; rebuilt in place.
+    SetResamplerQuality { quality: ResamplerQuality },
+    /// Normalization gains of the current / preloaded track, recomputed after the
+    /// ReplayGain mode changed.
+    SetTrackGain { gain: f32, next_gain: f32 },
+    /// Repeat `start..end` of the current track (seconds); `None` ends the loop.
+    /// Cleared whenever another track starts.
+    SetAbLoop { range: Option<(f64, f64)> },
+    /// Describe the active output chain; `None` is sent back while no output is open.
+    GetOutputInfo { reply: Sender<Option<OutputInfo>> },
+    /// Current DSP settings, for offline exports.
+    GetDspSettings { reply: Sender<DspSettings> },
+    /// The system is going to sleep: pause at once and release the output device.
+    SystemSuspend,
+    /// The system woke up: reopen the output, still paused at the position heard.
+    SystemResume,
+}
+
+/// Which ReplayGain value normalization uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    Off,
+    #[default]
+    Track,
+    Album,
 }
 
 /// Shared playback state readable from IPC.
@@ -46,6 +150,25 @@ pub struct PlaybackState {
     pub position_secs: f64,
     pub duration_secs: f64,
     pub volume: f32,
+    pub muted: bool,
+}
+
+/// The output chain as the audio thread runs it, to verify bit-perfect playback.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputInfo {
+    pub device_name: String,
+    /// Rate and layout negotiated with the device
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub source_sample_rate: u32,
+    pub source_channels: usize,
+    pub resampling: bool,
+    pub eq_enabled: bool,
+    /// Vocal reduction, night mode or a normalization gain alter the samples
+    pub other_dsp: bool,
+    /// Samples reach the device unchanged: same rate and layout, no DSP, full volume
+    pub bit_perfect: bool,
 }
 
 // Event payloads
@@ -62,8 +185,10 @@ struct FftPayload {
 }
 
 #[derive(Clone, Serialize)]
-struct ErrorPayload {
-    message: String,
+struct LevelsPayload {
+    /// Per output channel, dBFS
+    peak: Vec<f32>,
+    rms: Vec<f32>,
 }
 
 #[derive(Clone, Serialize)]
@@ -71,9 +196,110 @@ struct StateChangedPayload {
     is_playing: bool,
 }
 
+#[derive(Clone, Serialize)]
+struct DeviceChangedPayload {
+    device_name: String,
+}
+
+#[derive(Clone, Serialize)]
+struct ChapterChangedPayload {
+    index: usize,
+    start: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct TrackPlayedPayload {
+    song_id: Option<String>,
+    /// Seconds actually heard
+    listened: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct TrackChangedPayload {
+    source: String,
+    /// Song handed off to, when it was preloaded with one
+    song_id: Option<String>,
+    duration: f64,
+}
+
+/// A gapless handoff written to the output whose first frame hasn't been heard yet
+struct PendingTrackChange {
+    payload: TrackChangedPayload,
+    /// Clock and duration of the old track, whose buffered tail is still playing
+    previous_clock: PlaybackClock,
+    previous_duration: f64,
+}
+
+/// Maps frames rendered by the output device back to a track position.
+/// Re-anchored whenever the ring buffer is flushed or a new track starts writing.
+#[derive(Clone, Copy, Default)]
+struct PlaybackClock {
+    /// Track position (seconds) of the frame at `anchor_frame`.
+    anchor_secs: f64,
+    /// Output frame index (since the last flush) where `anchor_secs` begins.
+    anchor_frame: u64,
+}
+
+impl PlaybackClock {
+    fn anchor(secs: f64, frame: u64) -> Self {
+        Self { anchor_secs: secs, anchor_frame: frame }
+    }
+
+    /// True once the device has rendered past the anchor frame.
+    fn reached(&self, out: &AudioOutput) -> bool {
+        out.frames_played() >= self.anchor_frame
+    }
+
+    fn position(&self, out: &AudioOutput, duration_secs: f64) -> f64 {
+        let rendered = out.frames_played().saturating_sub(self.anchor_frame);
+        let pos = self.anchor_secs + rendered as f64 / out.config.sample_rate.0 as f64;
+        if duration_secs > 0.0 {
+            pos.min(duration_secs)
+        } else {
+            pos
+        }
+    }
+}
+
+/// Seconds of the current track heard in continuous playback; a play counts
+/// once half of the track (from where it started to its end) was heard.
+#[derive(Clone, Copy, Default)]
+struct PlayCounter {
+    start: f64,
+    last_heard: f64,
+    listened: f64,
+    counted: bool,
+}
+
+impl PlayCounter {
+    fn new(start_secs: f64) -> Self {
+        Self { start: start_secs, last_heard: start_secs, ..Self::default() }
+    }
+
+    /// Advance to `heard`; true the first time half of `start..end` was heard.
+    fn update(&mut self, heard: f64, end: f64) -> bool {
+        let step = heard - self.last_heard;
+        self.last_heard = heard;
+        // Seeks jump; only continuous playback counts
+        if step > 0.0 && step < MAX_HEARD_STEP_SECS {
+            self.listened += step;
+        }
+        if self.counted || end <= self.start {
+            return false;
+        }
+        self.counted = self.listened >= (end - self.start) * 0.5;
+        self.counted
+    }
+}
+
 pub struct AudioEngine {
     cmd_tx: Sender<AudioCommand>,
     pub state: Arc<Mutex<PlaybackState>>,
+    /// Previously played songs; gapless handoffs are recorded by the audio thread.
+    pub history: Arc<Mutex<PlayHistory>>,
+    pub replay_gain_mode: ReplayGainMode,
+    /// Native playback queue; its next track is kept preloaded while it is in use.
+    pub queue: PlaybackQueue,
 }
 
 impl AudioEngine {
@@ -85,17 +311,26 @@ impl AudioEngine {
             position_secs: 0.0,
             duration_secs: 0.0,
             volume: 1.0,
+            muted: false,
         }));
         let state_clone = state.clone();
+        let history = Arc::new(Mutex::new(PlayHistory::default()));
+        let history_clone = history.clone();
 
         std::thread::Builder::new()
             .name("audio-engine".into())
             .spawn(move || {
-                audio_thread(cmd_rx, state_clone, app_handle);
+                audio_thread(cmd_rx, state_clone, history_clone, app_handle);
             })
             .expect("Failed to spawn audio engine thread");
 
-        Self { cmd_tx, state }
+        Self {
+            cmd_tx,
+            state,
+            history,
+            replay_gain_mode: ReplayGainMode::default(),
+            queue: PlaybackQueue::default(),
+        }
     }
 
     pub fn send(&self, cmd: AudioCommand) {
@@ -103,17 +338,104 @@ impl AudioEngine {
     }
 }
 
+/// Open `source` and seek to `start_secs` (clamped to the track), returning the
+/// decoder and the position it starts from.
+fn open_at(
+    source: &str,
+    headers: &[(String, String)],
+    start_secs: f64,
+    dsd_output: DsdOutput,
+) -> Result<(AudioDecoder, f64), AudioError> {
+    let mut dec = AudioDecoder::open_with(source, dsd_output, headers)?;
+    let mut position = 0.0;
+    if start_secs > 0.0 {
+        let target = if dec.info.duration_secs > 0.0 {
+            start_secs.min(dec.info.duration_secs)
+        } else {
+            start_secs
+        };
+        match dec.seek(target) {
+            Ok(()) => position = target,
+            Err(e) => eprintln!("Start offset seek error: {}", e),
+        }
+    }
+    Ok((dec, position))
+}
+
+/// Decoder of the preloaded track, opened on a background thread so HTTP
+/// sources have finished their pre-buffer by the time the track starts.
+struct Prefetch {
+    source: String,
+    start_secs: f64,
+    rx: Receiver<Result<(AudioDecoder, f64), AudioError>>,
+}
+
+impl Prefetch {
+    fn spawn(source: String, headers: HttpHeaders, start_secs: f64, dsd_output: DsdOutput) -> Self {
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        let thread_source = source.clone();
+        let spawned = std::thread::Builder::new()
+            .name("audio-prefetch".into())
+            .spawn(move || {
+                // Nobody is waiting anymore if the preload was replaced; the decoder is just dropped
+                let _ = tx.send(open_at(&thread_source, &headers, start_secs, dsd_output));
+            });
+        if let Err(e) = spawned {
+            eprintln!("Prefetch thread not started: {}", e);
+        }
+        Self { source, start_secs, rx }
+    }
+
+    fn matches(&self, source: &str, start_secs: f64) -> bool {
+        self.source == source && (self.start_secs - start_secs).abs() < 1e-6
+    }
+
+    /// The opened decoder, waiting for the thread if it isn't done yet.
+    fn take(self) -> Result<(AudioDecoder, f64), AudioError> {
+        match self.rx.recv() {
+            Ok(opened) => opened,
+            // Thread never started or panicked: open here instead
+            Err(_) => Err(AudioError::new(AudioErrorCode::Unknown, "Prefetch failed")),
+        }
+    }
+
+    /// The prefetched decoder if it opened, otherwise a fresh attempt
+    /// (e.g. the stream URL expired in the meantime).
+    fn take_or_open(
+        prefetch: Option<Self>,
+        source: &str,
+        headers: &[(String, String)],
+        start_secs: f64,
+        dsd_output: DsdOutput,
+    ) -> Result<(AudioDecoder, f64), AudioError> {
+        match prefetch.map(Prefetch::take) {
+            Some(Ok(opened)) => Ok(opened),
+            Some(Err(e)) => {
+                eprintln!("Prefetched open of {} failed, retrying: {}", source, e);
+                open_at(source, headers, start_secs, dsd_output)
+            }
+            None => open_at(source, headers, start_secs, dsd_output),
+        }
+    }
+}
+
 /// Open a new audio source, set up output/resampler/EQ, and optionally start with fade-in.
 /// Returns true on success.
 #[allow(clippy::too_many_arguments)]
 fn execute_play(
     source: &str,
+    headers: &[(String, String)],
+    start_secs: f64,
+    prefetch: Option<Prefetch>,
     with_fade_in: bool,
     decoder: &mut Option<AudioDecoder>,
     output: &mut Option<AudioOutput>,
     resampler: &mut Option<AudioResampler>,
     resample_buffer: &mut Vec<f32>,
     eq: &mut Equalizer,
+    vocal: &mut VocalReducer,
+    night: &mut NightMode,
+    limiter: &mut Limiter,
     fade_state: &mut FadeState,
     source_sample_rate: &mut u32,
     source_channels: &mut usize,
@@ -121,6 +443,12 @@ fn execute_play(
     duration_secs: &mut f64,
     is_playing: &mut bool,
     volume: f32,
+    output_options: OutputOptions,
+    output_backend: &OutputBackend,
+    output_rate: OutputRate,
+    multichannel: bool,
+    resampler_quality: ResamplerQuality,
+    dsd_output: DsdOutput,
     state: &Arc<Mutex<PlaybackState>>,
     app_handle: &AppHandle,
 ) -> bool {
@@ -131,22 +459,43 @@ fn execute_play(
     *is_playing = false;
     *position_secs = 0.0;
 
-    match AudioDecoder::open(source) {
-        Ok(dec) => {
+    // Seeked before any audio reaches the output, so the head of the track is never heard
+    match Prefetch::take_or_open(prefetch, source, headers, start_secs, dsd_output) {
+        Ok((mut dec, start_position)) => {
+            *position_secs = start_position;
             *source_sample_rate = dec.info.sample_rate;
             *source_channels = dec.info.channels;
             *duration_secs = dec.info.duration_secs;
 
-            let output_channels = (*source_channels).min(2) as u16;
+            let output_channels = output_channels_for(*source_channels, multichannel);
 
-            match AudioOutput::new(*source_sample_rate, output_channels) {
+            let rate = output_rate.for_source(*source_sample_rate);
+            match AudioOutput::new(rate, output_channels, output_options, output_backend) {
                 Ok(out) => {
+                    // The device may offer fewer channels than asked for; the decode loop downmixes
+                    let output_channels = out.config.channels;
                     let out_rate = out.config.sample_rate.0;
+                    if dec.info.dop && !dop_fits(&dec, &out) {
+                        eprintln!("Device can't take DoP at {} Hz, converting DSD to PCM", dec.info.sample_rate);
+                        match open_at(source, headers, *position_secs, DsdOutput::Pcm) {
+                            Ok((pcm, start_position)) => {
+                                dec = pcm;
+                                *position_secs = start_position;
+                            }
+                            Err(e) => {
+                                let _ = app_handle.emit("audio:error", e);
+                                return false;
+                            }
+                        }
+                        *source_sample_rate = dec.info.sample_rate;
+                        *source_channels = dec.info.channels;
+                    }
                     if out_rate != *source_sample_rate {
                         match AudioResampler::new(
                             *source_sample_rate,
                             out_rate,
                             output_channels as usize,
+                            resampler_quality,
                         ) {
                             Ok(rs) => *resampler = Some(rs),
                             Err(e) => {
@@ -156,13 +505,10 @@ fn execute_play(
                     }
 
                     let effective_rate = if resampler.is_some() { out_rate } else { *source_sample_rate };
-                    {
-                        let current_eq_gains = eq.gains();
-                        let mut new_eq = Equalizer::new(effective_rate, output_channels as usize);
-                        new_eq.set_enabled(eq.is_enabled());
-                        new_eq.set_gains(&current_eq_gains);
-                        std::mem::swap(eq, &mut new_eq);
-                    }
+                    rebuild_eq(eq, effective_rate, output_channels as usize);
+                    rebuild_vocal_reducer(vocal, effective_rate, output_channels as usize);
+                    rebuild_night_mode(night, effective_rate, output_channels as usize);
+                    *limiter = Limiter::new(effective_rate, output_channels as usize);
 
                     let fade_rate = if resampler.is_some() { out_rate } else { *source_sample_rate };
                     let fade_ch = output_channels as usize;
@@ -185,51 +531,209 @@ fn execute_play(
                     true
                 }
                 Err(e) => {
-                    let _ = app_handle.emit("audio:error", ErrorPayload { message: e });
+                    let _ = app_handle.emit("audio:error", e);
                     false
                 }
             }
         }
         Err(e) => {
-            let _ = app_handle.emit("audio:error", ErrorPayload { message: e });
+            let _ = app_handle.emit("audio:error", e);
             false
         }
     }
 }
 
+/// Swap in the preloaded next source without tearing down the output stream,
+/// so the tail of the current track and the head of the next play back-to-back.
+#[allow(clippy::too_many_arguments)]
+fn execute_gapless_handoff(
+    source: &str,
+    headers: &[(String, String)],
+    start_secs: f64,
+    prefetch: Option<Prefetch>,
+    decoder: &mut Option<AudioDecoder>,
+    output: &Option<AudioOutput>,
+    resampler: &mut Option<AudioResampler>,
+    resample_buffer: &mut Vec<f32>,
+    source_sample_rate: &mut u32,
+    source_channels: &mut usize,
+    position_secs: &mut f64,
+    duration_secs: &mut f64,
+    resampler_quality: ResamplerQuality,
+    dsd_output: DsdOutput,
+) -> Result<(), AudioError> {
+    let out = output.as_ref().ok_or_else(|| {
+        AudioError::new(AudioErrorCode::DeviceUnavailable, "No active audio output")
+    })?;
+    let (mut dec, mut start_position) = Prefetch::take_or_open(prefetch, source, headers, start_secs, dsd_output)?;
+    // The running stream was opened for the previous track; DoP only if it matches
+    if dec.info.dop && !dop_fits(&dec, out) {
+        (dec, start_position) = open_at(source, headers, start_secs, DsdOutput::Pcm)?;
+    }
+    *position_secs = start_position;
+
+    retarget_resampler(
+        dec.info.sample_rate, out, resampler, resample_buffer, *source_sample_rate, resampler_quality,
+    );
+
+    *source_sample_rate = dec.info.sample_rate;
+    *source_channels = dec.info.channels;
+    *duration_secs = dec.info.duration_secs;
+    *decoder = Some(dec);
+    Ok(())
+}
+
+/// Recreate the equalizer for a new rate/channel layout, keeping gains and the enabled flag.
+fn rebuild_eq(eq: &mut Equalizer, sample_rate: u32, channels: usize) {
+    let mut new_eq = Equalizer::new(sample_rate, channels);
+    new_eq.set_layout(eq.layout());
+    new_eq.set_enabled(eq.is_enabled());
+    new_eq.set_gains(eq.gains());
+    std::mem::swap(eq, &mut new_eq);
+}
+
+/// Recreate the vocal reducer for a new rate/channel layout, keeping its settings.
+fn rebuild_vocal_reducer(vocal: &mut VocalReducer, sample_rate: u32, channels: usize) {
+    let mut new_vocal = VocalReducer::new(sample_rate, channels);
+    new_vocal.set_enabled(vocal.is_enabled());
+    new_vocal.set_strength(vocal.strength());
+    new_vocal.set_band_limited(vocal.is_band_limited());
+    std::mem::swap(vocal, &mut new_vocal);
+}
+
+/// Recreate the night mode compressor for a new rate/channel layout, keeping its settings.
+fn rebuild_night_mode(night: &mut NightMode, sample_rate: u32, channels: usize) {
+    let mut new_night = NightMode::new(sample_rate, channels);
+    new_night.set_enabled(night.is_enabled());
+    new_night.set_preset(night.preset());
+    std::mem::swap(night, &mut new_night);
+}
+
+/// Point the resampler at a new source rate while keeping the current output stream.
+fn retarget_resampler(
+    new_rate: u32,
+    out: &AudioOutput,
+    resampler: &mut Option<AudioResampler>,
+    resample_buffer: &mut Vec<f32>,
+    old_rate: u32,
+    quality: ResamplerQuality,
+) {
+    let out_rate = out.config.sample_rate.0;
+    let out_channels = out.config.channels as usize;
+
+    if new_rate == out_rate {
+        *resampler = None;
+        resample_buffer.clear();
+    } else if resampler.is_none() || new_rate != old_rate {
+        // Leftover input belongs to the old rate, so it can't be fed to the new resampler
+        resample_buffer.clear();
+        *resampler = AudioResampler::new(new_rate, out_rate, out_channels, quality)
+            .map_err(|e| eprintln!("Resampler init warning: {}", e))
+            .ok();
+    }
+}
+
 fn audio_thread(
     cmd_rx: Receiver<AudioCommand>,
     state: Arc<Mutex<PlaybackState>>,
+    history: Arc<Mutex<PlayHistory>>,
     app_handle: AppHandle,
 ) {
     let mut decoder: Option<AudioDecoder> = None;
     let mut output: Option<AudioOutput> = None;
     let mut eq = Equalizer::new(44100, 2);
+    let mut vocal = VocalReducer::new(44100, 2);
+    let mut night = NightMode::new(44100, 2);
+    let mut limiter = Limiter::new(44100, 2);
+    let mut limiter_enabled = true;
+    let mut dither = Dither::default();
+    let mut dither_enabled = true;
+    let mut silence = SilenceSkipper::default();
+    let mut end_fade_secs: f64 = 0.0;
+    // Held while playing so the system doesn't sleep on idle mid-playlist
+    let mut sleep_inhibitor = SleepInhibitor::default();
     let mut fft_proc = FftProcessor::new();
+    let mut levels = LevelMeter::new();
     let mut resampler: Option<AudioResampler> = None;
     let mut resample_buffer: Vec<f32> = Vec::new();
 
     let mut volume: f32 = 1.0;
+    let mut volume_curve = VolumeCurve::default();
+    // Gain actually applied; follows `volume` through the curve with a short ramp
+    let mut volume_ramp = VolumeRamp::new(1.0);
+    let mut muted = false;
     let mut position_secs: f64 = 0.0;
     let mut duration_secs: f64 = 0.0;
     let mut is_playing = false;
     let mut source_sample_rate: u32 = 44100;
     let mut source_channels: usize = 2;
     let mut fade_state = FadeState::None;
+    let mut next_source: Option<String> = None;
+    let mut next_headers: HttpHeaders = Vec::new();
+    // Background open of `next_source`, and of a preloaded track that was played directly
+    let mut next_prefetch: Option<Prefetch> = None;
+    let mut play_prefetch: Option<Prefetch> = None;
+    // Normalization gain of the current / preloaded track
+    let mut track_gain: f32 = 1.0;
+    let mut next_gain: f32 = 1.0;
+    // Cue-out of the current track, cue points of the preloaded one
+    let mut track_end: Option<f64> = None;
+    let mut next_start: f64 = 0.0;
+    let mut next_end: Option<f64> = None;
+    // Chapter starts of the current / preloaded track, and the chapter last reported
+    let mut chapters: Vec<f64> = Vec::new();
+    let mut next_chapters: Vec<f64> = Vec::new();
+    let mut chapter: Option<usize> = None;
+    let mut play_counter = PlayCounter::default();
+    // A–B repeat range of the current track
+    let mut ab_loop: Option<(f64, f64)> = None;
+    let mut clock = PlaybackClock::default();
+    let mut output_options = OutputOptions::default();
+    let mut output_backend = OutputBackend::default();
+    let mut output_rate = OutputRate::default();
+    // Gapless handoff to another rate with `OutputRate::Source`: reopen once the old tail has played
+    let mut rate_switch_pending = false;
+    let mut multichannel = false;
+    let mut resampler_quality = ResamplerQuality::default();
+    let mut dsd_output = DsdOutput::default();
+    // Output settings changed; reopen after the pending commands are processed
+    let mut reopen_output = false;
+    // Output released for system sleep; reopened on wake or when playback resumes
+    let mut suspended = false;
+    // Paused by a completed fade-out, but the faded tail is still in the ring buffer
+    let mut pause_draining = false;
+    // Track-change notification deferred until the old track's buffered tail has played out
+    let mut pending_track_change: Option<PendingTrackChange> = None;
 
     let mut last_time_emit = Instant::now();
     let mut last_fft_emit = Instant::now();
+    let mut last_levels_emit = Instant::now();
 
     loop {
         // 1. Process all pending commands
         while let Ok(cmd) = cmd_rx.try_recv() {
+            let cmd = match cmd {
+                AudioCommand::SeekChapter { index, offset_secs } => match chapters.get(index) {
+                    Some(start) => AudioCommand::Seek { position_secs: start + offset_secs.max(0.0) },
+                    None => continue,
+                },
+                cmd => cmd,
+            };
             match cmd {
-                AudioCommand::Play { source } => {
+                AudioCommand::Play { source, headers, start_secs, end_secs, gain, chapters: starts } => {
+                    pause_draining = false;
+                    // Skipping to the preloaded track reuses its background-opened decoder
+                    play_prefetch = next_prefetch.take().filter(|p| p.matches(&source, start_secs));
+                    next_source = None;
+                    ab_loop = None;
+                    pending_track_change = None;
+                    rate_switch_pending = false;
                     if is_playing {
                         // Currently playing: fade out then switch
-                        if let Some(ref out) = output {
+                        if let Some(ref mut out) = output {
                             out.flush();
                         }
+                        clock = PlaybackClock::anchor(position_secs, 0);
                         let out_rate = output.as_ref().map(|o| o.config.sample_rate.0).unwrap_or(source_sample_rate);
                         let out_ch = output.as_ref().map(|o| o.config.channels as usize).unwrap_or(2);
                         let current_gain = match &fade_state {
@@ -240,24 +744,36 @@ fn audio_thread(
                         fade_state = FadeState::FadingOut {
                             gain: current_gain,
                             step: fade_step(FADE_OUT_MS, out_rate, out_ch),
-                            action: FadeAction::PlayNext { source },
+                            action: FadeAction::PlayNext { source, headers, start_secs, end_secs, gain, chapters: starts },
                         };
                     } else {
+                        track_gain = gain;
+                        track_end = end_secs;
+                        chapters = starts;
+                        chapter = None;
+                        play_counter = PlayCounter::new(start_secs);
+                        silence.start_track();
                         execute_play(
-                            &source, true,
+                            &source, &headers, start_secs, play_prefetch.take(), true,
                             &mut decoder, &mut output, &mut resampler, &mut resample_buffer,
-                            &mut eq, &mut fade_state,
+                            &mut eq, &mut vocal, &mut night, &mut limiter, &mut fade_state,
                             &mut source_sample_rate, &mut source_channels,
                             &mut position_secs, &mut duration_secs, &mut is_playing,
-                            volume, &state, &app_handle,
+                            volume, output_options, &output_backend, output_rate, multichannel, resampler_quality, dsd_output,
+                            &state, &app_handle,
                         );
+                        if let Some(ref out) = output {
+                            out.set_muted(muted);
+                        }
+                        clock = PlaybackClock::anchor(position_secs, 0);
                     }
                 }
                 AudioCommand::Pause => {
                     if is_playing {
-                        if let Some(ref out) = output {
+                        if let Some(ref mut out) = output {
                             out.flush();
                         }
+                        clock = PlaybackClock::anchor(position_secs, 0);
                         let out_rate = output.as_ref().map(|o| o.config.sample_rate.0).unwrap_or(source_sample_rate);
                         let out_ch = output.as_ref().map(|o| o.config.channels as usize).unwrap_or(2);
                         let current_gain = match &fade_state {
@@ -275,6 +791,10 @@ fn audio_thread(
                 AudioCommand::Resume => {
                     if !is_playing && decoder.is_some() {
                         is_playing = true;
+                        pause_draining = false;
+                        if suspended && output.is_none() {
+                            reopen_output = true;
+                        }
                         if let Some(ref out) = output {
                             out.resume();
                         }
@@ -300,10 +820,17 @@ fn audio_thread(
                     }
                 }
                 AudioCommand::Stop => {
+                    pause_draining = false;
+                    next_source = None;
+                    next_prefetch = None;
+                    play_prefetch = None;
+                    pending_track_change = None;
+                    rate_switch_pending = false;
                     if is_playing {
-                        if let Some(ref out) = output {
+                        if let Some(ref mut out) = output {
                             out.flush();
                         }
+                        clock = PlaybackClock::anchor(position_secs, 0);
                         let out_rate = output.as_ref().map(|o| o.config.sample_rate.0).unwrap_or(source_sample_rate);
                         let out_ch = output.as_ref().map(|o| o.config.channels as usize).unwrap_or(2);
                         let current_gain = match &fade_state {
@@ -340,32 +867,295 @@ fn audio_thread(
                             eprintln!("Seek error: {}", e);
                         } else {
                             position_secs = clamped;
-                            if let Some(ref out) = output {
+                            // Discard audio from the old position, including while paused,
+                            // so resume starts exactly at the new position
+                            if let Some(ref mut out) = output {
                                 out.flush();
                             }
+                            resample_buffer.clear();
+                            clock = PlaybackClock::anchor(clamped, 0);
                             eq.reset();
+                            vocal.reset();
+                            night.reset();
+                            limiter.reset();
                             update_state(&state, is_playing, position_secs, duration_secs, volume);
+                            if !is_playing {
+                                // No time events are emitted while paused, so report the new position now
+                                let _ = app_handle.emit(
+                                    "audio:time",
+                                    TimePayload {
+                                        position: position_secs,
+                                        duration: duration_secs,
+                                    },
+                                );
+                            }
                         }
                     }
                 }
+                // Turned into a Seek before matching
+                AudioCommand::SeekChapter { .. } => {}
                 AudioCommand::SetVolume { volume: vol } => {
                     volume = vol.clamp(0.0, 1.0);
+                    let out_rate = output.as_ref().map(|o| o.config.sample_rate.0).unwrap_or(source_sample_rate);
+                    let out_ch = output.as_ref().map(|o| o.config.channels as usize).unwrap_or(2);
+                    volume_ramp.set_target(volume_curve.gain(volume), out_rate, out_ch);
                     update_state(&state, is_playing, position_secs, duration_secs, volume);
                 }
+                AudioCommand::SetVolumeCurve { curve } => {
+                    volume_curve = curve;
+                    let out_rate = output.as_ref().map(|o| o.config.sample_rate.0).unwrap_or(source_sample_rate);
+                    let out_ch = output.as_ref().map(|o| o.config.channels as usize).unwrap_or(2);
+                    volume_ramp.set_target(volume_curve.gain(volume), out_rate, out_ch);
+                }
+                AudioCommand::SetMuted { muted: m } => {
+                    muted = m;
+                    if let Some(ref out) = output {
+                        out.set_muted(muted);
+                    }
+                    if let Ok(mut s) = state.lock() {
+                        s.muted = muted;
+                    }
+                }
                 AudioCommand::SetEqBands { gains } => {
                     eq.set_gains(&gains);
                 }
+                AudioCommand::SetEqLayout { layout } => {
+                    eq.set_layout(layout);
+                }
                 AudioCommand::SetEqEnabled { enabled } => {
                     eq.set_enabled(enabled);
                 }
+                AudioCommand::SetVocalReduction { enabled, strength, band_limited } => {
+                    vocal.set_enabled(enabled);
+                    vocal.set_strength(strength);
+                    if let Some(band_limited) = band_limited {
+                        vocal.set_band_limited(band_limited);
+                    }
+                }
+                AudioCommand::SetNightMode { enabled, preset } => {
+                    night.set_enabled(enabled);
+                    night.set_preset(preset);
+                }
+                AudioCommand::SetLimiter { enabled } => {
+                    limiter_enabled = enabled;
+                }
+                AudioCommand::SetDither { enabled } => {
+                    dither_enabled = enabled;
+                }
+                AudioCommand::SetSkipSilence { enabled } => {
+                    silence.set_enabled(enabled);
+                }
+                AudioCommand::SetEndFade { secs } => {
+                    end_fade_secs = secs.max(0.0);
+                }
                 AudioCommand::EnableVisualization { enabled } => {
                     fft_proc.set_enabled(enabled);
                 }
+                AudioCommand::EnableLevels { enabled } => {
+                    levels.set_enabled(enabled);
+                }
+                AudioCommand::PreloadNext { source, headers, start_secs, end_secs, gain, chapters: starts } => {
+                    next_prefetch = source
+                        .clone()
+                        .map(|source| Prefetch::spawn(source, headers.clone(), start_secs, dsd_output));
+                    next_source = source;
+                    next_headers = headers;
+                    next_start = start_secs;
+                    next_end = end_secs;
+                    next_gain = gain;
+                    next_chapters = starts;
+                }
+                AudioCommand::SetTrackGain { gain, next_gain: preloaded_gain } => {
+                    track_gain = gain;
+                    next_gain = preloaded_gain;
+                }
+                AudioCommand::SetAbLoop { range } => {
+                    // The loop can't outlast the track (or its cue-out), or playback would end first
+                    let track_length = track_end.or((duration_secs > 0.0).then_some(duration_secs));
+                    ab_loop = range
+                        .map(|(start, end)| (start.max(0.0), track_length.map_or(end, |len| end.min(len))))
+                        .filter(|(start, end)| end > start);
+                }
+                AudioCommand::GetDspSettings { reply } => {
+                    let _ = reply.send(DspSettings {
+                        eq_enabled: eq.is_enabled(),
+                        eq_layout: eq.layout(),
+                        eq_gains: eq.gains().to_vec(),
+                        vocal_reduction: vocal.is_enabled().then(|| vocal.strength()),
+                        vocal_band_limited: vocal.is_band_limited(),
+                        night_mode: night.is_enabled().then(|| night.preset()),
+                        limiter: limiter_enabled,
+                        resampler_quality,
+                    });
+                }
+                AudioCommand::SystemSuspend => {
+                    if let (Some(ref mut dec), Some(out)) = (&mut decoder, output.take()) {
+                        // Cut right away (no time for a fade) and resume from what was heard
+                        let heard = clock.position(&out, duration_secs);
+                        drop(out);
+                        if dec.seek(heard).is_ok() {
+                            position_secs = heard;
+                        }
+                        clock = PlaybackClock::anchor(position_secs, 0);
+                        resample_buffer.clear();
+                        fade_state = FadeState::None;
+                        pause_draining = false;
+                        suspended = true;
+                        if is_playing {
+                            is_playing = false;
+                            let _ = app_handle.emit("audio:state_changed", StateChangedPayload { is_playing: false });
+                        }
+                        update_state(&state, false, position_secs, duration_secs, volume);
+                    }
+                }
+                AudioCommand::SystemResume => {
+                    if suspended && output.is_none() {
+                        reopen_output = true;
+                    }
+                }
+                AudioCommand::GetOutputInfo { reply } => {
+                    let info = output.as_ref().map(|out| {
+                        let resampling = resampler.is_some();
+                        let other_dsp = vocal.is_enabled()
+                            || night.is_enabled()
+                            || (track_gain - 1.0).abs() > f32::EPSILON;
+                        OutputInfo {
+                            device_name: out.device_name.clone(),
+                            sample_rate: out.config.sample_rate.0,
+                            channels: out.config.channels,
+                            source_sample_rate,
+                            source_channels,
+                            resampling,
+                            eq_enabled: eq.is_enabled(),
+                            other_dsp,
+                            bit_perfect: !resampling
+                                && !eq.is_enabled()
+                                && !other_dsp
+                                && !muted
+                                && volume_ramp.is_unity()
+                                && out.config.channels as usize == source_channels,
+                        }
+                    });
+                    let _ = reply.send(info);
+                }
+                AudioCommand::SetOutputOptions { options } => {
+                    output_options = options;
+                    reopen_output = true;
+                }
+                AudioCommand::SetOutputBackend { backend } => {
+                    if backend != output_backend {
+                        output_backend = backend;
+                        reopen_output = true;
+                    }
+                }
+                AudioCommand::SetOutputRate { rate } => {
+                    if rate != output_rate {
+                        output_rate = rate;
+                        rate_switch_pending = false;
+                        reopen_output = true;
+                    }
+                }
+                AudioCommand::SetMultichannel { enabled } => {
+                    if enabled != multichannel {
+                        multichannel = enabled;
+                        reopen_output = true;
+                    }
+                }
+                AudioCommand::SetDsdOutput { output } => {
+                    // Takes effect from the next DSD track
+                    dsd_output = output;
+                }
+                AudioCommand::SetResamplerQuality { quality } => {
+                    if quality != resampler_quality {
+                        resampler_quality = quality;
+                        // Buffered input is still at the source rate, so the new resampler takes it over
+                        if let (Some(out), Some(_)) = (&output, &resampler) {
+                            resampler = AudioResampler::new(
+                                source_sample_rate, out.config.sample_rate.0,
+                                out.config.channels as usize, quality,
+                            )
+                            .map_err(|e| eprintln!("Resampler init warning: {}", e))
+                            .ok();
+                        }
+                    }
+                }
+            }
+        }
+
+        // Device went away (unplugged, disabled): move to the current default device
+        let device_lost = output.as_ref().is_some_and(|out| out.has_failed());
+        if device_lost {
+            eprintln!("Audio output device lost, reopening on the default device");
+            if decoder.is_some() {
+                reopen_output = true;
+            } else {
+                output = None;
+            }
+        }
+
+        // Reopen an active output so new buffering / backend settings apply right away,
+        // continuing from what has actually been heard
+        if std::mem::take(&mut reopen_output) {
+            let heard = match output.take() {
+                Some(old) => Some(clock.position(&old, duration_secs)),
+                None => std::mem::take(&mut suspended).then_some(position_secs),
+            };
+            if let (Some(ref mut dec), Some(heard)) = (&mut decoder, heard) {
+                let channels = output_channels_for(source_channels, multichannel);
+
+                let rate = output_rate.for_source(source_sample_rate);
+                match AudioOutput::new(rate, channels, output_options, &output_backend) {
+                    Ok(out) => {
+                        out.set_muted(muted);
+                        if !is_playing {
+                            out.pause();
+                            pause_draining = false;
+                        }
+                        resampler = None;
+                        retarget_resampler(
+                            source_sample_rate, &out,
+                            &mut resampler, &mut resample_buffer, source_sample_rate, resampler_quality,
+                        );
+                        let out_rate = out.config.sample_rate.0;
+                        let effective_rate = if resampler.is_some() { out_rate } else { source_sample_rate };
+                        let out_channels = out.config.channels as usize;
+                        rebuild_eq(&mut eq, effective_rate, out_channels);
+                        rebuild_vocal_reducer(&mut vocal, effective_rate, out_channels);
+                        rebuild_night_mode(&mut night, effective_rate, out_channels);
+                        limiter = Limiter::new(effective_rate, out_channels);
+
+                        if dec.seek(heard).is_ok() {
+                            position_secs = heard;
+                        }
+                        clock = PlaybackClock::anchor(position_secs, 0);
+                        if device_lost {
+                            let _ = app_handle.emit(
+                                "audio:device_changed",
+                                DeviceChangedPayload { device_name: out.device_name.clone() },
+                            );
+                        }
+                        output = Some(out);
+                    }
+                    Err(e) => {
+                        decoder = None;
+                        resampler = None;
+                        resample_buffer.clear();
+                        is_playing = false;
+                        pause_draining = false;
+                        fade_state = FadeState::None;
+                        update_state(&state, false, position_secs, duration_secs, volume);
+                        let _ = app_handle.emit("audio:error", e);
+                        let _ = app_handle.emit("audio:state_changed", StateChangedPayload { is_playing: false });
+                    }
+                }
             }
         }
 
+        sleep_inhibitor.set_active(is_playing);
+
         // 2. If playing, decode and feed output
         let mut fade_completed = false;
+        let mut reached_end = false;
         if is_playing {
             if let (Some(ref mut dec), Some(ref mut out)) = (&mut decoder, &mut output) {
                 let out_channels = out.config.channels as usize;
@@ -376,16 +1166,94 @@ fn audio_thread(
                         break;
                     }
 
-                    match dec.decode_next() {
+                    // A–B loop: back to A once B is decoded; the audio buffered up to B still plays out
+                    if let Some((loop_start, loop_end)) = ab_loop {
+                        if position_secs >= loop_end {
+                            match dec.seek(loop_start) {
+                                Ok(()) => {
+                                    position_secs = loop_start;
+                                    clock = PlaybackClock::anchor(loop_start, out.frames_written());
+                                }
+                                Err(e) => {
+                                    eprintln!("A-B loop seek error: {}", e);
+                                    ab_loop = None;
+                                }
+                            }
+                        }
+                    }
+
+                    // Past the cue-out the track ends exactly like at the end of the stream
+                    let at_cue_out = track_end.is_some_and(|end| position_secs >= end);
+                    let next_packet = if at_cue_out { Ok(None) } else { dec.decode_next() };
+                    match next_packet {
                         Ok(Some(mut samples)) => {
+                            if dec.take_spec_change() {
+                                // Mid-stream format change: the output stream stays open, channel
+                                // conversion adapts per packet and the resampler follows the new rate
+                                retarget_resampler(
+                                    dec.info.sample_rate, out,
+                                    &mut resampler, &mut resample_buffer, source_sample_rate, resampler_quality,
+                                );
+                                source_sample_rate = dec.info.sample_rate;
+                                source_channels = dec.info.channels;
+                                eq.reset();
+                                vocal.reset();
+                                night.reset();
+                            }
+
                             let decoded_channels = source_channels;
                             let decoded_frames = samples.len() / decoded_channels;
+                            let packet_secs = decoded_frames as f64 / source_sample_rate as f64;
 
-                            if decoded_channels != out_channels {
+                            // Skipped silence never reaches the output; the clock jumps past it
+                            if !dec.info.dop && silence.should_skip(&samples, packet_secs) {
+                                position_secs += packet_secs;
+                                if duration_secs > 0.0 {
+                                    position_secs = position_secs.min(duration_secs);
+                                }
+                                clock = PlaybackClock::anchor(position_secs, out.frames_written());
+                                continue;
+                            }
+                            // Boosting stages the user can't turn the limiter off for
+                            let limit = (limiter_enabled && (eq.is_enabled() || vocal.is_enabled()))
+                                || track_gain > 1.0
+                                || night.is_enabled();
+                            // Requantize for integer devices when the samples carry more than the
+                            // device keeps: deeper or lossy sources, or anything DSP / volume touched.
+                            // f32 holds 24 bits exactly, so deeper devices need nothing.
+                            let processed = resampler.is_some()
+                                || limit
+                                || eq.is_enabled()
+                                || vocal.is_enabled()
+                                || (track_gain - 1.0).abs() > f32::EPSILON
+                                || !volume_ramp.is_unity();
+                            let dither_bits = out.device_bits.filter(|&device| {
+                                dither_enabled
+                                    && device < 24
+                                    && (processed || dec.info.bits_per_sample.is_none_or(|bits| bits > device))
+                            });
+
+                            if decoded_channels != out_channels && !dec.info.dop {
                                 samples = convert_channels(&samples, decoded_channels, out_channels);
                             }
 
-                            if let Some(ref mut rs) = resampler {
+                            // Playback stops at the cue-out, else at the decoder's duration
+                            if end_fade_secs > 0.0 && !dec.info.dop {
+                                if let Some(stop) = track_end.or((duration_secs > 0.0).then_some(duration_secs)) {
+                                    apply_end_fade(&mut samples, out_channels, position_secs, packet_secs, stop, end_fade_secs);
+                                }
+                            }
+
+                            if dec.info.dop {
+                                // DoP words must reach the DAC bit-exact: no DSP, volume, fades or
+                                // dither. A pending fade-out ends at once.
+                                if matches!(fade_state, FadeState::FadingOut { .. }) {
+                                    fade_completed = true;
+                                    break;
+                                }
+                                fade_state = FadeState::None;
+                                out.push(&samples);
+                            } else if let Some(ref mut rs) = resampler {
                                 resample_buffer.extend_from_slice(&samples);
                                 let needed = rs.input_frames_needed() * out_channels;
                                 while resample_buffer.len() >= needed {
@@ -393,14 +1261,23 @@ fn audio_thread(
                                     match rs.process(&chunk) {
                                         Ok(resampled) => {
                                             let mut resampled = resampled;
+                                            apply_replay_gain(&mut resampled, track_gain);
+                                            vocal.process(&mut resampled);
                                             eq.process(&mut resampled);
+                                            night.process(&mut resampled);
                                             fft_proc.push_samples(&resampled, out_channels);
-                                            if apply_volume_with_fade(&mut resampled, volume, &mut fade_state) {
-                                                out.producer.push_slice(&resampled);
+                                            limiter.process(&mut resampled, limit);
+                                            levels.push_samples(&resampled, out_channels);
+                                            let fade_done = apply_volume_with_fade(&mut resampled, &mut volume_ramp, &mut fade_state);
+                                            if let Some(bits) = dither_bits {
+                                                dither.process(&mut resampled, bits);
+                                            }
+                                            if fade_done {
+                                                out.push(&resampled);
                                                 fade_completed = true;
                                                 break;
                                             }
-                                            out.producer.push_slice(&resampled);
+                                            out.push(&resampled);
                                         }
                                         Err(e) => {
                                             eprintln!("Resample error: {}", e);
@@ -412,14 +1289,23 @@ fn audio_thread(
                                     }
                                 }
                             } else {
+                                apply_replay_gain(&mut samples, track_gain);
+                                vocal.process(&mut samples);
                                 eq.process(&mut samples);
+                                night.process(&mut samples);
                                 fft_proc.push_samples(&samples, out_channels);
-                                if apply_volume_with_fade(&mut samples, volume, &mut fade_state) {
-                                    out.producer.push_slice(&samples);
+                                limiter.process(&mut samples, limit);
+                                levels.push_samples(&samples, out_channels);
+                                let fade_done = apply_volume_with_fade(&mut samples, &mut volume_ramp, &mut fade_state);
+                                if let Some(bits) = dither_bits {
+                                    dither.process(&mut samples, bits);
+                                }
+                                if fade_done {
+                                    out.push(&samples);
                                     fade_completed = true;
                                 }
                                 if !fade_completed {
-                                    out.producer.push_slice(&samples);
+                                    out.push(&samples);
                                 }
                             }
 
@@ -427,15 +1313,19 @@ fn audio_thread(
                                 break;
                             }
 
-                            position_secs += decoded_frames as f64 / source_sample_rate as f64;
+                            position_secs += packet_secs;
                             if position_secs > duration_secs && duration_secs > 0.0 {
                                 position_secs = duration_secs;
                             }
                         }
+                        Ok(None) if next_source.is_some() => {
+                            reached_end = true;
+                            break;
+                        }
                         Ok(None) => {
                             // End of stream — use accumulated position as true duration
                             // if the initial duration was unknown or suspiciously off
-                            if duration_secs <= 0.0 || (position_secs - duration_secs).abs() > 1.0 {
+                            if !at_cue_out && (duration_secs <= 0.0 || (position_secs - duration_secs).abs() > 1.0) {
                                 duration_secs = position_secs;
                             }
                             is_playing = false;
@@ -448,7 +1338,7 @@ fn audio_thread(
                         Err(e) => {
                             is_playing = false;
                             fade_state = FadeState::None;
-                            let _ = app_handle.emit("audio:error", ErrorPayload { message: e });
+                            let _ = app_handle.emit("audio:error", e);
                             break;
                         }
                     }
@@ -456,6 +1346,66 @@ fn audio_thread(
             }
         }
 
+        // 2b. Natural end with a preloaded next track: hand off without draining the output
+        if reached_end {
+            if let Some(source) = next_source.take() {
+                let boundary_frame = output.as_ref().map(|out| out.frames_written()).unwrap_or(0);
+                let previous_clock = clock;
+                let previous_duration = duration_secs;
+
+                match execute_gapless_handoff(
+                    &source, &next_headers, next_start, next_prefetch.take(),
+                    &mut decoder, &output, &mut resampler, &mut resample_buffer,
+                    &mut source_sample_rate, &mut source_channels,
+                    &mut position_secs, &mut duration_secs, resampler_quality, dsd_output,
+                ) {
+                    Ok(()) => {
+                        track_gain = next_gain;
+                        track_end = next_end;
+                        chapters = std::mem::take(&mut next_chapters);
+                        chapter = None;
+                        play_counter = PlayCounter::new(next_start);
+                        ab_loop = None;
+                        silence.start_track();
+                        let song_id = {
+                            let mut history = history.lock().unwrap();
+                            history.advance();
+                            history.current().map(str::to_string)
+                        };
+                        // The new clock takes over once the device reaches the boundary frame;
+                        // until then the old track's tail is reported
+                        clock = PlaybackClock::anchor(position_secs, boundary_frame);
+                        pending_track_change = Some(PendingTrackChange {
+                            payload: TrackChangedPayload { source, song_id, duration: duration_secs },
+                            previous_clock,
+                            previous_duration,
+                        });
+                        rate_switch_pending = output_rate == OutputRate::Source
+                            && output.as_ref().is_some_and(|out| out.config.sample_rate.0 != source_sample_rate);
+                    }
+                    Err(e) => {
+                        // Couldn't open the next track: finish like a normal end of stream
+                        is_playing = false;
+                        fade_state = FadeState::None;
+                        update_state(&state, false, position_secs, duration_secs, volume);
+                        let _ = app_handle.emit("audio:error", e);
+                        let _ = app_handle.emit("audio:ended", ());
+                        let _ = app_handle.emit("audio:state_changed", StateChangedPayload { is_playing: false });
+                    }
+                }
+            }
+        }
+
+        if pending_track_change.is_some() && output.as_ref().is_some_and(|out| clock.reached(out)) {
+            if let Some(change) = pending_track_change.take() {
+                let _ = app_handle.emit("audio:track_changed", change.payload);
+            }
+            // Until now the new track was resampled to the old rate; continue at its own
+            if std::mem::take(&mut rate_switch_pending) {
+                reopen_output = true;
+            }
+        }
+
         // 3. Handle fade-out completion
         if fade_completed {
             // Take ownership of the action from fade_state
@@ -463,10 +1413,9 @@ fn audio_thread(
             match action {
                 FadeState::FadingOut { action, .. } => match action {
                     FadeAction::Pause => {
+                        // Stop decoding now; the output is paused once the faded tail has played
                         is_playing = false;
-                        if let Some(ref out) = output {
-                            out.pause();
-                        }
+                        pause_draining = true;
                         update_state(&state, false, position_secs, duration_secs, volume);
                         let _ = app_handle.emit("audio:state_changed", StateChangedPayload { is_playing: false });
                     }
@@ -483,41 +1432,76 @@ fn audio_thread(
                         update_state(&state, false, 0.0, 0.0, volume);
                         let _ = app_handle.emit("audio:state_changed", StateChangedPayload { is_playing: false });
                     }
-                    FadeAction::PlayNext { source } => {
+                    FadeAction::PlayNext { source, headers, start_secs, end_secs, gain, chapters: starts } => {
+                        track_gain = gain;
+                        track_end = end_secs;
+                        chapters = starts;
+                        chapter = None;
+                        play_counter = PlayCounter::new(start_secs);
+                        ab_loop = None;
+                        silence.start_track();
                         execute_play(
-                            &source, true,
+                            &source, &headers, start_secs, play_prefetch.take(), true,
                             &mut decoder, &mut output, &mut resampler, &mut resample_buffer,
-                            &mut eq, &mut fade_state,
+                            &mut eq, &mut vocal, &mut night, &mut limiter, &mut fade_state,
                             &mut source_sample_rate, &mut source_channels,
                             &mut position_secs, &mut duration_secs, &mut is_playing,
-                            volume, &state, &app_handle,
+                            volume, output_options, &output_backend, output_rate, multichannel, resampler_quality, dsd_output,
+                            &state, &app_handle,
                         );
+                        if let Some(ref out) = output {
+                            out.set_muted(muted);
+                        }
+                        clock = PlaybackClock::anchor(position_secs, 0);
                     }
                 },
                 _ => {}
             }
         }
 
+        if pause_draining {
+            match output {
+                Some(ref out) if out.frames_played() < out.frames_written() => {}
+                Some(ref out) => {
+                    out.pause();
+                    pause_draining = false;
+                }
+                None => pause_draining = false,
+            }
+        }
+
         // 4. Emit time event ~4Hz
         if is_playing && last_time_emit.elapsed() >= Duration::from_millis(250) {
-            let playback_pos = if let Some(ref out) = output {
-                let buffered_samples = out.producer.occupied_len();
-                let out_rate = out.config.sample_rate.0 as f64;
-                let out_ch = out.config.channels as f64;
-                let buffered_secs = buffered_samples as f64 / (out_rate * out_ch);
-                (position_secs - buffered_secs).max(0.0)
-            } else {
-                position_secs
+            // The old track is still heard until a pending handoff reaches the device
+            let (heard_clock, heard_duration) = match &pending_track_change {
+                Some(change) => (change.previous_clock, change.previous_duration),
+                None => (clock, duration_secs),
             };
+            let playback_pos = output
+                .as_ref()
+                .map(|out| heard_clock.position(out, heard_duration))
+                .unwrap_or(position_secs);
 
-            update_state(&state, is_playing, playback_pos, duration_secs, volume);
+            update_state(&state, is_playing, playback_pos, heard_duration, volume);
             let _ = app_handle.emit(
                 "audio:time",
                 TimePayload {
                     position: playback_pos,
-                    duration: duration_secs,
+                    duration: heard_duration,
                 },
             );
+
+            // Not until a gapless handoff is heard, or the old track's chapters would be skipped
+            if let Some(index) = chapters.iter().rposition(|&start| start <= playback_pos) {
+                if pending_track_change.is_none() && chapter != Some(index) {
+                    chapter = Some(index);
+                    let _ = app_handle.emit("audio:chapter_changed", ChapterChangedPayload { index, start: chapters[index] });
+                }
+            }
+            if pending_track_change.is_none() && play_counter.update(playback_pos, track_end.unwrap_or(duration_secs)) {
+                let song_id = history.lock().unwrap().current().map(str::to_string);
+                let _ = app_handle.emit("audio:track_played", TrackPlayedPayload { song_id, listened: play_counter.listened });
+            }
             last_time_emit = Instant::now();
         }
 
@@ -534,6 +1518,13 @@ fn audio_thread(
             last_fft_emit = Instant::now();
         }
 
+        // 5b. Emit level meter event ~20Hz
+        if is_playing && levels.is_enabled() && last_levels_emit.elapsed() >= Duration::from_millis(50) {
+            let (peak, rms) = levels.take();
+            let _ = app_handle.emit("audio:levels", LevelsPayload { peak, rms });
+            last_levels_emit = Instant::now();
+        }
+
         // 6. Sleep to avoid busy-waiting
         if is_playing {
             std::thread::sleep(Duration::from_millis(1));
@@ -562,20 +1553,49 @@ fn fade_step(duration_ms: f32, sample_rate: u32, channels: usize) -> f32 {
     1.0 / (duration_ms * 0.001 * sample_rate as f32 * channels as f32)
 }
 
+/// Apply the track's normalization gain ahead of the DSP chain, so the EQ and night
+/// mode see every track at the same loudness. Boosted peaks are left to the limiter
+/// at the end of the chain, which also catches night mode's makeup gain.
+fn apply_replay_gain(samples: &mut [f32], track_gain: f32) {
+    if (track_gain - 1.0).abs() > f32::EPSILON {
+        for s in samples.iter_mut() {
+            *s *= track_gain;
+        }
+    }
+}
+
+/// Fade linearly to silence over the last `fade_secs` before `stop`, for a packet
+/// of `packet_secs` starting at `start`. Applied before normalization and DSP, so
+/// the fade starts from the track's normalized level.
+fn apply_end_fade(samples: &mut [f32], channels: usize, start: f64, packet_secs: f64, stop: f64, fade_secs: f64) {
+    let fade_start = stop - fade_secs;
+    if start + packet_secs <= fade_start || fade_start <= 0.0 {
+        return;
+    }
+    let frames = samples.len() / channels;
+    for (i, frame) in samples.chunks_exact_mut(channels).enumerate() {
+        let t = start + packet_secs * i as f64 / frames as f64;
+        let gain = ((stop - t) / fade_secs).clamp(0.0, 1.0) as f32;
+        for s in frame {
+            *s *= gain;
+        }
+    }
+}
+
 /// Apply volume and fade envelope per-sample. Returns `true` when a fade-out reaches 0.0.
-fn apply_volume_with_fade(samples: &mut [f32], volume: f32, fade: &mut FadeState) -> bool {
+fn apply_volume_with_fade(samples: &mut [f32], volume: &mut VolumeRamp, fade: &mut FadeState) -> bool {
     match fade {
         FadeState::None => {
-            if (volume - 1.0).abs() > f32::EPSILON {
+            if !volume.is_unity() {
                 for s in samples.iter_mut() {
-                    *s *= volume;
+                    *s *= volume.next_gain();
                 }
             }
             false
         }
         FadeState::FadingIn { gain, step } => {
             for s in samples.iter_mut() {
-                *s *= volume * *gain;
+                *s *= volume.next_gain() * *gain;
                 *gain = (*gain + *step).min(1.0);
             }
             if *gain >= 1.0 {
@@ -585,7 +1605,7 @@ fn apply_volume_with_fade(samples: &mut [f32], volume: f32, fade: &mut FadeState
         }
         FadeState::FadingOut { gain, step, .. } => {
             for s in samples.iter_mut() {
-                *s *= volume * *gain;
+                *s *= volume.next_gain() * *gain;
                 *gain = (*gain - *step).max(0.0);
             }
             *gain <= 0.0
@@ -593,7 +1613,24 @@ fn apply_volume_with_fade(samples: &mut [f32], volume: f32, fade: &mut FadeState
     }
 }
 
-/// Convert between channel counts (mono<->stereo).
+/// Whether DoP frames can go out unchanged: same rate and channel count as the stream
+fn dop_fits(dec: &AudioDecoder, out: &AudioOutput) -> bool {
+    out.config.sample_rate.0 == dec.info.sample_rate && out.config.channels as usize == dec.info.channels
+}
+
+/// Output channel count for a source: its own layout (up to 7.1) with multichannel
+/// output on, otherwise at most stereo.
+fn output_channels_for(source_channels: usize, multichannel: bool) -> u16 {
+    let max = if multichannel { MAX_OUTPUT_CHANNELS } else { 2 };
+    source_channels.clamp(1, max) as u16
+}
+
+/// -3 dB, the usual weight for center and surround channels in a downmix.
+const DOWNMIX_WEIGHT: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Convert between channel counts.
+/// Multichannel layouts are taken in the usual interleaved order:
+/// FL, FR, FC, LFE, then surround pairs (left, right).
 fn convert_channels(samples: &[f32], from_ch: usize, to_ch: usize) -> Vec<f32> {
     if from_ch == to_ch {
         return samples.to_vec();
@@ -616,19 +1653,47 @@ fn convert_channels(samples: &[f32], from_ch: usize, to_ch: usize) -> Vec<f32> {
             let r = samples[frame * 2 + 1];
             out.push((l + r) * 0.5);
         }
+    } else if from_ch > 2 && to_ch <= 2 {
+        // Surround to stereo: center and surrounds at -3 dB, LFE dropped,
+        // scaled so a full-scale signal on every channel can't clip
+        let surround_pairs = from_ch.saturating_sub(4) / 2;
+        let norm = 1.0 / (1.0 + DOWNMIX_WEIGHT * (1 + surround_pairs) as f32);
+        for frame in samples.chunks(from_ch) {
+            let center = frame[2] * DOWNMIX_WEIGHT;
+            let mut l = frame[0] + center;
+            let mut r = frame[1] + center;
+            for pair in frame[4.min(from_ch)..].chunks(2) {
+                l += pair[0] * DOWNMIX_WEIGHT;
+                r += pair.get(1).copied().unwrap_or(pair[0]) * DOWNMIX_WEIGHT;
+            }
+            if to_ch == 1 {
+                out.push((l + r) * 0.5 * norm);
+            } else {
+                out.push(l * norm);
+                out.push(r * norm);
+            }
+        }
     } else if from_ch > to_ch {
-        // Downmix: average first to_ch channels
-        for frame in 0..frames {
-            for ch in 0..to_ch {
-                out.push(samples[frame * from_ch + ch]);
+        // Fewer surround channels (e.g. 7.1 to 5.1): fold the extra pairs into the last pair
+        let last_pair = to_ch - 2;
+        for frame in samples.chunks(from_ch) {
+            let mut mixed = frame[..to_ch].to_vec();
+            for (i, s) in frame[to_ch..].iter().enumerate() {
+                mixed[last_pair + i % 2] += s * DOWNMIX_WEIGHT;
             }
+            out.extend(mixed);
         }
     } else {
-        // Upmix: duplicate first channel into extra channels
-        for frame in 0..frames {
+        // Upmix: keep the existing channels in front, leave the rest silent
+        // (mono goes to both front speakers)
+        for frame in samples.chunks(from_ch) {
             for ch in 0..to_ch {
-                let src_ch = ch.min(from_ch - 1);
-                out.push(samples[frame * from_ch + src_ch]);
+                let s = if from_ch == 1 && ch < 2 {
+                    frame[0]
+                } else {
+                    frame.get(ch).copied().unwrap_or(0.0)
+                };
+                out.push(s);
             }
         }
     }
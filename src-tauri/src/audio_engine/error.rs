@@ -0,0 +1,63 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Machine-readable category of an audio engine failure, sent as `code` in `audio:error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioErrorCode {
+    FileNotFound,
+    PermissionDenied,
+    UnsupportedFormat,
+    UnsupportedCodec,
+    DecodeFailed,
+    DeviceUnavailable,
+    NetworkTimeout,
+    NetworkError,
+    Unknown,
+}
+
+/// Audio engine error: a stable `code` for the frontend plus human-readable `details`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioError {
+    pub code: AudioErrorCode,
+    pub details: String,
+}
+
+impl AudioError {
+    pub fn new(code: AudioErrorCode, details: impl Into<String>) -> Self {
+        Self {
+            code,
+            details: details.into(),
+        }
+    }
+
+    /// Classify an I/O error; `remote` marks errors coming from an HTTP source.
+    pub fn from_io(err: &std::io::Error, remote: bool, context: &str) -> Self {
+        let code = match err.kind() {
+            std::io::ErrorKind::NotFound => AudioErrorCode::FileNotFound,
+            std::io::ErrorKind::PermissionDenied => AudioErrorCode::PermissionDenied,
+            std::io::ErrorKind::TimedOut => AudioErrorCode::NetworkTimeout,
+            _ if remote => AudioErrorCode::NetworkError,
+            _ => AudioErrorCode::Unknown,
+        };
+        Self::new(code, format!("{}: {}", context, err))
+    }
+
+    /// Classify a reqwest error (timeouts vs. other network failures).
+    pub fn from_http(err: &reqwest::Error, context: &str) -> Self {
+        let code = if err.is_timeout() {
+            AudioErrorCode::NetworkTimeout
+        } else {
+            AudioErrorCode::NetworkError
+        };
+        Self::new(code, format!("{}: {}", context, err))
+    }
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.details)
+    }
+}
+
+impl std::error::Error for AudioError {}
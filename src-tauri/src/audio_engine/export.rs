@@ -0,0 +1,288 @@
+//! Offline render of a track through the playback chain (decoder → resampler →
+//! vocal reduction → EQ → night mode → limiter) into a WAV or FLAC file,
+//! without an output device.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::decoder::AudioDecoder;
+use super::dsp::{EqLayout, Equalizer, Limiter, NightMode, NightModePreset, VocalReducer};
+use super::flac::FlacWriter;
+use super::resampler::{AudioResampler, ResamplerQuality};
+
+/// Bit depth of exported files; f32 samples carry 24 bits exactly
+const EXPORT_BITS: u32 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Wav,
+    Flac,
+}
+
+/// Settings of the live DSP chain, so an export sounds like playback.
+#[derive(Debug, Clone)]
+pub struct DspSettings {
+    pub eq_enabled: bool,
+    pub eq_layout: EqLayout,
+    pub eq_gains: Vec<f32>,
+    /// Strength while vocal reduction is on
+    pub vocal_reduction: Option<f32>,
+    pub vocal_band_limited: bool,
+    pub night_mode: Option<NightModePreset>,
+    pub limiter: bool,
+    pub resampler_quality: ResamplerQuality,
+}
+
+/// Render `source` into `output_path`, converting to `sample_rate` if given.
+/// `on_progress` receives the rendered share (0.0 - 1.0) as it advances.
+pub fn export(
+    source: &str,
+    output_path: &Path,
+    format: ExportFormat,
+    sample_rate: Option<u32>,
+    settings: &DspSettings,
+    on_progress: impl FnMut(f64),
+) -> Result<(), String> {
+    let result = render(source, output_path, format, sample_rate, settings, on_progress);
+    if result.is_err() {
+        let _ = std::fs::remove_file(output_path);
+    }
+    result
+}
+
+fn render(
+    source: &str,
+    output_path: &Path,
+    format: ExportFormat,
+    sample_rate: Option<u32>,
+    settings: &DspSettings,
+    mut on_progress: impl FnMut(f64),
+) -> Result<(), String> {
+    let mut dec = AudioDecoder::open(source).map_err(|e| e.to_string())?;
+    let channels = dec.info.channels;
+    let source_rate = dec.info.sample_rate;
+    let rate = sample_rate.filter(|&rate| rate > 0).unwrap_or(source_rate);
+    let total_frames = (dec.info.duration_secs * source_rate as f64).max(1.0);
+
+    let mut resampler = if rate != source_rate {
+        Some(AudioResampler::new(source_rate, rate, channels, settings.resampler_quality)?)
+    } else {
+        None
+    };
+    let mut chain = Chain::new(settings, rate, channels);
+    let mut writer = Writer::create(format, output_path, rate, channels).map_err(|e| e.to_string())?;
+
+    let mut resample_buffer: Vec<f32> = Vec::new();
+    let mut decoded_frames: u64 = 0;
+    let mut reported = 0.0;
+    while let Some(mut samples) = dec.decode_next().map_err(|e| e.to_string())? {
+        if dec.take_spec_change() {
+            return Err("音源中途改变了采样率或声道数，无法导出".to_string());
+        }
+        decoded_frames += (samples.len() / channels) as u64;
+
+        match resampler.as_mut() {
+            Some(rs) => {
+                resample_buffer.extend_from_slice(&samples);
+                while resample_buffer.len() >= rs.input_frames_needed() * channels {
+                    let chunk: Vec<f32> = resample_buffer.drain(..rs.input_frames_needed() * channels).collect();
+                    let mut resampled = rs.process(&chunk)?;
+                    chain.process(&mut resampled);
+                    writer.write(&resampled).map_err(|e| e.to_string())?;
+                }
+            }
+            None => {
+                chain.process(&mut samples);
+                writer.write(&samples).map_err(|e| e.to_string())?;
+            }
+        }
+
+        let progress = (decoded_frames as f64 / total_frames).min(1.0);
+        if progress - reported >= 0.01 {
+            reported = progress;
+            on_progress(progress);
+        }
+    }
+
+    // Pad the resampler's last chunk with silence, and cut the output back to length
+    if let Some(rs) = resampler.as_mut() {
+        writer.limit_frames(decoded_frames * rate as u64 / source_rate as u64);
+        if !resample_buffer.is_empty() {
+            resample_buffer.resize(rs.input_frames_needed() * channels, 0.0);
+            let mut resampled = rs.process(&resample_buffer)?;
+            chain.process(&mut resampled);
+            writer.write(&resampled).map_err(|e| e.to_string())?;
+        }
+    }
+    let tail = chain.flush();
+    writer.write(&tail).map_err(|e| e.to_string())?;
+
+    writer.finish().map_err(|e| e.to_string())?;
+    on_progress(1.0);
+    Ok(())
+}
+
+/// The DSP stages of the playback chain, in playback order
+struct Chain {
+    vocal: VocalReducer,
+    eq: Equalizer,
+    night: NightMode,
+    limiter: Limiter,
+    limit: bool,
+    channels: usize,
+    /// Leading frames of limiter delay still to drop
+    skip: usize,
+}
+
+impl Chain {
+    fn new(settings: &DspSettings, sample_rate: u32, channels: usize) -> Self {
+        let mut vocal = VocalReducer::new(sample_rate, channels);
+        vocal.set_enabled(settings.vocal_reduction.is_some());
+        vocal.set_strength(settings.vocal_reduction.unwrap_or_default());
+        vocal.set_band_limited(settings.vocal_band_limited);
+        let mut eq = Equalizer::new(sample_rate, channels);
+        eq.set_enabled(settings.eq_enabled);
+        eq.set_layout(settings.eq_layout);
+        eq.set_gains(&settings.eq_gains);
+        let mut night = NightMode::new(sample_rate, channels);
+        night.set_enabled(settings.night_mode.is_some());
+        if let Some(preset) = settings.night_mode {
+            night.set_preset(preset);
+        }
+        let limiter = Limiter::new(sample_rate, channels);
+        Self {
+            // Same rule as playback, without a normalization gain
+            limit: (settings.limiter && (eq.is_enabled() || vocal.is_enabled())) || night.is_enabled(),
+            skip: limiter.latency(),
+            vocal,
+            eq,
+            night,
+            limiter,
+            channels,
+        }
+    }
+
+    fn process(&mut self, samples: &mut Vec<f32>) {
+        self.vocal.process(samples);
+        self.eq.process(samples);
+        self.night.process(samples);
+        self.limiter.process(samples, self.limit);
+        let skip = self.skip.min(samples.len() / self.channels);
+        samples.drain(..skip * self.channels);
+        self.skip -= skip;
+    }
+
+    /// Push the limiter's look-ahead out with silence.
+    fn flush(&mut self) -> Vec<f32> {
+        let mut tail = vec![0.0; self.limiter.latency() * self.channels];
+        self.limiter.process(&mut tail, self.limit);
+        tail
+    }
+}
+
+enum Encoder {
+    Wav(WavWriter),
+    Flac(FlacWriter),
+}
+
+/// Output file of an export, optionally capped at a frame count
+struct Writer {
+    encoder: Encoder,
+    channels: usize,
+    written: u64,
+    limit: Option<u64>,
+}
+
+impl Writer {
+    fn create(format: ExportFormat, path: &Path, sample_rate: u32, channels: usize) -> io::Result<Self> {
+        let encoder = match format {
+            ExportFormat::Wav => Encoder::Wav(WavWriter::create(path, sample_rate, channels)?),
+            ExportFormat::Flac => Encoder::Flac(FlacWriter::create(path, sample_rate, channels, EXPORT_BITS)?),
+        };
+        Ok(Self { encoder, channels, written: 0, limit: None })
+    }
+
+    /// Drop whatever is written beyond `frames` frames in total.
+    fn limit_frames(&mut self, frames: u64) {
+        self.limit = Some(frames);
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut frames = (samples.len() / self.channels) as u64;
+        if let Some(limit) = self.limit {
+            frames = frames.min(limit.saturating_sub(self.written));
+        }
+        self.written += frames;
+        let quantized: Vec<i32> = samples[..frames as usize * self.channels].iter().map(|&s| to_pcm(s)).collect();
+        match &mut self.encoder {
+            Encoder::Wav(wav) => wav.write(&quantized),
+            Encoder::Flac(flac) => flac.write(&quantized),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Wav(wav) => wav.finish(),
+            Encoder::Flac(flac) => flac.finish(),
+        }
+    }
+}
+
+/// Float sample to a signed integer at [`EXPORT_BITS`]
+fn to_pcm(sample: f32) -> i32 {
+    let scale = (1i32 << (EXPORT_BITS - 1)) as f32;
+    (sample * scale).round().clamp(-scale, scale - 1.0) as i32
+}
+
+/// 24-bit PCM WAV; the RIFF sizes are filled in on `finish`.
+struct WavWriter {
+    out: BufWriter<File>,
+    channels: usize,
+    frames: u64,
+}
+
+impl WavWriter {
+    fn create(path: &Path, sample_rate: u32, channels: usize) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let bytes_per_sample = EXPORT_BITS / 8;
+        let block_align = channels as u32 * bytes_per_sample;
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&(channels as u16).to_le_bytes())?;
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * block_align).to_le_bytes())?;
+        out.write_all(&(block_align as u16).to_le_bytes())?;
+        out.write_all(&(EXPORT_BITS as u16).to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { out, channels, frames: 0 })
+    }
+
+    fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        for &s in samples {
+            self.out.write_all(&s.to_le_bytes()[..3])?;
+        }
+        self.frames += (samples.len() / self.channels) as u64;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let data_bytes = self.frames * self.channels as u64 * (EXPORT_BITS / 8) as u64;
+        if data_bytes + 36 > u32::MAX as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "WAV 文件不能超过 4 GB，请导出为 FLAC"));
+        }
+        if data_bytes % 2 == 1 {
+            self.out.write_all(&[0])?;
+        }
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(36 + data_bytes as u32 + (data_bytes % 2) as u32).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&(data_bytes as u32).to_le_bytes())?;
+        self.out.flush()
+    }
+}
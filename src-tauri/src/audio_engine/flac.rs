@@ -0,0 +1,318 @@
+//! Minimal FLAC encoder for exports: fixed-size blocks, independent channels,
+//! fixed predictors (order 0-4) with a single Rice partition, and verbatim
+//! subframes where prediction doesn't pay off.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const BLOCK_SIZE: usize = 4096;
+/// Largest Rice parameter with the 4-bit encoding; 15 is the escape code
+const MAX_RICE_PARAM: u32 = 14;
+/// File offset of the STREAMINFO frame sizes, rewritten once encoding is done
+const STREAMINFO_FRAME_SIZES: u64 = 8 + 4;
+
+pub struct FlacWriter {
+    out: BufWriter<File>,
+    channels: usize,
+    bits: u32,
+    sample_rate: u32,
+    /// Interleaved samples not yet filling a block
+    pending: Vec<i32>,
+    frame_number: u64,
+    total_frames: u64,
+    min_frame_bytes: usize,
+    max_frame_bytes: usize,
+}
+
+impl FlacWriter {
+    pub fn create(path: &Path, sample_rate: u32, channels: usize, bits: u32) -> io::Result<Self> {
+        if !(1..=8).contains(&channels) || !(4..=24).contains(&bits) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Unsupported FLAC layout"));
+        }
+        let mut writer = Self {
+            out: BufWriter::new(File::create(path)?),
+            channels,
+            bits,
+            sample_rate,
+            pending: Vec::with_capacity(BLOCK_SIZE * channels),
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_bytes: usize::MAX,
+            max_frame_bytes: 0,
+        };
+        writer.out.write_all(b"fLaC")?;
+        let streaminfo = writer.streaminfo();
+        // Last metadata block, type 0 (STREAMINFO)
+        writer.out.write_all(&[0x80, 0, 0, streaminfo.len() as u8])?;
+        writer.out.write_all(&streaminfo)?;
+        Ok(writer)
+    }
+
+    /// Append interleaved samples at the writer's bit depth.
+    pub fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        self.pending.extend_from_slice(samples);
+        let block_len = BLOCK_SIZE * self.channels;
+        let mut start = 0;
+        while self.pending.len() - start >= block_len {
+            let block: Vec<i32> = self.pending[start..start + block_len].to_vec();
+            self.write_frame(&block)?;
+            start += block_len;
+        }
+        self.pending.drain(..start);
+        Ok(())
+    }
+
+    /// Encode the last partial block and fill in the stream totals.
+    pub fn finish(mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        let whole = pending.len() - pending.len() % self.channels;
+        if whole > 0 {
+            self.write_frame(&pending[..whole])?;
+        }
+        if self.min_frame_bytes == usize::MAX {
+            self.min_frame_bytes = 0;
+        }
+        let streaminfo = self.streaminfo();
+        self.out.seek(SeekFrom::Start(STREAMINFO_FRAME_SIZES))?;
+        self.out.write_all(&streaminfo[4..18])?;
+        self.out.flush()
+    }
+
+    fn streaminfo(&self) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.write(BLOCK_SIZE as u64, 16);
+        w.write(BLOCK_SIZE as u64, 16);
+        w.write(self.min_frame_bytes.min(0xFF_FFFF) as u64, 24);
+        w.write(self.max_frame_bytes.min(0xFF_FFFF) as u64, 24);
+        w.write(self.sample_rate as u64, 20);
+        w.write(self.channels as u64 - 1, 3);
+        w.write(self.bits as u64 - 1, 5);
+        w.write(self.total_frames >> 32, 4);
+        w.write(self.total_frames & 0xFFFF_FFFF, 32);
+        // MD5 of the audio left unset (allowed by the format)
+        for _ in 0..4 {
+            w.write(0, 32);
+        }
+        w.into_bytes()
+    }
+
+    fn write_frame(&mut self, interleaved: &[i32]) -> io::Result<()> {
+        let frames = interleaved.len() / self.channels;
+        let mut w = BitWriter::default();
+
+        // Header: sync code, fixed block size, block size and rate from the end of
+        // the header / STREAMINFO
+        w.write(0xFFF8, 16);
+        w.write(0b0111, 4);
+        w.write(0b0000, 4);
+        w.write(self.channels as u64 - 1, 4);
+        w.write(sample_size_code(self.bits), 3);
+        w.write(0, 1);
+        write_utf8_number(&mut w, self.frame_number);
+        w.write(frames as u64 - 1, 16);
+        let crc = crc8(w.bytes());
+        w.write(crc as u64, 8);
+
+        let mut channel = Vec::with_capacity(frames);
+        for ch in 0..self.channels {
+            channel.clear();
+            channel.extend(interleaved.iter().skip(ch).step_by(self.channels).map(|&s| s as i64));
+            write_subframe(&mut w, &channel, self.bits);
+        }
+
+        w.align();
+        let crc = crc16(w.bytes());
+        w.write(crc as u64, 16);
+
+        let bytes = w.into_bytes();
+        self.min_frame_bytes = self.min_frame_bytes.min(bytes.len());
+        self.max_frame_bytes = self.max_frame_bytes.max(bytes.len());
+        self.out.write_all(&bytes)?;
+        self.frame_number += 1;
+        self.total_frames += frames as u64;
+        Ok(())
+    }
+}
+
+fn sample_size_code(bits: u32) -> u64 {
+    match bits {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        // Taken from STREAMINFO
+        _ => 0b000,
+    }
+}
+
+/// Frame number in FLAC's UTF-8-like variable length coding
+fn write_utf8_number(w: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        w.write(value, 8);
+        return;
+    }
+    let continuation = match value {
+        0..=0x7FF => 1,
+        0x800..=0xFFFF => 2,
+        0x1_0000..=0x1F_FFFF => 3,
+        0x20_0000..=0x3FF_FFFF => 4,
+        _ => 5,
+    };
+    let marker = (0xFF00u64 >> (continuation + 1)) & 0xFF;
+    w.write(marker | (value >> (6 * continuation)), 8);
+    for i in (0..continuation).rev() {
+        w.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+    }
+}
+
+/// Encode one channel of a block as the cheapest of constant, fixed-predictor
+/// and verbatim subframes.
+fn write_subframe(w: &mut BitWriter, samples: &[i64], bits: u32) {
+    if samples.iter().all(|&s| s == samples[0]) {
+        w.write(0b0000_0000, 8);
+        w.write_signed(samples[0], bits);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * bits as u64;
+    let mut best: Option<(u64, usize, Vec<i64>, u32)> = None;
+    for order in 0..=4.min(samples.len() - 1) {
+        let residual = fixed_residual(samples, order);
+        let (param, cost) = best_rice_param(&residual);
+        let total = order as u64 * bits as u64 + 2 + 4 + 4 + cost;
+        if best.as_ref().is_none_or(|(best_total, ..)| total < *best_total) {
+            best = Some((total, order, residual, param));
+        }
+    }
+
+    match best {
+        Some((total, order, residual, param)) if total < verbatim_bits => {
+            w.write(0b0001_0000 | ((order as u64) << 1), 8);
+            for &s in &samples[..order] {
+                w.write_signed(s, bits);
+            }
+            // Rice coding with 4-bit parameters, partition order 0
+            w.write(0b00, 2);
+            w.write(0, 4);
+            w.write(param as u64, 4);
+            for &r in &residual {
+                let folded = if r >= 0 { (r as u64) << 1 } else { (((-r) as u64) << 1) - 1 };
+                w.write_unary(folded >> param);
+                w.write(folded & ((1u64 << param) - 1), param);
+            }
+        }
+        _ => {
+            w.write(0b0000_0010, 8);
+            for &s in samples {
+                w.write_signed(s, bits);
+            }
+        }
+    }
+}
+
+/// Residual of FLAC's fixed polynomial predictor of `order`
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |back: usize| samples[i - back];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+/// Rice parameter with the fewest bits for `residual`, and that bit count
+fn best_rice_param(residual: &[i64]) -> (u32, u64) {
+    let folded: Vec<u64> = residual
+        .iter()
+        .map(|&r| if r >= 0 { (r as u64) << 1 } else { (((-r) as u64) << 1) - 1 })
+        .collect();
+    (0..=MAX_RICE_PARAM)
+        .map(|param| {
+            let cost = folded.iter().map(|&u| (u >> param) + 1 + param as u64).sum();
+            (param, cost)
+        })
+        .min_by_key(|&(_, cost)| cost)
+        .unwrap_or((0, 0))
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    acc_bits: u32,
+}
+
+impl BitWriter {
+    /// Write the low `bits` bits of `value`, most significant first (at most 32).
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1u64 << bits) - 1));
+        self.acc_bits += bits;
+        while self.acc_bits >= 8 {
+            self.acc_bits -= 8;
+            self.bytes.push((self.acc >> self.acc_bits) as u8);
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    /// `zeros` zero bits followed by a one
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros as u32 + 1);
+    }
+
+    /// Pad with zero bits to a byte boundary.
+    fn align(&mut self) {
+        if self.acc_bits > 0 {
+            self.write(0, 8 - self.acc_bits);
+        }
+    }
+
+    /// Complete bytes written so far
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+/// CRC-8, polynomial x^8 + x^2 + x + 1
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// CRC-16, polynomial x^16 + x^15 + x^2 + 1
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
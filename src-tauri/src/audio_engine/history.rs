@@ -0,0 +1,59 @@
+//! Songs played before the current one, so "previous" goes back to what was
+//! actually heard (also under shuffle) instead of the queue neighbour.
+
+/// Most songs kept on the stack
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Default)]
+pub struct PlayHistory {
+    /// Song the engine is playing (or last played)
+    current: Option<String>,
+    /// Earlier songs, most recent last
+    previous: Vec<String>,
+    /// Song preloaded for the gapless handoff
+    next_up: Option<String>,
+}
+
+impl PlayHistory {
+    /// A song was started directly; restarting the current song doesn't add to the history.
+    pub fn start(&mut self, song_id: Option<String>) {
+        self.next_up = None;
+        if song_id.is_some() && song_id == self.current {
+            return;
+        }
+        if let Some(current) = self.current.take() {
+            self.previous.push(current);
+            let excess = self.previous.len().saturating_sub(MAX_HISTORY);
+            self.previous.drain(..excess);
+        }
+        self.current = song_id;
+    }
+
+    /// Song that becomes current at the next gapless handoff.
+    pub fn set_next_up(&mut self, song_id: Option<String>) {
+        self.next_up = song_id;
+    }
+
+    /// The engine handed off to the preloaded song.
+    pub fn advance(&mut self) {
+        let next = self.next_up.take();
+        self.start(next);
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn next_up(&self) -> Option<&str> {
+        self.next_up.as_deref()
+    }
+
+    /// Step back: the previous song becomes current and is returned, the
+    /// current one is dropped from the history.
+    pub fn back(&mut self) -> Option<String> {
+        let previous = self.previous.pop()?;
+        self.current = Some(previous.clone());
+        self.next_up = None;
+        Some(previous)
+    }
+}
@@ -1,11 +1,113 @@
 use std::io::{self, Read, Seek, SeekFrom};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
 use symphonia::core::io::MediaSource;
 
+use super::error::{AudioError, AudioErrorCode};
+use super::stream_cache::{self, CacheWriter};
+use super::waveform;
+
 const PRE_BUFFER: usize = 128 * 1024; // 128 KB pre-buffer before playback starts
 const READ_CHUNK: usize = 64 * 1024; // 64 KB per network read
 
+/// No new bytes for this long counts as a stalled connection
+const STALL_TIMEOUT: Duration = Duration::from_secs(8);
+/// A read blocked this long is reported as buffering
+const BUFFERING_DELAY: Duration = Duration::from_millis(300);
+/// Reconnects in a row without reading a byte before giving up
+const MAX_RECONNECTS: u32 = 5;
+/// Wait before the first reconnect; doubled for each further attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Extra request headers of a stream as (name, value), e.g. `Authorization`
+pub type HttpHeaders = Vec<(String, String)>;
+
+/// Builds a fresh URL for a stream URL whose credentials expired
+/// (re-authenticating with the server if needed). Returns None if it can't.
+pub type UrlRefresher = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+static URL_REFRESHER: OnceLock<UrlRefresher> = OnceLock::new();
+
+/// Register the URL refresher used when a server answers 401/403.
+pub fn set_url_refresher(refresher: UrlRefresher) {
+    let _ = URL_REFRESHER.set(refresher);
+}
+
+/// `audio:buffering` payload: playback is waiting on the network
+#[derive(Clone, Serialize)]
+pub struct BufferingPayload {
+    pub buffering: bool,
+}
+
+/// Notified when playback starts and stops waiting on the network.
+pub type BufferingListener = Box<dyn Fn(BufferingPayload) + Send + Sync>;
+
+static BUFFERING_LISTENER: OnceLock<BufferingListener> = OnceLock::new();
+
+/// Register the listener told about buffering stalls.
+pub fn set_buffering_listener(listener: BufferingListener) {
+    let _ = BUFFERING_LISTENER.set(listener);
+}
+
+/// GET request for `url` carrying the stream's headers.
+fn get(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    headers: &[(String, String)],
+) -> reqwest::blocking::RequestBuilder {
+    headers
+        .iter()
+        .fold(client.get(url), |request, (name, value)| request.header(name, value))
+}
+
+fn is_auth_error(status: u16) -> bool {
+    status == 401 || status == 403
+}
+
+/// Ask the registered refresher for a new URL.
+fn refresh_url(url: &str) -> Option<String> {
+    let fresh = URL_REFRESHER.get()?(url)?;
+    eprintln!("Stream credentials expired, retrying with a refreshed URL");
+    Some(fresh)
+}
+
+/// Block until `ready` holds or the download ends. Returns the guard and whether
+/// the wait gave up because no bytes arrived for [`STALL_TIMEOUT`].
+/// `on_slow` runs once if the wait exceeds [`BUFFERING_DELAY`].
+fn wait_for_data<'a>(
+    shared: &'a (Mutex<StreamBuffer>, Condvar),
+    ready: impl Fn(&StreamBuffer) -> bool,
+    mut on_slow: impl FnMut(),
+) -> (MutexGuard<'a, StreamBuffer>, bool) {
+    let (lock, cvar) = shared;
+    let mut buf = lock.lock().unwrap();
+    let started = Instant::now();
+    let mut last_progress = started;
+    let mut received = buf.data.len();
+    let mut slow = false;
+    while !ready(&buf) && !buf.done && buf.error.is_none() {
+        buf = cvar.wait_timeout(buf, BUFFERING_DELAY).unwrap().0;
+        if buf.data.len() != received {
+            received = buf.data.len();
+            last_progress = Instant::now();
+        }
+        if !slow && started.elapsed() >= BUFFERING_DELAY {
+            slow = true;
+            on_slow();
+        }
+        if last_progress.elapsed() >= STALL_TIMEOUT {
+            return (buf, true);
+        }
+    }
+    (buf, false)
+}
+
+fn stall_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "Stream stalled: no data received")
+}
+
 /// Shared state between the download thread and the reader.
 struct StreamBuffer {
     /// All data downloaded from the current segment.
@@ -14,8 +116,8 @@ struct StreamBuffer {
     data_start: u64,
     /// True when the download thread has finished (EOF or error).
     done: bool,
-    /// If the download thread hit an error.
-    error: Option<String>,
+    /// If the download thread hit an error (kind kept so timeouts stay recognizable).
+    error: Option<(io::ErrorKind, String)>,
     /// Set to true to signal the download thread to stop.
     abort: bool,
 }
@@ -27,6 +129,8 @@ struct StreamBuffer {
 /// (unless the buffer is empty, which only happens at the very start or after seek).
 pub struct HttpStreamSource {
     url: String,
+    /// Sent with every request, including reconnects and seeks.
+    headers: HttpHeaders,
     client: reqwest::blocking::Client,
     /// Shared buffer written by download thread, read by audio thread.
     buf: Arc<(Mutex<StreamBuffer>, Condvar)>,
@@ -34,25 +138,50 @@ pub struct HttpStreamSource {
     position: u64,
     /// Total content length, 0 if unknown.
     content_length: u64,
+    /// Reconnects since the last byte was read; bounds the retries of a dead stream.
+    reconnects: u32,
+    /// `audio:buffering` was reported and not yet cleared.
+    buffering: bool,
     /// Handle to the background download thread.
     _download_thread: Option<thread::JoinHandle<()>>,
 }
 
 impl HttpStreamSource {
-    pub fn open(url: &str) -> Result<Self, String> {
+    pub fn open(url: &str, headers: &[(String, String)]) -> Result<Self, AudioError> {
         let client = reqwest::blocking::Client::builder()
             .connect_timeout(std::time::Duration::from_secs(10))
             .build()
-            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+            .map_err(|e| AudioError::from_http(&e, "Failed to create HTTP client"))?;
+
+        // Requests are keyed by the URL the player asked for, before any refresh
+        let waveform_song = waveform::take_request(url);
+        let cache_song = stream_cache::take_request(url);
 
-        let resp = client
-            .get(url)
+        let mut url = url.to_string();
+        let mut resp = get(&client, &url, headers)
             .send()
-            .map_err(|e| format!("HTTP request failed: {}", e))?;
+            .map_err(|e| AudioError::from_http(&e, "HTTP request failed"))?;
+
+        if is_auth_error(resp.status().as_u16()) {
+            if let Some(fresh) = refresh_url(&url) {
+                url = fresh;
+                resp = get(&client, &url, headers)
+                    .send()
+                    .map_err(|e| AudioError::from_http(&e, "HTTP request failed"))?;
+            }
+        }
 
         let status = resp.status().as_u16();
         if status != 200 && status != 206 {
-            return Err(format!("HTTP request failed with status {}", status));
+            let code = if status == 404 {
+                AudioErrorCode::FileNotFound
+            } else {
+                AudioErrorCode::NetworkError
+            };
+            return Err(AudioError::new(
+                code,
+                format!("HTTP request failed with status {}", status),
+            ));
         }
 
         let content_length = resp
@@ -73,35 +202,58 @@ impl HttpStreamSource {
             Condvar::new(),
         ));
 
-        // Spawn background download thread
-        let handle = Self::spawn_download(shared.clone(), resp);
+        // Spawn background download thread, copying the download to the stream cache
+        let cache = cache_song.and_then(CacheWriter::create);
+        let handle = Self::spawn_download(shared.clone(), resp, cache, content_length);
+
+        // Build the seekbar waveform from the same download as it arrives
+        if let Some(song_id) = waveform_song {
+            let extension = url
+                .split(['?', '#'])
+                .next()
+                .and_then(|path| std::path::Path::new(path).extension())
+                .and_then(|ext| ext.to_str())
+                .map(str::to_string);
+            let reader = BufferReader {
+                buf: shared.clone(),
+                position: 0,
+                content_length,
+            };
+            waveform::spawn_builder(song_id, Box::new(reader), extension);
+        }
 
         // Wait until we have enough data for probing, or download finishes
         {
-            let (lock, cvar) = &*shared;
-            let mut buf = lock.lock().unwrap();
-            while buf.data.len() < PRE_BUFFER && !buf.done && buf.error.is_none() {
-                buf = cvar.wait(buf).unwrap();
+            let (buf, stalled) = wait_for_data(&shared, |buf| buf.data.len() >= PRE_BUFFER, || {});
+            if stalled {
+                return Err(AudioError::from_io(&stall_error(), true, "Download stalled during pre-buffer"));
             }
-            if let Some(ref e) = buf.error {
-                return Err(format!("Download error during pre-buffer: {}", e));
+            if let Some((kind, ref msg)) = buf.error {
+                let err = io::Error::new(kind, msg.clone());
+                return Err(AudioError::from_io(&err, true, "Download error during pre-buffer"));
             }
         }
 
         Ok(Self {
-            url: url.to_string(),
+            url,
+            headers: headers.to_vec(),
             client,
             buf: shared,
             position: 0,
             content_length,
+            reconnects: 0,
+            buffering: false,
             _download_thread: Some(handle),
         })
     }
 
     /// Spawn a thread that reads from `resp` and appends to the shared buffer.
+    /// `cache` receives the same bytes and is kept only if the download completes.
     fn spawn_download(
         shared: Arc<(Mutex<StreamBuffer>, Condvar)>,
         mut resp: reqwest::blocking::Response,
+        mut cache: Option<CacheWriter>,
+        content_length: u64,
     ) -> thread::JoinHandle<()> {
         thread::Builder::new()
             .name("http-stream-dl".into())
@@ -119,12 +271,20 @@ impl HttpStreamSource {
                     match resp.read(&mut tmp) {
                         Ok(0) => {
                             // EOF
-                            let mut buf = shared.0.lock().unwrap();
-                            buf.done = true;
-                            shared.1.notify_all();
+                            {
+                                let mut buf = shared.0.lock().unwrap();
+                                buf.done = true;
+                                shared.1.notify_all();
+                            }
+                            if let Some(cache) = cache.take() {
+                                cache.finish(content_length);
+                            }
                             return;
                         }
                         Ok(n) => {
+                            if let Some(cache) = cache.as_mut() {
+                                cache.write(&tmp[..n]);
+                            }
                             let mut buf = shared.0.lock().unwrap();
                             if buf.abort {
                                 return;
@@ -135,7 +295,7 @@ impl HttpStreamSource {
                         Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
                         Err(e) => {
                             let mut buf = shared.0.lock().unwrap();
-                            buf.error = Some(e.to_string());
+                            buf.error = Some((e.kind(), e.to_string()));
                             buf.done = true;
                             shared.1.notify_all();
                             return;
@@ -146,30 +306,35 @@ impl HttpStreamSource {
             .expect("Failed to spawn download thread")
     }
 
+    fn range_request(&self, offset: u64) -> io::Result<reqwest::blocking::Response> {
+        get(&self.client, &self.url, &self.headers)
+            .header("Range", format!("bytes={}-", offset))
+            .send()
+            .map_err(|e| io::Error::other(format!("Range request failed: {}", e)))
+    }
+
     /// Abort the current download, open a new Range request, restart download thread.
+    /// An expired URL is refreshed once and the request retried.
     fn reopen_from(&mut self, offset: u64) -> io::Result<()> {
         // Signal abort to current download thread
         {
             let mut buf = self.buf.0.lock().unwrap();
             buf.abort = true;
+            self.buf.1.notify_all();
         }
         // Don't join — just let it finish on its own. Create a new shared buffer.
 
-        let resp = self
-            .client
-            .get(&self.url)
-            .header("Range", format!("bytes={}-", offset))
-            .send()
-            .map_err(|e| {
-                io::Error::new(io::ErrorKind::Other, format!("Range request failed: {}", e))
-            })?;
+        let mut resp = self.range_request(offset)?;
+        if is_auth_error(resp.status().as_u16()) {
+            if let Some(fresh) = refresh_url(&self.url) {
+                self.url = fresh;
+                resp = self.range_request(offset)?;
+            }
+        }
 
         let status = resp.status().as_u16();
         if status != 206 && status != 200 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Range request returned status {}", status),
-            ));
+            return Err(io::Error::other(format!("Range request returned status {}", status)));
         }
 
         let actual_start = if status == 200 { 0 } else { offset };
@@ -185,20 +350,49 @@ impl HttpStreamSource {
             Condvar::new(),
         ));
 
-        let handle = Self::spawn_download(shared.clone(), resp);
+        // Resumed downloads don't start at byte 0, so they are never cached
+        let handle = Self::spawn_download(shared.clone(), resp, None, 0);
+
+        self.buf = shared.clone();
+        self._download_thread = Some(handle);
 
         // Wait for pre-buffer
-        {
-            let (lock, cvar) = &*shared;
-            let mut buf = lock.lock().unwrap();
-            while buf.data.len() < PRE_BUFFER && !buf.done && buf.error.is_none() {
-                buf = cvar.wait(buf).unwrap();
+        if wait_for_data(&shared, |buf| buf.data.len() >= PRE_BUFFER, || {}).1 {
+            return Err(stall_error());
+        }
+        Ok(())
+    }
+
+    /// Resume at the current position after the download dropped or stalled,
+    /// backing off exponentially between attempts. Gives up with `cause` after
+    /// [`MAX_RECONNECTS`] attempts that didn't yield a byte.
+    fn reconnect(&mut self, cause: io::Error) -> io::Result<()> {
+        self.set_buffering(true);
+        while self.reconnects < MAX_RECONNECTS {
+            let delay = RECONNECT_BASE_DELAY * 2u32.pow(self.reconnects);
+            self.reconnects += 1;
+            eprintln!(
+                "Stream interrupted at byte {} ({}), reconnecting in {:?}",
+                self.position, cause, delay
+            );
+            thread::sleep(delay);
+            match self.reopen_from(self.position) {
+                Ok(()) => return Ok(()),
+                Err(e) => eprintln!("Stream reconnect failed: {}", e),
             }
         }
+        self.set_buffering(false);
+        Err(cause)
+    }
 
-        self.buf = shared;
-        self._download_thread = Some(handle);
-        Ok(())
+    fn set_buffering(&mut self, buffering: bool) {
+        if self.buffering == buffering {
+            return;
+        }
+        self.buffering = buffering;
+        if let Some(listener) = BUFFERING_LISTENER.get() {
+            listener(BufferingPayload { buffering });
+        }
     }
 }
 
@@ -218,8 +412,7 @@ impl Read for HttpStreamSource {
         }
 
         let shared = self.buf.clone();
-        let (lock, cvar) = &*shared;
-        let mut stream_buf = lock.lock().unwrap();
+        let mut stream_buf = shared.0.lock().unwrap();
 
         let buf_end = stream_buf.data_start + stream_buf.data.len() as u64;
 
@@ -229,16 +422,28 @@ impl Read for HttpStreamSource {
                 return Ok(0); // EOF
             }
             // Wait until data is available at our position
-            while self.position >= stream_buf.data_start + stream_buf.data.len() as u64
-                && !stream_buf.done
-                && stream_buf.error.is_none()
-            {
-                stream_buf = cvar.wait(stream_buf).unwrap();
-            }
-            if let Some(ref e) = stream_buf.error {
-                return Err(io::Error::new(io::ErrorKind::Other, e.clone()));
+            drop(stream_buf);
+            let position = self.position;
+            let (guard, stalled) = wait_for_data(
+                &shared,
+                |buf| position < buf.data_start + buf.data.len() as u64,
+                || self.set_buffering(true),
+            );
+            stream_buf = guard;
+            let failure = match stream_buf.error {
+                Some((kind, ref msg)) => Some(io::Error::new(kind, msg.clone())),
+                None if stalled => Some(stall_error()),
+                None => None,
+            };
+            if let Some(err) = failure {
+                drop(stream_buf);
+                // Connection dropped or stalled mid-track (flaky network, expired
+                // session): resume from the current byte offset before giving up
+                self.reconnect(err)?;
+                return self.read(buf);
             }
             if self.position >= stream_buf.data_start + stream_buf.data.len() as u64 {
+                self.set_buffering(false);
                 return Ok(0); // EOF
             }
         }
@@ -249,7 +454,12 @@ impl Read for HttpStreamSource {
         let to_copy = buf.len().min(available);
         buf[..to_copy].copy_from_slice(&stream_buf.data[buf_offset..buf_offset + to_copy]);
         self.position += to_copy as u64;
+        drop(stream_buf);
 
+        if to_copy > 0 {
+            self.reconnects = 0;
+            self.set_buffering(false);
+        }
         Ok(to_copy)
     }
 }
@@ -306,9 +516,11 @@ impl Seek for HttpStreamSource {
 
 impl Drop for HttpStreamSource {
     fn drop(&mut self) {
+        self.set_buffering(false);
         // Signal download thread to stop
         let mut buf = self.buf.0.lock().unwrap();
         buf.abort = true;
+        self.buf.1.notify_all();
     }
 }
 
@@ -325,3 +537,70 @@ impl MediaSource for HttpStreamSource {
         }
     }
 }
+
+/// Second reader over the download buffer of a stream, used for the waveform.
+/// It never issues requests of its own: it waits for the download, and fails
+/// once the stream is closed or restarted elsewhere (seek) before finishing.
+struct BufferReader {
+    buf: Arc<(Mutex<StreamBuffer>, Condvar)>,
+    position: u64,
+    content_length: u64,
+}
+
+impl Read for BufferReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let (lock, cvar) = &*self.buf;
+        let mut buf = lock.lock().unwrap();
+        loop {
+            let offset = self.position.saturating_sub(buf.data_start) as usize;
+            if offset < buf.data.len() {
+                let to_copy = out.len().min(buf.data.len() - offset);
+                out[..to_copy].copy_from_slice(&buf.data[offset..offset + to_copy]);
+                self.position += to_copy as u64;
+                return Ok(to_copy);
+            }
+            if let Some((kind, ref msg)) = buf.error {
+                return Err(io::Error::new(kind, msg.clone()));
+            }
+            if buf.done {
+                return Ok(0);
+            }
+            if buf.abort {
+                // Not UnexpectedEof, so a partial waveform isn't taken as complete
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "Stream closed"));
+            }
+            buf = cvar.wait(buf).unwrap();
+        }
+    }
+}
+
+impl Seek for BufferReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) if self.content_length > 0 => self.content_length as i64 + offset,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "Unknown stream length"));
+            }
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if new_pos < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek to negative position",
+            ));
+        }
+        self.position = new_pos as u64;
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for BufferReader {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        (self.content_length > 0).then_some(self.content_length)
+    }
+}
@@ -0,0 +1,79 @@
+/// Reported for silence and channels that received no samples
+const FLOOR_DB: f32 = -96.0;
+
+/// Per-channel peak and RMS meter for VU displays, independent of the FFT path.
+/// Values cover everything pushed since the last `take`.
+pub struct LevelMeter {
+    enabled: bool,
+    peak: Vec<f32>,
+    sum_squares: Vec<f64>,
+    frames: u64,
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            peak: Vec::new(),
+            sum_squares: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset(0);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Feed interleaved samples; a change in channel count starts a new window.
+    pub fn push_samples(&mut self, samples: &[f32], channels: usize) {
+        if !self.enabled || channels == 0 {
+            return;
+        }
+        if self.peak.len() != channels {
+            self.reset(channels);
+        }
+
+        for frame in samples.chunks_exact(channels) {
+            for (ch, &sample) in frame.iter().enumerate() {
+                let level = sample.abs();
+                if level > self.peak[ch] {
+                    self.peak[ch] = level;
+                }
+                self.sum_squares[ch] += (sample as f64) * (sample as f64);
+            }
+        }
+        self.frames += (samples.len() / channels) as u64;
+    }
+
+    /// Peak and RMS per channel in dBFS, then start a new window.
+    pub fn take(&mut self) -> (Vec<f32>, Vec<f32>) {
+        let frames = self.frames.max(1) as f64;
+        let peak = self.peak.iter().map(|&p| to_dbfs(p)).collect();
+        let rms = self
+            .sum_squares
+            .iter()
+            .map(|&sum| to_dbfs((sum / frames).sqrt() as f32))
+            .collect();
+        self.reset(self.peak.len());
+        (peak, rms)
+    }
+
+    fn reset(&mut self, channels: usize) {
+        self.peak = vec![0.0; channels];
+        self.sum_squares = vec![0.0; channels];
+        self.frames = 0;
+    }
+}
+
+fn to_dbfs(level: f32) -> f32 {
+    if level > 0.0 {
+        (20.0 * level.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
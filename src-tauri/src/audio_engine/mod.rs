@@ -1,10 +1,20 @@
 pub mod decoder;
+pub mod dsd;
 pub mod dsp;
 pub mod engine;
+pub mod error;
+pub mod export;
 pub mod fft;
+pub mod flac;
+pub mod history;
 pub mod http_source;
+pub mod levels;
 pub mod output;
+pub mod queue;
 pub mod resampler;
+pub mod sleep_inhibit;
+pub mod stream_cache;
+pub mod waveform;
 
 use engine::AudioEngine;
 use std::sync::Mutex;
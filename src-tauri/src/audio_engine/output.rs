@@ -1,75 +1,209 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream, StreamConfig};
-use ringbuf::traits::{Consumer, Split};
+use cpal::{
+    BufferSize, SampleFormat, Stream, StreamConfig, SupportedBufferSize, SupportedStreamConfigRange,
+};
+use ringbuf::traits::{Consumer, Producer, Split};
 use ringbuf::{HeapCons, HeapProd, HeapRb};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
+use super::error::{AudioError, AudioErrorCode};
+
+/// Smallest ring buffer (in samples) the decode loop can safely feed.
+const MIN_RING_SAMPLES: usize = 16384;
+
+/// User-tunable output buffering: lower latency vs. underrun resistance.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputOptions {
+    /// cpal callback buffer size in frames (None = device default).
+    pub buffer_frames: Option<u32>,
+    /// Ring buffer depth in milliseconds; EQ/volume changes are heard after this delay.
+    pub ring_buffer_ms: u32,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            buffer_frames: None,
+            ring_buffer_ms: 2000,
+        }
+    }
+}
+
+/// Sample rate the output stream is opened at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputRate {
+    /// The rate of the track that opened the stream; tracks handed off gaplessly are resampled to it.
+    #[default]
+    Auto,
+    /// Always the source rate: the stream is reopened when a gapless handoff changes the rate.
+    Source,
+    /// Always this rate; other sources are resampled.
+    Fixed(u32),
+}
+
+impl OutputRate {
+    /// Rate to open the stream at for a source of `source_rate`.
+    pub fn for_source(self, source_rate: u32) -> u32 {
+        match self {
+            OutputRate::Fixed(rate) => rate,
+            OutputRate::Auto | OutputRate::Source => source_rate,
+        }
+    }
+}
+
+/// Audio server the output stream is opened on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OutputBackend {
+    /// Platform default host (WASAPI / CoreAudio / ALSA).
+    #[default]
+    System,
+    /// JACK client; PipeWire serves it through its JACK compatibility layer.
+    /// `ports` maps output channel i to `ports[i]`; empty = auto-connect to system playback.
+    Jack { client_name: String, ports: Vec<String> },
+}
+
+impl OutputBackend {
+    /// Whether this build can open the backend at all.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            OutputBackend::System => true,
+            OutputBackend::Jack { .. } => cfg!(all(target_os = "linux", feature = "jack")),
+        }
+    }
+}
+
 pub struct AudioOutput {
     _stream: Stream,
     pub producer: HeapProd<f32>,
     pub config: StreamConfig,
+    pub device_name: String,
+    /// Native bit depth of integer devices (from their default format); `None` for
+    /// float devices. The stream itself is always f32, converted by the system.
+    pub device_bits: Option<u32>,
     playing: Arc<AtomicBool>,
     flushing: Arc<AtomicBool>,
+    /// Hard mute applied in the callback, so it takes effect without waiting for the ring buffer.
+    muted: Arc<AtomicBool>,
+    /// Frames actually handed to the device since the last flush (written by the callback).
+    frames_played: Arc<AtomicU64>,
+    /// Frames pushed into the ring buffer since the last flush.
+    frames_written: u64,
+    /// Set by the error callback once the device has gone away (e.g. unplugged).
+    failed: Arc<AtomicBool>,
 }
 
 impl AudioOutput {
-    /// Create a new audio output with a ring buffer.
-    /// The ring buffer size is ~1 second of audio at the given sample rate and channels.
-    pub fn new(sample_rate: u32, channels: u16) -> Result<Self, String> {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or("No audio output device found")?;
+    /// Create a new audio output with a ring buffer on `backend`.
+    /// The ring buffer depth and device buffer size come from `options`.
+    pub fn new(
+        sample_rate: u32,
+        channels: u16,
+        options: OutputOptions,
+        backend: &OutputBackend,
+    ) -> Result<Self, AudioError> {
+        let device = open_device(backend)?;
 
-        let supported_config = device
+        let configs: Vec<SupportedStreamConfigRange> = device
             .supported_output_configs()
-            .map_err(|e| format!("Failed to query output configs: {}", e))?
-            .find(|c| {
-                c.channels() == channels
-                    && c.min_sample_rate().0 <= sample_rate
-                    && c.max_sample_rate().0 >= sample_rate
-                    && c.sample_format() == SampleFormat::F32
-            })
+            .map_err(|e| device_error(format!("Failed to query output configs: {}", e)))?
+            .filter(|c| c.sample_format() == SampleFormat::F32)
+            .collect();
+        let supports_rate = |c: &SupportedStreamConfigRange| {
+            c.min_sample_rate().0 <= sample_rate && c.max_sample_rate().0 >= sample_rate
+        };
+
+        // Exact layout first, else the widest layout below it (the engine downmixes),
+        // else any F32 config
+        let supported_config = configs
+            .iter()
+            .filter(|c| c.channels() == channels)
+            .max_by_key(|c| supports_rate(c))
             .or_else(|| {
-                // Fallback: any config with F32
-                device
-                    .supported_output_configs()
-                    .ok()?
-                    .find(|c| c.sample_format() == SampleFormat::F32)
+                configs
+                    .iter()
+                    .filter(|c| c.channels() < channels)
+                    .max_by_key(|c| (c.channels(), supports_rate(c)))
             })
-            .ok_or("No suitable audio output configuration found")?;
+            .or_else(|| configs.first())
+            .cloned()
+            .ok_or_else(|| device_error("No suitable audio output configuration found"))?;
 
         // Clamp sample rate to the supported range of the chosen config
         let actual_rate = sample_rate
             .max(supported_config.min_sample_rate().0)
             .min(supported_config.max_sample_rate().0);
 
-        let config = supported_config
+        let buffer_range = match supported_config.buffer_size() {
+            SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+            SupportedBufferSize::Unknown => None,
+        };
+
+        let mut config = supported_config
             .with_sample_rate(cpal::SampleRate(actual_rate))
             .config();
 
-        // Ring buffer: ~2 seconds for comfortable headroom
-        let buf_size = (actual_rate as usize) * (config.channels as usize) * 2;
-        let rb = HeapRb::<f32>::new(buf_size.max(4096));
+        // Fixed device buffer only when requested and the device reports a range for it
+        if let (Some(frames), Some((min, max))) = (options.buffer_frames, buffer_range) {
+            config.buffer_size = BufferSize::Fixed(frames.clamp(min, max));
+        }
+
+        let device_bits = device
+            .default_output_config()
+            .ok()
+            .map(|c| c.sample_format())
+            .filter(|format| !format.is_float())
+            .map(|format| format.sample_size() as u32 * 8);
+
+        let ring_ms = options.ring_buffer_ms.clamp(100, 5000) as usize;
+        let buf_size = (actual_rate as usize) * (config.channels as usize) * ring_ms / 1000;
+        let rb = HeapRb::<f32>::new(buf_size.max(MIN_RING_SAMPLES));
         let (producer, consumer) = rb.split();
 
         let playing = Arc::new(AtomicBool::new(true));
         let playing_clone = playing.clone();
         let flushing = Arc::new(AtomicBool::new(false));
         let flushing_clone = flushing.clone();
+        let muted = Arc::new(AtomicBool::new(false));
+        let muted_clone = muted.clone();
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let frames_played_clone = frames_played.clone();
+        let failed = Arc::new(AtomicBool::new(false));
+        let failed_clone = failed.clone();
 
-        let stream = build_output_stream(&device, &config, consumer, playing_clone, flushing_clone)?;
+        let stream = build_output_stream(
+            &device,
+            &config,
+            consumer,
+            playing_clone,
+            flushing_clone,
+            muted_clone,
+            frames_played_clone,
+            failed_clone,
+        )?;
         stream
             .play()
-            .map_err(|e| format!("Failed to start audio stream: {}", e))?;
+            .map_err(|e| device_error(format!("Failed to start audio stream: {}", e)))?;
+
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        if let OutputBackend::Jack { ports, .. } = backend {
+            if let Ok(client_name) = device.name() {
+                jack_output::connect_ports(&client_name, ports);
+            }
+        }
 
         Ok(Self {
             _stream: stream,
             producer,
             config,
+            device_name: device.name().unwrap_or_default(),
+            device_bits,
             playing,
             flushing,
+            muted,
+            frames_played,
+            frames_written: 0,
+            failed,
         })
     }
 
@@ -81,20 +215,76 @@ impl AudioOutput {
         self.playing.store(true, Ordering::Relaxed);
     }
 
+    /// Silence the device output while still consuming (and counting) buffered audio.
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
     /// Signal the output callback to discard all buffered audio.
-    pub fn flush(&self) {
+    /// Also restarts the played/written frame counters.
+    pub fn flush(&mut self) {
         self.flushing.store(true, Ordering::Relaxed);
+        self.frames_written = 0;
+    }
+
+    /// Push interleaved samples into the ring buffer, returning how many were accepted.
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let pushed = self.producer.push_slice(samples);
+        self.frames_written += (pushed / self.config.channels.max(1) as usize) as u64;
+        pushed
+    }
+
+    /// Frames rendered by the device since the last flush.
+    pub fn frames_played(&self) -> u64 {
+        self.frames_played.load(Ordering::Relaxed)
+    }
+
+    /// Frames pushed into the ring buffer since the last flush.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// True once the device is no longer available; the stream will not recover.
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+/// Output device of `backend`.
+fn open_device(backend: &OutputBackend) -> Result<cpal::Device, AudioError> {
+    match backend {
+        OutputBackend::System => cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| device_error("No audio output device found")),
+        #[cfg(all(target_os = "linux", feature = "jack"))]
+        OutputBackend::Jack { client_name, ports } => {
+            // Explicit port mapping replaces cpal's auto-connect to system:playback_*
+            let device = cpal::platform::JackDevice::default_output_device(
+                client_name,
+                ports.is_empty(),
+                false,
+            )
+            .map_err(|e| device_error(format!("Failed to open JACK client: {}", e)))?;
+            Ok(cpal::platform::DeviceInner::Jack(device).into())
+        }
+        #[cfg(not(all(target_os = "linux", feature = "jack")))]
+        OutputBackend::Jack { .. } => Err(device_error("JACK output is not available in this build")),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_output_stream(
     device: &cpal::Device,
     config: &StreamConfig,
     mut consumer: HeapCons<f32>,
     playing: Arc<AtomicBool>,
     flushing: Arc<AtomicBool>,
-) -> Result<Stream, String> {
+    muted: Arc<AtomicBool>,
+    frames_played: Arc<AtomicU64>,
+    failed: Arc<AtomicBool>,
+) -> Result<Stream, AudioError> {
     let mut flush_buf = vec![0.0f32; 4096];
+    let channels = (config.channels as usize).max(1);
     let stream = device
         .build_output_stream(
             config,
@@ -102,6 +292,7 @@ fn build_output_stream(
                 // On flush: drain all buffered data and output silence
                 if flushing.load(Ordering::Relaxed) {
                     while consumer.pop_slice(&mut flush_buf) > 0 {}
+                    frames_played.store(0, Ordering::Relaxed);
                     flushing.store(false, Ordering::Relaxed);
                     data.fill(0.0);
                     return;
@@ -111,15 +302,120 @@ fn build_output_stream(
                     return;
                 }
                 let read = consumer.pop_slice(data);
+                frames_played.fetch_add((read / channels) as u64, Ordering::Relaxed);
+                if muted.load(Ordering::Relaxed) {
+                    data.fill(0.0);
+                    return;
+                }
                 // Fill remaining with silence
                 data[read..].fill(0.0);
             },
-            |err| {
+            move |err| {
                 eprintln!("Audio output error: {}", err);
+                if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                    failed.store(true, Ordering::Relaxed);
+                }
             },
             None,
         )
-        .map_err(|e| format!("Failed to build output stream: {}", e))?;
+        .map_err(|e| device_error(format!("Failed to build output stream: {}", e)))?;
 
     Ok(stream)
 }
+
+/// Host backend and default output device, for diagnostics.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputDeviceInfo {
+    pub host: String,
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Most channels any output config of the device offers (e.g. 6 for 5.1)
+    pub max_channels: u16,
+    pub sample_format: String,
+}
+
+/// Describe the default output device without opening a stream.
+pub fn default_device_info() -> Result<OutputDeviceInfo, AudioError> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .ok_or_else(|| device_error("No audio output device found"))?;
+    let config = device
+        .default_output_config()
+        .map_err(|e| device_error(format!("Failed to query default output config: {}", e)))?;
+
+    let max_channels = device
+        .supported_output_configs()
+        .map(|configs| configs.map(|c| c.channels()).max().unwrap_or(0))
+        .unwrap_or(0)
+        .max(config.channels());
+
+    Ok(OutputDeviceInfo {
+        host: host.id().name().to_string(),
+        device: device.name().unwrap_or_else(|_| "Unknown".to_string()),
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+        max_channels,
+        sample_format: format!("{:?}", config.sample_format()),
+    })
+}
+
+fn device_error(details: impl Into<String>) -> AudioError {
+    AudioError::new(AudioErrorCode::DeviceUnavailable, details)
+}
+
+/// Port wiring for the JACK backend.
+#[cfg(all(target_os = "linux", feature = "jack"))]
+mod jack_output {
+    use jack::{Client, ClientOptions, PortFlags};
+
+    /// Client used only to query and wire ports; connections outlive it.
+    fn patch_client() -> Result<Client, String> {
+        Client::new("bayin_patch", ClientOptions::NO_START_SERVER)
+            .map(|(client, _)| client)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Connect `<client_name>:out_<i>` to `ports[i]`.
+    pub fn connect_ports(client_name: &str, ports: &[String]) {
+        if ports.is_empty() {
+            return;
+        }
+        let client = match patch_client() {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("JACK port mapping skipped: {}", e);
+                return;
+            }
+        };
+        for (i, target) in ports.iter().enumerate() {
+            if target.is_empty() {
+                continue;
+            }
+            let source = format!("{}:out_{}", client_name, i);
+            if let Err(e) = client.connect_ports_by_name(&source, target) {
+                eprintln!("Failed to connect {} -> {}: {}", source, target, e);
+            }
+        }
+    }
+
+    /// Audio input ports other clients expose (where our outputs can go).
+    pub fn playback_ports() -> Result<Vec<String>, String> {
+        let client = patch_client()?;
+        Ok(client.ports(None, Some("32 bit float mono audio"), PortFlags::IS_INPUT))
+    }
+}
+
+/// JACK input ports available as output targets; `None` when JACK isn't built in.
+pub fn jack_playback_ports() -> Result<Option<Vec<String>>, AudioError> {
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    {
+        jack_output::playback_ports().map(Some).map_err(device_error)
+    }
+    #[cfg(not(all(target_os = "linux", feature = "jack")))]
+    {
+        Ok(None)
+    }
+}
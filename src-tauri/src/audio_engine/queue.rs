@@ -0,0 +1,314 @@
+//! Playback queue kept next to the engine. The track after the current one is
+//! preloaded for the gapless handoff, and the engine moves on by itself when a
+//! track ends, so track transitions don't wait for the frontend.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// One queue entry: a playable source and the song it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueTrack {
+    /// Left empty for library songs, whose source is resolved when they play
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub song_id: Option<String>,
+    /// HTTP request headers of the source, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    /// Start over after the last track
+    All,
+    /// Loop the current track; skipping still moves on
+    One,
+}
+
+/// The queue as the frontend sees it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub tracks: Vec<QueueTrack>,
+    /// Index into `tracks` of the current track
+    pub current_index: Option<usize>,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+#[derive(Debug, Default)]
+pub struct PlaybackQueue {
+    tracks: Vec<QueueTrack>,
+    /// Play order as indices into `tracks`; shuffled when shuffle is on
+    order: Vec<usize>,
+    /// Position in `order` of the current track
+    position: Option<usize>,
+    /// Position in `order` of the track preloaded for the gapless handoff
+    preloaded: Option<usize>,
+    shuffle: bool,
+    repeat: RepeatMode,
+    rng: u64,
+}
+
+impl PlaybackQueue {
+    /// Replace the queue; the track at `start` becomes current and is returned.
+    pub fn set(&mut self, tracks: Vec<QueueTrack>, start: usize) -> Option<QueueTrack> {
+        self.tracks = tracks;
+        self.preloaded = None;
+        if self.tracks.is_empty() {
+            self.order.clear();
+            self.position = None;
+            return None;
+        }
+        let start = start.min(self.tracks.len() - 1);
+        self.rebuild_order(start);
+        self.current().cloned()
+    }
+
+    pub fn current(&self) -> Option<&QueueTrack> {
+        self.position.map(|pos| &self.tracks[self.order[pos]])
+    }
+
+    /// Skip forward; repeat-one doesn't hold a manual skip on the same track.
+    pub fn next(&mut self) -> Option<QueueTrack> {
+        let pos = self.following(false)?;
+        self.move_to(pos)
+    }
+
+    /// Step back in play order, wrapping around only with repeat-all.
+    pub fn previous(&mut self) -> Option<QueueTrack> {
+        let pos = self.position?;
+        let previous = match pos.checked_sub(1) {
+            Some(previous) => previous,
+            None if self.repeat == RepeatMode::All => self.order.len() - 1,
+            None => return None,
+        };
+        self.move_to(previous)
+    }
+
+    /// Track that plays when the current one ends on its own; remembered as preloaded.
+    pub fn preload_next(&mut self) -> Option<QueueTrack> {
+        self.preloaded = self.following(true);
+        self.preloaded.map(|pos| self.tracks[self.order[pos]].clone())
+    }
+
+    /// The current track ended without a handoff (nothing was preloaded):
+    /// move to the track that follows it naturally.
+    pub fn finish_current(&mut self) -> Option<QueueTrack> {
+        let pos = self.following(true)?;
+        self.move_to(pos)
+    }
+
+    /// Make the queued track of the same song current, or insert `track` right
+    /// after the current one when the song isn't queued yet.
+    pub fn jump_to(&mut self, track: QueueTrack) -> Option<QueueTrack> {
+        let key = track_key(&track);
+        let index = match self.tracks.iter().position(|queued| track_key(queued) == key) {
+            Some(index) => index,
+            None => {
+                let index = self.position.map_or(self.tracks.len(), |pos| self.order[pos] + 1);
+                self.insert(index, vec![track]);
+                index
+            }
+        };
+        let pos = self.order.iter().position(|&queued| queued == index)?;
+        self.move_to(pos)
+    }
+
+    /// Replace the tracks without interrupting playback. The current track stays
+    /// current if it's still in the list; under shuffle, tracks kept from the old
+    /// list keep their play order and new ones are shuffled in after them.
+    pub fn update(&mut self, tracks: Vec<QueueTrack>) {
+        let played: Vec<String> = self.order.iter().map(|&track| track_key(&self.tracks[track])).collect();
+        let current = self.current().map(track_key);
+        let index_of: HashMap<String, usize> =
+            tracks.iter().enumerate().map(|(index, track)| (track_key(track), index)).collect();
+        self.tracks = tracks;
+        self.preloaded = None;
+
+        if self.shuffle {
+            let mut order: Vec<usize> = played.iter().filter_map(|key| index_of.get(key).copied()).collect();
+            let kept: HashSet<usize> = order.iter().copied().collect();
+            let mut added: Vec<usize> = (0..self.tracks.len()).filter(|track| !kept.contains(track)).collect();
+            self.shuffle_tracks(&mut added);
+            order.extend(added);
+            self.order = order;
+        } else {
+            self.order = (0..self.tracks.len()).collect();
+        }
+        self.position = current
+            .and_then(|key| index_of.get(&key).copied())
+            .and_then(|index| self.order.iter().position(|&track| track == index));
+    }
+
+    /// The preload was replaced or dropped outside the queue.
+    pub fn clear_preloaded(&mut self) {
+        self.preloaded = None;
+    }
+
+    /// The engine handed off to the preloaded track. Returns false if nothing
+    /// from this queue was preloaded.
+    pub fn advance(&mut self) -> bool {
+        match self.preloaded.take() {
+            Some(pos) => {
+                self.position = Some(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert tracks before `index` (clamped to the end). Under shuffle, tracks
+    /// inserted right after the current one also play next; others are appended.
+    pub fn insert(&mut self, index: usize, tracks: Vec<QueueTrack>) {
+        let index = index.min(self.tracks.len());
+        let count = tracks.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.position.map(|pos| self.order[pos]);
+        self.tracks.splice(index..index, tracks);
+        self.preloaded = None;
+
+        if !self.shuffle {
+            self.order = (0..self.tracks.len()).collect();
+            self.position = current.map(|track| if track >= index { track + count } else { track });
+            return;
+        }
+
+        for slot in self.order.iter_mut() {
+            if *slot >= index {
+                *slot += count;
+            }
+        }
+        let play_next = current.is_some_and(|track| index == track + 1);
+        let at = match self.position {
+            Some(pos) if play_next => pos + 1,
+            _ => self.order.len(),
+        };
+        self.order.splice(at..at, index..index + count);
+    }
+
+    /// Remove the track at `index`. The current track can't be removed.
+    pub fn remove(&mut self, index: usize) -> Result<QueueTrack, String> {
+        if index >= self.tracks.len() {
+            return Err(format!("队列中没有第 {} 首", index + 1));
+        }
+        let order_pos = self.order.iter().position(|&track| track == index).unwrap_or_default();
+        if self.position == Some(order_pos) {
+            return Err("不能移除正在播放的歌曲".to_string());
+        }
+
+        let removed = self.tracks.remove(index);
+        self.order.remove(order_pos);
+        for slot in self.order.iter_mut() {
+            if *slot > index {
+                *slot -= 1;
+            }
+        }
+        if let Some(pos) = self.position.filter(|&pos| pos > order_pos) {
+            self.position = Some(pos - 1);
+        }
+        self.preloaded = None;
+        Ok(removed)
+    }
+
+    /// Turn shuffle on or off; the current track stays current either way.
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        if enabled == self.shuffle {
+            return;
+        }
+        self.shuffle = enabled;
+        self.preloaded = None;
+        if let Some(current) = self.position.map(|pos| self.order[pos]) {
+            self.rebuild_order(current);
+        } else if !self.tracks.is_empty() {
+            self.rebuild_order(0);
+            self.position = None;
+        }
+    }
+
+    pub fn set_repeat(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+        self.preloaded = None;
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            tracks: self.tracks.clone(),
+            current_index: self.position.map(|pos| self.order[pos]),
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+        }
+    }
+
+    /// Order position after the current one. `natural` is an end of track,
+    /// where repeat-one plays the same track again.
+    fn following(&self, natural: bool) -> Option<usize> {
+        let pos = self.position?;
+        if natural && self.repeat == RepeatMode::One {
+            return Some(pos);
+        }
+        if pos + 1 < self.order.len() {
+            Some(pos + 1)
+        } else if self.repeat != RepeatMode::Off {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn move_to(&mut self, pos: usize) -> Option<QueueTrack> {
+        self.position = Some(pos);
+        self.preloaded = None;
+        self.current().cloned()
+    }
+
+    /// Play order starting from track `current`: the queue order, or `current`
+    /// followed by the other tracks shuffled.
+    fn rebuild_order(&mut self, current: usize) {
+        if !self.shuffle {
+            self.order = (0..self.tracks.len()).collect();
+            self.position = Some(current);
+            return;
+        }
+
+        let mut rest: Vec<usize> = (0..self.tracks.len()).filter(|&track| track != current).collect();
+        self.shuffle_tracks(&mut rest);
+        self.order = std::iter::once(current).chain(rest).collect();
+        self.position = Some(0);
+    }
+
+    /// Fisher-Yates
+    fn shuffle_tracks(&mut self, tracks: &mut [usize]) {
+        for i in (1..tracks.len()).rev() {
+            let j = (self.next_random() % (i as u64 + 1)) as usize;
+            tracks.swap(i, j);
+        }
+    }
+
+    /// xorshift64, seeded from the clock on first use
+    fn next_random(&mut self) -> u64 {
+        if self.rng == 0 {
+            self.rng = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0x2545_f491_4f6c_dd1d)
+                | 1;
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
+
+/// Identity of a track across queue updates
+fn track_key(track: &QueueTrack) -> String {
+    track.song_id.clone().unwrap_or_else(|| track.source.clone())
+}
@@ -1,26 +1,64 @@
-use rubato::{FftFixedInOut, Resampler};
+use rubato::{
+    calculate_cutoff, FftFixedInOut, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    VecResampler, WindowFunction,
+};
+
+const CHUNK_SIZE: usize = 1024;
+
+/// Trade-off between CPU use and resampling quality.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResamplerQuality {
+    /// Short sinc filter with linear interpolation, for low-end machines
+    Fast,
+    /// Synchronous FFT resampler
+    #[default]
+    Normal,
+    /// Long sinc filter with cubic interpolation and a steep cutoff
+    High,
+}
+
+impl ResamplerQuality {
+    /// Sinc filter length, window and interpolation of the sinc-based qualities
+    fn sinc_parameters(self) -> Option<SincInterpolationParameters> {
+        let (sinc_len, window, interpolation) = match self {
+            ResamplerQuality::Fast => (64, WindowFunction::Hann2, SincInterpolationType::Linear),
+            ResamplerQuality::Normal => return None,
+            ResamplerQuality::High => (512, WindowFunction::BlackmanHarris2, SincInterpolationType::Cubic),
+        };
+        Some(SincInterpolationParameters {
+            sinc_len,
+            f_cutoff: calculate_cutoff(sinc_len, window),
+            oversampling_factor: 256,
+            interpolation,
+            window,
+        })
+    }
+}
 
 /// Resamples interleaved f32 audio from one sample rate to another.
 pub struct AudioResampler {
-    resampler: FftFixedInOut<f32>,
+    resampler: Box<dyn VecResampler<f32>>,
     channels: usize,
     input_frames_needed: usize,
 }
 
 impl AudioResampler {
-    pub fn new(from_rate: u32, to_rate: u32, channels: usize) -> Result<Self, String> {
+    pub fn new(from_rate: u32, to_rate: u32, channels: usize, quality: ResamplerQuality) -> Result<Self, String> {
         if from_rate == to_rate {
             return Err("No resampling needed".to_string());
         }
 
-        let chunk_size = 1024;
-        let resampler = FftFixedInOut::<f32>::new(
-            from_rate as usize,
-            to_rate as usize,
-            chunk_size,
-            channels,
-        )
-        .map_err(|e| format!("Failed to create resampler: {}", e))?;
+        let resampler: Box<dyn VecResampler<f32>> = match quality.sinc_parameters() {
+            Some(parameters) => Box::new(
+                SincFixedIn::<f32>::new(to_rate as f64 / from_rate as f64, 1.0, parameters, CHUNK_SIZE, channels)
+                    .map_err(|e| format!("Failed to create resampler: {}", e))?,
+            ),
+            None => Box::new(
+                FftFixedInOut::<f32>::new(from_rate as usize, to_rate as usize, CHUNK_SIZE, channels)
+                    .map_err(|e| format!("Failed to create resampler: {}", e))?,
+            ),
+        };
 
         let input_frames_needed = resampler.input_frames_next();
 
@@ -55,10 +93,9 @@ impl AudioResampler {
             }
         }
 
-        let input_refs: Vec<&[f32]> = input_channels.iter().map(|c| c.as_slice()).collect();
         let output_channels = self
             .resampler
-            .process(&input_refs, None)
+            .process(&input_channels, None)
             .map_err(|e| format!("Resample error: {}", e))?;
 
         self.input_frames_needed = self.resampler.input_frames_next();
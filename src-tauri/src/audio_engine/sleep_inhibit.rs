@@ -0,0 +1,181 @@
+//! Keeps the system from sleeping on idle while audio plays.
+//! Windows uses `SetThreadExecutionState` (per thread, so it must stay on the
+//! audio thread), macOS an IOKit power assertion, Linux a `systemd-inhibit`
+//! child that holds a logind inhibitor lock over D-Bus until it is killed.
+
+#[derive(Default)]
+pub struct SleepInhibitor {
+    active: bool,
+    /// Stop trying once the platform mechanism turned out to be unavailable
+    unavailable: bool,
+    #[cfg(target_os = "linux")]
+    child: Option<std::process::Child>,
+    #[cfg(target_os = "macos")]
+    assertion: Option<u32>,
+}
+
+const REASON: &str = "正在播放音乐";
+
+impl SleepInhibitor {
+    /// Hold the inhibitor while `active`; no-op when unchanged.
+    pub fn set_active(&mut self, active: bool) {
+        if active == self.active || self.unavailable {
+            return;
+        }
+        self.active = active;
+        let result = if active { self.acquire() } else { self.release() };
+        if let Err(e) = result {
+            eprintln!("Sleep inhibitor unavailable: {}", e);
+            self.unavailable = true;
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn acquire(&mut self) -> Result<(), String> {
+        windows::set_state(windows::ES_CONTINUOUS | windows::ES_SYSTEM_REQUIRED)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn release(&mut self) -> Result<(), String> {
+        windows::set_state(windows::ES_CONTINUOUS)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn acquire(&mut self) -> Result<(), String> {
+        use std::process::{Command, Stdio};
+        let child = Command::new("systemd-inhibit")
+            .args(["--what=sleep:idle", "--who=BaYin", "--mode=block"])
+            .arg(format!("--why={}", REASON))
+            .args(["sleep", "infinity"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        self.child = Some(child);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn release(&mut self) -> Result<(), String> {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn acquire(&mut self) -> Result<(), String> {
+        self.assertion = Some(macos::create_assertion(REASON)?);
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn release(&mut self) -> Result<(), String> {
+        if let Some(id) = self.assertion.take() {
+            macos::release_assertion(id);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    fn acquire(&mut self) -> Result<(), String> {
+        Err("not supported on this platform".to_string())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    fn release(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        self.set_active(false);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    pub const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+    pub const ES_CONTINUOUS: u32 = 0x8000_0000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetThreadExecutionState(flags: u32) -> u32;
+    }
+
+    pub fn set_state(flags: u32) -> Result<(), String> {
+        // Returns the previous state, or 0 on failure
+        if unsafe { SetThreadExecutionState(flags) } == 0 {
+            return Err("SetThreadExecutionState failed".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::{c_char, c_void, CString};
+
+    type CFStringRef = *const c_void;
+
+    const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    const IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+    const ASSERTION_TYPE: &str = "PreventUserIdleSystemSleep";
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFStringCreateWithCString(alloc: *const c_void, text: *const c_char, encoding: u32) -> CFStringRef;
+        fn CFRelease(object: *const c_void);
+    }
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPMAssertionCreateWithName(
+            assertion_type: CFStringRef,
+            level: u32,
+            name: CFStringRef,
+            id: *mut u32,
+        ) -> i32;
+        fn IOPMAssertionRelease(id: u32) -> i32;
+    }
+
+    fn cf_string(text: &str) -> Result<CFStringRef, String> {
+        let text = CString::new(text).map_err(|e| e.to_string())?;
+        let string = unsafe { CFStringCreateWithCString(std::ptr::null(), text.as_ptr(), CF_STRING_ENCODING_UTF8) };
+        if string.is_null() {
+            return Err("CFStringCreateWithCString failed".to_string());
+        }
+        Ok(string)
+    }
+
+    pub fn create_assertion(reason: &str) -> Result<u32, String> {
+        let assertion_type = cf_string(ASSERTION_TYPE)?;
+        let name = match cf_string(reason) {
+            Ok(name) => name,
+            Err(e) => {
+                unsafe { CFRelease(assertion_type) };
+                return Err(e);
+            }
+        };
+        let mut id = 0;
+        let status = unsafe {
+            let status = IOPMAssertionCreateWithName(assertion_type, IOPM_ASSERTION_LEVEL_ON, name, &mut id);
+            CFRelease(assertion_type);
+            CFRelease(name);
+            status
+        };
+        if status != 0 {
+            return Err(format!("IOPMAssertionCreateWithName failed: {:#x}", status));
+        }
+        Ok(id)
+    }
+
+    pub fn release_assertion(id: u32) {
+        unsafe {
+            IOPMAssertionRelease(id);
+        }
+    }
+}
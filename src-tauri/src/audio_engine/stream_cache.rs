@@ -0,0 +1,146 @@
+//! Disk cache for HTTP stream playback.
+//!
+//! A stream played from the start is written to the cache directory while it
+//! downloads. Once the whole file has arrived it is kept under its song ID, so the
+//! next play reads (and seeks in) the local copy instead of the network.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Cached streams kept at most; the least recently written are evicted first
+const MAX_CACHE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Pending requests kept at most
+const MAX_PENDING: usize = 4;
+
+/// Called with the song ID, file and size of every completed cache entry
+pub type CacheListener = Box<dyn Fn(&str, &Path, u64) + Send + Sync>;
+
+static LISTENER: OnceLock<CacheListener> = OnceLock::new();
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Stream URLs that should be cached, with their song IDs
+static PENDING: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Register where streams are cached and who records finished entries.
+pub fn init(cache_dir: PathBuf, listener: CacheListener) {
+    let _ = std::fs::create_dir_all(&cache_dir);
+    let _ = CACHE_DIR.set(cache_dir);
+    let _ = LISTENER.set(listener);
+}
+
+/// Cache file of a song. There is no extension: the decoder probes the content.
+fn cache_path(song_id: &str) -> Option<PathBuf> {
+    CACHE_DIR
+        .get()
+        .map(|dir| dir.join(format!("{:x}", md5::compute(song_id))))
+}
+
+/// Cache `url` under `song_id` the next time it is opened.
+pub fn request(url: &str, song_id: &str) {
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|(pending_url, _)| pending_url != url);
+    pending.push((url.to_string(), song_id.to_string()));
+    // Requests whose source never opened (errors, skipped preloads) are dropped
+    let excess = pending.len().saturating_sub(MAX_PENDING);
+    pending.drain(..excess);
+}
+
+/// Song ID waiting for `url` to be cached
+pub(super) fn take_request(url: &str) -> Option<String> {
+    let mut pending = PENDING.lock().unwrap();
+    let index = pending.iter().position(|(pending_url, _)| pending_url == url)?;
+    Some(pending.remove(index).1)
+}
+
+/// Writes one download into a `.part` file, which only becomes a cache entry
+/// when the download completes. Dropping it unfinished removes the file.
+pub(super) struct CacheWriter {
+    song_id: String,
+    file: Option<File>,
+    part: PathBuf,
+    written: u64,
+}
+
+impl CacheWriter {
+    pub(super) fn create(song_id: String) -> Option<Self> {
+        let part = cache_path(&song_id)?.with_extension("part");
+        let file = File::create(&part)
+            .map_err(|e| eprintln!("Stream cache for {} not created: {}", song_id, e))
+            .ok()?;
+        Some(Self { song_id, file: Some(file), part, written: 0 })
+    }
+
+    /// Append downloaded bytes; a failed write gives up on this entry.
+    pub(super) fn write(&mut self, data: &[u8]) {
+        let Some(file) = self.file.as_mut() else { return };
+        match file.write_all(data) {
+            Ok(()) => self.written += data.len() as u64,
+            Err(e) => {
+                eprintln!("Stream cache for {} dropped: {}", self.song_id, e);
+                self.file = None;
+            }
+        }
+    }
+
+    /// Keep the file if the download reached the end (`content_length` bytes when known).
+    pub(super) fn finish(mut self, content_length: u64) {
+        let Some(file) = self.file.take() else { return };
+        if self.written == 0 || (content_length > 0 && self.written != content_length) {
+            return;
+        }
+        if let Err(e) = file.sync_all() {
+            eprintln!("Stream cache for {} dropped: {}", self.song_id, e);
+            return;
+        }
+        drop(file);
+
+        let Some(path) = cache_path(&self.song_id) else { return };
+        if std::fs::rename(&self.part, &path).is_err() {
+            return;
+        }
+        if let Some(listener) = LISTENER.get() {
+            listener(&self.song_id, &path, self.written);
+        }
+        evict(&path);
+    }
+}
+
+impl Drop for CacheWriter {
+    fn drop(&mut self) {
+        // Also runs after a successful rename, when the part file is already gone
+        let _ = std::fs::remove_file(&self.part);
+    }
+}
+
+/// Delete the oldest entries until the cache fits `MAX_CACHE_BYTES`, never `keep`.
+/// Rows of evicted songs are left in the database; lookups check the file exists.
+fn evict(keep: &Path) {
+    let Some(dir) = CACHE_DIR.get() else { return };
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+
+    let mut files: Vec<(std::time::SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let path = entry.path();
+            // `.part` files belong to downloads still running
+            if !meta.is_file() || path.extension().is_some() {
+                return None;
+            }
+            Some((meta.modified().ok()?, meta.len(), path))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in files {
+        if total <= MAX_CACHE_BYTES {
+            break;
+        }
+        if path != keep && std::fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Seekbar waveform for HTTP streams.
+//!
+//! While a stream downloads, a second decoder reads the same download buffer and
+//! fills in per-bucket peaks, so the waveform grows during the first listen
+//! instead of waiting for a full pass. Finished waveforms are cached per song.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+
+use super::decoder::AudioDecoder;
+
+/// Number of peaks across the whole track
+pub const WAVEFORM_BUCKETS: usize = 200;
+
+/// Minimum interval between progress updates
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Pending requests kept at most
+const MAX_PENDING: usize = 4;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformUpdate {
+    pub song_id: String,
+    /// Peak per bucket, 0-255; buckets not downloaded yet are 0
+    pub peaks: Vec<u8>,
+    pub complete: bool,
+}
+
+pub type WaveformListener = Box<dyn Fn(&WaveformUpdate) + Send + Sync>;
+
+static LISTENER: OnceLock<WaveformListener> = OnceLock::new();
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Stream URLs that should get a waveform, with their song IDs
+static PENDING: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Register where finished waveforms are cached and who receives updates.
+pub fn init(cache_dir: PathBuf, listener: WaveformListener) {
+    let _ = std::fs::create_dir_all(&cache_dir);
+    let _ = CACHE_DIR.set(cache_dir);
+    let _ = LISTENER.set(listener);
+}
+
+fn cache_path(song_id: &str) -> Option<PathBuf> {
+    CACHE_DIR
+        .get()
+        .map(|dir| dir.join(format!("{:x}.bin", md5::compute(song_id))))
+}
+
+/// Cached waveform of a song, if it was fully built before
+pub fn cached(song_id: &str) -> Option<Vec<u8>> {
+    std::fs::read(cache_path(song_id)?)
+        .ok()
+        .filter(|peaks| peaks.len() == WAVEFORM_BUCKETS)
+}
+
+/// Build a waveform for `song_id` the next time `url` is opened, unless it's cached.
+pub fn request(url: &str, song_id: &str) {
+    if cached(song_id).is_some() {
+        return;
+    }
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|(pending_url, _)| pending_url != url);
+    pending.push((url.to_string(), song_id.to_string()));
+    // Requests whose source never opened (errors, skipped preloads) are dropped
+    let excess = pending.len().saturating_sub(MAX_PENDING);
+    pending.drain(..excess);
+}
+
+/// Song ID waiting for a waveform of `url`
+pub(super) fn take_request(url: &str) -> Option<String> {
+    let mut pending = PENDING.lock().unwrap();
+    let index = pending.iter().position(|(pending_url, _)| pending_url == url)?;
+    Some(pending.remove(index).1)
+}
+
+/// Decode `source` on a background thread, reporting peaks as they come in.
+pub(super) fn spawn_builder(song_id: String, source: Box<dyn MediaSource>, extension: Option<String>) {
+    let _ = std::thread::Builder::new()
+        .name("stream-waveform".into())
+        .spawn(move || {
+            if let Err(e) = build(&song_id, source, extension.as_deref()) {
+                eprintln!("Waveform for {} not built: {}", song_id, e);
+            }
+        });
+}
+
+fn build(song_id: &str, source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<(), String> {
+    let mss = MediaSourceStream::new(source, Default::default());
+    let mut decoder = AudioDecoder::open_stream(mss, extension, true).map_err(|e| e.to_string())?;
+    let duration = decoder.info.duration_secs;
+    if duration <= 0.0 {
+        return Err("unknown duration".to_string());
+    }
+
+    let mut peaks = vec![0f32; WAVEFORM_BUCKETS];
+    let mut elapsed_secs = 0.0;
+    let mut last_update = Instant::now();
+    while let Some(samples) = decoder.decode_next().map_err(|e| e.to_string())? {
+        let channels = decoder.info.channels.max(1);
+        let frame_secs = 1.0 / decoder.info.sample_rate.max(1) as f64;
+        for frame in samples.chunks(channels) {
+            let bucket = ((elapsed_secs / duration) * WAVEFORM_BUCKETS as f64) as usize;
+            let peak = frame.iter().fold(0f32, |max, s| max.max(s.abs()));
+            let slot = &mut peaks[bucket.min(WAVEFORM_BUCKETS - 1)];
+            *slot = slot.max(peak);
+            elapsed_secs += frame_secs;
+        }
+
+        if last_update.elapsed() >= UPDATE_INTERVAL {
+            notify(song_id, &peaks, false);
+            last_update = Instant::now();
+        }
+    }
+
+    let quantized = notify(song_id, &peaks, true);
+    if let Some(path) = cache_path(song_id) {
+        std::fs::write(path, quantized).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn notify(song_id: &str, peaks: &[f32], complete: bool) -> Vec<u8> {
+    let quantized: Vec<u8> = peaks
+        .iter()
+        .map(|peak| (peak.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    if let Some(listener) = LISTENER.get() {
+        listener(&WaveformUpdate {
+            song_id: song_id.to_string(),
+            peaks: quantized.clone(),
+            complete,
+        });
+    }
+    quantized
+}
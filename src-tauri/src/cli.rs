@@ -0,0 +1,192 @@
+//! Headless library scan for cron jobs / media servers
+//!
+//! `bayin --scan [--scan-config <file.json | inline JSON>]` scans the library
+//! stored in the portable data directory and exits without creating a window.
+//! The saved scan configuration is used unless overridden; progress goes to
+//! stderr and the `ScanResult` JSON to stdout.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{scan_local, scan_stream, ScanReporter};
+use crate::db::{self, DbState};
+use crate::models::{default_batch_size, LocalScanOptions, ScanMode, ScanResult, StreamScanOptions};
+use crate::utils::cover::CoverCache;
+
+/// How long to wait when the app itself holds a write lock on the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const USAGE: &str = "Usage: bayin --scan [--scan-config <file.json | JSON>]
+
+--scan-config fields (all optional):
+  directories           directories to scan (default: saved scan config)
+  mode                  \"full\" or \"incremental\" (default: incremental)
+  minDuration           skip audio shorter than this many seconds
+  hashCheckDirectories  directories compared by content hash instead of mtime
+  dryRun                report what would change without writing
+  local                 scan local directories (default: true)
+  streams               also refresh enabled stream servers (default: false)
+  serverId              only refresh this stream server";
+
+/// Overrides for the saved scan configuration
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ScanOverrides {
+    directories: Option<Vec<String>>,
+    mode: ScanMode,
+    min_duration: Option<f64>,
+    hash_check_directories: Option<Vec<String>>,
+    dry_run: bool,
+    local: bool,
+    streams: bool,
+    server_id: Option<String>,
+}
+
+impl Default for ScanOverrides {
+    fn default() -> Self {
+        Self {
+            directories: None,
+            mode: ScanMode::Incremental,
+            min_duration: None,
+            hash_check_directories: None,
+            dry_run: false,
+            local: true,
+            streams: false,
+            server_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CliScanResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local: Option<ScanResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<ScanResult>,
+}
+
+/// Run the headless scan if `--scan` was passed; returns the process exit code
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    if !args.iter().any(|arg| arg == "--scan") {
+        return None;
+    }
+
+    let overrides = match parse_overrides(args) {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+
+    match run_scan(overrides) {
+        Ok(result) => {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("Scan failed: {}", e);
+            Some(1)
+        }
+    }
+}
+
+fn parse_overrides(args: &[String]) -> Result<ScanOverrides, String> {
+    let mut overrides = ScanOverrides::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--scan" => {}
+            "--scan-config" => {
+                let value = iter.next().ok_or("--scan-config requires a value")?;
+                let json = if value.trim_start().starts_with('{') {
+                    value.clone()
+                } else {
+                    std::fs::read_to_string(value).map_err(|e| format!("Failed to read {}: {}", value, e))?
+                };
+                overrides = serde_json::from_str(&json).map_err(|e| format!("Invalid scan config: {}", e))?;
+            }
+            "-h" | "--help" => return Err("Headless library scan".to_string()),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    Ok(overrides)
+}
+
+fn run_scan(overrides: ScanOverrides) -> Result<CliScanResult, String> {
+    let data_root = crate::resolve_portable_data_root().map_err(|e| e.to_string())?;
+    let db_dir = data_root.join("db");
+    std::fs::create_dir_all(&db_dir).map_err(|e| e.to_string())?;
+    let db_path = db_dir.join("bayin.db");
+    let conn = db::open_db(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    // The app may be running against the same database
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    let reader = db::open_reader(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    let db = DbState::new(conn, reader);
+
+    let cover_cache = CoverCache::new(data_root.join("cache").join("covers"));
+    cover_cache.ensure_dirs().map_err(|e| e.to_string())?;
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let mut result = CliScanResult::default();
+
+        if overrides.local {
+            let options = local_options(&db, &overrides)?;
+            if options.directories.is_empty() {
+                return Err("No directories configured; set them in the app or pass `directories`".to_string());
+            }
+            let scanned = scan_local(&ScanReporter::Console, &db, cover_cache.clone_arc(), options)
+                .await
+                .map_err(String::from)?;
+            if !overrides.dry_run {
+                let conn = db.write().map_err(|e| e.to_string())?;
+                db::servers::update_last_scan_time(&conn).map_err(|e| e.to_string())?;
+            }
+            result.local = Some(scanned);
+        }
+
+        if overrides.streams || overrides.server_id.is_some() {
+            let options = StreamScanOptions {
+                server_id: overrides.server_id.clone(),
+            };
+            let scanned = scan_stream(&ScanReporter::Console, &db, options)
+                .await
+                .map_err(String::from)?;
+            result.stream = Some(scanned);
+        }
+
+        Ok(result)
+    })
+}
+
+/// Saved scan config with the command-line overrides applied
+fn local_options(db: &DbState, overrides: &ScanOverrides) -> Result<LocalScanOptions, String> {
+    let saved = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::servers::get_scan_config(&conn).map_err(|e| e.to_string())?
+    };
+    let saved_min_duration = saved
+        .as_ref()
+        .filter(|config| config.skip_short)
+        .map(|config| config.min_duration);
+
+    Ok(LocalScanOptions {
+        directories: overrides
+            .directories
+            .clone()
+            .or_else(|| saved.as_ref().map(|config| config.directories.clone()))
+            .unwrap_or_default(),
+        mode: overrides.mode.clone(),
+        min_duration: overrides.min_duration.or(saved_min_duration),
+        batch_size: default_batch_size(),
+        hash_check_directories: overrides
+            .hash_check_directories
+            .clone()
+            .or_else(|| saved.as_ref().map(|config| config.hash_check_directories.clone()))
+            .unwrap_or_default(),
+        dry_run: overrides.dry_run,
+    })
+}
@@ -0,0 +1,78 @@
+//! EBU R128 loudness analysis of the local library
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::{self, DbState};
+use crate::utils::loudness::analyze_file;
+
+/// Only one analysis pass at a time
+static ANALYZING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisProgress {
+    pub current: usize,
+    pub total: usize,
+    pub song_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessAnalysisResult {
+    pub analyzed: usize,
+    pub failed: usize,
+}
+
+/// 分析本地歌曲的积分响度与真峰值，没有 ReplayGain 标签的歌曲据此做音量均衡。
+/// 默认只分析尚无结果的歌曲，进度通过 `analysis-progress` 事件推送
+#[tauri::command]
+pub async fn library_analyze_loudness(
+    app: AppHandle,
+    reanalyze: Option<bool>,
+) -> Result<LoudnessAnalysisResult, String> {
+    if ANALYZING.swap(true, Ordering::SeqCst) {
+        return Err("响度分析正在进行中".to_string());
+    }
+
+    let result = tokio::task::spawn_blocking(move || analyze_library(&app, reanalyze.unwrap_or(false)))
+        .await
+        .map_err(|e| e.to_string());
+    ANALYZING.store(false, Ordering::SeqCst);
+    result?
+}
+
+fn analyze_library(app: &AppHandle, reanalyze: bool) -> Result<LoudnessAnalysisResult, String> {
+    let db = app.state::<DbState>();
+    let songs = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::songs::get_songs_for_loudness(&conn, reanalyze).map_err(|e| e.to_string())?
+    };
+
+    let total = songs.len();
+    let mut result = LoudnessAnalysisResult { analyzed: 0, failed: 0 };
+    for (index, (song_id, file_path)) in songs.into_iter().enumerate() {
+        let _ = app.emit(
+            "analysis-progress",
+            AnalysisProgress { current: index + 1, total, song_id: song_id.clone() },
+        );
+
+        // Decode without holding the database lock
+        match analyze_file(&file_path) {
+            Ok(metrics) => {
+                let conn = db.write().map_err(|e| e.to_string())?;
+                db::songs::set_song_loudness(&conn, &song_id, metrics.integrated_lufs, metrics.true_peak)
+                    .map_err(|e| e.to_string())?;
+                result.analyzed += 1;
+            }
+            Err(e) => {
+                eprintln!("Loudness analysis failed for {}: {}", file_path, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    Ok(result)
+}
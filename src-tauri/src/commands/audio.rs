@@ -1,13 +1,218 @@
-use crate::audio_engine::engine::{AudioCommand, PlaybackState};
+use crate::audio_engine::dsd::DsdOutput;
+use crate::audio_engine::dsp::{EqLayout, NightModePreset, VolumeCurve};
+use crate::audio_engine::engine::{AudioCommand, OutputInfo, PlaybackState, ReplayGainMode};
+use crate::audio_engine::export::{self, ExportFormat};
+use crate::audio_engine::http_source::HttpHeaders;
+use crate::audio_engine::output::{jack_playback_ports, OutputBackend, OutputOptions, OutputRate};
+use crate::audio_engine::queue::{QueueSnapshot, QueueTrack, RepeatMode};
+use crate::audio_engine::resampler::ResamplerQuality;
+use crate::audio_engine::{stream_cache, waveform};
 use crate::audio_engine::AudioEngineState;
-use tauri::State;
+use crate::commands::streaming::{server_config, stream_url_internal};
+use crate::db::{self, DbState};
+use crate::jellyfin_remote::JellyfinRemoteState;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+
+/// Linear normalization gain bringing a song to the target loudness, 1.0 when disabled or unknown.
+/// With the limiter on, boosted peaks are limited by the engine; otherwise gains are
+/// capped by the track peak, and without a peak only attenuation is applied.
+fn normalization_gain(db: &DbState, song_id: Option<&str>, mode: ReplayGainMode) -> f32 {
+    let Some(song_id) = song_id else { return 1.0 };
+    if mode == ReplayGainMode::Off {
+        return 1.0;
+    }
+    let Ok(conn) = db.write() else { return 1.0 };
+
+    let settings = db::settings::normalization_settings(&conn).unwrap_or_default();
+    if !settings.enabled {
+        return 1.0;
+    }
+    let album = mode == ReplayGainMode::Album;
+    let Ok(Some((gain_db, peak))) = db::songs::get_replay_gain(&conn, song_id, album) else {
+        return 1.0;
+    };
+
+    let target_offset = settings.target_lufs - db::settings::REPLAY_GAIN_REFERENCE_LUFS;
+    let gain = 10f32.powf((gain_db + target_offset + settings.preamp_db) / 20.0);
+    if settings.limiter {
+        return gain;
+    }
+    match peak.filter(|p| *p > 0.0) {
+        Some(peak) => gain.min(1.0 / peak),
+        None => gain.min(1.0),
+    }
+}
+
+/// Build the seekbar waveform of a stream while it downloads.
+/// Playback starting mid-track seeks away from the download, so only
+/// plays from the start are tracked.
+fn request_waveform(source: &str, song_id: Option<&str>) {
+    if let Some(song_id) = song_id {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            waveform::request(source, song_id);
+        }
+    }
+}
+
+/// Stream sources play from the playback cache once fully downloaded; until then
+/// a play from the start fills the cache as it downloads.
+fn stream_source(db: &DbState, source: String, song_id: Option<&str>, from_start: bool) -> String {
+    let Some(song_id) = song_id else { return source };
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return source;
+    }
+
+    if let Ok(conn) = db.write() {
+        if let Ok(Some(path)) = db::get_stream_cache_path(&conn, song_id) {
+            if std::path::Path::new(&path).is_file() {
+                return path;
+            }
+            let _ = db::remove_stream_cache(&conn, song_id);
+        }
+    }
+    if from_start {
+        stream_cache::request(&source, song_id);
+    }
+    source
+}
+
+/// Cue-in / cue-out points of a song within its cue-split track bounds, none when unset or unknown
+fn cue_points(db: &DbState, song_id: Option<&str>) -> db::extra::CuePoints {
+    let Some(song_id) = song_id else { return Default::default() };
+    let Ok(conn) = db.write() else { return Default::default() };
+    db::extra::get_playback_range(&conn, song_id).unwrap_or_default()
+}
+
+/// Chapter start times of a song, empty when it has no chapters
+fn chapter_starts(db: &DbState, song_id: Option<&str>) -> Vec<f64> {
+    let Some(song_id) = song_id else { return Vec::new() };
+    let Ok(conn) = db.write() else { return Vec::new() };
+    db::chapters::get_song_chapters(&conn, song_id)
+        .map(|chapters| chapters.into_iter().map(|c| c.start_secs).collect())
+        .unwrap_or_default()
+}
+
+/// Request headers of an HTTP source as passed from the frontend
+fn header_list(headers: Option<HashMap<String, String>>) -> HttpHeaders {
+    headers.unwrap_or_default().into_iter().collect()
+}
+
+/// Start a song with its normalization gain and cue points, as a direct play
+/// (the history records it, any preload is dropped).
+fn play_track(
+    source: String,
+    headers: HttpHeaders,
+    song_id: Option<String>,
+    engine: &AudioEngineState,
+    db: &DbState,
+    remote: &JellyfinRemoteState,
+) {
+    let mode = engine.lock().unwrap().replay_gain_mode;
+    let gain = normalization_gain(db, song_id.as_deref(), mode);
+    let cues = cue_points(db, song_id.as_deref());
+    let chapters = chapter_starts(db, song_id.as_deref());
+    let source = stream_source(db, source, song_id.as_deref(), cues.cue_in.is_none());
+    // Starting past the cue-in seeks away from the download, so the waveform is skipped then
+    if cues.cue_in.is_none() {
+        request_waveform(&source, song_id.as_deref());
+    }
+    remote.0.set_now_playing(song_id.clone());
+    let engine = engine.lock().unwrap();
+    engine.history.lock().unwrap().start(song_id);
+    engine.send(AudioCommand::Play {
+        source,
+        headers,
+        start_secs: cues.cue_in.unwrap_or(0.0),
+        end_secs: cues.cue_out,
+        gain,
+        chapters,
+    });
+}
+
+/// Hand the engine the song to continue with gaplessly; `None` clears the preload.
+fn preload_track(
+    source: Option<String>,
+    headers: HttpHeaders,
+    song_id: Option<String>,
+    engine: &AudioEngineState,
+    db: &DbState,
+    remote: &JellyfinRemoteState,
+) {
+    let mode = engine.lock().unwrap().replay_gain_mode;
+    let gain = normalization_gain(db, song_id.as_deref(), mode);
+    let cues = cue_points(db, song_id.as_deref());
+    let chapters = chapter_starts(db, song_id.as_deref());
+    let source = source.map(|source| stream_source(db, source, song_id.as_deref(), cues.cue_in.is_none()));
+    if let (Some(source), None) = (source.as_deref(), cues.cue_in) {
+        request_waveform(source, song_id.as_deref());
+    }
+    remote.0.set_next_up(song_id.clone());
+    let engine = engine.lock().unwrap();
+    engine.history.lock().unwrap().set_next_up(song_id);
+    engine.send(AudioCommand::PreloadNext {
+        source,
+        headers,
+        start_secs: cues.cue_in.unwrap_or(0.0),
+        end_secs: cues.cue_out,
+        gain,
+        chapters,
+    });
+}
 
 #[tauri::command]
-pub fn audio_play(source: String, engine: State<'_, AudioEngineState>) {
+pub fn audio_play(
+    source: String,
+    headers: Option<HashMap<String, String>>,
+    song_id: Option<String>,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) {
     #[cfg(debug_assertions)]
     eprintln!("audio_play: {}", source);
+    engine.lock().unwrap().queue.clear_preloaded();
+    play_track(source, header_list(headers), song_id, &engine, &db, &remote);
+}
+
+#[tauri::command]
+pub fn audio_play_at(
+    source: String,
+    headers: Option<HashMap<String, String>>,
+    position_secs: f64,
+    song_id: Option<String>,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_play_at: {} @ {}", source, position_secs);
+    let mode = engine.lock().unwrap().replay_gain_mode;
+    let gain = normalization_gain(&db, song_id.as_deref(), mode);
+    let cues = cue_points(&db, song_id.as_deref());
+    let chapters = chapter_starts(&db, song_id.as_deref());
+    let source = stream_source(&db, source, song_id.as_deref(), false);
+    remote.0.set_now_playing(song_id.clone());
+    let engine = engine.lock().unwrap();
+    engine.history.lock().unwrap().start(song_id);
+    engine.send(AudioCommand::Play {
+        source,
+        headers: header_list(headers),
+        start_secs: position_secs.max(0.0),
+        end_secs: cues.cue_out,
+        gain,
+        chapters,
+    });
+}
+
+/// Song played before the current one, taken off the history stack.
+/// `None` when nothing was played before; the caller then picks from the queue.
+#[tauri::command]
+pub fn audio_previous(engine: State<'_, AudioEngineState>) -> Option<String> {
     let engine = engine.lock().unwrap();
-    engine.send(AudioCommand::Play { source });
+    let previous = engine.history.lock().unwrap().back();
+    previous
 }
 
 #[tauri::command]
@@ -27,9 +232,10 @@ pub fn audio_resume(engine: State<'_, AudioEngineState>) {
 }
 
 #[tauri::command]
-pub fn audio_stop(engine: State<'_, AudioEngineState>) {
+pub fn audio_stop(engine: State<'_, AudioEngineState>, remote: State<'_, JellyfinRemoteState>) {
     #[cfg(debug_assertions)]
     eprintln!("audio_stop");
+    remote.0.set_now_playing(None);
     let engine = engine.lock().unwrap();
     engine.send(AudioCommand::Stop);
 }
@@ -42,6 +248,16 @@ pub fn audio_seek(position_secs: f64, engine: State<'_, AudioEngineState>) {
     engine.send(AudioCommand::Seek { position_secs });
 }
 
+/// 跳到当前曲目第 `index` 章（从 0 开始），可带章内偏移秒数。
+/// 播放进入新章节时发送 `audio:chapter_changed` 事件（`index`、`start`）
+#[tauri::command]
+pub fn audio_seek_chapter(index: usize, offset_secs: Option<f64>, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_seek_chapter: {} + {:?}", index, offset_secs);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SeekChapter { index, offset_secs: offset_secs.unwrap_or(0.0) });
+}
+
 #[tauri::command]
 pub fn audio_set_volume(volume: f32, engine: State<'_, AudioEngineState>) {
     #[cfg(debug_assertions)]
@@ -50,17 +266,43 @@ pub fn audio_set_volume(volume: f32, engine: State<'_, AudioEngineState>) {
     engine.send(AudioCommand::SetVolume { volume });
 }
 
+/// 音量曲线："logarithmic"（按分贝均匀变化）| "linear"
+#[tauri::command]
+pub fn audio_set_volume_curve(curve: VolumeCurve, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_volume_curve: {:?}", curve);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetVolumeCurve { curve });
+}
+
+#[tauri::command]
+pub fn audio_set_muted(muted: bool, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_muted: {}", muted);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetMuted { muted });
+}
+
+/// 各频段增益（dB），数量需与当前频段布局一致（10 或 31），否则忽略
 #[tauri::command]
 pub fn audio_set_eq_bands(gains: Vec<f32>, engine: State<'_, AudioEngineState>) {
-    if gains.len() != 10 {
+    if gains.len() != EqLayout::Ten.band_count() && gains.len() != EqLayout::ThirtyOne.band_count() {
         return;
     }
     #[cfg(debug_assertions)]
     eprintln!("audio_set_eq_bands: {:?}", gains);
-    let mut arr = [0.0f32; 10];
-    arr.copy_from_slice(&gains);
     let engine = engine.lock().unwrap();
-    engine.send(AudioCommand::SetEqBands { gains: arr });
+    engine.send(AudioCommand::SetEqBands { gains });
+}
+
+/// 均衡器频段布局："10"（默认）| "31"（ISO 1/3 倍频程，便于导入 EasyEffects/foobar2000 预设）。
+/// 切换后各频段增益归零
+#[tauri::command]
+pub fn audio_set_eq_layout(layout: EqLayout, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_eq_layout: {:?}", layout);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetEqLayout { layout });
 }
 
 #[tauri::command]
@@ -71,15 +313,570 @@ pub fn audio_set_eq_enabled(enabled: bool, engine: State<'_, AudioEngineState>)
     engine.send(AudioCommand::SetEqEnabled { enabled });
 }
 
+/// 卡拉 OK 模式：消除人声，保持当前的频段限制设置
+#[tauri::command]
+pub fn audio_set_karaoke(enabled: bool, strength: f32, engine: State<'_, AudioEngineState>) {
+    audio_set_vocal_reduction(enabled, strength, None, engine);
+}
+
+/// Karaoke vocal reduction, applied before the EQ; `strength` is 0.0 - 1.0.
+/// `band_limited` (default on) cuts only the vocal band; off removes the whole center.
+/// Omitting it keeps the current mode
+#[tauri::command]
+pub fn audio_set_vocal_reduction(
+    enabled: bool,
+    strength: f32,
+    band_limited: Option<bool>,
+    engine: State<'_, AudioEngineState>,
+) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_vocal_reduction: {} {} {:?}", enabled, strength, band_limited);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetVocalReduction {
+        enabled,
+        strength: strength.clamp(0.0, 1.0),
+        band_limited,
+    });
+}
+
+#[tauri::command]
+pub fn audio_set_night_mode(enabled: bool, preset: NightModePreset, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_night_mode: {} {:?}", enabled, preset);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetNightMode { enabled, preset });
+}
+
+/// 防削波限幅器：均衡器/人声消除提升后避免爆音
+#[tauri::command]
+pub fn audio_set_limiter(enabled: bool, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_limiter: {}", enabled);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetLimiter { enabled });
+}
+
+/// 抖动：输出设备位深低于音频流时加入 TPDF 抖动后再量化
+#[tauri::command]
+pub fn audio_set_dither(enabled: bool, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_dither: {}", enabled);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetDither { enabled });
+}
+
+/// 跳过静音：跳过歌曲开头的静音，并把结尾和曲中过长的静音缩短到 2 秒
+#[tauri::command]
+pub fn audio_set_skip_silence(enabled: bool, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_skip_silence: {}", enabled);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetSkipSilence { enabled });
+}
+
+/// 结尾淡出：每首歌最后 `seconds` 秒（有结束点时在结束点前）逐渐减弱到静音，0 为关闭
+#[tauri::command]
+pub fn audio_set_end_fade(seconds: f64, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_end_fade: {}", seconds);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetEndFade { secs: seconds.clamp(0.0, 30.0) });
+}
+
+/// 音量均衡使用的 ReplayGain："off" | "track" | "album"，立即作用于当前和预加载的歌曲
+#[tauri::command]
+pub fn audio_set_replaygain_mode(
+    mode: ReplayGainMode,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_replaygain_mode: {:?}", mode);
+    let mut engine = engine.lock().unwrap();
+    engine.replay_gain_mode = mode;
+    let (current, next_up) = {
+        let history = engine.history.lock().unwrap();
+        (history.current().map(str::to_string), history.next_up().map(str::to_string))
+    };
+    engine.send(AudioCommand::SetTrackGain {
+        gain: normalization_gain(&db, current.as_deref(), mode),
+        next_gain: normalization_gain(&db, next_up.as_deref(), mode),
+    });
+}
+
+/// 当前输出链路：设备、实际采样率/声道、是否重采样、均衡器等是否在路径中。未在输出时返回 None
+#[tauri::command]
+pub fn audio_get_output_info(engine: State<'_, AudioEngineState>) -> Result<Option<OutputInfo>, String> {
+    let (reply, response) = crossbeam_channel::bounded(1);
+    engine.lock().unwrap().send(AudioCommand::GetOutputInfo { reply });
+    response
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .map_err(|_| "音频引擎无响应".to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub progress: f64,
+}
+
+/// 离线导出：按当前均衡器、人声消除、夜间模式设置渲染歌曲，写入 24 bit WAV/FLAC，
+/// 可选转换采样率。进度（0-1）通过 `audio:export_progress` 事件推送
+#[tauri::command]
+pub async fn audio_export(
+    app: AppHandle,
+    source: String,
+    output_path: String,
+    format: ExportFormat,
+    sample_rate: Option<u32>,
+) -> Result<(), String> {
+    let settings = {
+        let (reply, response) = crossbeam_channel::bounded(1);
+        app.state::<AudioEngineState>().lock().unwrap().send(AudioCommand::GetDspSettings { reply });
+        response
+            .recv_timeout(std::time::Duration::from_secs(1))
+            .map_err(|_| "音频引擎无响应".to_string())?
+    };
+
+    tokio::task::spawn_blocking(move || {
+        export::export(&source, std::path::Path::new(&output_path), format, sample_rate, &settings, |progress| {
+            let _ = app.emit("audio:export_progress", ExportProgress { progress });
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// A-B 循环：播放到 `end_secs` 后跳回 `start_secs`，切换歌曲时自动取消
+#[tauri::command]
+pub fn audio_set_ab_loop(start_secs: f64, end_secs: f64, engine: State<'_, AudioEngineState>) -> Result<(), String> {
+    if !start_secs.is_finite() || !end_secs.is_finite() || end_secs <= start_secs {
+        return Err("B 点必须晚于 A 点".to_string());
+    }
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetAbLoop { range: Some((start_secs, end_secs)) });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn audio_clear_ab_loop(engine: State<'_, AudioEngineState>) {
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetAbLoop { range: None });
+}
+
 #[tauri::command]
 pub fn audio_enable_visualization(enabled: bool, engine: State<'_, AudioEngineState>) {
     let engine = engine.lock().unwrap();
     engine.send(AudioCommand::EnableVisualization { enabled });
 }
 
+/// 电平表：按声道发送峰值和 RMS（dBFS）的 `audio:levels` 事件，约 20 次/秒，不需要开启 FFT
+#[tauri::command]
+pub fn audio_enable_levels(enabled: bool, engine: State<'_, AudioEngineState>) {
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::EnableLevels { enabled });
+}
+
+#[tauri::command]
+pub fn audio_preload_next(
+    source: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    song_id: Option<String>,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_preload_next: {:?}", source);
+    engine.lock().unwrap().queue.clear_preloaded();
+    preload_track(source, header_list(headers), song_id, &engine, &db, &remote);
+}
+
+#[tauri::command]
+pub fn audio_set_output_options(
+    buffer_frames: Option<u32>,
+    ring_buffer_ms: u32,
+    engine: State<'_, AudioEngineState>,
+) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_output_options: {:?} frames, {} ms", buffer_frames, ring_buffer_ms);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetOutputOptions {
+        options: OutputOptions {
+            buffer_frames,
+            ring_buffer_ms,
+        },
+    });
+}
+
+/// Choose the audio server: "system" (default) or "jack" (also PipeWire via pipewire-jack).
+/// For JACK, `ports[i]` is where output channel i goes; empty = system playback ports.
+#[tauri::command]
+pub fn audio_set_output_backend(
+    backend: String,
+    client_name: Option<String>,
+    ports: Option<Vec<String>>,
+    engine: State<'_, AudioEngineState>,
+) -> Result<(), String> {
+    let backend = match backend.as_str() {
+        "system" => OutputBackend::System,
+        "jack" => OutputBackend::Jack {
+            client_name: client_name
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "BaYin".to_string()),
+            ports: ports.unwrap_or_default(),
+        },
+        other => return Err(format!("未知的输出后端: {}", other)),
+    };
+    if !backend.is_supported() {
+        return Err("当前版本未启用 JACK 输出".to_string());
+    }
+
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetOutputBackend { backend });
+    Ok(())
+}
+
+/// 输出采样率："auto"（由开始播放的歌曲决定，无缝衔接的歌曲重采样）|
+/// "source"（始终跟随音源，采样率变化时重新打开设备）| "fixed"（固定为 `rate`，其余音源重采样）
+#[tauri::command]
+pub fn audio_set_output_sample_rate(
+    mode: String,
+    rate: Option<u32>,
+    engine: State<'_, AudioEngineState>,
+) -> Result<(), String> {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_output_sample_rate: {} {:?}", mode, rate);
+    let rate = match mode.as_str() {
+        "auto" => OutputRate::Auto,
+        "source" => OutputRate::Source,
+        "fixed" => match rate {
+            Some(rate) if (8000..=768_000).contains(&rate) => OutputRate::Fixed(rate),
+            _ => return Err("无效的输出采样率".to_string()),
+        },
+        other => return Err(format!("未知的采样率模式: {}", other)),
+    };
+
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetOutputRate { rate });
+    Ok(())
+}
+
+/// 重采样质量："fast" | "normal" | "high"，正在重采样时立即重建
+#[tauri::command]
+pub fn audio_set_resampler_quality(quality: ResamplerQuality, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_resampler_quality: {:?}", quality);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetResamplerQuality { quality });
+}
+
+/// DSD 输出方式："pcm" | "dop"，从下一首 DSD 歌曲开始生效。
+/// 设备不支持所需采样率/声道时自动回退为 PCM
+#[tauri::command]
+pub fn audio_set_dsd_output(output: DsdOutput, engine: State<'_, AudioEngineState>) {
+    #[cfg(debug_assertions)]
+    eprintln!("audio_set_dsd_output: {:?}", output);
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetDsdOutput { output });
+}
+
+/// Output 5.1/7.1 sources in their own layout; the device falls back to a downmix if it has fewer channels
+#[tauri::command]
+pub fn audio_set_multichannel(enabled: bool, engine: State<'_, AudioEngineState>) {
+    let engine = engine.lock().unwrap();
+    engine.send(AudioCommand::SetMultichannel { enabled });
+}
+
+/// JACK input ports that output channels can be mapped to; `None` if this build has no JACK
+#[tauri::command]
+pub fn audio_list_jack_ports() -> Result<Option<Vec<String>>, String> {
+    jack_playback_ports().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn audio_get_state(engine: State<'_, AudioEngineState>) -> PlaybackState {
     let engine = engine.lock().unwrap();
     let state = engine.state.lock().unwrap().clone();
     state
 }
+
+/// Cached seekbar waveform of a stream song (peaks 0-255), None until it was fully built
+#[tauri::command]
+pub fn audio_get_waveform(song_id: String) -> Option<Vec<u8>> {
+    waveform::cached(&song_id)
+}
+
+/// Playable source of a library song, picked the way the player does: the first
+/// available copy (local files first), an offline download, then the stream URL.
+fn song_source(db: &DbState, song_id: &str) -> Result<String, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let song = db::unified::get_song_sources(&conn, song_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "歌曲文件不存在".to_string())?;
+    let Some(server_id) = song.server_id.as_deref().filter(|_| song.source_type == "stream") else {
+        return Ok(song.file_path);
+    };
+
+    if let Some(path) = db::offline::get_offline_path(&conn, &song.id).map_err(|e| e.to_string())? {
+        if std::path::Path::new(&path).is_file() {
+            return Ok(path);
+        }
+    }
+    let server = db::servers::get_stream_server(&conn, server_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "流媒体服务器不存在".to_string())?;
+    let remote_id = song.server_song_id.as_deref().unwrap_or(&song.id);
+    Ok(stream_url_internal(&server_config(&server), remote_id))
+}
+
+/// Source of a queue track, resolved from its song when none was given
+fn track_source(track: &QueueTrack, db: &DbState) -> Result<String, String> {
+    match track.song_id.as_deref() {
+        Some(song_id) if track.source.is_empty() => song_source(db, song_id),
+        _ => Ok(track.source.clone()),
+    }
+}
+
+/// Keep the engine preloaded with the track after the current queue track,
+/// or nothing at the end of the queue. Does nothing while the queue is unused.
+/// Every queue change ends here, so a preload never outlives the order it was taken from.
+fn preload_queue_next(engine: &AudioEngineState, db: &DbState, remote: &JellyfinRemoteState) {
+    let next = {
+        let mut engine = engine.lock().unwrap();
+        if engine.queue.current().is_none() {
+            return;
+        }
+        engine.queue.preload_next()
+    };
+    let Some(track) = next else {
+        return preload_track(None, Vec::new(), None, engine, db, remote);
+    };
+    match track_source(&track, db) {
+        Ok(source) => {
+            let headers = header_list(Some(track.headers));
+            preload_track(Some(source), headers, track.song_id, engine, db, remote)
+        }
+        Err(e) => {
+            eprintln!("Failed to preload queue track {:?}: {}", track.song_id, e);
+            engine.lock().unwrap().queue.clear_preloaded();
+            preload_track(None, Vec::new(), None, engine, db, remote)
+        }
+    }
+}
+
+/// Current queue, also sent to the UI as `audio:queue_changed`
+fn queue_changed(app: &AppHandle, engine: &AudioEngineState) -> QueueSnapshot {
+    let snapshot = engine.lock().unwrap().queue.snapshot();
+    let _ = app.emit("audio:queue_changed", &snapshot);
+    snapshot
+}
+
+/// Play a track the queue moved to, then preload the one after it.
+fn play_queue_track(
+    track: Option<QueueTrack>,
+    app: &AppHandle,
+    engine: &AudioEngineState,
+    db: &DbState,
+    remote: &JellyfinRemoteState,
+) -> Result<QueueSnapshot, String> {
+    if let Some(track) = track {
+        let source = track_source(&track, db)?;
+        play_track(source, header_list(Some(track.headers)), track.song_id, engine, db, remote);
+        preload_queue_next(engine, db, remote);
+    }
+    Ok(queue_changed(app, engine))
+}
+
+/// The queue drives playback: gapless handoffs to a queue track make it current
+/// and the track after it is preloaded next; a track that ends without a handoff
+/// is followed by the next one in the queue.
+/// Registered after the Jellyfin remote, which reads the old next-up song on the same event.
+pub fn follow_queue(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any("audio:track_changed", move |_| {
+        let engine = handle.state::<AudioEngineState>();
+        if !engine.lock().unwrap().queue.advance() {
+            return;
+        }
+        preload_queue_next(&engine, &handle.state::<DbState>(), &handle.state::<JellyfinRemoteState>());
+        queue_changed(&handle, &engine);
+    });
+
+    let handle = app.clone();
+    app.listen_any("audio:ended", move |_| {
+        let engine = handle.state::<AudioEngineState>();
+        let track = {
+            let mut engine = engine.lock().unwrap();
+            if engine.queue.current().is_none() {
+                return;
+            }
+            engine.queue.finish_current()
+        };
+        let (db, remote) = (handle.state::<DbState>(), handle.state::<JellyfinRemoteState>());
+        if let Err(e) = play_queue_track(track, &handle, &engine, &db, &remote) {
+            eprintln!("Failed to play the next queue track: {}", e);
+            queue_changed(&handle, &engine);
+        }
+    });
+}
+
+/// 一首歌听过一半（`audio:track_played`）时记一次播放
+pub fn record_plays(app: &AppHandle) {
+    #[derive(serde::Deserialize)]
+    struct TrackPlayed {
+        song_id: Option<String>,
+    }
+
+    let handle = app.clone();
+    app.listen_any("audio:track_played", move |event| {
+        let Some(song_id) = serde_json::from_str::<TrackPlayed>(event.payload()).ok().and_then(|p| p.song_id) else {
+            return;
+        };
+        let db = handle.state::<DbState>();
+        let conn = db.write().unwrap();
+        if let Err(e) = db::history::record_play(&conn, &song_id) {
+            eprintln!("Failed to record play of {}: {}", song_id, e);
+        }
+    });
+}
+
+/// 原生播放队列：替换队列并从 `start_index`（默认第一首）开始播放。
+/// 曲库歌曲可以只传 `songId`，播放时再解析音源
+#[tauri::command]
+pub fn audio_queue_set(
+    tracks: Vec<QueueTrack>,
+    start_index: Option<usize>,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> Result<QueueSnapshot, String> {
+    let track = engine.lock().unwrap().queue.set(tracks, start_index.unwrap_or(0));
+    play_queue_track(track, &app, &engine, &db, &remote)
+}
+
+/// 替换队列内容但不打断播放：当前歌曲仍在新队列中时保持为当前歌曲
+#[tauri::command]
+pub fn audio_queue_update(
+    tracks: Vec<QueueTrack>,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> QueueSnapshot {
+    let has_current = {
+        let mut engine = engine.lock().unwrap();
+        engine.queue.update(tracks);
+        engine.queue.current().is_some()
+    };
+    if has_current {
+        preload_queue_next(&engine, &db, &remote);
+    } else {
+        preload_track(None, Vec::new(), None, &engine, &db, &remote);
+    }
+    queue_changed(&app, &engine)
+}
+
+/// 播放队列中的某首歌；不在队列中时插到当前歌曲之后
+#[tauri::command]
+pub fn audio_queue_play(
+    song_id: String,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> Result<QueueSnapshot, String> {
+    let track = QueueTrack { source: String::new(), song_id: Some(song_id), headers: HashMap::new() };
+    let track = engine.lock().unwrap().queue.jump_to(track);
+    play_queue_track(track, &app, &engine, &db, &remote)
+}
+
+/// 下一首（单曲循环时也切到下一首）；队列末尾且未开启列表循环时不变
+#[tauri::command]
+pub fn audio_queue_next(
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> Result<QueueSnapshot, String> {
+    let track = engine.lock().unwrap().queue.next();
+    play_queue_track(track, &app, &engine, &db, &remote)
+}
+
+/// 按播放顺序回到上一首
+#[tauri::command]
+pub fn audio_queue_prev(
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> Result<QueueSnapshot, String> {
+    let track = engine.lock().unwrap().queue.previous();
+    play_queue_track(track, &app, &engine, &db, &remote)
+}
+
+/// 在 `index` 之前插入歌曲，省略时追加到末尾
+#[tauri::command]
+pub fn audio_queue_insert(
+    index: Option<usize>,
+    tracks: Vec<QueueTrack>,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> QueueSnapshot {
+    engine.lock().unwrap().queue.insert(index.unwrap_or(usize::MAX), tracks);
+    preload_queue_next(&engine, &db, &remote);
+    queue_changed(&app, &engine)
+}
+
+/// 移出队列中的歌曲（正在播放的除外）
+#[tauri::command]
+pub fn audio_queue_remove(
+    index: usize,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> Result<QueueSnapshot, String> {
+    engine.lock().unwrap().queue.remove(index)?;
+    preload_queue_next(&engine, &db, &remote);
+    Ok(queue_changed(&app, &engine))
+}
+
+/// 随机播放：开启时当前歌曲保持不变，其余歌曲重新打乱
+#[tauri::command]
+pub fn audio_queue_set_shuffle(
+    enabled: bool,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> QueueSnapshot {
+    engine.lock().unwrap().queue.set_shuffle(enabled);
+    preload_queue_next(&engine, &db, &remote);
+    queue_changed(&app, &engine)
+}
+
+/// 循环模式："off" | "all" | "one"
+#[tauri::command]
+pub fn audio_queue_set_repeat(
+    mode: RepeatMode,
+    app: AppHandle,
+    engine: State<'_, AudioEngineState>,
+    db: State<'_, DbState>,
+    remote: State<'_, JellyfinRemoteState>,
+) -> QueueSnapshot {
+    engine.lock().unwrap().queue.set_repeat(mode);
+    preload_queue_next(&engine, &db, &remote);
+    queue_changed(&app, &engine)
+}
+
+#[tauri::command]
+pub fn audio_queue_get(engine: State<'_, AudioEngineState>) -> QueueSnapshot {
+    let snapshot = engine.lock().unwrap().queue.snapshot();
+    snapshot
+}
@@ -1,10 +1,22 @@
 //! Database Tauri commands
 
 use crate::db::{
-    self, DbAlbum, DbArtist, DbSong, DbState, DbStreamServer, ScanConfig, SongInput,
-    StreamServerInput,
+    self, AlbumPage, AlbumPageQuery, ArtistPage, ArtistPageQuery, DbAlbum, DbArtist, DbBatchOp,
+    DbBatchResult, DbEqPreset, DbGenre, DbPlaylist, DbSong, DbState,
+    DbStreamServer, ListeningRange, ListeningStats, MissingSong,
+    ScanConfig, SearchMode, Setting, SmartQueueRule, SongInput, SongPicture, SongPage, SongPageQuery, StreamServerInput,
 };
+use crate::db::chapters::Chapter;
+use crate::db::extra::CuePoints;
+use crate::db::folders::DbFolder;
+use crate::db::maintenance::OptimizeResult;
+use crate::commands::streaming::server_config;
+use crate::downloads::DownloadManagerState;
+use crate::telemetry::TelemetryState;
+use crate::utils::library_export::{self, LibraryExportFormat};
+use crate::utils::{server_backup, subsonic};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use tauri::State;
 
 /// Migration data from localStorage
@@ -52,24 +64,153 @@ pub struct MigrationStreamConfig {
 /// Get all songs from the database
 #[tauri::command]
 pub fn db_get_all_songs(db: State<'_, DbState>) -> Result<Vec<DbSong>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
     db::songs::get_all_songs(&conn).map_err(|e| e.to_string())
 }
 
+/// Get all songs, showing tracks available both locally and on a server only once (local copy)
+#[tauri::command]
+pub fn db_get_unified_songs(db: State<'_, DbState>) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::unified::get_unified_songs(&conn).map_err(|e| e.to_string())
+}
+
+/// All playable copies of a song, local first (stream copies are the fallback)
+#[tauri::command]
+pub fn db_get_song_sources(db: State<'_, DbState>, song_id: String) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::unified::get_song_sources(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// Get one page of songs (sorting incl. date added, optional recency filter)
+#[tauri::command]
+pub fn db_get_songs_page(db: State<'_, DbState>, query: SongPageQuery) -> Result<SongPage, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::songs::get_songs_page(&conn, &query).map_err(|e| e.to_string())
+}
+
+/// 最近添加的歌曲（默认 50 首）
+#[tauri::command]
+pub fn db_get_recently_added(db: State<'_, DbState>, limit: Option<u32>) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::songs::get_recently_added(&conn, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+/// Search songs by title/artist/album substring or pinyin initials (optionally fuzzy)
+#[tauri::command]
+pub fn db_search_songs(
+    db: State<'_, DbState>,
+    query: String,
+    mode: Option<SearchMode>,
+    limit: Option<u32>,
+) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::search::search_songs(&conn, &query, mode.unwrap_or_default(), limit.unwrap_or(200))
+        .map_err(|e| e.to_string())
+}
+
 /// Get all albums (aggregated from songs)
 #[tauri::command]
 pub fn db_get_all_albums(db: State<'_, DbState>) -> Result<Vec<DbAlbum>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
     db::albums::get_all_albums(&conn).map_err(|e| e.to_string())
 }
 
+/// All embedded pictures of an album (front/back cover, booklet...), front cover first
+#[tauri::command]
+pub fn db_get_album_pictures(db: State<'_, DbState>, album_id: String) -> Result<Vec<SongPicture>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::pictures::get_album_pictures(&conn, &album_id).map_err(|e| e.to_string())
+}
+
+/// Embedded pictures of one song
+#[tauri::command]
+pub fn db_get_song_pictures(db: State<'_, DbState>, song_id: String) -> Result<Vec<SongPicture>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::pictures::get_song_pictures(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// Chapters of one song (m4b audiobooks), empty when it has none
+#[tauri::command]
+pub fn db_get_song_chapters(db: State<'_, DbState>, song_id: String) -> Result<Vec<Chapter>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::chapters::get_song_chapters(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// Get one page of albums (sorted, optionally filtered)
+#[tauri::command]
+pub fn db_get_albums_page(db: State<'_, DbState>, query: AlbumPageQuery) -> Result<AlbumPage, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::albums::get_albums_page(&conn, &query).map_err(|e| e.to_string())
+}
+
+/// Get all genres (aggregated from songs)
+#[tauri::command]
+pub fn db_get_all_genres(db: State<'_, DbState>) -> Result<Vec<DbGenre>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::albums::get_all_genres(&conn).map_err(|e| e.to_string())
+}
+
+/// Get songs of one genre
+#[tauri::command]
+pub fn db_get_songs_by_genre(db: State<'_, DbState>, genre: String) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::albums::get_songs_by_genre(&conn, &genre).map_err(|e| e.to_string())
+}
+
 /// Get all artists (aggregated from songs)
 #[tauri::command]
 pub fn db_get_all_artists(db: State<'_, DbState>) -> Result<Vec<DbArtist>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
     db::albums::get_all_artists(&conn).map_err(|e| e.to_string())
 }
 
+/// 专辑详情：专辑中的歌曲，按碟号、音轨号排列
+#[tauri::command]
+pub fn db_get_songs_by_album(db: State<'_, DbState>, album_id: String) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let songs = db::albums::get_songs_by_album(&conn, &album_id).map_err(|e| e.to_string())?;
+    if songs.is_empty() {
+        return Err(format!("专辑不存在: {}", album_id));
+    }
+    Ok(songs)
+}
+
+/// 艺术家详情：艺术家的全部歌曲，按专辑排列
+#[tauri::command]
+pub fn db_get_songs_by_artist(db: State<'_, DbState>, artist_id: String) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let artist = db::queue::resolve_artist_name(&conn, &artist_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("艺术家不存在: {}", artist_id))?;
+    db::albums::get_songs_by_artist(&conn, &artist).map_err(|e| e.to_string())
+}
+
+/// 文件夹视图：由本地歌曲路径得到的目录树
+#[tauri::command]
+pub fn db_get_folder_tree(db: State<'_, DbState>) -> Result<Vec<DbFolder>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::folders::get_folder_tree(&conn).map_err(|e| e.to_string())
+}
+
+/// 文件夹中的歌曲，按文件路径排列；`recursive` 为 true 时包含子文件夹
+#[tauri::command]
+pub fn db_get_songs_in_folder(
+    db: State<'_, DbState>,
+    path: String,
+    recursive: Option<bool>,
+) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::folders::get_songs_in_folder(&conn, &path, recursive.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// Get one page of artists (sorted, optionally filtered)
+#[tauri::command]
+pub fn db_get_artists_page(db: State<'_, DbState>, query: ArtistPageQuery) -> Result<ArtistPage, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::albums::get_artists_page(&conn, &query).map_err(|e| e.to_string())
+}
+
 /// Save songs to database
 #[tauri::command]
 pub fn db_save_songs(
@@ -78,7 +219,7 @@ pub fn db_save_songs(
     source_type: String,
     server_id: Option<String>,
 ) -> Result<usize, String> {
-    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.write().map_err(|e| e.to_string())?;
     db::songs::save_songs(&mut conn, &songs, &source_type, server_id.as_deref())
         .map_err(|e| e.to_string())
 }
@@ -90,7 +231,7 @@ pub fn db_delete_songs_by_source(
     source_type: String,
     server_id: Option<String>,
 ) -> Result<usize, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::songs::delete_songs_by_source(&conn, &source_type, server_id.as_deref())
         .map_err(|e| e.to_string())
 }
@@ -98,7 +239,7 @@ pub fn db_delete_songs_by_source(
 /// Delete songs by ids
 #[tauri::command]
 pub fn db_delete_songs_by_ids(db: State<'_, DbState>, song_ids: Vec<String>) -> Result<usize, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
 
     let mut affected = 0usize;
     for song_id in song_ids {
@@ -113,14 +254,14 @@ pub fn db_delete_songs_by_ids(db: State<'_, DbState>, song_ids: Vec<String>) ->
 /// Clear all songs
 #[tauri::command]
 pub fn db_clear_all_songs(db: State<'_, DbState>) -> Result<usize, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::songs::clear_all_songs(&conn).map_err(|e| e.to_string())
 }
 
 /// Get all stream servers
 #[tauri::command]
 pub fn db_get_stream_servers(db: State<'_, DbState>) -> Result<Vec<DbStreamServer>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
     db::servers::get_stream_servers(&conn).map_err(|e| e.to_string())
 }
 
@@ -130,42 +271,120 @@ pub fn db_save_stream_server(
     db: State<'_, DbState>,
     config: StreamServerInput,
 ) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::servers::save_stream_server(&conn, &config).map_err(|e| e.to_string())
 }
 
 /// Delete stream server and its associated songs
 #[tauri::command]
 pub fn db_delete_stream_server(db: State<'_, DbState>, server_id: String) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::servers::delete_stream_server(&conn, &server_id).map_err(|e| e.to_string())
 }
 
 /// Clear all stream servers
 #[tauri::command]
 pub fn db_clear_stream_servers(db: State<'_, DbState>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::servers::clear_stream_servers(&conn).map_err(|e| e.to_string())
 }
 
+/// Export all stream server configs to a file, encrypted when a password is given.
+/// Returns the number of exported servers.
+#[tauri::command]
+pub fn db_export_stream_servers(
+    db: State<'_, DbState>,
+    path: String,
+    password: Option<String>,
+) -> Result<usize, String> {
+    let servers: Vec<StreamServerInput> = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        db::servers::get_stream_servers(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|s| StreamServerInput {
+                server_type: s.server_type,
+                server_name: s.server_name,
+                server_url: s.server_url,
+                username: s.username,
+                password: s.password,
+                access_token: s.access_token,
+                user_id: s.user_id,
+            })
+            .collect()
+    };
+    if servers.is_empty() {
+        return Err("没有可导出的服务器".to_string());
+    }
+
+    let content = server_backup::export_servers(&servers, password.as_deref())?;
+    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    Ok(servers.len())
+}
+
+/// 导出曲库元数据为 JSON 或 CSV，`include_stream` 为 false 时只导出本地歌曲。
+/// 返回导出的歌曲数量
+#[tauri::command]
+pub fn db_export_library(
+    db: State<'_, DbState>,
+    format: LibraryExportFormat,
+    path: String,
+    include_stream: Option<bool>,
+) -> Result<usize, String> {
+    let mut songs = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        db::songs::get_all_songs(&conn).map_err(|e| e.to_string())?
+    };
+    if !include_stream.unwrap_or(false) {
+        songs.retain(|song| song.source_type == "local");
+    }
+    if songs.is_empty() {
+        return Err("没有可导出的歌曲".to_string());
+    }
+
+    let content = library_export::export_library(&songs, format)?;
+    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    Ok(songs.len())
+}
+
+/// Import stream server configs exported by `db_export_stream_servers`.
+/// Existing servers with the same URL and username are updated.
+#[tauri::command]
+pub fn db_import_stream_servers(
+    db: State<'_, DbState>,
+    path: String,
+    password: Option<String>,
+) -> Result<usize, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let servers = server_backup::import_servers(&content, password.as_deref())?;
+
+    let mut conn = db.write().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for server in &servers {
+        db::servers::save_stream_server(&tx, server).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(servers.len())
+}
+
 /// Save scan configuration
 #[tauri::command]
 pub fn db_save_scan_config(db: State<'_, DbState>, config: ScanConfig) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::servers::save_scan_config(&conn, &config).map_err(|e| e.to_string())
 }
 
 /// Get scan configuration
 #[tauri::command]
 pub fn db_get_scan_config(db: State<'_, DbState>) -> Result<Option<ScanConfig>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
     db::servers::get_scan_config(&conn).map_err(|e| e.to_string())
 }
 
 /// Clear scan configuration
 #[tauri::command]
 pub fn db_clear_scan_config(db: State<'_, DbState>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     db::servers::clear_scan_config(&conn).map_err(|e| e.to_string())
 }
 
@@ -175,7 +394,7 @@ pub fn db_migrate_from_localstorage(
     db: State<'_, DbState>,
     data: MigrationData,
 ) -> Result<usize, String> {
-    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.write().map_err(|e| e.to_string())?;
 
     // Check if we have any existing songs
     let existing_count = db::songs::get_song_count(&conn).map_err(|e| e.to_string())?;
@@ -198,12 +417,19 @@ pub fn db_migrate_from_localstorage(
             title: song.title,
             artist: song.artist,
             album: song.album,
+            album_artist: None,
+            composer: None,
+            year: None,
+            genre: None,
+            track_no: None,
+            disc_no: None,
             duration: song.duration,
             file_path: file_path.clone(),
             file_size: song.file_size.unwrap_or(0),
             is_hr: song.is_hr,
             is_sq: song.is_sq,
             cover_hash: None,
+            content_hash: None,
             server_song_id: None,
             stream_info: if is_stream { Some(file_path) } else { None },
             file_modified: None,
@@ -212,6 +438,13 @@ pub fn db_migrate_from_localstorage(
             sample_rate: None,
             bitrate: None,
             channels: None,
+            replay_gain: None,
+            replay_peak: None,
+            album_gain: None,
+            album_peak: None,
+            pictures: Vec::new(),
+            cue: None,
+            chapters: Vec::new(),
         };
 
         if is_stream {
@@ -269,7 +502,7 @@ pub struct LibraryStats {
 
 #[tauri::command]
 pub fn db_get_library_stats(db: State<'_, DbState>) -> Result<LibraryStats, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.read().map_err(|e| e.to_string())?;
 
     let total_songs = db::songs::get_song_count(&conn).map_err(|e| e.to_string())?;
     let local_songs = db::songs::get_song_count_by_source(&conn, "local").map_err(|e| e.to_string())?;
@@ -287,6 +520,453 @@ pub fn db_get_library_stats(db: State<'_, DbState>) -> Result<LibraryStats, Stri
     })
 }
 
+/// 整理数据库：截断 WAL、VACUUM 回收空间并 ANALYZE 更新统计信息。
+/// 返回整理前后的文件大小（含 WAL）
+#[tauri::command]
+pub fn db_optimize(db: State<'_, DbState>) -> Result<OptimizeResult, String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    // Keep the reader idle so the WAL can be truncated
+    let _reader = db.read().map_err(|e| e.to_string())?;
+    let result = db::maintenance::optimize(&conn).map_err(|e| format!("数据库整理失败: {}", e))?;
+    eprintln!(
+        "Database optimized: {} -> {} bytes in {} ms",
+        result.size_before, result.size_after, result.duration_ms
+    );
+    Ok(result)
+}
+
+// ============ Playlist Commands ============
+
+/// Get all playlists
+#[tauri::command]
+pub fn db_get_playlists(db: State<'_, DbState>) -> Result<Vec<DbPlaylist>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::playlists::get_playlists(&conn).map_err(|e| e.to_string())
+}
+
+/// Get the songs of a playlist in order (entries whose song is gone are skipped)
+#[tauri::command]
+pub fn db_get_playlist_songs(db: State<'_, DbState>, playlist_id: String) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let ids = db::playlists::get_playlist_song_ids(&conn, &playlist_id).map_err(|e| e.to_string())?;
+    db::songs::get_songs_by_ids(&conn, &ids).map_err(|e| e.to_string())
+}
+
+/// Run several mutations atomically (e.g. create playlist + add songs + set cover)
+#[tauri::command]
+pub fn db_batch(db: State<'_, DbState>, ops: Vec<DbBatchOp>) -> Result<Vec<DbBatchResult>, String> {
+    let mut conn = db.write().map_err(|e| e.to_string())?;
+    db::batch::execute_batch(&mut conn, &ops)
+        .map_err(|(index, e)| format!("批量操作第 {} 项失败，已全部回滚: {}", index + 1, e))
+}
+
+// ============ Custom Metadata Commands ============
+
+/// Get all custom metadata (BPM, key, third-party IDs, ...) of a song
+#[tauri::command]
+pub fn db_get_song_extra(
+    db: State<'_, DbState>,
+    song_id: String,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::extra::get_song_extra(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// Set one custom metadata key of a song; a null value removes the key
+#[tauri::command]
+pub fn db_set_song_extra(
+    db: State<'_, DbState>,
+    song_id: String,
+    key: String,
+    value: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let key = key.trim();
+    if key.is_empty() || key.len() > 64 {
+        return Err("无效的字段名".to_string());
+    }
+    let conn = db.write().map_err(|e| e.to_string())?;
+    db::extra::set_song_extra(&conn, &song_id, key, value.as_ref()).map_err(|e| e.to_string())
+}
+
+// ============ Cue Point Commands ============
+
+/// Cue-in / cue-out points (seconds) of a song
+#[tauri::command]
+pub fn db_get_cue_points(db: State<'_, DbState>, song_id: String) -> Result<CuePoints, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::extra::get_cue_points(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// 设置歌曲评分：1-5 星，0 为取消评分
+#[tauri::command]
+pub fn db_set_rating(db: State<'_, DbState>, song_id: String, rating: u8) -> Result<(), String> {
+    if rating > 5 {
+        return Err("评分需在 0-5 之间".to_string());
+    }
+    let conn = db.write().map_err(|e| e.to_string())?;
+    if db::songs::set_rating(&conn, &song_id, rating).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("歌曲不存在: {}", song_id))
+    }
+}
+
+/// Set where playback of a song starts and ends; a null point is cleared.
+/// Applies from the next time the song is played.
+#[tauri::command]
+pub fn db_set_cue_points(
+    db: State<'_, DbState>,
+    song_id: String,
+    cue_in: Option<f64>,
+    cue_out: Option<f64>,
+) -> Result<(), String> {
+    let cue_in = cue_in.filter(|secs| *secs > 0.0);
+    if [cue_in, cue_out].iter().flatten().any(|secs| !secs.is_finite() || *secs < 0.0) {
+        return Err("无效的时间点".to_string());
+    }
+    if let (Some(cue_in), Some(cue_out)) = (cue_in, cue_out) {
+        if cue_out <= cue_in {
+            return Err("结束点必须晚于开始点".to_string());
+        }
+    }
+
+    let conn = db.write().map_err(|e| e.to_string())?;
+    db::extra::set_cue_points(&conn, &song_id, &CuePoints { cue_in, cue_out }).map_err(|e| e.to_string())
+}
+
+// ============ Bookmark Commands ============
+
+/// Saved resume position (seconds) of a long track
+#[tauri::command]
+pub fn db_get_resume_position(db: State<'_, DbState>, song_id: String) -> Result<Option<f64>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::extra::get_resume_position(&conn, &song_id).map_err(|e| e.to_string())
+}
+
+/// Save the resume position of a long track; none (or 0) clears it.
+/// Subsonic songs also get a server bookmark so other clients can resume.
+#[tauri::command]
+pub async fn db_save_resume_position(
+    db: State<'_, DbState>,
+    song_id: String,
+    position_secs: Option<f64>,
+) -> Result<(), String> {
+    let position_secs = position_secs.filter(|p| p.is_finite() && *p > 0.0);
+
+    let server = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::extra::set_resume_position(&conn, &song_id, position_secs).map_err(|e| e.to_string())?;
+        match db::songs::get_server_song(&conn, &song_id).map_err(|e| e.to_string())? {
+            Some((server_id, server_song_id)) => db::servers::get_stream_server(&conn, &server_id)
+                .map_err(|e| e.to_string())?
+                .filter(|s| s.enabled)
+                .map(|s| (s, server_song_id)),
+            None => None,
+        }
+    };
+
+    let Some((server, server_song_id)) = server else { return Ok(()) };
+    let config = server_config(&server);
+    if !config.is_subsonic() {
+        return Ok(());
+    }
+
+    // The local position is already saved; a failed bookmark is picked up next time
+    let result = match position_secs {
+        Some(p) => subsonic::create_bookmark(&config, &server_song_id, (p * 1000.0) as i64).await,
+        None => subsonic::delete_bookmark(&config, &server_song_id).await,
+    };
+    if let Err(e) = result {
+        eprintln!("Bookmark sync failed for {}: {}", song_id, e);
+    }
+    Ok(())
+}
+
+/// Pull bookmarks from all enabled Subsonic servers into the local resume
+/// positions. Returns the number of songs updated.
+#[tauri::command]
+pub async fn sync_bookmarks(db: State<'_, DbState>) -> Result<usize, String> {
+    let servers = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::servers::get_stream_servers(&conn).map_err(|e| e.to_string())?
+    };
+
+    let mut updated = 0;
+    for server in servers.into_iter().filter(|s| s.enabled) {
+        let config = server_config(&server);
+        if !config.is_subsonic() {
+            continue;
+        }
+
+        let bookmarks = match subsonic::fetch_bookmarks(&config).await {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => {
+                eprintln!("getBookmarks failed for {}: {}", server.server_name, e);
+                continue;
+            }
+        };
+
+        let conn = db.write().map_err(|e| e.to_string())?;
+        for (server_song_id, position_ms) in bookmarks {
+            let ids = db::queue::map_server_songs(&conn, &server.id, &[server_song_id]).map_err(|e| e.to_string())?;
+            if let Some(song_id) = ids.first() {
+                let position = (position_ms > 0).then(|| position_ms as f64 / 1000.0);
+                db::extra::set_resume_position(&conn, song_id, position).map_err(|e| e.to_string())?;
+                updated += 1;
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+// ============ Settings Commands ============
+
+/// Get one setting (stored value or default)
+#[tauri::command]
+pub fn settings_get(db: State<'_, DbState>, key: String) -> Result<Setting, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::settings::get_setting(&conn, &key)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("未知的设置项: {}", key))
+}
+
+/// Set one setting
+#[tauri::command]
+pub fn settings_set(
+    db: State<'_, DbState>,
+    downloads: State<'_, DownloadManagerState>,
+    telemetry: State<'_, TelemetryState>,
+    setting: Setting,
+) -> Result<(), String> {
+    setting.validate()?;
+    {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::settings::set_setting(&conn, &setting).map_err(|e| e.to_string())?;
+    }
+    // 下载限速/并发数立即生效
+    match &setting {
+        Setting::Downloads(settings) => downloads.0.apply_settings(settings),
+        // 关闭统计时立即丢弃本地计数
+        Setting::Telemetry(settings) => telemetry.0.set_enabled(settings.enabled),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// All settings with defaults filled in
+#[tauri::command]
+pub fn settings_list(db: State<'_, DbState>) -> Result<Vec<Setting>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::settings::list_settings(&conn).map_err(|e| e.to_string())
+}
+
+/// Reset one setting to its default and return it
+#[tauri::command]
+pub fn settings_reset(
+    db: State<'_, DbState>,
+    telemetry: State<'_, TelemetryState>,
+    key: String,
+) -> Result<Setting, String> {
+    let setting = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::settings::reset_setting(&conn, &key).map_err(|e| e.to_string())?;
+        db::settings::get_setting(&conn, &key)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("未知的设置项: {}", key))?
+    };
+    if let Setting::Telemetry(settings) = &setting {
+        telemetry.0.set_enabled(settings.enabled);
+    }
+    Ok(setting)
+}
+
+// ============ EQ Preset Commands ============
+
+/// All saved equalizer presets
+#[tauri::command]
+pub fn db_get_eq_presets(db: State<'_, DbState>) -> Result<Vec<DbEqPreset>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::eq_presets::get_eq_presets(&conn).map_err(|e| e.to_string())
+}
+
+/// Save an equalizer preset, replacing one with the same name
+#[tauri::command]
+pub fn db_save_eq_preset(db: State<'_, DbState>, mut preset: DbEqPreset) -> Result<(), String> {
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+        return Err("预设名称不能为空".to_string());
+    }
+    if preset.gains.iter().any(|g| !(-12.0..=12.0).contains(g)) {
+        return Err("均衡器增益必须在 -12 到 12 dB 之间".to_string());
+    }
+    if !(-12.0..=12.0).contains(&preset.preamp) {
+        return Err("前置增益必须在 -12 到 12 dB 之间".to_string());
+    }
+    let conn = db.write().map_err(|e| e.to_string())?;
+    db::eq_presets::save_eq_preset(&conn, &preset).map_err(|e| e.to_string())
+}
+
+/// Delete an equalizer preset by name
+#[tauri::command]
+pub fn db_delete_eq_preset(db: State<'_, DbState>, name: String) -> Result<(), String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    if db::eq_presets::delete_eq_preset(&conn, &name).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("预设不存在: {}", name))
+    }
+}
+
+// ============ Play History Commands ============
+
+/// Record that a song was listened to for `listened_secs` seconds
+#[tauri::command]
+pub fn db_record_listen(
+    db: State<'_, DbState>,
+    song_id: String,
+    listened_secs: f64,
+) -> Result<(), String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    if db::history::record_listen(&conn, &song_id, listened_secs).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("歌曲不存在: {}", song_id))
+    }
+}
+
+/// Listening time per day/week and top artists/albums/songs for a range
+#[tauri::command]
+pub fn db_get_listening_stats(
+    db: State<'_, DbState>,
+    range: ListeningRange,
+) -> Result<ListeningStats, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::history::get_listening_stats(&conn, range).map_err(|e| e.to_string())
+}
+
+/// Count a play of a song: bumps its play count and last played time
+#[tauri::command]
+pub fn db_record_play(db: State<'_, DbState>, song_id: String) -> Result<(), String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    if db::history::record_play(&conn, &song_id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("歌曲不存在: {}", song_id))
+    }
+}
+
+/// Songs with the most plays (default 50)
+#[tauri::command]
+pub fn db_get_most_played(db: State<'_, DbState>, limit: Option<u32>) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::history::get_most_played(&conn, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+/// Most recently played songs (default 50)
+#[tauri::command]
+pub fn db_get_recently_played(db: State<'_, DbState>, limit: Option<u32>) -> Result<Vec<DbSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::history::get_recently_played(&conn, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+// ============ Queue Generation Commands ============
+
+/// Song IDs of an album in playing order
+#[tauri::command]
+pub fn queue_album(db: State<'_, DbState>, album_id: String) -> Result<Vec<String>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let ids = db::queue::get_album_queue(&conn, &album_id).map_err(|e| e.to_string())?;
+    if ids.is_empty() {
+        return Err(format!("专辑不存在: {}", album_id));
+    }
+    Ok(ids)
+}
+
+/// Song IDs of all songs by an artist, shuffled
+#[tauri::command]
+pub fn queue_artist_shuffle(db: State<'_, DbState>, artist_id: String) -> Result<Vec<String>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let artist = db::queue::resolve_artist_name(&conn, &artist_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("艺术家不存在: {}", artist_id))?;
+    db::queue::get_artist_shuffle_queue(&conn, &artist).map_err(|e| e.to_string())
+}
+
+/// Song IDs matching a smart rule
+#[tauri::command]
+pub fn queue_smart(db: State<'_, DbState>, rule: SmartQueueRule) -> Result<Vec<String>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::queue::get_smart_queue(&conn, &rule).map_err(|e| e.to_string())
+}
+
+/// `song_ids` shuffled with the same artist spread out (and albums alternating);
+/// `favor_less_played` plays rarely played songs earlier
+#[tauri::command]
+pub fn queue_smart_shuffle(
+    db: State<'_, DbState>,
+    song_ids: Vec<String>,
+    favor_less_played: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::queue::smart_shuffle(&conn, &song_ids, favor_less_played.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// Songs per radio batch
+const RADIO_BATCH: u32 = 20;
+
+/// Next batch of a song radio: server recommendations (Subsonic
+/// getSimilarSongs2) for stream songs, topped up with the local
+/// artist/genre heuristic. The frontend calls this again with the queue
+/// so far in `exclude` as the radio nears its end.
+#[tauri::command]
+pub async fn queue_radio(
+    db: State<'_, DbState>,
+    song_id: String,
+    exclude: Vec<String>,
+    count: Option<u32>,
+) -> Result<Vec<String>, String> {
+    let count = count.unwrap_or(RADIO_BATCH).clamp(1, 100) as usize;
+    let mut exclude: HashSet<String> = exclude.into_iter().collect();
+
+    let (seed, server) = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        let seed = db::queue::get_radio_seed(&conn, &song_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("歌曲不存在: {}", song_id))?;
+        let server = match seed.server_id {
+            Some(ref server_id) => db::servers::get_stream_server(&conn, server_id).map_err(|e| e.to_string())?,
+            None => None,
+        };
+        (seed, server)
+    };
+
+    let mut ids = Vec::new();
+    if let (Some(server), Some(server_song_id)) = (server, seed.server_song_id.as_deref()) {
+        let config = server_config(&server);
+        if config.is_subsonic() {
+            match subsonic::fetch_similar_songs(&config, server_song_id, count as u32 * 2).await {
+                Ok(similar) => {
+                    let conn = db.read().map_err(|e| e.to_string())?;
+                    ids = db::queue::map_server_songs(&conn, &server.id, &similar).map_err(|e| e.to_string())?;
+                    ids.retain(|id| *id != seed.song_id && exclude.insert(id.clone()));
+                    ids.truncate(count);
+                }
+                Err(e) => eprintln!("getSimilarSongs2 failed, falling back to local radio: {}", e),
+            }
+        }
+    }
+
+    if ids.len() < count {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        let more = db::queue::get_radio_queue(&conn, &seed, &exclude, count - ids.len())
+            .map_err(|e| e.to_string())?;
+        ids.extend(more);
+    }
+
+    Ok(ids)
+}
+
 // ============ Cover Cache Commands ============
 
 use crate::utils::cover::{CoverCache, CoverSize};
@@ -369,12 +1049,16 @@ pub fn cleanup_orphaned_covers(
     db: State<'_, DbState>,
     cover_cache: State<'_, CoverCacheState>,
 ) -> Result<usize, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
     let cache = cover_cache.0.lock().map_err(|e| e.to_string())?;
 
-    // Get all cover hashes from DB
+    // Get all cover hashes from DB (main covers and typed embedded pictures)
     let mut stmt = conn
-        .prepare("SELECT DISTINCT cover_hash FROM songs WHERE cover_hash IS NOT NULL")
+        .prepare(
+            "SELECT cover_hash FROM songs WHERE cover_hash IS NOT NULL
+             UNION
+             SELECT cover_hash FROM song_pictures",
+        )
         .map_err(|e| e.to_string())?;
 
     let valid_hashes: Vec<String> = stmt
@@ -395,10 +1079,10 @@ pub fn clear_cover_cache(
     cache.clear_all()
 }
 
-/// Clean up songs whose files no longer exist
+/// Mark songs whose files no longer exist as missing (they can be restored or purged later)
 #[tauri::command]
 pub fn cleanup_missing_songs(db: State<'_, DbState>) -> Result<usize, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.write().map_err(|e| e.to_string())?;
 
     // Get all local songs
     let songs = db::songs::get_all_songs(&conn).map_err(|e| e.to_string())?;
@@ -409,14 +1093,44 @@ pub fn cleanup_missing_songs(db: State<'_, DbState>) -> Result<usize, String> {
         .map(|s| s.id.clone())
         .collect();
 
-    let count = missing_ids.len();
+    db::songs::mark_songs_missing(&conn, &missing_ids).map_err(|e| e.to_string())
+}
+
+/// 获取已丢失（文件被删除或移走）的歌曲，最近丢失的在前
+#[tauri::command]
+pub fn db_get_missing_songs(db: State<'_, DbState>) -> Result<Vec<MissingSong>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::songs::get_missing_songs(&conn).map_err(|e| e.to_string())
+}
 
-    for id in missing_ids {
-        conn.execute("DELETE FROM songs WHERE id = ?1", [&id])
-            .map_err(|e| e.to_string())?;
+/// 恢复一首已丢失的歌曲；本地歌曲要求文件已重新存在
+#[tauri::command]
+pub fn db_restore_song(db: State<'_, DbState>, song_id: String) -> Result<(), String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    let song = db::songs::get_missing_songs(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|m| m.song.id == song_id)
+        .ok_or_else(|| "歌曲不存在或未被标记为丢失".to_string())?;
+
+    if song.song.source_type == "local" && !std::path::Path::new(&song.song.file_path).exists() {
+        return Err("文件不存在，无法恢复".to_string());
     }
 
-    Ok(count)
+    db::songs::restore_song(&conn, &song_id).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 永久删除丢失超过 `older_than_days` 天的歌曲（默认 30 天，0 表示全部），返回删除数量
+#[tauri::command]
+pub fn db_purge_missing(db: State<'_, DbState>, older_than_days: Option<u32>) -> Result<usize, String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    let purged = db::songs::purge_missing_songs(&conn, older_than_days.unwrap_or(30))
+        .map_err(|e| e.to_string())?;
+    if purged > 0 {
+        eprintln!("Purged {} missing songs", purged);
+    }
+    Ok(purged)
 }
 
 // ============ File Watcher Commands ============
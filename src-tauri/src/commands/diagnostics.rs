@@ -0,0 +1,111 @@
+//! Diagnostics report for bug reports
+
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::audio_engine::output::{default_device_info, OutputDeviceInfo};
+use crate::commands::scan::last_scan;
+use crate::commands::{db_get_library_stats, get_cover_cache_stats, CoverCacheState, CoverCacheStats, LibraryStats};
+use crate::db::{self, DbState};
+use crate::models::LastScan;
+
+/// File watcher status
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherStatus {
+    pub active: bool,
+    pub watched_dirs: Vec<String>,
+}
+
+/// Everything a bug report needs in one payload
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub schema_version: i32,
+    pub os: String,
+    pub arch: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_path: Option<String>,
+    /// Database file plus its WAL
+    pub db_size_bytes: u64,
+    pub library: LibraryStats,
+    pub stream_servers: usize,
+    pub enabled_stream_servers: usize,
+    pub cover_cache: CoverCacheStats,
+    pub watcher: WatcherStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_device: Option<OutputDeviceInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_error: Option<String>,
+    /// Most recent scan since the app started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_scan: Option<LastScan>,
+    /// Last scan time stored in the scan config (survives restarts)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_scan_at: Option<i64>,
+}
+
+fn file_size(path: &str) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn watcher_status(#[allow(unused_variables)] app: &AppHandle) -> WatcherStatus {
+    #[cfg(desktop)]
+    {
+        let (active, watched_dirs) = crate::watcher::desktop::watch_status(app);
+        WatcherStatus { active, watched_dirs }
+    }
+    #[cfg(not(desktop))]
+    {
+        WatcherStatus { active: false, watched_dirs: Vec::new() }
+    }
+}
+
+/// Collect app, database, cache, watcher, audio and scan information
+#[tauri::command]
+pub fn get_diagnostics(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    cover_cache: State<'_, CoverCacheState>,
+) -> Result<Diagnostics, String> {
+    let library = db_get_library_stats(db.clone())?;
+    let cover_cache = get_cover_cache_stats(cover_cache)?;
+
+    let (schema_version, db_path, servers, scan_config) = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        (
+            db::init::schema_version(&conn),
+            conn.path().filter(|p| !p.is_empty()).map(str::to_string),
+            db::servers::get_stream_servers(&conn).map_err(|e| e.to_string())?,
+            db::servers::get_scan_config(&conn).map_err(|e| e.to_string())?,
+        )
+    };
+    let db_size_bytes = db_path
+        .as_deref()
+        .map(|p| file_size(p) + file_size(&format!("{}-wal", p)))
+        .unwrap_or(0);
+
+    let (audio_device, audio_error) = match default_device_info() {
+        Ok(info) => (Some(info), None),
+        Err(e) => (None, Some(e.details)),
+    };
+
+    Ok(Diagnostics {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        db_path,
+        db_size_bytes,
+        library,
+        stream_servers: servers.len(),
+        enabled_stream_servers: servers.iter().filter(|s| s.enabled).count(),
+        cover_cache,
+        watcher: watcher_status(&app),
+        audio_device,
+        audio_error,
+        last_scan: last_scan(),
+        last_scan_at: scan_config.and_then(|c| c.last_scan_at),
+    })
+}
@@ -4,6 +4,16 @@ pub mod db;
 pub mod scan;
 pub mod audio;
 pub mod online_lyrics;
+pub mod offline;
+pub mod diagnostics;
+pub mod telemetry;
+pub mod providers;
+pub mod shell;
+pub mod play_queue;
+pub mod dynamics;
+pub mod analysis;
+pub mod organize;
+pub mod tags;
 
 pub use streaming::*;
 pub use scanner::*;
@@ -11,3 +21,13 @@ pub use db::*;
 pub use scan::*;
 pub use audio::*;
 pub use online_lyrics::*;
+pub use offline::*;
+pub use diagnostics::*;
+pub use telemetry::*;
+pub use providers::*;
+pub use shell::*;
+pub use play_queue::*;
+pub use dynamics::*;
+pub use analysis::*;
+pub use organize::*;
+pub use tags::*;
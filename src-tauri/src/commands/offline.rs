@@ -0,0 +1,64 @@
+//! Offline download commands
+
+use tauri::State;
+
+use crate::db::{self, DbState, OfflineDownload};
+use crate::downloads::{DownloadManagerState, OfflineSyncResult};
+
+/// 将流媒体歌曲加入离线下载队列，返回新加入的数量
+#[tauri::command]
+pub fn offline_enqueue(downloads: State<'_, DownloadManagerState>, song_ids: Vec<String>) -> Result<usize, String> {
+    downloads.0.enqueue(&song_ids)
+}
+
+/// 离线下载单首歌曲：播放缓存里已有完整文件时直接使用，否则加入下载队列
+#[tauri::command]
+pub fn download_song_for_offline(downloads: State<'_, DownloadManagerState>, song_id: String) -> Result<(), String> {
+    downloads.0.download_song(&song_id)
+}
+
+/// 下载队列（包括已完成的歌曲）
+#[tauri::command]
+pub fn offline_list(db: State<'_, DbState>) -> Result<Vec<OfflineDownload>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    db::offline::get_downloads(&conn).map_err(|e| e.to_string())
+}
+
+/// 暂停下载，已下载的部分保留
+#[tauri::command]
+pub fn offline_pause(downloads: State<'_, DownloadManagerState>, song_id: String) -> Result<(), String> {
+    downloads.0.pause(&song_id)
+}
+
+/// 继续已暂停或失败的下载
+#[tauri::command]
+pub fn offline_resume(downloads: State<'_, DownloadManagerState>, song_id: String) -> Result<(), String> {
+    downloads.0.resume(&song_id)
+}
+
+/// 移出队列并删除离线文件
+#[tauri::command]
+pub fn offline_remove(downloads: State<'_, DownloadManagerState>, song_id: String) -> Result<(), String> {
+    downloads.0.remove(&song_id)
+}
+
+/// 已下载歌曲的本地文件路径，文件丢失时返回 None
+#[tauri::command]
+pub fn offline_get_path(db: State<'_, DbState>, song_id: String) -> Result<Option<String>, String> {
+    let conn = db.read().map_err(|e| e.to_string())?;
+    let path = db::offline::get_offline_path(&conn, &song_id).map_err(|e| e.to_string())?;
+    Ok(path.filter(|p| std::path::Path::new(p).is_file()))
+}
+
+/// 按当前离线同步规则立即同步一次（规则未启用时也会执行）
+#[tauri::command]
+pub fn offline_sync_now(
+    db: State<'_, DbState>,
+    downloads: State<'_, DownloadManagerState>,
+) -> Result<OfflineSyncResult, String> {
+    let rules = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        db::settings::offline_sync_rules(&conn).map_err(|e| e.to_string())?
+    };
+    downloads.0.sync(&rules)
+}
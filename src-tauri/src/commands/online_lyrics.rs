@@ -8,6 +8,13 @@ use serde_json::{json, Value};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io::Read;
+use tauri::{AppHandle, State};
+
+use crate::db::{self, DbState};
+use crate::error::{CommandError, ErrorCode};
+use crate::providers::{ProviderKind, ProviderRegistry, ProviderRegistryState};
+use crate::telemetry;
+use crate::utils::lyrics::format_lrc_timestamp;
 
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
 const KUGOU_KRC_KEY: [u8; 16] = [0x40, 0x47, 0x61, 0x77, 0x5e, 0x32, 0x74, 0x47, 0x51, 0x36, 0x31, 0x2d, 0xce, 0xd2, 0x6e, 0x69];
@@ -27,6 +34,12 @@ pub struct OnlineLyricSearchRequest {
     pub providers: Option<Vec<String>>,
     #[serde(default)]
     pub limit_per_source: Option<usize>,
+    /// Library song ID, used to cache providers that had no result
+    #[serde(default)]
+    pub song_id: Option<String>,
+    /// Ignore cached "no result" entries (manual search)
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +58,9 @@ pub struct OnlineLyricCandidate {
     pub netease_song_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kugou_song_hash: Option<String>,
+    /// Song ID for plugin providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_song_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cover_url: Option<String>,
 }
@@ -59,24 +75,61 @@ pub struct OnlineLyricFetchRequest {
     pub netease_song_id: Option<String>,
     #[serde(default)]
     pub kugou_song_hash: Option<String>,
+    /// Song ID for plugin providers
+    #[serde(default)]
+    pub provider_song_id: Option<String>,
+    /// Merge translations into `lyric` as `original┃translation` (legacy format)
+    #[serde(default)]
+    pub merge_translation: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OnlineLyricFetchResult {
+    /// LRC of the original lyric (translations merged in only with `mergeTranslation`)
     pub lyric: String,
     pub format: String,
     pub provider: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw: Option<String>,
+    /// Translation LRC, if the provider has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+    /// Original lines in time order
+    pub lines: Vec<TimedLyricLine>,
+    /// Translation for each entry of `lines` (same length); empty without a translation
+    pub translations: Vec<Option<String>>,
 }
 
-#[tauri::command]
-pub async fn search_online_lyrics(request: OnlineLyricSearchRequest) -> Result<Vec<OnlineLyricCandidate>, String> {
-    let client = Client::builder()
-        .build()
-        .map_err(|error| format!("初始化网络客户端失败：{error}"))?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedLyricLine {
+    pub time_ms: i64,
+    pub text: String,
+}
+
+impl OnlineLyricFetchResult {
+    pub(crate) fn new(provider: &str, format: &str, lyric: String, translation: Option<&str>, raw: Option<String>) -> Self {
+        let (lines, translations) = align_lrc_translation(&lyric, translation);
+        Self {
+            lyric,
+            format: format.to_string(),
+            provider: provider.to_string(),
+            raw,
+            translation: translation.map(str::to_string),
+            lines,
+            translations,
+        }
+    }
+}
 
+#[tauri::command]
+pub async fn search_online_lyrics(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    registry: State<'_, ProviderRegistryState>,
+    request: OnlineLyricSearchRequest,
+) -> Result<Vec<OnlineLyricCandidate>, CommandError> {
     let query = if let Some(keyword) = request.keyword.as_ref() {
         let trimmed = keyword.trim();
         if !trimmed.is_empty() {
@@ -92,29 +145,43 @@ pub async fn search_online_lyrics(request: OnlineLyricSearchRequest) -> Result<V
         return Ok(Vec::new());
     }
 
-    let providers = normalize_providers(request.providers.clone());
+    telemetry::feature(&app, "lyrics.search");
+    let mut providers = normalize_providers(&registry.0, request.providers.clone());
     let limit = request.limit_per_source.unwrap_or(15).clamp(1, 30);
 
+    // Skip providers that recently found nothing for this song
+    let cache_key = lyric_cache_key(&request, &query);
+    if !request.bypass_cache {
+        let conn = db.read()?;
+        let negative = db::lyrics::get_negative_providers(&conn, &cache_key)?;
+        providers.retain(|provider| !negative.contains(provider));
+    }
+
     let mut candidates: Vec<OnlineLyricCandidate> = Vec::new();
+    let mut empty_providers: Vec<String> = Vec::new();
 
-    if providers.iter().any(|provider| provider == "kugou") {
-        match search_kugou(&client, &request, &query, limit).await {
-            Ok(mut list) => candidates.append(&mut list),
-            Err(error) => eprintln!("[lyrics][kugou][search] {error}"),
+    for provider in registry.0.with_kind(ProviderKind::Lyrics) {
+        let id = provider.info().id.as_str();
+        if !providers.iter().any(|p| p == id) {
+            continue;
         }
-    }
-
-    if providers.iter().any(|provider| provider == "netease") {
-        match search_netease(&client, &request, &query, limit).await {
+        match provider.search_lyrics(&request, &query, limit).await {
+            Ok(list) if list.is_empty() => empty_providers.push(id.to_string()),
             Ok(mut list) => candidates.append(&mut list),
-            Err(error) => eprintln!("[lyrics][netease][search] {error}"),
+            Err(error) => {
+                eprintln!("[lyrics][{id}][search] {error}");
+                telemetry::error(&app, "lyrics", &error);
+            }
         }
     }
 
-    if providers.iter().any(|provider| provider == "qq") {
-        match search_qq(&client, &request, &query, limit).await {
-            Ok(mut list) => candidates.append(&mut list),
-            Err(error) => eprintln!("[lyrics][qq][search] {error}"),
+    // Network errors are not cached, only successful empty searches
+    if !empty_providers.is_empty() {
+        let conn = db.write()?;
+        for provider in empty_providers {
+            if let Err(error) = db::lyrics::mark_lyrics_not_found(&conn, &cache_key, &provider) {
+                eprintln!("[lyrics][{provider}][cache] {error}");
+            }
         }
     }
 
@@ -131,43 +198,41 @@ pub async fn search_online_lyrics(request: OnlineLyricSearchRequest) -> Result<V
     Ok(candidates)
 }
 
+/// Forget cached "no result" lookups for a song (or for everything)
 #[tauri::command]
-pub async fn fetch_online_lyric(request: OnlineLyricFetchRequest) -> Result<Option<OnlineLyricFetchResult>, String> {
-    let client = Client::builder()
-        .build()
-        .map_err(|error| format!("初始化网络客户端失败：{error}"))?;
+pub fn clear_online_lyrics_cache(db: State<'_, DbState>, song_id: Option<String>) -> Result<usize, CommandError> {
+    let conn = db.write()?;
+    let key = song_id.map(|id| format!("song:{id}"));
+    db::lyrics::clear_lyrics_cache(&conn, key.as_deref()).map_err(CommandError::from)
+}
 
+#[tauri::command]
+pub async fn fetch_online_lyric(
+    registry: State<'_, ProviderRegistryState>,
+    request: OnlineLyricFetchRequest,
+) -> Result<Option<OnlineLyricFetchResult>, CommandError> {
     let source = request.source.trim().to_lowercase();
-    if source == "qq" {
-        if let Some(song_id) = request.qq_song_id {
-            return fetch_qq_lyric(&client, song_id).await;
-        }
-        return Ok(None);
-    }
-
-    if source == "kugou" {
-        if let Some(song_hash) = request.kugou_song_hash.as_deref() {
-            return fetch_kugou_lyric(&client, song_hash).await;
-        }
-        return Ok(None);
-    }
-
-    if source == "netease" {
-        if let Some(song_id) = request.netease_song_id.as_deref() {
-            return fetch_netease_lyric(&client, song_id).await;
+    let provider = registry
+        .0
+        .get(&source)
+        .filter(|p| p.supports(ProviderKind::Lyrics))
+        .ok_or_else(|| CommandError::new(ErrorCode::UnsupportedSource).with("source", &request.source))?;
+    let result = provider.fetch_lyric(&request).await?;
+
+    Ok(result.map(|mut result| {
+        if request.merge_translation {
+            result.lyric = merge_lrc_translation(&result.lyric, result.translation.as_deref());
         }
-        return Ok(None);
-    }
-
-    Err(format!("不支持的歌词来源：{}", request.source))
+        result
+    }))
 }
 
-async fn search_qq(
+pub(crate) async fn search_qq(
     client: &Client,
     request: &OnlineLyricSearchRequest,
     query: &str,
     limit: usize,
-) -> Result<Vec<OnlineLyricCandidate>, String> {
+) -> Result<Vec<OnlineLyricCandidate>, CommandError> {
     let payload = json!({
         "comm": {
             "mina": 1,
@@ -192,14 +257,14 @@ async fn search_qq(
         .header("Referer", "https://y.qq.com/")
         .send()
         .await
-        .map_err(|error| format!("QQ 搜索请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "qq"))?;
 
     let bytes = response
         .bytes()
         .await
-        .map_err(|error| format!("QQ 搜索响应读取失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "qq"))?;
     let body = String::from_utf8_lossy(&bytes).to_string();
-    let data: Value = serde_json::from_str(&body).map_err(|error| format!("QQ 搜索响应解析失败：{error}"))?;
+    let data: Value = serde_json::from_str(&body).map_err(|error| CommandError::parse(error).with("provider", "qq"))?;
 
     let list = data
         .pointer("/req/data/body/item_song")
@@ -254,6 +319,7 @@ async fn search_qq(
             qq_song_id,
             netease_song_id: None,
             kugou_song_hash: None,
+            provider_song_id: None,
             cover_url,
         });
     }
@@ -261,12 +327,12 @@ async fn search_qq(
     Ok(result)
 }
 
-async fn search_kugou(
+pub(crate) async fn search_kugou(
     client: &Client,
     request: &OnlineLyricSearchRequest,
     query: &str,
     limit: usize,
-) -> Result<Vec<OnlineLyricCandidate>, String> {
+) -> Result<Vec<OnlineLyricCandidate>, CommandError> {
     let response = client
         .get("http://mobilecdnbj.kugou.com/api/v3/search/song")
         .query(&[
@@ -277,12 +343,12 @@ async fn search_kugou(
         .header("User-Agent", USER_AGENT)
         .send()
         .await
-        .map_err(|error| format!("酷狗搜索请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "kugou"))?;
 
     let data: Value = response
         .json()
         .await
-        .map_err(|error| format!("酷狗搜索响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "kugou"))?;
 
     let list = data
         .pointer("/data/info")
@@ -318,6 +384,7 @@ async fn search_kugou(
             qq_song_id: None,
             netease_song_id: None,
             kugou_song_hash: value_as_str(item.get("hash")),
+            provider_song_id: None,
             cover_url,
         });
     }
@@ -325,12 +392,12 @@ async fn search_kugou(
     Ok(result)
 }
 
-async fn search_netease(
+pub(crate) async fn search_netease(
     client: &Client,
     request: &OnlineLyricSearchRequest,
     query: &str,
     limit: usize,
-) -> Result<Vec<OnlineLyricCandidate>, String> {
+) -> Result<Vec<OnlineLyricCandidate>, CommandError> {
     let response = client
         .get("https://music.163.com/api/search/get/web")
         .query(&[
@@ -344,12 +411,12 @@ async fn search_netease(
         .header("Referer", "https://music.163.com/")
         .send()
         .await
-        .map_err(|error| format!("网易云搜索请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "netease"))?;
 
     let data: Value = response
         .json()
         .await
-        .map_err(|error| format!("网易云搜索响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "netease"))?;
 
     let list = data
         .pointer("/result/songs")
@@ -398,6 +465,7 @@ async fn search_netease(
             qq_song_id: None,
             netease_song_id: value_as_string(item.get("id")),
             kugou_song_hash: None,
+            provider_song_id: None,
             cover_url,
         });
     }
@@ -405,7 +473,7 @@ async fn search_netease(
     Ok(result)
 }
 
-async fn fetch_qq_lyric(client: &Client, song_id: i64) -> Result<Option<OnlineLyricFetchResult>, String> {
+pub(crate) async fn fetch_qq_lyric(client: &Client, song_id: i64) -> Result<Option<OnlineLyricFetchResult>, CommandError> {
     let response = client
         .get("https://c.y.qq.com/lyric/fcgi-bin/fcg_query_lyric_new.fcg")
         .query(&[
@@ -417,12 +485,12 @@ async fn fetch_qq_lyric(client: &Client, song_id: i64) -> Result<Option<OnlineLy
         .header("Referer", "https://y.qq.com/")
         .send()
         .await
-        .map_err(|error| format!("QQ 歌词请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "qq"))?;
 
     let data: Value = response
         .json()
         .await
-        .map_err(|error| format!("QQ 歌词响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "qq"))?;
 
     let lyric = data
         .get("lyric")
@@ -440,17 +508,16 @@ async fn fetch_qq_lyric(client: &Client, song_id: i64) -> Result<Option<OnlineLy
         .map(str::trim)
         .filter(|line| !line.is_empty());
 
-    let merged = merge_lrc_translation(lyric, translation);
-
-    Ok(Some(OnlineLyricFetchResult {
-        lyric: merged,
-        format: "lrc".to_string(),
-        provider: "qq".to_string(),
-        raw: Some(lyric.to_string()),
-    }))
+    Ok(Some(OnlineLyricFetchResult::new(
+        "qq",
+        "lrc",
+        lyric.to_string(),
+        translation,
+        Some(lyric.to_string()),
+    )))
 }
 
-async fn fetch_kugou_lyric(client: &Client, song_hash: &str) -> Result<Option<OnlineLyricFetchResult>, String> {
+pub(crate) async fn fetch_kugou_lyric(client: &Client, song_hash: &str) -> Result<Option<OnlineLyricFetchResult>, CommandError> {
     if song_hash.trim().is_empty() {
         return Ok(None);
     }
@@ -468,12 +535,12 @@ async fn fetch_kugou_lyric(client: &Client, song_hash: &str) -> Result<Option<On
         .header("User-Agent", USER_AGENT)
         .send()
         .await
-        .map_err(|error| format!("酷狗歌词搜索请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "kugou"))?;
 
     let search_data: Value = search_response
         .json()
         .await
-        .map_err(|error| format!("酷狗歌词搜索响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "kugou"))?;
 
     let first_candidate = search_data
         .get("candidates")
@@ -505,12 +572,12 @@ async fn fetch_kugou_lyric(client: &Client, song_hash: &str) -> Result<Option<On
         .header("User-Agent", USER_AGENT)
         .send()
         .await
-        .map_err(|error| format!("酷狗歌词下载请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "kugou"))?;
 
     let download_data: Value = download_response
         .json()
         .await
-        .map_err(|error| format!("酷狗歌词下载响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "kugou"))?;
 
     let encoded = download_data
         .get("content")
@@ -525,15 +592,16 @@ async fn fetch_kugou_lyric(client: &Client, song_hash: &str) -> Result<Option<On
     let raw_krc = decode_kugou_krc(encoded)?;
     let converted = normalize_timed_lyric_text(&raw_krc);
 
-    Ok(Some(OnlineLyricFetchResult {
-        lyric: if converted.trim().is_empty() { raw_krc.clone() } else { converted },
-        format: "krc".to_string(),
-        provider: "kugou".to_string(),
-        raw: Some(raw_krc),
-    }))
+    Ok(Some(OnlineLyricFetchResult::new(
+        "kugou",
+        "krc",
+        if converted.trim().is_empty() { raw_krc.clone() } else { converted },
+        None,
+        Some(raw_krc),
+    )))
 }
 
-async fn fetch_netease_lyric(client: &Client, song_id: &str) -> Result<Option<OnlineLyricFetchResult>, String> {
+pub(crate) async fn fetch_netease_lyric(client: &Client, song_id: &str) -> Result<Option<OnlineLyricFetchResult>, CommandError> {
     if song_id.trim().is_empty() {
         return Ok(None);
     }
@@ -550,12 +618,12 @@ async fn fetch_netease_lyric(client: &Client, song_id: &str) -> Result<Option<On
         .header("Referer", "https://music.163.com/")
         .send()
         .await
-        .map_err(|error| format!("网易云歌词请求失败：{error}"))?;
+        .map_err(|error| CommandError::request(error).with("provider", "netease"))?;
 
     let data: Value = response
         .json()
         .await
-        .map_err(|error| format!("网易云歌词响应解析失败：{error}"))?;
+        .map_err(|error| CommandError::parse(error).with("provider", "netease"))?;
 
     let translation = data
         .pointer("/tlyric/lyric")
@@ -571,14 +639,14 @@ async fn fetch_netease_lyric(client: &Client, song_id: &str) -> Result<Option<On
 
     if !yrc.is_empty() {
         let normalized = normalize_timed_lyric_text(yrc);
-        let merged = merge_lrc_translation(&normalized, translation);
-        if !merged.trim().is_empty() {
-            return Ok(Some(OnlineLyricFetchResult {
-                lyric: merged,
-                format: "yrc".to_string(),
-                provider: "netease".to_string(),
-                raw: Some(yrc.to_string()),
-            }));
+        if !normalized.trim().is_empty() {
+            return Ok(Some(OnlineLyricFetchResult::new(
+                "netease",
+                "yrc",
+                normalized,
+                translation,
+                Some(yrc.to_string()),
+            )));
         }
     }
 
@@ -592,23 +660,22 @@ async fn fetch_netease_lyric(client: &Client, song_id: &str) -> Result<Option<On
         return Ok(None);
     }
 
-    let merged = merge_lrc_translation(lrc, translation);
-
-    Ok(Some(OnlineLyricFetchResult {
-        lyric: merged,
-        format: "lrc".to_string(),
-        provider: "netease".to_string(),
-        raw: Some(lrc.to_string()),
-    }))
+    Ok(Some(OnlineLyricFetchResult::new(
+        "netease",
+        "lrc",
+        lrc.to_string(),
+        translation,
+        Some(lrc.to_string()),
+    )))
 }
 
-fn decode_kugou_krc(content: &str) -> Result<String, String> {
+fn decode_kugou_krc(content: &str) -> Result<String, CommandError> {
     let mut decoded = BASE64_STANDARD
         .decode(content)
-        .map_err(|error| format!("酷狗歌词解码失败（base64）：{error}"))?;
+        .map_err(|error| CommandError::decode(error).with("provider", "kugou"))?;
 
     if decoded.len() <= 4 {
-        return Err("酷狗歌词解码失败：内容长度异常".to_string());
+        return Err(CommandError::decode("内容长度异常").with("provider", "kugou"));
     }
 
     let mut payload = decoded.split_off(4);
@@ -620,7 +687,7 @@ fn decode_kugou_krc(content: &str) -> Result<String, String> {
     let mut output = String::new();
     decoder
         .read_to_string(&mut output)
-        .map_err(|error| format!("酷狗歌词解码失败（zlib）：{error}"))?;
+        .map_err(|error| CommandError::decode(error).with("provider", "kugou"))?;
 
     Ok(output)
 }
@@ -691,16 +758,11 @@ fn convert_ms_tag_line(line: &str, krc_word_tag_re: &Regex, yrc_word_tag_re: &Re
     Some(format!("{}{}", format_lrc_timestamp(start_ms), cleaned))
 }
 
-fn merge_lrc_translation(base: &str, translation: Option<&str>) -> String {
-    let Some(translation_text) = translation else {
-        return base.to_string();
-    };
-
-    let tag_re = Regex::new(r"\[(\d{1,2}):(\d{2})(?:[.:](\d{1,3}))?\]").unwrap();
-
+/// Translation text by normalized time tag (`mm:ss.xx`)
+fn build_translation_map(translation_text: &str, tag_re: &Regex) -> HashMap<String, String> {
     let mut trans_map: HashMap<String, String> = HashMap::new();
     for line in translation_text.lines() {
-        let tags = extract_time_tags(line, &tag_re);
+        let tags = extract_time_tags(line, tag_re);
         if tags.is_empty() {
             continue;
         }
@@ -714,7 +776,53 @@ fn merge_lrc_translation(base: &str, translation: Option<&str>) -> String {
             trans_map.entry(tag).or_insert_with(|| text.clone());
         }
     }
+    trans_map
+}
+
+/// Split an LRC into timed lines plus an index-aligned translation array
+fn align_lrc_translation(base: &str, translation: Option<&str>) -> (Vec<TimedLyricLine>, Vec<Option<String>>) {
+    let tag_re = Regex::new(r"\[(\d{1,2}):(\d{2})(?:[.:](\d{1,3}))?\]").unwrap();
+    let trans_map = translation
+        .map(|text| build_translation_map(text, &tag_re))
+        .unwrap_or_default();
+
+    let mut entries: Vec<(TimedLyricLine, Option<String>)> = Vec::new();
+    for line in base.lines() {
+        let text = tag_re.replace_all(line, "").trim().to_string();
+        for tag in extract_time_tags(line, &tag_re) {
+            let translated = trans_map
+                .get(&tag)
+                .filter(|value| **value != text)
+                .cloned();
+            entries.push((TimedLyricLine { time_ms: time_tag_to_ms(&tag), text: text.clone() }, translated));
+        }
+    }
+    entries.sort_by_key(|(line, _)| line.time_ms);
+
+    let (lines, translations): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+    if trans_map.is_empty() {
+        return (lines, Vec::new());
+    }
+    (lines, translations)
+}
+
+/// `mm:ss.xx` (from `extract_time_tags`) to milliseconds
+fn time_tag_to_ms(tag: &str) -> i64 {
+    let (minute, rest) = tag.split_once(':').unwrap_or(("0", tag));
+    let (second, hundredth) = rest.split_once('.').unwrap_or((rest, "0"));
+    minute.parse::<i64>().unwrap_or(0) * 60_000
+        + second.parse::<i64>().unwrap_or(0) * 1000
+        + hundredth.parse::<i64>().unwrap_or(0) * 10
+}
+
+fn merge_lrc_translation(base: &str, translation: Option<&str>) -> String {
+    let Some(translation_text) = translation else {
+        return base.to_string();
+    };
 
+    let tag_re = Regex::new(r"\[(\d{1,2}):(\d{2})(?:[.:](\d{1,3}))?\]").unwrap();
+
+    let trans_map = build_translation_map(translation_text, &tag_re);
     if trans_map.is_empty() {
         return base.to_string();
     }
@@ -776,15 +884,7 @@ fn extract_time_tags(line: &str, tag_re: &Regex) -> Vec<String> {
         .collect()
 }
 
-fn format_lrc_timestamp(start_ms: i64) -> String {
-    let total_ms = start_ms.max(0);
-    let minute = total_ms / 60_000;
-    let second = (total_ms % 60_000) / 1000;
-    let hundredth = (total_ms % 1000) / 10;
-    format!("[{minute:02}:{second:02}.{hundredth:02}]")
-}
-
-fn compute_score(request: &OnlineLyricSearchRequest, title: &str, artists: &str, album: &str) -> f64 {
+pub(crate) fn compute_score(request: &OnlineLyricSearchRequest, title: &str, artists: &str, album: &str) -> f64 {
     let title_ref = request.title.trim();
     let artist_ref = request.artist.trim();
     let album_ref = request.album.as_deref().unwrap_or("").trim();
@@ -842,8 +942,22 @@ fn build_query(title: &str, artist: &str) -> String {
     }
 }
 
-fn normalize_providers(providers: Option<Vec<String>>) -> Vec<String> {
-    let default_list = vec!["qq".to_string(), "kugou".to_string(), "netease".to_string()];
+/// Negative-cache key: the library song ID, or the search query for ad-hoc lookups
+fn lyric_cache_key(request: &OnlineLyricSearchRequest, query: &str) -> String {
+    match request.song_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(song_id) => format!("song:{song_id}"),
+        None => format!("query:{}", query.to_lowercase()),
+    }
+}
+
+/// Requested lyric providers that exist, or all built-in ones if none do
+fn normalize_providers(registry: &ProviderRegistry, providers: Option<Vec<String>>) -> Vec<String> {
+    let available = registry.with_kind(ProviderKind::Lyrics);
+    let default_list: Vec<String> = available
+        .iter()
+        .filter(|provider| provider.info().builtin)
+        .map(|provider| provider.info().id.clone())
+        .collect();
 
     let Some(values) = providers else {
         return default_list;
@@ -852,7 +966,7 @@ fn normalize_providers(providers: Option<Vec<String>>) -> Vec<String> {
     let mut normalized: Vec<String> = values
         .into_iter()
         .map(|provider| provider.trim().to_lowercase())
-        .filter(|provider| provider == "qq" || provider == "kugou" || provider == "netease")
+        .filter(|provider| available.iter().any(|p| p.info().id == *provider))
         .collect();
 
     normalized.sort();
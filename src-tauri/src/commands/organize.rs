@@ -0,0 +1,259 @@
+//! Organize local files into folders and names built from their tags
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::db::{self, DbSong, DbState};
+use crate::utils::audio::{get_file_mtime, LYRIC_EXTENSIONS};
+use crate::utils::organize::{read_track_numbers, render, validate_template, OrganizeTags};
+
+/// Only one organize pass at a time
+static ORGANIZING: AtomicBool = AtomicBool::new(false);
+
+/// One planned move; entries with a `conflict` are left alone
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeEntry {
+    pub song_id: String,
+    pub from: String,
+    pub to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizePreview {
+    pub entries: Vec<OrganizeEntry>,
+    /// Files already where the template puts them
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeResult {
+    pub moved: usize,
+    pub failed: usize,
+    /// Old → new song ID of every moved song; local IDs follow the file path
+    pub id_map: HashMap<String, String>,
+}
+
+/// 预览整理结果：按模板计算每首本地歌曲的新路径，不移动任何文件。
+/// `song_ids` 为空时整理全部本地歌曲
+#[tauri::command]
+pub async fn library_organize_preview(
+    app: AppHandle,
+    template: String,
+    song_ids: Option<Vec<String>>,
+) -> Result<OrganizePreview, String> {
+    validate_template(&template)?;
+    tokio::task::spawn_blocking(move || plan(&app, &template, song_ids.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 按模板移动/重命名文件并更新曲库路径。重新计算计划，只执行没有冲突的条目
+#[tauri::command]
+pub async fn library_organize_apply(
+    app: AppHandle,
+    template: String,
+    song_ids: Option<Vec<String>>,
+) -> Result<OrganizeResult, String> {
+    validate_template(&template)?;
+    if ORGANIZING.swap(true, Ordering::SeqCst) {
+        return Err("文件整理正在进行中".to_string());
+    }
+
+    let result = tokio::task::spawn_blocking(move || {
+        let preview = plan(&app, &template, song_ids.as_deref())?;
+        apply(&app, preview.entries)
+    })
+    .await
+    .map_err(|e| e.to_string());
+    ORGANIZING.store(false, Ordering::SeqCst);
+    result?
+}
+
+fn plan(app: &AppHandle, template: &str, song_ids: Option<&[String]>) -> Result<OrganizePreview, String> {
+    let db = app.state::<DbState>();
+    let (songs, roots) = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        let songs = match song_ids {
+            Some(ids) if !ids.is_empty() => db::songs::get_songs_by_ids(&conn, ids),
+            _ => db::songs::get_all_songs(&conn),
+        }
+        .map_err(|e| e.to_string())?;
+        let roots = db::servers::get_scan_config(&conn)
+            .map_err(|e| e.to_string())?
+            .map(|config| config.directories)
+            .unwrap_or_default();
+        (songs, roots)
+    };
+
+    let mut entries = Vec::new();
+    let mut unchanged = 0;
+    // Lower-cased targets, so two songs can't be moved onto each other
+    let mut claimed: HashSet<String> = HashSet::new();
+
+    for song in songs.iter().filter(|s| s.source_type == "local") {
+        // Local IDs are derived from the path; CUE tracks share one image file and
+        // get per-track IDs. A per-track name can't fit the shared file, and the
+        // cue sheet next to it would be left behind
+        if song.id != format!("{:x}", md5::compute(&song.file_path)) {
+            entries.push(OrganizeEntry {
+                song_id: song.id.clone(),
+                from: song.file_path.clone(),
+                to: String::new(),
+                conflict: Some("CUE 分轨的文件无法整理".to_string()),
+            });
+            continue;
+        }
+
+        let from = Path::new(&song.file_path);
+        let Some(root) = library_root(from, &roots) else {
+            entries.push(OrganizeEntry {
+                song_id: song.id.clone(),
+                from: song.file_path.clone(),
+                to: String::new(),
+                conflict: Some("不在任何音乐文件夹中".to_string()),
+            });
+            continue;
+        };
+
+        let extension = from
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let to = root.join(render(template, &organize_tags(song, from), &extension));
+        let to_str = to.to_string_lossy().to_string();
+        if to_str == song.file_path {
+            unchanged += 1;
+            claimed.insert(to_str.to_lowercase());
+            continue;
+        }
+
+        let same_file = to_str.to_lowercase() == song.file_path.to_lowercase();
+        let conflict = if !claimed.insert(to_str.to_lowercase()) {
+            Some("与其他歌曲的目标路径重复".to_string())
+        } else if to.exists() && !same_file {
+            Some("目标文件已存在".to_string())
+        } else {
+            None
+        };
+        entries.push(OrganizeEntry {
+            song_id: song.id.clone(),
+            from: song.file_path.clone(),
+            to: to_str,
+            conflict,
+        });
+    }
+
+    Ok(OrganizePreview { entries, unchanged })
+}
+
+fn apply(app: &AppHandle, entries: Vec<OrganizeEntry>) -> Result<OrganizeResult, String> {
+    let db = app.state::<DbState>();
+    let mut result = OrganizeResult { moved: 0, failed: 0, id_map: HashMap::new() };
+
+    for entry in entries.into_iter().filter(|e| e.conflict.is_none()) {
+        let from = PathBuf::from(&entry.from);
+        let to = PathBuf::from(&entry.to);
+        // Local IDs are derived from the path the same way the scanner does it
+        let new_id = format!("{:x}", md5::compute(&entry.to));
+
+        let moved = move_file(&from, &to, || {
+            let mut conn = db.write().map_err(|e| e.to_string())?;
+            db::songs::relocate_local_song(&mut conn, &entry.song_id, &new_id, &entry.to, get_file_mtime(&to).ok())
+                .map_err(|e| e.to_string())
+        });
+        match moved {
+            Ok(()) => {
+                move_lyrics(&from, &to);
+                remove_empty_dirs(&from);
+                result.id_map.insert(entry.song_id, new_id);
+                result.moved += 1;
+            }
+            Err(e) => {
+                eprintln!("Failed to organize {}: {}", entry.from, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    // No "library-updated" here: the caller remaps its queue to the new IDs first
+    Ok(result)
+}
+
+fn organize_tags(song: &DbSong, path: &Path) -> OrganizeTags {
+    let (track, disc) = read_track_numbers(path);
+    OrganizeTags {
+        title: song.title.clone(),
+        artist: song.artist.clone(),
+        album: song.album.clone(),
+        album_artist: song.album_artist.clone(),
+        year: song.year,
+        genre: song.genre.clone(),
+        track,
+        disc,
+    }
+}
+
+/// Deepest music folder containing `path`
+fn library_root(path: &Path, roots: &[String]) -> Option<PathBuf> {
+    roots
+        .iter()
+        .map(PathBuf::from)
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+}
+
+/// Move a file, copying across file systems. `relocate` updates the library
+/// before the source disappears, so the watcher never sees the song go missing.
+fn move_file(from: &Path, to: &Path, relocate: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+    }
+
+    if std::fs::rename(from, to).is_ok() {
+        return relocate();
+    }
+    std::fs::copy(from, to).map_err(|e| format!("复制文件失败: {}", e))?;
+    if let Err(e) = relocate() {
+        let _ = std::fs::remove_file(to);
+        return Err(e);
+    }
+    // The library already points at the copy; a leftover source is only reported
+    if let Err(e) = std::fs::remove_file(from) {
+        eprintln!("Failed to remove {} after copying: {}", from.display(), e);
+    }
+    Ok(())
+}
+
+/// External lyric files follow the audio file, keeping the same stem
+fn move_lyrics(from: &Path, to: &Path) {
+    for ext in LYRIC_EXTENSIONS {
+        let lyric = from.with_extension(ext);
+        let target = to.with_extension(ext);
+        if lyric.exists() && !target.exists() {
+            if let Err(e) = move_file(&lyric, &target, || Ok(())) {
+                eprintln!("Failed to move lyrics {}: {}", lyric.display(), e);
+            }
+        }
+    }
+}
+
+/// Remove the source folders left empty, innermost first
+fn remove_empty_dirs(from: &Path) {
+    let mut dir = from.parent();
+    // remove_dir only succeeds on empty folders, so music folders with content stay
+    while let Some(current) = dir {
+        if std::fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
@@ -0,0 +1,168 @@
+//! Play queue mirror: export to M3U8 and format the now-playing track;
+//! playlist export to M3U8 / XSPF
+//!
+//! The frontend owns the queue and mirrors it here through `queue_sync`, so
+//! exports always reflect what is actually queued.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::streaming::{server_config, stream_url_internal};
+use crate::db::{self, DbSong, DbState};
+use crate::utils::playlist_file::{self, EntryLocation, PlaylistEntry, PlaylistFormat};
+
+/// Default now-playing format
+const DEFAULT_NOW_PLAYING: &str = "{artist} - {title}";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueue {
+    pub song_ids: Vec<String>,
+    pub current_song_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct PlayQueueState(pub Mutex<PlayQueue>);
+
+/// Mirror the frontend queue
+#[tauri::command]
+pub fn queue_sync(
+    queue: State<'_, PlayQueueState>,
+    song_ids: Vec<String>,
+    current_song_id: Option<String>,
+) -> Result<(), String> {
+    let mut current = queue.0.lock().map_err(|e| e.to_string())?;
+    *current = PlayQueue { song_ids, current_song_id };
+    Ok(())
+}
+
+/// Write the current queue as an M3U8 playlist; returns the number of entries.
+/// Stream songs are written as stream URLs, which carry the server credentials.
+#[tauri::command]
+pub fn queue_export_m3u8(
+    db: State<'_, DbState>,
+    queue: State<'_, PlayQueueState>,
+    path: String,
+) -> Result<usize, String> {
+    let song_ids = queue.0.lock().map_err(|e| e.to_string())?.song_ids.clone();
+    if song_ids.is_empty() {
+        return Err("播放队列为空".to_string());
+    }
+
+    let entries = playlist_entries(&db, &song_ids)?;
+    let content = playlist_file::render(PlaylistFormat::M3u8, None, &entries, None);
+    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    Ok(entries.len())
+}
+
+/// 导出歌单为 M3U8 或 XSPF，返回写入的条目数。
+/// `relative_paths` 为 true 时本地歌曲写为相对播放列表文件的路径；流媒体歌曲写为串流地址（含服务器凭据）
+#[tauri::command]
+pub fn playlist_export(
+    db: State<'_, DbState>,
+    playlist_id: String,
+    path: String,
+    format: PlaylistFormat,
+    relative_paths: Option<bool>,
+) -> Result<usize, String> {
+    let (name, song_ids) = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        let playlist = db::playlists::get_playlists(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|p| p.id == playlist_id)
+            .ok_or_else(|| format!("歌单不存在: {}", playlist_id))?;
+        let ids = db::playlists::get_playlist_song_ids(&conn, &playlist_id).map_err(|e| e.to_string())?;
+        (playlist.name, ids)
+    };
+
+    let entries = playlist_entries(&db, &song_ids)?;
+    if entries.is_empty() {
+        return Err("歌单中没有可导出的歌曲".to_string());
+    }
+    let base_dir = relative_paths
+        .unwrap_or(false)
+        .then(|| Path::new(&path).parent())
+        .flatten();
+    let content = playlist_file::render(format, Some(&name), &entries, base_dir);
+    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    Ok(entries.len())
+}
+
+/// Playlist entries for songs in order; songs without a playable location are left out
+fn playlist_entries(db: &DbState, song_ids: &[String]) -> Result<Vec<PlaylistEntry>, String> {
+    let (songs, servers) = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        let songs = db::songs::get_songs_by_ids(&conn, song_ids).map_err(|e| e.to_string())?;
+        let servers: HashMap<String, _> = db::servers::get_stream_servers(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|server| (server.id.clone(), server_config(&server)))
+            .collect();
+        (songs, servers)
+    };
+
+    let entries = songs
+        .into_iter()
+        .filter_map(|song| {
+            let location = match (&song.server_id, &song.server_song_id) {
+                (Some(server_id), Some(server_song_id)) => {
+                    EntryLocation::Url(stream_url_internal(servers.get(server_id)?, server_song_id))
+                }
+                _ if !song.file_path.is_empty() => EntryLocation::File(PathBuf::from(&song.file_path)),
+                _ => return None,
+            };
+            Some(PlaylistEntry {
+                location,
+                title: song.title,
+                artist: song.artist,
+                album: song.album,
+                duration: song.duration,
+            })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// The current track as text for sharing, e.g. `{artist} - {title}`.
+/// Placeholders: `{title}`, `{artist}`, `{album}`, `{year}`.
+#[tauri::command]
+pub fn queue_now_playing_text(
+    db: State<'_, DbState>,
+    queue: State<'_, PlayQueueState>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let song_id = queue
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .current_song_id
+        .clone()
+        .ok_or("当前没有正在播放的歌曲")?;
+
+    let song = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        db::songs::get_songs_by_ids(&conn, &[song_id])
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or("歌曲不存在")?
+    };
+
+    let template = template
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_NOW_PLAYING.to_string());
+    Ok(format_now_playing(&template, &song))
+}
+
+fn format_now_playing(template: &str, song: &DbSong) -> String {
+    template
+        .replace("{title}", &song.title)
+        .replace("{artist}", &song.artist)
+        .replace("{album}", &song.album)
+        .replace("{year}", &song.year.map(|y| y.to_string()).unwrap_or_default())
+}
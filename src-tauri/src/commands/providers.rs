@@ -0,0 +1,89 @@
+//! Provider registry commands: listing, reloading scripts, cover and metadata lookup
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::providers::{MetadataQuery, ProviderInfo, ProviderKind, ProviderLoadError, ProviderMetadata, ProviderRegistryState};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderList {
+    pub providers: Vec<ProviderInfo>,
+    /// Scripts that failed to load
+    pub errors: Vec<ProviderLoadError>,
+    /// Where `*.rhai` provider scripts go
+    pub script_dir: String,
+}
+
+/// First provider's answer, with the provider ID
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderHit<T> {
+    pub provider: String,
+    pub value: T,
+}
+
+fn provider_list(registry: &ProviderRegistryState) -> ProviderList {
+    ProviderList {
+        providers: registry.0.list(),
+        errors: registry.0.load_errors(),
+        script_dir: registry.0.script_dir().to_string_lossy().to_string(),
+    }
+}
+
+/// All registered providers
+#[tauri::command]
+pub fn list_providers(registry: State<'_, ProviderRegistryState>) -> ProviderList {
+    provider_list(&registry)
+}
+
+/// Re-read the provider scripts
+#[tauri::command]
+pub fn reload_providers(registry: State<'_, ProviderRegistryState>) -> Result<ProviderList, String> {
+    std::fs::create_dir_all(registry.0.script_dir()).map_err(|e| e.to_string())?;
+    registry.0.reload();
+    Ok(provider_list(&registry))
+}
+
+/// Ask cover providers in order until one finds an image
+#[tauri::command]
+pub async fn provider_find_cover(
+    registry: State<'_, ProviderRegistryState>,
+    query: MetadataQuery,
+) -> Result<Option<ProviderHit<String>>, CommandError> {
+    let mut last_error = None;
+    for provider in registry.0.with_kind(ProviderKind::Cover) {
+        match provider.find_cover(&query).await {
+            Ok(Some(url)) => return Ok(Some(ProviderHit { provider: provider.info().id.clone(), value: url })),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("[providers][{}][cover] {}", provider.info().id, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    last_error.map_or(Ok(None), Err)
+}
+
+/// Ask metadata providers in order until one has tags for the song
+#[tauri::command]
+pub async fn provider_find_metadata(
+    registry: State<'_, ProviderRegistryState>,
+    query: MetadataQuery,
+) -> Result<Option<ProviderHit<ProviderMetadata>>, CommandError> {
+    let mut last_error = None;
+    for provider in registry.0.with_kind(ProviderKind::Metadata) {
+        match provider.find_metadata(&query).await {
+            Ok(Some(metadata)) => {
+                return Ok(Some(ProviderHit { provider: provider.info().id.clone(), value: metadata }))
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("[providers][{}][metadata] {}", provider.info().id, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    last_error.map_or(Ok(None), Err)
+}
@@ -1,26 +1,91 @@
 //! Advanced scanning commands with incremental scan and progress events
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use rayon::prelude::*;
 use tauri::{AppHandle, Emitter, State};
-use walkdir::WalkDir;
 
 use crate::commands::CoverCacheState;
-use crate::db::{self, DbState, SongInput};
+use crate::db::{self, DbState, LocalFileState, SongInput};
+use crate::error::CommandError;
+use crate::telemetry;
 use crate::models::{
-    LocalScanOptions, ScanMode, ScanPhase, ScanProgress, ScanResult, StreamScanOptions,
+    LastScan, LocalScanOptions, ScanMode, ScanPhase, ScanPreview, ScanProgress, ScanResult, StreamScanOptions,
 };
-use crate::utils::audio::{is_audio_file, read_metadata_with_mtime};
-use crate::utils::cover::extract_and_cache_cover;
+use crate::utils::audio::read_metadata_with_mtime;
+use crate::utils::path_template::PathTemplates;
+use crate::utils::cover::{extract_and_cache_covers, CoverCache, ExtractedCovers};
+use crate::utils::{chapters, cuesheet};
+use crate::utils::fingerprint::{check_file, partial_content_hash, uses_content_hash};
+use crate::utils::walk::{collect_audio_files, CollectedFiles};
+
+/// Result of the most recent scan, reported by `get_diagnostics`
+static LAST_SCAN: Mutex<Option<LastScan>> = Mutex::new(None);
+
+/// Where scan progress goes: window events, or stderr for `--scan`
+pub enum ScanReporter<'a> {
+    App(&'a AppHandle),
+    Console,
+}
+
+impl ScanReporter<'_> {
+    /// Emit scan progress event
+    fn progress(&self, progress: &ScanProgress) {
+        match self {
+            ScanReporter::App(app) => {
+                let _ = app.emit("scan-progress", progress);
+            }
+            ScanReporter::Console => {
+                let current = progress.current_file.as_deref().unwrap_or("");
+                eprintln!(
+                    "[scan] {:?} {}/{} {}",
+                    progress.phase, progress.processed, progress.total, current
+                );
+            }
+        }
+    }
+
+    fn library_updated(&self) {
+        if let ScanReporter::App(app) = self {
+            let _ = app.emit("library-updated", ());
+        }
+    }
+
+    fn feature(&self, name: &str) {
+        if let ScanReporter::App(app) = self {
+            telemetry::feature(app, name);
+        }
+    }
 
-/// Emit scan progress event
-fn emit_progress(app: &AppHandle, progress: &ScanProgress) {
-    let _ = app.emit("scan-progress", progress);
+    fn error(&self, area: &str, err: &CommandError) {
+        if let ScanReporter::App(app) = self {
+            telemetry::error(app, area, err);
+        }
+    }
+}
+
+/// Remember a finished scan for diagnostics
+fn record_scan(kind: &str, result: &ScanResult) {
+    let finished_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if let Ok(mut last) = LAST_SCAN.lock() {
+        *last = Some(LastScan {
+            kind: kind.to_string(),
+            finished_at,
+            result: result.clone(),
+        });
+    }
+}
+
+/// The most recent scan since the app started
+pub fn last_scan() -> Option<LastScan> {
+    LAST_SCAN.lock().ok().and_then(|last| last.clone())
 }
 
 /// Scan local directories to database with progress events
@@ -30,17 +95,25 @@ pub async fn scan_local_to_db(
     db: State<'_, DbState>,
     cover_cache: State<'_, CoverCacheState>,
     options: LocalScanOptions,
-) -> Result<ScanResult, String> {
+) -> Result<ScanResult, CommandError> {
+    // Get cover cache for use in parallel processing
+    let cache = cover_cache.0.lock()?.clone_arc();
+    scan_local(&ScanReporter::App(&app), &db, cache, options).await
+}
+
+/// Local scan without Tauri state, shared by the command and `--scan`
+pub async fn scan_local(
+    reporter: &ScanReporter<'_>,
+    db: &DbState,
+    cache: Arc<CoverCache>,
+    options: LocalScanOptions,
+) -> Result<ScanResult, CommandError> {
     let start_time = Instant::now();
     let min_duration = options.min_duration.unwrap_or(0.0);
     let batch_size = options.batch_size;
 
-    // Get cover cache for use in parallel processing
-    let cache = cover_cache.0.lock().map_err(|e| e.to_string())?.clone_arc();
-
     // Phase 1: Collect all audio file paths
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Collecting,
             total: 0,
@@ -51,36 +124,29 @@ pub async fn scan_local_to_db(
         },
     );
 
-    let mut audio_paths: Vec<PathBuf> = Vec::new();
-
-    for dir in &options.directories {
-        let dir_path = Path::new(dir);
-        if !dir_path.exists() {
-            continue;
-        }
-
-        for entry in WalkDir::new(dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() && is_audio_file(path) {
-                audio_paths.push(path.to_path_buf());
-            }
-        }
-    }
+    let CollectedFiles { audio_paths, skipped_cycles } = collect_audio_files(&options.directories);
 
     let total_files = audio_paths.len();
 
+    // Local files already in the library, for change detection and added/updated counts
+    let (existing_files, templates) = {
+        let conn = db.write()?;
+        let existing = db::songs::get_local_file_states(&conn)?;
+        let templates = db::settings::filename_templates(&conn)?;
+        (existing, PathTemplates::compile(&templates))
+    };
+
     // Phase 2: Check which files need scanning (for incremental mode)
     let files_to_scan: Vec<PathBuf>;
     let mut skipped_count = 0;
+    // Content hashes computed while checking, reused when saving
+    let mut known_hashes: HashMap<PathBuf, String> = HashMap::new();
+    // Unchanged files stored without a fingerprint yet
+    let mut unhashed: Vec<PathBuf> = Vec::new();
 
     match options.mode {
         ScanMode::Incremental => {
-            emit_progress(
-                &app,
+            reporter.progress(
                 &ScanProgress {
                     phase: ScanPhase::Checking,
                     total: total_files,
@@ -91,47 +157,24 @@ pub async fn scan_local_to_db(
                 },
             );
 
-            // Get existing files from DB with their modification times
-            let existing_files: HashMap<String, Option<i64>> = {
-                let conn = db.0.lock().map_err(|e| e.to_string())?;
-                let songs = db::songs::get_all_songs(&conn).map_err(|e| e.to_string())?;
-                songs
-                    .into_iter()
-                    .filter(|s| s.source_type == "local")
-                    .map(|s| (s.file_path, s.file_modified))
-                    .collect()
-            };
-
             // Filter to only files that are new or modified
             files_to_scan = audio_paths
                 .into_iter()
                 .filter(|path| {
                     let path_str = path.to_string_lossy().to_string();
-                    match existing_files.get(&path_str) {
-                        Some(Some(db_mtime)) => {
-                            // File exists in DB, check if modified
-                            match std::fs::metadata(path) {
-                                Ok(meta) => match meta.modified() {
-                                    Ok(mtime) => {
-                                        let file_mtime = mtime
-                                            .duration_since(std::time::UNIX_EPOCH)
-                                            .map(|d| d.as_secs() as i64)
-                                            .unwrap_or(0);
-                                        if file_mtime > *db_mtime {
-                                            true // File modified, rescan
-                                        } else {
-                                            skipped_count += 1;
-                                            false // File unchanged, skip
-                                        }
-                                    }
-                                    Err(_) => true,
-                                },
-                                Err(_) => true,
-                            }
+                    let use_hash = uses_content_hash(path, &options.hash_check_directories);
+                    let known = existing_files.get(&path_str);
+                    let check = check_file(path, known, use_hash);
+                    if let Some(hash) = check.content_hash {
+                        known_hashes.insert(path.clone(), hash);
+                    }
+                    if !check.changed {
+                        skipped_count += 1; // File unchanged, skip
+                        if known.is_some_and(|k| k.content_hash.is_none()) {
+                            unhashed.push(path.clone());
                         }
-                        Some(None) => true, // No mtime in DB, rescan
-                        None => true,       // New file
                     }
+                    check.changed
                 })
                 .collect();
         }
@@ -143,8 +186,7 @@ pub async fn scan_local_to_db(
     let files_to_process = files_to_scan.len();
 
     // Phase 3: Read metadata in parallel
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Scanning,
             total: files_to_process,
@@ -162,22 +204,19 @@ pub async fn scan_local_to_db(
     let songs: Vec<SongInput> = files_to_scan
         .par_iter()
         .filter_map(|path| {
-            let result = read_metadata_with_mtime(path);
+            let result = read_metadata_with_mtime(path, &templates);
             let processed = processed_count.fetch_add(1, Ordering::Relaxed) + 1;
 
             // Emit progress every 50 files
             if processed % 50 == 0 || processed == files_to_process {
-                let _ = app.emit(
-                    "scan-progress",
-                    ScanProgress {
-                        phase: ScanPhase::Scanning,
-                        total: files_to_process,
-                        processed,
-                        current_file: Some(path.to_string_lossy().to_string()),
-                        skipped: skipped_count,
-                        errors: error_count.load(Ordering::Relaxed),
-                    },
-                );
+                reporter.progress(&ScanProgress {
+                    phase: ScanPhase::Scanning,
+                    total: files_to_process,
+                    processed,
+                    current_file: Some(path.to_string_lossy().to_string()),
+                    skipped: skipped_count,
+                    errors: error_count.load(Ordering::Relaxed),
+                });
             }
 
             match result {
@@ -187,20 +226,39 @@ pub async fn scan_local_to_db(
                         return None;
                     }
 
-                    // Extract and cache cover, get hash
-                    let cover_hash = extract_and_cache_cover(path, &cache_clone).ok().flatten();
+                    // Extract and cache embedded pictures (main cover + typed extras).
+                    // A dry run must not touch the cover cache either.
+                    let covers = if options.dry_run {
+                        ExtractedCovers::default()
+                    } else {
+                        extract_and_cache_covers(path, &cache_clone).unwrap_or_default()
+                    };
+
+                    // Every file gets a fingerprint, so a later move can be recognized
+                    let content_hash = known_hashes.get(path).cloned().or_else(|| {
+                        (!options.dry_run)
+                            .then(|| partial_content_hash(path).ok())
+                            .flatten()
+                    });
 
                     Some(SongInput {
                         id: song.id,
                         title: song.title,
                         artist: song.artist,
                         album: song.album,
+                        album_artist: song.album_artist,
+                        composer: song.composer,
+                        year: song.year,
+                        genre: song.genre,
+                        track_no: song.track_no,
+                        disc_no: song.disc_no,
                         duration: song.duration,
                         file_path: song.file_path,
                         file_size: song.file_size as i64,
                         is_hr: song.is_hr,
                         is_sq: song.is_sq,
-                        cover_hash, // Store hash instead of base64
+                        cover_hash: covers.cover_hash, // Store hash instead of base64
+                        content_hash,
                         server_song_id: None,
                         stream_info: None,
                         file_modified: Some(song.file_modified),
@@ -209,6 +267,13 @@ pub async fn scan_local_to_db(
                         sample_rate: song.sample_rate,
                         bitrate: song.bitrate,
                         channels: song.channels,
+                        replay_gain: song.replay_gain,
+                        replay_peak: song.replay_peak,
+                        album_gain: song.album_gain,
+                        album_peak: song.album_peak,
+                        pictures: covers.pictures,
+                        cue: None,
+                        chapters: chapters::read(path),
                     })
                 }
                 Err(_) => {
@@ -217,13 +282,46 @@ pub async fn scan_local_to_db(
                 }
             }
         })
+        // Image + embedded cue sheet: one song per track
+        .flat_map_iter(cuesheet::expand)
         .collect();
 
     let errors = error_count.load(Ordering::Relaxed);
+    let updated_count = songs
+        .iter()
+        .filter(|song| existing_files.contains_key(&song.file_path))
+        .count();
+
+    if options.dry_run {
+        return Ok(preview_scan(
+            reporter,
+            &options.mode,
+            &songs,
+            &existing_files,
+            skipped_count,
+            errors,
+            start_time,
+            skipped_cycles,
+        ));
+    }
+
+    // Fingerprints for files recorded before every file got one
+    let backfilled: Vec<(String, String)> = unhashed
+        .par_iter()
+        .filter_map(|path| {
+            partial_content_hash(path)
+                .ok()
+                .map(|hash| (path.to_string_lossy().to_string(), hash))
+        })
+        .collect();
+    let missing_paths: Vec<String> = existing_files
+        .keys()
+        .filter(|path| !Path::new(path).exists())
+        .cloned()
+        .collect();
 
     // Phase 4: Save to database in batches
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Saving,
             total: songs.len(),
@@ -234,23 +332,31 @@ pub async fn scan_local_to_db(
         },
     );
 
-    let added_count;
+    let full_scan = matches!(options.mode, ScanMode::Full);
+    let mut moved_count = 0;
+    let mut removed_count = 0;
     {
-        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut conn = db.write()?;
 
-        // For full scan, clear local songs first
-        if matches!(options.mode, ScanMode::Full) {
-            db::songs::delete_songs_by_source(&conn, "local", None).map_err(|e| e.to_string())?;
+        // A full scan replaces the local library: whatever it didn't find is marked missing below
+        let previous_ids = if full_scan {
+            db::songs::get_song_ids_by_source(&conn, "local", None)?
+        } else {
+            Vec::new()
+        };
+        if !full_scan {
+            db::songs::set_content_hashes(&mut conn, &backfilled)?;
+            // Moved/renamed files keep their rows instead of being removed and re-added
+            moved_count = db::songs::relocate_moved_songs(&mut conn, &songs, &missing_paths)?;
         }
 
         // Save in batches
         let mut total_saved = 0;
         for chunk in songs.chunks(batch_size) {
-            db::songs::save_songs(&mut conn, chunk, "local", None).map_err(|e| e.to_string())?;
+            db::songs::save_songs(&mut conn, chunk, "local", None)?;
             total_saved += chunk.len();
 
-            emit_progress(
-                &app,
+            reporter.progress(
                 &ScanProgress {
                     phase: ScanPhase::Saving,
                     total: songs.len(),
@@ -262,16 +368,21 @@ pub async fn scan_local_to_db(
             );
         }
 
-        added_count = total_saved;
+        if full_scan {
+            let scanned: HashSet<&str> = songs.iter().map(|song| song.id.as_str()).collect();
+            let gone: Vec<String> = previous_ids
+                .into_iter()
+                .filter(|id| !scanned.contains(id.as_str()))
+                .collect();
+            removed_count = db::songs::mark_songs_missing(&conn, &gone)?;
+        }
     }
 
-    // Phase 5: Cleanup - remove songs whose files no longer exist
-    let removed_count;
+    // Phase 5: Cleanup - mark songs whose files no longer exist as missing
     {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let conn = db.write()?;
 
-        emit_progress(
-            &app,
+        reporter.progress(
             &ScanProgress {
                 phase: ScanPhase::Cleanup,
                 total: 0,
@@ -283,8 +394,7 @@ pub async fn scan_local_to_db(
         );
 
         // Get all local songs from DB
-        let all_local_songs = db::songs::get_all_songs(&conn)
-            .map_err(|e| e.to_string())?
+        let all_local_songs = db::songs::get_all_songs(&conn)?
             .into_iter()
             .filter(|s| s.source_type == "local")
             .collect::<Vec<_>>();
@@ -296,26 +406,25 @@ pub async fn scan_local_to_db(
             .map(|s| s.id.clone())
             .collect();
 
-        removed_count = missing_ids.len();
-
-        // Delete missing songs
-        for id in &missing_ids {
-            conn.execute("DELETE FROM songs WHERE id = ?1", [id])
-                .map_err(|e| e.to_string())?;
+        // Keep missing songs (unplugged drive, ...) so they come back with their
+        // play counts and playlist entries; songs whose file is back are restored
+        removed_count += db::songs::mark_songs_missing(&conn, &missing_ids)?;
+        // After a full scan only what it found is present again
+        if !full_scan {
+            db::songs::restore_present_songs(&conn)?;
         }
     }
 
     // Get final count
     let total_songs = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        db::songs::get_song_count_by_source(&conn, "local").map_err(|e| e.to_string())? as usize
+        let conn = db.write()?;
+        db::songs::get_song_count_by_source(&conn, "local")? as usize
     };
 
     let duration_ms = start_time.elapsed().as_millis() as u64;
 
     // Phase 6: Complete
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Complete,
             total: total_songs,
@@ -327,17 +436,91 @@ pub async fn scan_local_to_db(
     );
 
     // Emit library-updated event
-    let _ = app.emit("library-updated", ());
+    reporter.library_updated();
 
-    Ok(ScanResult {
+    let result = ScanResult {
         total_songs,
-        added: added_count,
-        updated: 0, // TODO: track updates separately
+        added: songs.len() - updated_count - moved_count,
+        updated: updated_count,
+        moved: moved_count,
         removed: removed_count,
         skipped: skipped_count,
         errors,
         duration_ms,
-    })
+        skipped_cycles,
+        preview: None,
+    };
+    record_scan("local", &result);
+    reporter.feature("scan.local");
+    Ok(result)
+}
+
+/// Dry run: work out what a scan would add/update/remove without writing anything
+#[allow(clippy::too_many_arguments)]
+fn preview_scan(
+    reporter: &ScanReporter<'_>,
+    mode: &ScanMode,
+    songs: &[SongInput],
+    existing_files: &HashMap<String, LocalFileState>,
+    skipped_count: usize,
+    errors: usize,
+    start_time: Instant,
+    skipped_cycles: Vec<String>,
+) -> ScanResult {
+    let (mut updated, mut added): (Vec<String>, Vec<String>) = songs
+        .iter()
+        .map(|song| song.file_path.clone())
+        .partition(|path| existing_files.contains_key(path));
+    // Tracks split from one file by an embedded cue sheet follow each other
+    updated.dedup();
+    added.dedup();
+
+    // A full scan marks anything not re-read as missing; an incremental scan
+    // only the songs whose file is gone
+    let scanned: HashSet<&str> = songs.iter().map(|song| song.file_path.as_str()).collect();
+    let mut removed: Vec<String> = existing_files
+        .iter()
+        .filter(|(_, state)| !state.missing)
+        .map(|(path, _)| path)
+        .filter(|path| match mode {
+            ScanMode::Full => !scanned.contains(path.as_str()),
+            ScanMode::Incremental => !Path::new(path).exists(),
+        })
+        .cloned()
+        .collect();
+    removed.sort();
+
+    // Missing songs that were found again count as present once more
+    let present = existing_files.values().filter(|state| !state.missing).count();
+    let restored = updated
+        .iter()
+        .filter(|path| existing_files.get(*path).is_some_and(|state| state.missing))
+        .count();
+    let total_songs = present + restored + added.len() - removed.len();
+
+    reporter.progress(
+        &ScanProgress {
+            phase: ScanPhase::Complete,
+            total: total_songs,
+            processed: total_songs,
+            current_file: None,
+            skipped: skipped_count,
+            errors,
+        },
+    );
+
+    ScanResult {
+        total_songs,
+        added: added.len(),
+        updated: updated.len(),
+        moved: 0,
+        removed: removed.len(),
+        skipped: skipped_count,
+        errors,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        skipped_cycles,
+        preview: Some(ScanPreview { added, updated, removed }),
+    }
 }
 
 /// Scan stream servers to database
@@ -346,11 +529,19 @@ pub async fn scan_stream_to_db(
     app: AppHandle,
     db: State<'_, DbState>,
     options: StreamScanOptions,
-) -> Result<ScanResult, String> {
+) -> Result<ScanResult, CommandError> {
+    scan_stream(&ScanReporter::App(&app), &db, options).await
+}
+
+/// Stream server scan without Tauri state, shared by the command and `--scan`
+pub async fn scan_stream(
+    reporter: &ScanReporter<'_>,
+    db: &DbState,
+    options: StreamScanOptions,
+) -> Result<ScanResult, CommandError> {
     let start_time = Instant::now();
 
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Collecting,
             total: 0,
@@ -363,8 +554,8 @@ pub async fn scan_stream_to_db(
 
     // Get servers to scan
     let servers = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        let all_servers = db::servers::get_stream_servers(&conn).map_err(|e| e.to_string())?;
+        let conn = db.write()?;
+        let all_servers = db::servers::get_stream_servers(&conn)?;
 
         if let Some(server_id) = &options.server_id {
             all_servers
@@ -381,10 +572,13 @@ pub async fn scan_stream_to_db(
             total_songs: 0,
             added: 0,
             updated: 0,
+            moved: 0,
             removed: 0,
             skipped: 0,
             errors: 0,
             duration_ms: start_time.elapsed().as_millis() as u64,
+            skipped_cycles: Vec::new(),
+            preview: None,
         });
     }
 
@@ -392,8 +586,7 @@ pub async fn scan_stream_to_db(
     let mut total_errors = 0;
 
     for server in &servers {
-        emit_progress(
-            &app,
+        reporter.progress(
             &ScanProgress {
                 phase: ScanPhase::Scanning,
                 total: 0,
@@ -405,22 +598,7 @@ pub async fn scan_stream_to_db(
         );
 
         // Build config for fetching
-        let config = crate::models::StreamServerConfig {
-            server_type: match server.server_type.as_str() {
-                "navidrome" => crate::models::ServerType::Navidrome,
-                "subsonic" => crate::models::ServerType::Subsonic,
-                "opensubsonic" => crate::models::ServerType::OpenSubsonic,
-                "jellyfin" => crate::models::ServerType::Jellyfin,
-                "emby" => crate::models::ServerType::Emby,
-                _ => crate::models::ServerType::Navidrome,
-            },
-            server_name: server.server_name.clone(),
-            server_url: server.server_url.clone(),
-            username: server.username.clone(),
-            password: server.password.clone(),
-            access_token: server.access_token.clone(),
-            user_id: server.user_id.clone(),
-        };
+        let config = crate::commands::streaming::server_config(server);
 
         // Fetch songs from server
         let stream_songs = match crate::commands::streaming::fetch_stream_songs_internal(&config).await {
@@ -428,17 +606,11 @@ pub async fn scan_stream_to_db(
             Err(e) => {
                 total_errors += 1;
                 eprintln!("Failed to fetch songs from {}: {}", server.server_name, e);
+                reporter.error("scan.stream", &e);
                 continue;
             }
         };
 
-        // Clear old songs for this server
-        {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
-            db::songs::delete_songs_by_source(&conn, "stream", Some(&server.id))
-                .map_err(|e| e.to_string())?;
-        }
-
         // Convert to SongInput
         // Note: Stream songs don't cache covers locally, they use server URLs
         let song_inputs: Vec<SongInput> = stream_songs
@@ -448,12 +620,23 @@ pub async fn scan_stream_to_db(
                 title: s.title.clone(),
                 artist: s.artist.clone(),
                 album: s.album.clone(),
+                album_artist: s.album_artist.clone(),
+                composer: s.composer.clone(),
+                year: s.year,
+                genre: s.genre.clone(),
+                track_no: s.track_no,
+                disc_no: s.disc_no,
                 duration: s.duration,
                 file_path: String::new(),
                 file_size: s.file_size as i64,
                 is_hr: s.is_hr,
                 is_sq: s.is_sq,
                 cover_hash: None, // Stream songs use server cover URLs directly
+                content_hash: None,
+                replay_gain: s.replay_gain,
+                replay_peak: s.replay_peak,
+                album_gain: s.album_gain,
+                album_peak: s.album_peak,
                 server_song_id: Some(s.id.clone()),
                 stream_info: Some(serde_json::json!({
                     "type": "stream",
@@ -466,7 +649,6 @@ pub async fn scan_stream_to_db(
                         "serverName": server.server_name,
                         "serverUrl": server.server_url,
                         "username": server.username,
-                        "password": server.password,
                         "accessToken": server.access_token,
                         "userId": server.user_id
                     }
@@ -477,19 +659,29 @@ pub async fn scan_stream_to_db(
                 sample_rate: s.sample_rate,
                 bitrate: s.bitrate,
                 channels: s.channels,
+                pictures: Vec::new(),
+                cue: None,
+                chapters: Vec::new(),
             })
             .collect();
 
-        // Save to database
+        // Save to database; songs the server no longer lists are marked missing
+        // rather than deleted, so their plays and ratings return if they come back
         {
-            let mut conn = db.0.lock().map_err(|e| e.to_string())?;
-            let saved = db::songs::save_songs(&mut conn, &song_inputs, "stream", Some(&server.id))
-                .map_err(|e| e.to_string())?;
+            let mut conn = db.write()?;
+            let previous_ids = db::songs::get_song_ids_by_source(&conn, "stream", Some(&server.id))?;
+            let saved = db::songs::save_songs(&mut conn, &song_inputs, "stream", Some(&server.id))?;
             total_added += saved;
+
+            let listed: HashSet<&str> = song_inputs.iter().map(|song| song.id.as_str()).collect();
+            let gone: Vec<String> = previous_ids
+                .into_iter()
+                .filter(|id| !listed.contains(id.as_str()))
+                .collect();
+            db::songs::mark_songs_missing(&conn, &gone)?;
         }
 
-        emit_progress(
-            &app,
+        reporter.progress(
             &ScanProgress {
                 phase: ScanPhase::Saving,
                 total: stream_songs.len(),
@@ -503,14 +695,13 @@ pub async fn scan_stream_to_db(
 
     // Get final count
     let total_songs = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        db::songs::get_song_count_by_source(&conn, "stream").map_err(|e| e.to_string())? as usize
+        let conn = db.write()?;
+        db::songs::get_song_count_by_source(&conn, "stream")? as usize
     };
 
     let duration_ms = start_time.elapsed().as_millis() as u64;
 
-    emit_progress(
-        &app,
+    reporter.progress(
         &ScanProgress {
             phase: ScanPhase::Complete,
             total: total_songs,
@@ -522,15 +713,21 @@ pub async fn scan_stream_to_db(
     );
 
     // Emit library-updated event
-    let _ = app.emit("library-updated", ());
+    reporter.library_updated();
 
-    Ok(ScanResult {
+    let result = ScanResult {
         total_songs,
         added: total_added,
         updated: 0,
+        moved: 0,
         removed: 0,
         skipped: 0,
         errors: total_errors,
         duration_ms,
-    })
+        skipped_cycles: Vec::new(),
+        preview: None,
+    };
+    record_scan("stream", &result);
+    reporter.feature("scan.stream");
+    Ok(result)
 }
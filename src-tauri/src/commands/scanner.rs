@@ -1,11 +1,16 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::fs;
-use walkdir::WalkDir;
 use rayon::prelude::*;
 use serde::Serialize;
 
+use tauri::State;
+
+use crate::db::{self, DbState};
+use crate::error::{CommandError, ErrorCode};
 use crate::models::{ScanOptions, ScannedSong};
 use crate::utils::audio::{is_audio_file, read_lyrics, read_metadata};
+use crate::utils::path_template::PathTemplates;
+use crate::utils::walk::collect_audio_files;
 
 /// 目录项
 #[derive(Debug, Serialize)]
@@ -17,15 +22,15 @@ pub struct DirectoryEntry {
 
 /// 列出目录内容（仅目录）
 #[tauri::command]
-pub fn list_directories(path: String) -> Result<Vec<DirectoryEntry>, String> {
+pub fn list_directories(path: String) -> Result<Vec<DirectoryEntry>, CommandError> {
     let dir_path = Path::new(&path);
 
     if !dir_path.exists() {
-        return Err(format!("Path does not exist: {}", path));
+        return Err(CommandError::new(ErrorCode::NotFound).with("path", &path));
     }
 
     if !dir_path.is_dir() {
-        return Err(format!("Path is not a directory: {}", path));
+        return Err(CommandError::new(ErrorCode::NotADirectory).with("path", &path));
     }
 
     let mut entries = Vec::new();
@@ -55,7 +60,7 @@ pub fn list_directories(path: String) -> Result<Vec<DirectoryEntry>, String> {
             }
         }
         Err(e) => {
-            return Err(format!("Failed to read directory: {}", e));
+            return Err(e.into());
         }
     }
 
@@ -65,38 +70,28 @@ pub fn list_directories(path: String) -> Result<Vec<DirectoryEntry>, String> {
     Ok(entries)
 }
 
+/// 读取设置中的文件名模板
+fn filename_templates(db: &DbState) -> Result<PathTemplates, CommandError> {
+    let conn = db.write()?;
+    let templates = db::settings::filename_templates(&conn)?;
+    Ok(PathTemplates::compile(&templates))
+}
+
 /// 扫描指定目录中的音乐文件
 #[tauri::command]
-pub fn scan_music_files(options: ScanOptions) -> Result<Vec<ScannedSong>, String> {
+pub fn scan_music_files(db: State<'_, DbState>, options: ScanOptions) -> Result<Vec<ScannedSong>, CommandError> {
+    let templates = filename_templates(&db)?;
     let skip_short = options.skip_short_audio.unwrap_or(false);
     let min_duration = options.min_duration.unwrap_or(30.0);
 
     // 第一步：快速收集所有音频文件路径（单线程，I/O 受限但很快）
-    let mut audio_paths: Vec<PathBuf> = Vec::new();
-
-    for dir in &options.directories {
-        let dir_path = Path::new(dir);
-        if !dir_path.exists() {
-            continue;
-        }
-
-        for entry in WalkDir::new(dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() && is_audio_file(path) {
-                audio_paths.push(path.to_path_buf());
-            }
-        }
-    }
+    let audio_paths = collect_audio_files(&options.directories).audio_paths;
 
     // 第二步：并行读取元数据
     let songs: Vec<ScannedSong> = audio_paths
         .par_iter()
         .filter_map(|path| {
-            match read_metadata(path) {
+            match read_metadata(path, &templates) {
                 Ok(song) => {
                     if skip_short && song.duration < min_duration {
                         None
@@ -114,7 +109,7 @@ pub fn scan_music_files(options: ScanOptions) -> Result<Vec<ScannedSong>, String
 
 /// 获取单个音乐文件的元数据
 #[tauri::command]
-pub fn get_music_metadata(file_path: String) -> Result<Option<ScannedSong>, String> {
+pub fn get_music_metadata(db: State<'_, DbState>, file_path: String) -> Result<Option<ScannedSong>, CommandError> {
     let path = Path::new(&file_path);
 
     if !path.exists() || !path.is_file() {
@@ -125,7 +120,7 @@ pub fn get_music_metadata(file_path: String) -> Result<Option<ScannedSong>, Stri
         return Ok(None);
     }
 
-    match read_metadata(path) {
+    match read_metadata(path, &filename_templates(&db)?) {
         Ok(song) => Ok(Some(song)),
         Err(_) => Ok(None),
     }
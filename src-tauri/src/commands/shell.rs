@@ -0,0 +1,45 @@
+//! File manager "Add to BaYin queue" integration commands
+
+use tauri::State;
+
+use crate::shell_integration::{PendingQueueState, ShellIntegrationStatus};
+
+#[tauri::command]
+pub fn shell_integration_status() -> ShellIntegrationStatus {
+    #[cfg(desktop)]
+    {
+        crate::shell_integration::desktop::status()
+    }
+    #[cfg(not(desktop))]
+    {
+        ShellIntegrationStatus {
+            supported: false,
+            installed: false,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn shell_integration_install() -> Result<ShellIntegrationStatus, String> {
+    #[cfg(desktop)]
+    {
+        crate::shell_integration::desktop::install()?;
+    }
+    Ok(shell_integration_status())
+}
+
+#[tauri::command]
+pub fn shell_integration_uninstall() -> Result<ShellIntegrationStatus, String> {
+    #[cfg(desktop)]
+    {
+        crate::shell_integration::desktop::uninstall()?;
+    }
+    Ok(shell_integration_status())
+}
+
+/// Song IDs sent from the file manager since the last call, in selection order
+#[tauri::command]
+pub fn take_pending_queue(pending: State<'_, PendingQueueState>) -> Result<Vec<String>, String> {
+    let mut ids = pending.0.lock().map_err(|e| e.to_string())?;
+    Ok(std::mem::take(&mut *ids))
+}
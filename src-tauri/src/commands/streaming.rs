@@ -1,10 +1,102 @@
-use crate::models::{ConnectionTestResult, ScannedSong, StreamServerConfig};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::unified::{match_key, DURATION_TOLERANCE_SECS};
+use crate::db::{self, DbState, DbStreamServer, SearchMode};
+use crate::error::{CommandError, ErrorCode};
+use crate::models::{
+    ConnectionTestResult, ScannedSong, ServerType, SourceSearchHit, SourceSearchResult, StreamServerConfig,
+};
+use crate::telemetry;
 use crate::utils::{jellyfin, subsonic};
 
 // ============ 内部函数（供其他模块调用） ============
 
+/// 由数据库中的服务器记录构建连接配置
+pub fn server_config(server: &DbStreamServer) -> StreamServerConfig {
+    StreamServerConfig {
+        server_type: match server.server_type.as_str() {
+            "navidrome" => ServerType::Navidrome,
+            "subsonic" => ServerType::Subsonic,
+            "opensubsonic" => ServerType::OpenSubsonic,
+            "jellyfin" => ServerType::Jellyfin,
+            "emby" => ServerType::Emby,
+            _ => ServerType::Navidrome,
+        },
+        server_name: server.server_name.clone(),
+        server_url: server.server_url.clone(),
+        username: server.username.clone(),
+        password: server.password.clone(),
+        access_token: server.access_token.clone(),
+        user_id: server.user_id.clone(),
+    }
+}
+
+/// 获取流媒体歌曲的流 URL（内部函数）
+pub fn stream_url_internal(config: &StreamServerConfig, song_id: &str) -> String {
+    if config.is_subsonic() {
+        subsonic::get_stream_url(config, song_id)
+    } else {
+        jellyfin::get_stream_url(config, song_id)
+    }
+}
+
+/// 为过期的流 URL 生成新 URL（播放中 401/403 时由音频引擎调用）
+///
+/// 按 URL 前缀找到所属服务器；Jellyfin/Emby 重新登录并保存新 token，
+/// Subsonic 的 token 每次都重新加盐生成，直接重建 URL 即可。
+pub fn refresh_stream_url(app: &AppHandle, expired_url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(expired_url).ok()?;
+    let server = {
+        let db = app.state::<DbState>();
+        let conn = db.read().ok()?;
+        db::servers::get_stream_servers(&conn)
+            .ok()?
+            .into_iter()
+            .filter(|s| s.enabled)
+            .find(|s| expired_url.starts_with(s.server_url.trim_end_matches('/')))?
+    };
+    let mut config = server_config(&server);
+
+    let song_id = if config.is_subsonic() {
+        parsed
+            .query_pairs()
+            .find(|(k, _)| k == "id")
+            .map(|(_, v)| v.into_owned())?
+    } else {
+        let mut segments = parsed.path_segments()?;
+        segments.find(|s| *s == "Audio")?;
+        segments.next()?.to_string()
+    };
+
+    if config.is_jellyfin_like() {
+        let (token, user_id) =
+            match tauri::async_runtime::block_on(jellyfin::authenticate(&config)) {
+                Ok(auth) => auth,
+                Err(e) => {
+                    eprintln!("Stream re-authentication failed for {}: {}", server.server_name, e);
+                    return None;
+                }
+            };
+        if let Ok(conn) = app.state::<DbState>().write() {
+            if let Err(e) = db::servers::update_stream_server_token(&conn, &server.id, &token, &user_id) {
+                eprintln!("Failed to save refreshed token: {}", e);
+            }
+        }
+        config.access_token = Some(token);
+        config.user_id = Some(user_id);
+        // 前端缓存的服务器配置里还是旧 token
+        let _ = app.emit("stream:reauthenticated", &server.id);
+    }
+
+    Some(stream_url_internal(&config, &song_id))
+}
+
 /// 从流媒体服务器获取所有歌曲（内部函数）
-pub async fn fetch_stream_songs_internal(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, String> {
+pub async fn fetch_stream_songs_internal(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, CommandError> {
     if config.is_subsonic() {
         subsonic::fetch_all_songs(config).await
     } else {
@@ -16,7 +108,7 @@ pub async fn fetch_stream_songs_internal(config: &StreamServerConfig) -> Result<
 
 /// 测试流媒体服务器连接
 #[tauri::command]
-pub async fn test_stream_connection(config: StreamServerConfig) -> Result<ConnectionTestResult, String> {
+pub async fn test_stream_connection(config: StreamServerConfig) -> Result<ConnectionTestResult, CommandError> {
     if config.is_subsonic() {
         Ok(subsonic::test_connection(&config).await)
     } else {
@@ -26,7 +118,7 @@ pub async fn test_stream_connection(config: StreamServerConfig) -> Result<Connec
 
 /// 从流媒体服务器获取所有歌曲
 #[tauri::command]
-pub async fn fetch_stream_songs(config: StreamServerConfig) -> Result<Vec<ScannedSong>, String> {
+pub async fn fetch_stream_songs(config: StreamServerConfig) -> Result<Vec<ScannedSong>, CommandError> {
     if config.is_subsonic() {
         subsonic::fetch_all_songs(&config).await
     } else {
@@ -37,11 +129,7 @@ pub async fn fetch_stream_songs(config: StreamServerConfig) -> Result<Vec<Scanne
 /// 获取流媒体歌曲的流 URL
 #[tauri::command]
 pub fn get_stream_url(config: StreamServerConfig, song_id: String) -> String {
-    if config.is_subsonic() {
-        subsonic::get_stream_url(&config, &song_id)
-    } else {
-        jellyfin::get_stream_url(&config, &song_id)
-    }
+    stream_url_internal(&config, &song_id)
 }
 
 /// 获取流媒体歌曲歌词
@@ -56,25 +144,184 @@ pub async fn get_stream_lyrics(config: StreamServerConfig, song_id: String) -> O
 
 /// Jellyfin/Emby 认证并返回 token 和 userId
 #[tauri::command]
-pub async fn jellyfin_authenticate(config: StreamServerConfig) -> Result<(String, String), String> {
+pub async fn jellyfin_authenticate(config: StreamServerConfig) -> Result<(String, String), CommandError> {
     if config.is_jellyfin_like() {
         jellyfin::authenticate(&config).await
     } else {
-        Err("此命令仅适用于 Jellyfin/Emby 服务器".to_string())
+        Err(CommandError::new(ErrorCode::UnsupportedServer))
+    }
+}
+
+/// 聚合搜索默认超时（毫秒），超时的服务器结果直接丢弃
+const SEARCH_TIMEOUT_MS: u64 = 4000;
+
+/// 每个来源的默认结果数
+const SEARCH_LIMIT: u32 = 50;
+
+/// 本地曲库的来源名称
+const LOCAL_SOURCE: &str = "本地";
+
+/// 按关键词搜索流媒体服务器
+async fn search_server(config: &StreamServerConfig, query: &str, limit: u32) -> Result<Vec<ScannedSong>, CommandError> {
+    if config.is_subsonic() {
+        subsonic::search_songs(config, query, limit).await
+    } else {
+        jellyfin::search_songs(config, query, limit).await
     }
 }
 
+/// 把一条结果并入列表：同名同歌手且时长相近的视为同一首，只追加来源
+fn merge_hit(hits: &mut Vec<SourceSearchHit>, keys: &mut HashMap<String, Vec<usize>>, hit: SourceSearchHit) {
+    let key = match_key(&hit.title, &hit.artist);
+    let same = keys.get(&key).and_then(|indices| {
+        indices
+            .iter()
+            .copied()
+            .find(|&i| (hits[i].duration - hit.duration).abs() <= DURATION_TOLERANCE_SECS)
+    });
+
+    match same {
+        Some(i) => {
+            let existing = &mut hits[i];
+            for source in hit.sources {
+                if !existing.sources.contains(&source) {
+                    existing.sources.push(source);
+                }
+            }
+            if existing.song_id.is_none() {
+                existing.song_id = hit.song_id;
+            }
+            if existing.cover_url.is_none() {
+                existing.cover_url = hit.cover_url;
+            }
+        }
+        None => {
+            keys.entry(key).or_default().push(hits.len());
+            hits.push(hit);
+        }
+    }
+}
+
+/// 同时搜索本地曲库和所有已启用的流媒体服务器，合并去重后返回。
+/// 每个服务器最多等待 `timeout_ms`，超时或出错的记入 `failed_sources`。
+#[tauri::command]
+pub async fn search_all_sources(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    query: String,
+    limit: Option<u32>,
+    timeout_ms: Option<u64>,
+) -> Result<SourceSearchResult, CommandError> {
+    let query = query.trim().to_string();
+    let limit = limit.unwrap_or(SEARCH_LIMIT).clamp(1, 500);
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(SEARCH_TIMEOUT_MS));
+    if query.is_empty() {
+        return Ok(SourceSearchResult { hits: Vec::new(), failed_sources: Vec::new() });
+    }
+    telemetry::feature(&app, "search.allSources");
+
+    let (local, servers) = {
+        let conn = db.read()?;
+        let local = db::search::search_songs(&conn, &query, SearchMode::Standard, limit)?;
+        let servers: Vec<DbStreamServer> = db::servers::get_stream_servers(&conn)
+?
+            .into_iter()
+            .filter(|s| s.enabled)
+            .collect();
+        (local, servers)
+    };
+
+    let server_names: HashMap<&str, &str> = servers
+        .iter()
+        .map(|s| (s.id.as_str(), s.server_name.as_str()))
+        .collect();
+
+    let mut hits = Vec::new();
+    let mut keys = HashMap::new();
+    for song in local {
+        let source = match song.server_id.as_deref() {
+            Some(server_id) if song.source_type == "stream" => {
+                server_names.get(server_id).copied().unwrap_or(server_id).to_string()
+            }
+            _ => LOCAL_SOURCE.to_string(),
+        };
+        merge_hit(
+            &mut hits,
+            &mut keys,
+            SourceSearchHit {
+                song_id: Some(song.id),
+                title: song.title,
+                artist: song.artist,
+                album: song.album,
+                duration: song.duration,
+                server_id: song.server_id,
+                server_song_id: song.server_song_id,
+                cover_url: None,
+                sources: vec![source],
+            },
+        );
+    }
+
+    let searches = servers.iter().map(|server| {
+        let config = server_config(server);
+        let query = query.as_str();
+        async move { tokio::time::timeout(timeout, search_server(&config, query, limit)).await }
+    });
+    let results = join_all(searches).await;
+
+    let mut failed_sources = Vec::new();
+    for (server, result) in servers.iter().zip(results) {
+        let songs = match result {
+            Ok(Ok(songs)) => songs,
+            Ok(Err(e)) => {
+                eprintln!("Search failed on {}: {}", server.server_name, e);
+                telemetry::error(&app, "search", &e);
+                failed_sources.push(server.server_name.clone());
+                continue;
+            }
+            Err(_) => {
+                failed_sources.push(server.server_name.clone());
+                continue;
+            }
+        };
+
+        let conn = db.read()?;
+        for song in songs {
+            let song_id = db::queue::map_server_songs(&conn, &server.id, std::slice::from_ref(&song.id))
+    ?
+                .pop();
+            merge_hit(
+                &mut hits,
+                &mut keys,
+                SourceSearchHit {
+                    song_id,
+                    title: song.title,
+                    artist: song.artist,
+                    album: song.album,
+                    duration: song.duration,
+                    server_id: Some(server.id.clone()),
+                    server_song_id: Some(song.id),
+                    cover_url: song.cover_url,
+                    sources: vec![server.server_name.clone()],
+                },
+            );
+        }
+    }
+
+    Ok(SourceSearchResult { hits, failed_sources })
+}
+
 // ============ 向后兼容的旧命令（Subsonic API） ============
 
 /// 测试 Subsonic 服务器连接
 #[tauri::command]
-pub async fn test_subsonic_connection(config: StreamServerConfig) -> Result<ConnectionTestResult, String> {
+pub async fn test_subsonic_connection(config: StreamServerConfig) -> Result<ConnectionTestResult, CommandError> {
     Ok(subsonic::test_connection(&config).await)
 }
 
 /// 从 Subsonic 服务器获取所有歌曲
 #[tauri::command]
-pub async fn fetch_subsonic_songs(config: StreamServerConfig) -> Result<Vec<ScannedSong>, String> {
+pub async fn fetch_subsonic_songs(config: StreamServerConfig) -> Result<Vec<ScannedSong>, CommandError> {
     subsonic::fetch_all_songs(&config).await
 }
 
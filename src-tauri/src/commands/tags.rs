@@ -0,0 +1,76 @@
+//! Edit the tags of local songs, writing them back into the files
+
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::{self, DbSong, DbState};
+use crate::utils::audio::get_file_mtime;
+use crate::utils::fingerprint;
+use crate::utils::tag_writer::{self, TagEdit};
+
+/// 编辑本地歌曲的标签：写回音频文件并更新曲库，返回更新后的歌曲。
+/// 写入期间文件监听会忽略该文件，不会触发重新扫描
+#[tauri::command]
+pub async fn edit_song_tags(app: AppHandle, song_id: String, tags: TagEdit) -> Result<DbSong, String> {
+    let tags = tags.normalized();
+    if tags.title.is_empty() {
+        return Err("标题不能为空".to_string());
+    }
+
+    let song = tokio::task::spawn_blocking(move || write_song_tags(&app, &song_id, &tags))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(song)
+}
+
+fn write_song_tags(app: &AppHandle, song_id: &str, tags: &TagEdit) -> Result<DbSong, String> {
+    let db = app.state::<DbState>();
+    let song = {
+        let conn = db.read().map_err(|e| e.to_string())?;
+        db::songs::get_songs_by_ids(&conn, &[song_id.to_string()])
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("歌曲不存在: {}", song_id))?
+    };
+
+    if song.source_type != "local" {
+        return Err("只能编辑本地歌曲的标签".to_string());
+    }
+    // Local IDs are derived from the path; CUE tracks share one file and get their own IDs
+    if song.id != format!("{:x}", md5::compute(&song.file_path)) {
+        return Err("CUE 分轨的标签无法写回文件".to_string());
+    }
+
+    let path = Path::new(&song.file_path);
+    {
+        #[cfg(desktop)]
+        let _suppress = crate::watcher::desktop::suppress(path);
+        tag_writer::write_tags(path, tags)?;
+    }
+
+    let file_size = std::fs::metadata(path).map(|m| m.len() as i64).unwrap_or(song.file_size);
+    let content_hash = fingerprint::partial_content_hash(path).ok();
+
+    let updated = {
+        let conn = db.write().map_err(|e| e.to_string())?;
+        db::songs::update_song_tags(
+            &conn,
+            &song.id,
+            tags,
+            file_size,
+            get_file_mtime(path).ok(),
+            content_hash.as_deref(),
+        )
+        .map_err(|e| e.to_string())?;
+        db::songs::get_songs_by_ids(&conn, std::slice::from_ref(&song.id))
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("歌曲不存在: {}", song.id))?
+    };
+
+    let _ = app.emit("library-updated", ());
+    Ok(updated)
+}
@@ -0,0 +1,25 @@
+//! Opt-in usage statistics; consent itself is the `telemetry` setting
+
+use tauri::State;
+
+use crate::db::{self, DbState};
+use crate::telemetry::{TelemetryState, TelemetrySummary};
+
+/// Count a frontend feature use; ignored while telemetry is off
+#[tauri::command]
+pub fn telemetry_record(telemetry: State<'_, TelemetryState>, name: String) {
+    telemetry.0.feature(&name);
+}
+
+/// Consent state and the counters waiting for upload
+#[tauri::command]
+pub fn telemetry_summary(telemetry: State<'_, TelemetryState>) -> Result<TelemetrySummary, String> {
+    telemetry.0.summary()
+}
+
+/// Discard counters that haven't been uploaded yet
+#[tauri::command]
+pub fn telemetry_clear(db: State<'_, DbState>) -> Result<usize, String> {
+    let conn = db.write().map_err(|e| e.to_string())?;
+    db::telemetry::clear_counters(&conn).map_err(|e| e.to_string())
+}
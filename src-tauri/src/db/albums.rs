@@ -1,18 +1,35 @@
 //! Album and artist aggregation queries
 
-use rusqlite::{Connection, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Result, Row};
 use serde::{Deserialize, Serialize};
 
+use super::search::like_pattern;
+
 /// Aggregated album data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DbAlbum {
     pub id: String,
     pub name: String,
+    /// Album artist, or the track artist when untagged
     pub artist: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
     pub cover_hash: Option<String>,  // SHA256 hash for cover lookup
     pub stream_cover_url: Option<String>, // Cover URL from stream_info for stream songs
     pub song_count: i64,
+    /// Average DR value of the analyzed tracks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamic_range: Option<f32>,
+    /// Average star rating of the rated tracks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f32>,
+    /// When the newest song of the album was added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
 }
 
 /// Aggregated artist data
@@ -24,6 +41,36 @@ pub struct DbArtist {
     pub cover_hash: Option<String>,  // SHA256 hash for cover lookup
     pub stream_cover_url: Option<String>, // Cover URL from stream_info for stream songs
     pub song_count: i64,
+    /// Average star rating of the rated songs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f32>,
+    /// When the newest song of the artist was added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
+
+/// Aggregated genre data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbGenre {
+    pub id: String,
+    pub name: String,
+    pub cover_hash: Option<String>,  // SHA256 hash for cover lookup
+    pub stream_cover_url: Option<String>, // Cover URL from stream_info for stream songs
+    pub song_count: i64,
+    pub album_count: i64,
+}
+
+/// Stable album ID keyed on (album, album artist, year).
+/// Songs without an album artist tag fall back to their track artist, so
+/// same-named albums by different artists ("Greatest Hits") stay apart.
+pub fn album_id_for(album: &str, album_artist: Option<&str>, artist: &str, year: Option<u32>) -> String {
+    let album_artist = album_artist.map(str::trim).filter(|s| !s.is_empty()).unwrap_or(artist);
+    let key = match year {
+        Some(year) => format!("{}\u{1f}{}\u{1f}{}", album, album_artist, year),
+        None => format!("{}\u{1f}{}", album, album_artist),
+    };
+    format!("album-{:x}", md5::compute(key))
 }
 
 /// Extract coverUrl from stream_info JSON string
@@ -35,159 +82,353 @@ fn extract_cover_url(stream_info: &Option<String>) -> Option<String> {
     })
 }
 
-/// Get all albums aggregated from songs
-pub fn get_all_albums(conn: &Connection) -> Result<Vec<DbAlbum>> {
-    let mut stmt = conn.prepare(
-        "SELECT
-            album,
-            MIN(artist) as artist,
+/// Aggregate columns of an album, read by `album_from_row`
+const ALBUM_COLUMNS: &str = "album_id,
+            MIN(album) as album,
+            COALESCE(MAX(album_artist), MIN(artist)) as artist,
+            MAX(year) as year,
+            MAX(genre) as genre,
             MAX(cover_hash) as cover_hash,
             MAX(stream_info) as stream_info,
-            COUNT(*) as song_count
-         FROM songs
-         GROUP BY album
-         ORDER BY album COLLATE NOCASE"
-    )?;
+            COUNT(*) as song_count,
+            AVG(dynamic_range) as dynamic_range,
+            AVG(NULLIF(rating, 0)) as rating,
+            MAX(created_at) as created_at";
 
-    let albums = stmt.query_map([], |row| {
-        let album_name: String = row.get(0)?;
-        let artist: String = row.get(1)?;
-        let cover_hash: Option<String> = row.get(2)?;
-        let stream_info: Option<String> = row.get(3)?;
-        let song_count: i64 = row.get(4)?;
+/// Aggregate columns of an artist, read by `artist_from_row`
+const ARTIST_COLUMNS: &str = "artist,
+            MAX(cover_hash) as cover_hash,
+            MAX(stream_info) as stream_info,
+            COUNT(*) as song_count,
+            AVG(NULLIF(rating, 0)) as rating,
+            MAX(created_at) as created_at";
 
-        // Generate a stable ID from album name
-        let id = format!("album-{:x}", md5::compute(&album_name));
+/// Sort key for paginated album queries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlbumSort {
+    #[default]
+    Name,
+    Artist,
+    Year,
+    SongCount,
+    Rating,
+    DateAdded,
+}
 
-        // Extract cover URL from stream_info JSON
-        let stream_cover_url = extract_cover_url(&stream_info);
+/// Sort key for paginated artist queries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArtistSort {
+    #[default]
+    Name,
+    SongCount,
+    Rating,
+    DateAdded,
+}
 
-        Ok(DbAlbum {
-            id,
-            name: album_name,
-            artist,
-            cover_hash,
-            stream_cover_url,
-            song_count,
-        })
-    })?.collect::<Result<Vec<_>>>()?;
+/// Paginated album query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumPageQuery {
+    #[serde(default)]
+    pub offset: u32,
+    pub limit: u32,
+    #[serde(default)]
+    pub sort: AlbumSort,
+    #[serde(default)]
+    pub descending: bool,
+    /// Only albums whose name or artist contains this text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// Paginated artist query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistPageQuery {
+    #[serde(default)]
+    pub offset: u32,
+    pub limit: u32,
+    #[serde(default)]
+    pub sort: ArtistSort,
+    #[serde(default)]
+    pub descending: bool,
+    /// Only artists whose name contains this text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// One page of albums plus the total number of matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumPage {
+    pub albums: Vec<DbAlbum>,
+    pub total: i64,
+}
+
+/// One page of artists plus the total number of matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistPage {
+    pub artists: Vec<DbArtist>,
+    pub total: i64,
+}
+
+fn album_from_row(row: &Row) -> Result<DbAlbum> {
+    let id: String = row.get(0)?;
+    let album_name: String = row.get(1)?;
+    let artist: String = row.get(2)?;
+    let year: Option<u32> = row.get(3)?;
+    let genre: Option<String> = row.get(4)?;
+    let cover_hash: Option<String> = row.get(5)?;
+    let stream_info: Option<String> = row.get(6)?;
+    let song_count: i64 = row.get(7)?;
+    let dynamic_range: Option<f64> = row.get(8)?;
+    let rating: Option<f64> = row.get(9)?;
+    let created_at: Option<i64> = row.get(10)?;
+
+    // Extract cover URL from stream_info JSON
+    let stream_cover_url = extract_cover_url(&stream_info);
+
+    Ok(DbAlbum {
+        id,
+        name: album_name,
+        artist,
+        year,
+        genre,
+        cover_hash,
+        stream_cover_url,
+        song_count,
+        dynamic_range: dynamic_range.map(|dr| dr as f32),
+        rating: rating.map(|r| r as f32),
+        created_at,
+    })
+}
+
+fn artist_from_row(row: &Row) -> Result<DbArtist> {
+    let artist_name: String = row.get(0)?;
+    let cover_hash: Option<String> = row.get(1)?;
+    let stream_info: Option<String> = row.get(2)?;
+    let song_count: i64 = row.get(3)?;
+    let rating: Option<f64> = row.get(4)?;
+    let created_at: Option<i64> = row.get(5)?;
+
+    // Generate a stable ID from artist name
+    let id = format!("artist-{:x}", md5::compute(&artist_name));
+
+    // Extract cover URL from stream_info JSON
+    let stream_cover_url = extract_cover_url(&stream_info);
+
+    Ok(DbArtist {
+        id,
+        name: artist_name,
+        cover_hash,
+        stream_cover_url,
+        song_count,
+        rating: rating.map(|r| r as f32),
+        created_at,
+    })
+}
+
+/// Get all albums aggregated from songs
+pub fn get_all_albums(conn: &Connection) -> Result<Vec<DbAlbum>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+         FROM unified_songs
+         GROUP BY album_id
+         ORDER BY album COLLATE NOCASE, artist COLLATE NOCASE, year",
+        ALBUM_COLUMNS
+    ))?;
+
+    let albums = stmt.query_map([], album_from_row)?.collect::<Result<Vec<_>>>()?;
 
     Ok(albums)
 }
 
+/// One page of albums, sorted and optionally filtered by name or artist
+pub fn get_albums_page(conn: &Connection, query: &AlbumPageQuery) -> Result<AlbumPage> {
+    let filter = query.filter.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    let having = if filter.is_some() {
+        " HAVING MIN(album) LIKE ?1 ESCAPE '\\' OR COALESCE(MAX(album_artist), MIN(artist)) LIKE ?1 ESCAPE '\\'"
+    } else {
+        ""
+    };
+    let pattern = Value::from(filter.map(like_pattern));
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM (SELECT 1 FROM unified_songs GROUP BY album_id{})", having),
+        params_from_iter(filter.map(|_| &pattern)),
+        |row| row.get(0),
+    )?;
+
+    let direction = if query.descending { "DESC" } else { "ASC" };
+    let order = match query.sort {
+        AlbumSort::Name => format!("album COLLATE NOCASE {d}, artist COLLATE NOCASE {d}, year {d}", d = direction),
+        AlbumSort::Artist => format!("artist COLLATE NOCASE {d}, year {d}, album COLLATE NOCASE {d}", d = direction),
+        // Albums without a year / rating go last either way
+        AlbumSort::Year => format!("year IS NULL, year {}, album COLLATE NOCASE", direction),
+        AlbumSort::SongCount => format!("song_count {}, album COLLATE NOCASE", direction),
+        AlbumSort::Rating => format!("rating IS NULL, rating {}, album COLLATE NOCASE", direction),
+        AlbumSort::DateAdded => format!("created_at {}, album COLLATE NOCASE", direction),
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+         FROM unified_songs
+         GROUP BY album_id{}
+         ORDER BY {}
+         LIMIT ?2 OFFSET ?3",
+        ALBUM_COLUMNS, having, order
+    ))?;
+    let albums = stmt
+        .query_map(params![pattern, query.limit, query.offset], album_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(AlbumPage { albums, total })
+}
+
 /// Get all artists aggregated from songs
 pub fn get_all_artists(conn: &Connection) -> Result<Vec<DbArtist>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+         FROM unified_songs
+         GROUP BY artist
+         ORDER BY artist COLLATE NOCASE",
+        ARTIST_COLUMNS
+    ))?;
+
+    let artists = stmt.query_map([], artist_from_row)?.collect::<Result<Vec<_>>>()?;
+
+    Ok(artists)
+}
+
+/// One page of artists, sorted and optionally filtered by name
+pub fn get_artists_page(conn: &Connection, query: &ArtistPageQuery) -> Result<ArtistPage> {
+    let filter = query.filter.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    let where_clause = if filter.is_some() { " WHERE artist LIKE ?1 ESCAPE '\\'" } else { "" };
+    let pattern = Value::from(filter.map(like_pattern));
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(DISTINCT artist) FROM unified_songs{}", where_clause),
+        params_from_iter(filter.map(|_| &pattern)),
+        |row| row.get(0),
+    )?;
+
+    let direction = if query.descending { "DESC" } else { "ASC" };
+    let order = match query.sort {
+        ArtistSort::Name => format!("artist COLLATE NOCASE {}", direction),
+        ArtistSort::SongCount => format!("song_count {}, artist COLLATE NOCASE", direction),
+        ArtistSort::Rating => format!("rating IS NULL, rating {}, artist COLLATE NOCASE", direction),
+        ArtistSort::DateAdded => format!("created_at {}, artist COLLATE NOCASE", direction),
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+         FROM unified_songs{}
+         GROUP BY artist
+         ORDER BY {}
+         LIMIT ?2 OFFSET ?3",
+        ARTIST_COLUMNS, where_clause, order
+    ))?;
+    let artists = stmt
+        .query_map(params![pattern, query.limit, query.offset], artist_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ArtistPage { artists, total })
+}
+
+/// Get all genres aggregated from songs; untagged songs are left out
+pub fn get_all_genres(conn: &Connection) -> Result<Vec<DbGenre>> {
     let mut stmt = conn.prepare(
         "SELECT
-            artist,
+            MIN(TRIM(genre)) as genre,
             MAX(cover_hash) as cover_hash,
             MAX(stream_info) as stream_info,
-            COUNT(*) as song_count
-         FROM songs
-         GROUP BY artist
-         ORDER BY artist COLLATE NOCASE"
+            COUNT(*) as song_count,
+            COUNT(DISTINCT album_id) as album_count
+         FROM unified_songs
+         WHERE TRIM(COALESCE(genre, '')) <> ''
+         GROUP BY TRIM(genre) COLLATE NOCASE
+         ORDER BY genre COLLATE NOCASE"
     )?;
 
-    let artists = stmt.query_map([], |row| {
-        let artist_name: String = row.get(0)?;
+    let genres = stmt.query_map([], |row| {
+        let genre_name: String = row.get(0)?;
         let cover_hash: Option<String> = row.get(1)?;
         let stream_info: Option<String> = row.get(2)?;
         let song_count: i64 = row.get(3)?;
+        let album_count: i64 = row.get(4)?;
 
-        // Generate a stable ID from artist name
-        let id = format!("artist-{:x}", md5::compute(&artist_name));
+        // Generate a stable ID from genre name (case-insensitive, like the grouping)
+        let id = format!("genre-{:x}", md5::compute(genre_name.to_lowercase()));
 
         // Extract cover URL from stream_info JSON
         let stream_cover_url = extract_cover_url(&stream_info);
 
-        Ok(DbArtist {
+        Ok(DbGenre {
             id,
-            name: artist_name,
+            name: genre_name,
             cover_hash,
             stream_cover_url,
             song_count,
+            album_count,
         })
     })?.collect::<Result<Vec<_>>>()?;
 
-    Ok(artists)
+    Ok(genres)
 }
 
-/// Get songs for a specific album
-#[allow(dead_code)]
-pub fn get_songs_by_album(conn: &Connection, album: &str) -> Result<Vec<super::DbSong>> {
+/// Get songs tagged with a genre (case-insensitive)
+pub fn get_songs_by_genre(conn: &Connection, genre: &str) -> Result<Vec<super::DbSong>> {
     let mut stmt = conn.prepare(
         "SELECT id, title, artist, album, duration, file_path, file_size,
                 is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
-                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels
-         FROM songs
-         WHERE album = ?1
-         ORDER BY title COLLATE NOCASE"
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM unified_songs
+         WHERE TRIM(genre) = TRIM(?1) COLLATE NOCASE
+         ORDER BY artist COLLATE NOCASE, album COLLATE NOCASE, title COLLATE NOCASE"
     )?;
 
-    let songs = stmt.query_map([album], |row| {
-        Ok(super::DbSong {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            artist: row.get(2)?,
-            album: row.get(3)?,
-            duration: row.get(4)?,
-            file_path: row.get(5)?,
-            file_size: row.get(6)?,
-            is_hr: row.get::<_, Option<i32>>(7)?.map(|v| v != 0),
-            is_sq: row.get::<_, Option<i32>>(8)?.map(|v| v != 0),
-            cover_hash: row.get(9)?,
-            source_type: row.get(10)?,
-            server_id: row.get(11)?,
-            server_song_id: row.get(12)?,
-            stream_info: row.get(13)?,
-            file_modified: row.get(14)?,
-            format: row.get(15)?,
-            bit_depth: row.get::<_, Option<u8>>(16)?,
-            sample_rate: row.get::<_, Option<u32>>(17)?,
-            bitrate: row.get::<_, Option<u32>>(18)?,
-            channels: row.get::<_, Option<u8>>(19)?,
-        })
-    })?.collect::<Result<Vec<_>>>()?;
+    let songs = stmt.query_map([genre], super::songs::song_from_row)?.collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
+/// Get songs of an album (by `album_id`) in disc / track order
+pub fn get_songs_by_album(conn: &Connection, album_id: &str) -> Result<Vec<super::DbSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM unified_songs
+         WHERE album_id = ?1
+         ORDER BY COALESCE(disc_no, 1), track_no IS NULL, track_no, file_path COLLATE NOCASE, title COLLATE NOCASE"
+    )?;
+
+    let songs = stmt.query_map([album_id], super::songs::song_from_row)?.collect::<Result<Vec<_>>>()?;
 
     Ok(songs)
 }
 
 /// Get songs for a specific artist
-#[allow(dead_code)]
 pub fn get_songs_by_artist(conn: &Connection, artist: &str) -> Result<Vec<super::DbSong>> {
     let mut stmt = conn.prepare(
         "SELECT id, title, artist, album, duration, file_path, file_size,
                 is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
-                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels
-         FROM songs
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM unified_songs
          WHERE artist = ?1
-         ORDER BY album COLLATE NOCASE, title COLLATE NOCASE"
+         ORDER BY album COLLATE NOCASE, COALESCE(disc_no, 1), track_no IS NULL, track_no, title COLLATE NOCASE"
     )?;
 
-    let songs = stmt.query_map([artist], |row| {
-        Ok(super::DbSong {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            artist: row.get(2)?,
-            album: row.get(3)?,
-            duration: row.get(4)?,
-            file_path: row.get(5)?,
-            file_size: row.get(6)?,
-            is_hr: row.get::<_, Option<i32>>(7)?.map(|v| v != 0),
-            is_sq: row.get::<_, Option<i32>>(8)?.map(|v| v != 0),
-            cover_hash: row.get(9)?,
-            source_type: row.get(10)?,
-            server_id: row.get(11)?,
-            server_song_id: row.get(12)?,
-            stream_info: row.get(13)?,
-            file_modified: row.get(14)?,
-            format: row.get(15)?,
-            bit_depth: row.get::<_, Option<u8>>(16)?,
-            sample_rate: row.get::<_, Option<u32>>(17)?,
-            bitrate: row.get::<_, Option<u32>>(18)?,
-            channels: row.get::<_, Option<u8>>(19)?,
-        })
-    })?.collect::<Result<Vec<_>>>()?;
+    let songs = stmt.query_map([artist], super::songs::song_from_row)?.collect::<Result<Vec<_>>>()?;
 
     Ok(songs)
 }
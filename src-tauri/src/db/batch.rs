@@ -0,0 +1,94 @@
+//! Multiple mutations in one transaction
+
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+
+/// A single mutation inside `db_batch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum DbBatchOp {
+    /// Create a playlist; pass `id` to reference it from later ops in the same batch
+    CreatePlaylist { id: Option<String>, name: String },
+    RenamePlaylist { playlist_id: String, name: String },
+    SetPlaylistCover { playlist_id: String, cover: Option<String> },
+    DeletePlaylist { playlist_id: String },
+    AddToPlaylist { playlist_id: String, song_ids: Vec<String> },
+    RemoveFromPlaylist { playlist_id: String, song_ids: Vec<String> },
+    DeleteSongs { song_ids: Vec<String> },
+    SetSongExtra { song_id: String, key: String, value: Option<serde_json::Value> },
+}
+
+/// Outcome of one op
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbBatchResult {
+    /// Rows affected
+    pub affected: usize,
+    /// ID of a created entity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+impl DbBatchResult {
+    fn affected(affected: usize) -> Self {
+        Self { affected, id: None }
+    }
+}
+
+/// Run all ops in one transaction; any failure rolls back the whole batch.
+/// Returns the index of the failing op together with the error.
+pub fn execute_batch(
+    conn: &mut Connection,
+    ops: &[DbBatchOp],
+) -> std::result::Result<Vec<DbBatchResult>, (usize, rusqlite::Error)> {
+    let tx = conn.transaction().map_err(|e| (0, e))?;
+
+    let mut results = Vec::with_capacity(ops.len());
+    for (index, op) in ops.iter().enumerate() {
+        let result = execute_op(&tx, op).map_err(|e| (index, e))?;
+        results.push(result);
+    }
+
+    tx.commit().map_err(|e| (ops.len(), e))?;
+    Ok(results)
+}
+
+fn execute_op(conn: &Connection, op: &DbBatchOp) -> Result<DbBatchResult> {
+    use super::{extra, playlists};
+
+    let result = match op {
+        DbBatchOp::CreatePlaylist { id, name } => {
+            let id = id.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            playlists::create_playlist(conn, &id, name)?;
+            DbBatchResult { affected: 1, id: Some(id) }
+        }
+        DbBatchOp::RenamePlaylist { playlist_id, name } => {
+            DbBatchResult::affected(playlists::rename_playlist(conn, playlist_id, name)?)
+        }
+        DbBatchOp::SetPlaylistCover { playlist_id, cover } => {
+            DbBatchResult::affected(playlists::set_playlist_cover(conn, playlist_id, cover.as_deref())?)
+        }
+        DbBatchOp::DeletePlaylist { playlist_id } => {
+            DbBatchResult::affected(playlists::delete_playlist(conn, playlist_id)?)
+        }
+        DbBatchOp::AddToPlaylist { playlist_id, song_ids } => {
+            DbBatchResult::affected(playlists::add_songs_to_playlist(conn, playlist_id, song_ids)?)
+        }
+        DbBatchOp::RemoveFromPlaylist { playlist_id, song_ids } => {
+            DbBatchResult::affected(playlists::remove_songs_from_playlist(conn, playlist_id, song_ids)?)
+        }
+        DbBatchOp::DeleteSongs { song_ids } => {
+            let mut affected = 0;
+            for song_id in song_ids {
+                affected += conn.execute("DELETE FROM songs WHERE id = ?1", [song_id])?;
+            }
+            DbBatchResult::affected(affected)
+        }
+        DbBatchOp::SetSongExtra { song_id, key, value } => {
+            extra::set_song_extra(conn, song_id, key, value.as_ref())?;
+            DbBatchResult::affected(1)
+        }
+    };
+
+    Ok(result)
+}
@@ -0,0 +1,47 @@
+//! Chapters of audiobooks and other chaptered files (m4b / m4a chapter lists)
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+/// One chapter, starting at `start_secs` and running until the next one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub title: String,
+    pub start_secs: f64,
+}
+
+/// Replace the stored chapters of a song
+pub(crate) fn replace_song_chapters(conn: &Connection, song_id: &str, chapters: &[Chapter]) -> Result<()> {
+    conn.execute("DELETE FROM chapters WHERE song_id = ?1", [song_id])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO chapters (song_id, position, title, start_secs)
+         VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for (position, chapter) in chapters.iter().enumerate() {
+        stmt.execute(params![song_id, position as i64, chapter.title, chapter.start_secs])?;
+    }
+
+    Ok(())
+}
+
+/// Chapters of one song in playback order; empty when it has none
+pub fn get_song_chapters(conn: &Connection, song_id: &str) -> Result<Vec<Chapter>> {
+    let mut stmt = conn.prepare(
+        "SELECT title, start_secs FROM chapters
+         WHERE song_id = ?1
+         ORDER BY start_secs, position"
+    )?;
+
+    let chapters = stmt
+        .query_map([song_id], |row| {
+            Ok(Chapter {
+                title: row.get(0)?,
+                start_secs: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(chapters)
+}
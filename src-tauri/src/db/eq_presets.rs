@@ -0,0 +1,67 @@
+//! User equalizer presets
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+/// A named equalizer preset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbEqPreset {
+    pub name: String,
+    /// Gain per band in dB
+    pub gains: [f32; 10],
+    /// Gain applied before the bands in dB
+    #[serde(default)]
+    pub preamp: f32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// All presets, by name
+pub fn get_eq_presets(conn: &Connection) -> Result<Vec<DbEqPreset>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, gains, preamp, enabled, updated_at FROM eq_presets ORDER BY name COLLATE NOCASE"
+    )?;
+
+    let presets = stmt.query_map([], |row| {
+        let gains: String = row.get(1)?;
+        Ok(DbEqPreset {
+            name: row.get(0)?,
+            // Gains are written as a JSON array; a damaged value reads as flat
+            gains: serde_json::from_str(&gains).unwrap_or([0.0; 10]),
+            preamp: row.get(2)?,
+            enabled: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    })?.collect::<Result<Vec<_>>>()?;
+
+    Ok(presets)
+}
+
+/// Insert a preset, or overwrite the one with the same name
+pub fn save_eq_preset(conn: &Connection, preset: &DbEqPreset) -> Result<()> {
+    let gains = serde_json::to_string(&preset.gains).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT INTO eq_presets (name, gains, preamp, enabled, updated_at)
+         VALUES (?1, ?2, ?3, ?4, strftime('%s','now'))
+         ON CONFLICT(name) DO UPDATE SET
+            gains = excluded.gains,
+            preamp = excluded.preamp,
+            enabled = excluded.enabled,
+            updated_at = excluded.updated_at",
+        params![preset.name, gains, preset.preamp, preset.enabled],
+    )?;
+    Ok(())
+}
+
+/// Delete a preset; returns whether it existed
+pub fn delete_eq_preset(conn: &Connection, name: &str) -> Result<bool> {
+    let removed = conn.execute("DELETE FROM eq_presets WHERE name = ?1", [name])?;
+    Ok(removed > 0)
+}
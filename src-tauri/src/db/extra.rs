@@ -0,0 +1,157 @@
+//! Custom per-song metadata stored as JSON values by key
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Get all custom metadata of a song
+pub fn get_song_extra(conn: &Connection, song_id: &str) -> Result<HashMap<String, serde_json::Value>> {
+    let mut stmt = conn.prepare(
+        "SELECT key, value FROM song_extra WHERE song_id = ?1"
+    )?;
+
+    let rows = stmt.query_map([song_id], |row| {
+        let key: String = row.get(0)?;
+        let value: String = row.get(1)?;
+        Ok((key, value))
+    })?;
+
+    let mut extra = HashMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        // Values are always written as JSON; fall back to a plain string if edited by hand
+        let value = serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+        extra.insert(key, value);
+    }
+
+    Ok(extra)
+}
+
+/// Set (or with `None`, remove) one custom metadata key of a song
+pub fn set_song_extra(
+    conn: &Connection,
+    song_id: &str,
+    key: &str,
+    value: Option<&serde_json::Value>,
+) -> Result<()> {
+    match value {
+        Some(value) => {
+            conn.execute(
+                "INSERT INTO song_extra (song_id, key, value, updated_at)
+                 VALUES (?1, ?2, ?3, strftime('%s','now'))
+                 ON CONFLICT(song_id, key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = excluded.updated_at",
+                params![song_id, key, value.to_string()],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM song_extra WHERE song_id = ?1 AND key = ?2",
+                params![song_id, key],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Key of the resume position (seconds) of long tracks
+const RESUME_KEY: &str = "resume_position";
+
+/// Saved resume position of a song in seconds
+pub fn get_resume_position(conn: &Connection, song_id: &str) -> Result<Option<f64>> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM song_extra WHERE song_id = ?1 AND key = ?2",
+            params![song_id, RESUME_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(value.and_then(|v| v.parse::<f64>().ok()))
+}
+
+/// Save (or with `None`, clear) the resume position of a song
+pub fn set_resume_position(conn: &Connection, song_id: &str, position_secs: Option<f64>) -> Result<()> {
+    let value = position_secs.map(|p| serde_json::json!(p));
+    set_song_extra(conn, song_id, RESUME_KEY, value.as_ref())
+}
+
+/// Keys of the per-song cue-in / cue-out points (seconds)
+const CUE_IN_KEY: &str = "cue_in";
+const CUE_OUT_KEY: &str = "cue_out";
+
+/// Where playback of a song starts and ends, e.g. to skip applause on live recordings
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CuePoints {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cue_in: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cue_out: Option<f64>,
+}
+
+/// Cue points of a song; unset points are `None`
+pub fn get_cue_points(conn: &Connection, song_id: &str) -> Result<CuePoints> {
+    let extra = get_song_extra(conn, song_id)?;
+    let seconds = |key: &str| extra.get(key).and_then(serde_json::Value::as_f64);
+    Ok(CuePoints {
+        cue_in: seconds(CUE_IN_KEY),
+        cue_out: seconds(CUE_OUT_KEY),
+    })
+}
+
+/// Save the cue points of a song; `None` clears a point
+pub fn set_cue_points(conn: &Connection, song_id: &str, cues: &CuePoints) -> Result<()> {
+    let cue_in = cues.cue_in.map(|secs| serde_json::json!(secs));
+    let cue_out = cues.cue_out.map(|secs| serde_json::json!(secs));
+    set_song_extra(conn, song_id, CUE_IN_KEY, cue_in.as_ref())?;
+    set_song_extra(conn, song_id, CUE_OUT_KEY, cue_out.as_ref())
+}
+
+/// Keys of the bounds (seconds) of a track split from a whole-file image by its cue sheet
+const TRACK_START_KEY: &str = "track_start";
+const TRACK_END_KEY: &str = "track_end";
+
+/// Save the bounds of a cue-split track. Written by the scanner and kept apart
+/// from the cue points the user edits.
+pub fn set_track_bounds(conn: &Connection, song_id: &str, bounds: &CuePoints) -> Result<()> {
+    let start = bounds.cue_in.map(|secs| serde_json::json!(secs));
+    let end = bounds.cue_out.map(|secs| serde_json::json!(secs));
+    set_song_extra(conn, song_id, TRACK_START_KEY, start.as_ref())?;
+    set_song_extra(conn, song_id, TRACK_END_KEY, end.as_ref())
+}
+
+/// Set the cue points of a song unless the user already has some
+pub fn seed_cue_points(conn: &Connection, song_id: &str, cues: &CuePoints) -> Result<()> {
+    let current = get_cue_points(conn, song_id)?;
+    if current.cue_in.is_some() || current.cue_out.is_some() {
+        return Ok(());
+    }
+    set_cue_points(conn, song_id, cues)
+}
+
+/// Where playback of a song starts and ends: its cue points, kept inside the
+/// bounds of a cue-split track
+pub fn get_playback_range(conn: &Connection, song_id: &str) -> Result<CuePoints> {
+    let extra = get_song_extra(conn, song_id)?;
+    let seconds = |key: &str| extra.get(key).and_then(serde_json::Value::as_f64);
+    let (start, end) = (seconds(TRACK_START_KEY), seconds(TRACK_END_KEY));
+
+    let cue_in = match (seconds(CUE_IN_KEY), start) {
+        (Some(cue_in), Some(start)) => Some(cue_in.max(start)),
+        (cue_in, start) => cue_in.or(start),
+    };
+    let cue_out = match (seconds(CUE_OUT_KEY), end) {
+        (Some(cue_out), Some(end)) => Some(cue_out.min(end)),
+        (cue_out, end) => cue_out.or(end),
+    };
+    // Cue points that leave nothing of the track fall back to its bounds
+    if let (Some(cue_in), Some(cue_out)) = (cue_in, cue_out) {
+        if cue_out <= cue_in {
+            return Ok(CuePoints { cue_in: start, cue_out: end });
+        }
+    }
+    Ok(CuePoints { cue_in, cue_out })
+}
@@ -0,0 +1,126 @@
+//! Folder view: directory hierarchy derived from the paths of local songs
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+use super::songs::{song_from_row, DbSong};
+
+/// One folder of the tree
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbFolder {
+    pub path: String,
+    pub name: String,
+    /// Songs directly in this folder
+    pub song_count: usize,
+    /// Songs in this folder and all subfolders
+    pub total_songs: usize,
+    pub children: Vec<DbFolder>,
+}
+
+#[derive(Default)]
+struct FolderBuilder {
+    path: PathBuf,
+    song_count: usize,
+    children: BTreeMap<String, FolderBuilder>,
+}
+
+impl FolderBuilder {
+    fn insert(&mut self, dir: &Path, songs: usize) {
+        let mut node = self;
+        for component in dir.components() {
+            let key = component.as_os_str().to_string_lossy().into_owned();
+            let parent = node.path.clone();
+            node = node.children.entry(key).or_insert_with(|| FolderBuilder {
+                path: parent.join(component),
+                ..Default::default()
+            });
+        }
+        node.song_count += songs;
+    }
+
+    fn build(self) -> DbFolder {
+        let name = match self.path.components().next_back() {
+            Some(Component::Normal(name)) => name.to_string_lossy().into_owned(),
+            // Filesystem roots (`/`, `C:\`) are named by their path
+            _ => self.path.to_string_lossy().into_owned(),
+        };
+        let mut children: Vec<DbFolder> = self.children.into_values().map(FolderBuilder::build).collect();
+        children.sort_by_cached_key(|child| child.name.to_lowercase());
+        DbFolder {
+            path: self.path.to_string_lossy().into_owned(),
+            name,
+            song_count: self.song_count,
+            total_songs: self.song_count + children.iter().map(|c| c.total_songs).sum::<usize>(),
+            children,
+        }
+    }
+}
+
+/// Folder tree of all local songs. Leading folders that hold nothing but a
+/// single subfolder are skipped, so the tree starts at the music folders.
+pub fn get_folder_tree(conn: &Connection) -> Result<Vec<DbFolder>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_path, COUNT(*) FROM songs
+         WHERE source_type = 'local' AND file_path != '' AND missing_since IS NULL
+         GROUP BY file_path"
+    )?;
+    let files = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut root = FolderBuilder::default();
+    for (file_path, songs) in &files {
+        if let Some(dir) = Path::new(file_path).parent() {
+            root.insert(dir, *songs as usize);
+        }
+    }
+
+    let mut top = root;
+    while top.song_count == 0 && top.children.len() == 1 {
+        top = top.children.into_values().next().unwrap_or_default();
+    }
+    if top.path.as_os_str().is_empty() {
+        let mut folders: Vec<DbFolder> = top.children.into_values().map(FolderBuilder::build).collect();
+        folders.sort_by_cached_key(|folder| folder.name.to_lowercase());
+        Ok(folders)
+    } else {
+        Ok(vec![top.build()])
+    }
+}
+
+/// Local songs in a folder, in file order; with `recursive` also those in its subfolders
+pub fn get_songs_in_folder(conn: &Connection, path: &str, recursive: bool) -> Result<Vec<DbSong>> {
+    let mut prefix = path.to_string();
+    if !prefix.ends_with(MAIN_SEPARATOR) && !prefix.ends_with('/') {
+        prefix.push(MAIN_SEPARATOR);
+    }
+
+    // Exact prefix comparison: LIKE would be case-insensitive and treat `_` as a wildcard
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM songs
+         WHERE source_type = 'local' AND missing_since IS NULL
+           AND substr(file_path, 1, length(?1)) = ?1
+         ORDER BY file_path, COALESCE(disc_no, 1), track_no"
+    )?;
+
+    let folder = Path::new(path);
+    let songs = stmt
+        .query_map([&prefix], song_from_row)?
+        .filter(|song| {
+            recursive
+                || song
+                    .as_ref()
+                    .map_or(true, |s| Path::new(&s.file_path).parent() == Some(folder))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
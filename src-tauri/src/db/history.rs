@@ -0,0 +1,221 @@
+//! Play history and listening statistics
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+use super::songs::{song_from_row, DbSong};
+
+/// Time range for listening statistics
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ListeningRange {
+    /// The last N days up to now
+    LastDays { days: u32 },
+    /// A calendar year (local time), for year-in-review
+    Year { year: i32 },
+    All,
+}
+
+/// Listening time aggregated over one day or week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningBucket {
+    /// "YYYY-MM-DD" for days, "YYYY-Www" for weeks
+    pub period: String,
+    pub seconds: f64,
+    pub plays: i64,
+}
+
+/// A ranked artist, album or song
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopEntry {
+    pub name: String,
+    /// Artist for albums and songs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    pub seconds: f64,
+    pub plays: i64,
+}
+
+/// Listening statistics for a range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningStats {
+    pub total_seconds: f64,
+    pub total_plays: i64,
+    pub unique_songs: i64,
+    pub unique_artists: i64,
+    pub daily: Vec<ListeningBucket>,
+    pub weekly: Vec<ListeningBucket>,
+    pub top_artists: Vec<TopEntry>,
+    pub top_albums: Vec<TopEntry>,
+    pub top_songs: Vec<TopEntry>,
+    /// Day with the most listening time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busiest_day: Option<ListeningBucket>,
+}
+
+const TOP_LIMIT: i64 = 10;
+
+/// Record a listen of a song; returns false if the song is unknown
+pub fn record_listen(conn: &Connection, song_id: &str, listened_secs: f64) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT INTO play_history (song_id, title, artist, album, listened_secs)
+         SELECT id, title, artist, album, ?2 FROM songs WHERE id = ?1",
+        params![song_id, listened_secs.max(0.0)],
+    )?;
+    Ok(inserted > 0)
+}
+
+/// Count a play of a song (played past half); returns false if the song is unknown
+pub fn record_play(conn: &Connection, song_id: &str) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE songs SET play_count = play_count + 1, last_played_at = strftime('%s','now')
+         WHERE id = ?1",
+        [song_id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Songs with the most counted plays, most played first
+pub fn get_most_played(conn: &Connection, limit: u32) -> Result<Vec<DbSong>> {
+    played_songs(conn, "play_count > 0", "play_count DESC, last_played_at DESC", limit)
+}
+
+/// Songs by the time of their last counted play, latest first
+pub fn get_recently_played(conn: &Connection, limit: u32) -> Result<Vec<DbSong>> {
+    played_songs(conn, "last_played_at IS NOT NULL", "last_played_at DESC", limit)
+}
+
+fn played_songs(conn: &Connection, condition: &str, order: &str, limit: u32) -> Result<Vec<DbSong>> {
+    let sql = format!(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM songs
+         WHERE missing_since IS NULL AND {}
+         ORDER BY {}
+         LIMIT ?1",
+        condition, order
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let songs = stmt
+        .query_map([limit], song_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(songs)
+}
+
+/// Unix timestamp bounds [start, end) for a range
+fn range_bounds(conn: &Connection, range: ListeningRange) -> Result<(i64, i64)> {
+    match range {
+        ListeningRange::LastDays { days } => {
+            let now: i64 = conn.query_row(
+                "SELECT CAST(strftime('%s','now') AS INTEGER)",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok((now - days as i64 * 86400, i64::MAX))
+        }
+        ListeningRange::Year { year } => conn.query_row(
+            "SELECT CAST(strftime('%s', ?1 || '-01-01', 'utc') AS INTEGER),
+                    CAST(strftime('%s', ?1 || '-01-01', '+1 year', 'utc') AS INTEGER)",
+            [format!("{:04}", year)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ),
+        ListeningRange::All => Ok((0, i64::MAX)),
+    }
+}
+
+/// Listening time per period; `format` is a strftime pattern applied in local time
+fn get_buckets(conn: &Connection, format: &str, start: i64, end: i64) -> Result<Vec<ListeningBucket>> {
+    let mut stmt = conn.prepare(
+        "SELECT strftime(?1, played_at, 'unixepoch', 'localtime') AS period,
+                SUM(listened_secs), COUNT(*)
+         FROM play_history
+         WHERE played_at >= ?2 AND played_at < ?3
+         GROUP BY period
+         ORDER BY period",
+    )?;
+
+    let buckets = stmt.query_map(params![format, start, end], |row| {
+        Ok(ListeningBucket {
+            period: row.get(0)?,
+            seconds: row.get(1)?,
+            plays: row.get(2)?,
+        })
+    })?.collect::<Result<Vec<_>>>()?;
+
+    Ok(buckets)
+}
+
+/// Top entries grouped by `group_cols`; the first column is the name, an optional second the artist
+fn get_top(conn: &Connection, group_cols: &str, with_artist: bool, start: i64, end: i64) -> Result<Vec<TopEntry>> {
+    let sql = format!(
+        "SELECT {cols}, SUM(listened_secs) AS seconds, COUNT(*)
+         FROM play_history
+         WHERE played_at >= ?1 AND played_at < ?2
+         GROUP BY {cols}
+         ORDER BY seconds DESC
+         LIMIT ?3",
+        cols = group_cols
+    );
+    let mut stmt = conn.prepare(&sql)?;
+
+    let entries = stmt.query_map(params![start, end, TOP_LIMIT], |row| {
+        if with_artist {
+            Ok(TopEntry {
+                name: row.get(0)?,
+                artist: row.get(1)?,
+                seconds: row.get(2)?,
+                plays: row.get(3)?,
+            })
+        } else {
+            Ok(TopEntry {
+                name: row.get(0)?,
+                artist: None,
+                seconds: row.get(1)?,
+                plays: row.get(2)?,
+            })
+        }
+    })?.collect::<Result<Vec<_>>>()?;
+
+    Ok(entries)
+}
+
+/// Aggregate listening statistics for a range
+pub fn get_listening_stats(conn: &Connection, range: ListeningRange) -> Result<ListeningStats> {
+    let (start, end) = range_bounds(conn, range)?;
+
+    let (total_seconds, total_plays, unique_songs, unique_artists) = conn.query_row(
+        "SELECT COALESCE(SUM(listened_secs), 0.0), COUNT(*),
+                COUNT(DISTINCT song_id), COUNT(DISTINCT artist)
+         FROM play_history
+         WHERE played_at >= ?1 AND played_at < ?2",
+        params![start, end],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+
+    let daily = get_buckets(conn, "%Y-%m-%d", start, end)?;
+    let weekly = get_buckets(conn, "%Y-W%W", start, end)?;
+
+    let busiest_day = daily
+        .iter()
+        .max_by(|a, b| a.seconds.total_cmp(&b.seconds))
+        .cloned();
+
+    Ok(ListeningStats {
+        total_seconds,
+        total_plays,
+        unique_songs,
+        unique_artists,
+        top_artists: get_top(conn, "artist", false, start, end)?,
+        top_albums: get_top(conn, "album, artist", true, start, end)?,
+        top_songs: get_top(conn, "title, artist", true, start, end)?,
+        daily,
+        weekly,
+        busiest_day,
+    })
+}
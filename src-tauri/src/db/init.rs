@@ -1,9 +1,10 @@
 //! Database initialization and migration
 
-use rusqlite::{Connection, Result};
-use std::path::Path;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, DatabaseName, OpenFlags, Result};
+use std::path::{Path, PathBuf};
 
-const CURRENT_SCHEMA_VERSION: i32 = 3;
+const CURRENT_SCHEMA_VERSION: i32 = 31;
 
 /// Initialize the database with tables and indexes
 pub fn init_db(conn: &Connection) -> Result<()> {
@@ -15,13 +16,7 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         [],
     )?;
 
-    // Check current version
-    let current_version: i32 = conn
-        .query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
-            row.get(0)
-        })
-        .unwrap_or(0);
-
+    let current_version = schema_version(conn);
     if current_version < CURRENT_SCHEMA_VERSION {
         run_migrations(conn, current_version)?;
     }
@@ -29,19 +24,110 @@ pub fn init_db(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// Run database migrations from current version to latest
+/// Current schema version (0 for a fresh database)
+pub fn schema_version(conn: &Connection) -> i32 {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+        row.get(0)
+    })
+    .unwrap_or(0)
+}
+
+/// Run database migrations from current version to latest.
+/// Each migration runs in its own transaction, so a failing step leaves the
+/// database at the previous version instead of half-applied.
 fn run_migrations(conn: &Connection, from_version: i32) -> Result<()> {
-    if from_version < 1 {
-        migrate_v1(conn)?;
+    let migrations: [fn(&Connection) -> Result<()>; CURRENT_SCHEMA_VERSION as usize] = [
+        migrate_v1, migrate_v2, migrate_v3, migrate_v4, migrate_v5, migrate_v6, migrate_v7,
+        migrate_v8, migrate_v9, migrate_v10, migrate_v11, migrate_v12, migrate_v13,
+        migrate_v14, migrate_v15, migrate_v16, migrate_v17, migrate_v18, migrate_v19,
+        migrate_v20, migrate_v21, migrate_v22, migrate_v23, migrate_v24, migrate_v25, migrate_v26,
+        migrate_v27, migrate_v28, migrate_v29, migrate_v30, migrate_v31,
+    ];
+
+    for (version, migrate) in (1..).zip(migrations) {
+        if from_version < version {
+            let tx = conn.unchecked_transaction()?;
+            migrate(&tx)?;
+            tx.commit()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `PRAGMA integrity_check`, returning the reported problems if any
+fn check_integrity(conn: &Connection) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    if messages.len() == 1 && messages[0] == "ok" {
+        Ok(None)
+    } else {
+        Ok(Some(messages.join("; ")))
     }
-    if from_version < 2 {
-        migrate_v2(conn)?;
+}
+
+/// Snapshot path used before migrating from `version`, e.g. `bayin.db.v6.bak`
+fn backup_path(path: &Path, version: i32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+/// Migrate an existing database with a safety net:
+/// snapshot the file, migrate, verify integrity, and restore the snapshot on failure.
+fn migrate_with_backup(conn: &mut Connection, path: &Path) -> std::result::Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let from_version = schema_version(conn);
+    if from_version >= CURRENT_SCHEMA_VERSION {
+        return Ok(());
     }
-    if from_version < 3 {
-        migrate_v3(conn)?;
+    if from_version == 0 {
+        // Fresh database, nothing worth backing up
+        return init_db(conn).map_err(|e| e.to_string());
     }
 
-    Ok(())
+    // VACUUM INTO writes a consistent copy (WAL contents included)
+    let backup = backup_path(path, from_version);
+    let _ = std::fs::remove_file(&backup);
+    conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+        .map_err(|e| format!("数据库迁移前备份失败: {}", e))?;
+
+    let failure = match init_db(conn) {
+        Err(e) => Some(e.to_string()),
+        Ok(()) => match check_integrity(conn) {
+            Ok(None) => None,
+            Ok(Some(problems)) => Some(format!("完整性检查未通过: {}", problems)),
+            Err(e) => Some(e.to_string()),
+        },
+    };
+
+    let Some(reason) = failure else {
+        return Ok(());
+    };
+
+    match conn.restore(DatabaseName::Main, &backup, None::<fn(rusqlite::backup::Progress)>) {
+        Ok(()) => Err(format!(
+            "数据库从 v{} 升级到 v{} 失败，已恢复到升级前的状态: {}",
+            from_version, CURRENT_SCHEMA_VERSION, reason
+        )),
+        Err(e) => Err(format!(
+            "数据库从 v{} 升级到 v{} 失败且自动恢复失败（{}），备份位于 {}: {}",
+            from_version,
+            CURRENT_SCHEMA_VERSION,
+            e,
+            backup.display(),
+            reason
+        )),
+    }
 }
 
 /// Version 1: Initial schema
@@ -168,19 +254,645 @@ fn migrate_v3(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// Open or create a database at the given path
-pub fn open_db(path: &Path) -> Result<Connection> {
-    let conn = Connection::open(path)?;
+/// Version 4: Play history for listening statistics
+fn migrate_v4(conn: &Connection) -> Result<()> {
+    // Title/artist/album are copied at play time so stats survive library changes
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS play_history (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            song_id         TEXT NOT NULL,
+            title           TEXT NOT NULL,
+            artist          TEXT NOT NULL,
+            album           TEXT NOT NULL,
+            listened_secs   REAL NOT NULL DEFAULT 0.0,
+            played_at       INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_play_history_played_at ON play_history(played_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_play_history_song ON play_history(song_id)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [4])?;
+
+    Ok(())
+}
+
+/// Version 5: Key-value custom metadata per song (BPM, key, third-party IDs, ...)
+fn migrate_v5(conn: &Connection) -> Result<()> {
+    // Separate table so `INSERT OR REPLACE` rescans of songs don't wipe it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS song_extra (
+            song_id         TEXT NOT NULL,
+            key             TEXT NOT NULL,
+            value           TEXT NOT NULL,
+            updated_at      INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            PRIMARY KEY (song_id, key)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_song_extra_key ON song_extra(key)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [5])?;
+
+    Ok(())
+}
+
+/// Version 6: Full-text search index (trigram FTS5) with pinyin initials
+fn migrate_v6(conn: &Connection) -> Result<()> {
+    // rowid mirrors songs.rowid; trigram allows substring matches incl. CJK
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS songs_fts USING fts5(
+            title, artist, album, pinyin,
+            tokenize = 'trigram'
+        )",
+        [],
+    )?;
+
+    // Keep the index in sync (REPLACE fires the delete trigger via recursive_triggers)
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS songs_fts_insert AFTER INSERT ON songs BEGIN
+            INSERT INTO songs_fts (rowid, title, artist, album, pinyin)
+            VALUES (new.rowid, new.title, new.artist, new.album,
+                    pinyin_initials(new.title) || ' ' || pinyin_initials(new.artist) || ' ' || pinyin_initials(new.album));
+         END;
+         CREATE TRIGGER IF NOT EXISTS songs_fts_delete AFTER DELETE ON songs BEGIN
+            DELETE FROM songs_fts WHERE rowid = old.rowid;
+         END;
+         CREATE TRIGGER IF NOT EXISTS songs_fts_update AFTER UPDATE OF title, artist, album ON songs BEGIN
+            DELETE FROM songs_fts WHERE rowid = old.rowid;
+            INSERT INTO songs_fts (rowid, title, artist, album, pinyin)
+            VALUES (new.rowid, new.title, new.artist, new.album,
+                    pinyin_initials(new.title) || ' ' || pinyin_initials(new.artist) || ' ' || pinyin_initials(new.album));
+         END;"
+    )?;
+
+    // Index existing songs
+    conn.execute(
+        "INSERT INTO songs_fts (rowid, title, artist, album, pinyin)
+         SELECT rowid, title, artist, album,
+                pinyin_initials(title) || ' ' || pinyin_initials(artist) || ' ' || pinyin_initials(album)
+         FROM songs",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [6])?;
+
+    Ok(())
+}
+
+/// Version 7: Playlists
+fn migrate_v7(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlists (
+            id              TEXT PRIMARY KEY,
+            name            TEXT NOT NULL,
+            cover           TEXT,
+            created_at      INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at      INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+
+    // No foreign key to songs: rescans REPLACE song rows and would cascade
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlist_songs (
+            playlist_id     TEXT NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+            position        INTEGER NOT NULL,
+            song_id         TEXT NOT NULL,
+            added_at        INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            PRIMARY KEY (playlist_id, position)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_playlist_songs_song ON playlist_songs(song_id)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [7])?;
+
+    Ok(())
+}
+
+/// Version 8: Settings
+fn migrate_v8(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key         TEXT PRIMARY KEY,
+            value       TEXT NOT NULL,
+            updated_at  INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [8])?;
+
+    Ok(())
+}
+
+/// Version 9: Album artist/year and a stable album_id for album grouping
+fn migrate_v9(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN album_artist TEXT", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN year INTEGER", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN album_id TEXT", [])?;
+
+    // Keep album_id in sync for every writer
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS songs_album_id_insert AFTER INSERT ON songs BEGIN
+            UPDATE songs SET album_id = album_key(new.album, new.album_artist, new.artist, new.year)
+            WHERE rowid = new.rowid;
+         END;
+         CREATE TRIGGER IF NOT EXISTS songs_album_id_update AFTER UPDATE OF album, album_artist, artist, year ON songs BEGIN
+            UPDATE songs SET album_id = album_key(new.album, new.album_artist, new.artist, new.year)
+            WHERE rowid = new.rowid;
+         END;"
+    )?;
+
+    // Existing rows have no album artist/year yet; a rescan fills them in
+    conn.execute(
+        "UPDATE songs SET album_id = album_key(album, album_artist, artist, year)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_album_id ON songs(album_id)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [9])?;
+
+    Ok(())
+}
+
+/// Version 10: match_key linking local and stream copies, and the unified_songs view
+fn migrate_v10(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN match_key TEXT", [])?;
+
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS songs_match_key_insert AFTER INSERT ON songs BEGIN
+            UPDATE songs SET match_key = song_match_key(new.title, new.artist)
+            WHERE rowid = new.rowid;
+         END;
+         CREATE TRIGGER IF NOT EXISTS songs_match_key_update AFTER UPDATE OF title, artist ON songs BEGIN
+            UPDATE songs SET match_key = song_match_key(new.title, new.artist)
+            WHERE rowid = new.rowid;
+         END;"
+    )?;
+
+    conn.execute("UPDATE songs SET match_key = song_match_key(title, artist)", [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_match_key ON songs(match_key)",
+        [],
+    )?;
+
+    // Local copies win; stream songs are hidden when a local song with the same
+    // key and a duration within unified::DURATION_TOLERANCE_SECS exists
+    conn.execute(
+        "CREATE VIEW IF NOT EXISTS unified_songs AS
+         SELECT s.* FROM songs s
+         WHERE s.source_type = 'local'
+            OR NOT EXISTS (
+                SELECT 1 FROM songs l
+                WHERE l.source_type = 'local'
+                  AND l.match_key = s.match_key
+                  AND ABS(l.duration - s.duration) <= 3.0
+            )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [10])?;
+
+    Ok(())
+}
+
+/// Version 11: Online lyric lookup cache
+fn migrate_v11(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS lyrics (
+            song_key    TEXT NOT NULL,
+            provider    TEXT NOT NULL,
+            found       INTEGER NOT NULL DEFAULT 0,
+            expires_at  INTEGER NOT NULL,
+            updated_at  INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            PRIMARY KEY (song_key, provider)
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [11])?;
+
+    Ok(())
+}
+
+/// Version 12: Genre column (from tags or album.nfo / artist.nfo)
+fn migrate_v12(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN genre TEXT", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [12])?;
+
+    Ok(())
+}
+
+/// Version 13: Typed embedded pictures per song
+fn migrate_v13(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS song_pictures (
+            song_id         TEXT NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            position        INTEGER NOT NULL,
+            picture_type    TEXT NOT NULL,
+            cover_hash      TEXT NOT NULL,
+            PRIMARY KEY (song_id, position)
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [13])?;
+
+    Ok(())
+}
+
+/// Version 14: Partial content hash for directories where mtime is unreliable
+fn migrate_v14(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN content_hash TEXT", [])?;
+    conn.execute(
+        "ALTER TABLE scan_configs ADD COLUMN hash_directories TEXT NOT NULL DEFAULT '[]'",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [14])?;
+
+    Ok(())
+}
+
+/// Version 15: Offline download queue for stream songs
+fn migrate_v15(conn: &Connection) -> Result<()> {
+    // No foreign key: stream rescans delete and re-insert songs, the
+    // download has to survive that
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offline_downloads (
+            song_id             TEXT PRIMARY KEY,
+            status              TEXT NOT NULL DEFAULT 'queued',
+            local_path          TEXT,
+            total_bytes         INTEGER NOT NULL DEFAULT 0,
+            downloaded_bytes    INTEGER NOT NULL DEFAULT 0,
+            error               TEXT,
+            created_at          INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at          INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_offline_downloads_status ON offline_downloads(status)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [15])?;
+
+    Ok(())
+}
+
+/// Version 16: Mark downloads managed by the offline sync rules
+fn migrate_v16(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE offline_downloads ADD COLUMN auto INTEGER NOT NULL DEFAULT 0",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [16])?;
+
+    Ok(())
+}
+
+/// Version 17: ReplayGain / server normalization data per song
+fn migrate_v17(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN replay_gain REAL", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN replay_peak REAL", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [17])?;
+
+    Ok(())
+}
+
+/// Version 18: Local counters for the opt-in usage telemetry
+fn migrate_v18(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS telemetry_counters (
+            kind        TEXT NOT NULL,
+            name        TEXT NOT NULL,
+            count       INTEGER NOT NULL DEFAULT 0,
+            first_at    INTEGER NOT NULL,
+            last_at     INTEGER NOT NULL,
+            PRIMARY KEY (kind, name)
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [18])?;
+
+    Ok(())
+}
+
+/// Version 19: Dynamic range metrics from the analysis job
+fn migrate_v19(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN dynamic_range REAL", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN crest_factor REAL", [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_dynamic_range ON songs(dynamic_range)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [19])?;
+
+    Ok(())
+}
+
+/// Version 20: ReplayGain album gain, kept apart from the track gain
+fn migrate_v20(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN album_gain REAL", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN album_peak REAL", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [20])?;
+
+    Ok(())
+}
+
+/// Version 21: EBU R128 loudness from the analysis job
+fn migrate_v21(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN loudness_lufs REAL", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN true_peak REAL", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [21])?;
+
+    Ok(())
+}
+
+/// Version 22: User equalizer presets
+fn migrate_v22(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS eq_presets (
+            name        TEXT PRIMARY KEY,
+            gains       TEXT NOT NULL,
+            preamp      REAL NOT NULL DEFAULT 0,
+            enabled     INTEGER NOT NULL DEFAULT 1,
+            created_at  INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            updated_at  INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [22])?;
+
+    Ok(())
+}
+
+/// Version 23: Stream songs fully downloaded into the playback cache
+fn migrate_v23(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stream_cache (
+            song_id     TEXT PRIMARY KEY,
+            local_path  TEXT NOT NULL,
+            size_bytes  INTEGER NOT NULL DEFAULT 0,
+            cached_at   INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [23])?;
+
+    Ok(())
+}
+
+/// Version 24: Chapters of chaptered files (m4b audiobooks)
+fn migrate_v24(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chapters (
+            song_id     TEXT NOT NULL REFERENCES songs(id) ON DELETE CASCADE,
+            position    INTEGER NOT NULL,
+            title       TEXT NOT NULL,
+            start_secs  REAL NOT NULL,
+            PRIMARY KEY (song_id, position)
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [24])?;
+
+    Ok(())
+}
+
+/// Version 25: Star ratings (0 = unrated)
+fn migrate_v25(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN rating INTEGER NOT NULL DEFAULT 0", [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_rating ON songs(rating)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [25])?;
+
+    Ok(())
+}
+
+/// Version 26: Play count and last played time, seeded from the play history
+fn migrate_v26(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN last_played_at INTEGER", [])?;
+    conn.execute(
+        "UPDATE songs SET
+            play_count = (SELECT COUNT(*) FROM play_history WHERE song_id = songs.id),
+            last_played_at = (SELECT MAX(played_at) FROM play_history WHERE song_id = songs.id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_play_count ON songs(play_count)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_last_played ON songs(last_played_at)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [26])?;
+
+    Ok(())
+}
+
+/// Version 27: Genre lookups for genre browsing
+fn migrate_v27(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_genre ON songs(genre COLLATE NOCASE)",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [27])?;
+
+    Ok(())
+}
+
+/// Version 28: Track and disc numbers, for album order
+fn migrate_v28(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN track_no INTEGER", [])?;
+    conn.execute("ALTER TABLE songs ADD COLUMN disc_no INTEGER", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [28])?;
+
+    Ok(())
+}
+
+/// Version 29: Composer
+fn migrate_v29(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN composer TEXT", [])?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [29])?;
+
+    Ok(())
+}
+
+/// Version 30: Drop plaintext server passwords copied into stream song info;
+/// players look the password up from the (encrypted) server config instead
+fn migrate_v30(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "UPDATE songs SET stream_info = json_remove(stream_info, '$.config.password')
+         WHERE source_type = 'stream' AND json_valid(stream_info)
+           AND json_type(stream_info, '$.config.password') IS NOT NULL",
+        [],
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [30])?;
+
+    Ok(())
+}
+
+/// Version 31: Soft delete. Songs whose file disappeared are marked instead of
+/// deleted, keeping play counts and playlist entries while a drive is unplugged.
+fn migrate_v31(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE songs ADD COLUMN missing_since INTEGER", [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_songs_missing_since ON songs(missing_since)
+         WHERE missing_since IS NOT NULL",
+        [],
+    )?;
+
+    // Missing songs are hidden, and no longer hide their stream copies
+    conn.execute_batch(
+        "DROP VIEW IF EXISTS unified_songs;
+         CREATE VIEW unified_songs AS
+         SELECT s.* FROM songs s
+         WHERE s.missing_since IS NULL
+           AND (s.source_type = 'local'
+            OR NOT EXISTS (
+                SELECT 1 FROM songs l
+                WHERE l.source_type = 'local'
+                  AND l.missing_since IS NULL
+                  AND l.match_key = s.match_key
+                  AND ABS(l.duration - s.duration) <= 3.0
+            ));"
+    )?;
+
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [31])?;
+
+    Ok(())
+}
+
+/// Register SQL functions used by migrations and triggers
+fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "song_match_key",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        |ctx| {
+            let title: String = ctx.get(0)?;
+            let artist: String = ctx.get(1)?;
+            Ok(super::unified::match_key(&title, &artist))
+        },
+    )?;
+
+    conn.create_scalar_function(
+        "album_key",
+        4,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        |ctx| {
+            let album: String = ctx.get(0)?;
+            let album_artist: Option<String> = ctx.get(1)?;
+            let artist: String = ctx.get(2)?;
+            let year: Option<i64> = ctx.get(3)?;
+            Ok(super::albums::album_id_for(
+                &album,
+                album_artist.as_deref(),
+                &artist,
+                year.and_then(|y| u32::try_from(y).ok()),
+            ))
+        },
+    )?;
+
+    conn.create_scalar_function(
+        "pinyin_initials",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+        |ctx| {
+            let text: Option<String> = ctx.get(0)?;
+            Ok(text
+                .map(|t| crate::utils::pinyin::initials(&t))
+                .unwrap_or_default())
+        },
+    )
+}
+
+/// Open or create a database at the given path, migrating it to the latest schema
+pub fn open_db(path: &Path) -> std::result::Result<Connection, String> {
+    let mut conn = Connection::open(path).map_err(|e| e.to_string())?;
 
     // Enable foreign keys and WAL mode for better performance
     conn.execute_batch(
         "PRAGMA foreign_keys = ON;
          PRAGMA journal_mode = WAL;
          PRAGMA synchronous = NORMAL;
-         PRAGMA cache_size = -64000;"
-    )?;
+         PRAGMA cache_size = -64000;
+         PRAGMA recursive_triggers = ON;"
+    )
+    .map_err(|e| e.to_string())?;
+
+    register_functions(&conn).map_err(|e| e.to_string())?;
+    migrate_with_backup(&mut conn, path)?;
+
+    // Server passwords are encrypted with a per-install key kept next to the database
+    crate::utils::secret::load_key(&path.with_file_name("secret.key"))?;
+    let sealed = super::servers::seal_stored_passwords(&conn).map_err(|e| e.to_string())?;
+    if sealed > 0 {
+        eprintln!("Encrypted {} stored server password(s)", sealed);
+    }
+
+    Ok(conn)
+}
+
+/// Open a read-only connection to a database already opened (and migrated) by `open_db`
+pub fn open_reader(path: &Path) -> std::result::Result<Connection, String> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute_batch(
+        "PRAGMA cache_size = -16000;
+         PRAGMA query_only = ON;"
+    )
+    .map_err(|e| e.to_string())?;
+    // A checkpoint on the writer can briefly lock the WAL index
+    conn.busy_timeout(std::time::Duration::from_secs(5)).map_err(|e| e.to_string())?;
 
-    init_db(&conn)?;
+    register_functions(&conn).map_err(|e| e.to_string())?;
 
     Ok(conn)
 }
@@ -0,0 +1,43 @@
+//! Per-song online lyric lookup cache
+//!
+//! Currently only negative results are stored: a provider that returned no
+//! candidates for a song is skipped until the entry expires.
+
+use rusqlite::{params, Connection, Result};
+
+/// How long a "no result" entry suppresses further searches (3 days)
+pub const NEGATIVE_CACHE_TTL_SECS: i64 = 3 * 24 * 60 * 60;
+
+/// Providers with an unexpired "no result" entry for the song
+pub fn get_negative_providers(conn: &Connection, song_key: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT provider FROM lyrics
+         WHERE song_key = ?1 AND found = 0 AND expires_at > strftime('%s','now')"
+    )?;
+    let providers = stmt
+        .query_map([song_key], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(providers)
+}
+
+/// Remember that a provider has no lyrics for the song
+pub fn mark_lyrics_not_found(conn: &Connection, song_key: &str, provider: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO lyrics (song_key, provider, found, expires_at, updated_at)
+         VALUES (?1, ?2, 0, strftime('%s','now') + ?3, strftime('%s','now'))
+         ON CONFLICT(song_key, provider) DO UPDATE SET
+            found = 0,
+            expires_at = excluded.expires_at,
+            updated_at = excluded.updated_at",
+        params![song_key, provider, NEGATIVE_CACHE_TTL_SECS],
+    )?;
+    Ok(())
+}
+
+/// Drop cached lookups for a song (all songs if `None`)
+pub fn clear_lyrics_cache(conn: &Connection, song_key: Option<&str>) -> Result<usize> {
+    match song_key {
+        Some(key) => conn.execute("DELETE FROM lyrics WHERE song_key = ?1", [key]),
+        None => conn.execute("DELETE FROM lyrics", []),
+    }
+}
@@ -1,20 +1,75 @@
 //! Database module for SQLite persistence
 //!
 //! This module provides persistent storage for songs, albums, artists,
-//! stream server configurations, and scan settings.
+//! playlists, stream server configurations, scan settings, app settings, play history,
+//! the offline download queue, local telemetry counters, equalizer presets, the stream playback cache,
+//! audiobook chapters, the folder view and database maintenance.
 
 pub mod init;
 pub mod songs;
 pub mod albums;
 pub mod servers;
+pub mod queue;
+pub mod history;
+pub mod extra;
+pub mod search;
+pub mod playlists;
+pub mod batch;
+pub mod settings;
+pub mod unified;
+pub mod lyrics;
+pub mod pictures;
+pub mod offline;
+pub mod telemetry;
+pub mod eq_presets;
+pub mod stream_cache;
+pub mod chapters;
+pub mod maintenance;
+pub mod folders;
 
 use rusqlite::Connection;
-use std::sync::Mutex;
+use std::sync::{LockResult, Mutex, MutexGuard};
 
 pub use init::*;
 pub use songs::*;
 pub use albums::*;
 pub use servers::*;
+pub use queue::*;
+pub use history::*;
+pub use search::*;
+pub use playlists::*;
+pub use batch::*;
+pub use settings::*;
+pub use pictures::*;
+pub use offline::*;
+pub use eq_presets::*;
+pub use stream_cache::*;
 
-/// Database state wrapper for Tauri managed state
-pub struct DbState(pub Mutex<Connection>);
+/// Database state wrapper for Tauri managed state.
+///
+/// Holds one connection for writes and a read-only one for queries. In WAL mode
+/// the reader sees the last committed data without waiting for the writer, so
+/// UI queries are not blocked behind bulk writes such as a library scan.
+pub struct DbState {
+    writer: Mutex<Connection>,
+    reader: Mutex<Connection>,
+}
+
+impl DbState {
+    pub fn new(writer: Connection, reader: Connection) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            reader: Mutex::new(reader),
+        }
+    }
+
+    /// Connection for writes, and for reads that must happen in the same transaction
+    pub fn write(&self) -> LockResult<MutexGuard<'_, Connection>> {
+        self.writer.lock()
+    }
+
+    /// Read-only connection for queries
+    pub fn read(&self) -> LockResult<MutexGuard<'_, Connection>> {
+        self.reader.lock()
+    }
+}
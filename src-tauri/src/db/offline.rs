@@ -0,0 +1,297 @@
+//! Offline download queue
+//!
+//! Stream songs queued for the offline cache. Rows are keyed by song ID and
+//! survive stream rescans; the downloaded files live in the offline directory.
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::settings::OfflineSyncRules;
+
+pub const DOWNLOAD_QUEUED: &str = "queued";
+pub const DOWNLOAD_ACTIVE: &str = "downloading";
+pub const DOWNLOAD_PAUSED: &str = "paused";
+pub const DOWNLOAD_DONE: &str = "done";
+pub const DOWNLOAD_FAILED: &str = "failed";
+
+/// One entry of the download queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineDownload {
+    pub song_id: String,
+    /// Missing when the song left the library after it was queued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    /// queued / downloading / paused / done / failed
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_path: Option<String>,
+    pub total_bytes: i64,
+    pub downloaded_bytes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Managed by the offline sync rules (evicted when no longer matching)
+    pub auto: bool,
+    pub created_at: i64,
+}
+
+const DOWNLOAD_COLUMNS: &str = "d.song_id, s.title, s.artist, d.status, d.local_path,
+    d.total_bytes, d.downloaded_bytes, d.error, d.auto, d.created_at";
+
+fn download_from_row(row: &Row) -> Result<OfflineDownload> {
+    Ok(OfflineDownload {
+        song_id: row.get(0)?,
+        title: row.get(1)?,
+        artist: row.get(2)?,
+        status: row.get(3)?,
+        local_path: row.get(4)?,
+        total_bytes: row.get(5)?,
+        downloaded_bytes: row.get(6)?,
+        error: row.get(7)?,
+        auto: row.get::<_, i32>(8)? != 0,
+        created_at: row.get(9)?,
+    })
+}
+
+/// Queue stream songs for download. Failed entries are retried; local songs
+/// and songs already queued are ignored. Returns the number of queued songs.
+///
+/// Manually queued songs are never evicted by the sync rules, so this also
+/// takes over entries the rules added before.
+pub fn enqueue_downloads(conn: &Connection, song_ids: &[String]) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO offline_downloads (song_id)
+         SELECT id FROM songs WHERE id = ?1 AND source_type = 'stream'
+         ON CONFLICT(song_id) DO UPDATE SET
+            status = 'queued',
+            error = NULL,
+            updated_at = strftime('%s','now')
+         WHERE status = 'failed'",
+    )?;
+    let mut pin = conn.prepare("UPDATE offline_downloads SET auto = 0 WHERE song_id = ?1 AND auto = 1")?;
+
+    let mut queued = 0;
+    for id in song_ids {
+        queued += stmt.execute([id])?;
+        pin.execute([id])?;
+    }
+    Ok(queued)
+}
+
+/// Queue songs selected by the sync rules; existing entries are left alone
+pub fn enqueue_auto_downloads(conn: &Connection, song_ids: &[String]) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO offline_downloads (song_id, auto)
+         SELECT id, 1 FROM songs WHERE id = ?1 AND source_type = 'stream'
+         ON CONFLICT(song_id) DO NOTHING",
+    )?;
+
+    let mut queued = 0;
+    for id in song_ids {
+        queued += stmt.execute([id])?;
+    }
+    Ok(queued)
+}
+
+/// Song IDs of downloads managed by the sync rules
+pub fn get_auto_downloads(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT song_id FROM offline_downloads WHERE auto = 1")?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Stream songs the sync rules want offline, highest priority first
+/// (favorites, recent playlists, recently played), cut to the storage budget.
+/// Songs that don't fit are skipped so smaller ones further down can still fit.
+pub fn plan_offline_sync(conn: &Connection, rules: &OfflineSyncRules) -> Result<Vec<String>> {
+    // Unknown file sizes are estimated from bitrate (kbps) and duration
+    const SIZE: &str = "COALESCE(NULLIF(s.file_size, 0), CAST(s.bitrate * 125 * s.duration AS INTEGER), 0)";
+
+    let mut candidates: Vec<(String, i64)> = Vec::new();
+    let mut collect = |sql: String, limit: Option<u32>| -> Result<()> {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(limit), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            candidates.push(row?);
+        }
+        Ok(())
+    };
+
+    if rules.favorites {
+        collect(
+            format!(
+                "SELECT s.id, {SIZE} FROM song_extra e
+                 JOIN songs s ON s.id = e.song_id
+                 WHERE e.key = 'favorite' AND e.value = 'true' AND s.source_type = 'stream'
+                 ORDER BY e.updated_at DESC"
+            ),
+            None,
+        )?;
+    }
+    if rules.recent_playlists > 0 {
+        collect(
+            format!(
+                "SELECT s.id, {SIZE}
+                 FROM (SELECT id, updated_at FROM playlists ORDER BY updated_at DESC LIMIT ?1) p
+                 JOIN playlist_songs ps ON ps.playlist_id = p.id
+                 JOIN songs s ON s.id = ps.song_id
+                 WHERE s.source_type = 'stream'
+                 ORDER BY p.updated_at DESC, ps.position"
+            ),
+            Some(rules.recent_playlists),
+        )?;
+    }
+    if rules.recent_played > 0 {
+        collect(
+            format!(
+                "SELECT s.id, {SIZE}, MAX(h.played_at) AS last_played
+                 FROM play_history h
+                 JOIN songs s ON s.id = h.song_id
+                 WHERE s.source_type = 'stream'
+                 GROUP BY s.id
+                 ORDER BY last_played DESC
+                 LIMIT ?1"
+            ),
+            Some(rules.recent_played),
+        )?;
+    }
+
+    let budget = rules.budget_mb as i64 * 1024 * 1024;
+    let mut used = 0;
+    let mut seen = HashSet::new();
+    let mut wanted = Vec::new();
+    for (id, size) in candidates {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        if budget > 0 && used + size > budget {
+            continue;
+        }
+        used += size;
+        wanted.push(id);
+    }
+
+    Ok(wanted)
+}
+
+/// The whole queue, oldest first
+pub fn get_downloads(conn: &Connection) -> Result<Vec<OfflineDownload>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {DOWNLOAD_COLUMNS}
+         FROM offline_downloads d
+         LEFT JOIN songs s ON s.id = d.song_id
+         ORDER BY d.created_at, d.rowid"
+    ))?;
+
+    let downloads = stmt
+        .query_map([], download_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(downloads)
+}
+
+/// One queue entry
+pub fn get_download(conn: &Connection, song_id: &str) -> Result<Option<OfflineDownload>> {
+    conn.query_row(
+        &format!(
+            "SELECT {DOWNLOAD_COLUMNS}
+             FROM offline_downloads d
+             LEFT JOIN songs s ON s.id = d.song_id
+             WHERE d.song_id = ?1"
+        ),
+        [song_id],
+        download_from_row,
+    )
+    .optional()
+}
+
+/// Queued song IDs in download order
+pub fn get_queued_downloads(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT song_id FROM offline_downloads
+         WHERE status = 'queued'
+         ORDER BY created_at, rowid",
+    )?;
+
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(ids)
+}
+
+/// Change the status of an entry whose current status is one of `from`.
+/// Returns false if the entry is missing or in another state.
+pub fn set_download_status(
+    conn: &Connection,
+    song_id: &str,
+    from: &[&str],
+    status: &str,
+    error: Option<&str>,
+) -> Result<bool> {
+    let placeholders = vec!["?"; from.len()].join(", ");
+    let sql = format!(
+        "UPDATE offline_downloads
+         SET status = ?, error = ?, updated_at = strftime('%s','now')
+         WHERE song_id = ? AND status IN ({placeholders})"
+    );
+
+    let mut values: Vec<Value> = vec![
+        Value::Text(status.to_string()),
+        error.map_or(Value::Null, |e| Value::Text(e.to_string())),
+        Value::Text(song_id.to_string()),
+    ];
+    values.extend(from.iter().map(|s| Value::Text(s.to_string())));
+
+    let affected = conn.execute(&sql, params_from_iter(values))?;
+    Ok(affected > 0)
+}
+
+/// Record where the file is written and how far the download got
+pub fn update_download_progress(
+    conn: &Connection,
+    song_id: &str,
+    local_path: &str,
+    downloaded_bytes: i64,
+    total_bytes: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE offline_downloads
+         SET local_path = ?2, downloaded_bytes = ?3, total_bytes = ?4, updated_at = strftime('%s','now')
+         WHERE song_id = ?1",
+        params![song_id, local_path, downloaded_bytes, total_bytes],
+    )?;
+    Ok(())
+}
+
+/// Downloads interrupted by an app exit go back into the queue
+pub fn requeue_interrupted_downloads(conn: &Connection) -> Result<usize> {
+    conn.execute(
+        "UPDATE offline_downloads SET status = 'queued' WHERE status = 'downloading'",
+        [],
+    )
+}
+
+/// Remove an entry, returning it so the caller can delete the file
+pub fn remove_download(conn: &Connection, song_id: &str) -> Result<Option<OfflineDownload>> {
+    let download = get_download(conn, song_id)?;
+    conn.execute("DELETE FROM offline_downloads WHERE song_id = ?1", [song_id])?;
+    Ok(download)
+}
+
+/// Local file of a finished download
+pub fn get_offline_path(conn: &Connection, song_id: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT local_path FROM offline_downloads WHERE song_id = ?1 AND status = 'done'",
+        [song_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
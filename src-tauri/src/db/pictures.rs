@@ -0,0 +1,80 @@
+//! Typed embedded pictures (front/back cover, booklet, artist...) per song
+//!
+//! The images themselves live in the cover cache; this table only maps songs
+//! to cover hashes so album detail views can show every picture.
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+/// One cached picture of a song
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongPicture {
+    /// front / back / booklet / media / artist / icon / illustration / logo / other
+    pub picture_type: String,
+    pub cover_hash: String,
+}
+
+/// Display order: front cover first, then back cover, booklet, disc
+const TYPE_ORDER: &str = "CASE picture_type
+    WHEN 'front' THEN 0 WHEN 'back' THEN 1 WHEN 'booklet' THEN 2 WHEN 'media' THEN 3
+    ELSE 4 END";
+
+/// Replace the stored pictures of a song
+pub(crate) fn replace_song_pictures(conn: &Connection, song_id: &str, pictures: &[SongPicture]) -> Result<()> {
+    conn.execute("DELETE FROM song_pictures WHERE song_id = ?1", [song_id])?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO song_pictures (song_id, position, picture_type, cover_hash)
+         VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for (position, picture) in pictures.iter().enumerate() {
+        stmt.execute(params![song_id, position as i64, picture.picture_type, picture.cover_hash])?;
+    }
+
+    Ok(())
+}
+
+/// Pictures embedded in one song
+pub fn get_song_pictures(conn: &Connection, song_id: &str) -> Result<Vec<SongPicture>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT picture_type, cover_hash FROM song_pictures
+         WHERE song_id = ?1
+         ORDER BY {TYPE_ORDER}, position"
+    ))?;
+
+    let pictures = stmt
+        .query_map([song_id], |row| {
+            Ok(SongPicture {
+                picture_type: row.get(0)?,
+                cover_hash: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(pictures)
+}
+
+/// Distinct pictures across all songs of an album
+pub fn get_album_pictures(conn: &Connection, album_id: &str) -> Result<Vec<SongPicture>> {
+    // Bare columns in an aggregate query come from the row holding MIN(rank)
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.picture_type, p.cover_hash, MIN({TYPE_ORDER} * 1000000 + p.position) AS rank
+         FROM song_pictures p
+         JOIN songs s ON s.id = p.song_id
+         WHERE s.album_id = ?1
+         GROUP BY p.cover_hash
+         ORDER BY rank"
+    ))?;
+
+    let pictures = stmt
+        .query_map([album_id], |row| {
+            Ok(SongPicture {
+                picture_type: row.get(0)?,
+                cover_hash: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(pictures)
+}
@@ -0,0 +1,142 @@
+//! Playlist database operations
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+/// Playlist summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbPlaylist {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    pub song_count: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Get all playlists, most recently changed first
+pub fn get_playlists(conn: &Connection) -> Result<Vec<DbPlaylist>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.cover, p.created_at, p.updated_at,
+                (SELECT COUNT(*) FROM playlist_songs ps WHERE ps.playlist_id = p.id)
+         FROM playlists p
+         ORDER BY p.updated_at DESC"
+    )?;
+
+    let playlists = stmt.query_map([], |row| {
+        Ok(DbPlaylist {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            cover: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            song_count: row.get(5)?,
+        })
+    })?.collect::<Result<Vec<_>>>()?;
+
+    Ok(playlists)
+}
+
+/// Song IDs of a playlist in order
+pub fn get_playlist_song_ids(conn: &Connection, playlist_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT song_id FROM playlist_songs WHERE playlist_id = ?1 ORDER BY position"
+    )?;
+    let ids = stmt
+        .query_map([playlist_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Create an empty playlist
+pub fn create_playlist(conn: &Connection, id: &str, name: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO playlists (id, name) VALUES (?1, ?2)",
+        params![id, name],
+    )?;
+    Ok(())
+}
+
+/// Rename a playlist
+pub fn rename_playlist(conn: &Connection, id: &str, name: &str) -> Result<usize> {
+    conn.execute(
+        "UPDATE playlists SET name = ?2, updated_at = strftime('%s','now') WHERE id = ?1",
+        params![id, name],
+    )
+}
+
+/// Set or clear the cover (image path or URL) of a playlist
+pub fn set_playlist_cover(conn: &Connection, id: &str, cover: Option<&str>) -> Result<usize> {
+    conn.execute(
+        "UPDATE playlists SET cover = ?2, updated_at = strftime('%s','now') WHERE id = ?1",
+        params![id, cover],
+    )
+}
+
+/// Delete a playlist and its entries
+pub fn delete_playlist(conn: &Connection, id: &str) -> Result<usize> {
+    conn.execute("DELETE FROM playlists WHERE id = ?1", [id])
+}
+
+/// Append songs to the end of a playlist
+pub fn add_songs_to_playlist(conn: &Connection, playlist_id: &str, song_ids: &[String]) -> Result<usize> {
+    let next: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_songs WHERE playlist_id = ?1",
+        [playlist_id],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO playlist_songs (playlist_id, position, song_id) VALUES (?1, ?2, ?3)"
+    )?;
+    for (i, song_id) in song_ids.iter().enumerate() {
+        stmt.execute(params![playlist_id, next + i as i64, song_id])?;
+    }
+
+    touch_playlist(conn, playlist_id)?;
+    Ok(song_ids.len())
+}
+
+/// Remove every occurrence of the given songs from a playlist, keeping positions contiguous
+pub fn remove_songs_from_playlist(conn: &Connection, playlist_id: &str, song_ids: &[String]) -> Result<usize> {
+    let mut removed = 0;
+    {
+        let mut stmt = conn.prepare(
+            "DELETE FROM playlist_songs WHERE playlist_id = ?1 AND song_id = ?2"
+        )?;
+        for song_id in song_ids {
+            removed += stmt.execute(params![playlist_id, song_id])?;
+        }
+    }
+
+    if removed > 0 {
+        // Rewrite the remaining entries with contiguous positions
+        let remaining: Vec<(String, i64)> = {
+            let mut stmt = conn.prepare(
+                "SELECT song_id, added_at FROM playlist_songs WHERE playlist_id = ?1 ORDER BY position"
+            )?;
+            let rows = stmt.query_map([playlist_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        conn.execute("DELETE FROM playlist_songs WHERE playlist_id = ?1", [playlist_id])?;
+        let mut stmt = conn.prepare(
+            "INSERT INTO playlist_songs (playlist_id, position, song_id, added_at) VALUES (?1, ?2, ?3, ?4)"
+        )?;
+        for (position, (song_id, added_at)) in remaining.iter().enumerate() {
+            stmt.execute(params![playlist_id, position as i64, song_id, added_at])?;
+        }
+        touch_playlist(conn, playlist_id)?;
+    }
+
+    Ok(removed)
+}
+
+fn touch_playlist(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE playlists SET updated_at = strftime('%s','now') WHERE id = ?1",
+        [id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,325 @@
+//! Queue generation queries
+//!
+//! Resolve play queues in SQL and return ordered song IDs, so the frontend
+//! doesn't need the whole library just to build a queue.
+
+use rusqlite::types::Value;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Ordering for a smart queue
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SmartQueueOrder {
+    #[default]
+    Random,
+    RecentlyAdded,
+    Title,
+    Album,
+    /// Highest rated first
+    Rating,
+}
+
+/// Filter rule for `queue_smart`; all set conditions must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SmartQueueRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// "local" or "stream"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    pub hi_res_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<f64>,
+    /// Only songs added within the last N days
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_within_days: Option<u32>,
+    /// Only songs rated at least this many stars
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_rating: Option<u8>,
+    pub order: SmartQueueOrder,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Find the artist name behind an `artist-<md5>` ID (see `get_all_artists`)
+pub fn resolve_artist_name(conn: &Connection, artist_id: &str) -> Result<Option<String>> {
+    resolve_name(conn, "artist", artist_id)
+}
+
+fn resolve_name(conn: &Connection, column: &str, id: &str) -> Result<Option<String>> {
+    let sql = format!("SELECT DISTINCT {} FROM songs", column);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        if format!("{}-{:x}", column, md5::compute(&name)) == id {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+/// Song IDs of an album (by `album_id`) in playing order
+pub fn get_album_queue(conn: &Connection, album_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM songs
+         WHERE album_id = ?1 AND missing_since IS NULL
+         ORDER BY COALESCE(disc_no, 1), track_no IS NULL, track_no, file_path COLLATE NOCASE, title COLLATE NOCASE"
+    )?;
+    let ids = stmt
+        .query_map([album_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Song IDs of an artist, shuffled
+pub fn get_artist_shuffle_queue(conn: &Connection, artist: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM songs
+         WHERE artist = ?1 AND missing_since IS NULL
+         ORDER BY RANDOM()"
+    )?;
+    let ids = stmt
+        .query_map([artist], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Song IDs matching a smart rule
+pub fn get_smart_queue(conn: &Connection, rule: &SmartQueueRule) -> Result<Vec<String>> {
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(ref artist) = rule.artist {
+        conditions.push("artist = ?");
+        values.push(Value::Text(artist.clone()));
+    }
+    if let Some(ref album) = rule.album {
+        conditions.push("album = ?");
+        values.push(Value::Text(album.clone()));
+    }
+    if let Some(ref album_id) = rule.album_id {
+        conditions.push("album_id = ?");
+        values.push(Value::Text(album_id.clone()));
+    }
+    if let Some(ref format) = rule.format {
+        conditions.push("format = ? COLLATE NOCASE");
+        values.push(Value::Text(format.clone()));
+    }
+    if let Some(ref source_type) = rule.source_type {
+        conditions.push("source_type = ?");
+        values.push(Value::Text(source_type.clone()));
+    }
+    if rule.hi_res_only {
+        conditions.push("is_hr = 1");
+    }
+    if let Some(min) = rule.min_duration {
+        conditions.push("duration >= ?");
+        values.push(Value::Real(min));
+    }
+    if let Some(max) = rule.max_duration {
+        conditions.push("duration <= ?");
+        values.push(Value::Real(max));
+    }
+    if let Some(days) = rule.added_within_days {
+        conditions.push("created_at >= strftime('%s','now') - ?");
+        values.push(Value::Integer(days as i64 * 86400));
+    }
+    if let Some(min) = rule.min_rating {
+        conditions.push("rating >= ?");
+        values.push(Value::Integer(min as i64));
+    }
+
+    conditions.push("missing_since IS NULL");
+    let mut sql = String::from("SELECT id FROM songs WHERE ");
+    sql.push_str(&conditions.join(" AND "));
+    sql.push_str(match rule.order {
+        SmartQueueOrder::Random => " ORDER BY RANDOM()",
+        SmartQueueOrder::RecentlyAdded => " ORDER BY created_at DESC",
+        SmartQueueOrder::Title => " ORDER BY title COLLATE NOCASE",
+        SmartQueueOrder::Album => " ORDER BY album COLLATE NOCASE, COALESCE(disc_no, 1), track_no IS NULL, track_no, file_path COLLATE NOCASE",
+        SmartQueueOrder::Rating => " ORDER BY rating DESC, RANDOM()",
+    });
+    if let Some(limit) = rule.limit {
+        sql.push_str(" LIMIT ?");
+        values.push(Value::Integer(limit as i64));
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let ids = stmt
+        .query_map(params_from_iter(values), |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// What a radio station is built around
+#[derive(Debug, Clone)]
+pub struct RadioSeed {
+    pub song_id: String,
+    pub artist: String,
+    pub genre: Option<String>,
+    pub server_id: Option<String>,
+    pub server_song_id: Option<String>,
+}
+
+/// Look up the song a radio starts from
+pub fn get_radio_seed(conn: &Connection, song_id: &str) -> Result<Option<RadioSeed>> {
+    conn.query_row(
+        "SELECT id, artist, genre, server_id, server_song_id FROM songs WHERE id = ?1",
+        [song_id],
+        |row| {
+            Ok(RadioSeed {
+                song_id: row.get(0)?,
+                artist: row.get(1)?,
+                genre: row.get::<_, Option<String>>(2)?.filter(|g| !g.trim().is_empty()),
+                server_id: row.get(3)?,
+                server_song_id: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// Map song IDs of a stream server back to library song IDs, keeping order.
+/// Songs that aren't in the library are dropped.
+pub fn map_server_songs(conn: &Connection, server_id: &str, server_song_ids: &[String]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM songs WHERE server_id = ?1 AND server_song_id = ?2")?;
+    let mut ids = Vec::new();
+    for server_song_id in server_song_ids {
+        if let Some(id) = stmt
+            .query_row(params![server_id, server_song_id], |row| row.get(0))
+            .optional()?
+        {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Local radio heuristic: up to half from the same artist, then the same
+/// genre, topped up with random songs so the radio never runs dry.
+/// IDs in `exclude` are skipped; the result is shuffled.
+pub fn get_radio_queue(
+    conn: &Connection,
+    seed: &RadioSeed,
+    exclude: &HashSet<String>,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let mut picked: Vec<String> = Vec::new();
+    let mut take = |sql: &str, values: Vec<Value>, up_to: usize| -> Result<()> {
+        let mut stmt = conn.prepare(sql)?;
+        let mut rows = stmt.query(params_from_iter(values))?;
+        while picked.len() < up_to {
+            let Some(row) = rows.next()? else { break };
+            let id: String = row.get(0)?;
+            if id != seed.song_id && !exclude.contains(&id) && !picked.contains(&id) {
+                picked.push(id);
+            }
+        }
+        Ok(())
+    };
+
+    take(
+        "SELECT id FROM songs WHERE artist = ? AND missing_since IS NULL ORDER BY RANDOM()",
+        vec![Value::Text(seed.artist.clone())],
+        limit.div_ceil(2),
+    )?;
+    if let Some(ref genre) = seed.genre {
+        take(
+            "SELECT id FROM songs WHERE genre = ? COLLATE NOCASE AND artist <> ? AND missing_since IS NULL ORDER BY RANDOM()",
+            vec![Value::Text(genre.clone()), Value::Text(seed.artist.clone())],
+            limit,
+        )?;
+    }
+    take("SELECT id FROM songs WHERE missing_since IS NULL ORDER BY RANDOM()", Vec::new(), limit)?;
+
+    picked.shuffle(&mut rand::thread_rng());
+    Ok(picked)
+}
+
+/// A song as `smart_shuffle` sees it
+struct ShuffleSong {
+    id: String,
+    album: String,
+    /// Random sort key, higher plays earlier
+    key: f64,
+}
+
+/// Shuffle song IDs so the songs of one artist are spread evenly over the
+/// queue instead of playing back-to-back, and within an artist the albums
+/// alternate. With `favor_less_played`, rarely played songs of an artist tend
+/// to come first. IDs that aren't in the library are kept as their own artist.
+pub fn smart_shuffle(conn: &Connection, song_ids: &[String], favor_less_played: bool) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT artist, album_id, play_count FROM songs WHERE id = ?1"
+    )?;
+    let mut rng = rand::thread_rng();
+
+    // Artists in order of first appearance, so equal inputs don't depend on hash order
+    let mut artists: Vec<Vec<ShuffleSong>> = Vec::new();
+    let mut artist_index: HashMap<String, usize> = HashMap::new();
+    for id in song_ids {
+        let row: Option<(String, Option<String>, i64)> = stmt
+            .query_row([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .optional()?;
+        let (artist, album, plays) = row.unwrap_or_else(|| (id.clone(), None, 0));
+        // Weighted random key (u^(1/w)): sorting by it draws songs with probability ∝ weight
+        let weight = if favor_less_played { 1.0 / (1.0 + plays as f64) } else { 1.0 };
+        let key = rng.gen::<f64>().powf(1.0 / weight);
+
+        let index = *artist_index.entry(artist.to_lowercase()).or_insert_with(|| {
+            artists.push(Vec::new());
+            artists.len() - 1
+        });
+        artists[index].push(ShuffleSong { id: id.clone(), album: album.unwrap_or_default(), key });
+    }
+
+    let mut placed: Vec<(f64, String)> = Vec::with_capacity(song_ids.len());
+    for songs in artists {
+        let songs = interleave_albums(songs);
+        // n songs at a random offset and 1/n apart, jittered a little so artists don't lock step
+        let n = songs.len() as f64;
+        let offset = rng.gen::<f64>() / n;
+        for (i, song) in songs.into_iter().enumerate() {
+            let jitter = rng.gen_range(-0.1..0.1) / n;
+            placed.push((offset + i as f64 / n + jitter, song.id));
+        }
+    }
+    placed.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(placed.into_iter().map(|(_, id)| id).collect())
+}
+
+/// Songs by key, taking one from each album in turn so an album's songs aren't adjacent
+fn interleave_albums(mut songs: Vec<ShuffleSong>) -> Vec<ShuffleSong> {
+    songs.sort_by(|a, b| b.key.total_cmp(&a.key));
+    let mut albums: Vec<Vec<ShuffleSong>> = Vec::new();
+    for song in songs {
+        match albums.iter_mut().find(|album| album[0].album == song.album) {
+            Some(album) => album.push(song),
+            None => albums.push(vec![song]),
+        }
+    }
+
+    let mut rounds: Vec<_> = albums.into_iter().map(Vec::into_iter).collect();
+    let mut interleaved = Vec::new();
+    loop {
+        let before = interleaved.len();
+        interleaved.extend(rounds.iter_mut().filter_map(Iterator::next));
+        if interleaved.len() == before {
+            return interleaved;
+        }
+    }
+}
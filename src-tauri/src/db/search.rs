@@ -0,0 +1,127 @@
+//! Song search over the FTS index
+//!
+//! `songs_fts` uses the trigram tokenizer, so any substring of 3+ characters
+//! (CJK included) is matched via the index; shorter queries fall back to LIKE.
+//! The `pinyin` column holds initials of title/artist/album ("zjl" -> 周杰伦).
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::DbSong;
+use crate::utils::pinyin;
+
+/// Search behavior
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchMode {
+    /// Substring match on title/artist/album and pinyin initials
+    #[default]
+    Standard,
+    /// Standard results, topped up with typo-tolerant matches
+    Fuzzy,
+}
+
+/// Minimum similarity (0..1) for a fuzzy match
+const FUZZY_THRESHOLD: f64 = 0.75;
+
+/// Search songs; results are ordered by relevance
+pub fn search_songs(conn: &Connection, query: &str, mode: SearchMode, limit: u32) -> Result<Vec<DbSong>> {
+    let query = query.trim();
+    if query.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = if query.chars().count() >= 3 {
+        match_ids(conn, query, limit)?
+    } else {
+        like_ids(conn, query, limit)?
+    };
+
+    if mode == SearchMode::Fuzzy && ids.len() < limit as usize {
+        let seen: HashSet<String> = ids.iter().cloned().collect();
+        let extra = fuzzy_ids(conn, query, &seen, limit as usize - ids.len())?;
+        ids.extend(extra);
+    }
+
+    super::songs::get_songs_by_ids(conn, &ids)
+}
+
+/// Indexed trigram match, ranked by bm25
+fn match_ids(conn: &Connection, query: &str, limit: u32) -> Result<Vec<String>> {
+    // Quote as a single FTS phrase so user input can't inject query syntax
+    let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+    let mut stmt = conn.prepare(
+        "SELECT songs.id FROM songs_fts
+         JOIN songs ON songs.rowid = songs_fts.rowid
+         WHERE songs_fts MATCH ?1 AND songs.missing_since IS NULL
+         ORDER BY songs_fts.rank
+         LIMIT ?2"
+    )?;
+    let ids = stmt
+        .query_map(params![phrase, limit], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Unindexed substring match for 1-2 character queries
+fn like_ids(conn: &Connection, query: &str, limit: u32) -> Result<Vec<String>> {
+    let pattern = like_pattern(query);
+    let mut stmt = conn.prepare(
+        "SELECT songs.id FROM songs_fts
+         JOIN songs ON songs.rowid = songs_fts.rowid
+         WHERE songs.missing_since IS NULL
+           AND (songs_fts.title LIKE ?1 ESCAPE '\\'
+            OR songs_fts.artist LIKE ?1 ESCAPE '\\'
+            OR songs_fts.album LIKE ?1 ESCAPE '\\'
+            OR songs_fts.pinyin LIKE ?1 ESCAPE '\\')
+         ORDER BY songs.title COLLATE NOCASE
+         LIMIT ?2"
+    )?;
+    let ids = stmt
+        .query_map(params![pattern, limit], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Pattern for `LIKE ? ESCAPE '\'` matching `text` anywhere
+pub(crate) fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Typo-tolerant matches by edit distance against titles, artists and their words
+fn fuzzy_ids(conn: &Connection, query: &str, exclude: &HashSet<String>, limit: usize) -> Result<Vec<String>> {
+    let query = query.to_lowercase();
+    let mut stmt = conn.prepare("SELECT id, title, artist FROM songs WHERE missing_since IS NULL")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut scored: Vec<(f64, String)> = Vec::new();
+    for row in rows {
+        let (id, title, artist) = row?;
+        if exclude.contains(&id) {
+            continue;
+        }
+        let score = [title.as_str(), artist.as_str()]
+            .iter()
+            .flat_map(|field| {
+                let lower = field.to_lowercase();
+                let initials = pinyin::initials(field);
+                let words: Vec<String> = lower.split_whitespace().map(String::from).collect();
+                std::iter::once(lower).chain(std::iter::once(initials)).chain(words)
+            })
+            .map(|candidate| strsim::normalized_damerau_levenshtein(&query, &candidate))
+            .fold(0.0, f64::max);
+        if score >= FUZZY_THRESHOLD {
+            scored.push((score, id));
+        }
+    }
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(scored.into_iter().take(limit).map(|(_, id)| id).collect())
+}
@@ -4,6 +4,8 @@ use rusqlite::{Connection, Result, params};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
+use crate::utils::secret;
+
 /// Database stream server record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -46,6 +48,9 @@ pub struct ScanConfig {
     pub skip_short: bool,
     pub min_duration: f64,
     pub last_scan_at: Option<i64>,
+    /// Directories using size + partial content hash instead of mtime
+    #[serde(default)]
+    pub hash_check_directories: Vec<String>,
 }
 
 /// Generate a server ID from URL and username
@@ -57,10 +62,43 @@ fn generate_server_id(server_url: &str, username: &str) -> String {
     format!("server-{:x}", result)[..32].to_string()
 }
 
+/// Read and decrypt the stored password column
+fn read_password(row: &rusqlite::Row, index: usize) -> Result<String> {
+    let stored: String = row.get(index)?;
+    secret::open(&stored).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// Encrypt passwords still stored as plaintext by older versions.
+/// Returns the number of rows updated.
+pub fn seal_stored_passwords(conn: &Connection) -> Result<usize> {
+    let plaintext: Vec<(String, String)> = conn
+        .prepare("SELECT id, password FROM stream_servers")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>>>()?
+        .into_iter()
+        .filter(|(_, password)| !password.is_empty() && !secret::is_sealed(password))
+        .collect();
+
+    for (id, password) in &plaintext {
+        let sealed = secret::seal(password)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        conn.execute(
+            "UPDATE stream_servers SET password = ?2 WHERE id = ?1",
+            params![id, sealed],
+        )?;
+    }
+
+    Ok(plaintext.len())
+}
+
 /// Save or update a stream server configuration
 /// Returns the server ID
 pub fn save_stream_server(conn: &Connection, input: &StreamServerInput) -> Result<String> {
     let id = generate_server_id(&input.server_url, &input.username);
+    let password = secret::seal(&input.password)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
 
     conn.execute(
         "INSERT OR REPLACE INTO stream_servers
@@ -74,7 +112,7 @@ pub fn save_stream_server(conn: &Connection, input: &StreamServerInput) -> Resul
             input.server_name,
             input.server_url,
             input.username,
-            input.password,
+            password,
             input.access_token,
             input.user_id,
         ],
@@ -99,7 +137,7 @@ pub fn get_stream_servers(conn: &Connection) -> Result<Vec<DbStreamServer>> {
             server_name: row.get(2)?,
             server_url: row.get(3)?,
             username: row.get(4)?,
-            password: row.get(5)?,
+            password: read_password(row, 5)?,
             access_token: row.get(6)?,
             user_id: row.get(7)?,
             enabled: row.get::<_, i32>(8)? != 0,
@@ -127,7 +165,7 @@ pub fn get_stream_server(conn: &Connection, server_id: &str) -> Result<Option<Db
             server_name: row.get(2)?,
             server_url: row.get(3)?,
             username: row.get(4)?,
-            password: row.get(5)?,
+            password: read_password(row, 5)?,
             access_token: row.get(6)?,
             user_id: row.get(7)?,
             enabled: row.get::<_, i32>(8)? != 0,
@@ -142,6 +180,20 @@ pub fn get_stream_server(conn: &Connection, server_id: &str) -> Result<Option<Db
     }
 }
 
+/// Store a new Jellyfin/Emby access token after re-authentication
+pub fn update_stream_server_token(
+    conn: &Connection,
+    server_id: &str,
+    access_token: &str,
+    user_id: &str,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE stream_servers SET access_token = ?2, user_id = ?3 WHERE id = ?1",
+        params![server_id, access_token, user_id],
+    )?;
+    Ok(())
+}
+
 /// Delete a stream server and all associated songs
 pub fn delete_stream_server(conn: &Connection, server_id: &str) -> Result<()> {
     // Delete associated songs first
@@ -173,16 +225,20 @@ pub fn save_scan_config(conn: &Connection, config: &ScanConfig) -> Result<()> {
     let directories_json = serde_json::to_string(&config.directories)
         .unwrap_or_else(|_| "[]".to_string());
 
+    let hash_directories_json = serde_json::to_string(&config.hash_check_directories)
+        .unwrap_or_else(|_| "[]".to_string());
+
     // We keep only one scan config, so delete and insert
     conn.execute("DELETE FROM scan_configs", [])?;
     conn.execute(
-        "INSERT INTO scan_configs (directories, skip_short, min_duration, last_scan_at)
-         VALUES (?1, ?2, ?3, ?4)",
+        "INSERT INTO scan_configs (directories, skip_short, min_duration, last_scan_at, hash_directories)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
         params![
             directories_json,
             if config.skip_short { 1 } else { 0 },
             config.min_duration,
             config.last_scan_at,
+            hash_directories_json,
         ],
     )?;
 
@@ -192,7 +248,7 @@ pub fn save_scan_config(conn: &Connection, config: &ScanConfig) -> Result<()> {
 /// Get scan configuration
 pub fn get_scan_config(conn: &Connection) -> Result<Option<ScanConfig>> {
     let mut stmt = conn.prepare(
-        "SELECT id, directories, skip_short, min_duration, last_scan_at
+        "SELECT id, directories, skip_short, min_duration, last_scan_at, hash_directories
          FROM scan_configs
          LIMIT 1"
     )?;
@@ -203,9 +259,12 @@ pub fn get_scan_config(conn: &Connection) -> Result<Option<ScanConfig>> {
         let skip_short: i32 = row.get(2)?;
         let min_duration: f64 = row.get(3)?;
         let last_scan_at: Option<i64> = row.get(4)?;
+        let hash_directories_json: String = row.get(5)?;
 
         let directories: Vec<String> = serde_json::from_str(&directories_json)
             .unwrap_or_default();
+        let hash_check_directories: Vec<String> = serde_json::from_str(&hash_directories_json)
+            .unwrap_or_default();
 
         Ok(ScanConfig {
             id: Some(id),
@@ -213,6 +272,7 @@ pub fn get_scan_config(conn: &Connection) -> Result<Option<ScanConfig>> {
             skip_short: skip_short != 0,
             min_duration,
             last_scan_at,
+            hash_check_directories,
         })
     });
 
@@ -224,7 +284,6 @@ pub fn get_scan_config(conn: &Connection) -> Result<Option<ScanConfig>> {
 }
 
 /// Update last scan timestamp
-#[allow(dead_code)]
 pub fn update_last_scan_time(conn: &Connection) -> Result<()> {
     conn.execute(
         "UPDATE scan_configs SET last_scan_at = strftime('%s','now')",
@@ -0,0 +1,312 @@
+//! Typed application settings stored in the `settings` table
+//!
+//! Each setting is stored as JSON under its key. Values are validated by
+//! deserializing into the typed [`Setting`] enum, so unknown keys or malformed
+//! values never reach the database.
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+
+/// 10-band equalizer state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EqSetting {
+    pub enabled: bool,
+    /// Gain per band in dB
+    pub gains: [f32; 10],
+}
+
+/// Crossfade between tracks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossfadeSetting {
+    pub enabled: bool,
+    pub duration_secs: f32,
+}
+
+/// Automatic library rescan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSchedule {
+    pub enabled: bool,
+    /// Minutes between scans
+    pub interval_minutes: u32,
+    /// Rescan once at startup
+    pub on_startup: bool,
+}
+
+/// Offline download queue limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSettings {
+    /// Downloads running at the same time
+    pub max_concurrent: u32,
+    /// Total bandwidth cap in KB/s, 0 = unlimited
+    pub bandwidth_limit_kbps: u32,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self { max_concurrent: 2, bandwidth_limit_kbps: 0 }
+    }
+}
+
+/// ReplayGain 2.0 reference loudness; stored track gains bring a track to this level
+pub const REPLAY_GAIN_REFERENCE_LUFS: f32 = -18.0;
+
+/// Per-track loudness normalization from ReplayGain / server data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizationSettings {
+    pub enabled: bool,
+    /// Extra gain in dB added to every track's normalization gain
+    pub preamp_db: f32,
+    /// Loudness every track is brought to, e.g. -14 LUFS
+    #[serde(default = "default_target_lufs")]
+    pub target_lufs: f32,
+    /// Let boosted tracks exceed their peak and run them through the limiter,
+    /// instead of capping the gain at the track peak
+    #[serde(default = "default_limiter")]
+    pub limiter: bool,
+}
+
+fn default_target_lufs() -> f32 {
+    REPLAY_GAIN_REFERENCE_LUFS
+}
+
+fn default_limiter() -> bool {
+    true
+}
+
+impl Default for NormalizationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preamp_db: 0.0,
+            target_lufs: default_target_lufs(),
+            limiter: default_limiter(),
+        }
+    }
+}
+
+/// Rules for keeping stream songs offline automatically
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineSyncRules {
+    pub enabled: bool,
+    /// Songs whose custom metadata `favorite` is true
+    pub favorites: bool,
+    /// Songs of the N most recently changed playlists
+    pub recent_playlists: u32,
+    /// The N most recently played songs
+    pub recent_played: u32,
+    /// Storage budget for rule-managed downloads in MB, 0 = unlimited
+    pub budget_mb: u32,
+    /// Minutes between background evaluations
+    pub interval_minutes: u32,
+}
+
+impl Default for OfflineSyncRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            favorites: true,
+            recent_playlists: 5,
+            recent_played: 200,
+            budget_mb: 10240,
+            interval_minutes: 60,
+        }
+    }
+}
+
+/// Consent for anonymous usage statistics, off until the user opts in
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+}
+
+/// One setting with its typed value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "key", content = "value", rename_all = "camelCase")]
+pub enum Setting {
+    /// Output volume, 0.0 - 1.0
+    Volume(f32),
+    Eq(EqSetting),
+    Crossfade(CrossfadeSetting),
+    ScanSchedule(ScanSchedule),
+    /// Online lyrics providers (qq/kugou/netease), highest priority first
+    ProviderPriorities(Vec<String>),
+    /// Filename/directory templates for untagged files, tried in order
+    FilenameTemplates(Vec<String>),
+    Downloads(DownloadSettings),
+    OfflineSync(OfflineSyncRules),
+    Normalization(NormalizationSettings),
+    Telemetry(TelemetrySettings),
+}
+
+impl Setting {
+    /// Default value of every known setting
+    pub fn defaults() -> Vec<Setting> {
+        vec![
+            Setting::Volume(1.0),
+            Setting::Eq(EqSetting { enabled: false, gains: [0.0; 10] }),
+            Setting::Crossfade(CrossfadeSetting { enabled: false, duration_secs: 3.0 }),
+            Setting::ScanSchedule(ScanSchedule { enabled: false, interval_minutes: 60, on_startup: false }),
+            Setting::ProviderPriorities(vec![
+                "qq".to_string(),
+                "kugou".to_string(),
+                "netease".to_string(),
+            ]),
+            Setting::FilenameTemplates(vec![
+                "{artist}/{album}/{track} - {title}".to_string(),
+                "{artist}/{album}/{track}. {title}".to_string(),
+                "{artist} - {title}".to_string(),
+            ]),
+            Setting::Downloads(DownloadSettings::default()),
+            Setting::OfflineSync(OfflineSyncRules::default()),
+            Setting::Normalization(NormalizationSettings::default()),
+            Setting::Telemetry(TelemetrySettings::default()),
+        ]
+    }
+
+    /// Storage key, matching the serialized `key` field
+    pub fn key(&self) -> &'static str {
+        match self {
+            Setting::Volume(_) => "volume",
+            Setting::Eq(_) => "eq",
+            Setting::Crossfade(_) => "crossfade",
+            Setting::ScanSchedule(_) => "scanSchedule",
+            Setting::ProviderPriorities(_) => "providerPriorities",
+            Setting::FilenameTemplates(_) => "filenameTemplates",
+            Setting::Downloads(_) => "downloads",
+            Setting::OfflineSync(_) => "offlineSync",
+            Setting::Normalization(_) => "normalization",
+            Setting::Telemetry(_) => "telemetry",
+        }
+    }
+
+    /// Check value ranges that the types alone can't express
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            Setting::Volume(v) if !(0.0..=1.0).contains(v) => Err("音量必须在 0 到 1 之间".to_string()),
+            Setting::Eq(eq) if eq.gains.iter().any(|g| !(-12.0..=12.0).contains(g)) => {
+                Err("均衡器增益必须在 -12 到 12 dB 之间".to_string())
+            }
+            Setting::Crossfade(c) if !(0.0..=12.0).contains(&c.duration_secs) => {
+                Err("淡入淡出时长必须在 0 到 12 秒之间".to_string())
+            }
+            Setting::ScanSchedule(s) if s.interval_minutes < 5 => Err("扫描间隔不能少于 5 分钟".to_string()),
+            Setting::FilenameTemplates(t) if t.iter().any(|t| !t.contains("{title}")) => {
+                Err("文件名模板必须包含 {title}".to_string())
+            }
+            Setting::Downloads(d) if !(1..=8).contains(&d.max_concurrent) => {
+                Err("同时下载数必须在 1 到 8 之间".to_string())
+            }
+            Setting::OfflineSync(r) if r.interval_minutes < 15 => Err("同步间隔不能少于 15 分钟".to_string()),
+            Setting::Normalization(n) if !(-15.0..=15.0).contains(&n.preamp_db) => {
+                Err("前置增益必须在 -15 到 15 dB 之间".to_string())
+            }
+            Setting::Normalization(n) if !(-30.0..=-5.0).contains(&n.target_lufs) => {
+                Err("目标响度必须在 -30 到 -5 LUFS 之间".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rebuild a setting from its key and stored JSON
+    fn from_stored(key: &str, value: &str) -> Option<Setting> {
+        let value: serde_json::Value = serde_json::from_str(value).ok()?;
+        serde_json::from_value(serde_json::json!({ "key": key, "value": value })).ok()
+    }
+
+    fn value_json(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|mut v| v.get_mut("value").map(serde_json::Value::take))
+            .unwrap_or(serde_json::Value::Null)
+            .to_string()
+    }
+}
+
+/// Get one setting by key, falling back to its default.
+/// Returns `None` for unknown keys.
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<Setting>> {
+    let stored: Option<String> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .optional()?;
+
+    // Values that no longer parse (e.g. after a type change) fall back to the default
+    let setting = stored
+        .and_then(|value| Setting::from_stored(key, &value))
+        .or_else(|| Setting::defaults().into_iter().find(|s| s.key() == key));
+
+    Ok(setting)
+}
+
+/// Filename templates for untagged files
+pub fn filename_templates(conn: &Connection) -> Result<Vec<String>> {
+    match get_setting(conn, "filenameTemplates")? {
+        Some(Setting::FilenameTemplates(templates)) => Ok(templates),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Offline download queue limits
+pub fn download_settings(conn: &Connection) -> Result<DownloadSettings> {
+    match get_setting(conn, "downloads")? {
+        Some(Setting::Downloads(settings)) => Ok(settings),
+        _ => Ok(DownloadSettings::default()),
+    }
+}
+
+/// Offline sync rules
+pub fn offline_sync_rules(conn: &Connection) -> Result<OfflineSyncRules> {
+    match get_setting(conn, "offlineSync")? {
+        Some(Setting::OfflineSync(rules)) => Ok(rules),
+        _ => Ok(OfflineSyncRules::default()),
+    }
+}
+
+/// Loudness normalization settings
+pub fn normalization_settings(conn: &Connection) -> Result<NormalizationSettings> {
+    match get_setting(conn, "normalization")? {
+        Some(Setting::Normalization(settings)) => Ok(settings),
+        _ => Ok(NormalizationSettings::default()),
+    }
+}
+
+/// Telemetry consent
+pub fn telemetry_settings(conn: &Connection) -> Result<TelemetrySettings> {
+    match get_setting(conn, "telemetry")? {
+        Some(Setting::Telemetry(settings)) => Ok(settings),
+        _ => Ok(TelemetrySettings::default()),
+    }
+}
+
+/// All known settings, stored values taking precedence over defaults
+pub fn list_settings(conn: &Connection) -> Result<Vec<Setting>> {
+    Setting::defaults()
+        .into_iter()
+        .map(|default| Ok(get_setting(conn, default.key())?.unwrap_or(default)))
+        .collect()
+}
+
+/// Store a setting
+pub fn set_setting(conn: &Connection, setting: &Setting) -> Result<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value, updated_at)
+         VALUES (?1, ?2, strftime('%s','now'))
+         ON CONFLICT(key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = excluded.updated_at",
+        params![setting.key(), setting.value_json()],
+    )?;
+    Ok(())
+}
+
+/// Reset a setting to its default
+pub fn reset_setting(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+    Ok(())
+}
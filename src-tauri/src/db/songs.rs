@@ -1,7 +1,15 @@
 //! Song database operations
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Result, Row, params, params_from_iter};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::chapters::{replace_song_chapters, Chapter};
+use super::extra::{seed_cue_points, set_track_bounds, CuePoints};
+use super::pictures::{replace_song_pictures, SongPicture};
+use super::search::like_pattern;
+use crate::utils::tag_writer::TagEdit;
 
 /// Database song record
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +47,39 @@ pub struct DbSong {
     pub bitrate: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub channels: Option<u8>,
+    /// Unix timestamp when the song was first added to the library
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub composer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_no: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc_no: Option<u32>,
+    /// Stable album key, see `album_id_for`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    /// DR value (dB) from the dynamics analysis
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamic_range: Option<f32>,
+    /// Peak-to-RMS ratio (dB) from the dynamics analysis
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crest_factor: Option<f32>,
+    /// Star rating 1-5, 0 when unrated
+    #[serde(default)]
+    pub rating: u8,
+    /// Plays that got past half of the track
+    #[serde(default)]
+    pub play_count: i64,
+    /// Unix timestamp of the last counted play
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_played_at: Option<i64>,
 }
 
 /// Input data for saving a song
@@ -49,6 +90,18 @@ pub struct SongInput {
     pub title: String,
     pub artist: String,
     pub album: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_no: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc_no: Option<u32>,
     pub duration: f64,
     pub file_path: String,
     #[serde(default)]
@@ -75,6 +128,30 @@ pub struct SongInput {
     pub bitrate: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub channels: Option<u8>,
+    /// Partial content hash: change detection in hash directories, and recognizing moved files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// ReplayGain track gain (dB) from tags or the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_gain: Option<f32>,
+    /// ReplayGain track peak (linear)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_peak: Option<f32>,
+    /// ReplayGain album gain (dB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_gain: Option<f32>,
+    /// ReplayGain album peak (linear)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_peak: Option<f32>,
+    /// Typed embedded pictures; replaces the stored ones on save
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pictures: Vec<SongPicture>,
+    /// Range of the file this song covers, for tracks of an embedded cue sheet
+    #[serde(skip)]
+    pub cue: Option<CuePoints>,
+    /// Chapter list (audiobooks); replaces the stored one on save
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<Chapter>,
 }
 
 /// Get all songs from the database (fast loading, no cover data)
@@ -82,8 +159,11 @@ pub fn get_all_songs(conn: &Connection) -> Result<Vec<DbSong>> {
     let mut stmt = conn.prepare(
         "SELECT id, title, artist, album, duration, file_path, file_size,
                 is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
-                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
          FROM songs
+         WHERE missing_since IS NULL
          ORDER BY title COLLATE NOCASE"
     )?;
 
@@ -109,6 +189,19 @@ pub fn get_all_songs(conn: &Connection) -> Result<Vec<DbSong>> {
             sample_rate: row.get::<_, Option<u32>>(17)?,
             bitrate: row.get::<_, Option<u32>>(18)?,
             channels: row.get::<_, Option<u8>>(19)?,
+            created_at: row.get(20)?,
+            album_artist: row.get(21)?,
+            year: row.get(22)?,
+            album_id: row.get(23)?,
+            genre: row.get(24)?,
+            dynamic_range: row.get(25)?,
+            crest_factor: row.get(26)?,
+            rating: row.get(27)?,
+            play_count: row.get(28)?,
+            last_played_at: row.get(29)?,
+            track_no: row.get(30)?,
+            disc_no: row.get(31)?,
+            composer: row.get(32)?,
         })
     })?.collect::<Result<Vec<_>>>()?;
 
@@ -121,9 +214,11 @@ pub fn get_songs_by_source(conn: &Connection, source_type: &str) -> Result<Vec<D
     let mut stmt = conn.prepare(
         "SELECT id, title, artist, album, duration, file_path, file_size,
                 is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
-                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
          FROM songs
-         WHERE source_type = ?1
+         WHERE source_type = ?1 AND missing_since IS NULL
          ORDER BY title COLLATE NOCASE"
     )?;
 
@@ -149,12 +244,235 @@ pub fn get_songs_by_source(conn: &Connection, source_type: &str) -> Result<Vec<D
             sample_rate: row.get::<_, Option<u32>>(17)?,
             bitrate: row.get::<_, Option<u32>>(18)?,
             channels: row.get::<_, Option<u8>>(19)?,
+            created_at: row.get(20)?,
+            album_artist: row.get(21)?,
+            year: row.get(22)?,
+            album_id: row.get(23)?,
+            genre: row.get(24)?,
+            dynamic_range: row.get(25)?,
+            crest_factor: row.get(26)?,
+            rating: row.get(27)?,
+            play_count: row.get(28)?,
+            last_played_at: row.get(29)?,
+            track_no: row.get(30)?,
+            disc_no: row.get(31)?,
+            composer: row.get(32)?,
         })
     })?.collect::<Result<Vec<_>>>()?;
 
     Ok(songs)
 }
 
+/// Sort key for paginated song queries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SongSort {
+    #[default]
+    Title,
+    Artist,
+    Album,
+    Duration,
+    DateAdded,
+    DynamicRange,
+    CrestFactor,
+    Rating,
+}
+
+/// Paginated song query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongPageQuery {
+    #[serde(default)]
+    pub offset: u32,
+    pub limit: u32,
+    #[serde(default)]
+    pub sort: SongSort,
+    #[serde(default)]
+    pub descending: bool,
+    /// Only songs added within the last N days
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_within_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    /// Only analyzed songs with a DR value in this range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_dynamic_range: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_dynamic_range: Option<f32>,
+    /// Only songs rated at least this many stars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rating: Option<u8>,
+    /// Only songs whose title, artist or album contains this text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// One page of songs plus the total number of matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongPage {
+    pub songs: Vec<DbSong>,
+    pub total: i64,
+}
+
+/// Map a row selected with the standard 33-column song list
+pub(crate) fn song_from_row(row: &Row) -> Result<DbSong> {
+    Ok(DbSong {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        artist: row.get(2)?,
+        album: row.get(3)?,
+        duration: row.get(4)?,
+        file_path: row.get(5)?,
+        file_size: row.get(6)?,
+        is_hr: row.get::<_, Option<i32>>(7)?.map(|v| v != 0),
+        is_sq: row.get::<_, Option<i32>>(8)?.map(|v| v != 0),
+        cover_hash: row.get(9)?,
+        source_type: row.get(10)?,
+        server_id: row.get(11)?,
+        server_song_id: row.get(12)?,
+        stream_info: row.get(13)?,
+        file_modified: row.get(14)?,
+        format: row.get(15)?,
+        bit_depth: row.get::<_, Option<u8>>(16)?,
+        sample_rate: row.get::<_, Option<u32>>(17)?,
+        bitrate: row.get::<_, Option<u32>>(18)?,
+        channels: row.get::<_, Option<u8>>(19)?,
+        created_at: row.get(20)?,
+        album_artist: row.get(21)?,
+        year: row.get(22)?,
+        album_id: row.get(23)?,
+        genre: row.get(24)?,
+        dynamic_range: row.get(25)?,
+        crest_factor: row.get(26)?,
+        rating: row.get(27)?,
+        play_count: row.get(28)?,
+        last_played_at: row.get(29)?,
+        track_no: row.get(30)?,
+        disc_no: row.get(31)?,
+        composer: row.get(32)?,
+    })
+}
+
+/// Get songs by ID, in the given order (unknown and missing songs are skipped)
+pub fn get_songs_by_ids(conn: &Connection, ids: &[String]) -> Result<Vec<DbSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM songs
+         WHERE id = ?1 AND missing_since IS NULL"
+    )?;
+
+    let mut songs = Vec::with_capacity(ids.len());
+    for id in ids {
+        let mut rows = stmt.query_map([id], song_from_row)?;
+        if let Some(song) = rows.next() {
+            songs.push(song?);
+        }
+    }
+
+    Ok(songs)
+}
+
+/// Get one page of songs with sorting and filters
+pub fn get_songs_page(conn: &Connection, query: &SongPageQuery) -> Result<SongPage> {
+    let mut conditions: Vec<&str> = vec!["missing_since IS NULL"];
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(days) = query.added_within_days {
+        conditions.push("created_at >= strftime('%s','now') - ?");
+        values.push(Value::Integer(days as i64 * 86400));
+    }
+    if let Some(ref source_type) = query.source_type {
+        conditions.push("source_type = ?");
+        values.push(Value::Text(source_type.clone()));
+    }
+    if let Some(min) = query.min_dynamic_range {
+        conditions.push("dynamic_range >= ?");
+        values.push(Value::Real(min as f64));
+    }
+    if let Some(max) = query.max_dynamic_range {
+        conditions.push("dynamic_range <= ?");
+        values.push(Value::Real(max as f64));
+    }
+    if let Some(min) = query.min_rating {
+        conditions.push("rating >= ?");
+        values.push(Value::Integer(min as i64));
+    }
+    if let Some(filter) = query.filter.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        conditions.push("(title LIKE ? ESCAPE '\\' OR artist LIKE ? ESCAPE '\\' OR album LIKE ? ESCAPE '\\')");
+        values.extend(std::iter::repeat_n(Value::Text(like_pattern(filter)), 3));
+    }
+
+    let where_clause = format!(" WHERE {}", conditions.join(" AND "));
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM songs{}", where_clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let direction = if query.descending { "DESC" } else { "ASC" };
+    let order = match query.sort {
+        SongSort::Title => format!("title COLLATE NOCASE {}", direction),
+        SongSort::Artist => format!("artist COLLATE NOCASE {d}, album COLLATE NOCASE {d}, title COLLATE NOCASE {d}", d = direction),
+        SongSort::Album => format!(
+            "album COLLATE NOCASE {d}, COALESCE(disc_no, 1) {d}, track_no IS NULL, track_no {d}, file_path COLLATE NOCASE {d}",
+            d = direction
+        ),
+        SongSort::Duration => format!("duration {}, title COLLATE NOCASE", direction),
+        SongSort::DateAdded => format!("created_at {}, title COLLATE NOCASE", direction),
+        // Songs that haven't been analyzed go last either way
+        SongSort::DynamicRange => format!("dynamic_range IS NULL, dynamic_range {}, title COLLATE NOCASE", direction),
+        SongSort::CrestFactor => format!("crest_factor IS NULL, crest_factor {}, title COLLATE NOCASE", direction),
+        SongSort::Rating => format!("rating {}, title COLLATE NOCASE", direction),
+    };
+
+    let sql = format!(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM songs{}
+         ORDER BY {}
+         LIMIT ? OFFSET ?",
+        where_clause, order
+    );
+    values.push(Value::Integer(query.limit as i64));
+    values.push(Value::Integer(query.offset as i64));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let songs = stmt
+        .query_map(params_from_iter(values.iter()), song_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(SongPage { songs, total })
+}
+
+/// Newest songs of the library, most recently added first
+pub fn get_recently_added(conn: &Connection, limit: u32) -> Result<Vec<DbSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM unified_songs
+         ORDER BY created_at DESC, album COLLATE NOCASE, COALESCE(disc_no, 1), track_no
+         LIMIT ?1"
+    )?;
+
+    let songs = stmt
+        .query_map([limit], song_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
 /// Save songs to database in batches (within a transaction)
 pub fn save_songs(
     conn: &mut Connection,
@@ -165,12 +483,60 @@ pub fn save_songs(
     let tx = conn.transaction()?;
 
     {
+        // Upsert, so user data on the row (rating, plays, date added) stays put and the
+        // pictures / chapters referencing it aren't cascaded away. The dynamics / loudness
+        // analysis is kept only while the file itself is unchanged.
         let mut stmt = tx.prepare(
-            "INSERT OR REPLACE INTO songs
+            "INSERT INTO songs
              (id, title, artist, album, duration, file_path, file_size,
               is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
-              stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, strftime('%s','now'))"
+              stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels,
+              album_artist, year, genre, content_hash, replay_gain, replay_peak, album_gain, album_peak,
+              track_no, disc_no, composer, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+                     ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31,
+                     strftime('%s','now'), strftime('%s','now'))
+             ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                artist = excluded.artist,
+                album = excluded.album,
+                duration = excluded.duration,
+                file_path = excluded.file_path,
+                file_size = excluded.file_size,
+                is_hr = excluded.is_hr,
+                is_sq = excluded.is_sq,
+                cover_hash = excluded.cover_hash,
+                source_type = excluded.source_type,
+                server_id = excluded.server_id,
+                server_song_id = excluded.server_song_id,
+                stream_info = excluded.stream_info,
+                file_modified = excluded.file_modified,
+                format = excluded.format,
+                bit_depth = excluded.bit_depth,
+                sample_rate = excluded.sample_rate,
+                bitrate = excluded.bitrate,
+                channels = excluded.channels,
+                album_artist = excluded.album_artist,
+                year = excluded.year,
+                genre = excluded.genre,
+                content_hash = excluded.content_hash,
+                replay_gain = excluded.replay_gain,
+                replay_peak = excluded.replay_peak,
+                album_gain = excluded.album_gain,
+                album_peak = excluded.album_peak,
+                track_no = excluded.track_no,
+                disc_no = excluded.disc_no,
+                composer = excluded.composer,
+                dynamic_range = CASE WHEN file_size = excluded.file_size AND file_modified IS excluded.file_modified
+                                     THEN dynamic_range END,
+                crest_factor = CASE WHEN file_size = excluded.file_size AND file_modified IS excluded.file_modified
+                                    THEN crest_factor END,
+                loudness_lufs = CASE WHEN file_size = excluded.file_size AND file_modified IS excluded.file_modified
+                                     THEN loudness_lufs END,
+                true_peak = CASE WHEN file_size = excluded.file_size AND file_modified IS excluded.file_modified
+                                 THEN true_peak END,
+                missing_since = NULL,
+                updated_at = excluded.updated_at"
         )?;
 
         for song in songs {
@@ -195,7 +561,36 @@ pub fn save_songs(
                 song.sample_rate,
                 song.bitrate,
                 song.channels,
+                song.album_artist,
+                song.year,
+                song.genre,
+                song.content_hash,
+                song.replay_gain,
+                song.replay_peak,
+                song.album_gain,
+                song.album_peak,
+                song.track_no,
+                song.disc_no,
+                song.composer,
             ])?;
+            replace_song_pictures(&tx, &song.id, &song.pictures)?;
+            replace_song_chapters(&tx, &song.id, &song.chapters)?;
+
+            // A file split by an embedded cue sheet replaces its whole-file row, and the other way round
+            if source_type == "local" {
+                if let Some(cues) = &song.cue {
+                    let whole_file_id = format!("{:x}", md5::compute(&song.file_path));
+                    tx.execute("DELETE FROM songs WHERE id = ?1 AND file_path = ?2", params![whole_file_id, song.file_path])?;
+                    set_track_bounds(&tx, &song.id, cues)?;
+                    // The user's cue points start out at the track bounds; edits survive rescans
+                    seed_cue_points(&tx, &song.id, cues)?;
+                } else {
+                    tx.execute(
+                        "DELETE FROM songs WHERE file_path = ?1 AND id != ?2 AND source_type = 'local'",
+                        params![song.file_path, song.id],
+                    )?;
+                }
+            }
         }
     }
 
@@ -203,6 +598,370 @@ pub fn save_songs(
     Ok(songs.len())
 }
 
+/// ReplayGain gain (dB) and peak of a song, if known. `album` prefers the album
+/// gain; either kind falls back to the other when missing, and songs without
+/// tags use the gain derived from the loudness analysis.
+pub fn get_replay_gain(conn: &Connection, song_id: &str, album: bool) -> Result<Option<(f32, Option<f32>)>> {
+    type Gain = (Option<f32>, Option<f32>);
+    let row: Option<(Gain, Gain, Gain)> = conn
+        .query_row(
+            "SELECT replay_gain, replay_peak, album_gain, album_peak, loudness_lufs, true_peak
+             FROM songs WHERE id = ?1",
+            [song_id],
+            |row| Ok(((row.get(0)?, row.get(1)?), (row.get(2)?, row.get(3)?), (row.get(4)?, row.get(5)?))),
+        )
+        .optional()?;
+
+    Ok(row.and_then(|(track_row, album_row, (loudness, true_peak))| {
+        let (first, second) = if album { (album_row, track_row) } else { (track_row, album_row) };
+        let analyzed = loudness.map(|lufs| (super::settings::REPLAY_GAIN_REFERENCE_LUFS - lufs, true_peak));
+        first.0.map(|g| (g, first.1)).or_else(|| second.0.map(|g| (g, second.1))).or(analyzed)
+    }))
+}
+
+/// Local songs (id, file path) still waiting for the dynamics analysis, or all with `all`
+pub fn get_songs_for_dynamics(conn: &Connection, all: bool) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_path FROM songs
+         WHERE source_type = 'local' AND missing_since IS NULL AND (?1 OR dynamic_range IS NULL)
+         ORDER BY file_path"
+    )?;
+
+    let songs = stmt
+        .query_map([all], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
+/// Store the dynamics analysis result of a song
+pub fn set_song_dynamics(conn: &Connection, song_id: &str, dynamic_range: f32, crest_factor: f32) -> Result<()> {
+    conn.execute(
+        "UPDATE songs SET dynamic_range = ?2, crest_factor = ?3 WHERE id = ?1",
+        params![song_id, dynamic_range, crest_factor],
+    )?;
+    Ok(())
+}
+
+/// Set the star rating (0 = unrated, 1-5); false when the song doesn't exist
+pub fn set_rating(conn: &Connection, song_id: &str, rating: u8) -> Result<bool> {
+    let changed = conn.execute("UPDATE songs SET rating = ?2 WHERE id = ?1", params![song_id, rating])?;
+    Ok(changed > 0)
+}
+
+/// Store tags just written back to a local file, with the file's new state so
+/// the next incremental scan sees it as unchanged; false when the song doesn't exist
+pub fn update_song_tags(
+    conn: &Connection,
+    song_id: &str,
+    edit: &TagEdit,
+    file_size: i64,
+    file_modified: Option<i64>,
+    content_hash: Option<&str>,
+) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE songs SET title = ?2, artist = ?3, album = ?4, genre = ?5, year = ?6, track_no = ?7,
+                file_size = ?8, file_modified = COALESCE(?9, file_modified),
+                content_hash = COALESCE(?10, content_hash), updated_at = strftime('%s','now')
+         WHERE id = ?1 AND source_type = 'local'",
+        params![
+            song_id,
+            edit.title,
+            edit.artist,
+            edit.album,
+            edit.genre,
+            edit.year,
+            edit.track_no,
+            file_size,
+            file_modified,
+            content_hash,
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Local songs (id, file path) still waiting for the loudness analysis, or all with `all`
+pub fn get_songs_for_loudness(conn: &Connection, all: bool) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_path FROM songs
+         WHERE source_type = 'local' AND missing_since IS NULL AND (?1 OR loudness_lufs IS NULL)
+         ORDER BY file_path"
+    )?;
+
+    let songs = stmt
+        .query_map([all], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
+/// Store the loudness analysis result of a song
+pub fn set_song_loudness(conn: &Connection, song_id: &str, loudness_lufs: f32, true_peak: f32) -> Result<()> {
+    conn.execute(
+        "UPDATE songs SET loudness_lufs = ?2, true_peak = ?3 WHERE id = ?1",
+        params![song_id, loudness_lufs, true_peak],
+    )?;
+    Ok(())
+}
+
+/// Point a local song at its new file after it was moved. Local song IDs are
+/// derived from the path, so the ID changes too and every reference follows it.
+pub fn relocate_local_song(
+    conn: &mut Connection,
+    old_id: &str,
+    new_id: &str,
+    new_path: &str,
+    file_modified: Option<i64>,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    // song_pictures and chapters reference songs(id); checked again at commit
+    tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+
+    // A row already at the new path (e.g. picked up by the watcher) is superseded
+    if old_id != new_id {
+        tx.execute("DELETE FROM songs WHERE id = ?1", [new_id])?;
+    }
+    tx.execute(
+        "UPDATE songs SET id = ?2, file_path = ?3, file_modified = COALESCE(?4, file_modified),
+                updated_at = strftime('%s','now')
+         WHERE id = ?1 AND source_type = 'local'",
+        params![old_id, new_id, new_path, file_modified],
+    )?;
+    for table in ["song_pictures", "chapters", "song_extra", "playlist_songs", "play_history"] {
+        tx.execute(
+            &format!("UPDATE OR REPLACE {} SET song_id = ?2 WHERE song_id = ?1", table),
+            params![old_id, new_id],
+        )?;
+    }
+
+    tx.commit()
+}
+
+/// Stream server and server-side song ID of a stream song
+pub fn get_server_song(conn: &Connection, song_id: &str) -> Result<Option<(String, String)>> {
+    conn.query_row(
+        "SELECT server_id, server_song_id FROM songs
+         WHERE id = ?1 AND server_id IS NOT NULL AND server_song_id IS NOT NULL",
+        [song_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+/// Stored state of a local file, used for incremental change detection
+#[derive(Debug, Clone)]
+pub struct LocalFileState {
+    pub file_size: i64,
+    pub file_modified: Option<i64>,
+    pub content_hash: Option<String>,
+    /// Already marked as missing
+    pub missing: bool,
+}
+
+/// Change-detection state of every local song, keyed by file path
+pub fn get_local_file_states(conn: &Connection) -> Result<HashMap<String, LocalFileState>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_path, file_size, file_modified, content_hash, missing_since IS NOT NULL
+         FROM songs WHERE source_type = 'local'"
+    )?;
+
+    let states = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                LocalFileState {
+                    file_size: row.get(1)?,
+                    file_modified: row.get(2)?,
+                    content_hash: row.get(3)?,
+                    missing: row.get(4)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    Ok(states)
+}
+
+/// Store content fingerprints (file path, hash) of local files recorded without one
+pub fn set_content_hashes(conn: &mut Connection, hashes: &[(String, String)]) -> Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE songs SET content_hash = ?2 WHERE file_path = ?1 AND source_type = 'local'"
+        )?;
+        for (path, hash) in hashes {
+            stmt.execute(params![path, hash])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Files that disappeared (`missing_paths`) and newly found files with the same
+/// content fingerprint are the same file moved or renamed: point the existing
+/// rows at the new path instead of deleting them, keeping play counts, ratings
+/// and playlist entries. Returns the number of songs relocated.
+pub fn relocate_moved_songs(conn: &mut Connection, songs: &[SongInput], missing_paths: &[String]) -> Result<usize> {
+    if missing_paths.is_empty() {
+        return Ok(0);
+    }
+
+    // Content hash -> old path; None when several missing files share the hash
+    let mut by_hash: HashMap<String, Option<String>> = HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT content_hash FROM songs
+             WHERE file_path = ?1 AND source_type = 'local' AND content_hash IS NOT NULL
+             LIMIT 1"
+        )?;
+        for path in missing_paths {
+            if let Some(hash) = stmt.query_row([path], |row| row.get::<_, String>(0)).optional()? {
+                by_hash
+                    .entry(hash)
+                    .and_modify(|old| *old = None)
+                    .or_insert_with(|| Some(path.clone()));
+            }
+        }
+    }
+
+    let mut relocated = 0;
+    for song in songs {
+        let Some(Some(old_path)) = song.content_hash.as_ref().and_then(|hash| by_hash.get(hash)) else {
+            continue;
+        };
+        if *old_path == song.file_path {
+            continue;
+        }
+
+        let old_rows: Vec<(String, Option<u32>)> = conn
+            .prepare("SELECT id, track_no FROM songs WHERE file_path = ?1 AND source_type = 'local'")?
+            .query_map([old_path], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        // Tracks of a CUE image share the file and are told apart by number
+        let old_id = if song.cue.is_some() {
+            old_rows.iter().find(|(_, track_no)| *track_no == song.track_no)
+        } else if old_rows.len() == 1 {
+            old_rows.first()
+        } else {
+            None
+        }
+        .map(|(id, _)| id.clone());
+
+        if let Some(old_id) = old_id {
+            relocate_local_song(conn, &old_id, &song.id, &song.file_path, song.file_modified)?;
+            relocated += 1;
+        }
+    }
+
+    Ok(relocated)
+}
+
+/// A song whose file has disappeared, kept until it is restored or purged
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingSong {
+    #[serde(flatten)]
+    pub song: DbSong,
+    pub missing_since: i64,
+}
+
+/// Mark songs as missing instead of deleting them; already missing ones keep their time
+pub fn mark_songs_missing(conn: &Connection, ids: &[String]) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "UPDATE songs SET missing_since = strftime('%s','now')
+         WHERE id = ?1 AND missing_since IS NULL"
+    )?;
+    let mut marked = 0;
+    for id in ids {
+        marked += stmt.execute([id])?;
+    }
+    Ok(marked)
+}
+
+/// Mark the local songs of files that disappeared as missing
+pub fn mark_files_missing(conn: &Connection, paths: &[String]) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "UPDATE songs SET missing_since = strftime('%s','now')
+         WHERE file_path = ?1 AND source_type = 'local' AND missing_since IS NULL"
+    )?;
+    let mut marked = 0;
+    for path in paths {
+        marked += stmt.execute([path])?;
+    }
+    Ok(marked)
+}
+
+/// Missing songs, most recently missing first
+pub fn get_missing_songs(conn: &Connection) -> Result<Vec<MissingSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer, missing_since
+         FROM songs
+         WHERE missing_since IS NOT NULL
+         ORDER BY missing_since DESC, file_path, track_no"
+    )?;
+
+    let songs = stmt
+        .query_map([], |row| {
+            Ok(MissingSong {
+                song: song_from_row(row)?,
+                missing_since: row.get(33)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
+/// Clear the missing mark of a song; false when it wasn't missing
+pub fn restore_song(conn: &Connection, song_id: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE songs SET missing_since = NULL WHERE id = ?1 AND missing_since IS NOT NULL",
+        [song_id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Restore missing local songs whose file is back (e.g. a drive plugged in again)
+pub fn restore_present_songs(conn: &Connection) -> Result<usize> {
+    let present: Vec<String> = conn
+        .prepare("SELECT id, file_path FROM songs WHERE missing_since IS NOT NULL AND source_type = 'local'")?
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, path)| std::path::Path::new(path).exists())
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in &present {
+        restore_song(conn, id)?;
+    }
+    Ok(present.len())
+}
+
+/// Permanently delete songs missing for at least `older_than_days` days
+pub fn purge_missing_songs(conn: &Connection, older_than_days: u32) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM songs
+         WHERE missing_since IS NOT NULL AND missing_since <= strftime('%s','now') - ?1",
+        [older_than_days as i64 * 86400],
+    )
+}
+
+/// IDs of the present (not missing) songs of a source, optionally of one server
+pub fn get_song_ids_by_source(conn: &Connection, source_type: &str, server_id: Option<&str>) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM songs
+         WHERE source_type = ?1 AND (?2 IS NULL OR server_id = ?2) AND missing_since IS NULL"
+    )?;
+    let ids = stmt
+        .query_map(params![source_type, server_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
 /// Delete songs by source type (optionally filtered by server_id)
 pub fn delete_songs_by_source(
     conn: &Connection,
@@ -232,13 +991,13 @@ pub fn clear_all_songs(conn: &Connection) -> Result<usize> {
 
 /// Get count of songs
 pub fn get_song_count(conn: &Connection) -> Result<i64> {
-    conn.query_row("SELECT COUNT(*) FROM songs", [], |row| row.get(0))
+    conn.query_row("SELECT COUNT(*) FROM songs WHERE missing_since IS NULL", [], |row| row.get(0))
 }
 
 /// Get count of songs by source
 pub fn get_song_count_by_source(conn: &Connection, source_type: &str) -> Result<i64> {
     conn.query_row(
-        "SELECT COUNT(*) FROM songs WHERE source_type = ?1",
+        "SELECT COUNT(*) FROM songs WHERE source_type = ?1 AND missing_since IS NULL",
         [source_type],
         |row| row.get(0),
     )
@@ -0,0 +1,37 @@
+//! Stream playback cache
+//!
+//! Stream songs whose whole file was cached while playing. The files live in the
+//! stream cache directory and may be evicted without the row being removed, so
+//! callers check the file before using it.
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Record a completed cache file for a song
+pub fn mark_stream_cached(conn: &Connection, song_id: &str, local_path: &str, size_bytes: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO stream_cache (song_id, local_path, size_bytes)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(song_id) DO UPDATE SET
+            local_path = excluded.local_path,
+            size_bytes = excluded.size_bytes,
+            cached_at = strftime('%s','now')",
+        params![song_id, local_path, size_bytes],
+    )?;
+    Ok(())
+}
+
+/// Cache file recorded for a song
+pub fn get_stream_cache_path(conn: &Connection, song_id: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT local_path FROM stream_cache WHERE song_id = ?1",
+        [song_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Forget a cache entry whose file is gone
+pub fn remove_stream_cache(conn: &Connection, song_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM stream_cache WHERE song_id = ?1", [song_id])?;
+    Ok(())
+}
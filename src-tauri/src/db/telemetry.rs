@@ -0,0 +1,70 @@
+//! Local usage counters for the opt-in telemetry
+//!
+//! Only event names and counts are stored; rows are removed once uploaded.
+
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+pub const EVENT_FEATURE: &str = "feature";
+pub const EVENT_ERROR: &str = "error";
+
+/// How often one event happened since the last upload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryCounter {
+    /// feature / error
+    pub kind: String,
+    pub name: String,
+    pub count: i64,
+    pub first_at: i64,
+    pub last_at: i64,
+}
+
+/// Count one occurrence of an event
+pub fn increment_counter(conn: &Connection, kind: &str, name: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO telemetry_counters (kind, name, count, first_at, last_at)
+         VALUES (?1, ?2, 1, strftime('%s','now'), strftime('%s','now'))
+         ON CONFLICT(kind, name) DO UPDATE SET
+            count = count + 1,
+            last_at = excluded.last_at",
+        params![kind, name],
+    )?;
+    Ok(())
+}
+
+/// All pending counters, most frequent first
+pub fn list_counters(conn: &Connection) -> Result<Vec<TelemetryCounter>> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, name, count, first_at, last_at FROM telemetry_counters
+         ORDER BY kind, count DESC, name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TelemetryCounter {
+            kind: row.get(0)?,
+            name: row.get(1)?,
+            count: row.get(2)?,
+            first_at: row.get(3)?,
+            last_at: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Subtract uploaded counts, keeping events recorded during the upload
+pub fn remove_uploaded(conn: &mut Connection, uploaded: &[TelemetryCounter]) -> Result<()> {
+    let tx = conn.transaction()?;
+    for counter in uploaded {
+        tx.execute(
+            "UPDATE telemetry_counters SET count = count - ?3 WHERE kind = ?1 AND name = ?2",
+            params![counter.kind, counter.name, counter.count],
+        )?;
+    }
+    tx.execute("DELETE FROM telemetry_counters WHERE count <= 0", [])?;
+    tx.commit()
+}
+
+/// Drop all pending counters
+pub fn clear_counters(conn: &Connection) -> Result<usize> {
+    conn.execute("DELETE FROM telemetry_counters", [])
+}
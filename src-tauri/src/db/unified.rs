@@ -0,0 +1,71 @@
+//! One logical entry per track across local files and stream servers
+//!
+//! Songs are linked by `match_key` (normalized title + artist) plus a duration
+//! tolerance. The `unified_songs` view hides stream copies of tracks that
+//! also exist locally; `get_song_sources` lists every copy, local first.
+
+use rusqlite::{Connection, Result};
+
+use super::songs::song_from_row;
+use super::DbSong;
+
+/// Max duration difference (seconds) for two copies to count as the same track
+pub const DURATION_TOLERANCE_SECS: f64 = 3.0;
+
+/// Normalized title + artist used to link copies of the same track.
+/// Case, whitespace and punctuation are ignored.
+pub fn match_key(title: &str, artist: &str) -> String {
+    let normalize = |text: &str| -> String {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    format!("{}\u{1f}{}", normalize(title), normalize(artist))
+}
+
+/// All songs with stream duplicates of local tracks removed
+pub fn get_unified_songs(conn: &Connection) -> Result<Vec<DbSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, artist, album, duration, file_path, file_size,
+                is_hr, is_sq, cover_hash, source_type, server_id, server_song_id,
+                stream_info, file_modified, format, bit_depth, sample_rate, bitrate, channels, created_at,
+                album_artist, year, album_id, genre, dynamic_range, crest_factor, rating,
+                play_count, last_played_at, track_no, disc_no, composer
+         FROM unified_songs
+         ORDER BY title COLLATE NOCASE"
+    )?;
+
+    let songs = stmt
+        .query_map([], song_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs)
+}
+
+/// Every copy of a song (itself included), local first.
+/// Local copies whose file no longer exists are skipped, so the first entry
+/// is the one to play.
+pub fn get_song_sources(conn: &Connection, song_id: &str) -> Result<Vec<DbSong>> {
+    let mut stmt = conn.prepare(
+        "SELECT o.id, o.title, o.artist, o.album, o.duration, o.file_path, o.file_size,
+                o.is_hr, o.is_sq, o.cover_hash, o.source_type, o.server_id, o.server_song_id,
+                o.stream_info, o.file_modified, o.format, o.bit_depth, o.sample_rate, o.bitrate, o.channels, o.created_at,
+                o.album_artist, o.year, o.album_id, o.genre, o.dynamic_range, o.crest_factor, o.rating,
+                o.play_count, o.last_played_at, o.track_no, o.disc_no, o.composer
+         FROM songs s
+         JOIN songs o ON o.match_key = s.match_key AND ABS(o.duration - s.duration) <= ?2
+                      AND o.missing_since IS NULL
+         WHERE s.id = ?1
+         ORDER BY CASE o.source_type WHEN 'local' THEN 0 ELSE 1 END, o.id = ?1 DESC, o.id"
+    )?;
+
+    let songs = stmt
+        .query_map(rusqlite::params![song_id, DURATION_TOLERANCE_SECS], song_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(songs
+        .into_iter()
+        .filter(|song| song.source_type != "local" || std::path::Path::new(&song.file_path).exists())
+        .collect())
+}
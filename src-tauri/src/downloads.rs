@@ -0,0 +1,515 @@
+//! Offline download queue
+//!
+//! Downloads stream songs into the offline directory. The queue itself lives in
+//! the `offline_downloads` table; the manager runs at most `max_concurrent`
+//! downloads and shares one token bucket between them for the bandwidth cap.
+//! Unfinished files are kept as `.part`, so paused or interrupted downloads
+//! continue with an HTTP Range request.
+//!
+//! The offline sync rules (favorites, recent playlists, recently played) are
+//! re-evaluated in the background: new matches are queued and rule-managed
+//! downloads that no longer match or exceed the storage budget are evicted.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+
+use crate::commands::streaming::{server_config, stream_url_internal};
+use crate::db::{
+    self, DbState, DownloadSettings, OfflineSyncRules, DOWNLOAD_ACTIVE, DOWNLOAD_DONE, DOWNLOAD_FAILED,
+    DOWNLOAD_PAUSED, DOWNLOAD_QUEUED,
+};
+
+/// Minimum time between progress events of one download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the background job checks whether a sync is due
+const SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Payload of `offline:progress`, sent on status changes and while downloading
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub song_id: String,
+    pub status: String,
+    pub downloaded_bytes: i64,
+    pub total_bytes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Token bucket shared by all running downloads
+struct Throttle {
+    state: Mutex<ThrottleState>,
+}
+
+struct ThrottleState {
+    /// 0 = unlimited
+    bytes_per_sec: u64,
+    /// May go negative: downloads that overdraw wait until it is paid back
+    available: f64,
+    last_refill: Instant,
+}
+
+impl Throttle {
+    fn new(limit_kbps: u32) -> Self {
+        Self {
+            state: Mutex::new(ThrottleState {
+                bytes_per_sec: limit_kbps as u64 * 1024,
+                available: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    fn set_limit(&self, limit_kbps: u32) {
+        if let Ok(mut state) = self.state.lock() {
+            state.bytes_per_sec = limit_kbps as u64 * 1024;
+            state.available = 0.0;
+            state.last_refill = Instant::now();
+        }
+    }
+
+    /// Take `bytes` from the bucket; returns how long to wait to stay under the cap
+    fn reserve(&self, bytes: usize) -> Option<Duration> {
+        let mut state = self.state.lock().ok()?;
+        if state.bytes_per_sec == 0 {
+            return None;
+        }
+
+        let rate = state.bytes_per_sec as f64;
+        let now = Instant::now();
+        // Burst of at most one second worth of data after an idle period
+        state.available = (state.available + now.duration_since(state.last_refill).as_secs_f64() * rate).min(rate);
+        state.last_refill = now;
+        state.available -= bytes as f64;
+
+        (state.available < 0.0).then(|| Duration::from_secs_f64(-state.available / rate))
+    }
+}
+
+/// Result of one offline sync run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineSyncResult {
+    pub queued: usize,
+    pub evicted: usize,
+}
+
+/// How a download task ended without error
+enum Outcome {
+    Finished,
+    /// Paused or removed; holds the partial file
+    Stopped(PathBuf),
+}
+
+/// Runs the offline download queue
+pub struct DownloadManager {
+    app: AppHandle,
+    dir: PathBuf,
+    client: reqwest::Client,
+    max_concurrent: AtomicUsize,
+    throttle: Throttle,
+    /// Running downloads and their stop flags
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// Tauri managed state for the download manager
+pub struct DownloadManagerState(pub Arc<DownloadManager>);
+
+impl DownloadManager {
+    pub fn new(app: AppHandle, dir: PathBuf, settings: &DownloadSettings) -> Self {
+        Self {
+            app,
+            dir,
+            client: reqwest::Client::new(),
+            max_concurrent: AtomicUsize::new(settings.max_concurrent.max(1) as usize),
+            throttle: Throttle::new(settings.bandwidth_limit_kbps),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Apply new limits; running downloads pick up the bandwidth cap immediately
+    pub fn apply_settings(self: &Arc<Self>, settings: &DownloadSettings) {
+        self.max_concurrent
+            .store(settings.max_concurrent.max(1) as usize, Ordering::Relaxed);
+        self.throttle.set_limit(settings.bandwidth_limit_kbps);
+        self.pump();
+    }
+
+    /// Start queued downloads until the concurrency limit is reached
+    pub fn pump(self: &Arc<Self>) {
+        let Ok(mut active) = self.active.lock() else {
+            return;
+        };
+        let limit = self.max_concurrent.load(Ordering::Relaxed);
+        if active.len() >= limit {
+            return;
+        }
+
+        let db_state = self.app.state::<DbState>();
+        let Ok(conn) = db_state.write() else {
+            return;
+        };
+        let queued = db::get_queued_downloads(&conn).unwrap_or_default();
+
+        for song_id in queued {
+            if active.len() >= limit {
+                break;
+            }
+            if active.contains_key(&song_id) {
+                // Resumed while the paused task is still winding down; it pumps again on exit
+                continue;
+            }
+            let started = db::set_download_status(&conn, &song_id, &[DOWNLOAD_QUEUED], DOWNLOAD_ACTIVE, None)
+                .unwrap_or(false);
+            if !started {
+                continue;
+            }
+
+            let stop = Arc::new(AtomicBool::new(false));
+            active.insert(song_id.clone(), stop.clone());
+            self.emit_status(&conn, &song_id);
+
+            let manager = Arc::clone(self);
+            tauri::async_runtime::spawn(async move { manager.run(song_id, stop).await });
+        }
+    }
+
+    /// Queue songs and start downloading
+    pub fn enqueue(self: &Arc<Self>, song_ids: &[String]) -> Result<usize, String> {
+        let queued = {
+            let db_state = self.app.state::<DbState>();
+            let conn = db_state.write().map_err(|e| e.to_string())?;
+            db::enqueue_downloads(&conn, song_ids).map_err(|e| e.to_string())?
+        };
+        self.pump();
+        Ok(queued)
+    }
+
+    /// Make one song available offline. A complete copy in the stream playback
+    /// cache is taken over right away; otherwise the song goes through the queue.
+    pub fn download_song(self: &Arc<Self>, song_id: &str) -> Result<(), String> {
+        let cached = {
+            let db_state = self.app.state::<DbState>();
+            let conn = db_state.write().map_err(|e| e.to_string())?;
+            match db::get_download(&conn, song_id).map_err(|e| e.to_string())? {
+                Some(d) if d.status == DOWNLOAD_PAUSED => None,
+                Some(d) if d.status != DOWNLOAD_FAILED => return Ok(()),
+                _ => db::get_stream_cache_path(&conn, song_id)
+                    .map_err(|e| e.to_string())?
+                    .map(PathBuf::from)
+                    .filter(|path| path.is_file()),
+            }
+        };
+
+        let Some(cached) = cached else {
+            self.enqueue(&[song_id.to_string()])?;
+            return self.resume(song_id);
+        };
+
+        let (_, target) = self.resolve(song_id)?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("无法创建离线目录: {}", e))?;
+        }
+        let size = std::fs::copy(&cached, &target).map_err(|e| format!("写入文件失败: {}", e))? as i64;
+        // Leftover of an earlier failed attempt
+        let _ = std::fs::remove_file(part_path(&target));
+
+        let db_state = self.app.state::<DbState>();
+        let conn = db_state.write().map_err(|e| e.to_string())?;
+        db::enqueue_downloads(&conn, &[song_id.to_string()]).map_err(|e| e.to_string())?;
+        db::update_download_progress(&conn, song_id, &target.to_string_lossy(), size, size)
+            .map_err(|e| e.to_string())?;
+        db::set_download_status(&conn, song_id, &[DOWNLOAD_QUEUED], DOWNLOAD_DONE, None)
+            .map_err(|e| e.to_string())?;
+        self.emit_status(&conn, song_id);
+        Ok(())
+    }
+
+    /// Pause a queued or running download, keeping the partial file
+    pub fn pause(&self, song_id: &str) -> Result<(), String> {
+        let db_state = self.app.state::<DbState>();
+        let conn = db_state.write().map_err(|e| e.to_string())?;
+        let paused = db::set_download_status(&conn, song_id, &[DOWNLOAD_QUEUED, DOWNLOAD_ACTIVE], DOWNLOAD_PAUSED, None)
+            .map_err(|e| e.to_string())?;
+        if !paused {
+            return Ok(());
+        }
+
+        self.emit_status(&conn, song_id);
+        drop(conn);
+        self.signal_stop(song_id);
+        Ok(())
+    }
+
+    /// Put a paused or failed download back into the queue
+    pub fn resume(self: &Arc<Self>, song_id: &str) -> Result<(), String> {
+        {
+            let db_state = self.app.state::<DbState>();
+            let conn = db_state.write().map_err(|e| e.to_string())?;
+            let resumed = db::set_download_status(&conn, song_id, &[DOWNLOAD_PAUSED, DOWNLOAD_FAILED], DOWNLOAD_QUEUED, None)
+                .map_err(|e| e.to_string())?;
+            if resumed {
+                self.emit_status(&conn, song_id);
+            }
+        }
+        self.pump();
+        Ok(())
+    }
+
+    /// Drop a download from the queue and delete its file
+    pub fn remove(self: &Arc<Self>, song_id: &str) -> Result<(), String> {
+        let removed = {
+            let db_state = self.app.state::<DbState>();
+            let conn = db_state.write().map_err(|e| e.to_string())?;
+            db::remove_download(&conn, song_id).map_err(|e| e.to_string())?
+        };
+        self.signal_stop(song_id);
+
+        if let Some(path) = removed.and_then(|d| d.local_path) {
+            let path = PathBuf::from(path);
+            let _ = std::fs::remove_file(part_path(&path));
+            let _ = std::fs::remove_file(&path);
+        }
+        self.pump();
+        Ok(())
+    }
+
+    /// Apply the sync rules once: queue new matches, evict stale rule-managed downloads
+    pub fn sync(self: &Arc<Self>, rules: &OfflineSyncRules) -> Result<OfflineSyncResult, String> {
+        let (queued, stale) = {
+            let db_state = self.app.state::<DbState>();
+            let conn = db_state.write().map_err(|e| e.to_string())?;
+            let wanted = db::plan_offline_sync(&conn, rules).map_err(|e| e.to_string())?;
+            let queued = db::enqueue_auto_downloads(&conn, &wanted).map_err(|e| e.to_string())?;
+
+            let wanted: HashSet<String> = wanted.into_iter().collect();
+            let stale: Vec<String> = db::get_auto_downloads(&conn)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|id| !wanted.contains(id))
+                .collect();
+            (queued, stale)
+        };
+
+        for song_id in &stale {
+            self.remove(song_id)?;
+        }
+        self.pump();
+
+        Ok(OfflineSyncResult { queued, evicted: stale.len() })
+    }
+
+    /// Re-evaluate the sync rules every `interval_minutes` while they are enabled
+    pub fn spawn_sync_loop(self: &Arc<Self>) {
+        let manager = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            let mut last_run: Option<Instant> = None;
+            loop {
+                let rules = {
+                    let db_state = manager.app.state::<DbState>();
+                    let conn = db_state.write();
+                    conn.ok().and_then(|conn| db::settings::offline_sync_rules(&conn).ok())
+                };
+
+                if let Some(rules) = rules.filter(|r| r.enabled) {
+                    let interval = Duration::from_secs(rules.interval_minutes as u64 * 60);
+                    if !matches!(last_run, Some(t) if t.elapsed() < interval) {
+                        if let Err(e) = manager.sync(&rules) {
+                            eprintln!("Offline sync failed: {}", e);
+                        }
+                        last_run = Some(Instant::now());
+                    }
+                }
+
+                tokio::time::sleep(SYNC_CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    fn signal_stop(&self, song_id: &str) {
+        if let Ok(active) = self.active.lock() {
+            if let Some(stop) = active.get(song_id) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    async fn run(self: Arc<Self>, song_id: String, stop: Arc<AtomicBool>) {
+        let result = self.download(&song_id, &stop).await;
+
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(&song_id);
+        }
+
+        self.finish(&song_id, result);
+        self.pump();
+    }
+
+    /// Record how a download task ended
+    fn finish(&self, song_id: &str, result: Result<Outcome, String>) {
+        let db_state = self.app.state::<DbState>();
+        let Ok(conn) = db_state.write() else {
+            return;
+        };
+
+        match result {
+            Ok(Outcome::Finished) => {}
+            Ok(Outcome::Stopped(part)) => {
+                // Removed while running: the file was still open when remove() ran
+                if matches!(db::get_download(&conn, song_id), Ok(None)) {
+                    let _ = std::fs::remove_file(part);
+                }
+            }
+            Err(e) => {
+                eprintln!("Offline download of {} failed: {}", song_id, e);
+                let _ = db::set_download_status(&conn, song_id, &[DOWNLOAD_ACTIVE], DOWNLOAD_FAILED, Some(&e));
+                self.emit_status(&conn, song_id);
+            }
+        }
+    }
+
+    async fn download(&self, song_id: &str, stop: &AtomicBool) -> Result<Outcome, String> {
+        let (url, target) = self.resolve(song_id)?;
+        let part = part_path(&target);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("无法创建离线目录: {}", e))?;
+        }
+
+        let mut offset = tokio::fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0);
+        let mut request = self.client.get(&url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = request.send().await.map_err(|e| format!("下载请求失败: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // Partial file no longer matches the server's copy, start over
+            offset = 0;
+            response = self.client.get(&url).send().await.map_err(|e| format!("下载请求失败: {}", e))?;
+        }
+        if !response.status().is_success() {
+            return Err(format!("服务器返回错误: {}", response.status()));
+        }
+        // Servers that ignore Range send the whole file again
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+
+        let total = response.content_length().map(|len| len + offset).unwrap_or(0);
+        let mut options = tokio::fs::OpenOptions::new();
+        if offset > 0 {
+            options.append(true);
+        } else {
+            options.write(true).create(true).truncate(true);
+        }
+        let mut file = options.open(&part).await.map_err(|e| format!("无法写入文件: {}", e))?;
+
+        let mut downloaded = offset;
+        self.report(song_id, &target, downloaded, total);
+        let mut last_report = Instant::now();
+
+        while let Some(chunk) = response.chunk().await.map_err(|e| format!("下载中断: {}", e))? {
+            if stop.load(Ordering::Relaxed) {
+                file.flush().await.map_err(|e| format!("写入文件失败: {}", e))?;
+                self.report(song_id, &target, downloaded, total);
+                return Ok(Outcome::Stopped(part));
+            }
+            if let Some(wait) = self.throttle.reserve(chunk.len()) {
+                tokio::time::sleep(wait).await;
+            }
+
+            file.write_all(&chunk).await.map_err(|e| format!("写入文件失败: {}", e))?;
+            downloaded += chunk.len() as u64;
+
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                self.report(song_id, &target, downloaded, total);
+                last_report = Instant::now();
+            }
+        }
+
+        file.flush().await.map_err(|e| format!("写入文件失败: {}", e))?;
+        drop(file);
+        tokio::fs::rename(&part, &target)
+            .await
+            .map_err(|e| format!("写入文件失败: {}", e))?;
+
+        let db_state = self.app.state::<DbState>();
+        let conn = db_state.write().map_err(|e| e.to_string())?;
+        let path_str = target.to_string_lossy();
+        db::update_download_progress(&conn, song_id, &path_str, downloaded as i64, downloaded as i64)
+            .map_err(|e| e.to_string())?;
+        // A pause that raced with the last chunk still ends up done
+        db::set_download_status(&conn, song_id, &[DOWNLOAD_ACTIVE, DOWNLOAD_PAUSED], DOWNLOAD_DONE, None)
+            .map_err(|e| e.to_string())?;
+        self.emit_status(&conn, song_id);
+
+        Ok(Outcome::Finished)
+    }
+
+    /// Stream URL and target file of a queued song
+    fn resolve(&self, song_id: &str) -> Result<(String, PathBuf), String> {
+        let db_state = self.app.state::<DbState>();
+        let conn = db_state.write().map_err(|e| e.to_string())?;
+
+        let song = db::get_songs_by_ids(&conn, &[song_id.to_string()])
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "歌曲已不在音乐库中".to_string())?;
+        let server_id = song.server_id.as_deref().ok_or_else(|| "不是流媒体歌曲".to_string())?;
+        let server = db::get_stream_server(&conn, server_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "流媒体服务器不存在".to_string())?;
+
+        let remote_id = song.server_song_id.as_deref().unwrap_or(&song.id);
+        let url = stream_url_internal(&server_config(&server), remote_id);
+
+        let ext = song.format.as_deref().map(str::to_lowercase).unwrap_or_else(|| "audio".to_string());
+        let target = self.dir.join(server_id).join(format!("{:x}.{}", md5::compute(song_id), ext));
+
+        Ok((url, target))
+    }
+
+    /// Store progress and notify the frontend
+    fn report(&self, song_id: &str, target: &Path, downloaded: u64, total: u64) {
+        let db_state = self.app.state::<DbState>();
+        let Ok(conn) = db_state.write() else {
+            return;
+        };
+        let path_str = target.to_string_lossy();
+        let _ = db::update_download_progress(&conn, song_id, &path_str, downloaded as i64, total as i64);
+        self.emit_status(&conn, song_id);
+    }
+
+    fn emit_status(&self, conn: &rusqlite::Connection, song_id: &str) {
+        if let Ok(Some(download)) = db::get_download(conn, song_id) {
+            let _ = self.app.emit(
+                "offline:progress",
+                DownloadProgress {
+                    song_id: download.song_id,
+                    status: download.status,
+                    downloaded_bytes: download.downloaded_bytes,
+                    total_bytes: download.total_bytes,
+                    error: download.error,
+                },
+            );
+        }
+    }
+}
+
+/// Unfinished downloads are written next to the target file
+fn part_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
@@ -0,0 +1,161 @@
+//! Structured command errors
+//!
+//! Commands return a stable `code` plus `params` instead of a finished
+//! message, so the frontend can localize and branch on failures. `Display`
+//! renders the Chinese message for logs and for commands that still return
+//! plain strings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Machine-readable error category, sent as `code`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// Server unreachable (`details`)
+    ConnectionFailed,
+    /// Request timed out (`details`)
+    Timeout,
+    /// Non-success HTTP status (`status`)
+    HttpStatus,
+    /// Login rejected (`details`)
+    AuthFailed,
+    /// Error reported by the server API (`message`)
+    ServerError,
+    /// Response could not be parsed (`details`)
+    InvalidResponse,
+    /// Server config lacks a credential (`field`)
+    MissingCredentials,
+    /// Command doesn't apply to this server type
+    UnsupportedServer,
+    /// Unknown lyrics provider or other source (`source`)
+    UnsupportedSource,
+    /// Path doesn't exist (`path`)
+    NotFound,
+    /// Path is not a directory (`path`)
+    NotADirectory,
+    /// File system error (`details`)
+    Io,
+    /// Database error (`details`)
+    Database,
+    /// Downloaded data could not be decoded (`details`)
+    DecodeFailed,
+    /// A plugin provider script failed (`provider`, `details`)
+    ProviderFailed,
+    /// Anything else (`details`, optional)
+    Unknown,
+}
+
+/// Error returned by commands: a `code` and the values to fill into its message
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub code: ErrorCode,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<&'static str, String>,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Attach a message parameter
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.params.insert(key, value.to_string());
+        self
+    }
+
+    /// Classify a failed HTTP request (timeout vs. connection failure)
+    pub fn request(err: reqwest::Error) -> Self {
+        let code = if err.is_timeout() {
+            ErrorCode::Timeout
+        } else {
+            ErrorCode::ConnectionFailed
+        };
+        Self::new(code).with("details", err)
+    }
+
+    pub fn http(status: reqwest::StatusCode) -> Self {
+        Self::new(ErrorCode::HttpStatus).with("status", status.as_u16())
+    }
+
+    pub fn parse(err: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::InvalidResponse).with("details", err)
+    }
+
+    pub fn server(message: impl ToString) -> Self {
+        Self::new(ErrorCode::ServerError).with("message", message)
+    }
+
+    pub fn missing(field: &str) -> Self {
+        Self::new(ErrorCode::MissingCredentials).with("field", field)
+    }
+
+    pub fn decode(details: impl ToString) -> Self {
+        Self::new(ErrorCode::DecodeFailed).with("details", details)
+    }
+
+    fn param(&self, key: &str) -> &str {
+        self.params.get(key).map(String::as_str).unwrap_or("")
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(provider) = self.params.get("provider") {
+            write!(f, "[{}] ", provider)?;
+        }
+        match self.code {
+            ErrorCode::ConnectionFailed => write!(f, "连接失败: {}", self.param("details")),
+            ErrorCode::Timeout => write!(f, "请求超时: {}", self.param("details")),
+            ErrorCode::HttpStatus => write!(f, "服务器返回错误: HTTP {}", self.param("status")),
+            ErrorCode::AuthFailed => write!(f, "认证失败: {}", self.param("details")),
+            ErrorCode::ServerError => write!(f, "API 错误: {}", self.param("message")),
+            ErrorCode::InvalidResponse => write!(f, "解析响应失败: {}", self.param("details")),
+            ErrorCode::MissingCredentials => write!(f, "缺少 {}，请先测试连接", self.param("field")),
+            ErrorCode::UnsupportedServer => f.write_str("此操作不适用于该服务器类型"),
+            ErrorCode::UnsupportedSource => write!(f, "不支持的来源：{}", self.param("source")),
+            ErrorCode::NotFound => write!(f, "路径不存在: {}", self.param("path")),
+            ErrorCode::NotADirectory => write!(f, "不是文件夹: {}", self.param("path")),
+            ErrorCode::Io => write!(f, "文件操作失败: {}", self.param("details")),
+            ErrorCode::Database => write!(f, "数据库错误: {}", self.param("details")),
+            ErrorCode::DecodeFailed => write!(f, "解码失败: {}", self.param("details")),
+            ErrorCode::ProviderFailed => write!(f, "插件执行失败: {}", self.param("details")),
+            ErrorCode::Unknown if self.params.contains_key("details") => {
+                write!(f, "未知错误: {}", self.param("details"))
+            }
+            ErrorCode::Unknown => f.write_str("未知错误"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<CommandError> for String {
+    fn from(err: CommandError) -> Self {
+        err.to_string()
+    }
+}
+
+impl From<rusqlite::Error> for CommandError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::new(ErrorCode::Database).with("details", err)
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for CommandError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        Self::new(ErrorCode::Unknown).with("details", err)
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(ErrorCode::Io).with("details", err)
+    }
+}
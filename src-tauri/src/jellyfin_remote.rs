@@ -0,0 +1,356 @@
+//! Jellyfin remote control
+//!
+//! Keeps the session websocket of the first enabled Jellyfin server open so
+//! other Jellyfin apps can "cast" to BaYin. Playstate commands (pause, seek,
+//! stop, ...) go straight to the audio engine; Play, next/previous and volume
+//! are forwarded to the frontend, which owns the play queue. Playback of the
+//! server's songs is reported back through the Sessions API.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::audio_engine::engine::AudioCommand;
+use crate::audio_engine::AudioEngineState;
+use crate::commands::streaming::server_config;
+use crate::db::{self, DbState};
+use crate::models::StreamServerConfig;
+use crate::utils::jellyfin;
+
+/// Jellyfin positions are in 100 ns ticks
+const TICKS_PER_SEC: f64 = 10_000_000.0;
+
+/// How often playback progress is reported
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wait before reconnecting, or before looking for a server again
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Keep-alive period until the server sends `ForceKeepAlive`
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Payload of `jellyfin:play`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePlay {
+    /// Library song IDs; items that aren't in the library are dropped
+    pub song_ids: Vec<String>,
+    pub start_index: usize,
+    pub start_secs: f64,
+    /// now / next / last
+    pub mode: String,
+}
+
+/// Payload of `jellyfin:command`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteCommand {
+    /// next / previous / setVolume
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SocketMessage {
+    message_type: String,
+    #[serde(default)]
+    data: Value,
+}
+
+pub struct JellyfinRemote {
+    app: AppHandle,
+    /// Library song ID of the track handed to the audio engine
+    now_playing: Mutex<Option<String>>,
+    /// Song preloaded for the gapless handoff
+    next_up: Mutex<Option<String>>,
+}
+
+pub struct JellyfinRemoteState(pub Arc<JellyfinRemote>);
+
+impl JellyfinRemote {
+    pub fn new(app: AppHandle) -> Arc<Self> {
+        let remote = Arc::new(Self {
+            app: app.clone(),
+            now_playing: Mutex::new(None),
+            next_up: Mutex::new(None),
+        });
+
+        // Gapless handoffs don't go through audio_play, so follow the engine
+        let weak = Arc::downgrade(&remote);
+        app.listen_any("audio:track_changed", move |_| {
+            if let Some(remote) = weak.upgrade() {
+                let next = remote.next_up.lock().ok().and_then(|mut next| next.take());
+                remote.set_now_playing(next);
+            }
+        });
+
+        remote
+    }
+
+    /// Called whenever playback starts or stops, so progress can be reported
+    pub fn set_now_playing(&self, song_id: Option<String>) {
+        if let Ok(mut now_playing) = self.now_playing.lock() {
+            *now_playing = song_id;
+        }
+    }
+
+    /// Called when the next track is preloaded
+    pub fn set_next_up(&self, song_id: Option<String>) {
+        if let Ok(mut next_up) = self.next_up.lock() {
+            *next_up = song_id;
+        }
+    }
+
+    /// Keep a session open in the background, reconnecting when it drops
+    pub fn spawn(self: &Arc<Self>) {
+        let remote = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            loop {
+                if let Some((server_id, config)) = remote.find_server() {
+                    if let Err(e) = remote.run_session(&server_id, &config).await {
+                        eprintln!("Jellyfin remote session ended: {}", e);
+                    }
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    /// First enabled Jellyfin server with a token; re-read on every reconnect
+    /// so new servers and refreshed tokens are picked up
+    fn find_server(&self) -> Option<(String, StreamServerConfig)> {
+        let db_state = self.app.state::<DbState>();
+        let conn = db_state.write().ok()?;
+        db::servers::get_stream_servers(&conn)
+            .ok()?
+            .into_iter()
+            .find(|s| s.enabled && s.server_type == "jellyfin" && s.access_token.is_some())
+            .map(|s| (s.id.clone(), server_config(&s)))
+    }
+
+    async fn run_session(&self, server_id: &str, config: &StreamServerConfig) -> Result<(), String> {
+        let url = jellyfin::session_socket_url(config).ok_or("缺少 accessToken")?;
+        let client = reqwest::Client::new();
+
+        // Without capabilities the session doesn't show up as a cast target
+        jellyfin::post_session(
+            &client,
+            config,
+            "/Sessions/Capabilities/Full",
+            &json!({
+                "PlayableMediaTypes": ["Audio"],
+                "SupportedCommands": ["SetVolume"],
+                "SupportsMediaControl": true,
+            }),
+        )
+        .await?;
+
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| format!("WebSocket 连接失败: {}", e))?;
+        let (mut write, mut read) = socket.split();
+
+        let mut keep_alive = tokio::time::interval(DEFAULT_KEEP_ALIVE);
+        let mut report = tokio::time::interval(REPORT_INTERVAL);
+        let mut reported: Option<String> = None;
+
+        let result = loop {
+            tokio::select! {
+                message = read.next() => {
+                    let message = match message {
+                        Some(Ok(message)) => message,
+                        Some(Err(e)) => break Err(format!("WebSocket 错误: {}", e)),
+                        None => break Ok(()),
+                    };
+                    match message {
+                        Message::Text(text) => {
+                            if let Some(period) = self.handle_message(server_id, &text) {
+                                keep_alive = tokio::time::interval(period);
+                            }
+                        }
+                        Message::Close(_) => break Ok(()),
+                        _ => {}
+                    }
+                }
+                _ = keep_alive.tick() => {
+                    let ping = Message::Text(json!({ "MessageType": "KeepAlive" }).to_string().into());
+                    if let Err(e) = write.send(ping).await {
+                        break Err(format!("WebSocket 错误: {}", e));
+                    }
+                }
+                _ = report.tick() => {
+                    self.report(&client, server_id, config, &mut reported).await;
+                }
+            }
+        };
+
+        // The session is gone, so close the reported playback as well
+        if let Some(item_id) = reported {
+            let _ = jellyfin::post_session(&client, config, "/Sessions/Playing/Stopped", &json!({ "ItemId": item_id })).await;
+        }
+        result
+    }
+
+    /// Handle one socket message. Returns a new keep-alive period when the
+    /// server asks for one.
+    fn handle_message(&self, server_id: &str, text: &str) -> Option<Duration> {
+        let message: SocketMessage = serde_json::from_str(text).ok()?;
+        let data = &message.data;
+
+        match message.message_type.as_str() {
+            "ForceKeepAlive" => {
+                let timeout = data.as_u64().unwrap_or(60);
+                return Some(Duration::from_secs((timeout / 2).max(1)));
+            }
+            "Play" => self.handle_play(server_id, data),
+            "Playstate" => {
+                let command = data["Command"].as_str().unwrap_or_default();
+                match command {
+                    "Pause" => self.send_engine(AudioCommand::Pause),
+                    "Unpause" => self.send_engine(AudioCommand::Resume),
+                    "PlayPause" => {
+                        let playing = self.playback_state().map(|(playing, _, _)| playing).unwrap_or(false);
+                        self.send_engine(if playing { AudioCommand::Pause } else { AudioCommand::Resume });
+                    }
+                    "Stop" => {
+                        self.send_engine(AudioCommand::Stop);
+                        self.set_now_playing(None);
+                    }
+                    "Seek" => {
+                        let ticks = data["SeekPositionTicks"].as_f64().unwrap_or(0.0);
+                        self.send_engine(AudioCommand::Seek { position_secs: ticks / TICKS_PER_SEC });
+                    }
+                    "NextTrack" => self.emit_command("next", None),
+                    "PreviousTrack" => self.emit_command("previous", None),
+                    _ => {}
+                }
+            }
+            "GeneralCommand" if data["Name"].as_str() == Some("SetVolume") => {
+                // Arguments are strings, volume 0 - 100
+                let volume = data["Arguments"]["Volume"]
+                    .as_str()
+                    .and_then(|v| v.parse::<f64>().ok());
+                if let Some(volume) = volume {
+                    self.emit_command("setVolume", Some((volume / 100.0).clamp(0.0, 1.0)));
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn handle_play(&self, server_id: &str, data: &Value) {
+        let item_ids: Vec<String> = data["ItemIds"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let start_item = data["StartIndex"]
+            .as_u64()
+            .and_then(|i| item_ids.get(i as usize))
+            .map(|id| format!("{}-{}", server_id, id));
+
+        let song_ids = {
+            let db_state = self.app.state::<DbState>();
+            let Ok(conn) = db_state.write() else { return };
+            db::queue::map_server_songs(&conn, server_id, &item_ids).unwrap_or_default()
+        };
+        if song_ids.is_empty() {
+            eprintln!("Jellyfin remote: none of the requested items are in the library");
+            return;
+        }
+
+        let mode = match data["PlayCommand"].as_str() {
+            Some("PlayNext") => "next",
+            Some("PlayLast") => "last",
+            _ => "now",
+        };
+        let payload = RemotePlay {
+            start_index: start_item
+                .and_then(|id| song_ids.iter().position(|s| *s == id))
+                .unwrap_or(0),
+            song_ids,
+            start_secs: data["StartPositionTicks"].as_f64().unwrap_or(0.0) / TICKS_PER_SEC,
+            mode: mode.to_string(),
+        };
+        let _ = self.app.emit("jellyfin:play", payload);
+    }
+
+    /// Report the current track of this server to Jellyfin
+    async fn report(
+        &self,
+        client: &reqwest::Client,
+        server_id: &str,
+        config: &StreamServerConfig,
+        reported: &mut Option<String>,
+    ) {
+        let Some((is_playing, position_secs, duration_secs)) = self.playback_state() else { return };
+        // Stream songs are stored as `<server id>-<item id>`
+        let prefix = format!("{}-", server_id);
+        let current = self
+            .now_playing
+            .lock()
+            .ok()
+            .and_then(|now| now.as_ref().and_then(|id| id.strip_prefix(&prefix)).map(str::to_string))
+            .filter(|_| is_playing || duration_secs > 0.0);
+        let position_ticks = (position_secs * TICKS_PER_SEC) as i64;
+
+        if reported.is_some() && *reported != current {
+            let body = json!({ "ItemId": reported.take(), "PositionTicks": position_ticks });
+            if let Err(e) = jellyfin::post_session(client, config, "/Sessions/Playing/Stopped", &body).await {
+                eprintln!("Jellyfin remote: failed to report stop: {}", e);
+            }
+        }
+
+        let Some(item_id) = current else { return };
+        let body = json!({
+            "ItemId": item_id,
+            "PositionTicks": position_ticks,
+            "IsPaused": !is_playing,
+            "CanSeek": true,
+            "PlayMethod": "DirectStream",
+        });
+        let endpoint = if reported.is_none() {
+            "/Sessions/Playing"
+        } else {
+            "/Sessions/Playing/Progress"
+        };
+        match jellyfin::post_session(client, config, endpoint, &body).await {
+            Ok(()) => *reported = Some(item_id),
+            Err(e) => eprintln!("Jellyfin remote: failed to report playback: {}", e),
+        }
+    }
+
+    /// (is_playing, position, duration) of the audio engine
+    fn playback_state(&self) -> Option<(bool, f64, f64)> {
+        let engine_state = self.app.try_state::<AudioEngineState>()?;
+        let engine = engine_state.lock().ok()?;
+        let state = engine.state.lock().ok()?;
+        Some((state.is_playing, state.position_secs, state.duration_secs))
+    }
+
+    fn send_engine(&self, command: AudioCommand) {
+        if let Some(engine_state) = self.app.try_state::<AudioEngineState>() {
+            if let Ok(engine) = engine_state.lock() {
+                engine.send(command);
+            }
+        }
+    }
+
+    fn emit_command(&self, name: &str, value: Option<f64>) {
+        let _ = self.app.emit(
+            "jellyfin:command",
+            RemoteCommand {
+                name: name.to_string(),
+                value,
+            },
+        );
+    }
+}
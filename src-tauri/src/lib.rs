@@ -1,31 +1,90 @@
+mod cli;
 mod commands;
 mod db;
+mod error;
 mod models;
 mod utils;
 mod watcher;
 mod audio_engine;
+mod downloads;
+mod jellyfin_remote;
+mod telemetry;
+mod power;
+mod providers;
+mod shell_integration;
 
 use commands::{
     db_clear_all_songs, db_clear_scan_config, db_clear_stream_servers, db_delete_songs_by_ids,
-    db_delete_songs_by_source, db_delete_stream_server, db_get_all_albums, db_get_all_artists,
-    db_get_all_songs,
-    db_get_library_stats, db_get_scan_config, db_get_stream_servers,
+    db_export_stream_servers, db_import_stream_servers, db_export_library,
+    db_delete_songs_by_source, db_delete_stream_server, db_get_all_albums, db_get_album_pictures,
+    db_get_song_pictures, db_get_song_chapters, db_get_all_artists,
+    db_get_all_genres, db_get_songs_by_genre, db_get_albums_page, db_get_artists_page,
+    db_get_songs_by_album, db_get_songs_by_artist, db_get_folder_tree, db_get_songs_in_folder,
+    db_get_all_songs, db_get_unified_songs, db_get_song_sources, db_get_songs_page, db_search_songs,
+    db_get_recently_added,
+    db_get_library_stats, db_optimize, db_get_scan_config, db_get_stream_servers,
     db_migrate_from_localstorage, db_save_scan_config, db_save_songs, db_save_stream_server,
     fetch_stream_songs, fetch_subsonic_songs, get_lyrics, get_music_metadata, get_stream_lyrics,
     get_stream_url, get_subsonic_lyrics, get_subsonic_stream_url, jellyfin_authenticate,
-    list_directories, scan_music_files, test_stream_connection, test_subsonic_connection,
+    list_directories, scan_music_files, search_all_sources, test_stream_connection, test_subsonic_connection,
     scan_local_to_db, scan_stream_to_db,
     // Cover cache commands
     get_cover_url, get_cover_urls_batch, get_cover_cache_stats, cleanup_orphaned_covers, clear_cover_cache,
-    cleanup_missing_songs, CoverCacheState,
+    cleanup_missing_songs, db_get_missing_songs, db_restore_song, db_purge_missing, CoverCacheState,
+    // Library analysis commands
+    library_analyze_dynamics, library_analyze_loudness,
+    // Library organize commands
+    library_organize_preview,
+    library_organize_apply,
+    // Tag editing commands
+    edit_song_tags,
     // File watcher commands
     start_file_watcher, stop_file_watcher,
+    // Diagnostics commands
+    get_diagnostics,
+    // Telemetry commands
+    telemetry_record, telemetry_summary, telemetry_clear,
+    // Provider commands
+    list_providers, reload_providers, provider_find_cover, provider_find_metadata,
+    // Shell integration commands
+    shell_integration_status, shell_integration_install, shell_integration_uninstall, take_pending_queue,
+    // Playlist commands
+    db_get_playlists, db_get_playlist_songs, db_batch,
+    // Custom metadata commands
+    db_get_song_extra, db_set_song_extra,
+    // Cue point commands
+    db_get_cue_points, db_set_cue_points, db_set_rating,
+    // Bookmark commands
+    db_get_resume_position, db_save_resume_position, sync_bookmarks,
+    // Settings commands
+    settings_get, settings_set, settings_list, settings_reset,
+    // EQ preset commands
+    db_get_eq_presets, db_save_eq_preset, db_delete_eq_preset,
+    // Play history commands
+    db_record_listen, db_get_listening_stats, db_record_play, db_get_most_played, db_get_recently_played,
+    // Queue generation commands
+    queue_album, queue_artist_shuffle, queue_smart, queue_smart_shuffle, queue_radio,
+    // Queue export commands
+    queue_sync, queue_export_m3u8, playlist_export, queue_now_playing_text, PlayQueueState,
     // Audio engine commands
-    audio_play, audio_pause, audio_resume, audio_stop, audio_seek,
-    audio_set_volume, audio_set_eq_bands, audio_set_eq_enabled,
-    audio_enable_visualization, audio_get_state,
+    audio_play, audio_play_at, audio_previous, audio_pause, audio_resume, audio_stop, audio_seek, audio_seek_chapter,
+    audio_set_volume, audio_set_volume_curve, audio_set_eq_bands, audio_set_eq_layout, audio_set_eq_enabled,
+    audio_set_karaoke, audio_set_vocal_reduction,
+    audio_set_night_mode, audio_set_limiter, audio_set_dither, audio_set_skip_silence, audio_set_end_fade,
+    audio_set_replaygain_mode, audio_get_output_info, audio_export,
+    audio_set_ab_loop, audio_clear_ab_loop, audio_enable_visualization, audio_enable_levels, audio_get_state,
+    audio_preload_next,
+    audio_set_output_options, audio_set_muted, audio_get_waveform,
+    audio_set_output_backend, audio_set_output_sample_rate, audio_list_jack_ports, audio_set_multichannel, audio_set_resampler_quality,
+    audio_set_dsd_output,
+    // Native playback queue commands
+    audio_queue_set, audio_queue_update, audio_queue_play, audio_queue_next, audio_queue_prev,
+    audio_queue_insert, audio_queue_remove, audio_queue_set_shuffle, audio_queue_set_repeat, audio_queue_get,
     // 在线歌词命令
-    search_online_lyrics, fetch_online_lyric,
+    search_online_lyrics, fetch_online_lyric, clear_online_lyrics_cache,
+    // Offline download commands
+    offline_enqueue, offline_list, offline_pause, offline_resume, offline_remove, offline_get_path,
+    offline_sync_now, download_song_for_offline,
 };
 use db::DbState;
 use std::{io, path::PathBuf, sync::Mutex};
@@ -33,6 +92,8 @@ use utils::cover::CoverCache;
 use tauri::{Emitter, Manager, LogicalSize, Size};
 use rayon::iter::{ParallelIterator, IntoParallelRefIterator};
 
+pub use cli::run_cli;
+
 #[cfg(desktop)]
 use tauri::menu::{Menu, MenuItem};
 #[cfg(desktop)]
@@ -69,7 +130,15 @@ fn set_tray_language(app: tauri::AppHandle, lang: String) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let builder = tauri::Builder::default()
+    let builder = tauri::Builder::default();
+
+    // 单实例：右键“添加到 BaYin 队列”启动的新进程把参数交给已运行的实例
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+        shell_integration::desktop::handle_args(app, &argv, std::path::Path::new(&cwd));
+    }));
+
+    let builder = builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -83,6 +152,15 @@ pub fn run() {
             get_lyrics,
             search_online_lyrics,
             fetch_online_lyric,
+            clear_online_lyrics_cache,
+            offline_enqueue,
+            offline_list,
+            offline_pause,
+            offline_resume,
+            offline_remove,
+            offline_get_path,
+            offline_sync_now,
+            download_song_for_offline,
             list_directories,
             // 统一流媒体命令
             test_stream_connection,
@@ -90,6 +168,7 @@ pub fn run() {
             get_stream_url,
             get_stream_lyrics,
             jellyfin_authenticate,
+            search_all_sources,
             // Subsonic API 命令
             test_subsonic_connection,
             fetch_subsonic_songs,
@@ -97,8 +176,24 @@ pub fn run() {
             get_subsonic_lyrics,
             // 数据库命令
             db_get_all_songs,
+            db_get_unified_songs,
+            db_get_song_sources,
+            db_get_songs_page,
+            db_get_recently_added,
+            db_search_songs,
             db_get_all_albums,
+            db_get_album_pictures,
+            db_get_song_pictures,
+            db_get_song_chapters,
             db_get_all_artists,
+            db_get_all_genres,
+            db_get_songs_by_genre,
+            db_get_albums_page,
+            db_get_artists_page,
+            db_get_songs_by_album,
+            db_get_songs_by_artist,
+            db_get_folder_tree,
+            db_get_songs_in_folder,
             db_save_songs,
             db_delete_songs_by_source,
             db_delete_songs_by_ids,
@@ -107,11 +202,15 @@ pub fn run() {
             db_save_stream_server,
             db_delete_stream_server,
             db_clear_stream_servers,
+            db_export_stream_servers,
+            db_export_library,
+            db_import_stream_servers,
             db_save_scan_config,
             db_get_scan_config,
             db_clear_scan_config,
             db_migrate_from_localstorage,
             db_get_library_stats,
+            db_optimize,
             // 高级扫描命令
             scan_local_to_db,
             scan_stream_to_db,
@@ -122,23 +221,130 @@ pub fn run() {
             cleanup_orphaned_covers,
             clear_cover_cache,
             cleanup_missing_songs,
+            db_get_missing_songs,
+            db_restore_song,
+            db_purge_missing,
+            // 曲库分析命令
+            library_analyze_dynamics,
+            library_analyze_loudness,
+            // 文件整理命令
+            library_organize_preview,
+            library_organize_apply,
+            // 标签编辑命令
+            edit_song_tags,
             // 文件监听命令
             start_file_watcher,
             stop_file_watcher,
+            // 诊断命令
+            get_diagnostics,
+            // 使用统计命令
+            telemetry_record,
+            telemetry_summary,
+            telemetry_clear,
+            // 歌词/封面/元数据来源命令
+            list_providers,
+            reload_providers,
+            provider_find_cover,
+            provider_find_metadata,
+            // 右键菜单集成命令
+            shell_integration_status,
+            shell_integration_install,
+            shell_integration_uninstall,
+            take_pending_queue,
+            // 歌单命令
+            db_get_playlists,
+            db_get_playlist_songs,
+            db_batch,
+            // 自定义元数据命令
+            db_get_song_extra,
+            db_set_song_extra,
+            // 歌曲起止点命令
+            db_get_cue_points,
+            db_set_rating,
+            db_set_cue_points,
+            // 书签命令
+            db_get_resume_position,
+            db_save_resume_position,
+            sync_bookmarks,
+            // 设置命令
+            settings_get,
+            settings_set,
+            settings_list,
+            settings_reset,
+            // 均衡器预设命令
+            db_get_eq_presets,
+            db_save_eq_preset,
+            db_delete_eq_preset,
+            // 播放历史命令
+            db_record_listen,
+            db_get_listening_stats,
+            db_record_play,
+            db_get_most_played,
+            db_get_recently_played,
+            // 播放队列生成命令
+            queue_album,
+            queue_artist_shuffle,
+            queue_smart,
+            queue_smart_shuffle,
+            queue_radio,
+            // 播放队列导出命令
+            queue_sync,
+            queue_export_m3u8,
+            playlist_export,
+            queue_now_playing_text,
             // 托盘命令
             #[cfg(desktop)]
             set_tray_language,
             // 音频引擎命令
             audio_play,
+            audio_play_at,
+            audio_previous,
             audio_pause,
             audio_resume,
             audio_stop,
             audio_seek,
+            audio_seek_chapter,
             audio_set_volume,
+            audio_set_volume_curve,
             audio_set_eq_bands,
+            audio_set_eq_layout,
             audio_set_eq_enabled,
+            audio_set_karaoke,
+            audio_set_vocal_reduction,
+            audio_set_night_mode,
+            audio_set_limiter,
+            audio_set_dither,
+            audio_set_skip_silence,
+            audio_set_end_fade,
+            audio_set_replaygain_mode,
+            audio_get_output_info,
+            audio_export,
+            audio_set_ab_loop,
+            audio_clear_ab_loop,
             audio_enable_visualization,
-            audio_get_state
+            audio_enable_levels,
+            audio_get_state,
+            audio_preload_next,
+            audio_set_output_options,
+            audio_set_muted,
+            audio_get_waveform,
+            audio_set_output_backend,
+            audio_set_output_sample_rate,
+            audio_list_jack_ports,
+            audio_set_multichannel,
+            audio_set_resampler_quality,
+            audio_set_dsd_output,
+            // 原生播放队列命令
+            audio_queue_set,
+            audio_queue_update,
+            audio_queue_play,
+            audio_queue_next,
+            audio_queue_prev,
+            audio_queue_insert,
+            audio_queue_remove,
+            audio_queue_set_shuffle,
+            audio_queue_set_repeat,
+            audio_queue_get
         ])
         .on_window_event(|_window, _event| {
             #[cfg(desktop)]
@@ -178,9 +384,11 @@ pub fn run() {
             let db_dir = data_root.join("db");
             std::fs::create_dir_all(&db_dir).expect("Failed to create database directory");
             let db_path = db_dir.join("bayin.db");
-            let conn = db::open_db(&db_path).expect("Failed to open database");
+            // 迁移失败时已自动恢复备份，把原因带出去而不是直接 panic
+            let conn = db::open_db(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+            let reader = db::open_reader(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
 
-            app.manage(DbState(Mutex::new(conn)));
+            app.manage(DbState::new(conn, reader));
 
             // 初始化封面缓存
             let cover_cache_dir = data_root.join("cache").join("covers");
@@ -189,6 +397,37 @@ pub fn run() {
 
             app.manage(CoverCacheState(Mutex::new(cover_cache)));
 
+            // 歌词/封面/元数据来源：内置来源 + 用户脚本
+            app.manage(providers::ProviderRegistryState(providers::ProviderRegistry::new(
+                data_root.join("providers"),
+            )));
+
+            // 匿名使用统计（默认关闭，用户同意后才计数和上传）
+            {
+                let telemetry = telemetry::Telemetry::new(app.handle().clone());
+                telemetry.spawn();
+                app.manage(telemetry::TelemetryState(telemetry));
+            }
+
+            // 初始化离线下载队列，上次退出时未完成的下载重新排队
+            {
+                use downloads::{DownloadManager, DownloadManagerState};
+                let db_state: tauri::State<'_, DbState> = app.state();
+                let download_settings = {
+                    let conn = db_state.write().expect("Failed to lock database");
+                    let _ = db::offline::requeue_interrupted_downloads(&conn);
+                    db::settings::download_settings(&conn).unwrap_or_default()
+                };
+                let manager = std::sync::Arc::new(DownloadManager::new(
+                    app.handle().clone(),
+                    data_root.join("offline"),
+                    &download_settings,
+                ));
+                manager.pump();
+                manager.spawn_sync_loop();
+                app.manage(DownloadManagerState(manager));
+            }
+
             // 初始化文件监听器状态（仅桌面端）
             #[cfg(desktop)]
             {
@@ -196,13 +435,90 @@ pub fn run() {
                 app.manage(FileWatcherState(Mutex::new(WatcherState::new())));
             }
 
+            // 播放队列镜像（前端同步），用于导出
+            app.manage(PlayQueueState::default());
+
+            // 右键菜单“添加到 BaYin 队列”：首次启动时的参数也走同一流程
+            app.manage(shell_integration::PendingQueueState::default());
+            #[cfg(desktop)]
+            {
+                let argv: Vec<String> = std::env::args().collect();
+                if let Ok(cwd) = std::env::current_dir() {
+                    shell_integration::desktop::handle_args(app.handle(), &argv, &cwd);
+                }
+            }
+
             // 初始化音频引擎
             {
                 use audio_engine::engine::AudioEngine;
                 let audio_engine = AudioEngine::new(app.handle().clone());
                 app.manage(audio_engine::AudioEngineState::new(audio_engine));
+
+                // 播放中流 URL 过期（401/403）时重新认证并生成新 URL
+                let handle = app.handle().clone();
+                audio_engine::http_source::set_url_refresher(Box::new(move |url| {
+                    commands::streaming::refresh_stream_url(&handle, url)
+                }));
+
+                // 网络卡顿时流媒体自动重连，期间通知前端显示缓冲状态
+                let handle = app.handle().clone();
+                audio_engine::http_source::set_buffering_listener(Box::new(move |payload| {
+                    let _ = handle.emit("audio:buffering", payload);
+                }));
+
+                // 流媒体进度条波形：边下载边生成，完成后缓存
+                let handle = app.handle().clone();
+                audio_engine::waveform::init(
+                    data_root.join("cache").join("waveforms"),
+                    Box::new(move |update| {
+                        let _ = handle.emit("audio:waveform", update);
+                    }),
+                );
+
+                // 流媒体播放缓存：完整下载过的歌曲再次播放和跳转都读本地文件
+                let handle = app.handle().clone();
+                audio_engine::stream_cache::init(
+                    data_root.join("cache").join("streams"),
+                    Box::new(move |song_id, path, size| {
+                        let db_state = handle.state::<DbState>();
+                        let Ok(conn) = db_state.write() else {
+                            return;
+                        };
+                        let _ = db::mark_stream_cached(&conn, song_id, &path.to_string_lossy(), size as i64);
+                    }),
+                );
+            }
+
+            // Jellyfin 远程控制：作为投放目标接收其他客户端的播放指令
+            {
+                let remote = jellyfin_remote::JellyfinRemote::new(app.handle().clone());
+                remote.spawn();
+                app.manage(jellyfin_remote::JellyfinRemoteState(remote));
             }
 
+            // 系统睡眠前立即暂停并释放输出设备，唤醒后在原位置重新打开（保持暂停）
+            #[cfg(desktop)]
+            {
+                use audio_engine::engine::AudioCommand;
+                use power::desktop::PowerEvent;
+                let handle = app.handle().clone();
+                power::desktop::watch(Box::new(move |event| {
+                    let engine = handle.state::<audio_engine::AudioEngineState>();
+                    let Ok(engine) = engine.lock() else {
+                        return;
+                    };
+                    engine.send(match event {
+                        PowerEvent::Suspend => AudioCommand::SystemSuspend,
+                        PowerEvent::Resume => AudioCommand::SystemResume,
+                    });
+                }));
+            }
+
+            // 原生播放队列：无缝切到队列下一首后预加载再下一首，播完时接着播放下一首
+            commands::audio::follow_queue(app.handle());
+            // 听过一半的歌曲计入播放次数
+            commands::audio::record_plays(app.handle());
+
             // 桌面端：创建系统托盘
             #[cfg(desktop)]
             {
@@ -282,7 +598,7 @@ pub fn run() {
                 // Read scan config from DB
                 let db_state: tauri::State<'_, DbState> = app_handle.state();
                 let scan_config = {
-                    let conn = match db_state.0.lock() {
+                    let conn = match db_state.write() {
                         Ok(c) => c,
                         Err(_) => return,
                     };
@@ -299,6 +615,8 @@ pub fn run() {
                             mode: models::ScanMode::Incremental,
                             min_duration: if config.skip_short { Some(config.min_duration) } else { None },
                             batch_size: 500,
+                            hash_check_directories: config.hash_check_directories,
+                            dry_run: false,
                         };
 
                         // Use tokio runtime to run async scan
@@ -307,79 +625,82 @@ pub fn run() {
                         rt.block_on(async move {
                             let db_state2: tauri::State<'_, DbState> = app_clone.state();
                             // Collect files
-                            let mut audio_paths = Vec::new();
-                            for dir in &options.directories {
-                                let dir_path = std::path::Path::new(dir);
-                                if !dir_path.exists() {
-                                    continue;
-                                }
-                                for entry in walkdir::WalkDir::new(dir_path)
-                                    .follow_links(true)
-                                    .into_iter()
-                                    .filter_map(|e| e.ok())
-                                {
-                                    let path = entry.path();
-                                    if path.is_file() && utils::audio::is_audio_file(path) {
-                                        audio_paths.push(path.to_path_buf());
-                                    }
-                                }
+                            let collected = utils::walk::collect_audio_files(&options.directories);
+                            for dir in &collected.skipped_cycles {
+                                eprintln!("Startup scan: skipped symlink cycle at {}", dir);
                             }
+                            let audio_paths = collected.audio_paths;
 
                             // Check for changes (incremental)
-                            let existing_files: std::collections::HashMap<String, Option<i64>> = {
-                                let conn = match db_state2.0.lock() {
+                            let (existing_files, templates) = {
+                                let conn = match db_state2.write() {
                                     Ok(c) => c,
                                     Err(_) => return,
                                 };
-                                let songs = db::songs::get_all_songs(&conn).unwrap_or_default();
-                                songs
-                                    .into_iter()
-                                    .filter(|s| s.source_type == "local")
-                                    .map(|s| (s.file_path, s.file_modified))
-                                    .collect()
+                                (
+                                    db::songs::get_local_file_states(&conn).unwrap_or_default(),
+                                    db::settings::filename_templates(&conn).unwrap_or_default(),
+                                )
                             };
+                            let templates = utils::path_template::PathTemplates::compile(&templates);
 
                             let min_dur = options.min_duration.unwrap_or(0.0);
                             let mut new_or_changed = Vec::new();
+                            let mut known_hashes = std::collections::HashMap::new();
+                            let mut unhashed = Vec::new();
 
                             for path in &audio_paths {
                                 let path_str = path.to_string_lossy().to_string();
-                                let needs_scan = match existing_files.get(&path_str) {
-                                    Some(Some(db_mtime)) => {
-                                        match std::fs::metadata(path) {
-                                            Ok(meta) => match meta.modified() {
-                                                Ok(mtime) => {
-                                                    let file_mtime = mtime
-                                                        .duration_since(std::time::UNIX_EPOCH)
-                                                        .map(|d| d.as_secs() as i64)
-                                                        .unwrap_or(0);
-                                                    file_mtime > *db_mtime
-                                                }
-                                                Err(_) => true,
-                                            },
-                                            Err(_) => true,
-                                        }
-                                    }
-                                    _ => true,
-                                };
+                                let use_hash = utils::fingerprint::uses_content_hash(path, &options.hash_check_directories);
+                                let known = existing_files.get(&path_str);
+                                let check = utils::fingerprint::check_file(path, known, use_hash);
+                                if let Some(hash) = check.content_hash {
+                                    known_hashes.insert(path.clone(), hash);
+                                }
 
-                                if needs_scan {
+                                if check.changed {
                                     new_or_changed.push(path.clone());
+                                } else if known.is_some_and(|k| k.content_hash.is_none()) {
+                                    unhashed.push(path.clone());
                                 }
                             }
 
+                            // Fingerprints for files recorded before every file got one
+                            let backfilled: Vec<(String, String)> = unhashed
+                                .par_iter()
+                                .filter_map(|path| {
+                                    utils::fingerprint::partial_content_hash(path)
+                                        .ok()
+                                        .map(|hash| (path.to_string_lossy().to_string(), hash))
+                                })
+                                .collect();
+
                             // Only proceed if there are changes or deleted files
                             let disk_paths: std::collections::HashSet<String> = audio_paths
                                 .iter()
                                 .map(|p| p.to_string_lossy().to_string())
                                 .collect();
                             let deleted_ids: Vec<String> = existing_files
-                                .keys()
-                                .filter(|k| !disk_paths.contains(k.as_str()))
-                                .cloned()
+                                .iter()
+                                .filter(|(k, state)| !state.missing && !disk_paths.contains(k.as_str()))
+                                .map(|(k, _)| k.clone())
                                 .collect();
 
+                            // Songs whose file is back (e.g. a drive plugged in again) are restored
+                            let restored = match db_state2.write() {
+                                Ok(mut conn) => {
+                                    if !backfilled.is_empty() {
+                                        let _ = db::songs::set_content_hashes(&mut conn, &backfilled);
+                                    }
+                                    db::songs::restore_present_songs(&conn).unwrap_or(0)
+                                }
+                                Err(_) => 0,
+                            };
+
                             if new_or_changed.is_empty() && deleted_ids.is_empty() {
+                                if restored > 0 {
+                                    let _ = app_clone.emit("library-updated", ());
+                                }
                                 return; // No changes, skip
                             }
 
@@ -394,24 +715,35 @@ pub fn run() {
                             let song_inputs: Vec<db::SongInput> = new_or_changed
                                 .par_iter()
                                 .filter_map(|path| {
-                                    match utils::audio::read_metadata_with_mtime(path) {
+                                    match utils::audio::read_metadata_with_mtime(path, &templates) {
                                         Ok(song) => {
                                             if min_dur > 0.0 && song.duration < min_dur {
                                                 return None;
                                             }
-                                            // Extract and cache cover
-                                            let cover_hash = utils::cover::extract_and_cache_cover(path, &cover_cache).ok().flatten();
+                                            // Extract and cache embedded pictures (main cover + typed extras)
+                                            let covers = utils::cover::extract_and_cache_covers(path, &cover_cache).unwrap_or_default();
+                                            let content_hash = known_hashes
+                                                .get(path)
+                                                .cloned()
+                                                .or_else(|| utils::fingerprint::partial_content_hash(path).ok());
                                             Some(db::SongInput {
                                                 id: song.id,
                                                 title: song.title,
                                                 artist: song.artist,
                                                 album: song.album,
+                                                album_artist: song.album_artist,
+                                                composer: song.composer,
+                                                year: song.year,
+                                                genre: song.genre,
+                                                track_no: song.track_no,
+                                                disc_no: song.disc_no,
                                                 duration: song.duration,
                                                 file_path: song.file_path,
                                                 file_size: song.file_size as i64,
                                                 is_hr: song.is_hr,
                                                 is_sq: song.is_sq,
-                                                cover_hash,
+                                                cover_hash: covers.cover_hash,
+                                                content_hash,
                                                 server_song_id: None,
                                                 stream_info: None,
                                                 file_modified: Some(song.file_modified),
@@ -420,27 +752,35 @@ pub fn run() {
                                                 sample_rate: song.sample_rate,
                                                 bitrate: song.bitrate,
                                                 channels: song.channels,
+                                                replay_gain: song.replay_gain,
+                                                replay_peak: song.replay_peak,
+                                                album_gain: song.album_gain,
+                                                album_peak: song.album_peak,
+                                                pictures: covers.pictures,
+                                                cue: None,
+                                                chapters: utils::chapters::read(path),
                                             })
                                         }
                                         Err(_) => None,
                                     }
                                 })
+                                .flat_map_iter(utils::cuesheet::expand)
                                 .collect();
 
                             // Write to DB
                             {
-                                let mut conn = match db_state2.0.lock() {
+                                let mut conn = match db_state2.write() {
                                     Ok(c) => c,
                                     Err(_) => return,
                                 };
+                                // Moved/renamed files keep their rows instead of being removed and re-added
+                                let _ = db::songs::relocate_moved_songs(&mut conn, &song_inputs, &deleted_ids);
                                 // Save new/changed songs
                                 if !song_inputs.is_empty() {
                                     let _ = db::songs::save_songs(&mut conn, &song_inputs, "local", None);
                                 }
-                                // Delete removed files
-                                for id in &deleted_ids {
-                                    let _ = conn.execute("DELETE FROM songs WHERE file_path = ?1 AND source_type = 'local'", [id]);
-                                }
+                                // Mark songs of removed files as missing
+                                let _ = db::songs::mark_files_missing(&conn, &deleted_ids);
                             }
 
                             // Emit library-updated event
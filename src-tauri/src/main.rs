@@ -2,5 +2,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `--scan`: scan the library and exit without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = bayin_lib::run_cli(&args) {
+        std::process::exit(code);
+    }
     bayin_lib::run()
 }
@@ -59,6 +59,8 @@ pub struct ScanResult {
     pub added: usize,
     /// Existing songs updated
     pub updated: usize,
+    /// Songs whose file was moved or renamed, recognized by content
+    pub moved: usize,
     /// Songs removed (file no longer exists)
     pub removed: usize,
     /// Files skipped (unchanged)
@@ -67,6 +69,31 @@ pub struct ScanResult {
     pub errors: usize,
     /// Time taken in milliseconds
     pub duration_ms: u64,
+    /// Directories skipped to avoid symlink cycles
+    pub skipped_cycles: Vec<String>,
+    /// File paths per change, only for dry runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<ScanPreview>,
+}
+
+/// The most recent (non dry-run) scan, kept in memory for diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastScan {
+    /// local / stream
+    pub kind: String,
+    /// Unix timestamp (seconds)
+    pub finished_at: i64,
+    pub result: ScanResult,
+}
+
+/// What a dry-run scan would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanPreview {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
 }
 
 /// Scan options for local directories
@@ -84,9 +111,16 @@ pub struct LocalScanOptions {
     /// Batch size for database writes
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
+    /// Directories where incremental scans compare size + partial content hash
+    /// instead of mtime (for NAS/SMB mounts with unreliable timestamps)
+    #[serde(default)]
+    pub hash_check_directories: Vec<String>,
+    /// Report what would change without writing to the database
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
-fn default_batch_size() -> usize {
+pub(crate) fn default_batch_size() -> usize {
     500
 }
 
@@ -105,6 +139,12 @@ pub struct ScannedSongWithMtime {
     pub title: String,
     pub artist: String,
     pub album: String,
+    pub album_artist: Option<String>,
+    pub composer: Option<String>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    pub track_no: Option<u32>,
+    pub disc_no: Option<u32>,
     pub duration: f64,
     pub file_path: String,
     pub file_size: u64,
@@ -115,5 +155,9 @@ pub struct ScannedSongWithMtime {
     pub sample_rate: Option<u32>,
     pub bitrate: Option<u32>,
     pub channels: Option<u8>,
+    pub replay_gain: Option<f32>,
+    pub replay_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
     pub file_modified: i64,
 }
@@ -8,6 +8,18 @@ pub struct ScannedSong {
     pub title: String,
     pub artist: String,
     pub album: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub composer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_no: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc_no: Option<u32>,
     pub duration: f64,
     pub file_path: String,
     pub file_size: u64,
@@ -27,6 +39,18 @@ pub struct ScannedSong {
     pub bitrate: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub channels: Option<u8>,
+    /// ReplayGain 音轨增益（dB），来自标签或服务器
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain: Option<f32>,
+    /// ReplayGain 音轨峰值（线性，1.0 为满幅）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_peak: Option<f32>,
+    /// ReplayGain 专辑增益（dB）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_gain: Option<f32>,
+    /// ReplayGain 专辑峰值（线性）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_peak: Option<f32>,
 }
 
 /// 扫描选项
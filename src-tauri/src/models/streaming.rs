@@ -3,6 +3,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::error::{CommandError, ErrorCode};
+
 /// 服务器类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -53,6 +55,60 @@ pub struct ConnectionTestResult {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub server_version: Option<String>,
+    /// 失败时的错误码，前端据此本地化提示
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+}
+
+impl ConnectionTestResult {
+    pub fn ok(message: impl Into<String>, server_version: Option<String>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            server_version,
+            code: None,
+        }
+    }
+
+    pub fn failed(err: CommandError) -> Self {
+        Self {
+            success: false,
+            message: err.to_string(),
+            server_version: None,
+            code: Some(err.code),
+        }
+    }
+}
+
+/// 聚合搜索中的一首歌（多个来源的同一首歌合并为一条）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSearchHit {
+    /// 曲库中的歌曲 ID；仅存在于服务器上的歌曲为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub song_id: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: f64,
+    /// 未入库时用于直接播放
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_song_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    /// 返回该歌曲的来源（"本地" 或服务器名称）
+    pub sources: Vec<String>,
+}
+
+/// 聚合搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSearchResult {
+    pub hits: Vec<SourceSearchHit>,
+    /// 超时或出错而没有结果的服务器
+    pub failed_sources: Vec<String>,
 }
 
 // ============ Subsonic API 模型 ============
@@ -138,6 +194,37 @@ pub struct SubsonicSong {
     pub bit_depth: Option<u8>,
     #[serde(default)]
     pub path: Option<String>,
+    #[serde(default)]
+    pub year: Option<u32>,
+    /// OpenSubsonic extension
+    #[serde(default)]
+    pub display_album_artist: Option<String>,
+    /// OpenSubsonic extension
+    #[serde(default)]
+    pub display_composer: Option<String>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    #[serde(default)]
+    pub track: Option<u32>,
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    /// OpenSubsonic extension
+    #[serde(default)]
+    pub replay_gain: Option<SubsonicReplayGain>,
+}
+
+/// OpenSubsonic ReplayGain 信息（增益 dB，峰值线性）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsonicReplayGain {
+    #[serde(default)]
+    pub track_gain: Option<f32>,
+    #[serde(default)]
+    pub album_gain: Option<f32>,
+    #[serde(default)]
+    pub track_peak: Option<f32>,
+    #[serde(default)]
+    pub album_peak: Option<f32>,
 }
 
 /// 获取专辑列表响应
@@ -169,6 +256,42 @@ pub struct SubsonicAlbum {
     pub year: Option<u32>,
 }
 
+/// 相似歌曲响应 (getSimilarSongs2)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSimilarSongs2Response {
+    pub similar_songs2: Option<SimilarSongs>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarSongs {
+    #[serde(default)]
+    pub song: Option<Vec<SubsonicSong>>,
+}
+
+/// 书签响应 (getBookmarks)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBookmarksResponse {
+    pub bookmarks: Option<Bookmarks>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmarks {
+    #[serde(default)]
+    pub bookmark: Option<Vec<SubsonicBookmark>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsonicBookmark {
+    /// 播放位置（毫秒）
+    pub position: i64,
+    pub entry: SubsonicSong,
+}
+
 /// 获取专辑详情响应
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -247,6 +370,19 @@ pub struct JellyfinItem {
     pub album: Option<String>,
     #[serde(default)]
     pub album_artist: Option<String>,
+    /// Requested with `Fields=People`; composers have type "Composer"
+    #[serde(default)]
+    pub people: Option<Vec<JellyfinPerson>>,
+    #[serde(default)]
+    pub production_year: Option<u32>,
+    #[serde(default)]
+    pub genres: Option<Vec<String>>,
+    /// Track number
+    #[serde(default)]
+    pub index_number: Option<u32>,
+    /// Disc number
+    #[serde(default)]
+    pub parent_index_number: Option<u32>,
     #[serde(default, rename = "Artists")]
     pub artists: Option<Vec<String>>,
     #[serde(default)]
@@ -261,6 +397,17 @@ pub struct JellyfinItem {
     pub image_tags: Option<std::collections::HashMap<String, String>>,
     #[serde(default)]
     pub media_sources: Option<Vec<JellyfinMediaSource>>,
+    /// Jellyfin 10.9+ 的响度归一化增益（dB）
+    #[serde(default)]
+    pub normalization_gain: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPerson {
+    pub name: String,
+    #[serde(default, rename = "Type")]
+    pub kind: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -0,0 +1,184 @@
+//! System sleep / wake notifications for desktop platforms.
+//! Windows uses the power-management callback, Linux logind's `PrepareForSleep`
+//! signal; elsewhere (or without logind) a jump of the wall clock past the
+//! monotonic clock reveals that the machine was asleep.
+
+#[cfg(desktop)]
+pub mod desktop {
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PowerEvent {
+        /// The system is about to sleep (or, when only detected afterwards, has slept)
+        Suspend,
+        /// The system woke up
+        Resume,
+    }
+
+    pub type PowerListener = Box<dyn Fn(PowerEvent) + Send + Sync>;
+
+    /// How often the fallback compares the two clocks
+    const WAKE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+    /// Wall-clock time beyond the monotonic clock that counts as a sleep
+    const WAKE_MIN_GAP: Duration = Duration::from_secs(10);
+
+    /// Call `listener` on every sleep and wake from now on.
+    pub fn watch(listener: PowerListener) {
+        #[cfg(target_os = "windows")]
+        {
+            if let Err(listener) = windows::register(listener) {
+                watch_clock(listener);
+            }
+        }
+        #[cfg(target_os = "linux")]
+        {
+            logind::watch(listener);
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+        {
+            watch_clock(listener);
+        }
+    }
+
+    /// Detect a sleep after the fact: the monotonic clock stands still while
+    /// suspended, the wall clock doesn't. Reports `Suspend` then `Resume` on wake.
+    fn watch_clock(listener: PowerListener) {
+        let spawned = thread::Builder::new().name("power-watch".into()).spawn(move || {
+            let mut last_instant = Instant::now();
+            let mut last_wall = SystemTime::now();
+            loop {
+                thread::sleep(WAKE_POLL_INTERVAL);
+                let (instant, wall) = (Instant::now(), SystemTime::now());
+                let elapsed = instant.duration_since(last_instant);
+                // Manual clock changes look the same; they only cost a pause
+                if let Ok(wall_elapsed) = wall.duration_since(last_wall) {
+                    if wall_elapsed > elapsed + WAKE_MIN_GAP {
+                        listener(PowerEvent::Suspend);
+                        listener(PowerEvent::Resume);
+                    }
+                }
+                last_instant = instant;
+                last_wall = wall;
+            }
+        });
+        if let Err(e) = spawned {
+            eprintln!("Failed to start power watcher: {}", e);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    mod logind {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+        use std::thread;
+
+        use super::{watch_clock, PowerEvent, PowerListener};
+
+        const MATCH_RULE: &str =
+            "type='signal',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+        /// Follow `PrepareForSleep` through `dbus-monitor`; its argument is true
+        /// before sleeping and false after waking.
+        pub fn watch(listener: PowerListener) {
+            let child = Command::new("dbus-monitor")
+                .args(["--system", MATCH_RULE])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    eprintln!("dbus-monitor unavailable ({}), detecting sleep from the clock", e);
+                    watch_clock(listener);
+                    return;
+                }
+            };
+            let Some(stdout) = child.stdout.take() else {
+                watch_clock(listener);
+                return;
+            };
+
+            let spawned = thread::Builder::new().name("power-watch".into()).spawn(move || {
+                let mut in_signal = false;
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    let line = line.trim();
+                    if line.starts_with("signal ") {
+                        in_signal = line.contains("member=PrepareForSleep");
+                    } else if in_signal && line.starts_with("boolean ") {
+                        in_signal = false;
+                        listener(if line.ends_with("true") { PowerEvent::Suspend } else { PowerEvent::Resume });
+                    }
+                }
+                // System bus gone; nothing to report any more
+                let _ = child.wait();
+            });
+            if let Err(e) = spawned {
+                eprintln!("Failed to start power watcher: {}", e);
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    mod windows {
+        use std::ffi::c_void;
+
+        use super::{PowerEvent, PowerListener};
+
+        const DEVICE_NOTIFY_CALLBACK: u32 = 2;
+        const PBT_APMSUSPEND: u32 = 0x4;
+        const PBT_APMRESUMEAUTOMATIC: u32 = 0x12;
+
+        type DeviceNotifyCallback = unsafe extern "system" fn(*mut c_void, u32, *mut c_void) -> u32;
+
+        #[repr(C)]
+        struct DeviceNotifySubscribeParameters {
+            callback: DeviceNotifyCallback,
+            context: *mut c_void,
+        }
+
+        #[link(name = "powrprof")]
+        extern "system" {
+            fn PowerRegisterSuspendResumeNotification(
+                flags: u32,
+                recipient: *mut c_void,
+                registration: *mut *mut c_void,
+            ) -> u32;
+        }
+
+        unsafe extern "system" fn on_power_event(context: *mut c_void, kind: u32, _setting: *mut c_void) -> u32 {
+            let listener = &*(context as *const PowerListener);
+            match kind {
+                PBT_APMSUSPEND => listener(PowerEvent::Suspend),
+                // Sent on every wake, with or without user input
+                PBT_APMRESUMEAUTOMATIC => listener(PowerEvent::Resume),
+                _ => {}
+            }
+            0
+        }
+
+        /// Register for suspend / resume callbacks for the rest of the process.
+        /// Hands the listener back when registration fails.
+        pub fn register(listener: PowerListener) -> Result<(), PowerListener> {
+            let context = Box::into_raw(Box::new(listener));
+            // The system keeps using the parameters, so they live as long as the process
+            let params = Box::into_raw(Box::new(DeviceNotifySubscribeParameters {
+                callback: on_power_event,
+                context: context as *mut c_void,
+            }));
+            let mut registration = std::ptr::null_mut();
+            let status = unsafe {
+                PowerRegisterSuspendResumeNotification(DEVICE_NOTIFY_CALLBACK, params as *mut c_void, &mut registration)
+            };
+            if status != 0 {
+                eprintln!("Failed to register for power notifications: error {}", status);
+                unsafe {
+                    drop(Box::from_raw(params));
+                    return Err(*Box::from_raw(context));
+                }
+            }
+            Ok(())
+        }
+    }
+}
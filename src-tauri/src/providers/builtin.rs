@@ -0,0 +1,94 @@
+//! Built-in lyric sources: QQ Music, Kugou and NetEase
+
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+
+use super::{Provider, ProviderInfo, ProviderKind};
+use crate::commands::online_lyrics::{
+    fetch_kugou_lyric, fetch_netease_lyric, fetch_qq_lyric, search_kugou, search_netease, search_qq,
+    OnlineLyricCandidate, OnlineLyricFetchRequest, OnlineLyricFetchResult, OnlineLyricSearchRequest,
+};
+use crate::error::CommandError;
+
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    Qq,
+    Kugou,
+    Netease,
+}
+
+struct BuiltinLyricProvider {
+    source: Source,
+    info: ProviderInfo,
+    client: Client,
+}
+
+/// All built-in providers, in default priority order
+pub fn providers() -> Vec<Arc<dyn Provider>> {
+    let client = Client::new();
+    [
+        (Source::Qq, "qq", "QQ 音乐"),
+        (Source::Kugou, "kugou", "酷狗"),
+        (Source::Netease, "netease", "网易云"),
+    ]
+    .into_iter()
+    .map(|(source, id, name)| {
+        Arc::new(BuiltinLyricProvider {
+            source,
+            info: ProviderInfo {
+                id: id.to_string(),
+                name: name.to_string(),
+                kinds: vec![ProviderKind::Lyrics],
+                builtin: true,
+                path: None,
+            },
+            client: client.clone(),
+        }) as Arc<dyn Provider>
+    })
+    .collect()
+}
+
+impl Provider for BuiltinLyricProvider {
+    fn info(&self) -> &ProviderInfo {
+        &self.info
+    }
+
+    fn search_lyrics<'a>(
+        &'a self,
+        request: &'a OnlineLyricSearchRequest,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<OnlineLyricCandidate>, CommandError>> {
+        Box::pin(async move {
+            match self.source {
+                Source::Qq => search_qq(&self.client, request, query, limit).await,
+                Source::Kugou => search_kugou(&self.client, request, query, limit).await,
+                Source::Netease => search_netease(&self.client, request, query, limit).await,
+            }
+        })
+    }
+
+    fn fetch_lyric<'a>(
+        &'a self,
+        request: &'a OnlineLyricFetchRequest,
+    ) -> BoxFuture<'a, Result<Option<OnlineLyricFetchResult>, CommandError>> {
+        Box::pin(async move {
+            match self.source {
+                Source::Qq => match request.qq_song_id {
+                    Some(song_id) => fetch_qq_lyric(&self.client, song_id).await,
+                    None => Ok(None),
+                },
+                Source::Kugou => match request.kugou_song_hash.as_deref() {
+                    Some(song_hash) => fetch_kugou_lyric(&self.client, song_hash).await,
+                    None => Ok(None),
+                },
+                Source::Netease => match request.netease_song_id.as_deref() {
+                    Some(song_id) => fetch_netease_lyric(&self.client, song_id).await,
+                    None => Ok(None),
+                },
+            }
+        })
+    }
+}
@@ -0,0 +1,210 @@
+//! Lyric / cover / metadata providers
+//!
+//! Every source implements [`Provider`] and is registered in the
+//! [`ProviderRegistry`]. The built-in lyric sources (QQ, Kugou, NetEase) are
+//! always present; user scripts in `<app data>/providers/*.rhai` are loaded at
+//! startup and on `reload_providers`, so new sources can be added without
+//! recompiling the app.
+
+pub mod builtin;
+pub mod script;
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::online_lyrics::{
+    OnlineLyricCandidate, OnlineLyricFetchRequest, OnlineLyricFetchResult, OnlineLyricSearchRequest,
+};
+use crate::error::{CommandError, ErrorCode};
+
+/// What a provider can look up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProviderKind {
+    Lyrics,
+    Cover,
+    Metadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderInfo {
+    /// Used as `source` in lyric candidates; scripts get a `plugin:` prefix
+    pub id: String,
+    pub name: String,
+    pub kinds: Vec<ProviderKind>,
+    pub builtin: bool,
+    /// Script file the provider was loaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Song to find a cover or metadata for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataQuery {
+    pub title: String,
+    pub artist: String,
+    #[serde(default)]
+    pub album: Option<String>,
+    /// Seconds
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// Tags found by a metadata provider; missing fields are left unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+}
+
+/// A lyric / cover / metadata source.
+///
+/// Only the methods matching [`ProviderInfo::kinds`] are called; the
+/// defaults report the operation as unsupported.
+pub trait Provider: Send + Sync {
+    fn info(&self) -> &ProviderInfo;
+
+    fn search_lyrics<'a>(
+        &'a self,
+        _request: &'a OnlineLyricSearchRequest,
+        _query: &'a str,
+        _limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<OnlineLyricCandidate>, CommandError>> {
+        Box::pin(async move { Err(self.unsupported()) })
+    }
+
+    fn fetch_lyric<'a>(
+        &'a self,
+        _request: &'a OnlineLyricFetchRequest,
+    ) -> BoxFuture<'a, Result<Option<OnlineLyricFetchResult>, CommandError>> {
+        Box::pin(async move { Err(self.unsupported()) })
+    }
+
+    /// Cover image URL
+    fn find_cover<'a>(&'a self, _query: &'a MetadataQuery) -> BoxFuture<'a, Result<Option<String>, CommandError>> {
+        Box::pin(async move { Err(self.unsupported()) })
+    }
+
+    fn find_metadata<'a>(
+        &'a self,
+        _query: &'a MetadataQuery,
+    ) -> BoxFuture<'a, Result<Option<ProviderMetadata>, CommandError>> {
+        Box::pin(async move { Err(self.unsupported()) })
+    }
+
+    fn supports(&self, kind: ProviderKind) -> bool {
+        self.info().kinds.contains(&kind)
+    }
+
+    fn unsupported(&self) -> CommandError {
+        CommandError::new(ErrorCode::UnsupportedSource).with("source", &self.info().id)
+    }
+}
+
+/// A script that failed to load
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderLoadError {
+    pub path: String,
+    pub message: String,
+}
+
+pub struct ProviderRegistry {
+    providers: RwLock<Vec<Arc<dyn Provider>>>,
+    load_errors: RwLock<Vec<ProviderLoadError>>,
+    script_dir: PathBuf,
+}
+
+pub struct ProviderRegistryState(pub Arc<ProviderRegistry>);
+
+impl ProviderRegistry {
+    /// Built-in providers plus the scripts in `script_dir`
+    pub fn new(script_dir: PathBuf) -> Arc<Self> {
+        let registry = Arc::new(Self {
+            providers: RwLock::new(Vec::new()),
+            load_errors: RwLock::new(Vec::new()),
+            script_dir,
+        });
+        registry.reload();
+        registry
+    }
+
+    /// Re-read the script directory; built-ins always come first
+    pub fn reload(&self) {
+        let mut providers = builtin::providers();
+        let (scripts, errors) = script::load_dir(&self.script_dir);
+        let mut errors = errors;
+        for script in scripts {
+            if providers.iter().any(|p| p.info().id == script.info().id) {
+                errors.push(ProviderLoadError {
+                    path: script.info().path.clone().unwrap_or_default(),
+                    message: format!("来源 ID 重复: {}", script.info().id),
+                });
+                continue;
+            }
+            providers.push(script);
+        }
+
+        for error in &errors {
+            eprintln!("[providers] {}: {}", error.path, error.message);
+        }
+        if let Ok(mut current) = self.providers.write() {
+            *current = providers;
+        }
+        if let Ok(mut current) = self.load_errors.write() {
+            *current = errors;
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn Provider>> {
+        self.providers
+            .read()
+            .ok()?
+            .iter()
+            .find(|p| p.info().id == id)
+            .cloned()
+    }
+
+    /// Providers of one kind in registration order
+    pub fn with_kind(&self, kind: ProviderKind) -> Vec<Arc<dyn Provider>> {
+        self.providers
+            .read()
+            .map(|providers| providers.iter().filter(|p| p.supports(kind)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn list(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .read()
+            .map(|providers| providers.iter().map(|p| p.info().clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn load_errors(&self) -> Vec<ProviderLoadError> {
+        self.load_errors.read().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    pub fn script_dir(&self) -> &PathBuf {
+        &self.script_dir
+    }
+}
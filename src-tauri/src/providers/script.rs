@@ -0,0 +1,352 @@
+//! User-supplied providers written in Rhai
+//!
+//! Each `*.rhai` file in the provider directory is one provider. The script
+//! must define `info()` returning `#{ id, name, kinds }` (`kinds` is a list
+//! of `"lyrics"`, `"cover"`, `"metadata"`) and the functions for every kind
+//! it declares:
+//!
+//! - lyrics: `search_lyrics(query)` returning an array of
+//!   `#{ id, title, artist, album, durationMs, coverUrl }`, and
+//!   `fetch_lyric(id)` returning LRC text, `#{ lyric, translation }` or `()`
+//! - cover: `find_cover(query)` returning an image URL or `()`
+//! - metadata: `find_metadata(query)` returning a map with any of `title`,
+//!   `artist`, `album`, `albumArtist`, `year`, `genre`, `trackNumber`,
+//!   `coverUrl`, or `()`
+//!
+//! `query` is `#{ title, artist, album, duration, keyword, limit }`. Scripts
+//! can call `http_get(url[, headers])`, `http_post(url, body[, headers])`,
+//! `json_parse(text)`, `json_stringify(value)` and `url_encode(text)`.
+//! Scripts run on a blocking thread with operation and size limits.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{MetadataQuery, Provider, ProviderInfo, ProviderKind, ProviderLoadError, ProviderMetadata};
+use crate::commands::online_lyrics::{
+    compute_score, OnlineLyricCandidate, OnlineLyricFetchRequest, OnlineLyricFetchResult, OnlineLyricSearchRequest,
+};
+use crate::error::{CommandError, ErrorCode};
+
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Prefix of script provider IDs, keeps them apart from built-in sources
+pub const ID_PREFIX: &str = "plugin:";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bound on work per call, so a broken loop can't hang a lookup
+const MAX_OPERATIONS: u64 = 5_000_000;
+const MAX_STRING_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct ScriptInfo {
+    id: String,
+    name: String,
+    kinds: Vec<ProviderKind>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptCandidate {
+    id: Value,
+    title: String,
+    #[serde(default)]
+    artist: String,
+    #[serde(default)]
+    album: String,
+    #[serde(default)]
+    duration_ms: Option<i64>,
+    #[serde(default)]
+    cover_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ScriptLyric {
+    Text(String),
+    Full {
+        lyric: String,
+        #[serde(default)]
+        translation: Option<String>,
+    },
+}
+
+/// Compiled script with its own engine
+struct Runtime {
+    engine: Engine,
+    ast: AST,
+}
+
+pub struct ScriptProvider {
+    info: ProviderInfo,
+    runtime: Arc<Runtime>,
+}
+
+/// Load every script in `dir`; a missing directory just means no plugins
+pub fn load_dir(dir: &Path) -> (Vec<Arc<dyn Provider>>, Vec<ProviderLoadError>) {
+    let mut providers: Vec<Arc<dyn Provider>> = Vec::new();
+    let mut errors = Vec::new();
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (providers, errors);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match ScriptProvider::load(&path) {
+            Ok(provider) => providers.push(Arc::new(provider)),
+            Err(message) => errors.push(ProviderLoadError {
+                path: path.to_string_lossy().to_string(),
+                message,
+            }),
+        }
+    }
+
+    (providers, errors)
+}
+
+impl ScriptProvider {
+    fn load(path: &Path) -> Result<Self, String> {
+        let engine = create_engine();
+        let ast = engine.compile_file(path.to_path_buf()).map_err(|e| e.to_string())?;
+        let runtime = Runtime { engine, ast };
+
+        let info: ScriptInfo = runtime.call("info", ()).map_err(|e| format!("info() 失败: {}", e))?;
+        if info.id.is_empty()
+            || info.id.len() > 32
+            || !info.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        {
+            return Err(format!("无效的来源 ID: {}", info.id));
+        }
+        for kind in &info.kinds {
+            let required: &[&str] = match kind {
+                ProviderKind::Lyrics => &["search_lyrics", "fetch_lyric"],
+                ProviderKind::Cover => &["find_cover"],
+                ProviderKind::Metadata => &["find_metadata"],
+            };
+            if let Some(missing) = required.iter().find(|name| !runtime.has_fn(name)) {
+                return Err(format!("缺少函数 {}()", missing));
+            }
+        }
+
+        Ok(Self {
+            info: ProviderInfo {
+                id: format!("{}{}", ID_PREFIX, info.id),
+                name: info.name,
+                kinds: info.kinds,
+                builtin: false,
+                path: Some(path.to_string_lossy().to_string()),
+            },
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Run a script function on a blocking thread
+    async fn run<T, F>(&self, f: F) -> Result<T, CommandError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Runtime) -> Result<T, String> + Send + 'static,
+    {
+        let runtime = Arc::clone(&self.runtime);
+        let result = tokio::task::spawn_blocking(move || f(&runtime))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+        result.map_err(|details| {
+            CommandError::new(ErrorCode::ProviderFailed)
+                .with("provider", &self.info.id)
+                .with("details", details)
+        })
+    }
+}
+
+impl Runtime {
+    fn has_fn(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn call<T: DeserializeOwned>(&self, name: &str, args: impl rhai::FuncArgs) -> Result<T, String> {
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| e.to_string())?;
+        from_dynamic(&result).map_err(|e| format!("{}() 返回值格式错误: {}", name, e))
+    }
+}
+
+impl Provider for ScriptProvider {
+    fn info(&self) -> &ProviderInfo {
+        &self.info
+    }
+
+    fn search_lyrics<'a>(
+        &'a self,
+        request: &'a OnlineLyricSearchRequest,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<OnlineLyricCandidate>, CommandError>> {
+        Box::pin(async move {
+            let arg = json!({
+                "title": request.title,
+                "artist": request.artist,
+                "album": request.album,
+                "duration": request.duration,
+                "keyword": query,
+                "limit": limit,
+            });
+            let found: Vec<ScriptCandidate> = self
+                .run(move |rt| rt.call("search_lyrics", (to_script(&arg)?,)))
+                .await?;
+
+            Ok(found
+                .into_iter()
+                .take(limit)
+                .map(|c| OnlineLyricCandidate {
+                    source: self.info.id.clone(),
+                    score: compute_score(request, &c.title, &c.artist, &c.album),
+                    title: c.title,
+                    artists: c.artist,
+                    album: c.album,
+                    duration_ms: c.duration_ms,
+                    qq_song_id: None,
+                    netease_song_id: None,
+                    kugou_song_hash: None,
+                    provider_song_id: Some(match c.id {
+                        Value::String(id) => id,
+                        other => other.to_string(),
+                    }),
+                    cover_url: c.cover_url,
+                })
+                .collect())
+        })
+    }
+
+    fn fetch_lyric<'a>(
+        &'a self,
+        request: &'a OnlineLyricFetchRequest,
+    ) -> BoxFuture<'a, Result<Option<OnlineLyricFetchResult>, CommandError>> {
+        Box::pin(async move {
+            let Some(id) = request.provider_song_id.clone() else {
+                return Ok(None);
+            };
+            let lyric: Option<ScriptLyric> = self.run(move |rt| rt.call("fetch_lyric", (id,))).await?;
+            let (lyric, translation) = match lyric {
+                Some(ScriptLyric::Text(lyric)) => (lyric, None),
+                Some(ScriptLyric::Full { lyric, translation }) => (lyric, translation),
+                None => return Ok(None),
+            };
+            if lyric.trim().is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(OnlineLyricFetchResult::new(
+                &self.info.id,
+                "lrc",
+                lyric.clone(),
+                translation.as_deref().filter(|t| !t.trim().is_empty()),
+                Some(lyric),
+            )))
+        })
+    }
+
+    fn find_cover<'a>(&'a self, query: &'a MetadataQuery) -> BoxFuture<'a, Result<Option<String>, CommandError>> {
+        Box::pin(async move {
+            let arg = metadata_arg(query);
+            let url: Option<String> = self.run(move |rt| rt.call("find_cover", (to_script(&arg)?,))).await?;
+            Ok(url.filter(|u| !u.is_empty()))
+        })
+    }
+
+    fn find_metadata<'a>(
+        &'a self,
+        query: &'a MetadataQuery,
+    ) -> BoxFuture<'a, Result<Option<ProviderMetadata>, CommandError>> {
+        Box::pin(async move {
+            let arg = metadata_arg(query);
+            self.run(move |rt| rt.call("find_metadata", (to_script(&arg)?,))).await
+        })
+    }
+}
+
+fn metadata_arg(query: &MetadataQuery) -> Value {
+    json!({
+        "title": query.title,
+        "artist": query.artist,
+        "album": query.album,
+        "duration": query.duration,
+        "keyword": format!("{} {}", query.title, query.artist).trim(),
+        "limit": 1,
+    })
+}
+
+fn to_script(value: &Value) -> Result<Dynamic, String> {
+    to_dynamic(value).map_err(|e| e.to_string())
+}
+
+/// Shared blocking HTTP client, created on the first script request
+/// (building it inside the async runtime would panic)
+fn http_client() -> &'static reqwest::blocking::Client {
+    static CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::blocking::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+fn send(request: reqwest::blocking::RequestBuilder, headers: &Map) -> Result<String, Box<EvalAltResult>> {
+    let request = headers
+        .iter()
+        .fold(request, |req, (k, v)| req.header(k.as_str(), v.to_string()));
+    let response = request.send().map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    Ok(response.text().map_err(|e| e.to_string())?)
+}
+
+fn create_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.on_print(|text| eprintln!("[providers][script] {}", text));
+    engine.on_debug(|text, _, _| eprintln!("[providers][script] {}", text));
+
+    engine.register_fn("http_get", |url: &str| send(http_client().get(url), &Map::new()));
+    engine.register_fn("http_get", |url: &str, headers: Map| send(http_client().get(url), &headers));
+    engine.register_fn("http_post", |url: &str, body: &str| {
+        send(http_client().post(url).body(body.to_string()), &Map::new())
+    });
+    engine.register_fn("http_post", |url: &str, body: &str, headers: Map| {
+        send(http_client().post(url).body(body.to_string()), &headers)
+    });
+    engine.register_fn("json_parse", |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+        let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        to_dynamic(&value)
+    });
+    engine.register_fn("json_stringify", |value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+        let value: Value = from_dynamic(&value)?;
+        Ok(value.to_string())
+    });
+    engine.register_fn("url_encode", |text: &str| utf8_percent_encode(text, NON_ALPHANUMERIC).to_string());
+
+    engine
+}
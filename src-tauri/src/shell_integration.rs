@@ -0,0 +1,274 @@
+//! "Add to BaYin queue" file manager integration
+//!
+//! Installs a context-menu entry (Windows) or an "Open With" desktop action
+//! (Linux) that launches `bayin --enqueue <paths>`. The single-instance plugin
+//! hands those arguments to the running instance, which reads the files' tags,
+//! saves them as local songs and asks the frontend to append them to the queue.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// Arguments after this flag are files or folders to append to the queue
+pub const ENQUEUE_ARG: &str = "--enqueue";
+
+/// Song IDs waiting for the frontend to append to the queue
+#[derive(Default)]
+pub struct PendingQueueState(pub Mutex<Vec<String>>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellIntegrationStatus {
+    /// Whether this OS has an integration we can install
+    pub supported: bool,
+    pub installed: bool,
+}
+
+#[cfg(desktop)]
+pub mod desktop {
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+
+    use tauri::{AppHandle, Emitter, Manager};
+
+    use super::{PendingQueueState, ShellIntegrationStatus, ENQUEUE_ARG};
+    use crate::db::{self, DbState};
+    use crate::utils::audio;
+    use crate::utils::walk::collect_audio_files;
+    use crate::watcher::desktop::read_song_inputs;
+
+    /// Menu entry label
+    const MENU_LABEL: &str = "添加到 BaYin 队列";
+
+    /// Handle `--enqueue` from our own command line or a second instance's
+    pub fn handle_args(app: &AppHandle, argv: &[String], cwd: &Path) {
+        let Some(index) = argv.iter().position(|arg| arg == ENQUEUE_ARG) else {
+            return;
+        };
+        let paths: Vec<PathBuf> = argv[index + 1..]
+            .iter()
+            .filter(|arg| !arg.starts_with("--"))
+            .map(|arg| cwd.join(arg))
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+
+        // Reading tags can take a while for whole folders
+        let app = app.clone();
+        std::thread::spawn(move || enqueue_paths(&app, &paths));
+    }
+
+    fn enqueue_paths(app: &AppHandle, paths: &[PathBuf]) {
+        let files = expand_paths(paths);
+        if files.is_empty() {
+            return;
+        }
+
+        let file_refs: Vec<&PathBuf> = files.iter().collect();
+        let song_inputs = read_song_inputs(app, &file_refs);
+        if song_inputs.is_empty() {
+            return;
+        }
+
+        {
+            let db_state = app.state::<DbState>();
+            let Ok(mut conn) = db_state.write() else { return };
+            if let Err(e) = db::songs::save_songs(&mut conn, &song_inputs, "local", None) {
+                eprintln!("Failed to save enqueued files: {}", e);
+                return;
+            }
+        }
+
+        let pending = app.state::<PendingQueueState>();
+        if let Ok(mut ids) = pending.0.lock() {
+            ids.extend(song_inputs.into_iter().map(|song| song.id));
+        };
+        let _ = app.emit("queue:enqueue", ());
+    }
+
+    /// Audio files in selection order; folders are expanded recursively and sorted by path
+    fn expand_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let mut collected = collect_audio_files(&[path.to_string_lossy().to_string()]).audio_paths;
+                collected.sort();
+                files.extend(collected);
+            } else if path.is_file() && audio::is_audio_file(path) {
+                files.push(path.clone());
+            }
+        }
+        files.retain(|path| seen.insert(path.clone()));
+        files
+    }
+
+    /// Path the menu entry should launch
+    fn launcher_path() -> Result<PathBuf, String> {
+        // An AppImage runs from a temporary mount; point at the image itself
+        if let Some(appimage) = std::env::var_os("APPIMAGE") {
+            return Ok(PathBuf::from(appimage));
+        }
+        std::env::current_exe().map_err(|e| e.to_string())
+    }
+
+    pub fn status() -> ShellIntegrationStatus {
+        ShellIntegrationStatus {
+            supported: platform::SUPPORTED,
+            installed: platform::SUPPORTED && platform::is_installed(),
+        }
+    }
+
+    pub fn install() -> Result<(), String> {
+        platform::install(&launcher_path()?)
+    }
+
+    pub fn uninstall() -> Result<(), String> {
+        platform::uninstall()
+    }
+
+    #[cfg(windows)]
+    mod platform {
+        use std::os::windows::process::CommandExt;
+        use std::path::Path;
+        use std::process::Command;
+
+        use super::{ENQUEUE_ARG, MENU_LABEL};
+
+        pub const SUPPORTED: bool = true;
+
+        /// Don't flash a console window for each `reg` call
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+        /// Audio files (by perceived type) and folders
+        const KEYS: &[&str] = &[
+            r"HKCU\Software\Classes\SystemFileAssociations\audio\shell\BaYin.Enqueue",
+            r"HKCU\Software\Classes\Directory\shell\BaYin.Enqueue",
+        ];
+
+        fn reg(args: &[&str]) -> Result<bool, String> {
+            Command::new("reg")
+                .args(args)
+                .creation_flags(CREATE_NO_WINDOW)
+                .output()
+                .map(|output| output.status.success())
+                .map_err(|e| format!("无法运行 reg: {}", e))
+        }
+
+        pub fn is_installed() -> bool {
+            KEYS.iter()
+                .all(|key| reg(&["query", &format!(r"{}\command", key)]).unwrap_or(false))
+        }
+
+        pub fn install(exe: &Path) -> Result<(), String> {
+            let exe = exe.to_string_lossy().to_string();
+            let command = format!("\"{}\" {} \"%1\"", exe, ENQUEUE_ARG);
+            for &key in KEYS {
+                let command_key = format!(r"{}\command", key);
+                let steps: [&[&str]; 3] = [
+                    &["add", key, "/ve", "/d", MENU_LABEL, "/f"],
+                    &["add", key, "/v", "Icon", "/d", exe.as_str(), "/f"],
+                    &["add", command_key.as_str(), "/ve", "/d", command.as_str(), "/f"],
+                ];
+                for args in steps {
+                    if !reg(args)? {
+                        return Err(format!("写入注册表失败: {}", key));
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        pub fn uninstall() -> Result<(), String> {
+            for key in KEYS {
+                // Missing keys are fine
+                reg(&["delete", key, "/f"])?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    mod platform {
+        use std::path::{Path, PathBuf};
+
+        use super::{ENQUEUE_ARG, MENU_LABEL};
+
+        pub const SUPPORTED: bool = true;
+
+        const DESKTOP_FILE: &str = "bayin-enqueue.desktop";
+
+        const MIME_TYPES: &str = "audio/mpeg;audio/flac;audio/x-flac;audio/ogg;audio/x-vorbis+ogg;audio/wav;\
+            audio/x-wav;audio/aac;audio/mp4;audio/x-m4a;audio/x-aiff;audio/x-ape;audio/x-matroska;inode/directory;";
+
+        fn desktop_file() -> Option<PathBuf> {
+            let data_home = std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+            Some(data_home.join("applications").join(DESKTOP_FILE))
+        }
+
+        pub fn is_installed() -> bool {
+            desktop_file().is_some_and(|path| path.exists())
+        }
+
+        pub fn install(exe: &Path) -> Result<(), String> {
+            let path = desktop_file().ok_or("无法确定用户数据目录")?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let entry = format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name={}\n\
+                 Exec=\"{}\" {} %F\n\
+                 MimeType={}\n\
+                 NoDisplay=true\n\
+                 Terminal=false\n",
+                MENU_LABEL,
+                exe.to_string_lossy(),
+                ENQUEUE_ARG,
+                MIME_TYPES
+            );
+            std::fs::write(&path, entry).map_err(|e| e.to_string())?;
+            // Refresh the "Open With" cache; harmless if the tool is missing
+            if let Some(dir) = path.parent() {
+                let _ = std::process::Command::new("update-desktop-database").arg(dir).status();
+            }
+            Ok(())
+        }
+
+        pub fn uninstall() -> Result<(), String> {
+            match desktop_file() {
+                Some(path) if path.exists() => std::fs::remove_file(path).map_err(|e| e.to_string()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// macOS needs a Services entry in the bundle's Info.plist instead
+    #[cfg(not(any(windows, target_os = "linux")))]
+    mod platform {
+        use std::path::Path;
+
+        pub const SUPPORTED: bool = false;
+
+        pub fn is_installed() -> bool {
+            false
+        }
+
+        pub fn install(_exe: &Path) -> Result<(), String> {
+            Err("当前系统暂不支持右键菜单集成".to_string())
+        }
+
+        pub fn uninstall() -> Result<(), String> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Opt-in anonymous usage statistics
+//!
+//! Disabled until the user turns on the `telemetry` setting. While enabled,
+//! feature usage and non-fatal error codes are counted in the local database;
+//! nothing else (paths, titles, server addresses) is recorded. Counters are
+//! uploaded in batches to the endpoint baked in at build time through
+//! `BAYIN_TELEMETRY_ENDPOINT`; builds without it never send anything.
+//! Turning the setting off drops all pending counters.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::db::telemetry::{TelemetryCounter, EVENT_ERROR, EVENT_FEATURE};
+use crate::db::{self, DbState};
+use crate::error::CommandError;
+
+/// Upload endpoint, set for release builds only
+const ENDPOINT: Option<&str> = option_env!("BAYIN_TELEMETRY_ENDPOINT");
+
+/// Delay before the first upload after startup
+const FIRST_UPLOAD_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Time between uploads
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Longest accepted event name
+const MAX_NAME_LEN: usize = 64;
+
+/// One uploaded batch
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Batch<'a> {
+    app_version: &'a str,
+    os: &'a str,
+    arch: &'a str,
+    events: &'a [TelemetryCounter],
+}
+
+/// What the settings page shows: consent and the counters not yet uploaded
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetrySummary {
+    pub enabled: bool,
+    /// Whether this build has an upload endpoint
+    pub upload_available: bool,
+    pub pending: Vec<TelemetryCounter>,
+}
+
+pub struct Telemetry {
+    app: AppHandle,
+    enabled: AtomicBool,
+}
+
+pub struct TelemetryState(pub Arc<Telemetry>);
+
+impl Telemetry {
+    pub fn new(app: AppHandle) -> Arc<Self> {
+        let enabled = {
+            let db = app.state::<DbState>();
+            let conn = db.write();
+            conn.ok()
+                .and_then(|conn| db::settings::telemetry_settings(&conn).ok())
+                .map(|s| s.enabled)
+                .unwrap_or(false)
+        };
+        Arc::new(Self {
+            app,
+            enabled: AtomicBool::new(enabled),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Apply a consent change; opting out discards everything not yet uploaded
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            let db = self.app.state::<DbState>();
+            if let Ok(conn) = db.write() {
+                if let Err(e) = db::telemetry::clear_counters(&conn) {
+                    eprintln!("Failed to clear telemetry counters: {}", e);
+                }
+            };
+        }
+    }
+
+    /// Count one use of a feature, e.g. `scan.local`
+    pub fn feature(&self, name: &str) {
+        self.record(EVENT_FEATURE, name);
+    }
+
+    /// Count a non-fatal error by its code, e.g. `lyrics.qq.timeout`
+    pub fn error(&self, area: &str, err: &CommandError) {
+        let code = serde_json::to_value(err.code)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        match err.params.get("provider") {
+            Some(provider) => self.record(EVENT_ERROR, &format!("{}.{}.{}", area, provider, code)),
+            None => self.record(EVENT_ERROR, &format!("{}.{}", area, code)),
+        }
+    }
+
+    fn record(&self, kind: &str, name: &str) {
+        if !self.is_enabled() || !valid_name(name) {
+            return;
+        }
+        let db = self.app.state::<DbState>();
+        let Ok(conn) = db.write() else {
+            return;
+        };
+        if let Err(e) = db::telemetry::increment_counter(&conn, kind, name) {
+            eprintln!("Failed to record telemetry event: {}", e);
+        }
+    }
+
+    pub fn summary(&self) -> Result<TelemetrySummary, String> {
+        let db = self.app.state::<DbState>();
+        let conn = db.write().map_err(|e| e.to_string())?;
+        Ok(TelemetrySummary {
+            enabled: self.is_enabled(),
+            upload_available: ENDPOINT.is_some(),
+            pending: db::telemetry::list_counters(&conn).map_err(|e| e.to_string())?,
+        })
+    }
+
+    /// Start the periodic upload; does nothing in builds without an endpoint
+    pub fn spawn(self: &Arc<Self>) {
+        let Some(endpoint) = ENDPOINT else {
+            return;
+        };
+        let telemetry = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(FIRST_UPLOAD_DELAY).await;
+            loop {
+                if telemetry.is_enabled() {
+                    if let Err(e) = telemetry.upload(endpoint).await {
+                        eprintln!("Telemetry upload failed: {}", e);
+                    }
+                }
+                tokio::time::sleep(UPLOAD_INTERVAL).await;
+            }
+        });
+    }
+
+    async fn upload(&self, endpoint: &str) -> Result<(), String> {
+        let pending = {
+            let db = self.app.state::<DbState>();
+            let conn = db.write().map_err(|e| e.to_string())?;
+            db::telemetry::list_counters(&conn).map_err(|e| e.to_string())?
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let batch = Batch {
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            events: &pending,
+        };
+        let response = reqwest::Client::new()
+            .post(endpoint)
+            .timeout(UPLOAD_TIMEOUT)
+            .json(&batch)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+
+        // Consent may have been withdrawn while uploading; counters are gone then anyway
+        let db = self.app.state::<DbState>();
+        let mut conn = db.write().map_err(|e| e.to_string())?;
+        db::telemetry::remove_uploaded(&mut conn, &pending).map_err(|e| e.to_string())
+    }
+}
+
+/// Event names are short identifiers, never free text that could carry user data
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Count a feature use from anywhere with an app handle
+pub fn feature(app: &AppHandle, name: &str) {
+    if let Some(state) = app.try_state::<TelemetryState>() {
+        state.0.feature(name);
+    }
+}
+
+/// Count a non-fatal error from anywhere with an app handle
+pub fn error(app: &AppHandle, area: &str, err: &CommandError) {
+    if let Some(state) = app.try_state::<TelemetryState>() {
+        state.0.error(area, err);
+    }
+}
@@ -6,15 +6,19 @@ use lofty::file::AudioFile;
 use lofty::prelude::*;
 use lofty::probe::Probe;
 
+use super::path_template::PathTemplates;
+use super::sidecar;
+use super::tak;
 use crate::models::{ScannedSong, ScannedSongWithMtime};
 
 /// 支持的音频文件扩展名
 const AUDIO_EXTENSIONS: &[&str] = &[
-    "mp3", "flac", "wav", "aac", "m4a", "ogg", "wma", "ape", "aiff", "dsf", "dff",
+    "mp3", "flac", "wav", "aac", "m4a", "m4b", "ogg", "wma", "ape", "aiff", "dsf", "dff",
+    "mka", "ac3", "dts", "tta", "opus", "wv", "mpc", "tak",
 ];
 
 /// 无损音频格式扩展名
-const LOSSLESS_EXTENSIONS: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff"];
+const LOSSLESS_EXTENSIONS: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff", "tta", "wv", "tak"];
 
 /// 判断文件是否为音频文件
 pub fn is_audio_file(path: &Path) -> bool {
@@ -40,6 +44,108 @@ fn extract_filename(path: &Path) -> String {
         .unwrap_or_else(|| "未知标题".to_string())
 }
 
+/// 解析 ReplayGain 标签值，如 "-6.52 dB" 或 "0.988"
+fn parse_replay_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value)
+        .trim();
+    number.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// R128 增益标签（Opus），Q7.8 定点数，相对 -23 LUFS；换算为 ReplayGain 2.0 的 -18 LUFS 基准
+fn parse_r128_gain(value: &str) -> Option<f32> {
+    value.trim().parse::<i32>().ok().map(|q| q as f32 / 256.0 + 5.0)
+}
+
+/// 标签中的 ReplayGain 音轨/专辑增益（dB）与峰值
+#[derive(Debug, Default, Clone, Copy)]
+struct ReplayGainTags {
+    track_gain: Option<f32>,
+    track_peak: Option<f32>,
+    album_gain: Option<f32>,
+    album_peak: Option<f32>,
+}
+
+/// 读取标签中的 ReplayGain，没有 REPLAYGAIN_* 时使用 R128_* 标签
+fn read_replay_gain(tag: Option<&lofty::tag::Tag>) -> ReplayGainTags {
+    use lofty::tag::ItemKey;
+
+    let value = |key: &ItemKey| tag.and_then(|t| t.get_string(key)).and_then(parse_replay_gain);
+    let r128 = |key: &str| {
+        tag.and_then(|t| t.get_string(&ItemKey::Unknown(key.to_string())))
+            .and_then(parse_r128_gain)
+    };
+    ReplayGainTags {
+        track_gain: value(&ItemKey::ReplayGainTrackGain).or_else(|| r128("R128_TRACK_GAIN")),
+        track_peak: value(&ItemKey::ReplayGainTrackPeak),
+        album_gain: value(&ItemKey::ReplayGainAlbumGain).or_else(|| r128("R128_ALBUM_GAIN")),
+        album_peak: value(&ItemKey::ReplayGainAlbumPeak),
+    }
+}
+
+/// 读取标签中的年份和流派
+fn read_year_and_genre(tag: Option<&lofty::tag::Tag>) -> (Option<u32>, Option<String>) {
+    let year = tag.and_then(|t| t.year()).filter(|y| *y > 0);
+    let genre = tag
+        .and_then(|t| t.genre().map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty());
+
+    (year, genre)
+}
+
+/// 读取标签中的音轨号和碟号
+fn read_track_and_disc(tag: Option<&lofty::tag::Tag>) -> (Option<u32>, Option<u32>) {
+    (
+        tag.and_then(|t| t.track()).filter(|n| *n > 0),
+        tag.and_then(|t| t.disk()).filter(|n| *n > 0),
+    )
+}
+
+/// 解析 "3" 或 "3/12" 形式的音轨号/碟号
+fn parse_position(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse::<u32>().ok().filter(|n| *n > 0)
+}
+
+/// 标签缺失的标题/艺术家/专辑/年份按文件名模板补全，仍缺失时使用默认值
+fn with_path_fallback(
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    year: Option<u32>,
+    path: &Path,
+    templates: &PathTemplates,
+) -> (String, String, String, Option<u32>) {
+    let from_path = if title.is_none() || artist.is_none() || album.is_none() || year.is_none() {
+        templates.parse(path).unwrap_or_default()
+    } else {
+        Default::default()
+    };
+
+    (
+        title.or(from_path.title).unwrap_or_else(|| extract_filename(path)),
+        artist.or(from_path.artist).unwrap_or_else(|| "未知艺术家".to_string()),
+        album.or(from_path.album).unwrap_or_else(|| "未知专辑".to_string()),
+        year.or(from_path.year),
+    )
+}
+
+/// 用 album.nfo / artist.nfo 补全缺失的年份和流派
+fn with_nfo_fallback(mut year: Option<u32>, mut genre: Option<String>, path: &Path) -> (Option<u32>, Option<String>) {
+    if year.is_none() || genre.is_none() {
+        if let Some(nfo) = sidecar::read_album_nfo(path) {
+            year = year.or(nfo.year);
+            if genre.is_none() && !nfo.genres.is_empty() {
+                genre = Some(nfo.genres.join("; "));
+            }
+        }
+    }
+
+    (year, genre)
+}
+
 /// 从路径字符串中提取文件名（不含扩展名），用于流媒体等场景
 pub fn extract_filename_from_path_str(path_str: &str) -> Option<String> {
     if path_str.is_empty() {
@@ -54,13 +160,18 @@ pub fn extract_filename_from_path_str(path_str: &str) -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
-/// 读取歌词（优先从外部 .lrc 文件，其次从音频文件内嵌歌词）
+/// 外部歌词文件扩展名，按优先级排列
+pub const LYRIC_EXTENSIONS: &[&str] = &["lrc", "ttml", "srt", "vtt"];
+
+/// 读取歌词（优先从外部 .lrc/.ttml/.srt 文件，其次从音频文件内嵌歌词），统一转换为 LRC
 pub fn read_lyrics(audio_path: &Path) -> Option<String> {
-    // 1. 尝试读取外部 .lrc 文件
-    let lrc_path = audio_path.with_extension("lrc");
-    if lrc_path.exists() {
-        if let Ok(content) = std::fs::read_to_string(&lrc_path) {
-            return Some(content);
+    // 1. 尝试读取外部歌词文件
+    for ext in LYRIC_EXTENSIONS {
+        let lyric_path = audio_path.with_extension(ext);
+        if lyric_path.exists() {
+            if let Ok(content) = std::fs::read_to_string(&lyric_path) {
+                return Some(super::lyrics::to_lrc(&content));
+            }
         }
     }
 
@@ -70,7 +181,7 @@ pub fn read_lyrics(audio_path: &Path) -> Option<String> {
             // 尝试获取 LYRICS 标签（不同格式可能有不同的标签名）
             // lofty 使用 ItemKey::Lyrics 来获取歌词
             if let Some(lyrics) = tag.get_string(&lofty::tag::ItemKey::Lyrics) {
-                return Some(lyrics.to_string());
+                return Some(super::lyrics::to_lrc(lyrics));
             }
         }
     }
@@ -79,7 +190,7 @@ pub fn read_lyrics(audio_path: &Path) -> Option<String> {
 }
 
 /// 读取音频文件元数据
-pub fn read_metadata(path: &Path) -> Result<ScannedSong, String> {
+pub fn read_metadata(path: &Path, templates: &PathTemplates) -> Result<ScannedSong, String> {
     let file_path_str = path.to_string_lossy().to_string();
 
     // 获取文件大小
@@ -87,11 +198,14 @@ pub fn read_metadata(path: &Path) -> Result<ScannedSong, String> {
         .map_err(|e| format!("无法获取文件信息: {}", e))?
         .len();
 
-    // 使用 lofty 读取音频文件
-    let tagged_file = Probe::open(path)
-        .map_err(|e| format!("无法打开文件: {}", e))?
-        .read()
-        .map_err(|e| format!("无法读取音频文件: {}", e))?;
+    // 使用 lofty 读取音频文件；lofty 不支持的容器（如 Matroska）改用 symphonia
+    let tagged_file = match Probe::open(path).and_then(|probe| probe.read()) {
+        Ok(tagged_file) => tagged_file,
+        Err(e) => {
+            return read_metadata_without_lofty(path, file_size, templates)
+                .map_err(|fallback| format!("无法读取音频文件: {}; {}", e, fallback));
+        }
+    };
 
     // 获取音频属性
     let properties = tagged_file.properties();
@@ -115,18 +229,29 @@ pub fn read_metadata(path: &Path) -> Result<ScannedSong, String> {
 
     let title = tag
         .and_then(|t| t.title().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| extract_filename(path));
+        .filter(|s| !s.is_empty());
 
     let artist = tag
         .and_then(|t| t.artist().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "未知艺术家".to_string());
+        .filter(|s| !s.is_empty());
 
     let album = tag
         .and_then(|t| t.album().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "未知专辑".to_string());
+        .filter(|s| !s.is_empty());
+
+    let album_artist = tag
+        .and_then(|t| t.get_string(&lofty::tag::ItemKey::AlbumArtist).map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty());
+
+    let composer = tag
+        .and_then(|t| t.get_string(&lofty::tag::ItemKey::Composer).map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty());
+
+    let (year, genre) = read_year_and_genre(tag);
+    let (track_no, disc_no) = read_track_and_disc(tag);
+    let replay_gain = read_replay_gain(tag);
+    let (title, artist, album, year) = with_path_fallback(title, artist, album, year, path, templates);
+    let (year, genre) = with_nfo_fallback(year, genre, path);
 
     // 提取封面
     let cover_url = tag.and_then(|t| {
@@ -145,6 +270,12 @@ pub fn read_metadata(path: &Path) -> Result<ScannedSong, String> {
         title,
         artist,
         album,
+        album_artist,
+        composer,
+        year,
+        genre,
+        track_no,
+        disc_no,
         duration,
         file_path: file_path_str,
         file_size,
@@ -156,11 +287,15 @@ pub fn read_metadata(path: &Path) -> Result<ScannedSong, String> {
         sample_rate: if sample_rate > 0 { Some(sample_rate) } else { None },
         bitrate,
         channels,
+        replay_gain: replay_gain.track_gain,
+        replay_peak: replay_gain.track_peak,
+        album_gain: replay_gain.album_gain,
+        album_peak: replay_gain.album_peak,
     })
 }
 
 /// Read audio file metadata with modification time (for incremental scanning)
-pub fn read_metadata_with_mtime(path: &Path) -> Result<ScannedSongWithMtime, String> {
+pub fn read_metadata_with_mtime(path: &Path, templates: &PathTemplates) -> Result<ScannedSongWithMtime, String> {
     let file_path_str = path.to_string_lossy().to_string();
 
     // Get file metadata
@@ -177,11 +312,41 @@ pub fn read_metadata_with_mtime(path: &Path) -> Result<ScannedSongWithMtime, Str
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
-    // Use lofty to read audio file
-    let tagged_file = Probe::open(path)
-        .map_err(|e| format!("无法打开文件: {}", e))?
-        .read()
-        .map_err(|e| format!("无法读取音频文件: {}", e))?;
+    // Use lofty to read audio file, falling back to symphonia for containers it can't parse
+    let tagged_file = match Probe::open(path).and_then(|probe| probe.read()) {
+        Ok(tagged_file) => tagged_file,
+        Err(e) => {
+            let song = read_metadata_without_lofty(path, file_size, templates)
+                .map_err(|fallback| format!("无法读取音频文件: {}; {}", e, fallback))?;
+            return Ok(ScannedSongWithMtime {
+                id: song.id,
+                title: song.title,
+                artist: song.artist,
+                album: song.album,
+                album_artist: song.album_artist,
+                composer: song.composer,
+                year: song.year,
+                genre: song.genre,
+                track_no: song.track_no,
+                disc_no: song.disc_no,
+                duration: song.duration,
+                file_path: song.file_path,
+                file_size: song.file_size,
+                is_hr: song.is_hr,
+                is_sq: song.is_sq,
+                format: song.format,
+                bit_depth: song.bit_depth,
+                sample_rate: song.sample_rate,
+                bitrate: song.bitrate,
+                channels: song.channels,
+                replay_gain: song.replay_gain,
+                replay_peak: song.replay_peak,
+                album_gain: song.album_gain,
+                album_peak: song.album_peak,
+                file_modified,
+            });
+        }
+    };
 
     // Get audio properties
     let properties = tagged_file.properties();
@@ -205,18 +370,29 @@ pub fn read_metadata_with_mtime(path: &Path) -> Result<ScannedSongWithMtime, Str
 
     let title = tag
         .and_then(|t| t.title().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| extract_filename(path));
+        .filter(|s| !s.is_empty());
 
     let artist = tag
         .and_then(|t| t.artist().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "未知艺术家".to_string());
+        .filter(|s| !s.is_empty());
 
     let album = tag
         .and_then(|t| t.album().map(|s| s.to_string()))
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "未知专辑".to_string());
+        .filter(|s| !s.is_empty());
+
+    let album_artist = tag
+        .and_then(|t| t.get_string(&lofty::tag::ItemKey::AlbumArtist).map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty());
+
+    let composer = tag
+        .and_then(|t| t.get_string(&lofty::tag::ItemKey::Composer).map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty());
+
+    let (year, genre) = read_year_and_genre(tag);
+    let (track_no, disc_no) = read_track_and_disc(tag);
+    let replay_gain = read_replay_gain(tag);
+    let (title, artist, album, year) = with_path_fallback(title, artist, album, year, path, templates);
+    let (year, genre) = with_nfo_fallback(year, genre, path);
 
     // Use file path hash as unique ID
     let id = format!("{:x}", md5::compute(&file_path_str));
@@ -226,6 +402,12 @@ pub fn read_metadata_with_mtime(path: &Path) -> Result<ScannedSongWithMtime, Str
         title,
         artist,
         album,
+        album_artist,
+        composer,
+        year,
+        genre,
+        track_no,
+        disc_no,
         duration,
         file_path: file_path_str,
         file_size,
@@ -236,10 +418,216 @@ pub fn read_metadata_with_mtime(path: &Path) -> Result<ScannedSongWithMtime, Str
         sample_rate: if sample_rate > 0 { Some(sample_rate) } else { None },
         bitrate,
         channels,
+        replay_gain: replay_gain.track_gain,
+        replay_peak: replay_gain.track_peak,
+        album_gain: replay_gain.album_gain,
+        album_peak: replay_gain.album_peak,
         file_modified,
     })
 }
 
+/// 用 symphonia 读取 lofty 无法解析的文件（Matroska 等）。
+/// 解码器不支持其编码时返回错误，扫描时该文件会被跳过。
+/// lofty 不支持的格式：TAK 自行解析，其余容器（如 Matroska）交给 symphonia
+fn read_metadata_without_lofty(path: &Path, file_size: u64, templates: &PathTemplates) -> Result<ScannedSong, String> {
+    let is_tak = path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tak"));
+    if is_tak {
+        read_metadata_tak(path, file_size, templates)
+    } else {
+        read_metadata_with_symphonia(path, file_size, templates)
+    }
+}
+
+/// 读取 TAK 文件的流信息和 APEv2 标签
+fn read_metadata_tak(path: &Path, file_size: u64, templates: &PathTemplates) -> Result<ScannedSong, String> {
+    let info = tak::read_info(path)?;
+    let duration = info.duration_secs();
+    let tag_value = |key: &str| info.tag(key).map(|s| s.to_string());
+
+    let year = tag_value("YEAR")
+        .and_then(|date| date.get(..4).and_then(|y| y.parse::<u32>().ok()))
+        .filter(|y| *y > 0);
+    let (title, artist, album, year) = with_path_fallback(
+        tag_value("TITLE"),
+        tag_value("ARTIST"),
+        tag_value("ALBUM"),
+        year,
+        path,
+        templates,
+    );
+    let album_artist = tag_value("ALBUM ARTIST").or_else(|| tag_value("ALBUMARTIST"));
+    let composer = tag_value("COMPOSER");
+    let (year, genre) = with_nfo_fallback(year, tag_value("GENRE"), path);
+    let track_no = info.tag("TRACK").and_then(parse_position);
+    let disc_no = info.tag("DISC").and_then(parse_position);
+    let gain_value = |key: &str| info.tag(key).and_then(parse_replay_gain);
+
+    let cover_url = info.cover.as_ref().map(|data| {
+        let mime = if data.starts_with(b"\x89PNG") { "image/png" } else { "image/jpeg" };
+        format!("data:{};base64,{}", mime, BASE64.encode(data))
+    });
+    let bitrate = (duration > 0.0).then(|| (file_size as f64 * 8.0 / duration / 1000.0).round() as u32);
+    let file_path_str = path.to_string_lossy().to_string();
+    let id = format!("{:x}", md5::compute(&file_path_str));
+
+    Ok(ScannedSong {
+        id,
+        title,
+        artist,
+        album,
+        album_artist,
+        composer,
+        year,
+        genre,
+        track_no,
+        disc_no,
+        duration,
+        file_path: file_path_str,
+        file_size,
+        cover_url,
+        is_hr: Some(info.sample_rate > 44100 || info.bit_depth > 16),
+        is_sq: Some(true),
+        format: Some("TAK".to_string()),
+        bit_depth: Some(info.bit_depth as u8),
+        sample_rate: Some(info.sample_rate),
+        bitrate,
+        channels: Some(info.channels),
+        replay_gain: gain_value("REPLAYGAIN_TRACK_GAIN"),
+        replay_peak: gain_value("REPLAYGAIN_TRACK_PEAK"),
+        album_gain: gain_value("REPLAYGAIN_ALBUM_GAIN"),
+        album_peak: gain_value("REPLAYGAIN_ALBUM_PEAK"),
+    })
+}
+
+fn read_metadata_with_symphonia(path: &Path, file_size: u64, templates: &PathTemplates) -> Result<ScannedSong, String> {
+    use symphonia::core::codecs::CODEC_TYPE_NULL;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("不支持的音频格式: {}", e))?;
+
+    // 与播放器一致：取第一条可解码的音频轨道
+    let codecs = symphonia::default::get_codecs();
+    let params = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && codecs.get_codec(t.codec_params.codec).is_some())
+        .map(|t| t.codec_params.clone())
+        .ok_or_else(|| "不支持的音频编码".to_string())?;
+
+    let sample_rate = params.sample_rate.unwrap_or(0);
+    let duration = match (params.n_frames, params.time_base) {
+        (Some(frames), Some(tb)) => {
+            let t = tb.calc_time(frames);
+            t.seconds as f64 + t.frac
+        }
+        (Some(frames), None) if sample_rate > 0 => frames as f64 / sample_rate as f64,
+        _ => 0.0,
+    };
+    let bit_depth = params.bits_per_sample;
+    let channels = params.channels.map(|c| c.count() as u8);
+    // 单轨音频文件按文件大小估算码率（kbps）
+    let bitrate = (duration > 0.0).then(|| (file_size as f64 * 8.0 / duration / 1000.0).round() as u32);
+
+    // 容器标签，优先使用最新的一组
+    let mut tags = Vec::new();
+    if let Some(rev) = probed.metadata.get().as_ref().and_then(|m| m.current().cloned()) {
+        tags.extend(rev.tags().to_vec());
+    }
+    if let Some(rev) = probed.format.metadata().current() {
+        tags.extend(rev.tags().to_vec());
+    }
+    let tag_value = |key: StandardTagKey| -> Option<String> {
+        tags.iter()
+            .rev()
+            .find(|tag| tag.std_key == Some(key))
+            .map(|tag| tag.value.to_string().trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let album_artist = tag_value(StandardTagKey::AlbumArtist);
+    let composer = tag_value(StandardTagKey::Composer);
+    let year = tag_value(StandardTagKey::Date)
+        .and_then(|date| date.get(..4).and_then(|y| y.parse::<u32>().ok()))
+        .filter(|y| *y > 0);
+    let (title, artist, album, year) = with_path_fallback(
+        tag_value(StandardTagKey::TrackTitle),
+        tag_value(StandardTagKey::Artist),
+        tag_value(StandardTagKey::Album),
+        year,
+        path,
+        templates,
+    );
+    let (year, genre) = with_nfo_fallback(year, tag_value(StandardTagKey::Genre), path);
+    let track_no = tag_value(StandardTagKey::TrackNumber).and_then(|v| parse_position(&v));
+    let disc_no = tag_value(StandardTagKey::DiscNumber).and_then(|v| parse_position(&v));
+    let gain_value = |key: StandardTagKey| tag_value(key).and_then(|v| parse_replay_gain(&v));
+    let r128_value = |key: &str| -> Option<f32> {
+        tags.iter()
+            .rev()
+            .find(|tag| tag.key.eq_ignore_ascii_case(key))
+            .and_then(|tag| parse_r128_gain(&tag.value.to_string()))
+    };
+    let replay_gain = ReplayGainTags {
+        track_gain: gain_value(StandardTagKey::ReplayGainTrackGain).or_else(|| r128_value("R128_TRACK_GAIN")),
+        track_peak: gain_value(StandardTagKey::ReplayGainTrackPeak),
+        album_gain: gain_value(StandardTagKey::ReplayGainAlbumGain).or_else(|| r128_value("R128_ALBUM_GAIN")),
+        album_peak: gain_value(StandardTagKey::ReplayGainAlbumPeak),
+    };
+
+    let format = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_uppercase());
+    let is_sq = is_lossless_format(path);
+    let is_hr = sample_rate > 44100 || bit_depth.map(|d| d > 16).unwrap_or(false);
+
+    let file_path_str = path.to_string_lossy().to_string();
+    let id = format!("{:x}", md5::compute(&file_path_str));
+
+    Ok(ScannedSong {
+        id,
+        title,
+        artist,
+        album,
+        album_artist,
+        composer,
+        year,
+        genre,
+        track_no,
+        disc_no,
+        duration,
+        file_path: file_path_str,
+        file_size,
+        cover_url: None,
+        is_hr: Some(is_hr),
+        is_sq: Some(is_sq),
+        format,
+        bit_depth: bit_depth.map(|d| d as u8),
+        sample_rate: if sample_rate > 0 { Some(sample_rate) } else { None },
+        bitrate,
+        channels,
+        replay_gain: replay_gain.track_gain,
+        replay_peak: replay_gain.track_peak,
+        album_gain: replay_gain.album_gain,
+        album_peak: replay_gain.album_peak,
+    })
+}
+
 /// Get file modification time without reading full metadata
 #[allow(dead_code)]
 pub fn get_file_mtime(path: &Path) -> Result<i64, String> {
@@ -0,0 +1,249 @@
+//! MP4 / M4B 章节
+//!
+//! 优先读取 QuickTime 章节文本轨（`trak/tref/chap` 指向的轨道，每个样本是一章的标题），
+//! 没有时读取 Nero 章节（`moov/udta/chpl`）。symphonia 和 lofty 都不解析这两种结构。
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::db::chapters::Chapter;
+
+/// 可能带章节的扩展名
+const CHAPTER_EXTENSIONS: &[&str] = &["m4b", "m4a", "mp4"];
+/// moov 大小上限，防止损坏的文件导致超大分配
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+/// 单个章节标题样本的大小上限
+const MAX_TITLE_SAMPLE: u32 = 64 * 1024;
+/// Nero 章节的时间单位：100 纳秒
+const CHPL_TIMESCALE: f64 = 10_000_000.0;
+
+/// 文件中的章节，按开始时间排列；没有章节（或不是 MP4）时为空
+pub fn read(path: &Path) -> Vec<Chapter> {
+    let has_chapters = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| CHAPTER_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)));
+    if !has_chapters {
+        return Vec::new();
+    }
+
+    let Ok(mut file) = File::open(path) else {
+        return Vec::new();
+    };
+    let Some(moov) = read_moov(&mut file) else {
+        return Vec::new();
+    };
+    let mut chapters = quicktime_chapters(&mut file, &moov)
+        .filter(|chapters| !chapters.is_empty())
+        .or_else(|| nero_chapters(&moov))
+        .unwrap_or_default();
+    chapters.sort_by(|a, b| a.start_secs.total_cmp(&b.start_secs));
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        if chapter.title.trim().is_empty() {
+            chapter.title = format!("第 {} 章", i + 1);
+        }
+    }
+    chapters
+}
+
+/// 顶层的 moov box 内容
+fn read_moov(file: &mut File) -> Option<Vec<u8>> {
+    let file_len = file.seek(SeekFrom::End(0)).ok()?;
+    let mut pos = 0;
+    while pos + 8 <= file_len {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).ok()?;
+        let (header_len, size) = match be32(&header[0..4]) {
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large).ok()?;
+                (16, u64::from_be_bytes(large))
+            }
+            0 => (8, file_len - pos),
+            size => (8, size as u64),
+        };
+        if size < header_len {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_SIZE {
+                return None;
+            }
+            let mut body = vec![0u8; body_len as usize];
+            file.read_exact(&mut body).ok()?;
+            return Some(body);
+        }
+        pos += size;
+    }
+    None
+}
+
+/// QuickTime 章节：章节轨每个样本的时长即该章长度
+fn quicktime_chapters(file: &mut File, moov: &[u8]) -> Option<Vec<Chapter>> {
+    let traks: Vec<&[u8]> = Atoms(moov).filter(|(kind, _)| kind == b"trak").map(|(_, body)| body).collect();
+    let chapter_track = traks
+        .iter()
+        .find_map(|trak| find_path(trak, &[b"tref", b"chap"]))
+        .and_then(|chap| chap.get(0..4))
+        .map(be32)?;
+    let trak = traks.iter().find(|trak| track_id(trak) == Some(chapter_track))?;
+
+    let mdia = find(trak, b"mdia")?;
+    let timescale = media_timescale(find(mdia, b"mdhd")?)?;
+    let stbl = find_path(mdia, &[b"minf", b"stbl"])?;
+    let durations = sample_durations(find(stbl, b"stts")?)?;
+    let offsets = sample_offsets(stbl)?;
+    let sizes = sample_sizes(find(stbl, b"stsz")?)?;
+
+    let mut chapters = Vec::new();
+    let mut time: u64 = 0;
+    for ((offset, size), duration) in offsets.into_iter().zip(sizes).zip(durations) {
+        let title = read_text_sample(file, offset, size).unwrap_or_default();
+        chapters.push(Chapter { title, start_secs: time as f64 / timescale as f64 });
+        time += duration as u64;
+    }
+    Some(chapters)
+}
+
+/// Nero 章节：开始时间（100 ns）+ 标题
+fn nero_chapters(moov: &[u8]) -> Option<Vec<Chapter>> {
+    let chpl = find_path(moov, &[b"udta", b"chpl"])?;
+    let version = *chpl.first()?;
+    let mut pos = if version > 0 { 8 } else { 4 };
+    let count = *chpl.get(pos)?;
+    pos += 1;
+
+    let mut chapters = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let start = u64::from_be_bytes(chpl.get(pos..pos + 8)?.try_into().ok()?);
+        let len = *chpl.get(pos + 8)? as usize;
+        let title = chpl.get(pos + 9..pos + 9 + len)?;
+        chapters.push(Chapter {
+            title: String::from_utf8_lossy(title).into_owned(),
+            start_secs: start as f64 / CHPL_TIMESCALE,
+        });
+        pos += 9 + len;
+    }
+    Some(chapters)
+}
+
+/// 文本样本：16 位长度 + UTF-8（或带 BOM 的 UTF-16）标题
+fn read_text_sample(file: &mut File, offset: u64, size: u32) -> Option<String> {
+    if !(2..=MAX_TITLE_SAMPLE).contains(&size) {
+        return None;
+    }
+    let mut sample = vec![0u8; size as usize];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut sample).ok()?;
+    let len = (u16::from_be_bytes([sample[0], sample[1]]) as usize).min(sample.len() - 2);
+    let text = &sample[2..2 + len];
+    if let Some(utf16) = text.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        return Some(String::from_utf16_lossy(&units));
+    }
+    Some(String::from_utf8_lossy(text).into_owned())
+}
+
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = find(trak, b"tkhd")?;
+    let at = if *tkhd.first()? == 1 { 20 } else { 12 };
+    tkhd.get(at..at + 4).map(be32)
+}
+
+fn media_timescale(mdhd: &[u8]) -> Option<u32> {
+    let at = if *mdhd.first()? == 1 { 20 } else { 12 };
+    mdhd.get(at..at + 4).map(be32).filter(|&scale| scale > 0)
+}
+
+/// `stts`：（样本数，每个样本的时长）展开为逐样本时长
+fn sample_durations(stts: &[u8]) -> Option<Vec<u32>> {
+    let count = be32(stts.get(4..8)?) as usize;
+    let mut durations = Vec::new();
+    for i in 0..count {
+        let entry = stts.get(8 + i * 8..16 + i * 8)?;
+        durations.extend(std::iter::repeat_n(be32(&entry[4..8]), be32(&entry[0..4]) as usize));
+    }
+    Some(durations)
+}
+
+/// `stsz`：统一大小或逐样本大小
+fn sample_sizes(stsz: &[u8]) -> Option<Vec<u32>> {
+    let uniform = be32(stsz.get(4..8)?);
+    let count = be32(stsz.get(8..12)?) as usize;
+    if uniform != 0 {
+        return Some(vec![uniform; count]);
+    }
+    (0..count).map(|i| stsz.get(12 + i * 4..16 + i * 4).map(be32)).collect()
+}
+
+/// 由 `stsc` 与 `stco` / `co64` 计算每个样本在文件中的位置
+fn sample_offsets(stbl: &[u8]) -> Option<Vec<u64>> {
+    let chunk_offsets: Vec<u64> = if let Some(stco) = find(stbl, b"stco") {
+        let count = be32(stco.get(4..8)?) as usize;
+        (0..count).map(|i| stco.get(8 + i * 4..12 + i * 4).map(|b| be32(b) as u64)).collect::<Option<_>>()?
+    } else {
+        let co64 = find(stbl, b"co64")?;
+        let count = be32(co64.get(4..8)?) as usize;
+        (0..count)
+            .map(|i| co64.get(8 + i * 8..16 + i * 8).and_then(|b| b.try_into().ok()).map(u64::from_be_bytes))
+            .collect::<Option<_>>()?
+    };
+    let stsc = find(stbl, b"stsc")?;
+    let runs: Vec<(usize, u32)> = (0..be32(stsc.get(4..8)?) as usize)
+        .map(|i| stsc.get(8 + i * 12..20 + i * 12).map(|e| (be32(&e[0..4]) as usize, be32(&e[4..8]))))
+        .collect::<Option<_>>()?;
+    let sizes = sample_sizes(find(stbl, b"stsz")?)?;
+
+    let mut offsets = Vec::with_capacity(sizes.len());
+    let mut sizes = sizes.into_iter();
+    for (chunk, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        // stsc 的 first_chunk 从 1 开始
+        let per_chunk = runs.iter().rev().find(|(first, _)| *first <= chunk + 1).map_or(1, |&(_, n)| n);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            let Some(size) = sizes.next() else {
+                return Some(offsets);
+            };
+            offsets.push(offset);
+            offset += size as u64;
+        }
+    }
+    Some(offsets)
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn find<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    Atoms(data).find(|(k, _)| k == kind).map(|(_, body)| body)
+}
+
+fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| find(data, kind))
+}
+
+/// Box 序列：（类型，内容）
+struct Atoms<'a>(&'a [u8]);
+
+impl<'a> Iterator for Atoms<'a> {
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.0;
+        let kind: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+        let (header_len, size) = match be32(data.get(0..4)?) {
+            1 => (16, u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)),
+            0 => (8, data.len() as u64),
+            size => (8, size as u64),
+        };
+        if size < header_len || size > data.len() as u64 {
+            return None;
+        }
+        self.0 = &data[size as usize..];
+        Some((kind, &data[header_len as usize..size as usize]))
+    }
+}
@@ -12,6 +12,8 @@ use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::db::SongPicture;
+
 /// Cover size variants
 #[derive(Debug, Clone, Copy)]
 pub enum CoverSize {
@@ -250,32 +252,105 @@ fn save_as_jpeg(img: &DynamicImage, path: &Path, quality: u8) -> Result<(), Stri
     fs::write(path, buffer.into_inner()).map_err(|e| format!("Failed to write file: {}", e))
 }
 
-/// Extract cover from audio file and cache it
-pub fn extract_and_cache_cover(
+/// Covers extracted from an audio file
+#[derive(Debug, Default)]
+pub struct ExtractedCovers {
+    /// Main cover: the front cover, else the first picture, else folder artwork
+    pub cover_hash: Option<String>,
+    /// Every embedded picture with its type, in tag order
+    pub pictures: Vec<SongPicture>,
+}
+
+/// Map lofty's picture type to the name stored in `song_pictures`
+fn picture_type_name(picture_type: lofty::picture::PictureType) -> &'static str {
+    use lofty::picture::PictureType;
+
+    match picture_type {
+        PictureType::CoverFront => "front",
+        PictureType::CoverBack => "back",
+        PictureType::Leaflet => "booklet",
+        PictureType::Media => "media",
+        PictureType::LeadArtist
+        | PictureType::Artist
+        | PictureType::Conductor
+        | PictureType::Band
+        | PictureType::Composer
+        | PictureType::Lyricist => "artist",
+        PictureType::Icon | PictureType::OtherIcon => "icon",
+        PictureType::Illustration => "illustration",
+        PictureType::BandLogo | PictureType::PublisherLogo => "logo",
+        _ => "other",
+    }
+}
+
+/// Extract and cache every embedded picture of an audio file
+pub fn extract_and_cache_covers(
     audio_path: &Path,
     cache: &CoverCache,
-) -> Result<Option<String>, String> {
+) -> Result<ExtractedCovers, String> {
     use lofty::prelude::*;
     use lofty::probe::Probe;
 
-    let tagged_file = Probe::open(audio_path)
-        .map_err(|e| format!("Failed to open file: {}", e))?
-        .read()
-        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let tagged_file = match Probe::open(audio_path).and_then(|probe| probe.read()) {
+        Ok(tagged_file) => tagged_file,
+        // Containers lofty can't parse (e.g. Matroska) may still have folder artwork
+        Err(_) => {
+            return Ok(ExtractedCovers {
+                cover_hash: extract_folder_artwork(audio_path, cache)?,
+                pictures: Vec::new(),
+            });
+        }
+    };
 
     let tag = tagged_file
         .primary_tag()
         .or_else(|| tagged_file.first_tag());
 
+    let mut covers = ExtractedCovers::default();
     if let Some(tag) = tag {
-        if let Some(pic) = tag.pictures().first() {
-            let mime = pic.mime_type().map(|m| m.as_str());
-            let hash = cache.save_cover(pic.data(), mime)?;
-            return Ok(Some(hash));
-        }
+        // Pictures that fail to decode are skipped, the rest are still usable
+        covers.pictures = tag
+            .pictures()
+            .iter()
+            .filter_map(|pic| {
+                let mime = pic.mime_type().map(|m| m.as_str());
+                let hash = cache.save_cover(pic.data(), mime).ok()?;
+                Some(SongPicture {
+                    picture_type: picture_type_name(pic.pic_type()).to_string(),
+                    cover_hash: hash,
+                })
+            })
+            .collect();
+
+        covers.cover_hash = covers
+            .pictures
+            .iter()
+            .find(|pic| pic.picture_type == "front")
+            .or_else(|| covers.pictures.first())
+            .map(|pic| pic.cover_hash.clone());
+    }
+
+    if covers.cover_hash.is_none() {
+        covers.cover_hash = extract_folder_artwork(audio_path, cache)?;
     }
 
-    Ok(None)
+    Ok(covers)
+}
+
+/// No embedded picture: fall back to cover.jpg / folder.png etc. next to the file
+fn extract_folder_artwork(audio_path: &Path, cache: &CoverCache) -> Result<Option<String>, String> {
+    let Some(artwork) = audio_path.parent().and_then(super::sidecar::find_folder_artwork) else {
+        return Ok(None);
+    };
+    let data = fs::read(&artwork).map_err(|e| format!("Failed to read artwork: {}", e))?;
+    let mime = match artwork.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    };
+    let hash = cache.save_cover(&data, Some(mime))?;
+
+    Ok(Some(hash))
 }
 
 /// Download and cache cover from URL
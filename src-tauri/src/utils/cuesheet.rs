@@ -0,0 +1,195 @@
+//! 整轨 FLAC 内嵌的 CUE：优先读取 `CUESHEET` 标签（文本，含曲名），
+//! 没有时读取 CUESHEET 元数据块（只有分轨位置）。
+//!
+//! 扫描时把这样的文件拆成多首歌曲，每首按分轨起止点播放整轨文件的一段；
+//! 起止点与用户设置的 cue-in / cue-out 分开保存，用户的设置只能在分轨范围内生效。
+
+use std::path::Path;
+
+use lofty::config::ParseOptions;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::ItemKey;
+
+use crate::db::extra::CuePoints;
+use crate::db::SongInput;
+
+/// CD 帧率，`INDEX mm:ss:ff` 中 ff 的单位
+const CD_FRAMES_PER_SEC: f64 = 75.0;
+/// CUESHEET 块中表示结束位置的音轨号（CD-DA 为 170）
+const LEAD_OUT_TRACKS: [u32; 2] = [170, 255];
+
+#[derive(Debug, Clone)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub start_secs: f64,
+}
+
+/// 文件内嵌的分轨，少于两轨时为空（无需拆分）
+fn read_embedded(path: &Path) -> Vec<CueTrack> {
+    let is_flac = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"));
+    if !is_flac {
+        return Vec::new();
+    }
+
+    let mut tracks = read_cuesheet_tag(path).unwrap_or_default();
+    if tracks.is_empty() {
+        tracks = read_cuesheet_block(path).unwrap_or_default();
+    }
+    tracks.sort_by_key(|track| track.number);
+    if tracks.len() < 2 {
+        return Vec::new();
+    }
+    tracks
+}
+
+/// 扫描结果：带内嵌 CUE 的整轨文件拆成各分轨，其余原样返回
+pub fn expand(song: SongInput) -> Vec<SongInput> {
+    let tracks = read_embedded(Path::new(&song.file_path));
+    if tracks.is_empty() {
+        return vec![song];
+    }
+    split_song(&song, &tracks)
+}
+
+/// 按内嵌 CUE 把整轨歌曲拆分为各分轨，附带每轨的起止点
+fn split_song(song: &SongInput, tracks: &[CueTrack]) -> Vec<SongInput> {
+    tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let end_secs = tracks.get(i + 1).map(|next| next.start_secs);
+            let duration = end_secs.unwrap_or(song.duration) - track.start_secs;
+            SongInput {
+                id: track_id(&song.file_path, track.number),
+                title: track
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("{} - 音轨 {:02}", song.title, track.number)),
+                artist: track.performer.clone().unwrap_or_else(|| song.artist.clone()),
+                track_no: Some(track.number),
+                duration: duration.max(0.0),
+                cue: Some(CuePoints {
+                    cue_in: Some(track.start_secs),
+                    cue_out: end_secs,
+                }),
+                ..song.clone()
+            }
+        })
+        .collect()
+}
+
+/// 分轨的歌曲 ID：整轨文件路径加音轨号的哈希
+fn track_id(file_path: &str, number: u32) -> String {
+    format!("{:x}", md5::compute(format!("{}#{}", file_path, number)))
+}
+
+/// `CUESHEET` 标签中的文本 CUE
+fn read_cuesheet_tag(path: &Path) -> Option<Vec<CueTrack>> {
+    let tagged_file = Probe::open(path)
+        .ok()?
+        .options(ParseOptions::new().read_properties(false).read_cover_art(false))
+        .read()
+        .ok()?;
+    let tag = tagged_file.primary_tag().or_else(|| tagged_file.first_tag())?;
+    let text = tag.get_string(&ItemKey::Unknown("CUESHEET".to_string()))?;
+    Some(parse_text(text))
+}
+
+/// 解析文本 CUE 中的 TRACK / TITLE / PERFORMER / INDEX 01（内嵌 CUE 只对应一个文件）
+fn parse_text(text: &str) -> Vec<CueTrack> {
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    let mut starts: Vec<Option<f64>> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "TRACK" => {
+                let Some(number) = rest.split_whitespace().next().and_then(|n| n.parse().ok()) else {
+                    continue;
+                };
+                tracks.push(CueTrack { number, title: None, performer: None, start_secs: 0.0 });
+                starts.push(None);
+            }
+            "TITLE" | "PERFORMER" => {
+                let Some(track) = tracks.last_mut() else {
+                    continue;
+                };
+                let value = Some(unquote(rest)).filter(|v| !v.is_empty());
+                if command.eq_ignore_ascii_case("TITLE") {
+                    track.title = value;
+                } else {
+                    track.performer = value;
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                let (Some(index), Some(time)) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+                if let (Some(start), Ok(1)) = (starts.last_mut(), index.parse::<u32>()) {
+                    *start = parse_time(time);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tracks
+        .into_iter()
+        .zip(starts)
+        .filter_map(|(track, start)| Some(CueTrack { start_secs: start?, ..track }))
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').trim().to_string()
+}
+
+/// `mm:ss:ff`（ff 为 1/75 秒）
+fn parse_time(value: &str) -> Option<f64> {
+    let mut parts = value.split(':').map(|p| p.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / CD_FRAMES_PER_SEC)
+}
+
+/// FLAC 的 CUESHEET 元数据块（由 symphonia 解析）
+fn read_cuesheet_block(path: &Path) -> Option<Vec<CueTrack>> {
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("flac");
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let sample_rate = probed.format.default_track()?.codec_params.sample_rate? as f64;
+
+    let tracks = probed
+        .format
+        .cues()
+        .iter()
+        .filter(|cue| !LEAD_OUT_TRACKS.contains(&cue.index))
+        .map(|cue| {
+            // INDEX 00 是前一轨末尾的间隙，有 INDEX 01 时从它开始
+            let offset = cue.points.get(1).or(cue.points.first()).map_or(0, |p| p.start_offset_ts);
+            CueTrack {
+                number: cue.index,
+                title: None,
+                performer: None,
+                start_secs: (cue.start_ts + offset) as f64 / sample_rate,
+            }
+        })
+        .collect();
+    Some(tracks)
+}
@@ -0,0 +1,119 @@
+//! 动态范围分析：DR 值与峰值因数
+//!
+//! DR 参照 TT DR Meter：每个声道按 3 秒分块，取块 RMS 最高的 20% 求均方根，
+//! 与第二高的块峰值相比 (dB)，再对各声道取平均。峰值因数是整首歌峰值与 RMS 之比 (dB)。
+
+use crate::audio_engine::decoder::AudioDecoder;
+
+const BLOCK_SECS: f64 = 3.0;
+
+/// Share of the loudest blocks used for the DR value
+const LOUDEST_BLOCKS: f64 = 0.2;
+
+/// Dynamic range metrics of one track
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicsMetrics {
+    /// DR value (dB)
+    pub dynamic_range: f32,
+    /// Crest factor, peak to RMS (dB)
+    pub crest_factor: f32,
+}
+
+#[derive(Default, Clone)]
+struct ChannelStats {
+    block_sum_sq: f64,
+    block_peak: f64,
+    block_len: usize,
+    /// (RMS, peak) of each finished block
+    blocks: Vec<(f64, f64)>,
+    sum_sq: f64,
+    len: usize,
+    peak: f64,
+}
+
+impl ChannelStats {
+    fn push(&mut self, sample: f64) {
+        let square = sample * sample;
+        let abs = sample.abs();
+        self.block_sum_sq += square;
+        self.block_peak = self.block_peak.max(abs);
+        self.block_len += 1;
+        self.sum_sq += square;
+        self.len += 1;
+        self.peak = self.peak.max(abs);
+    }
+
+    fn end_block(&mut self) {
+        if self.block_len > 0 {
+            // Scaled so a full-scale sine reads 0 dB, as in the DR meter
+            let rms = (2.0 * self.block_sum_sq / self.block_len as f64).sqrt();
+            self.blocks.push((rms, self.block_peak));
+        }
+        self.block_sum_sq = 0.0;
+        self.block_peak = 0.0;
+        self.block_len = 0;
+    }
+
+    fn dynamic_range(&self) -> Option<f64> {
+        let mut rms: Vec<f64> = self.blocks.iter().map(|&(rms, _)| rms).collect();
+        let mut peaks: Vec<f64> = self.blocks.iter().map(|&(_, peak)| peak).collect();
+        rms.sort_by(|a, b| b.total_cmp(a));
+        peaks.sort_by(|a, b| b.total_cmp(a));
+
+        // The second highest peak ignores a single stray click
+        let peak = *peaks.get(1).or(peaks.first())?;
+        let loudest = ((rms.len() as f64 * LOUDEST_BLOCKS).ceil() as usize).max(1);
+        let top_rms = (rms.iter().take(loudest).map(|r| r * r).sum::<f64>() / loudest as f64).sqrt();
+        (top_rms > 0.0 && peak > 0.0).then(|| 20.0 * (peak / top_rms).log10())
+    }
+}
+
+/// Decode a local file and measure its dynamic range
+pub fn analyze_file(path: &str) -> Result<DynamicsMetrics, String> {
+    let mut decoder = AudioDecoder::open(path).map_err(|e| e.to_string())?;
+    let mut channels = decoder.info.channels.max(1);
+    let mut block_frames = block_frames_for(decoder.info.sample_rate);
+    let mut stats = vec![ChannelStats::default(); channels];
+    let mut frame_in_block = 0;
+
+    while let Some(samples) = decoder.decode_next().map_err(|e| e.to_string())? {
+        if decoder.take_spec_change() {
+            stats.iter_mut().for_each(ChannelStats::end_block);
+            frame_in_block = 0;
+            channels = decoder.info.channels.max(1);
+            block_frames = block_frames_for(decoder.info.sample_rate);
+            stats.resize_with(channels.max(stats.len()), Default::default);
+        }
+
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in stats.iter_mut().zip(frame) {
+                channel.push(sample as f64);
+            }
+            frame_in_block += 1;
+            if frame_in_block == block_frames {
+                stats.iter_mut().for_each(ChannelStats::end_block);
+                frame_in_block = 0;
+            }
+        }
+    }
+    stats.iter_mut().for_each(ChannelStats::end_block);
+
+    let ranges: Vec<f64> = stats.iter().filter_map(ChannelStats::dynamic_range).collect();
+    if ranges.is_empty() {
+        return Err("无法分析：音频为空或全部静音".to_string());
+    }
+
+    let peak = stats.iter().map(|c| c.peak).fold(0.0, f64::max);
+    let sum_sq: f64 = stats.iter().map(|c| c.sum_sq).sum();
+    let len: usize = stats.iter().map(|c| c.len).sum();
+    let rms = (sum_sq / len.max(1) as f64).sqrt();
+
+    Ok(DynamicsMetrics {
+        dynamic_range: (ranges.iter().sum::<f64>() / ranges.len() as f64) as f32,
+        crest_factor: (20.0 * (peak / rms).log10()) as f32,
+    })
+}
+
+fn block_frames_for(sample_rate: u32) -> usize {
+    ((sample_rate as f64 * BLOCK_SECS) as usize).max(1)
+}
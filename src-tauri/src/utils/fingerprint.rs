@@ -0,0 +1,90 @@
+//! 增量扫描的文件变更检测
+//!
+//! 默认比较修改时间；NAS/SMB 挂载上 mtime 不可靠（总在变或从不变），
+//! 这类目录可以改用「文件大小 + 部分内容哈希」判断。
+//!
+//! 部分内容哈希同时作为文件指纹保存：文件被移动或改名后，扫描按指纹找回原来的
+//! 歌曲记录，而不是删除后重新添加。
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::db::LocalFileState;
+
+/// Bytes hashed from the start, middle and end of the file
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Result of comparing a file on disk with its library entry
+pub struct FileCheck {
+    pub changed: bool,
+    /// Partial content hash, if it was computed for the comparison
+    pub content_hash: Option<String>,
+}
+
+/// 文件是否位于启用了哈希检测的目录下
+pub fn uses_content_hash(path: &Path, hash_directories: &[String]) -> bool {
+    hash_directories.iter().any(|dir| path.starts_with(dir))
+}
+
+/// 文件大小 + 开头/中间/结尾各 64KB 的 MD5
+pub fn partial_content_hash(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let size = file.metadata().map_err(|e| format!("无法获取文件信息: {}", e))?.len();
+
+    let mut context = md5::Context::new();
+    context.consume(size.to_le_bytes());
+
+    // 小文件整个哈希
+    let (offsets, chunk_len) = if size <= CHUNK_SIZE * 3 {
+        (vec![0], size)
+    } else {
+        (vec![0, size / 2 - CHUNK_SIZE / 2, size - CHUNK_SIZE], CHUNK_SIZE)
+    };
+
+    let mut buffer = vec![0u8; chunk_len as usize];
+    for offset in offsets {
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut buffer))
+            .map_err(|e| format!("读取文件失败: {}", e))?;
+        context.consume(&buffer);
+    }
+
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// Compare a file with its stored state. With `use_hash` the size and content
+/// hash decide; otherwise a newer mtime means changed.
+pub fn check_file(path: &Path, known: Option<&LocalFileState>, use_hash: bool) -> FileCheck {
+    let changed = |content_hash| FileCheck { changed: true, content_hash };
+
+    let Some(known) = known else {
+        return changed(None);
+    };
+    let Ok(meta) = std::fs::metadata(path) else {
+        return changed(None);
+    };
+
+    if use_hash {
+        if meta.len() as i64 != known.file_size {
+            return changed(None);
+        }
+        let content_hash = partial_content_hash(path).ok();
+        let changed = content_hash.is_none() || content_hash != known.content_hash;
+        return FileCheck { changed, content_hash };
+    }
+
+    let Some(db_mtime) = known.file_modified else {
+        return changed(None);
+    };
+    match meta.modified() {
+        Ok(mtime) => {
+            let file_mtime = mtime
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            FileCheck { changed: file_mtime > db_mtime, content_hash: None }
+        }
+        Err(_) => changed(None),
+    }
+}
@@ -7,10 +7,11 @@ use crate::models::{
     JellyfinItemsResponse, JellyfinLyricsResponse, JellyfinMediaStream, JellyfinSystemInfo,
     ScannedSong, ServerType, StreamServerConfig,
 };
+use crate::error::{CommandError, ErrorCode};
 use crate::utils::audio::extract_filename_from_path_str;
 
 /// 无损音频格式
-const LOSSLESS_CONTAINERS: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff", "alac"];
+const LOSSLESS_CONTAINERS: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff", "alac", "tta", "wv", "tak"];
 
 /// 构建 Jellyfin/Emby 认证头
 fn build_auth_header(config: &StreamServerConfig) -> Vec<(String, String)> {
@@ -33,7 +34,7 @@ fn base_url(config: &StreamServerConfig) -> String {
 }
 
 /// 认证并获取 access_token 和 user_id
-pub async fn authenticate(config: &StreamServerConfig) -> Result<(String, String), String> {
+pub async fn authenticate(config: &StreamServerConfig) -> Result<(String, String), CommandError> {
     let client = Client::new();
     let url = format!("{}/Users/AuthenticateByName", base_url(config));
 
@@ -47,16 +48,16 @@ pub async fn authenticate(config: &StreamServerConfig) -> Result<(String, String
         req = req.header(k.as_str(), v.as_str());
     }
 
-    let response = req.send().await.map_err(|e| format!("连接失败: {}", e))?;
+    let response = req.send().await.map_err(CommandError::request)?;
 
     if !response.status().is_success() {
-        return Err(format!("认证失败: HTTP {}", response.status()));
+        return Err(CommandError::new(ErrorCode::AuthFailed).with("details", format!("HTTP {}", response.status())));
     }
 
     let auth: JellyfinAuthResponse = response
         .json()
         .await
-        .map_err(|e| format!("解析认证响应失败: {}", e))?;
+        .map_err(CommandError::parse)?;
 
     Ok((auth.access_token, auth.user.id))
 }
@@ -66,13 +67,7 @@ pub async fn test_connection(config: &StreamServerConfig) -> ConnectionTestResul
     // 先认证
     let (token, _user_id) = match authenticate(config).await {
         Ok(v) => v,
-        Err(e) => {
-            return ConnectionTestResult {
-                success: false,
-                message: e,
-                server_version: None,
-            }
-        }
+        Err(e) => return ConnectionTestResult::failed(e),
     };
 
     // 获取系统信息
@@ -82,24 +77,12 @@ pub async fn test_connection(config: &StreamServerConfig) -> ConnectionTestResul
     match client.get(&url).send().await {
         Ok(resp) => {
             if let Ok(info) = resp.json::<JellyfinSystemInfo>().await {
-                ConnectionTestResult {
-                    success: true,
-                    message: "连接成功".to_string(),
-                    server_version: info.version,
-                }
+                ConnectionTestResult::ok("连接成功", info.version)
             } else {
-                ConnectionTestResult {
-                    success: true,
-                    message: format!("连接成功 (token: {}...)", &token[..8.min(token.len())]),
-                    server_version: None,
-                }
+                ConnectionTestResult::ok(format!("连接成功 (token: {}...)", &token[..8.min(token.len())]), None)
             }
         }
-        Err(_) => ConnectionTestResult {
-            success: true,
-            message: "认证成功".to_string(),
-            server_version: None,
-        },
+        Err(_) => ConnectionTestResult::ok("认证成功", None),
     }
 }
 
@@ -164,6 +147,15 @@ fn convert_item(item: &JellyfinItem, config: &StreamServerConfig) -> ScannedSong
         })
         .unwrap_or(0);
 
+    let composers: Vec<&str> = item
+        .people
+        .iter()
+        .flatten()
+        .filter(|p| p.kind.as_deref() == Some("Composer"))
+        .map(|p| p.name.as_str())
+        .collect();
+    let composer = (!composers.is_empty()).then(|| composers.join("; "));
+
     // 标题：如果 name 为空，尝试从路径提取文件名
     let title = if item.name.is_empty() {
         item.path
@@ -182,6 +174,12 @@ fn convert_item(item: &JellyfinItem, config: &StreamServerConfig) -> ScannedSong
             .album
             .clone()
             .unwrap_or_else(|| "未知专辑".to_string()),
+        album_artist: item.album_artist.clone().filter(|s| !s.is_empty()),
+        composer,
+        year: item.production_year.filter(|y| *y > 0),
+        genre: item.genres.as_ref().filter(|g| !g.is_empty()).map(|g| g.join("; ")),
+        track_no: item.index_number.filter(|n| *n > 0),
+        disc_no: item.parent_index_number.filter(|n| *n > 0),
         duration: duration_secs as f64,
         file_path: item.path.clone().unwrap_or_default(),
         file_size,
@@ -196,19 +194,24 @@ fn convert_item(item: &JellyfinItem, config: &StreamServerConfig) -> ScannedSong
             .and_then(|s| s.bitrate)
             .map(|b| b / 1000), // Jellyfin reports bps, convert to kbps
         channels: audio_stream.and_then(|s| s.channels).map(|c| c as u8),
+        replay_gain: item.normalization_gain,
+        // Jellyfin 不提供峰值和专辑增益
+        replay_peak: None,
+        album_gain: None,
+        album_peak: None,
     }
 }
 
 /// 获取所有音频项
-pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, String> {
+pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, CommandError> {
     let user_id = config
         .user_id
         .as_deref()
-        .ok_or("缺少 userId，请先测试连接")?;
+        .ok_or_else(|| CommandError::missing("userId"))?;
     let _token = config
         .access_token
         .as_deref()
-        .ok_or("缺少 accessToken，请先测试连接")?;
+        .ok_or_else(|| CommandError::missing("accessToken"))?;
 
     let client = Client::new();
     let url = format!("{}/Users/{}/Items", base_url(config), user_id);
@@ -223,7 +226,7 @@ pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedS
             .query(&[
                 ("IncludeItemTypes", "Audio"),
                 ("Recursive", "true"),
-                ("Fields", "MediaSources,Path"),
+                ("Fields", "MediaSources,Path,People"),
                 ("SortBy", "SortName"),
                 ("SortOrder", "Ascending"),
             ])
@@ -235,16 +238,16 @@ pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedS
             req = req.header(k.as_str(), v.as_str());
         }
 
-        let response = req.send().await.map_err(|e| format!("请求失败: {}", e))?;
+        let response = req.send().await.map_err(CommandError::request)?;
 
         if !response.status().is_success() {
-            return Err(format!("获取歌曲失败: HTTP {}", response.status()));
+            return Err(CommandError::http(response.status()));
         }
 
         let data: JellyfinItemsResponse = response
             .json()
             .await
-            .map_err(|e| format!("解析响应失败: {}", e))?;
+            .map_err(CommandError::parse)?;
 
         let count = data.items.len() as u64;
         for item in &data.items {
@@ -260,6 +263,42 @@ pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedS
     Ok(all_songs)
 }
 
+/// 按关键词搜索音频项
+pub async fn search_songs(config: &StreamServerConfig, query: &str, limit: u32) -> Result<Vec<ScannedSong>, CommandError> {
+    let user_id = config
+        .user_id
+        .as_deref()
+        .ok_or_else(|| CommandError::missing("userId"))?;
+
+    let client = Client::new();
+    let url = format!("{}/Users/{}/Items", base_url(config), user_id);
+    let mut req = client
+        .get(&url)
+        .query(&[
+            ("IncludeItemTypes", "Audio"),
+            ("Recursive", "true"),
+            ("Fields", "MediaSources,Path,People"),
+            ("SearchTerm", query),
+        ])
+        .query(&[("Limit", &limit.to_string())]);
+
+    for (k, v) in &build_auth_header(config) {
+        req = req.header(k.as_str(), v.as_str());
+    }
+
+    let response = req.send().await.map_err(CommandError::request)?;
+    if !response.status().is_success() {
+        return Err(CommandError::http(response.status()));
+    }
+
+    let data: JellyfinItemsResponse = response
+        .json()
+        .await
+        .map_err(CommandError::parse)?;
+
+    Ok(data.items.iter().map(|item| convert_item(item, config)).collect())
+}
+
 /// 获取流 URL
 pub fn get_stream_url(config: &StreamServerConfig, song_id: &str) -> String {
     let token = config.access_token.as_deref().unwrap_or("");
@@ -284,6 +323,38 @@ pub fn get_stream_url(config: &StreamServerConfig, song_id: &str) -> String {
     }
 }
 
+/// 会话 WebSocket 地址（远程控制）
+pub fn session_socket_url(config: &StreamServerConfig) -> Option<String> {
+    let token = config.access_token.as_deref()?;
+    let base = base_url(config);
+    let ws_base = if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else {
+        format!("ws://{}", base.strip_prefix("http://").unwrap_or(&base))
+    };
+    Some(format!("{}/socket?api_key={}&deviceId=bayin-app", ws_base, token))
+}
+
+/// 向会话接口 POST（上报能力、播放状态）
+pub async fn post_session(
+    client: &Client,
+    config: &StreamServerConfig,
+    endpoint: &str,
+    body: &serde_json::Value,
+) -> Result<(), CommandError> {
+    let url = format!("{}{}", base_url(config), endpoint);
+    let mut req = client.post(&url).json(body);
+    for (k, v) in &build_auth_header(config) {
+        req = req.header(k.as_str(), v.as_str());
+    }
+
+    let response = req.send().await.map_err(CommandError::request)?;
+    if !response.status().is_success() {
+        return Err(CommandError::http(response.status()));
+    }
+    Ok(())
+}
+
 /// 获取歌词
 pub async fn get_lyrics(config: &StreamServerConfig, song_id: &str) -> Option<String> {
     let _token = config.access_token.as_deref()?;
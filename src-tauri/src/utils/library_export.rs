@@ -0,0 +1,160 @@
+//! 曲库导出为 JSON / CSV
+//!
+//! 供外部编目或在表格软件中批量整理标签使用。只导出元数据，流媒体歌曲的
+//! `stream_info`（含服务器凭据）不会写入文件。
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbSong;
+
+const FORMAT: &str = "bayin-library";
+const VERSION: u32 = 1;
+
+/// Output file format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryExportFormat {
+    Json,
+    Csv,
+}
+
+/// One exported song; field names double as the CSV header
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedSong<'a> {
+    id: &'a str,
+    title: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    album_artist: Option<&'a str>,
+    composer: Option<&'a str>,
+    year: Option<u32>,
+    genre: Option<&'a str>,
+    track_no: Option<u32>,
+    disc_no: Option<u32>,
+    duration: f64,
+    source_type: &'a str,
+    file_path: &'a str,
+    file_size: i64,
+    format: Option<&'a str>,
+    bit_depth: Option<u8>,
+    sample_rate: Option<u32>,
+    bitrate: Option<u32>,
+    channels: Option<u8>,
+    server_song_id: Option<&'a str>,
+    rating: u8,
+    play_count: i64,
+    last_played_at: Option<i64>,
+    created_at: Option<i64>,
+    dynamic_range: Option<f32>,
+}
+
+/// CSV header, in the order of `ExportedSong`
+const CSV_HEADER: &[&str] = &[
+    "id", "title", "artist", "album", "albumArtist", "composer", "year", "genre", "trackNo", "discNo",
+    "duration", "sourceType", "filePath", "fileSize", "format", "bitDepth", "sampleRate", "bitrate",
+    "channels", "serverSongId", "rating", "playCount", "lastPlayedAt", "createdAt", "dynamicRange",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryFile<'a> {
+    format: &'static str,
+    version: u32,
+    songs: Vec<ExportedSong<'a>>,
+}
+
+impl<'a> From<&'a DbSong> for ExportedSong<'a> {
+    fn from(song: &'a DbSong) -> Self {
+        Self {
+            id: &song.id,
+            title: &song.title,
+            artist: &song.artist,
+            album: &song.album,
+            album_artist: song.album_artist.as_deref(),
+            composer: song.composer.as_deref(),
+            year: song.year,
+            genre: song.genre.as_deref(),
+            track_no: song.track_no,
+            disc_no: song.disc_no,
+            duration: song.duration,
+            source_type: &song.source_type,
+            file_path: &song.file_path,
+            file_size: song.file_size,
+            format: song.format.as_deref(),
+            bit_depth: song.bit_depth,
+            sample_rate: song.sample_rate,
+            bitrate: song.bitrate,
+            channels: song.channels,
+            server_song_id: song.server_song_id.as_deref(),
+            rating: song.rating,
+            play_count: song.play_count,
+            last_played_at: song.last_played_at,
+            created_at: song.created_at,
+            dynamic_range: song.dynamic_range,
+        }
+    }
+}
+
+/// Serialize songs in the given format
+pub fn export_library(songs: &[DbSong], format: LibraryExportFormat) -> Result<String, String> {
+    let songs: Vec<ExportedSong> = songs.iter().map(ExportedSong::from).collect();
+    match format {
+        LibraryExportFormat::Json => {
+            let file = LibraryFile { format: FORMAT, version: VERSION, songs };
+            serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+        }
+        LibraryExportFormat::Csv => Ok(to_csv(&songs)),
+    }
+}
+
+fn to_csv(songs: &[ExportedSong]) -> String {
+    // BOM 让 Excel 按 UTF-8 打开，中文不会乱码
+    let mut out = String::from("\u{feff}");
+    out.push_str(&CSV_HEADER.join(","));
+    out.push_str("\r\n");
+
+    for song in songs {
+        let text = |value: Option<&str>| value.map(csv_field).unwrap_or_default();
+        let number = |value: Option<String>| value.unwrap_or_default();
+        let fields = [
+            csv_field(song.id),
+            csv_field(song.title),
+            csv_field(song.artist),
+            csv_field(song.album),
+            text(song.album_artist),
+            text(song.composer),
+            number(song.year.map(|v| v.to_string())),
+            text(song.genre),
+            number(song.track_no.map(|v| v.to_string())),
+            number(song.disc_no.map(|v| v.to_string())),
+            format!("{:.3}", song.duration),
+            csv_field(song.source_type),
+            csv_field(song.file_path),
+            song.file_size.to_string(),
+            text(song.format),
+            number(song.bit_depth.map(|v| v.to_string())),
+            number(song.sample_rate.map(|v| v.to_string())),
+            number(song.bitrate.map(|v| v.to_string())),
+            number(song.channels.map(|v| v.to_string())),
+            text(song.server_song_id),
+            song.rating.to_string(),
+            song.play_count.to_string(),
+            number(song.last_played_at.map(|v| v.to_string())),
+            number(song.created_at.map(|v| v.to_string())),
+            number(song.dynamic_range.map(|v| format!("{:.1}", v))),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Quote a field when it contains a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
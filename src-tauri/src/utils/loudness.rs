@@ -0,0 +1,225 @@
+//! EBU R128 响度分析：积分响度 (LUFS) 与真峰值
+//!
+//! 按 ITU-R BS.1770-4：K 加权滤波后以 400ms 块（75% 重叠）计算响度，
+//! 先用 -70 LUFS 绝对门限，再用低于均值 10 LU 的相对门限。
+//! 真峰值通过 4 倍过采样估算。
+
+use std::f64::consts::PI;
+
+use crate::audio_engine::decoder::AudioDecoder;
+
+/// Gating blocks advance in 100 ms steps and span four of them
+const STEP_SECS: f64 = 0.1;
+const STEPS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+/// Loudness metrics of one track
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessMetrics {
+    /// Integrated loudness (LUFS)
+    pub integrated_lufs: f32,
+    /// True peak (linear, 1.0 = full scale)
+    pub true_peak: f32,
+}
+
+/// Second order IIR section, direct form I
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// The two K-weighting stages (high shelf, high pass) for a sample rate
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    [shelf, high_pass]
+}
+
+/// Channel weights; the LFE is left out and surrounds count +1.5 dB
+fn channel_weights(channels: usize) -> Vec<f64> {
+    (0..channels)
+        .map(|channel| match (channels, channel) {
+            (6 | 8, 3) => 0.0,
+            (6 | 8, 4..) => 1.41,
+            _ => 1.0,
+        })
+        .collect()
+}
+
+/// Windowed-sinc interpolation filter, split into one phase per oversampled position
+fn oversampling_phases() -> Vec<[f64; TAPS_PER_PHASE]> {
+    let len = OVERSAMPLING * TAPS_PER_PHASE;
+    let center = (len - 1) as f64 / 2.0;
+    let coefficient = |n: usize| {
+        let x = (n as f64 - center) / OVERSAMPLING as f64;
+        let sinc = if x.abs() < 1e-9 { 1.0 } else { (PI * x).sin() / (PI * x) };
+        let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / (len - 1) as f64).cos();
+        sinc * window
+    };
+
+    (0..OVERSAMPLING)
+        .map(|phase| std::array::from_fn(|tap| coefficient(tap * OVERSAMPLING + phase)))
+        .collect()
+}
+
+struct ChannelState {
+    filters: [Biquad; 2],
+    /// Sum of squares of the K-weighted signal in the running step
+    step_sum_sq: f64,
+    /// Recent input samples for the true peak interpolation, newest first
+    history: [f64; TAPS_PER_PHASE],
+    peak: f64,
+}
+
+impl ChannelState {
+    fn new(sample_rate: u32) -> Self {
+        Self { filters: k_weighting(sample_rate), step_sum_sq: 0.0, history: [0.0; TAPS_PER_PHASE], peak: 0.0 }
+    }
+
+    fn push(&mut self, sample: f64, phases: &[[f64; TAPS_PER_PHASE]]) {
+        let weighted = self.filters.iter_mut().fold(sample, |s, filter| filter.process(s));
+        self.step_sum_sq += weighted * weighted;
+
+        self.history.rotate_right(1);
+        self.history[0] = sample;
+        for phase in phases {
+            let interpolated: f64 = phase.iter().zip(&self.history).map(|(h, x)| h * x).sum();
+            self.peak = self.peak.max(interpolated.abs());
+        }
+        self.peak = self.peak.max(sample.abs());
+    }
+}
+
+struct Meter {
+    channels: Vec<ChannelState>,
+    weights: Vec<f64>,
+    step_frames: usize,
+    frame_in_step: usize,
+    /// Weighted mean square of each finished 100 ms step
+    steps: Vec<f64>,
+    /// Channel peaks of earlier formats, after a mid-stream format change
+    peak: f64,
+}
+
+impl Meter {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            channels: (0..channels).map(|_| ChannelState::new(sample_rate)).collect(),
+            weights: channel_weights(channels),
+            step_frames: ((sample_rate as f64 * STEP_SECS) as usize).max(1),
+            frame_in_step: 0,
+            steps: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Start over with a new format, keeping the measured steps and peak.
+    /// The partial step is dropped.
+    fn reconfigure(&mut self, sample_rate: u32, channels: usize) {
+        let steps = std::mem::take(&mut self.steps);
+        let peak = self.peak();
+        *self = Self::new(sample_rate, channels);
+        self.steps = steps;
+        self.peak = peak;
+    }
+
+    fn push_frame(&mut self, frame: &[f32], phases: &[[f64; TAPS_PER_PHASE]]) {
+        for (channel, &sample) in self.channels.iter_mut().zip(frame) {
+            channel.push(sample as f64, phases);
+        }
+        self.frame_in_step += 1;
+        if self.frame_in_step == self.step_frames {
+            let energy = self
+                .channels
+                .iter_mut()
+                .zip(&self.weights)
+                .map(|(channel, weight)| weight * std::mem::take(&mut channel.step_sum_sq))
+                .sum::<f64>();
+            self.steps.push(energy / self.step_frames as f64);
+            self.frame_in_step = 0;
+        }
+    }
+
+    fn peak(&self) -> f64 {
+        self.channels.iter().map(|c| c.peak).fold(self.peak, f64::max)
+    }
+
+    fn integrated_lufs(&self) -> Option<f64> {
+        let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(STEPS_PER_BLOCK)
+            .map(|window| window.iter().sum::<f64>() / STEPS_PER_BLOCK as f64)
+            .filter(|&energy| energy > 0.0 && loudness(energy) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+        let relative_gate = loudness(mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks.into_iter().filter(|&energy| loudness(energy) > relative_gate).collect();
+        (!gated.is_empty()).then(|| loudness(mean(&gated)))
+    }
+}
+
+/// Decode a local file and measure its integrated loudness and true peak
+pub fn analyze_file(path: &str) -> Result<LoudnessMetrics, String> {
+    let mut decoder = AudioDecoder::open(path).map_err(|e| e.to_string())?;
+    let mut channels = decoder.info.channels.max(1);
+    let mut meter = Meter::new(decoder.info.sample_rate, channels);
+    let phases = oversampling_phases();
+
+    while let Some(samples) = decoder.decode_next().map_err(|e| e.to_string())? {
+        if decoder.take_spec_change() {
+            channels = decoder.info.channels.max(1);
+            meter.reconfigure(decoder.info.sample_rate, channels);
+        }
+        for frame in samples.chunks_exact(channels) {
+            meter.push_frame(frame, &phases);
+        }
+    }
+
+    let integrated = meter
+        .integrated_lufs()
+        .ok_or_else(|| "无法分析：音频过短或全部静音".to_string())?;
+    Ok(LoudnessMetrics { integrated_lufs: integrated as f32, true_peak: meter.peak() as f32 })
+}
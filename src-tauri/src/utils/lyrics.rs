@@ -0,0 +1,249 @@
+//! 歌词格式转换
+//!
+//! 前端只解析 LRC，所以 TTML（Apple Music 风格）和 SRT/WebVTT 字幕在这里统一转成 LRC。
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// 歌词文本格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LyricFormat {
+    Lrc,
+    Ttml,
+    Srt,
+}
+
+/// A converted line; `end_ms` is used to insert blank lines in long gaps
+struct TimedLine {
+    start_ms: i64,
+    end_ms: Option<i64>,
+    text: String,
+}
+
+/// Gap after a line's end before a blank line is inserted to clear the display
+const BLANK_GAP_MS: i64 = 1500;
+
+/// LRC 时间标签，如 `[01:02.34]`
+pub fn format_lrc_timestamp(start_ms: i64) -> String {
+    let total_ms = start_ms.max(0);
+    let minute = total_ms / 60_000;
+    let second = (total_ms % 60_000) / 1000;
+    let hundredth = (total_ms % 1000) / 10;
+    format!("[{minute:02}:{second:02}.{hundredth:02}]")
+}
+
+/// 根据内容判断歌词格式
+pub fn detect_format(content: &str) -> LyricFormat {
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with("<?xml") || head.starts_with("<tt") {
+        return LyricFormat::Ttml;
+    }
+    if head.starts_with("WEBVTT") || content.lines().take(5).any(|line| line.contains("-->")) {
+        return LyricFormat::Srt;
+    }
+    LyricFormat::Lrc
+}
+
+/// 把任意支持的格式转成 LRC；无法识别或解析失败时原样返回
+pub fn to_lrc(content: &str) -> String {
+    let converted = match detect_format(content) {
+        LyricFormat::Lrc => None,
+        LyricFormat::Ttml => ttml_to_lrc(content),
+        LyricFormat::Srt => srt_to_lrc(content),
+    };
+    converted.unwrap_or_else(|| content.to_string())
+}
+
+/// TTML → LRC。每个 `<p>` 为一行；逐字 `<span>` 合并，背景人声（`ttm:role="x-bg"`）忽略
+pub fn ttml_to_lrc(content: &str) -> Option<String> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(false);
+
+    let mut lines: Vec<TimedLine> = Vec::new();
+    let mut current: Option<TimedLine> = None;
+    // One entry per open <span> inside a line: whether it is background vocals
+    let mut span_stack: Vec<bool> = Vec::new();
+
+    loop {
+        let event = reader.read_event().ok()?;
+        let in_background = span_stack.iter().any(|bg| *bg);
+
+        match event {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    let start_ms = attribute(&e, "begin").and_then(|v| parse_ttml_time(&v));
+                    let end_ms = attribute(&e, "end").and_then(|v| parse_ttml_time(&v));
+                    span_stack.clear();
+                    current = start_ms.map(|start_ms| TimedLine { start_ms, end_ms, text: String::new() });
+                }
+                b"span" if current.is_some() => {
+                    let is_bg = attribute(&e, "ttm:role").is_some_and(|role| role == "x-bg");
+                    span_stack.push(is_bg);
+                }
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"br" => {
+                if let Some(line) = current.as_mut() {
+                    line.text.push(' ');
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    if let Some(mut line) = current.take() {
+                        line.text = collapse_whitespace(&line.text);
+                        if !line.text.is_empty() {
+                            lines.push(line);
+                        }
+                    }
+                }
+                b"span" => {
+                    span_stack.pop();
+                }
+                _ => {}
+            },
+            Event::Text(text) if !in_background => {
+                if let Some(line) = current.as_mut() {
+                    line.text.push_str(&text.decode().ok()?);
+                }
+            }
+            Event::CData(text) if !in_background => {
+                if let Some(line) = current.as_mut() {
+                    line.text.push_str(&text.decode().ok()?);
+                }
+            }
+            Event::GeneralRef(reference) if !in_background => {
+                if let Some(line) = current.as_mut() {
+                    let resolved = match reference.resolve_char_ref().ok()? {
+                        Some(c) => Some(c),
+                        None => match reference.decode().ok()?.as_ref() {
+                            "amp" => Some('&'),
+                            "lt" => Some('<'),
+                            "gt" => Some('>'),
+                            "quot" => Some('"'),
+                            "apos" => Some('\''),
+                            _ => None,
+                        },
+                    };
+                    if let Some(c) = resolved {
+                        line.text.push(c);
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    build_lrc(lines)
+}
+
+/// SRT / WebVTT → LRC。每个字幕块为一行，多行文本用空格连接
+pub fn srt_to_lrc(content: &str) -> Option<String> {
+    let normalized = content.replace("\r\n", "\n");
+    let mut lines: Vec<TimedLine> = Vec::new();
+
+    for block in normalized.split("\n\n") {
+        let mut block_lines = block.lines().map(str::trim).filter(|line| !line.is_empty());
+        let Some(timing) = block_lines.by_ref().find(|line| line.contains("-->")) else {
+            continue;
+        };
+
+        let mut parts = timing.split("-->");
+        let Some(start_ms) = parts.next().and_then(parse_srt_time) else {
+            continue;
+        };
+        // WebVTT may append cue settings after the end time
+        let end_ms = parts
+            .next()
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(parse_srt_time);
+
+        let text = block_lines.map(strip_markup).collect::<Vec<_>>().join(" ");
+        let text = collapse_whitespace(&text);
+        if !text.is_empty() {
+            lines.push(TimedLine { start_ms, end_ms, text });
+        }
+    }
+
+    build_lrc(lines)
+}
+
+fn build_lrc(mut lines: Vec<TimedLine>) -> Option<String> {
+    if lines.is_empty() {
+        return None;
+    }
+    lines.sort_by_key(|line| line.start_ms);
+
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        output.push(format!("{}{}", format_lrc_timestamp(line.start_ms), line.text));
+
+        let next_start = lines.get(index + 1).map(|next| next.start_ms);
+        if let Some(end_ms) = line.end_ms {
+            if next_start.is_none_or(|next| next - end_ms >= BLANK_GAP_MS) {
+                output.push(format_lrc_timestamp(end_ms));
+            }
+        }
+    }
+
+    Some(output.join("\n"))
+}
+
+fn attribute(e: &quick_xml::events::BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// TTML 时间：`hh:mm:ss.fff`、`mm:ss.fff`、`12.5s`、`1200ms` 或纯秒数
+fn parse_ttml_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let seconds = if let Some(ms) = value.strip_suffix("ms") {
+        ms.parse::<f64>().ok()? / 1000.0
+    } else if let Some(s) = value.strip_suffix('s') {
+        s.parse::<f64>().ok()?
+    } else if let Some(m) = value.strip_suffix('m') {
+        m.parse::<f64>().ok()? * 60.0
+    } else if let Some(h) = value.strip_suffix('h') {
+        h.parse::<f64>().ok()? * 3600.0
+    } else if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        let (hours, minutes, seconds) = match parts.as_slice() {
+            [h, m, s] => (h.parse::<f64>().ok()?, m.parse::<f64>().ok()?, s.parse::<f64>().ok()?),
+            [m, s] => (0.0, m.parse::<f64>().ok()?, s.parse::<f64>().ok()?),
+            _ => return None,
+        };
+        hours * 3600.0 + minutes * 60.0 + seconds
+    } else {
+        value.parse::<f64>().ok()?
+    };
+
+    (seconds.is_finite() && seconds >= 0.0).then(|| (seconds * 1000.0).round() as i64)
+}
+
+/// SRT 时间 `00:01:02,345`，WebVTT 时间 `00:01:02.345` 或 `01:02.345`
+fn parse_srt_time(value: &str) -> Option<i64> {
+    parse_ttml_time(&value.trim().replace(',', "."))
+        .filter(|_| value.contains(':'))
+}
+
+/// Remove `<i>`/`<b>`/`<c.x>` tags and `{\an8}` style overrides
+fn strip_markup(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut skip_until: Option<char> = None;
+    for c in line.chars() {
+        match skip_until {
+            Some(end) if c == end => skip_until = None,
+            Some(_) => {}
+            None if c == '<' => skip_until = Some('>'),
+            None if c == '{' => skip_until = Some('}'),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
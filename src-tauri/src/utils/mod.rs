@@ -1,4 +1,21 @@
 pub mod audio;
+pub mod chapters;
 pub mod jellyfin;
 pub mod subsonic;
 pub mod cover;
+pub mod cuesheet;
+pub mod pinyin;
+pub mod lyrics;
+pub mod sidecar;
+pub mod tak;
+pub mod fingerprint;
+pub mod walk;
+pub mod path_template;
+pub mod organize;
+pub mod server_backup;
+pub mod library_export;
+pub mod playlist_file;
+pub mod dynamics;
+pub mod loudness;
+pub mod secret;
+pub mod tag_writer;
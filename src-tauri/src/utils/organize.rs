@@ -0,0 +1,133 @@
+//! 按标签整理文件：由目录/文件名模板生成目标路径
+//!
+//! 模板按 `/` 分段，例如 `{albumartist}/{year} - {album}/{track} {title}`。
+//! 可用占位符：`{albumartist}` `{artist}` `{album}` `{title}` `{track}` `{disc}`
+//! `{year}` `{genre}` `{ext}`；文件名未写 `{ext}` 时自动补上原扩展名。
+
+use std::path::{Path, PathBuf};
+
+use lofty::prelude::*;
+use lofty::probe::Probe;
+
+const PLACEHOLDERS: &[&str] = &[
+    "albumartist", "artist", "album", "title", "track", "disc", "year", "genre", "ext",
+];
+
+/// Longest file or directory name written, in characters
+const MAX_SEGMENT_CHARS: usize = 120;
+
+/// Tag values a target path is built from
+#[derive(Debug, Default, Clone)]
+pub struct OrganizeTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: Option<String>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+}
+
+/// 检查模板：占位符必须已知，且不能跳出音乐文件夹
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let segments: Vec<&str> = template.split('/').map(str::trim).filter(|s| !s.is_empty()).collect();
+    let Some(file_name) = segments.last() else {
+        return Err("模板不能为空".to_string());
+    };
+    if segments.iter().any(|s| *s == "." || *s == "..") {
+        return Err("模板不能包含 . 或 .. 目录".to_string());
+    }
+    for segment in &segments {
+        let mut rest = *segment;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map(|end| end + start)
+                .ok_or_else(|| format!("占位符未闭合: {}", segment))?;
+            let name = &rest[start + 1..end];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(format!("未知占位符: {{{}}}", name));
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    // Without the title or track every file of an album would get the same name
+    if !file_name.contains("{title}") && !file_name.contains("{track}") {
+        return Err("文件名部分需包含 {title} 或 {track}".to_string());
+    }
+    Ok(())
+}
+
+/// Track and disc numbers straight from the file's tags
+pub fn read_track_numbers(path: &Path) -> (Option<u32>, Option<u32>) {
+    let Ok(tagged_file) = Probe::open(path).and_then(|p| p.read()) else {
+        return (None, None);
+    };
+    let tag = tagged_file.primary_tag().or_else(|| tagged_file.first_tag());
+    (
+        tag.and_then(|t| t.track()).filter(|n| *n > 0),
+        tag.and_then(|t| t.disk()).filter(|n| *n > 0),
+    )
+}
+
+/// Target path of a file relative to its music folder; the template must be valid.
+pub fn render(template: &str, tags: &OrganizeTags, extension: &str) -> PathBuf {
+    let segments: Vec<&str> = template.split('/').map(str::trim).filter(|s| !s.is_empty()).collect();
+    let mut path = PathBuf::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        let is_file_name = index + 1 == segments.len();
+        let mut name = sanitize(&fill(segment, tags, extension));
+        if is_file_name && !segment.contains("{ext}") && !extension.is_empty() {
+            name = format!("{}.{}", name, extension);
+        }
+        path.push(name);
+    }
+
+    path
+}
+
+fn fill(segment: &str, tags: &OrganizeTags, extension: &str) -> String {
+    let number = |n: Option<u32>| n.map(|n| format!("{:02}", n)).unwrap_or_default();
+    let album_artist = tags
+        .album_artist
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(&tags.artist);
+
+    segment
+        .replace("{albumartist}", album_artist)
+        .replace("{artist}", &tags.artist)
+        .replace("{album}", &tags.album)
+        .replace("{title}", &tags.title)
+        .replace("{track}", &number(tags.track))
+        .replace("{disc}", &tags.disc.map(|d| d.to_string()).unwrap_or_default())
+        .replace("{year}", &tags.year.map(|y| y.to_string()).unwrap_or_default())
+        .replace("{genre}", tags.genre.as_deref().unwrap_or_default())
+        .replace("{ext}", extension)
+}
+
+/// Make a tag value safe as a single file or directory name on every platform
+fn sanitize(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_SEGMENT_CHARS)
+        .collect();
+
+    // Separators left over from empty placeholders, e.g. "{year} - {album}" without a year;
+    // trailing dots and spaces aren't allowed on Windows
+    let trimmed = replaced
+        .trim_start_matches([' ', '-'])
+        .trim_end_matches([' ', '-', '.']);
+    if trimmed.is_empty() {
+        "_".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
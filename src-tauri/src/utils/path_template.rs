@@ -0,0 +1,124 @@
+//! 文件名模板：从目录/文件名解析未打标签文件的元数据
+//!
+//! 模板按 `/` 分段，从右往左与路径末尾的目录名和文件名（不含扩展名）逐段匹配，
+//! 例如 `{artist}/{album}/{track} - {title}`。可用占位符：
+//! `{artist}` `{album}` `{title}` `{track}` `{year}`，其余文字按字面匹配。
+
+use std::path::{Component, Path};
+
+use regex::Regex;
+
+/// Fields recovered from a file path
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PathMetadata {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    /// Only matched so it doesn't end up in the title; not stored
+    pub track: Option<u32>,
+    pub year: Option<u32>,
+}
+
+const PLACEHOLDERS: &[&str] = &["artist", "album", "title", "track", "year"];
+
+/// One compiled template: a regex per path segment, outermost directory first
+struct CompiledTemplate {
+    segments: Vec<Regex>,
+}
+
+/// Filename templates compiled once per scan
+#[derive(Default)]
+pub struct PathTemplates {
+    templates: Vec<CompiledTemplate>,
+}
+
+impl PathTemplates {
+    /// 编译模板，无效模板直接忽略
+    pub fn compile(templates: &[String]) -> Self {
+        let templates = templates
+            .iter()
+            .filter_map(|template| {
+                let segments = template
+                    .split('/')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(segment_regex)
+                    .collect::<Option<Vec<_>>>()?;
+                (!segments.is_empty()).then_some(CompiledTemplate { segments })
+            })
+            .collect();
+
+        Self { templates }
+    }
+
+    /// 第一个能匹配的模板的解析结果
+    pub fn parse(&self, path: &Path) -> Option<PathMetadata> {
+        let stem = path.file_stem()?.to_str()?;
+        let mut components: Vec<&str> = path
+            .parent()
+            .map(|dir| {
+                dir.components()
+                    .filter_map(|c| match c {
+                        Component::Normal(name) => name.to_str(),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        components.push(stem);
+
+        self.templates.iter().find_map(|template| template.parse(&components))
+    }
+}
+
+impl CompiledTemplate {
+    fn parse(&self, components: &[&str]) -> Option<PathMetadata> {
+        let offset = components.len().checked_sub(self.segments.len())?;
+        let mut meta = PathMetadata::default();
+
+        for (regex, component) in self.segments.iter().zip(&components[offset..]) {
+            let caps = regex.captures(component)?;
+            let field = |name: &str| {
+                caps.name(name)
+                    .map(|m| m.as_str().trim().to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            meta.artist = field("artist").or(meta.artist);
+            meta.album = field("album").or(meta.album);
+            meta.title = field("title").or(meta.title);
+            meta.track = field("track").and_then(|s| s.parse().ok()).or(meta.track);
+            meta.year = field("year").and_then(|s| s.parse().ok()).or(meta.year);
+        }
+
+        Some(meta)
+    }
+}
+
+/// Build an anchored regex for one segment such as `{track} - {title}`
+fn segment_regex(segment: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut seen = Vec::new();
+    let mut rest = segment;
+
+    while let Some(start) = rest.find('{') {
+        pattern.push_str(&regex::escape(&rest[..start]));
+        let end = rest[start..].find('}')? + start;
+        let name = &rest[start + 1..end];
+        // 未知占位符或同一段内重复的占位符视为无效模板
+        if !PLACEHOLDERS.contains(&name) || seen.contains(&name) {
+            return None;
+        }
+        seen.push(name);
+        let group = match name {
+            "track" => r"\d{1,3}",
+            "year" => r"\d{4}",
+            _ => ".+?",
+        };
+        pattern.push_str(&format!("\\s*(?P<{}>{})\\s*", name, group));
+        rest = &rest[end + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+
+    Regex::new(&pattern).ok()
+}
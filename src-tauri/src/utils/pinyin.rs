@@ -0,0 +1,56 @@
+//! Pinyin initials for Chinese text, used by the search index
+//!
+//! GB2312 level-1 hanzi (the 3755 most common characters) are ordered by pinyin,
+//! so the initial letter can be read off the GB2312 code range without a dictionary.
+//! Level-2 and traditional-only characters have no pinyin order and are skipped.
+
+use encoding_rs::GB18030;
+
+/// First GB2312 code of each initial letter (i, u and v never start a syllable)
+const INITIAL_STARTS: [(u16, char); 23] = [
+    (0xB0A1, 'a'), (0xB0C5, 'b'), (0xB2C1, 'c'), (0xB4EE, 'd'), (0xB6EA, 'e'),
+    (0xB7A2, 'f'), (0xB8C1, 'g'), (0xB9FE, 'h'), (0xBBF7, 'j'), (0xBFA6, 'k'),
+    (0xC0AC, 'l'), (0xC2E8, 'm'), (0xC4C3, 'n'), (0xC5B6, 'o'), (0xC5BE, 'p'),
+    (0xC6DA, 'q'), (0xC8BB, 'r'), (0xC8F6, 's'), (0xCBFA, 't'), (0xCDDA, 'w'),
+    (0xCEF4, 'x'), (0xD1B9, 'y'), (0xD4D1, 'z'),
+];
+
+/// Last GB2312 level-1 code
+const LEVEL1_END: u16 = 0xD7F9;
+
+/// Pinyin initial of a single hanzi, if it is a GB2312 level-1 character
+pub fn initial_of(c: char) -> Option<char> {
+    if c.is_ascii() {
+        return None;
+    }
+    let mut buf = [0u8; 4];
+    let (bytes, _, had_errors) = GB18030.encode(c.encode_utf8(&mut buf));
+    if had_errors || bytes.len() != 2 {
+        return None;
+    }
+    let code = u16::from_be_bytes([bytes[0], bytes[1]]);
+    if !(INITIAL_STARTS[0].0..=LEVEL1_END).contains(&code) {
+        return None;
+    }
+    INITIAL_STARTS
+        .iter()
+        .rev()
+        .find(|(start, _)| code >= *start)
+        .map(|(_, letter)| *letter)
+}
+
+/// Initials of a string, e.g. "周杰伦" -> "zjl".
+/// ASCII letters/digits are kept (lowercased); other characters act as word breaks.
+pub fn initials(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if let Some(initial) = initial_of(c) {
+            out.push(initial);
+        } else if !out.ends_with(' ') && !out.is_empty() {
+            out.push(' ');
+        }
+    }
+    out.trim_end().to_string()
+}
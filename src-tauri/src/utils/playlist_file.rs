@@ -0,0 +1,140 @@
+//! 播放列表文件（M3U8 / XSPF）
+//!
+//! 本地歌曲可以写成相对于播放列表文件所在目录的路径，音乐目录连同播放列表
+//! 一起拷走（U 盘、车机）后仍然可用；流媒体歌曲写为串流地址。
+
+use std::path::{Component, Path, PathBuf};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+
+/// Characters kept as-is in a URI path segment (RFC 3986 unreserved)
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Playlist file format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    M3u8,
+    Xspf,
+}
+
+/// Where an entry is played from
+pub enum EntryLocation {
+    File(PathBuf),
+    Url(String),
+}
+
+pub struct PlaylistEntry {
+    pub location: EntryLocation,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: f64,
+}
+
+/// Render a playlist. With `base_dir`, local files are written relative to it
+/// (falling back to absolute paths when they share no common root).
+pub fn render(format: PlaylistFormat, title: Option<&str>, entries: &[PlaylistEntry], base_dir: Option<&Path>) -> String {
+    match format {
+        PlaylistFormat::M3u8 => render_m3u8(title, entries, base_dir),
+        PlaylistFormat::Xspf => render_xspf(title, entries, base_dir),
+    }
+}
+
+fn render_m3u8(title: Option<&str>, entries: &[PlaylistEntry], base_dir: Option<&Path>) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    if let Some(title) = title {
+        content.push_str(&format!("#PLAYLIST:{}\n", title));
+    }
+    for entry in entries {
+        let location = match &entry.location {
+            EntryLocation::File(path) => base_dir
+                .and_then(|base| relative_path(path, base))
+                .unwrap_or_else(|| path.clone())
+                .to_string_lossy()
+                .into_owned(),
+            EntryLocation::Url(url) => url.clone(),
+        };
+        content.push_str(&format!(
+            "#EXTINF:{},{} - {}\n{}\n",
+            entry.duration.round() as i64,
+            entry.artist,
+            entry.title,
+            location
+        ));
+    }
+    content
+}
+
+fn render_xspf(title: Option<&str>, entries: &[PlaylistEntry], base_dir: Option<&Path>) -> String {
+    let mut content = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(title) = title {
+        content.push_str(&format!("  <title>{}</title>\n", escape(title)));
+    }
+    content.push_str("  <trackList>\n");
+    for entry in entries {
+        let location = match &entry.location {
+            EntryLocation::File(path) => match base_dir.and_then(|base| relative_path(path, base)) {
+                Some(relative) => encode_segments(&relative),
+                None => file_uri(path),
+            },
+            EntryLocation::Url(url) => url.clone(),
+        };
+        content.push_str("    <track>\n");
+        content.push_str(&format!("      <location>{}</location>\n", escape(location.as_str())));
+        content.push_str(&format!("      <title>{}</title>\n", escape(entry.title.as_str())));
+        content.push_str(&format!("      <creator>{}</creator>\n", escape(entry.artist.as_str())));
+        content.push_str(&format!("      <album>{}</album>\n", escape(entry.album.as_str())));
+        content.push_str(&format!("      <duration>{}</duration>\n", (entry.duration * 1000.0).round() as i64));
+        content.push_str("    </track>\n");
+    }
+    content.push_str("  </trackList>\n</playlist>\n");
+    content
+}
+
+/// `path` relative to `base`, or None when they share no common root (other drive)
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 || !path.first().is_some_and(|c| matches!(c, Component::Prefix(_) | Component::RootDir)) {
+        return None;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component.as_os_str());
+    }
+    Some(relative)
+}
+
+/// Percent-encoded path segments joined with `/`
+fn encode_segments(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(utf8_percent_encode(&name.to_string_lossy(), SEGMENT).to_string()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `file:///` URI of an absolute path (drive letters stay unencoded: `file:///C:/...`)
+fn file_uri(path: &Path) -> String {
+    let drive = path.components().find_map(|component| match component {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+        _ => None,
+    });
+    match drive {
+        Some(drive) => format!("file:///{}/{}", drive, encode_segments(path)),
+        None => format!("file:///{}", encode_segments(path)),
+    }
+}
@@ -0,0 +1,97 @@
+//! 本机凭据加密
+//!
+//! 流媒体服务器密码用每台设备独立生成的密钥（数据库旁的 `secret.key`）经
+//! XChaCha20-Poly1305 加密后再写入 SQLite，数据库文件单独被拷走时密码不会泄露。
+//! 密文带 `enc:v1:` 前缀，旧版本留下的明文在读取时原样返回，并在启动时补加密。
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+
+static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Load the per-install key from `path`, creating it on first run
+pub fn load_key(path: &Path) -> Result<(), String> {
+    if KEY.get().is_some() {
+        return Ok(());
+    }
+
+    let key = match std::fs::read(path) {
+        Ok(bytes) => <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| "密钥文件已损坏".to_string())?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut key = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            write_key_file(path, &key).map_err(|e| format!("无法创建密钥文件: {}", e))?;
+            key
+        }
+        Err(e) => return Err(format!("无法读取密钥文件: {}", e)),
+    };
+
+    let _ = KEY.set(key);
+    Ok(())
+}
+
+fn write_key_file(path: &Path, key: &[u8; 32]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(key)
+}
+
+fn cipher() -> Result<XChaCha20Poly1305, String> {
+    KEY.get()
+        .map(|key| XChaCha20Poly1305::new(key.into()))
+        .ok_or_else(|| "密钥未加载".to_string())
+}
+
+/// Whether a stored value is already encrypted
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Encrypt a secret for storage. Empty values stay empty.
+pub fn seal(plaintext: &str) -> Result<String, String> {
+    if plaintext.is_empty() || is_sealed(plaintext) {
+        return Ok(plaintext.to_string());
+    }
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher()?
+        .encrypt(XNonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "加密失败".to_string())?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", PREFIX, BASE64.encode(payload)))
+}
+
+/// Decrypt a stored secret; legacy plaintext passes through unchanged
+pub fn open(stored: &str) -> Result<String, String> {
+    let Some(encoded) = stored.strip_prefix(PREFIX) else {
+        return Ok(stored.to_string());
+    };
+
+    let payload = BASE64.decode(encoded).map_err(|_| "密文格式无效".to_string())?;
+    if payload.len() < NONCE_LEN {
+        return Err("密文格式无效".to_string());
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let plaintext = cipher()?
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "解密失败，密钥文件可能已更换".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
@@ -0,0 +1,110 @@
+//! 流媒体服务器配置的导入导出
+//!
+//! 导出为 JSON 文件。设置密码时，服务器列表用 Argon2id 从密码派生的密钥
+//! 经 XChaCha20-Poly1305 加密，文件中只保留 salt、nonce 和密文。
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::db::StreamServerInput;
+
+const FORMAT: &str = "bayin-servers";
+const VERSION: u32 = 1;
+
+/// Exported file; either `servers` or the encrypted fields are set
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupFile {
+    format: String,
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    servers: Option<Vec<StreamServerInput>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("密钥派生失败: {}", e))?;
+    Ok(key)
+}
+
+/// Serialize servers for export; an empty password means no encryption
+pub fn export_servers(servers: &[StreamServerInput], password: Option<&str>) -> Result<String, String> {
+    let mut file = BackupFile {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        servers: None,
+        salt: None,
+        nonce: None,
+        data: None,
+    };
+
+    match password.filter(|p| !p.is_empty()) {
+        Some(password) => {
+            let mut salt = [0u8; 16];
+            let mut nonce = [0u8; 24];
+            rand::thread_rng().fill_bytes(&mut salt);
+            rand::thread_rng().fill_bytes(&mut nonce);
+
+            let key = derive_key(password, &salt)?;
+            let plaintext = serde_json::to_vec(servers).map_err(|e| e.to_string())?;
+            let ciphertext = XChaCha20Poly1305::new(&key.into())
+                .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+                .map_err(|_| "加密失败".to_string())?;
+
+            file.salt = Some(BASE64.encode(salt));
+            file.nonce = Some(BASE64.encode(nonce));
+            file.data = Some(BASE64.encode(ciphertext));
+        }
+        None => file.servers = Some(servers.to_vec()),
+    }
+
+    serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+}
+
+/// Parse an exported file, decrypting it with `password` if it is encrypted
+pub fn import_servers(content: &str, password: Option<&str>) -> Result<Vec<StreamServerInput>, String> {
+    let file: BackupFile = serde_json::from_str(content).map_err(|_| "不是有效的服务器配置文件".to_string())?;
+    if file.format != FORMAT {
+        return Err("不是有效的服务器配置文件".to_string());
+    }
+    if file.version > VERSION {
+        return Err("配置文件版本过新，请先更新应用".to_string());
+    }
+
+    if let Some(servers) = file.servers {
+        return Ok(servers);
+    }
+
+    let (Some(salt), Some(nonce), Some(data)) = (file.salt, file.nonce, file.data) else {
+        return Err("配置文件内容不完整".to_string());
+    };
+    let password = password.filter(|p| !p.is_empty()).ok_or("该配置文件已加密，请输入密码")?;
+
+    let decode = |value: &str| BASE64.decode(value).map_err(|_| "配置文件已损坏".to_string());
+    let salt = decode(&salt)?;
+    let nonce = decode(&nonce)?;
+    let data = decode(&data)?;
+    if nonce.len() != 24 {
+        return Err("配置文件已损坏".to_string());
+    }
+
+    let key = derive_key(password, &salt)?;
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(XNonce::from_slice(&nonce), data.as_slice())
+        .map_err(|_| "密码错误或配置文件已损坏".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|_| "配置文件已损坏".to_string())
+}
@@ -0,0 +1,112 @@
+//! 专辑目录旁的辅助文件（Kodi / Jellyfin 约定）
+//!
+//! - `album.nfo` / `artist.nfo`：补充标签里缺失的年份与流派
+//! - `cover.jpg`、`folder.png` 等：音频文件没有内嵌封面时使用
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::{Path, PathBuf};
+
+/// 封面文件名（不含扩展名），按优先级排列
+const ARTWORK_NAMES: &[&str] = &["cover", "folder", "front", "album", "albumart", "poster"];
+
+/// 封面文件扩展名
+const ARTWORK_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Metadata read from `album.nfo` (falling back to `artist.nfo` for genres)
+#[derive(Debug, Clone, Default)]
+pub struct AlbumNfo {
+    pub year: Option<u32>,
+    pub genres: Vec<String>,
+}
+
+/// 查找目录中的封面图片（文件名不区分大小写）
+pub fn find_folder_artwork(dir: &Path) -> Option<PathBuf> {
+    let entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+
+    ARTWORK_NAMES.iter().find_map(|name| {
+        entries.iter().find(|path| {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default();
+            stem.eq_ignore_ascii_case(name)
+                && ARTWORK_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e))
+        }).cloned()
+    })
+}
+
+/// 读取音频文件所在目录的 `album.nfo`，流派缺失时再看上一级目录的 `artist.nfo`
+pub fn read_album_nfo(audio_path: &Path) -> Option<AlbumNfo> {
+    let album_dir = audio_path.parent()?;
+    let album = read_nfo(&album_dir.join("album.nfo"), "album");
+    let artist = album_dir
+        .parent()
+        .and_then(|artist_dir| read_nfo(&artist_dir.join("artist.nfo"), "artist"));
+
+    match (album, artist) {
+        (None, None) => None,
+        (album, artist) => {
+            let mut nfo = album.unwrap_or_default();
+            if nfo.genres.is_empty() {
+                nfo.genres = artist.map(|a| a.genres).unwrap_or_default();
+            }
+            Some(nfo)
+        }
+    }
+}
+
+/// Parse the top-level `<year>`/`<genre>` (and `<premiered>`/`<releasedate>`) of a Kodi NFO
+fn read_nfo(path: &Path, root: &str) -> Option<AlbumNfo> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut reader = Reader::from_str(&content);
+    reader.config_mut().trim_text(true);
+
+    let mut nfo = AlbumNfo::default();
+    let mut stack: Vec<String> = Vec::new();
+    let mut found_root = false;
+
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+                if stack.is_empty() && name == root {
+                    found_root = true;
+                }
+                stack.push(name);
+            }
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(text) if stack.len() == 2 && found_root => {
+                let value = text.decode().ok()?.trim().to_string();
+                if value.is_empty() {
+                    continue;
+                }
+                match stack[1].as_str() {
+                    "year" => nfo.year = nfo.year.or_else(|| parse_year(&value)),
+                    "premiered" | "releasedate" => nfo.year = nfo.year.or_else(|| parse_year(&value)),
+                    "genre" => nfo.genres.extend(
+                        value.split(['/', ';']).map(str::trim).filter(|g| !g.is_empty()).map(String::from),
+                    ),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    found_root.then_some(nfo)
+}
+
+/// "2019" 或 "2019-05-01" 中的年份
+fn parse_year(value: &str) -> Option<u32> {
+    value
+        .get(..4)
+        .and_then(|year| year.parse::<u32>().ok())
+        .filter(|year| *year > 0)
+}
@@ -7,13 +7,14 @@ use reqwest::Client;
 use serde::Deserialize;
 
 use crate::models::{
-    ConnectionTestResult, GetAlbumListResponse, GetAlbumResponse, StreamServerConfig, PingResponse,
+    ConnectionTestResult, GetAlbumListResponse, GetAlbumResponse, GetBookmarksResponse, GetSimilarSongs2Response, StreamServerConfig, PingResponse,
     ScannedSong, SearchResponse, SubsonicResponse, SubsonicSong,
 };
+use crate::error::{CommandError, ErrorCode};
 use crate::utils::audio::extract_filename_from_path_str;
 
 /// 无损音频格式
-const LOSSLESS_SUFFIXES: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff", "alac"];
+const LOSSLESS_SUFFIXES: &[&str] = &["flac", "wav", "ape", "aiff", "dsf", "dff", "alac", "tta", "wv", "tak"];
 
 /// 生成 Subsonic API 认证参数
 fn generate_auth_params(config: &StreamServerConfig) -> Vec<(&str, String)> {
@@ -50,48 +51,26 @@ pub async fn test_connection(config: &StreamServerConfig) -> ConnectionTestResul
     match client.get(&url).query(&params).send().await {
         Ok(response) => {
             if !response.status().is_success() {
-                return ConnectionTestResult {
-                    success: false,
-                    message: format!("服务器返回错误: {}", response.status()),
-                    server_version: None,
-                };
+                return ConnectionTestResult::failed(CommandError::http(response.status()));
             }
 
             match response.json::<SubsonicResponse<PingResponse>>().await {
                 Ok(data) => {
                     let inner = data.subsonic_response;
                     if inner.status == "ok" {
-                        ConnectionTestResult {
-                            success: true,
-                            message: "连接成功".to_string(),
-                            server_version: Some(inner.version),
-                        }
+                        ConnectionTestResult::ok("连接成功", Some(inner.version))
                     } else if let Some(error) = inner.error {
-                        ConnectionTestResult {
-                            success: false,
-                            message: format!("认证失败: {}", error.message),
-                            server_version: None,
-                        }
+                        ConnectionTestResult::failed(
+                            CommandError::new(ErrorCode::AuthFailed).with("details", error.message),
+                        )
                     } else {
-                        ConnectionTestResult {
-                            success: false,
-                            message: "未知错误".to_string(),
-                            server_version: None,
-                        }
+                        ConnectionTestResult::failed(CommandError::new(ErrorCode::Unknown))
                     }
                 }
-                Err(e) => ConnectionTestResult {
-                    success: false,
-                    message: format!("解析响应失败: {}", e),
-                    server_version: None,
-                },
+                Err(e) => ConnectionTestResult::failed(CommandError::parse(e)),
             }
         }
-        Err(e) => ConnectionTestResult {
-            success: false,
-            message: format!("连接失败: {}", e),
-            server_version: None,
-        },
+        Err(e) => ConnectionTestResult::failed(CommandError::request(e)),
     }
 }
 
@@ -132,6 +111,12 @@ fn convert_song(song: &SubsonicSong, config: &StreamServerConfig) -> ScannedSong
             .clone()
             .unwrap_or_else(|| "未知艺术家".to_string()),
         album: song.album.clone().unwrap_or_else(|| "未知专辑".to_string()),
+        album_artist: song.display_album_artist.clone().filter(|s| !s.is_empty()),
+        composer: song.display_composer.clone().filter(|s| !s.is_empty()),
+        year: song.year.filter(|y| *y > 0),
+        genre: song.genre.clone().filter(|s| !s.is_empty()),
+        track_no: song.track.filter(|n| *n > 0),
+        disc_no: song.disc_number.filter(|n| *n > 0),
         duration: song.duration.unwrap_or(0) as f64,
         file_path: song.path.clone().unwrap_or_default(),
         file_size: song.size.unwrap_or(0),
@@ -143,11 +128,23 @@ fn convert_song(song: &SubsonicSong, config: &StreamServerConfig) -> ScannedSong
         sample_rate: song.sampling_rate,
         bitrate: song.bit_rate,
         channels: None,
+        replay_gain: song.replay_gain.as_ref().and_then(|rg| rg.track_gain),
+        replay_peak: song
+            .replay_gain
+            .as_ref()
+            .and_then(|rg| rg.track_peak)
+            .filter(|p| *p > 0.0),
+        album_gain: song.replay_gain.as_ref().and_then(|rg| rg.album_gain),
+        album_peak: song
+            .replay_gain
+            .as_ref()
+            .and_then(|rg| rg.album_peak)
+            .filter(|p| *p > 0.0),
     }
 }
 
 /// 获取所有歌曲（通过搜索所有）
-pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, String> {
+pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedSong>, CommandError> {
     let client = Client::new();
     let mut all_songs = Vec::new();
 
@@ -164,19 +161,19 @@ pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedS
         .query(&params)
         .send()
         .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(CommandError::request)?;
 
     let data: SubsonicResponse<SearchResponse> = response
         .json()
         .await
-        .map_err(|e| format!("解析响应失败: {}", e))?;
+        .map_err(CommandError::parse)?;
 
     let inner = data.subsonic_response;
     if inner.status != "ok" {
         if let Some(error) = inner.error {
-            return Err(format!("API 错误: {}", error.message));
+            return Err(CommandError::server(error.message));
         }
-        return Err("未知错误".to_string());
+        return Err(CommandError::new(ErrorCode::Unknown));
     }
 
     if let Some(search_result) = inner.data {
@@ -192,10 +189,48 @@ pub async fn fetch_all_songs(config: &StreamServerConfig) -> Result<Vec<ScannedS
     Ok(all_songs)
 }
 
+/// 按关键词搜索歌曲 (search3)
+pub async fn search_songs(config: &StreamServerConfig, query: &str, count: u32) -> Result<Vec<ScannedSong>, CommandError> {
+    let client = Client::new();
+    let url = build_url(config, "search3");
+    let mut params = generate_auth_params(config);
+    params.push(("query", query.to_string()));
+    params.push(("songCount", count.to_string()));
+    params.push(("albumCount", "0".to_string()));
+    params.push(("artistCount", "0".to_string()));
+
+    let response = client
+        .get(&url)
+        .query(&params)
+        .send()
+        .await
+        .map_err(CommandError::request)?;
+
+    let data: SubsonicResponse<SearchResponse> = response
+        .json()
+        .await
+        .map_err(CommandError::parse)?;
+
+    let inner = data.subsonic_response;
+    if inner.status != "ok" {
+        if let Some(error) = inner.error {
+            return Err(CommandError::server(error.message));
+        }
+        return Err(CommandError::new(ErrorCode::Unknown));
+    }
+
+    Ok(inner
+        .data
+        .and_then(|d| d.search_result3)
+        .and_then(|r| r.song)
+        .map(|songs| songs.iter().map(|s| convert_song(s, config)).collect())
+        .unwrap_or_default())
+}
+
 /// 获取专辑列表
 pub async fn fetch_albums(
     config: &StreamServerConfig,
-) -> Result<Vec<crate::models::SubsonicAlbum>, String> {
+) -> Result<Vec<crate::models::SubsonicAlbum>, CommandError> {
     let client = Client::new();
     let url = build_url(config, "getAlbumList2");
     let mut params = generate_auth_params(config);
@@ -207,19 +242,19 @@ pub async fn fetch_albums(
         .query(&params)
         .send()
         .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(CommandError::request)?;
 
     let data: SubsonicResponse<GetAlbumListResponse> = response
         .json()
         .await
-        .map_err(|e| format!("解析响应失败: {}", e))?;
+        .map_err(CommandError::parse)?;
 
     let inner = data.subsonic_response;
     if inner.status != "ok" {
         if let Some(error) = inner.error {
-            return Err(format!("API 错误: {}", error.message));
+            return Err(CommandError::server(error.message));
         }
-        return Err("未知错误".to_string());
+        return Err(CommandError::new(ErrorCode::Unknown));
     }
 
     if let Some(album_list_data) = inner.data {
@@ -235,7 +270,7 @@ pub async fn fetch_albums(
 pub async fn fetch_album_songs(
     config: &StreamServerConfig,
     album_id: &str,
-) -> Result<Vec<ScannedSong>, String> {
+) -> Result<Vec<ScannedSong>, CommandError> {
     let client = Client::new();
     let url = build_url(config, "getAlbum");
     let mut params = generate_auth_params(config);
@@ -246,19 +281,19 @@ pub async fn fetch_album_songs(
         .query(&params)
         .send()
         .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(CommandError::request)?;
 
     let data: SubsonicResponse<GetAlbumResponse> = response
         .json()
         .await
-        .map_err(|e| format!("解析响应失败: {}", e))?;
+        .map_err(CommandError::parse)?;
 
     let inner = data.subsonic_response;
     if inner.status != "ok" {
         if let Some(error) = inner.error {
-            return Err(format!("API 错误: {}", error.message));
+            return Err(CommandError::server(error.message));
         }
-        return Err("未知错误".to_string());
+        return Err(CommandError::new(ErrorCode::Unknown));
     }
 
     if let Some(album_data) = inner.data {
@@ -272,6 +307,123 @@ pub async fn fetch_album_songs(
     Ok(Vec::new())
 }
 
+/// 获取与某首歌相似的歌曲 ID（按服务器推荐顺序）
+pub async fn fetch_similar_songs(
+    config: &StreamServerConfig,
+    song_id: &str,
+    count: u32,
+) -> Result<Vec<String>, CommandError> {
+    let client = Client::new();
+    let url = build_url(config, "getSimilarSongs2");
+    let mut params = generate_auth_params(config);
+    params.push(("id", song_id.to_string()));
+    params.push(("count", count.to_string()));
+
+    let response = client
+        .get(&url)
+        .query(&params)
+        .send()
+        .await
+        .map_err(CommandError::request)?;
+
+    let data: SubsonicResponse<GetSimilarSongs2Response> = response
+        .json()
+        .await
+        .map_err(CommandError::parse)?;
+
+    let inner = data.subsonic_response;
+    if inner.status != "ok" {
+        if let Some(error) = inner.error {
+            return Err(CommandError::server(error.message));
+        }
+        return Err(CommandError::new(ErrorCode::Unknown));
+    }
+
+    Ok(inner
+        .data
+        .and_then(|d| d.similar_songs2)
+        .and_then(|s| s.song)
+        .map(|songs| songs.into_iter().map(|s| s.id).collect())
+        .unwrap_or_default())
+}
+
+/// 获取当前用户的书签：(歌曲 ID, 播放位置毫秒)
+pub async fn fetch_bookmarks(config: &StreamServerConfig) -> Result<Vec<(String, i64)>, CommandError> {
+    let client = Client::new();
+    let url = build_url(config, "getBookmarks");
+    let params = generate_auth_params(config);
+
+    let response = client
+        .get(&url)
+        .query(&params)
+        .send()
+        .await
+        .map_err(CommandError::request)?;
+
+    let data: SubsonicResponse<GetBookmarksResponse> = response
+        .json()
+        .await
+        .map_err(CommandError::parse)?;
+
+    let inner = data.subsonic_response;
+    if inner.status != "ok" {
+        if let Some(error) = inner.error {
+            return Err(CommandError::server(error.message));
+        }
+        return Err(CommandError::new(ErrorCode::Unknown));
+    }
+
+    Ok(inner
+        .data
+        .and_then(|d| d.bookmarks)
+        .and_then(|b| b.bookmark)
+        .map(|bookmarks| bookmarks.into_iter().map(|b| (b.entry.id, b.position)).collect())
+        .unwrap_or_default())
+}
+
+/// 创建或更新书签
+pub async fn create_bookmark(config: &StreamServerConfig, song_id: &str, position_ms: i64) -> Result<(), CommandError> {
+    let mut params = generate_auth_params(config);
+    params.push(("id", song_id.to_string()));
+    params.push(("position", position_ms.to_string()));
+    send_action(config, "createBookmark", &params).await
+}
+
+/// 删除书签
+pub async fn delete_bookmark(config: &StreamServerConfig, song_id: &str) -> Result<(), CommandError> {
+    let mut params = generate_auth_params(config);
+    params.push(("id", song_id.to_string()));
+    send_action(config, "deleteBookmark", &params).await
+}
+
+/// 调用只返回状态的接口
+async fn send_action(config: &StreamServerConfig, endpoint: &str, params: &[(&str, String)]) -> Result<(), CommandError> {
+    let client = Client::new();
+    let url = build_url(config, endpoint);
+
+    let response = client
+        .get(&url)
+        .query(params)
+        .send()
+        .await
+        .map_err(CommandError::request)?;
+
+    let data: SubsonicResponse<PingResponse> = response
+        .json()
+        .await
+        .map_err(CommandError::parse)?;
+
+    let inner = data.subsonic_response;
+    if inner.status != "ok" {
+        if let Some(error) = inner.error {
+            return Err(CommandError::server(error.message));
+        }
+        return Err(CommandError::new(ErrorCode::Unknown));
+    }
+
+    Ok(())
+}
+
 /// 获取歌曲流 URL
 pub fn get_stream_url(config: &StreamServerConfig, song_id: &str) -> String {
     let base = config.server_url.trim_end_matches('/');
@@ -0,0 +1,76 @@
+//! 把编辑后的标签写回本地音频文件
+//!
+//! 写入文件的主标签（ID3v2、Vorbis Comments、MP4 ilst 等）；文件还没有标签时
+//! 按格式新建一个。空的可选字段会从标签中删除。
+
+use std::path::Path;
+
+use lofty::config::WriteOptions;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::Tag;
+use serde::Deserialize;
+
+/// Tag values edited by the user
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagEdit {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub track_no: Option<u32>,
+}
+
+impl TagEdit {
+    /// Trimmed values; empty optional text and zero numbers become None
+    pub fn normalized(self) -> Self {
+        Self {
+            title: self.title.trim().to_string(),
+            artist: self.artist.trim().to_string(),
+            album: self.album.trim().to_string(),
+            genre: self.genre.map(|g| g.trim().to_string()).filter(|g| !g.is_empty()),
+            year: self.year.filter(|y| *y > 0),
+            track_no: self.track_no.filter(|n| *n > 0),
+        }
+    }
+}
+
+/// Write `edit` into the tags of the file at `path`
+pub fn write_tags(path: &Path, edit: &TagEdit) -> Result<(), String> {
+    let mut tagged_file = Probe::open(path)
+        .and_then(|p| p.read())
+        .map_err(|e| format!("无法读取文件标签: {}", e))?;
+
+    let tag_type = match tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
+        Some(tag) => tag.tag_type(),
+        None => {
+            let tag_type = tagged_file.primary_tag_type();
+            tagged_file.insert_tag(Tag::new(tag_type));
+            tag_type
+        }
+    };
+    let tag = tagged_file
+        .tag_mut(tag_type)
+        .ok_or_else(|| "该格式不支持写入标签".to_string())?;
+
+    tag.set_title(edit.title.clone());
+    tag.set_artist(edit.artist.clone());
+    tag.set_album(edit.album.clone());
+    match &edit.genre {
+        Some(genre) => tag.set_genre(genre.clone()),
+        None => tag.remove_genre(),
+    }
+    match edit.year {
+        Some(year) => tag.set_year(year),
+        None => tag.remove_year(),
+    }
+    match edit.track_no {
+        Some(track) => tag.set_track(track),
+        None => tag.remove_track(),
+    }
+
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| format!("写入标签失败: {}", e))
+}
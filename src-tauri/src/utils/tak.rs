@@ -0,0 +1,185 @@
+//! TAK（Tom's lossless Audio Kompressor）文件信息
+//!
+//! lofty 与 symphonia 都不识别 TAK，这里只读取扫描需要的部分：
+//! STREAMINFO 元数据块（采样率、位深、声道、总采样数）和文件末尾的 APEv2 标签。
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const STREAMINFO: u8 = 1;
+const END: u8 = 0;
+const SAMPLE_RATE_MIN: u32 = 6000;
+const BPS_MIN: u32 = 8;
+const APE_FOOTER_LEN: u64 = 32;
+const ID3V1_LEN: u64 = 128;
+/// 标签大小上限，防止损坏的文件头导致超大分配
+const APE_TAG_MAX: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct TakInfo {
+    pub sample_rate: u32,
+    pub bit_depth: u32,
+    pub channels: u8,
+    pub samples: u64,
+    /// APEv2 文本标签，键为大写
+    pub tags: Vec<(String, String)>,
+    /// `Cover Art (Front)` 中的图片数据
+    pub cover: Option<Vec<u8>>,
+}
+
+impl TakInfo {
+    pub fn duration_secs(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.samples as f64 / self.sample_rate as f64
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+}
+
+pub fn read_info(path: &Path) -> Result<TakInfo, String> {
+    let mut file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).map_err(|e| e.to_string())?;
+    if magic != *b"tBaK" {
+        return Err("不是有效的 TAK 文件".to_string());
+    }
+
+    let mut info = TakInfo::default();
+    loop {
+        let mut header = [0u8; 4];
+        file.read_exact(&mut header).map_err(|e| e.to_string())?;
+        let kind = header[0] & 0x7F;
+        let size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+        if kind == END {
+            break;
+        }
+        if kind == STREAMINFO {
+            let mut block = vec![0u8; size];
+            file.read_exact(&mut block).map_err(|e| e.to_string())?;
+            parse_streaminfo(&block, &mut info)?;
+            break;
+        }
+        file.seek(SeekFrom::Current(size as i64)).map_err(|e| e.to_string())?;
+    }
+    if info.sample_rate == 0 {
+        return Err("TAK 文件缺少 STREAMINFO".to_string());
+    }
+
+    // 标签损坏不影响扫描
+    let _ = read_ape_tag(&mut file, &mut info);
+    Ok(info)
+}
+
+/// STREAMINFO 按位从低到高排列
+fn parse_streaminfo(block: &[u8], info: &mut TakInfo) -> Result<(), String> {
+    let mut bits = LsbReader { data: block, pos: 0 };
+    // 编码器版本与配置、帧长
+    bits.skip(6 + 4 + 4);
+    let samples = bits.read(35).ok_or("STREAMINFO 过短")?;
+    // 数据类型
+    bits.skip(3);
+    let sample_rate = bits.read(18).ok_or("STREAMINFO 过短")? as u32 + SAMPLE_RATE_MIN;
+    let bit_depth = bits.read(5).ok_or("STREAMINFO 过短")? as u32 + BPS_MIN;
+    let channels = bits.read(4).ok_or("STREAMINFO 过短")? as u8 + 1;
+    info.samples = samples;
+    info.sample_rate = sample_rate;
+    info.bit_depth = bit_depth;
+    info.channels = channels;
+    Ok(())
+}
+
+struct LsbReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl LsbReader<'_> {
+    fn skip(&mut self, bits: usize) {
+        self.pos += bits;
+    }
+
+    fn read(&mut self, bits: usize) -> Option<u64> {
+        let mut value = 0u64;
+        for i in 0..bits {
+            let byte = *self.data.get((self.pos + i) / 8)?;
+            value |= (((byte >> ((self.pos + i) % 8)) & 1) as u64) << i;
+        }
+        self.pos += bits;
+        Some(value)
+    }
+}
+
+/// 读取文件末尾（ID3v1 之前）的 APEv2 标签
+fn read_ape_tag(file: &mut File, info: &mut TakInfo) -> Result<(), String> {
+    let len = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    let mut footer = [0u8; APE_FOOTER_LEN as usize];
+    let mut footer_end = len;
+    for end in [len, len.saturating_sub(ID3V1_LEN)] {
+        if end < APE_FOOTER_LEN {
+            continue;
+        }
+        file.seek(SeekFrom::Start(end - APE_FOOTER_LEN)).map_err(|e| e.to_string())?;
+        file.read_exact(&mut footer).map_err(|e| e.to_string())?;
+        if &footer[..8] == b"APETAGEX" {
+            footer_end = end;
+            break;
+        }
+    }
+    if &footer[..8] != b"APETAGEX" {
+        return Ok(());
+    }
+
+    let le32 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    // 大小包含尾部但不包含头部
+    let tag_size = le32(&footer[12..16]) as u64;
+    let item_count = le32(&footer[16..20]);
+    if !(APE_FOOTER_LEN..=APE_TAG_MAX).contains(&tag_size) || tag_size > footer_end {
+        return Err("APE 标签大小无效".to_string());
+    }
+    let mut items = vec![0u8; (tag_size - APE_FOOTER_LEN) as usize];
+    file.seek(SeekFrom::Start(footer_end - tag_size)).map_err(|e| e.to_string())?;
+    file.read_exact(&mut items).map_err(|e| e.to_string())?;
+
+    let mut rest = &items[..];
+    for _ in 0..item_count {
+        if rest.len() < 8 {
+            break;
+        }
+        let value_len = le32(&rest[0..4]) as usize;
+        let flags = le32(&rest[4..8]);
+        let Some(key_len) = rest[8..].iter().position(|&b| b == 0) else {
+            break;
+        };
+        let key = String::from_utf8_lossy(&rest[8..8 + key_len]).to_uppercase();
+        let value_start = 8 + key_len + 1;
+        let Some(value) = rest.get(value_start..value_start + value_len) else {
+            break;
+        };
+        // 位 1-2：0 为 UTF-8 文本，1 为二进制
+        match (flags >> 1) & 0b11 {
+            0 => {
+                // 多个值以 NUL 分隔
+                let text = String::from_utf8_lossy(value).replace('\0', "; ");
+                info.tags.push((key, text));
+            }
+            1 if key == "COVER ART (FRONT)" => {
+                // 文件名 + NUL + 图片数据
+                if let Some(name_len) = value.iter().position(|&b| b == 0) {
+                    info.cover = Some(value[name_len + 1..].to_vec());
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[value_start + value_len..];
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! 扫描目录遍历
+//!
+//! 跟随符号链接，但记录已访问目录的规范路径：指回祖先目录的链接（循环）或
+//! 重复指向同一目录的链接只遍历一次，跳过的目录会出现在扫描报告中。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use super::audio::is_audio_file;
+
+/// Depth cap, a last-resort guard against pathological link chains
+const MAX_DEPTH: usize = 64;
+
+/// Audio files found under the scan directories
+#[derive(Debug, Default)]
+pub struct CollectedFiles {
+    pub audio_paths: Vec<PathBuf>,
+    /// Directories not entered because they would revisit an already
+    /// scanned directory (symlink cycles, duplicate links)
+    pub skipped_cycles: Vec<String>,
+}
+
+/// 收集目录下的所有音频文件
+pub fn collect_audio_files(directories: &[String]) -> CollectedFiles {
+    let mut collected = CollectedFiles::default();
+    let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+    let roots: HashSet<PathBuf> = directories.iter().filter_map(|dir| std::fs::canonicalize(dir).ok()).collect();
+
+    for dir in directories {
+        let dir_path = Path::new(dir);
+        if !dir_path.exists() {
+            continue;
+        }
+
+        let mut walker = WalkDir::new(dir_path)
+            .follow_links(true)
+            .max_depth(MAX_DEPTH)
+            .into_iter();
+
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    // walkdir reports links back to an ancestor as errors
+                    if err.loop_ancestor().is_some() {
+                        if let Some(path) = err.path() {
+                            collected.skipped_cycles.push(path.to_string_lossy().to_string());
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            if entry.file_type().is_dir() {
+                let canonical = std::fs::canonicalize(entry.path())
+                    .unwrap_or_else(|_| entry.path().to_path_buf());
+                if !visited_dirs.insert(canonical.clone()) {
+                    // Overlapping scan roots are skipped silently
+                    if entry.depth() > 0 && !roots.contains(&canonical) {
+                        collected.skipped_cycles.push(entry.path().to_string_lossy().to_string());
+                    }
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+
+            let path = entry.path();
+            if path.is_file() && is_audio_file(path) {
+                collected.audio_paths.push(path.to_path_buf());
+            }
+        }
+    }
+
+    collected
+}
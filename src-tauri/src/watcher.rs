@@ -3,9 +3,9 @@
 
 #[cfg(desktop)]
 pub mod desktop {
-    use std::collections::HashSet;
-    use std::path::PathBuf;
-    use std::sync::{Arc, Mutex};
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, OnceLock};
     use std::time::{Duration, Instant};
 
     use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
@@ -13,8 +13,10 @@ pub mod desktop {
 
     use crate::commands::CoverCacheState;
     use crate::db::{self, DbState, SongInput};
-    use crate::utils::audio;
-    use crate::utils::cover::extract_and_cache_cover;
+    use crate::utils::{audio, fingerprint};
+    use crate::utils::cover::extract_and_cache_covers;
+    use crate::utils::{chapters, cuesheet};
+    use crate::utils::path_template::PathTemplates;
 
     /// Shared state for the file watcher
     pub struct WatcherState {
@@ -34,6 +36,45 @@ pub mod desktop {
     /// Managed Tauri state wrapper
     pub struct FileWatcherState(pub Mutex<WatcherState>);
 
+    /// How long changes to a file the app wrote itself are still ignored afterwards
+    const SUPPRESS_GRACE: Duration = Duration::from_secs(2);
+
+    /// Files being written by the app, ignored until the deadline (None while writing)
+    static SUPPRESSED: OnceLock<Mutex<HashMap<PathBuf, Option<Instant>>>> = OnceLock::new();
+
+    fn suppressed() -> &'static Mutex<HashMap<PathBuf, Option<Instant>>> {
+        SUPPRESSED.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    /// Ignores watcher events for a file the app is about to modify, so the
+    /// change doesn't trigger a rescan. Lasts until shortly after the guard drops.
+    pub struct SuppressGuard(PathBuf);
+
+    impl Drop for SuppressGuard {
+        fn drop(&mut self) {
+            if let Ok(mut map) = suppressed().lock() {
+                map.insert(self.0.clone(), Some(Instant::now() + SUPPRESS_GRACE));
+            }
+        }
+    }
+
+    /// Suppress watcher events for `path` while the returned guard is alive
+    pub fn suppress(path: &Path) -> SuppressGuard {
+        if let Ok(mut map) = suppressed().lock() {
+            map.insert(path.to_path_buf(), None);
+        }
+        SuppressGuard(path.to_path_buf())
+    }
+
+    fn is_suppressed(path: &Path) -> bool {
+        let Ok(mut map) = suppressed().lock() else {
+            return false;
+        };
+        let now = Instant::now();
+        map.retain(|_, deadline| deadline.is_none_or(|d| d > now));
+        map.contains_key(path)
+    }
+
     /// Start watching directories for file changes
     pub fn start_watching(
         app_handle: &AppHandle,
@@ -148,22 +189,25 @@ pub mod desktop {
         Ok(())
     }
 
+    /// Whether a watcher is running, and the directories it watches
+    pub fn watch_status(app_handle: &AppHandle) -> (bool, Vec<String>) {
+        let watcher_state: tauri::State<'_, FileWatcherState> = app_handle.state();
+        let status = match watcher_state.0.lock() {
+            Ok(state) => (state.watcher.is_some(), state.watched_dirs.clone()),
+            Err(_) => (false, Vec::new()),
+        };
+        status
+    }
+
     /// Process changed files: mini incremental scan
     fn process_changed_files(app_handle: &AppHandle, paths: &[PathBuf]) {
         let db_state: tauri::State<'_, DbState> = app_handle.state();
-        let cover_cache_state: tauri::State<'_, CoverCacheState> = app_handle.state();
-
-        // Get cover cache for processing
-        let cover_cache = match cover_cache_state.0.lock() {
-            Ok(c) => c.clone_arc(),
-            Err(_) => return,
-        };
 
         // Separate existing files from deleted files
         let mut to_scan: Vec<&PathBuf> = Vec::new();
         let mut to_delete: Vec<String> = Vec::new();
 
-        for path in paths {
+        for path in paths.iter().filter(|p| !is_suppressed(p)) {
             if path.exists() && path.is_file() && audio::is_audio_file(path) {
                 to_scan.push(path);
             } else if !path.exists() {
@@ -176,53 +220,22 @@ pub mod desktop {
 
         // Scan new/modified files
         if !to_scan.is_empty() {
-            let song_inputs: Vec<SongInput> = to_scan
-                .iter()
-                .filter_map(|path| {
-                    audio::read_metadata_with_mtime(path).ok().map(|song| {
-                        // Extract and cache cover
-                        let cover_hash = extract_and_cache_cover(path, &cover_cache).ok().flatten();
-                        SongInput {
-                            id: song.id,
-                            title: song.title,
-                            artist: song.artist,
-                            album: song.album,
-                            duration: song.duration,
-                            file_path: song.file_path,
-                            file_size: song.file_size as i64,
-                            is_hr: song.is_hr,
-                            is_sq: song.is_sq,
-                            cover_hash,
-                            server_song_id: None,
-                            stream_info: None,
-                            file_modified: Some(song.file_modified),
-                            format: song.format,
-                            bit_depth: song.bit_depth,
-                            sample_rate: song.sample_rate,
-                            bitrate: song.bitrate,
-                            channels: song.channels,
-                        }
-                    })
-                })
-                .collect();
+            let song_inputs = read_song_inputs(app_handle, &to_scan);
 
             if !song_inputs.is_empty() {
-                if let Ok(mut conn) = db_state.0.lock() {
+                if let Ok(mut conn) = db_state.write() {
+                    // A move shows up as a removal plus a new file with the same content
+                    let _ = db::songs::relocate_moved_songs(&mut conn, &song_inputs, &to_delete);
                     let _ = db::songs::save_songs(&mut conn, &song_inputs, "local", None);
                     changed = true;
                 }
             }
         }
 
-        // Delete removed files from DB
+        // Mark songs of removed files as missing
         if !to_delete.is_empty() {
-            if let Ok(conn) = db_state.0.lock() {
-                for path_str in &to_delete {
-                    let _ = conn.execute(
-                        "DELETE FROM songs WHERE file_path = ?1 AND source_type = 'local'",
-                        [path_str],
-                    );
-                }
+            if let Ok(conn) = db_state.write() {
+                let _ = db::songs::mark_files_missing(&conn, &to_delete);
                 changed = true;
             }
         }
@@ -232,4 +245,69 @@ pub mod desktop {
             let _ = app_handle.emit("library-updated", ());
         }
     }
+
+    /// Read tags and cache covers for individual audio files, ready for `save_songs`
+    pub fn read_song_inputs(app_handle: &AppHandle, paths: &[&PathBuf]) -> Vec<SongInput> {
+        let db_state: tauri::State<'_, DbState> = app_handle.state();
+        let cover_cache_state: tauri::State<'_, CoverCacheState> = app_handle.state();
+
+        // Get cover cache for processing
+        let cover_cache = match cover_cache_state.0.lock() {
+            Ok(c) => c.clone_arc(),
+            Err(_) => return Vec::new(),
+        };
+
+        let templates = match db_state.read() {
+            Ok(conn) => db::settings::filename_templates(&conn).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        let templates = PathTemplates::compile(&templates);
+
+        paths
+            .iter()
+            .filter_map(|path| {
+                audio::read_metadata_with_mtime(path, &templates).ok().map(|song| {
+                    // Extract and cache embedded pictures (main cover + typed extras)
+                    let covers = extract_and_cache_covers(path, &cover_cache).unwrap_or_default();
+                    // Content fingerprint: change detection and recognizing moved files
+                    let content_hash = fingerprint::partial_content_hash(path).ok();
+                    SongInput {
+                        id: song.id,
+                        title: song.title,
+                        artist: song.artist,
+                        album: song.album,
+                        album_artist: song.album_artist,
+                        composer: song.composer,
+                        year: song.year,
+                        genre: song.genre,
+                        track_no: song.track_no,
+                        disc_no: song.disc_no,
+                        duration: song.duration,
+                        file_path: song.file_path,
+                        file_size: song.file_size as i64,
+                        is_hr: song.is_hr,
+                        is_sq: song.is_sq,
+                        cover_hash: covers.cover_hash,
+                        content_hash,
+                        server_song_id: None,
+                        stream_info: None,
+                        file_modified: Some(song.file_modified),
+                        format: song.format,
+                        bit_depth: song.bit_depth,
+                        sample_rate: song.sample_rate,
+                        bitrate: song.bitrate,
+                        channels: song.channels,
+                        replay_gain: song.replay_gain,
+                        replay_peak: song.replay_peak,
+                        album_gain: song.album_gain,
+                        album_peak: song.album_peak,
+                        pictures: covers.pictures,
+                        cue: None,
+                        chapters: chapters::read(path),
+                    }
+                })
+            })
+            .flat_map(cuesheet::expand)
+            .collect()
+    }
 }
@@ -1,14 +1,14 @@
 import { useCallback, useEffect, useMemo, useRef, useState, type CSSProperties, type PointerEvent as ReactPointerEvent } from "react";
 import { convertFileSrc, invoke, isTauri } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import * as ScrollArea from "@radix-ui/react-scroll-area";
 import * as Checkbox from "@radix-ui/react-checkbox";
 import * as Select from "@radix-ui/react-select";
 import { DiscIcon, ImageIcon } from "@radix-ui/react-icons";
 import { animate, motion } from "framer-motion";
-import NowPlayingPage from "./NowPlayingPage";
+import NowPlayingPage, { type UserEqualizerPreset } from "./NowPlayingPage";
 
 type Page =
   | "songs"
@@ -20,11 +20,13 @@ type Page =
   | "stats"
   | "settings"
   | "settings-ui"
-  | "settings-lyrics";
+  | "settings-lyrics"
+  | "settings-downloads";
 
 type DialogMode = "create" | "rename" | null;
 type Language = "中文" | "English";
 type FontWeightOption = "Normal" | "Medium" | "Bold";
+type NightModePreset = "light" | "medium" | "strong";
 
 interface DbSong {
   id: string;
@@ -47,17 +49,108 @@ interface DbSong {
   sampleRate?: number;
   bitrate?: number;
   channels?: number;
+  createdAt?: number;
+  albumArtist?: string;
+  year?: number;
+  albumId?: string;
+  genre?: string;
+  // 动态范围分析结果 (dB)
+  dynamicRange?: number;
+  crestFactor?: number;
+  // 星级评分 1-5，0 为未评分
+  rating?: number;
+  // 播放次数（听过一半才计数）与最近播放时间（Unix 秒）
+  playCount?: number;
+  lastPlayedAt?: number;
+  trackNo?: number;
+  discNo?: number;
+  composer?: string;
 }
 
 interface DbAlbum {
   id: string;
   name: string;
   artist: string;
+  year?: number;
+  genre?: string;
   coverHash?: string;
   streamCoverUrl?: string;
   songCount: number;
+  // 已分析曲目的平均 DR 值
+  dynamicRange?: number;
+  // 已评分曲目的平均星级
+  rating?: number;
+  // 最新一首歌的添加时间（Unix 秒）
+  createdAt?: number;
 }
 
+interface WaveformPayload {
+  songId: string;
+  peaks: number[];
+  complete: boolean;
+}
+
+interface CuePoints {
+  cueIn?: number;
+  cueOut?: number;
+}
+
+interface DynamicsProgress {
+  current: number;
+  total: number;
+  songId: string;
+}
+
+interface DynamicsAnalysisResult {
+  analyzed: number;
+  failed: number;
+}
+
+type ResamplerQuality = "fast" | "normal" | "high";
+
+interface OutputInfo {
+  deviceName: string;
+  sampleRate: number;
+  channels: number;
+  sourceSampleRate: number;
+  sourceChannels: number;
+  resampling: boolean;
+  eqEnabled: boolean;
+  otherDsp: boolean;
+  bitPerfect: boolean;
+}
+
+interface AnalysisProgress {
+  current: number;
+  total: number;
+  songId: string;
+}
+
+interface LoudnessAnalysisResult {
+  analyzed: number;
+  failed: number;
+}
+
+interface OrganizeEntry {
+  songId: string;
+  from: string;
+  to: string;
+  conflict?: string;
+}
+
+interface OrganizePreview {
+  entries: OrganizeEntry[];
+  unchanged: number;
+}
+
+interface OrganizeResult {
+  moved: number;
+  failed: number;
+  idMap: Record<string, string>;
+}
+
+const DEFAULT_ORGANIZE_TEMPLATE = "{albumartist}/{year} - {album}/{track} {title}";
+
 interface DbArtist {
   id: string;
   name: string;
@@ -80,12 +173,102 @@ interface CoverCacheStats {
   totalSizeMb: number;
 }
 
+type OfflineDownloadStatus = "queued" | "downloading" | "paused" | "done" | "failed";
+
+interface OfflineDownload {
+  songId: string;
+  title?: string;
+  artist?: string;
+  status: OfflineDownloadStatus;
+  localPath?: string;
+  totalBytes: number;
+  downloadedBytes: number;
+  error?: string;
+  // 由离线同步规则管理，不再匹配时自动删除
+  auto: boolean;
+  createdAt: number;
+}
+
+interface OfflineDownloadProgress {
+  songId: string;
+  status: OfflineDownloadStatus;
+  downloadedBytes: number;
+  totalBytes: number;
+  error?: string;
+}
+
+interface OfflineSyncRules {
+  enabled: boolean;
+  favorites: boolean;
+  recentPlaylists: number;
+  recentPlayed: number;
+  // MB，0 表示不限
+  budgetMb: number;
+  intervalMinutes: number;
+}
+
+interface OfflineSyncResult {
+  queued: number;
+  evicted: number;
+}
+
+interface NormalizationSettings {
+  enabled: boolean;
+  preampDb: number;
+  targetLufs: number;
+  limiter: boolean;
+}
+
+/** 原生引擎的输出后端；JACK 同样适用于 PipeWire（pipewire-jack） */
+interface OutputBackendSettings {
+  backend: "system" | "jack";
+  clientName: string;
+  /** 第 i 个输出声道连接到的 JACK 端口，空字符串表示自动连接 */
+  ports: string[];
+}
+
+const DEFAULT_OUTPUT_BACKEND: OutputBackendSettings = { backend: "system", clientName: "BaYin", ports: [] };
+
+function loadOutputBackendSettings(): OutputBackendSettings {
+  try {
+    const parsed = JSON.parse(localStorage.getItem("audio_output_backend") ?? "null") as Partial<OutputBackendSettings> | null;
+    if (!parsed) {
+      return DEFAULT_OUTPUT_BACKEND;
+    }
+    return {
+      backend: parsed.backend === "jack" ? "jack" : "system",
+      clientName: typeof parsed.clientName === "string" ? parsed.clientName : DEFAULT_OUTPUT_BACKEND.clientName,
+      ports: Array.isArray(parsed.ports) ? parsed.ports.filter((port): port is string => typeof port === "string") : [],
+    };
+  } catch {
+    return DEFAULT_OUTPUT_BACKEND;
+  }
+}
+
+interface ShellIntegrationStatus {
+  supported: boolean;
+  installed: boolean;
+}
+
+interface TelemetrySummary {
+  enabled: boolean;
+  uploadAvailable: boolean;
+  pending: { kind: string; name: string; count: number }[];
+}
+
+interface DownloadSettings {
+  maxConcurrent: number;
+  // KB/s，0 表示不限速
+  bandwidthLimitKbps: number;
+}
+
 interface ScanConfig {
   id: number | null;
   directories: string[];
   skipShort: boolean;
   minDuration: number;
   lastScanAt: number | null;
+  hashCheckDirectories?: string[];
 }
 
 interface LocalScanOptions {
@@ -93,16 +276,25 @@ interface LocalScanOptions {
   mode: "full" | "incremental";
   minDuration: number;
   batchSize: number;
+  hashCheckDirectories: string[];
+  dryRun?: boolean;
 }
 
 interface ScanResult {
   totalSongs: number;
   added: number;
   updated: number;
+  moved: number;
   removed: number;
   skipped: number;
   errors: number;
   durationMs: number;
+  skippedCycles: string[];
+  preview?: {
+    added: string[];
+    updated: string[];
+    removed: string[];
+  };
 }
 
 interface ScanProgress {
@@ -123,8 +315,62 @@ interface AudioStateChangedPayload {
   is_playing: boolean;
 }
 
+interface AudioTrackChangedPayload {
+  source: string;
+  song_id?: string | null;
+  duration: number;
+}
+
+interface QueueTrack {
+  source?: string;
+  songId?: string | null;
+}
+
+interface QueueSnapshot {
+  tracks: QueueTrack[];
+  currentIndex: number | null;
+  shuffle: boolean;
+  repeat: "off" | "all" | "one";
+}
+
+interface AudioDeviceChangedPayload {
+  device_name: string;
+}
+
+interface AudioBufferingPayload {
+  buffering: boolean;
+}
+
+interface AudioExportProgressPayload {
+  progress: number;
+}
+
+type AudioErrorCode =
+  | "fileNotFound"
+  | "permissionDenied"
+  | "unsupportedFormat"
+  | "unsupportedCodec"
+  | "decodeFailed"
+  | "deviceUnavailable"
+  | "networkTimeout"
+  | "networkError"
+  | "unknown";
+
 interface AudioErrorPayload {
-  message: string;
+  code: AudioErrorCode;
+  details: string;
+}
+
+interface JellyfinRemotePlayPayload {
+  songIds: string[];
+  startIndex: number;
+  startSecs: number;
+  mode: "now" | "next" | "last";
+}
+
+interface JellyfinRemoteCommandPayload {
+  name: "next" | "previous" | "setVolume";
+  value?: number;
 }
 
 interface AudioPlaybackState {
@@ -181,6 +427,7 @@ interface ConnectionTestResult {
   success: boolean;
   message: string;
   serverVersion?: string;
+  code?: string;
 }
 
 interface PlaylistStoreItem {
@@ -190,8 +437,8 @@ interface PlaylistStoreItem {
 }
 
 type PlayMode = "sequence" | "shuffle" | "repeat-one";
-type SongSortKey = "title" | "artist" | "album" | "duration" | "addedAt";
-type AlbumSortKey = "title" | "artist" | "year" | "songCount";
+type SongSortKey = "title" | "artist" | "album" | "duration" | "addedAt" | "dynamicRange" | "rating";
+type AlbumSortKey = "title" | "artist" | "year" | "songCount" | "addedAt" | "dynamicRange" | "rating";
 type ArtistSortKey = "name" | "songCount";
 type PlaylistSortKey = "addedAt" | "name" | "songCount";
 
@@ -209,7 +456,22 @@ interface ParsedLrcLine {
   text: string;
 }
 
-type LyricProvider = "qq" | "kugou" | "netease";
+/** 内置来源，或脚本插件提供的 `plugin:<id>` */
+type LyricProvider = "qq" | "kugou" | "netease" | `plugin:${string}`;
+
+interface ProviderInfo {
+  id: string;
+  name: string;
+  kinds: ("lyrics" | "cover" | "metadata")[];
+  builtin: boolean;
+  path?: string;
+}
+
+interface ProviderList {
+  providers: ProviderInfo[];
+  errors: { path: string; message: string }[];
+  scriptDir: string;
+}
 type LyricSourceMode = "local" | "online";
 
 interface OnlineLyricCandidate {
@@ -222,14 +484,23 @@ interface OnlineLyricCandidate {
   qqSongId?: number;
   neteaseSongId?: string;
   kugouSongHash?: string;
+  providerSongId?: string;
   coverUrl?: string;
 }
 
+interface TimedLyricLine {
+  timeMs: number;
+  text: string;
+}
+
 interface OnlineLyricFetchResult {
   lyric: string;
   format: string;
   provider: LyricProvider;
   raw?: string;
+  translation?: string;
+  lines: TimedLyricLine[];
+  translations: (string | null)[];
 }
 
 interface SongLyricBinding {
@@ -237,6 +508,7 @@ interface SongLyricBinding {
   qqSongId?: number;
   neteaseSongId?: string;
   kugouSongHash?: string;
+  providerSongId?: string;
   lyric?: string;
   format?: string;
   title?: string;
@@ -285,6 +557,8 @@ type IconName =
   | "palette"
   | "help-circle"
   | "download"
+  | "heart"
+  | "radio"
   | "image-off"
   | "alert"
   | "user";
@@ -383,6 +657,12 @@ const LineIcon = ({ name, className }: { name: IconName; className?: string }) =
   if (name === "download") {
     return <svg className={classes} viewBox="0 0 24 24" fill="none" aria-hidden><path d="M12 15V3" /><path d="m8 11 4 4 4-4" /><path d="M21 15v4a2 2 0 0 1-2 2H5a2 2 0 0 1-2-2v-4" /></svg>;
   }
+  if (name === "heart") {
+    return <svg className={classes} viewBox="0 0 24 24" fill="none" aria-hidden><path d="M19 14c1.49-1.46 3-3.21 3-5.5A5.5 5.5 0 0 0 16.5 3c-1.76 0-3 .5-4.5 2-1.5-1.5-2.74-2-4.5-2A5.5 5.5 0 0 0 2 8.5c0 2.3 1.5 4.05 3 5.5l7 7Z" /></svg>;
+  }
+  if (name === "radio") {
+    return <svg className={classes} viewBox="0 0 24 24" fill="none" aria-hidden><path d="M4.9 19.1C1 15.2 1 8.8 4.9 4.9" /><path d="M7.8 16.2c-2.3-2.3-2.3-6.1 0-8.5" /><circle cx="12" cy="12" r="2" /><path d="M16.2 7.8c2.3 2.3 2.3 6.1 0 8.5" /><path d="M19.1 4.9C23 8.8 23 15.1 19.1 19" /></svg>;
+  }
   if (name === "folder") {
     return <svg className={classes} viewBox="0 0 24 24" fill="none" aria-hidden><path d="M20 20a2 2 0 0 0 2-2V8a2 2 0 0 0-2-2h-7l-2-2H4a2 2 0 0 0-2 2v12a2 2 0 0 0 2 2Z" /></svg>;
   }
@@ -440,6 +720,15 @@ const PAGE_TITLE: Record<Page, string> = {
   settings: "设置",
   "settings-ui": "用户界面",
   "settings-lyrics": "在线歌词",
+  "settings-downloads": "离线下载",
+};
+
+const OFFLINE_STATUS_LABEL: Record<OfflineDownloadStatus, string> = {
+  queued: "等待中",
+  downloading: "下载中",
+  paused: "已暂停",
+  done: "已完成",
+  failed: "失败",
 };
 
 const STREAM_SERVER_TYPE_OPTIONS = [
@@ -456,6 +745,8 @@ const SONG_SORT_OPTIONS: Array<{ key: SongSortKey; label: string }> = [
   { key: "album", label: "专辑" },
   { key: "duration", label: "时长" },
   { key: "addedAt", label: "添加日期" },
+  { key: "dynamicRange", label: "动态范围" },
+  { key: "rating", label: "评分" },
 ];
 
 const ALBUM_SORT_OPTIONS: Array<{ key: AlbumSortKey; label: string }> = [
@@ -463,6 +754,9 @@ const ALBUM_SORT_OPTIONS: Array<{ key: AlbumSortKey; label: string }> = [
   { key: "artist", label: "艺术家" },
   { key: "year", label: "年份" },
   { key: "songCount", label: "歌曲数量" },
+  { key: "addedAt", label: "添加日期" },
+  { key: "dynamicRange", label: "动态范围" },
+  { key: "rating", label: "评分" },
 ];
 
 const ARTIST_SORT_OPTIONS: Array<{ key: ArtistSortKey; label: string }> = [
@@ -536,10 +830,18 @@ const DEFAULT_LYRIC_PROVIDER_ENABLED: Record<LyricProvider, boolean> = {
 };
 
 const DEFAULT_LYRIC_PROVIDER_ORDER: LyricProvider[] = ["qq", "kugou", "netease"];
+const PLUGIN_PROVIDER_PREFIX = "plugin:";
 const ARTIST_SPLIT_REGEX = /\/|、/;
 
 const EQ_MIN_GAIN = -12;
 const EQ_MAX_GAIN = 12;
+// 电台模式下剩余歌曲少于该数量时自动续接
+const RADIO_REFILL_THRESHOLD = 3;
+// 超过该时长（秒）的曲目（有声书、播客）记录续播位置，并同步为 Subsonic 书签
+const RESUME_MIN_DURATION = 20 * 60;
+const RESUME_SAVE_INTERVAL = 30;
+// 距结尾不足该秒数视为已听完，清除续播位置
+const RESUME_END_MARGIN = 15;
 const EQ_FREQUENCIES = [80, 100, 125, 250, 500, 1000, 2000, 4000, 8000, 16000] as const;
 const EQ_DEFAULT_GAINS = new Array(EQ_FREQUENCIES.length).fill(0);
 
@@ -558,6 +860,52 @@ function normalizeEqGains(value: unknown): number[] {
 }
 
 
+interface CommandErrorPayload {
+  code: string;
+  params?: Record<string, string>;
+}
+
+const PROVIDER_NAMES: Record<string, string> = {
+  qq: "QQ 音乐",
+  kugou: "酷狗",
+  netease: "网易云",
+};
+
+/** 后端错误码 → 本地化文案，{key} 由 params 填充 */
+const COMMAND_ERROR_MESSAGES: Record<string, string> = {
+  connectionFailed: "连接失败：{details}",
+  timeout: "请求超时，请检查网络或服务器地址",
+  httpStatus: "服务器返回错误：HTTP {status}",
+  authFailed: "认证失败，请检查用户名和密码",
+  serverError: "服务器错误：{message}",
+  invalidResponse: "服务器响应无法解析",
+  missingCredentials: "缺少 {field}，请先测试连接",
+  unsupportedServer: "此操作不适用于该服务器类型",
+  unsupportedSource: "不支持的来源：{source}",
+  notFound: "路径不存在：{path}",
+  notADirectory: "不是文件夹：{path}",
+  io: "文件操作失败：{details}",
+  database: "数据库错误：{details}",
+  decodeFailed: "解码失败：{details}",
+  providerFailed: "插件执行失败：{details}",
+};
+
+function isCommandError(error: unknown): error is CommandErrorPayload {
+  return typeof error === "object" && error !== null && typeof (error as CommandErrorPayload).code === "string";
+}
+
+function formatCommandError(error: CommandErrorPayload): string {
+  const params = error.params ?? {};
+  const template = COMMAND_ERROR_MESSAGES[error.code];
+  let message = template
+    ? template.replace(/\{(\w+)\}/g, (_, key: string) => params[key] ?? "")
+    : params.details ?? (error as { details?: string }).details ?? "未知错误";
+  if (params.provider) {
+    message = `[${PROVIDER_NAMES[params.provider] ?? params.provider}] ${message}`;
+  }
+  return message;
+}
+
 function parseMessage(error: unknown): string {
   if (error instanceof Error) {
     return error.message;
@@ -565,6 +913,9 @@ function parseMessage(error: unknown): string {
   if (typeof error === "string") {
     return error;
   }
+  if (isCommandError(error)) {
+    return formatCommandError(error);
+  }
   return "未知错误";
 }
 
@@ -630,6 +981,9 @@ function resolveLyricProviderLabel(provider?: string | null): string {
   if (provider === "netease") {
     return "网易云";
   }
+  if (provider?.startsWith(PLUGIN_PROVIDER_PREFIX)) {
+    return provider.slice(PLUGIN_PROVIDER_PREFIX.length);
+  }
   return "未知来源";
 }
 
@@ -637,6 +991,9 @@ function normalizeLyricProvider(value: string): LyricProvider | null {
   if (value === "qq" || value === "kugou" || value === "netease") {
     return value;
   }
+  if (value.startsWith(PLUGIN_PROVIDER_PREFIX) && value.length > PLUGIN_PROVIDER_PREFIX.length) {
+    return value as LyricProvider;
+  }
   return null;
 }
 
@@ -665,6 +1022,9 @@ function createCandidateIdentity(candidate: OnlineLyricCandidate): string {
   if (candidate.source === "netease" && candidate.neteaseSongId) {
     return `netease:${candidate.neteaseSongId}`;
   }
+  if (candidate.providerSongId) {
+    return `${candidate.source}:${candidate.providerSongId}`;
+  }
   return `${candidate.source}:${candidate.title}:${candidate.artists}:${candidate.album}`;
 }
 
@@ -911,17 +1271,82 @@ export default function App() {
   });
 
   const [directories, setDirectories] = useState<string[]>([]);
+  // 这些目录用「大小 + 部分内容哈希」检测变更（NAS/SMB 上 mtime 不可靠）
+  const [hashCheckDirectories, setHashCheckDirectories] = useState<string[]>([]);
   const [skipShortAudio, setSkipShortAudio] = useState(true);
   const [minDuration, setMinDuration] = useState(60);
   const [scanMode] = useState<"full" | "incremental">("incremental");
   const [scanRunning, setScanRunning] = useState(false);
   const [scanMessage, setScanMessage] = useState<string>("");
+  const [offlineDownloads, setOfflineDownloads] = useState<OfflineDownload[]>([]);
+  const [downloadSettings, setDownloadSettings] = useState<DownloadSettings>({
+    maxConcurrent: 2,
+    bandwidthLimitKbps: 0,
+  });
+  const [offlineSyncRules, setOfflineSyncRules] = useState<OfflineSyncRules>({
+    enabled: false,
+    favorites: true,
+    recentPlaylists: 5,
+    recentPlayed: 200,
+    budgetMb: 10240,
+    intervalMinutes: 60,
+  });
+  const [offlineSyncing, setOfflineSyncing] = useState(false);
+  const [normalizationSettings, setNormalizationSettings] = useState<NormalizationSettings>({
+    enabled: false,
+    preampDb: 0,
+    targetLufs: -18,
+    limiter: true,
+  });
+  const [telemetrySummary, setTelemetrySummary] = useState<TelemetrySummary | null>(null);
+  const [shellIntegration, setShellIntegration] = useState<ShellIntegrationStatus | null>(null);
+  const [outputBackend, setOutputBackend] = useState<OutputBackendSettings>(loadOutputBackendSettings);
+  const [replayGainMode, setReplayGainMode] = useState<"track" | "album">(
+    () => (localStorage.getItem("audio_replaygain_mode") === "album" ? "album" : "track"),
+  );
+  const [resamplerQuality, setResamplerQuality] = useState<ResamplerQuality>(() => {
+    const stored = localStorage.getItem("audio_resampler_quality");
+    return stored === "fast" || stored === "high" ? stored : "normal";
+  });
+  const [multichannelOutput, setMultichannelOutput] = useState(
+    () => localStorage.getItem("audio_multichannel") === "true",
+  );
+  const [limiterEnabled, setLimiterEnabled] = useState(
+    () => localStorage.getItem("audio_limiter_enabled") !== "false",
+  );
+  const [ditherEnabled, setDitherEnabled] = useState(
+    () => localStorage.getItem("audio_dither_enabled") !== "false",
+  );
+  // 结尾淡出秒数，0 为关闭
+  const [endFadeSecs, setEndFadeSecs] = useState(
+    () => Number(localStorage.getItem("audio_end_fade_secs")) || 0,
+  );
+  const [skipSilence, setSkipSilence] = useState(
+    () => localStorage.getItem("audio_skip_silence") === "true",
+  );
+  const [volumeCurve, setVolumeCurve] = useState<"logarithmic" | "linear">(
+    () => (localStorage.getItem("audio_volume_curve") === "linear" ? "linear" : "logarithmic"),
+  );
+  // "auto" | "source" | 固定采样率（Hz）
+  const [outputSampleRate, setOutputSampleRate] = useState(
+    () => localStorage.getItem("audio_output_sample_rate") || "auto",
+  );
+  const [dsdOutput, setDsdOutput] = useState<"pcm" | "dop">(
+    () => (localStorage.getItem("audio_dsd_output") === "dop" ? "dop" : "pcm"),
+  );
+  // null：当前版本未启用 JACK；undefined：尚未查询
+  const [jackPorts, setJackPorts] = useState<string[] | null | undefined>(undefined);
+  const [jackPortsError, setJackPortsError] = useState("");
+  // undefined：尚未查询；null：当前没有打开输出
+  const [outputInfo, setOutputInfo] = useState<OutputInfo | null | undefined>(undefined);
 
   const [playlists, setPlaylists] = useState<Playlist[]>([]);
   const [selectedPlaylistId, setSelectedPlaylistId] = useState<string | null>(null);
   const [openedPlaylistId, setOpenedPlaylistId] = useState<string | null>(null);
   const [playlistMenuId, setPlaylistMenuId] = useState<string | null>(null);
   const [songMenuSongId, setSongMenuSongId] = useState<string | null>(null);
+  // 收藏存放在自定义元数据的 favorite 字段，离线同步规则按它选歌
+  const [songMenuFavorite, setSongMenuFavorite] = useState(false);
   const [songInfoSongId, setSongInfoSongId] = useState<string | null>(null);
   const [dialogMode, setDialogMode] = useState<DialogMode>(null);
   const [dialogInput, setDialogInput] = useState("");
@@ -963,16 +1388,29 @@ export default function App() {
   const [streamTesting, setStreamTesting] = useState(false);
   const [streamSaving, setStreamSaving] = useState(false);
   const [streamFormMessage, setStreamFormMessage] = useState<string>("");
+  const [serverBackupPassword, setServerBackupPassword] = useState("");
 
   const [queueSongIds, setQueueSongIds] = useState<string[]>([]);
+  const [radioActive, setRadioActive] = useState(false);
   const [currentSongId, setCurrentSongId] = useState<string | null>(null);
+  // A-B 循环只作用于当前歌曲，引擎换歌时会自动取消
+  const [abLoop, setAbLoop] = useState<{ start: number | null; active: boolean }>({ start: null, active: false });
   const [isPlaying, setIsPlaying] = useState(false);
   const [isResolvingSong, setIsResolvingSong] = useState(false);
+  // 流媒体网络卡顿、正在重连
+  const [isBuffering, setIsBuffering] = useState(false);
   const [playMode, setPlayMode] = useState<PlayMode>("sequence");
   const [volume, setVolume] = useState(0.72);
   const [muted, setMuted] = useState(false);
   const [eqEnabled, setEqEnabled] = useState(true);
   const [eqGains, setEqGains] = useState<number[]>(() => [...EQ_DEFAULT_GAINS]);
+  const [userEqPresets, setUserEqPresets] = useState<UserEqualizerPreset[]>([]);
+  const [vocalReductionEnabled, setVocalReductionEnabled] = useState(false);
+  // 流媒体进度条波形，按歌曲 ID 保存（首次播放时边下载边生成）
+  const [streamWaveforms, setStreamWaveforms] = useState<Record<string, number[]>>({});
+  const [vocalReductionStrength, setVocalReductionStrength] = useState(0.8);
+  const [nightModeEnabled, setNightModeEnabled] = useState(false);
+  const [nightModePreset, setNightModePreset] = useState<NightModePreset>("medium");
   const [currentTime, setCurrentTime] = useState(0);
   const [duration, setDuration] = useState(0);
   const [showQueuePanel, setShowQueuePanel] = useState(false);
@@ -983,12 +1421,21 @@ export default function App() {
   const [lyricSourceMode, setLyricSourceMode] = useState<LyricSourceMode>("local");
   const [lyricProviderEnabled, setLyricProviderEnabled] = useState<Record<LyricProvider, boolean>>(DEFAULT_LYRIC_PROVIDER_ENABLED);
   const [lyricProviderPreference, setLyricProviderPreference] = useState<LyricProvider[]>(DEFAULT_LYRIC_PROVIDER_ORDER);
+  const [providerList, setProviderList] = useState<ProviderList | null>(null);
   const [lyricAutoPerSourceLimit, setLyricAutoPerSourceLimit] = useState(8);
   const [lyricManualPerSourceLimit, setLyricManualPerSourceLimit] = useState(12);
   const [currentLyricProvider, setCurrentLyricProvider] = useState<LyricProvider | null>(null);
   const [currentLyricSourceText, setCurrentLyricSourceText] = useState<string>("本地歌词");
   const [songLyricBindings, setSongLyricBindings] = useState<Record<string, SongLyricBinding>>({});
 
+  const [organizeDialogOpen, setOrganizeDialogOpen] = useState(false);
+  const [organizeTemplate, setOrganizeTemplate] = useState(
+    () => localStorage.getItem("organize_template") || DEFAULT_ORGANIZE_TEMPLATE,
+  );
+  const [organizePreview, setOrganizePreview] = useState<OrganizePreview | null>(null);
+  const [organizeError, setOrganizeError] = useState("");
+  const [organizeBusy, setOrganizeBusy] = useState(false);
+
   const [lyricSourceDialogOpen, setLyricSourceDialogOpen] = useState(false);
   const [lyricSourceDialogKeyword, setLyricSourceDialogKeyword] = useState("");
   const [lyricSourceDialogLoading, setLyricSourceDialogLoading] = useState(false);
@@ -1027,6 +1474,9 @@ export default function App() {
   const songsAlphabetItemElementMapRef = useRef<Map<string, HTMLSpanElement>>(new Map());
   const songsAlphabetHideTimerRef = useRef<number | null>(null);
   const songsAlphabetDraggingRef = useRef(false);
+  const radioLoadingRef = useRef(false);
+  const queueCurrentSongIdRef = useRef<string | null>(null);
+  const resumeTrackRef = useRef<{ songId: string; position: number; duration: number; saved: number } | null>(null);
   const songsAlphabetLastScrolledRef = useRef<string | null>(null);
 
   const [songsAlphabetActiveLetter, setSongsAlphabetActiveLetter] = useState<string | null>(null);
@@ -1212,6 +1662,10 @@ export default function App() {
       muted?: boolean;
       eqEnabled?: boolean;
       eqGains?: number[];
+      vocalReductionEnabled?: boolean;
+      vocalReductionStrength?: number;
+      nightModeEnabled?: boolean;
+      nightModePreset?: NightModePreset;
       lyricSourceMode?: LyricSourceMode;
       lyricProviderEnabled?: Partial<Record<LyricProvider, boolean>>;
       lyricProviderPreference?: LyricProvider[];
@@ -1247,6 +1701,22 @@ export default function App() {
       if (Array.isArray(parsedUiSettings.eqGains)) {
         setEqGains(normalizeEqGains(parsedUiSettings.eqGains));
       }
+      if (typeof parsedUiSettings.vocalReductionEnabled === "boolean") {
+        setVocalReductionEnabled(parsedUiSettings.vocalReductionEnabled);
+      }
+      if (typeof parsedUiSettings.vocalReductionStrength === "number") {
+        setVocalReductionStrength(Math.min(1, Math.max(0, parsedUiSettings.vocalReductionStrength)));
+      }
+      if (typeof parsedUiSettings.nightModeEnabled === "boolean") {
+        setNightModeEnabled(parsedUiSettings.nightModeEnabled);
+      }
+      if (
+        parsedUiSettings.nightModePreset === "light" ||
+        parsedUiSettings.nightModePreset === "medium" ||
+        parsedUiSettings.nightModePreset === "strong"
+      ) {
+        setNightModePreset(parsedUiSettings.nightModePreset);
+      }
       if (parsedUiSettings.lyricSourceMode === "local" || parsedUiSettings.lyricSourceMode === "online") {
         setLyricSourceMode(parsedUiSettings.lyricSourceMode);
       }
@@ -1328,6 +1798,10 @@ export default function App() {
         muted,
         eqEnabled,
         eqGains,
+        vocalReductionEnabled,
+        vocalReductionStrength,
+        nightModeEnabled,
+        nightModePreset,
         lyricSourceMode,
         lyricProviderEnabled,
         lyricProviderPreference,
@@ -1348,7 +1822,11 @@ export default function App() {
     lyricSize,
     lyricSourceMode,
     muted,
+    nightModeEnabled,
+    nightModePreset,
     showCover,
+    vocalReductionEnabled,
+    vocalReductionStrength,
     volume,
   ]);
 
@@ -1396,7 +1874,7 @@ export default function App() {
 
       const [songRows, albumRows, artistRows, statsResult, scanConfig, servers, coverStatsResult] =
         await Promise.all([
-          invoke<DbSong[]>("db_get_all_songs"),
+          invoke<DbSong[]>("db_get_unified_songs"),
           invoke<DbAlbum[]>("db_get_all_albums"),
           invoke<DbArtist[]>("db_get_all_artists"),
           invoke<LibraryStats>("db_get_library_stats"),
@@ -1414,6 +1892,7 @@ export default function App() {
 
       if (scanConfig) {
         setDirectories(scanConfig.directories ?? []);
+        setHashCheckDirectories(scanConfig.hashCheckDirectories ?? []);
         setSkipShortAudio(scanConfig.skipShort);
         setMinDuration(Math.max(1, Math.round(scanConfig.minDuration || 60)));
       }
@@ -1449,6 +1928,16 @@ export default function App() {
     void refreshLibrary();
   }, [refreshLibrary]);
 
+  // 启动时拉取其他 Subsonic 客户端留下的书签
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke<number>("sync_bookmarks").catch((error) => {
+      console.error("书签同步失败：", error);
+    });
+  }, [isTauriEnv]);
+
   useEffect(() => {
     if (!isTauriEnv) {
       return;
@@ -1456,6 +1945,8 @@ export default function App() {
 
     let unlistenLibrary: UnlistenFn | null = null;
     let unlistenScan: UnlistenFn | null = null;
+    let unlistenOffline: UnlistenFn | null = null;
+    let unlistenReauth: UnlistenFn | null = null;
     let disposed = false;
 
     const bindEvents = async () => {
@@ -1482,6 +1973,28 @@ export default function App() {
           );
         }
       });
+
+      unlistenOffline = await listen<OfflineDownloadProgress>("offline:progress", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        const { songId, ...progress } = event.payload;
+        setOfflineDownloads((previous) =>
+          previous.map((item) => (item.songId === songId ? { ...item, ...progress, error: progress.error } : item)),
+        );
+      });
+
+      // 播放中 token 过期后后端已重新登录，刷新缓存的服务器配置
+      unlistenReauth = await listen<string>("stream:reauthenticated", async () => {
+        try {
+          const servers = await invoke<DbStreamServer[]>("db_get_stream_servers");
+          if (!disposed) {
+            setStreamServers(servers.filter((server) => server.enabled));
+          }
+        } catch (error) {
+          console.error("刷新服务器配置失败：", error);
+        }
+      });
     };
 
     void bindEvents();
@@ -1494,9 +2007,219 @@ export default function App() {
       if (unlistenScan) {
         unlistenScan();
       }
+      if (unlistenOffline) {
+        unlistenOffline();
+      }
+      if (unlistenReauth) {
+        unlistenReauth();
+      }
     };
   }, [isTauriEnv, refreshLibrary]);
 
+  const loadOfflineDownloads = useCallback(async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+    try {
+      const [items, setting, rules] = await Promise.all([
+        invoke<OfflineDownload[]>("offline_list"),
+        invoke<{ key: string; value: DownloadSettings }>("settings_get", { key: "downloads" }),
+        invoke<{ key: string; value: OfflineSyncRules }>("settings_get", { key: "offlineSync" }),
+      ]);
+      setOfflineDownloads(items);
+      setDownloadSettings(setting.value);
+      setOfflineSyncRules(rules.value);
+    } catch (error) {
+      console.error("Failed to load offline downloads:", error);
+    }
+  }, [isTauriEnv]);
+
+  useEffect(() => {
+    if (page === "settings-downloads") {
+      void loadOfflineDownloads();
+    }
+  }, [page, loadOfflineDownloads]);
+
+  const downloadSongForOffline = useCallback(
+    async (songId: string) => {
+      try {
+        await invoke("download_song_for_offline", { songId });
+        setScanMessage("已加入离线下载");
+        await loadOfflineDownloads();
+      } catch (error) {
+        setScanMessage(`加入下载队列失败：${parseMessage(error)}`);
+      }
+    },
+    [loadOfflineDownloads],
+  );
+
+  const runOfflineAction = useCallback(
+    async (command: "offline_pause" | "offline_resume" | "offline_remove", songId: string) => {
+      try {
+        await invoke(command, { songId });
+        await loadOfflineDownloads();
+      } catch (error) {
+        setScanMessage(`操作失败：${parseMessage(error)}`);
+      }
+    },
+    [loadOfflineDownloads],
+  );
+
+  const saveOfflineSyncRules = useCallback(async (next: OfflineSyncRules) => {
+    setOfflineSyncRules(next);
+    try {
+      await invoke("settings_set", { setting: { key: "offlineSync", value: next } });
+    } catch (error) {
+      setScanMessage(`保存同步规则失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  const runOfflineSync = useCallback(async () => {
+    setOfflineSyncing(true);
+    try {
+      const result = await invoke<OfflineSyncResult>("offline_sync_now");
+      setScanMessage(`离线同步完成：新增 ${result.queued} 首，移除 ${result.evicted} 首`);
+      await loadOfflineDownloads();
+    } catch (error) {
+      setScanMessage(`离线同步失败：${parseMessage(error)}`);
+    } finally {
+      setOfflineSyncing(false);
+    }
+  }, [loadOfflineDownloads]);
+
+  const saveDownloadSettings = useCallback(async (next: DownloadSettings) => {
+    setDownloadSettings(next);
+    try {
+      await invoke("settings_set", { setting: { key: "downloads", value: next } });
+    } catch (error) {
+      setScanMessage(`保存下载设置失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriEnv || page !== "settings-ui") {
+      return;
+    }
+    void invoke<{ key: string; value: NormalizationSettings }>("settings_get", { key: "normalization" })
+      .then((setting) => setNormalizationSettings(setting.value))
+      .catch((error) => console.error("Failed to load normalization settings:", error));
+  }, [isTauriEnv, page]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke<ProviderList>("list_providers")
+      .then(setProviderList)
+      .catch((error) => console.error("Failed to load providers:", error));
+  }, [isTauriEnv]);
+
+  const reloadProviders = useCallback(async () => {
+    try {
+      const list = await invoke<ProviderList>("reload_providers");
+      setProviderList(list);
+      setScanMessage(list.errors.length ? `插件已重新加载，${list.errors.length} 个加载失败` : "插件已重新加载。");
+    } catch (error) {
+      setScanMessage(`重新加载插件失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  const loadJackPorts = useCallback(async () => {
+    try {
+      setJackPorts(await invoke<string[] | null>("audio_list_jack_ports"));
+      setJackPortsError("");
+    } catch (error) {
+      // 已编译 JACK 支持但服务未运行
+      setJackPorts([]);
+      setJackPortsError(parseMessage(error));
+    }
+  }, []);
+
+  const loadOutputInfo = useCallback(async () => {
+    try {
+      setOutputInfo(await invoke<OutputInfo | null>("audio_get_output_info"));
+    } catch (error) {
+      setOutputInfo(undefined);
+      setScanMessage(`读取输出信息失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  const saveOutputBackend = useCallback((next: OutputBackendSettings) => {
+    setOutputBackend(next);
+    localStorage.setItem("audio_output_backend", JSON.stringify(next));
+  }, []);
+
+  const loadTelemetrySummary = useCallback(async () => {
+    try {
+      setTelemetrySummary(await invoke<TelemetrySummary>("telemetry_summary"));
+    } catch (error) {
+      console.error("Failed to load telemetry summary:", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriEnv || page !== "settings-ui") {
+      return;
+    }
+    void loadTelemetrySummary();
+    void invoke<ShellIntegrationStatus>("shell_integration_status")
+      .then(setShellIntegration)
+      .catch((error) => {
+        console.error("Failed to load shell integration status:", error);
+      });
+    void loadJackPorts();
+    void loadOutputInfo();
+  }, [isTauriEnv, page, loadTelemetrySummary, loadJackPorts, loadOutputInfo]);
+
+  const toggleShellIntegration = useCallback(async (install: boolean) => {
+    try {
+      setShellIntegration(
+        await invoke<ShellIntegrationStatus>(install ? "shell_integration_install" : "shell_integration_uninstall"),
+      );
+    } catch (error) {
+      setScanMessage(`${install ? "添加" : "移除"}右键菜单失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  // 关闭时后端会丢弃尚未上传的计数
+  const setTelemetryEnabled = useCallback(async (enabled: boolean) => {
+    try {
+      await invoke("settings_set", { setting: { key: "telemetry", value: { enabled } } });
+      await loadTelemetrySummary();
+    } catch (error) {
+      setScanMessage(`保存统计设置失败：${parseMessage(error)}`);
+    }
+  }, [loadTelemetrySummary]);
+
+  const clearTelemetry = useCallback(async () => {
+    try {
+      await invoke("telemetry_clear");
+      await loadTelemetrySummary();
+    } catch (error) {
+      setScanMessage(`清除统计数据失败：${parseMessage(error)}`);
+    }
+  }, [loadTelemetrySummary]);
+
+  // 页面访问计入功能使用统计（未开启时后端直接忽略）
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("telemetry_record", { name: `page.${page}` }).catch(() => undefined);
+  }, [isTauriEnv, page]);
+
+  // 新设置从下一首开始生效
+  const saveNormalizationSettings = useCallback(async (next: NormalizationSettings) => {
+    setNormalizationSettings(next);
+    try {
+      await invoke("settings_set", { setting: { key: "normalization", value: next } });
+      // 重新计算当前与预加载歌曲的增益，设置立即生效
+      await invoke("audio_set_replaygain_mode", { mode: replayGainMode });
+    } catch (error) {
+      setScanMessage(`保存音量均衡设置失败：${parseMessage(error)}`);
+    }
+  }, [replayGainMode]);
+
   const songMap = useMemo(() => {
     const map = new Map<string, DbSong>();
     songs.forEach((song) => map.set(song.id, song));
@@ -1553,24 +2276,43 @@ export default function App() {
         );
       }
 
-      if (songsSortKey === "duration") {
+      if (songsSortKey === "rating") {
+        // 评分高的在前
         return (
-          leftSong.duration - rightSong.duration
+          (rightSong.rating ?? 0) - (leftSong.rating ?? 0)
           || compareText(leftSong.title, rightSong.title)
           || compareText(leftSong.artist, rightSong.artist)
         );
       }
 
-      return (
-        (rightSong.fileModified ?? 0) - (leftSong.fileModified ?? 0)
-        || compareText(leftSong.title, rightSong.title)
-        || compareText(leftSong.artist, rightSong.artist)
-      );
-    });
-
-    return sorted;
-  }, [filteredSongs, songsSortKey, textSorter]);
-
+      if (songsSortKey === "dynamicRange") {
+        // 动态最大的在前，未分析的排在最后
+        return (
+          (rightSong.dynamicRange ?? -1) - (leftSong.dynamicRange ?? -1)
+          || compareText(leftSong.title, rightSong.title)
+          || compareText(leftSong.artist, rightSong.artist)
+        );
+      }
+
+      if (songsSortKey === "duration") {
+        return (
+          leftSong.duration - rightSong.duration
+          || compareText(leftSong.title, rightSong.title)
+          || compareText(leftSong.artist, rightSong.artist)
+        );
+      }
+
+      // 按加入曲库的时间，旧数据没有时退回文件修改时间
+      return (
+        (rightSong.createdAt ?? rightSong.fileModified ?? 0) - (leftSong.createdAt ?? leftSong.fileModified ?? 0)
+        || compareText(leftSong.title, rightSong.title)
+        || compareText(leftSong.artist, rightSong.artist)
+      );
+    });
+
+    return sorted;
+  }, [filteredSongs, songsSortKey, textSorter]);
+
   useEffect(() => {
     songsAlphabetLastScrolledRef.current = null;
   }, [sortedSongs]);
@@ -1828,9 +2570,10 @@ export default function App() {
         return;
       }
 
-      const previousYear = yearMap.get(song.album) ?? 0;
+      const albumKey = song.albumId ?? song.album;
+      const previousYear = yearMap.get(albumKey) ?? 0;
       if (year > previousYear) {
-        yearMap.set(song.album, year);
+        yearMap.set(albumKey, year);
       }
     });
 
@@ -1856,9 +2599,34 @@ export default function App() {
         );
       }
 
+      if (albumsSortKey === "rating") {
+        return (
+          (rightAlbum.rating ?? 0) - (leftAlbum.rating ?? 0)
+          || compareText(leftAlbum.name, rightAlbum.name)
+          || compareText(leftAlbum.artist, rightAlbum.artist)
+        );
+      }
+
+      if (albumsSortKey === "dynamicRange") {
+        return (
+          (rightAlbum.dynamicRange ?? -1) - (leftAlbum.dynamicRange ?? -1)
+          || compareText(leftAlbum.name, rightAlbum.name)
+          || compareText(leftAlbum.artist, rightAlbum.artist)
+        );
+      }
+
+      if (albumsSortKey === "addedAt") {
+        return (
+          (rightAlbum.createdAt ?? 0) - (leftAlbum.createdAt ?? 0)
+          || compareText(leftAlbum.name, rightAlbum.name)
+          || compareText(leftAlbum.artist, rightAlbum.artist)
+        );
+      }
+
       if (albumsSortKey === "year") {
-        const leftYear = albumYearMap.get(leftAlbum.name) ?? 0;
-        const rightYear = albumYearMap.get(rightAlbum.name) ?? 0;
+        // 优先使用标签年份，没有时退回文件修改年份
+        const leftYear = leftAlbum.year ?? albumYearMap.get(leftAlbum.id) ?? 0;
+        const rightYear = rightAlbum.year ?? albumYearMap.get(rightAlbum.id) ?? 0;
         return (
           rightYear - leftYear
           || compareText(leftAlbum.name, rightAlbum.name)
@@ -1981,6 +2749,24 @@ export default function App() {
     [songMap, songMenuSongId],
   );
 
+  useEffect(() => {
+    setSongMenuFavorite(false);
+    if (!isTauriEnv || !songMenuSongId) {
+      return;
+    }
+    let cancelled = false;
+    invoke<Record<string, unknown>>("db_get_song_extra", { songId: songMenuSongId })
+      .then((extra) => {
+        if (!cancelled) {
+          setSongMenuFavorite(extra.favorite === true);
+        }
+      })
+      .catch(() => undefined);
+    return () => {
+      cancelled = true;
+    };
+  }, [isTauriEnv, songMenuSongId]);
+
   const songInfoSong = useMemo(
     () => (songInfoSongId ? songMap.get(songInfoSongId) ?? null : null),
     [songInfoSongId, songMap],
@@ -2085,6 +2871,14 @@ export default function App() {
     [currentSongId, queueSongs],
   );
 
+  // 网页端：当前歌曲播完后接着播放的歌曲（桌面端由原生队列决定）
+  const upcomingSongId = useMemo(() => {
+    if (!queueSongs.length || currentQueueIndex < 0) {
+      return null;
+    }
+    return queueSongs[pickNextIndex(queueSongs.length, currentQueueIndex, playMode)]?.id ?? null;
+  }, [currentQueueIndex, playMode, queueSongs]);
+
   const enabledLyricProviders = useMemo(() => {
     const sorted = lyricProviderPreference.filter((provider) => lyricProviderEnabled[provider]);
     if (sorted.length) {
@@ -2093,11 +2887,13 @@ export default function App() {
     return DEFAULT_LYRIC_PROVIDER_ORDER.filter((provider) => lyricProviderEnabled[provider]);
   }, [lyricProviderEnabled, lyricProviderPreference]);
 
-  const lyricSourceDialogProviderCounts = useMemo(() => ({
-    qq: lyricSourceDialogResults.filter((candidate) => candidate.source === "qq").length,
-    kugou: lyricSourceDialogResults.filter((candidate) => candidate.source === "kugou").length,
-    netease: lyricSourceDialogResults.filter((candidate) => candidate.source === "netease").length,
-  }), [lyricSourceDialogResults]);
+  const lyricSourceDialogProviderCounts = useMemo(() => {
+    const counts: Partial<Record<LyricProvider, number>> = {};
+    lyricSourceDialogResults.forEach((candidate) => {
+      counts[candidate.source] = (counts[candidate.source] ?? 0) + 1;
+    });
+    return counts;
+  }, [lyricSourceDialogResults]);
 
   const lyricSourceDialogFilteredResults = useMemo(
     () => lyricSourceDialogResults.filter((candidate) => candidate.source === lyricSourceDialogProvider),
@@ -2278,6 +3074,231 @@ export default function App() {
     });
   }, [eqEnabled, eqGains, isTauriEnv]);
 
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_output_backend", {
+      backend: outputBackend.backend,
+      clientName: outputBackend.clientName,
+      ports: outputBackend.ports,
+    }).catch((error) => {
+      setScanMessage(`切换音频输出失败：${parseMessage(error)}`);
+    });
+  }, [isTauriEnv, outputBackend]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_multichannel", { enabled: multichannelOutput }).catch(() => {
+    });
+  }, [isTauriEnv, multichannelOutput]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_limiter", { enabled: limiterEnabled }).catch(() => {
+    });
+  }, [isTauriEnv, limiterEnabled]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_dither", { enabled: ditherEnabled }).catch(() => {
+    });
+  }, [isTauriEnv, ditherEnabled]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_dsd_output", { output: dsdOutput }).catch(() => {
+    });
+  }, [isTauriEnv, dsdOutput]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    const fixed = outputSampleRate !== "auto" && outputSampleRate !== "source";
+    void invoke("audio_set_output_sample_rate", {
+      mode: fixed ? "fixed" : outputSampleRate,
+      rate: fixed ? Number(outputSampleRate) : null,
+    }).catch(() => {
+    });
+  }, [isTauriEnv, outputSampleRate]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_skip_silence", { enabled: skipSilence }).catch(() => {
+    });
+  }, [isTauriEnv, skipSilence]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_end_fade", { seconds: endFadeSecs }).catch(() => {
+    });
+  }, [isTauriEnv, endFadeSecs]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_volume_curve", { curve: volumeCurve }).catch(() => {
+    });
+  }, [isTauriEnv, volumeCurve]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_replaygain_mode", { mode: replayGainMode }).catch(() => {
+    });
+  }, [isTauriEnv, replayGainMode]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_resampler_quality", { quality: resamplerQuality }).catch(() => {
+    });
+  }, [isTauriEnv, resamplerQuality]);
+
+  // 人声消除只在原生音频引擎里实现，位于均衡器之前
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_karaoke", {
+      enabled: vocalReductionEnabled,
+      strength: vocalReductionStrength,
+    }).catch(() => {
+    });
+  }, [isTauriEnv, vocalReductionEnabled, vocalReductionStrength]);
+
+  // 夜间模式压缩位于均衡器之后、限幅器之前
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_set_night_mode", {
+      enabled: nightModeEnabled,
+      preset: nightModePreset,
+    }).catch(() => {
+    });
+  }, [isTauriEnv, nightModeEnabled, nightModePreset]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    let disposed = false;
+    let unlisten: UnlistenFn | null = null;
+    void listen<WaveformPayload>("audio:waveform", (event) => {
+      if (disposed || !event.payload) {
+        return;
+      }
+      const { songId, peaks } = event.payload;
+      setStreamWaveforms((previous) => ({ ...previous, [songId]: peaks }));
+    }).then((fn) => {
+      if (disposed) {
+        fn();
+      } else {
+        unlisten = fn;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, [isTauriEnv]);
+
+  // 切歌时只保留当前歌曲的波形，已缓存的直接读取
+  useEffect(() => {
+    if (!isTauriEnv || !currentSong || currentSong.sourceType !== "stream") {
+      return;
+    }
+
+    const songId = currentSong.id;
+    setStreamWaveforms((previous) => (previous[songId] ? { [songId]: previous[songId] } : {}));
+    void invoke<number[] | null>("audio_get_waveform", { songId })
+      .then((peaks) => {
+        if (peaks) {
+          setStreamWaveforms((previous) => ({ ...previous, [songId]: peaks }));
+        }
+      })
+      .catch(() => {
+      });
+  }, [currentSong, isTauriEnv]);
+
+  const handleVocalReductionChange = useCallback((enabled: boolean, strength: number) => {
+    setVocalReductionEnabled(enabled);
+    setVocalReductionStrength(Math.min(1, Math.max(0, strength)));
+  }, []);
+
+  const handleNightModeChange = useCallback((enabled: boolean, preset: NightModePreset) => {
+    setNightModeEnabled(enabled);
+    setNightModePreset(preset);
+  }, []);
+
+  useEffect(() => {
+    setAbLoop({ start: null, active: false });
+  }, [currentSongId]);
+
+  const handleSetAbLoop = useCallback(async (kind: "a" | "b" | "clear") => {
+    try {
+      if (kind === "b") {
+        if (abLoop.start === null) {
+          return;
+        }
+        await invoke("audio_set_ab_loop", { startSecs: abLoop.start, endSecs: currentTime });
+        setAbLoop({ start: abLoop.start, active: true });
+        setScanMessage(`A-B 循环：${formatTime(abLoop.start)} - ${formatTime(currentTime)}`);
+        return;
+      }
+      if (abLoop.active) {
+        await invoke("audio_clear_ab_loop");
+      }
+      if (kind === "a") {
+        setAbLoop({ start: currentTime, active: false });
+        setScanMessage(`已将 ${formatTime(currentTime)} 设为 A 点，播放到结束处再设置 B 点`);
+      } else {
+        setAbLoop({ start: null, active: false });
+        setScanMessage("已取消 A-B 循环");
+      }
+    } catch (error) {
+      setScanMessage(`设置 A-B 循环失败：${parseMessage(error)}`);
+    }
+  }, [abLoop, currentTime]);
+
+  const handleSetCuePoint = useCallback(async (kind: "in" | "out" | "clear") => {
+    if (!currentSong) {
+      return;
+    }
+    try {
+      const cues = await invoke<CuePoints>("db_get_cue_points", { songId: currentSong.id });
+      const cueIn = kind === "clear" ? null : kind === "in" ? currentTime : cues.cueIn ?? null;
+      const cueOut = kind === "clear" ? null : kind === "out" ? currentTime : cues.cueOut ?? null;
+      await invoke("db_set_cue_points", { songId: currentSong.id, cueIn, cueOut });
+      setScanMessage(
+        kind === "clear"
+          ? "已清除起止点"
+          : `已将 ${formatTime(currentTime)} 设为${kind === "in" ? "开始点" : "结束点"}，下次播放生效`,
+      );
+    } catch (error) {
+      setScanMessage(`设置起止点失败：${parseMessage(error)}`);
+    }
+  }, [currentSong, currentTime]);
+
   useEffect(() => {
     if (isTauriEnv) {
       void invoke("audio_set_volume", { volume: muted ? 0 : volume }).catch(() => {
@@ -2304,11 +3325,7 @@ export default function App() {
 
   const findServerBySong = useCallback(
     (song: DbSong): StreamServerConfig | null => {
-      const payload = safeParseJson<StreamInfoPayload>(song.streamInfo);
-      if (payload?.config) {
-        return payload.config;
-      }
-
+      // 凭据以服务器配置为准，streamInfo 中不再保存密码
       if (song.serverId) {
         const server = streamServers.find((item) => item.id === song.serverId);
         if (server) {
@@ -2316,6 +3333,11 @@ export default function App() {
         }
       }
 
+      const payload = safeParseJson<StreamInfoPayload>(song.streamInfo);
+      if (payload?.config?.password) {
+        return payload.config;
+      }
+
       return null;
     },
     [streamServers],
@@ -2350,12 +3372,47 @@ export default function App() {
     });
   }, [ensureAudioEqGraph]);
 
-  const handleEqualizerApplyPreset = useCallback((gains: number[]) => {
+  const handleEqualizerApplyPreset = useCallback((gains: number[], enabled = true) => {
     ensureAudioEqGraph();
-    setEqEnabled(true);
+    setEqEnabled(enabled);
     setEqGains(normalizeEqGains(gains));
   }, [ensureAudioEqGraph]);
 
+  const refreshUserEqPresets = useCallback(async () => {
+    try {
+      setUserEqPresets(await invoke<UserEqualizerPreset[]>("db_get_eq_presets"));
+    } catch (error) {
+      console.error("Failed to load EQ presets:", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (isTauriEnv) {
+      void refreshUserEqPresets();
+    }
+  }, [isTauriEnv, refreshUserEqPresets]);
+
+  const handleSaveEqPreset = useCallback(async (name: string) => {
+    try {
+      await invoke("db_save_eq_preset", {
+        preset: { name, gains: normalizeEqGains(eqGains), preamp: 0, enabled: eqEnabled },
+      });
+      await refreshUserEqPresets();
+      setScanMessage(`已保存均衡器预设「${name}」`);
+    } catch (error) {
+      setScanMessage(`保存预设失败：${parseMessage(error)}`);
+    }
+  }, [eqEnabled, eqGains, refreshUserEqPresets]);
+
+  const handleDeleteEqPreset = useCallback(async (name: string) => {
+    try {
+      await invoke("db_delete_eq_preset", { name });
+      await refreshUserEqPresets();
+    } catch (error) {
+      setScanMessage(`删除预设失败：${parseMessage(error)}`);
+    }
+  }, [refreshUserEqPresets]);
+
   const handleEqualizerReset = useCallback(() => {
     ensureAudioEqGraph();
     setEqEnabled(true);
@@ -2370,17 +3427,25 @@ export default function App() {
     }));
   }, []);
 
+  const pluginLyricProviders = useMemo(
+    () => (providerList?.providers ?? [])
+      .filter((provider) => !provider.builtin && provider.kinds.includes("lyrics"))
+      .map((provider) => provider.id as LyricProvider),
+    [providerList],
+  );
+
   const getEnabledLyricProviders = useCallback((): LyricProvider[] => {
     const preferred = enabledLyricProviders.length
       ? enabledLyricProviders
       : DEFAULT_LYRIC_PROVIDER_ORDER.filter((provider) => lyricProviderEnabled[provider]);
 
+    // 已安装的歌词插件排在内置来源之后
     if (!preferred.length) {
-      return DEFAULT_LYRIC_PROVIDER_ORDER;
+      return [...DEFAULT_LYRIC_PROVIDER_ORDER, ...pluginLyricProviders];
     }
 
-    return preferred;
-  }, [enabledLyricProviders, lyricProviderEnabled]);
+    return [...preferred, ...pluginLyricProviders];
+  }, [enabledLyricProviders, lyricProviderEnabled, pluginLyricProviders]);
 
   const searchOnlineLyricCandidates = useCallback(
     async (song: DbSong, keyword?: string, limitPerSource?: number) => {
@@ -2398,6 +3463,7 @@ export default function App() {
           keyword: keyword?.trim() ? keyword.trim() : undefined,
           providers,
           limitPerSource: limitPerSource ?? lyricAutoPerSourceLimit,
+          songId: song.id,
         },
       });
 
@@ -2428,6 +3494,9 @@ export default function App() {
         qqSongId: candidate.qqSongId,
         neteaseSongId: candidate.neteaseSongId,
         kugouSongHash: candidate.kugouSongHash,
+        providerSongId: candidate.providerSongId,
+        // 歌词视图目前按“原文┃译文”解析，先沿用合并格式
+        mergeTranslation: true,
       },
     });
 
@@ -2512,6 +3581,7 @@ export default function App() {
           qqSongId: binding.qqSongId,
           neteaseSongId: binding.neteaseSongId,
           kugouSongHash: binding.kugouSongHash,
+          providerSongId: binding.providerSongId,
         };
 
         const fetched = await fetchOnlineLyricByCandidate(fallbackCandidate);
@@ -2538,11 +3608,7 @@ export default function App() {
       const providerRank = new Map<LyricProvider, number>();
       providers.forEach((provider, index) => providerRank.set(provider, index));
 
-      const perProviderCount: Record<LyricProvider, number> = {
-        qq: 0,
-        kugou: 0,
-        netease: 0,
-      };
+      const perProviderCount: Partial<Record<LyricProvider, number>> = {};
 
       const sortedCandidates = candidates
         .filter((candidate) => providers.includes(candidate.source))
@@ -2556,11 +3622,12 @@ export default function App() {
         });
 
       for (const candidate of sortedCandidates) {
-        if (perProviderCount[candidate.source] >= lyricAutoPerSourceLimit) {
+        const used = perProviderCount[candidate.source] ?? 0;
+        if (used >= lyricAutoPerSourceLimit) {
           continue;
         }
 
-        perProviderCount[candidate.source] += 1;
+        perProviderCount[candidate.source] = used + 1;
 
         const fetched = await fetchOnlineLyricByCandidate(candidate);
         if (!fetched || !fetched.lyric.trim()) {
@@ -2572,6 +3639,7 @@ export default function App() {
           qqSongId: candidate.qqSongId,
           neteaseSongId: candidate.neteaseSongId,
           kugouSongHash: candidate.kugouSongHash,
+          providerSongId: candidate.providerSongId,
           lyric: fetched.lyric,
           format: fetched.format,
           title: candidate.title,
@@ -2779,6 +3847,9 @@ export default function App() {
             keyword: keyword?.trim() ? keyword.trim() : undefined,
             providers,
             limitPerSource: lyricManualPerSourceLimit,
+            songId: currentSong.id,
+            // 手动搜索时忽略“无结果”缓存
+            bypassCache: true,
           },
         });
 
@@ -2896,6 +3967,7 @@ export default function App() {
         qqSongId: candidate.qqSongId,
         neteaseSongId: candidate.neteaseSongId,
         kugouSongHash: candidate.kugouSongHash,
+        providerSongId: candidate.providerSongId,
         lyric: fetched.lyric,
         format: fetched.format,
         title: candidate.title,
@@ -2912,29 +3984,90 @@ export default function App() {
     }
   }, [currentSong, fetchOnlineLyricByCandidate, updateSongLyricBinding]);
 
-  const resolveSongSource = useCallback(
+  const resolveStreamSource = useCallback(
     async (song: DbSong) => {
-      if (song.sourceType === "stream") {
-        const payload = safeParseJson<StreamInfoPayload>(song.streamInfo);
-        const config = findServerBySong(song);
-        const songId = payload?.songId || song.serverSongId || song.id;
-        if (!config || !songId) {
-          throw new Error("缺少流媒体配置或歌曲 ID");
+      // 已离线下载的歌曲直接播放本地文件
+      if (isTauriEnv) {
+        const offlinePath = await invoke<string | null>("offline_get_path", { songId: song.id });
+        if (offlinePath) {
+          return offlinePath;
         }
-        return invoke<string>("get_stream_url", { config, songId });
       }
 
-      if (!song.filePath) {
-        throw new Error("歌曲文件路径为空");
+      const payload = safeParseJson<StreamInfoPayload>(song.streamInfo);
+      const config = findServerBySong(song);
+      const songId = payload?.songId || song.serverSongId || song.id;
+      if (!config || !songId) {
+        throw new Error("缺少流媒体配置或歌曲 ID");
+      }
+      return invoke<string>("get_stream_url", { config, songId });
+    },
+    [findServerBySong, isTauriEnv],
+  );
+
+  const resolveSongSource = useCallback(
+    async (song: DbSong) => {
+      if (song.sourceType === "stream") {
+        return resolveStreamSource(song);
       }
 
       if (!isTauriEnv) {
+        if (!song.filePath) {
+          throw new Error("歌曲文件路径为空");
+        }
         return song.filePath;
       }
 
-      return song.filePath;
+      // 本地文件优先；文件丢失时回退到服务器上的同一首歌
+      const sources = await invoke<DbSong[]>("db_get_song_sources", { songId: song.id });
+      const preferred = sources[0];
+      if (preferred?.sourceType === "stream") {
+        return resolveStreamSource(preferred);
+      }
+
+      const filePath = preferred?.filePath || song.filePath;
+      if (!filePath) {
+        throw new Error("歌曲文件路径为空");
+      }
+      return filePath;
     },
-    [findServerBySong, isTauriEnv],
+    [isTauriEnv, resolveStreamSource],
+  );
+
+  // 按当前均衡器等音效设置离线渲染歌曲，导出为 FLAC/WAV
+  const exportSongAudio = useCallback(
+    async (song: DbSong) => {
+      let unlistenProgress: UnlistenFn | null = null;
+      try {
+        const path = await save({
+          title: "导出音频",
+          defaultPath: `${song.title || "bayin-export"}.flac`,
+          filters: [
+            { name: "FLAC", extensions: ["flac"] },
+            { name: "WAV", extensions: ["wav"] },
+          ],
+        });
+        if (!path) {
+          return;
+        }
+        const format = path.toLowerCase().endsWith(".wav") ? "wav" : "flac";
+        const source = await resolveSongSource(song);
+        setScanMessage("正在导出音频…");
+        unlistenProgress = await listen<AudioExportProgressPayload>("audio:export_progress", (event) => {
+          const progress = Number(event.payload?.progress ?? 0);
+          setScanMessage(`正在导出音频… ${Math.round(progress * 100)}%`);
+        });
+        await invoke("audio_export", { source, outputPath: path, format, sampleRate: null });
+        setScanMessage(`已导出：${path}`);
+      } catch (error) {
+        setScanMessage(`导出音频失败：${parseMessage(error)}`);
+      } finally {
+        if (unlistenProgress) {
+          unlistenProgress();
+        }
+      }
+    },
+    [resolveSongSource],
   );
 
   const playSongById = useCallback(
@@ -2954,14 +4087,21 @@ export default function App() {
       setDuration(song.duration || 0);
 
       try {
-        const source = await resolveSongSource(song);
         if (isTauriEnv) {
           if (autoPlay) {
-            await invoke("audio_play", { source });
+            // 原生队列解析音源并播放，歌词在 audio:queue_changed 中加载
+            await invoke("audio_queue_play", { songId: song.id });
             setIsPlaying(true);
+            if ((song.duration || 0) >= RESUME_MIN_DURATION) {
+              const resume = await invoke<number | null>("db_get_resume_position", { songId: song.id });
+              if (resume && resume < song.duration - RESUME_END_MARGIN) {
+                await invoke("audio_seek", { positionSecs: resume });
+                setCurrentTime(resume);
+              }
+            }
           }
         } else if (audio) {
-          const src = convertFileSrc(source);
+          const src = convertFileSrc(await resolveSongSource(song));
           if (audio.src !== src) {
             audio.src = src;
           }
@@ -2971,9 +4111,8 @@ export default function App() {
             await audio.play();
             setIsPlaying(true);
           }
+          await fetchLyricsForSong(song);
         }
-
-        await fetchLyricsForSong(song);
       } catch (error) {
         setIsPlaying(false);
         setScanMessage(`播放失败：${parseMessage(error)}`);
@@ -2985,7 +4124,20 @@ export default function App() {
   );
 
   const playNext = useCallback(async () => {
-    if (!queueSongs.length) {
+    // 原生队列决定下一首（随机顺序、循环模式都在引擎侧）
+    if (isTauriEnv) {
+      await invoke("audio_queue_next").catch((error) => {
+        setScanMessage(`播放失败：${parseMessage(error)}`);
+      });
+      return;
+    }
+
+    if (!queueSongs.length) {
+      return;
+    }
+
+    if (upcomingSongId) {
+      await playSongById(upcomingSongId, true);
       return;
     }
 
@@ -2995,9 +4147,17 @@ export default function App() {
     if (nextSong) {
       await playSongById(nextSong.id, true);
     }
-  }, [currentQueueIndex, playMode, playSongById, queueSongs]);
+  }, [currentQueueIndex, isTauriEnv, playMode, playSongById, queueSongs, upcomingSongId]);
 
   const playPrevious = useCallback(async () => {
+    // 原生队列按实际播放顺序回到上一首（随机播放时也是刚才听过的那首）
+    if (isTauriEnv) {
+      await invoke("audio_queue_prev").catch((error) => {
+        setScanMessage(`播放失败：${parseMessage(error)}`);
+      });
+      return;
+    }
+
     if (!queueSongs.length) {
       return;
     }
@@ -3008,7 +4168,53 @@ export default function App() {
     if (previousSong) {
       await playSongById(previousSong.id, true);
     }
-  }, [currentQueueIndex, playSongById, queueSongs]);
+  }, [currentQueueIndex, isTauriEnv, playSongById, queueSongs]);
+
+  // 原生队列跟随界面上的播放列表；当前歌曲不变时不打断播放，引擎据此预加载下一首
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    const tracks: QueueTrack[] = queueSongIds.map((songId) => ({ songId }));
+    void invoke("audio_queue_update", { tracks }).catch((error) => {
+      console.error("同步播放队列失败：", error);
+    });
+  }, [isTauriEnv, queueSongIds]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("audio_queue_set_shuffle", { enabled: playMode === "shuffle" })
+      .then(() => invoke("audio_queue_set_repeat", { mode: playMode === "repeat-one" ? "one" : "all" }))
+      .catch((error) => {
+        console.error("同步播放模式失败：", error);
+      });
+  }, [isTauriEnv, playMode]);
+
+  // 电台模式：快播完时按当前歌曲继续追加相似歌曲
+  useEffect(() => {
+    if (!radioActive || !currentSongId || currentQueueIndex < 0 || radioLoadingRef.current) {
+      return;
+    }
+    if (queueSongs.length - currentQueueIndex > RADIO_REFILL_THRESHOLD) {
+      return;
+    }
+
+    radioLoadingRef.current = true;
+    void invoke<string[]>("queue_radio", { songId: currentSongId, exclude: queueSongIds })
+      .then((ids) => {
+        if (ids.length) {
+          setQueueSongIds((previous) => [...previous, ...ids.filter((id) => !previous.includes(id))]);
+        }
+      })
+      .catch((error) => {
+        console.error("电台续接失败：", error);
+      })
+      .finally(() => {
+        radioLoadingRef.current = false;
+      });
+  }, [currentQueueIndex, currentSongId, queueSongIds, queueSongs.length, radioActive]);
 
   useEffect(() => {
     if (!isTauriEnv) {
@@ -3018,8 +4224,11 @@ export default function App() {
     let disposed = false;
     let unlistenTime: UnlistenFn | null = null;
     let unlistenStateChanged: UnlistenFn | null = null;
-    let unlistenEnded: UnlistenFn | null = null;
+    let unlistenQueueChanged: UnlistenFn | null = null;
     let unlistenError: UnlistenFn | null = null;
+    let unlistenDeviceChanged: UnlistenFn | null = null;
+    let unlistenTrackChanged: UnlistenFn | null = null;
+    let unlistenBuffering: UnlistenFn | null = null;
 
     const bindEvents = async () => {
       unlistenTime = await listen<AudioTimePayload>("audio:time", (event) => {
@@ -3041,11 +4250,27 @@ export default function App() {
         setIsPlaying(Boolean(event.payload.is_playing));
       });
 
-      unlistenEnded = await listen("audio:ended", () => {
-        if (disposed) {
+      // 原生队列切歌（播完接下一首、无缝衔接、上一首/下一首）后同步当前歌曲
+      unlistenQueueChanged = await listen<QueueSnapshot>("audio:queue_changed", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        const { currentIndex, tracks } = event.payload;
+        const songId = currentIndex == null ? null : tracks[currentIndex]?.songId ?? null;
+        if (songId === queueCurrentSongIdRef.current) {
+          return;
+        }
+        queueCurrentSongIdRef.current = songId;
+        if (!songId) {
           return;
         }
-        void playNext();
+        setCurrentSongId(songId);
+        setCurrentTime(0);
+        const song = songMap.get(songId);
+        if (song) {
+          setDuration(song.duration || 0);
+          void fetchLyricsForSong(song);
+        }
       });
 
       unlistenError = await listen<AudioErrorPayload>("audio:error", (event) => {
@@ -3053,7 +4278,33 @@ export default function App() {
           return;
         }
         setIsPlaying(false);
-        setScanMessage(`播放失败：${event.payload.message || "未知错误"}`);
+        setScanMessage(`播放失败：${event.payload.details || "未知错误"}`);
+      });
+
+      unlistenDeviceChanged = await listen<AudioDeviceChangedPayload>("audio:device_changed", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        setScanMessage(`输出设备已断开，已切换到：${event.payload.device_name || "默认设备"}`);
+      });
+
+      unlistenBuffering = await listen<AudioBufferingPayload>("audio:buffering", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        setIsBuffering(Boolean(event.payload.buffering));
+      });
+
+      // 原生队列无缝切歌时不经过前端，按引擎通知同步当前歌曲
+      unlistenTrackChanged = await listen<AudioTrackChangedPayload>("audio:track_changed", (event) => {
+        if (disposed || !event.payload?.song_id) {
+          return;
+        }
+        setCurrentSongId(event.payload.song_id);
+        setCurrentTime(0);
+        if (Number.isFinite(event.payload.duration) && event.payload.duration > 0) {
+          setDuration(event.payload.duration);
+        }
       });
     };
 
@@ -3083,14 +4334,213 @@ export default function App() {
       if (unlistenStateChanged) {
         unlistenStateChanged();
       }
-      if (unlistenEnded) {
-        unlistenEnded();
+      if (unlistenQueueChanged) {
+        unlistenQueueChanged();
       }
       if (unlistenError) {
         unlistenError();
       }
+      if (unlistenDeviceChanged) {
+        unlistenDeviceChanged();
+      }
+      if (unlistenTrackChanged) {
+        unlistenTrackChanged();
+      }
+      if (unlistenBuffering) {
+        unlistenBuffering();
+      }
+    };
+  }, [fetchLyricsForSong, isTauriEnv, songMap]);
+
+  // 长曲目续播位置：播放中定期保存，暂停和切歌时立即保存
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    const save = (songId: string, position: number, total: number) => {
+      const positionSecs = position > 0 && position < total - RESUME_END_MARGIN ? position : null;
+      void invoke("db_save_resume_position", { songId, positionSecs }).catch(() => {
+      });
+    };
+
+    const tracked = resumeTrackRef.current;
+    if (tracked && tracked.songId !== currentSongId) {
+      save(tracked.songId, tracked.position, tracked.duration);
+      resumeTrackRef.current = null;
+    }
+    if (!currentSongId || duration < RESUME_MIN_DURATION) {
+      return;
+    }
+    if (!resumeTrackRef.current) {
+      resumeTrackRef.current = { songId: currentSongId, position: currentTime, duration, saved: currentTime };
+      return;
+    }
+
+    const current = resumeTrackRef.current;
+    current.position = currentTime;
+    current.duration = duration;
+    if (currentTime !== current.saved && (!isPlaying || Math.abs(currentTime - current.saved) >= RESUME_SAVE_INTERVAL)) {
+      current.saved = currentTime;
+      save(current.songId, currentTime, duration);
+    }
+  }, [currentSongId, currentTime, duration, isPlaying, isTauriEnv]);
+
+  // Jellyfin 远程控制：其他客户端投放到本机的播放指令
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    let disposed = false;
+    let unlistenPlay: UnlistenFn | null = null;
+    let unlistenCommand: UnlistenFn | null = null;
+
+    const bindEvents = async () => {
+      unlistenPlay = await listen<JellyfinRemotePlayPayload>("jellyfin:play", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        const { songIds, startIndex, startSecs, mode } = event.payload;
+        const ids = songIds.filter((id) => songMap.has(id));
+        if (!ids.length) {
+          return;
+        }
+
+        if (mode === "now") {
+          const startId = songIds[startIndex] && songMap.has(songIds[startIndex]) ? songIds[startIndex] : ids[0];
+          setQueueSongIds(ids);
+          setRadioActive(false);
+          void playSongById(startId, true).then(() => {
+            if (startSecs > 0) {
+              void invoke("audio_seek", { positionSecs: startSecs }).catch(() => {
+              });
+            }
+          });
+          return;
+        }
+
+        setQueueSongIds((previous) => {
+          const rest = previous.filter((id) => !ids.includes(id));
+          if (mode === "last") {
+            return [...rest, ...ids];
+          }
+          const currentIndex = currentSongId ? rest.indexOf(currentSongId) : -1;
+          const nextQueue = [...rest];
+          nextQueue.splice(currentIndex + 1, 0, ...ids);
+          return nextQueue;
+        });
+      });
+
+      unlistenCommand = await listen<JellyfinRemoteCommandPayload>("jellyfin:command", (event) => {
+        if (disposed || !event.payload) {
+          return;
+        }
+        const { name, value } = event.payload;
+        if (name === "next") {
+          void playNext();
+        } else if (name === "previous") {
+          void playPrevious();
+        } else if (name === "setVolume" && typeof value === "number") {
+          setVolume(Math.min(1, Math.max(0, value)));
+        }
+      });
+    };
+
+    void bindEvents();
+
+    return () => {
+      disposed = true;
+      if (unlistenPlay) {
+        unlistenPlay();
+      }
+      if (unlistenCommand) {
+        unlistenCommand();
+      }
+    };
+  }, [currentSongId, isTauriEnv, playNext, playPrevious, playSongById, songMap]);
+
+  // 把队列镜像到后端，导出 M3U8 / 复制正在播放都以后端状态为准
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+    void invoke("queue_sync", { songIds: queueSongIds, currentSongId }).catch((error) => {
+      console.error("同步播放队列失败：", error);
+    });
+  }, [currentSongId, isTauriEnv, queueSongIds]);
+
+  const exportQueue = useCallback(async () => {
+    try {
+      const path = await save({
+        title: "导出播放队列",
+        defaultPath: "bayin-queue.m3u8",
+        filters: [{ name: "M3U8", extensions: ["m3u8"] }],
+      });
+      if (!path) {
+        return;
+      }
+      const count = await invoke<number>("queue_export_m3u8", { path });
+      setScanMessage(`已导出 ${count} 首歌曲。`);
+    } catch (error) {
+      setScanMessage(`导出播放队列失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  const copyNowPlaying = useCallback(async () => {
+    try {
+      const text = await invoke<string>("queue_now_playing_text", { template: null });
+      await navigator.clipboard.writeText(text);
+      setScanMessage("正在播放已复制到剪贴板。");
+    } catch (error) {
+      setScanMessage(`复制失败：${parseMessage(error)}`);
+    }
+  }, []);
+
+  // 文件管理器右键“添加到 BaYin 队列”：后端已写入曲库，刷新后追加到队列末尾
+  const consumePendingQueue = useCallback(async () => {
+    try {
+      const ids = await invoke<string[]>("take_pending_queue");
+      if (!ids.length) {
+        return;
+      }
+      await refreshLibrary();
+      setQueueSongIds((previous) => [...previous, ...ids.filter((id) => !previous.includes(id))]);
+      setScanMessage(`已添加 ${ids.length} 首歌曲到播放队列。`);
+    } catch (error) {
+      console.error("添加到播放队列失败：", error);
+    }
+  }, [refreshLibrary]);
+
+  useEffect(() => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    let disposed = false;
+    let unlisten: UnlistenFn | null = null;
+
+    // 启动参数里的文件可能在前端就绪前已处理完
+    void consumePendingQueue();
+    void listen("queue:enqueue", () => {
+      if (!disposed) {
+        void consumePendingQueue();
+      }
+    }).then((fn) => {
+      if (disposed) {
+        fn();
+      } else {
+        unlisten = fn;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      if (unlisten) {
+        unlisten();
+      }
     };
-  }, [isTauriEnv, playNext]);
+  }, [consumePendingQueue, isTauriEnv]);
 
   const togglePlayPause = useCallback(async () => {
     if (!currentSongId && queueSongs.length) {
@@ -3217,6 +4667,7 @@ export default function App() {
 
   const clearQueue = () => {
     setQueueSongIds([]);
+    setRadioActive(false);
     setCurrentSongId(null);
     if (isTauriEnv) {
       void invoke("audio_stop").catch(() => {
@@ -3343,6 +4794,19 @@ export default function App() {
     closeSongMenu();
   };
 
+  const startRadio = async (songId: string) => {
+    closeSongMenu();
+    try {
+      const ids = await invoke<string[]>("queue_radio", { songId, exclude: [songId] });
+      setQueueSongIds([songId, ...ids]);
+      setRadioActive(true);
+      setScanMessage(`电台已开始：${ids.length} 首相似歌曲`);
+      void playSongById(songId, true);
+    } catch (error) {
+      setScanMessage(`电台启动失败：${parseMessage(error)}`);
+    }
+  };
+
   const jumpToSongArtist = (song: DbSong, preferredArtist?: string) => {
     const artistName = preferredArtist?.trim() || splitArtistNames(song.artist)[0] || song.artist;
     setArtistSearchQuery(artistName);
@@ -3562,6 +5026,7 @@ export default function App() {
 
     const selectedIds = selectedSongsInViewOrder.map((song) => song.id);
     setQueueSongIds(selectedIds);
+    setRadioActive(false);
     void playSongById(selectedIds[0], true);
   };
 
@@ -3815,7 +5280,10 @@ export default function App() {
       if (result.success) {
         setStreamFormMessage(`连接成功：${result.message}`);
       } else {
-        setStreamFormMessage(`连接失败：${result.message}`);
+        // 错误码对应的文案不需要参数时优先使用，否则沿用后端消息
+        const template = result.code ? COMMAND_ERROR_MESSAGES[result.code] : undefined;
+        const message = template && !template.includes("{") ? template : result.message;
+        setStreamFormMessage(`连接失败：${message}`);
       }
     } catch (error) {
       setStreamFormMessage(`连接异常：${parseMessage(error)}`);
@@ -3852,6 +5320,66 @@ export default function App() {
     }
   };
 
+  // 提交问题时可直接粘贴的诊断信息
+  const copyDiagnostics = async () => {
+    try {
+      const diagnostics = await invoke<Record<string, unknown>>("get_diagnostics");
+      await navigator.clipboard.writeText(JSON.stringify(diagnostics, null, 2));
+      setScanMessage("诊断信息已复制到剪贴板。");
+    } catch (error) {
+      setScanMessage(`获取诊断信息失败：${parseMessage(error)}`);
+    }
+  };
+
+  const exportStreamServers = async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    try {
+      const path = await save({
+        title: "导出服务器配置",
+        defaultPath: "bayin-servers.json",
+        filters: [{ name: "JSON", extensions: ["json"] }],
+      });
+      if (!path) {
+        return;
+      }
+      const count = await invoke<number>("db_export_stream_servers", {
+        path,
+        password: serverBackupPassword || null,
+      });
+      setStreamFormMessage(`已导出 ${count} 个服务器${serverBackupPassword ? "（已加密）" : ""}。`);
+    } catch (error) {
+      setStreamFormMessage(`导出失败：${parseMessage(error)}`);
+    }
+  };
+
+  const importStreamServers = async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    try {
+      const path = await open({
+        multiple: false,
+        title: "导入服务器配置",
+        filters: [{ name: "JSON", extensions: ["json"] }],
+      });
+      if (typeof path !== "string") {
+        return;
+      }
+      const count = await invoke<number>("db_import_stream_servers", {
+        path,
+        password: serverBackupPassword || null,
+      });
+      setStreamFormMessage(`已导入 ${count} 个服务器。`);
+      await refreshLibrary();
+    } catch (error) {
+      setStreamFormMessage(`导入失败：${parseMessage(error)}`);
+    }
+  };
+
   const addDirectory = async () => {
     if (!isTauriEnv) {
       setScanMessage("当前是浏览器预览模式，请使用桌面端选择目录。");
@@ -3877,9 +5405,19 @@ export default function App() {
 
   const removeDirectory = (path: string) => {
     setDirectories((previous) => previous.filter((directory) => directory !== path));
+    setHashCheckDirectories((previous) => previous.filter((directory) => directory !== path));
+  };
+
+  const toggleHashCheckDirectory = (path: string) => {
+    setHashCheckDirectories((previous) =>
+      previous.includes(path)
+        ? previous.filter((directory) => directory !== path)
+        : [...previous, path],
+    );
   };
 
-  const startScan = async () => {
+  // dryRun: 只计算将新增/更新/移除的歌曲，不写数据库，也不保存扫描配置
+  const startScan = async (dryRun = false) => {
     if (!directories.length) {
       setScanMessage("请先添加至少一个文件夹。");
       return;
@@ -3891,7 +5429,7 @@ export default function App() {
     }
 
     setScanRunning(true);
-    setScanMessage("准备开始扫描...");
+    setScanMessage(dryRun ? "正在预览扫描结果..." : "准备开始扫描...");
 
     try {
       const config: ScanConfig = {
@@ -3900,6 +5438,7 @@ export default function App() {
         skipShort: skipShortAudio,
         minDuration,
         lastScanAt: null,
+        hashCheckDirectories,
       };
 
       const options: LocalScanOptions = {
@@ -3907,15 +5446,28 @@ export default function App() {
         mode: scanMode,
         minDuration: skipShortAudio ? minDuration : 0,
         batchSize: 500,
+        hashCheckDirectories,
+        dryRun,
       };
 
+      if (dryRun) {
+        const result = await invoke<ScanResult>("scan_local_to_db", { options });
+        setScanMessage(
+          `预览：将新增 ${result.added}，更新 ${result.updated}，移除 ${result.removed}，跳过 ${result.skipped}（未写入数据库）。`,
+        );
+        return;
+      }
+
       const [result] = await Promise.all([
         invoke<ScanResult>("scan_local_to_db", { options }),
         invoke<void>("db_save_scan_config", { config }),
       ]);
 
+      const cycleNote = result.skippedCycles.length
+        ? `，跳过 ${result.skippedCycles.length} 个循环链接目录`
+        : "";
       setScanMessage(
-        `扫描完成：新增 ${result.added}，更新 ${result.updated}，移除 ${result.removed}，跳过 ${result.skipped}。`,
+        `扫描完成：新增 ${result.added}，更新 ${result.updated}，移动 ${result.moved}，移除 ${result.removed}，跳过 ${result.skipped}${cycleNote}。`,
       );
 
       await refreshLibrary();
@@ -3940,49 +5492,165 @@ export default function App() {
     }
   };
 
-  const cleanupMissingSongs = async () => {
+  const analyzeDynamics = async () => {
     if (!isTauriEnv) {
       return;
     }
 
+    let unlisten: UnlistenFn | undefined;
     try {
-      const removed = await invoke<number>("cleanup_missing_songs");
-      setScanMessage(`已清理 ${removed} 条失效歌曲记录。`);
-      await refreshLibrary();
+      unlisten = await listen<DynamicsProgress>("dynamics:progress", (event) => {
+        setScanMessage(`正在分析动态范围 ${event.payload.current}/${event.payload.total}…`);
+      });
+      const result = await invoke<DynamicsAnalysisResult>("library_analyze_dynamics");
+      setScanMessage(
+        result.failed
+          ? `已分析 ${result.analyzed} 首歌曲，${result.failed} 首无法解码。`
+          : `已分析 ${result.analyzed} 首歌曲的动态范围。`,
+      );
     } catch (error) {
-      setScanMessage(`清理失效歌曲失败：${parseMessage(error)}`);
+      setScanMessage(`动态范围分析失败：${parseMessage(error)}`);
+    } finally {
+      unlisten?.();
     }
   };
 
-  const clearMusicLibrary = async () => {
+  const analyzeLoudness = async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    let unlisten: UnlistenFn | undefined;
+    try {
+      unlisten = await listen<AnalysisProgress>("analysis-progress", (event) => {
+        setScanMessage(`正在分析响度 ${event.payload.current}/${event.payload.total}…`);
+      });
+      const result = await invoke<LoudnessAnalysisResult>("library_analyze_loudness");
+      setScanMessage(
+        result.failed
+          ? `已分析 ${result.analyzed} 首歌曲的响度，${result.failed} 首无法解码。`
+          : `已分析 ${result.analyzed} 首歌曲的响度。`,
+      );
+    } catch (error) {
+      setScanMessage(`响度分析失败：${parseMessage(error)}`);
+    } finally {
+      unlisten?.();
+    }
+  };
+
+  const previewOrganize = async (template = organizeTemplate) => {
     if (!isTauriEnv) {
       return;
     }
 
+    setOrganizeBusy(true);
+    setOrganizeError("");
+    try {
+      const preview = await invoke<OrganizePreview>("library_organize_preview", { template });
+      setOrganizePreview(preview);
+      localStorage.setItem("organize_template", template);
+    } catch (error) {
+      setOrganizePreview(null);
+      setOrganizeError(parseMessage(error));
+    } finally {
+      setOrganizeBusy(false);
+    }
+  };
+
+  const openOrganizeDialog = () => {
+    setOrganizeDialogOpen(true);
+    void previewOrganize();
+  };
+
+  const closeOrganizeDialog = () => {
+    if (organizeBusy) {
+      return;
+    }
+    setOrganizeDialogOpen(false);
+    setOrganizePreview(null);
+    setOrganizeError("");
+  };
+
+  const applyOrganize = async () => {
+    if (!isTauriEnv || !organizePreview) {
+      return;
+    }
+
+    const pending = organizePreview.entries.filter((entry) => !entry.conflict).length;
     const confirmed =
       typeof window !== "undefined"
-        ? window.confirm("该操作会清空音乐库记录和封面缓存，是否继续？")
-        : false;
-
+        ? window.confirm(`将移动 ${pending} 个文件，此操作会改变磁盘上的文件位置。确定继续？`)
+        : true;
     if (!confirmed) {
       return;
     }
 
+    setOrganizeBusy(true);
     try {
-      const [removedSongs, removedCovers] = await Promise.all([
-        invoke<number>("db_clear_all_songs"),
-        invoke<number>("clear_cover_cache"),
-      ]);
-      setScanMessage(`已清空音乐库：删除 ${removedSongs} 首歌曲，清理 ${removedCovers} 张封面。`);
+      const result = await invoke<OrganizeResult>("library_organize_apply", { template: organizeTemplate });
+      // 本地歌曲 ID 随路径变化，先换成新 ID，避免刷新曲库时被移出播放队列
+      const remap = (id: string) => result.idMap[id] ?? id;
+      setQueueSongIds((previous) => previous.map(remap));
+      setCurrentSongId((previous) => (previous ? remap(previous) : previous));
       await refreshLibrary();
+      setScanMessage(
+        result.failed
+          ? `已整理 ${result.moved} 个文件，${result.failed} 个失败。`
+          : `已整理 ${result.moved} 个文件。`,
+      );
+      setOrganizeDialogOpen(false);
+      setOrganizePreview(null);
     } catch (error) {
-      setScanMessage(`清空音乐库失败：${parseMessage(error)}`);
+      setOrganizeError(parseMessage(error));
+    } finally {
+      setOrganizeBusy(false);
     }
   };
 
-  const isPlaylistDetailView = page === "playlists" && Boolean(openedPlaylist);
-  const shouldShowBack = page === "settings-ui"
+  const cleanupMissingSongs = async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    try {
+      const removed = await invoke<number>("cleanup_missing_songs");
+      setScanMessage(`已清理 ${removed} 条失效歌曲记录。`);
+      await refreshLibrary();
+    } catch (error) {
+      setScanMessage(`清理失效歌曲失败：${parseMessage(error)}`);
+    }
+  };
+
+  const clearMusicLibrary = async () => {
+    if (!isTauriEnv) {
+      return;
+    }
+
+    const confirmed =
+      typeof window !== "undefined"
+        ? window.confirm("该操作会清空音乐库记录和封面缓存，是否继续？")
+        : false;
+
+    if (!confirmed) {
+      return;
+    }
+
+    try {
+      const [removedSongs, removedCovers] = await Promise.all([
+        invoke<number>("db_clear_all_songs"),
+        invoke<number>("clear_cover_cache"),
+      ]);
+      setScanMessage(`已清空音乐库：删除 ${removedSongs} 首歌曲，清理 ${removedCovers} 张封面。`);
+      await refreshLibrary();
+    } catch (error) {
+      setScanMessage(`清空音乐库失败：${parseMessage(error)}`);
+    }
+  };
+
+  const isPlaylistDetailView = page === "playlists" && Boolean(openedPlaylist);
+  const shouldShowBack = page === "settings-ui"
     || page === "settings-lyrics"
+    || page === "settings-downloads"
     || page === "stream-config"
     || isPlaylistDetailView;
   const showSongsSearchBar = page === "songs" && songsSearchMode;
@@ -4000,6 +5668,7 @@ export default function App() {
     || page === "settings"
     || page === "settings-ui"
     || page === "settings-lyrics"
+    || page === "settings-downloads"
     || page === "stream-config";
   const pageTitleText = isPlaylistDetailView && openedPlaylist ? openedPlaylist.name : PAGE_TITLE[page];
 
@@ -4468,7 +6137,7 @@ export default function App() {
                     {coverUrl ? <img src={coverUrl} alt={album.name} className="cover-image" /> : null}
                   </div>
                   <h3>{album.name}</h3>
-                  <p>{album.artist}</p>
+                  <p>{album.year ? `${album.artist} · ${album.year}` : album.artist}</p>
                   <p className="cover-meta">{album.songCount} songs</p>
                 </article>
               );
@@ -4716,6 +6385,14 @@ export default function App() {
               {directories.map((directory) => (
                 <div key={directory} className="directory-item">
                   <span title={directory}>{directory}</span>
+                  <button
+                    type="button"
+                    className={`directory-hash-btn${hashCheckDirectories.includes(directory) ? " active" : ""}`}
+                    title="用文件大小和部分内容哈希检测变更（适用于修改时间不可靠的 NAS/SMB 目录）"
+                    onClick={() => toggleHashCheckDirectory(directory)}
+                  >
+                    哈希
+                  </button>
                   <button
                     type="button"
                     className="directory-remove-btn"
@@ -4766,12 +6443,21 @@ export default function App() {
         <button
           type="button"
           className="primary-btn full scan-start-btn"
-          onClick={startScan}
+          onClick={() => void startScan()}
           disabled={scanRunning}
         >
           {scanRunning ? "扫描中..." : "开始扫描"}
         </button>
 
+        <button
+          type="button"
+          className="ghost-btn full scan-preview-btn"
+          onClick={() => void startScan(true)}
+          disabled={scanRunning}
+        >
+          预览变更
+        </button>
+
         {scanMessage ? <p className="status-text">{scanMessage}</p> : null}
       </div>
     </section>
@@ -4885,6 +6571,43 @@ export default function App() {
             </button>
           </div>
 
+          {isTauriEnv ? (
+            <div className="stream-config-backup">
+              <label className="stream-config-field">
+                <span>备份密码（可选，用于加密导出文件）</span>
+                <input
+                  type="password"
+                  value={serverBackupPassword}
+                  onChange={(event) => setServerBackupPassword(event.target.value)}
+                  placeholder="留空则不加密"
+                />
+              </label>
+              <div className="stream-config-actions-row">
+                <button
+                  type="button"
+                  className="stream-config-clear-btn"
+                  onClick={() => {
+                    void exportStreamServers();
+                  }}
+                  disabled={streamSaving || streamTesting}
+                >
+                  <span>导出配置</span>
+                </button>
+                <button
+                  type="button"
+                  className="stream-config-clear-btn"
+                  onClick={() => {
+                    void importStreamServers();
+                  }}
+                  disabled={streamSaving || streamTesting}
+                >
+                  <LineIcon name="download" />
+                  <span>导入配置</span>
+                </button>
+              </div>
+            </div>
+          ) : null}
+
           {streamFormMessage ? <p className="status-text stream-config-status">{streamFormMessage}</p> : null}
         </div>
       </section>
@@ -5086,6 +6809,26 @@ export default function App() {
           <span className="settings-item-main"><strong>在线歌词</strong></span>
           <span>›</span>
         </button>
+        {isTauriEnv ? (
+          <button type="button" className="settings-item rich" onClick={() => go("settings-downloads")}>
+            <span className="settings-icon orange"><LineIcon name="download" /></span>
+            <span className="settings-item-main"><strong>离线下载</strong></span>
+            <span>›</span>
+          </button>
+        ) : null}
+        {isTauriEnv ? (
+          <button
+            type="button"
+            className="settings-item rich"
+            onClick={() => {
+              void copyDiagnostics();
+            }}
+          >
+            <span className="settings-icon blue"><LineIcon name="stats" /></span>
+            <span className="settings-item-main"><strong>复制诊断信息</strong></span>
+            <span>›</span>
+          </button>
+        ) : null}
         <button
           type="button"
           className="settings-item rich"
@@ -5140,6 +6883,33 @@ export default function App() {
           <span>›</span>
         </button>
 
+        <button type="button" className="settings-item rich" onClick={() => { void analyzeDynamics(); }}>
+          <span className="settings-icon gray"><LineIcon name="stats" /></span>
+          <span className="settings-item-main">
+            <strong>分析动态范围</strong>
+            <small>计算本地歌曲的 DR 值，可按动态范围排序</small>
+          </span>
+          <span>›</span>
+        </button>
+
+        <button type="button" className="settings-item rich" onClick={() => { void analyzeLoudness(); }}>
+          <span className="settings-icon gray"><LineIcon name="stats" /></span>
+          <span className="settings-item-main">
+            <strong>分析响度</strong>
+            <small>测量 EBU R128 响度，无 ReplayGain 标签的歌曲也能音量均衡</small>
+          </span>
+          <span>›</span>
+        </button>
+
+        <button type="button" className="settings-item rich" onClick={openOrganizeDialog}>
+          <span className="settings-icon gray"><LineIcon name="folder" /></span>
+          <span className="settings-item-main">
+            <strong>整理文件</strong>
+            <small>按标签重命名并移动本地歌曲文件</small>
+          </span>
+          <span>›</span>
+        </button>
+
         <button type="button" className="settings-item rich" onClick={cleanupMissingSongs}>
           <span className="settings-icon gray"><LineIcon name="trash" /></span>
           <span className="settings-item-main">
@@ -5310,12 +7080,607 @@ export default function App() {
           </button>
         </div>
       </article>
+
+      {isTauriEnv ? (
+        <article className="settings-card padded">
+          <p className="block-title">播放</p>
+          <div className="setting-line">
+            <span>音量均衡（ReplayGain / 服务器响度数据）</span>
+            <button
+              type="button"
+              className={`switch ${normalizationSettings.enabled ? "on" : ""}`}
+              onClick={() => {
+                void saveNormalizationSettings({ ...normalizationSettings, enabled: !normalizationSettings.enabled });
+              }}
+            >
+              <span />
+            </button>
+          </div>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>增益类型</span>
+            <select
+              className="offline-bandwidth-input"
+              value={replayGainMode}
+              disabled={!normalizationSettings.enabled}
+              onChange={(event) => {
+                const mode = event.target.value === "album" ? "album" : "track";
+                setReplayGainMode(mode);
+                localStorage.setItem("audio_replaygain_mode", mode);
+              }}
+            >
+              <option value="track">音轨增益</option>
+              <option value="album">专辑增益（保留专辑内的响度差异）</option>
+            </select>
+          </div>
+
+          <div className="setting-line setting-line-divider">
+            <span>目标响度</span>
+            <span>{normalizationSettings.targetLufs} LUFS</span>
+          </div>
+          <input
+            type="range"
+            min={-30}
+            max={-5}
+            step={1}
+            value={normalizationSettings.targetLufs}
+            disabled={!normalizationSettings.enabled}
+            onChange={(event) => {
+              void saveNormalizationSettings({ ...normalizationSettings, targetLufs: Number(event.target.value) });
+            }}
+          />
+          <p className="setting-hint">-18 LUFS 为 ReplayGain 参考响度，-14 LUFS 与主流流媒体平台一致。</p>
+
+          <div className="setting-line setting-line-divider">
+            <span>限幅器（提升增益时防止削波）</span>
+            <button
+              type="button"
+              className={`switch ${normalizationSettings.limiter ? "on" : ""}`}
+              disabled={!normalizationSettings.enabled}
+              onClick={() => {
+                void saveNormalizationSettings({ ...normalizationSettings, limiter: !normalizationSettings.limiter });
+              }}
+            >
+              <span />
+            </button>
+          </div>
+
+          <div className="setting-line setting-line-divider">
+            <span>前置增益</span>
+            <span>{normalizationSettings.preampDb > 0 ? "+" : ""}{normalizationSettings.preampDb} dB</span>
+          </div>
+          <input
+            type="range"
+            min={-15}
+            max={15}
+            step={1}
+            value={normalizationSettings.preampDb}
+            disabled={!normalizationSettings.enabled}
+            onChange={(event) => {
+              void saveNormalizationSettings({ ...normalizationSettings, preampDb: Number(event.target.value) });
+            }}
+          />
+
+          <div className="setting-line setting-line-divider">
+            <span>多声道输出（5.1 / 7.1）</span>
+            <button
+              type="button"
+              className={`switch ${multichannelOutput ? "on" : ""}`}
+              onClick={() => {
+                const v = !multichannelOutput;
+                setMultichannelOutput(v);
+                localStorage.setItem("audio_multichannel", String(v));
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">关闭时多声道音源缩混为立体声；开启后按原声道布局输出，设备声道不足时自动缩混。</p>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>重采样质量</span>
+            <select
+              className="offline-bandwidth-input"
+              value={resamplerQuality}
+              onChange={(event) => {
+                const quality = event.target.value as ResamplerQuality;
+                setResamplerQuality(quality);
+                localStorage.setItem("audio_resampler_quality", quality);
+              }}
+            >
+              <option value="fast">快速（低 CPU 占用）</option>
+              <option value="normal">标准</option>
+              <option value="high">高（长 sinc 滤波器）</option>
+            </select>
+          </div>
+          <p className="setting-hint">仅在音源采样率与设备不一致时生效。</p>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>输出采样率</span>
+            <select
+              className="offline-bandwidth-input"
+              value={outputSampleRate}
+              onChange={(event) => {
+                setOutputSampleRate(event.target.value);
+                localStorage.setItem("audio_output_sample_rate", event.target.value);
+              }}
+            >
+              <option value="auto">自动</option>
+              <option value="source">始终跟随音源</option>
+              <option value="44100">固定 44.1 kHz</option>
+              <option value="48000">固定 48 kHz</option>
+              <option value="88200">固定 88.2 kHz</option>
+              <option value="96000">固定 96 kHz</option>
+              <option value="176400">固定 176.4 kHz</option>
+              <option value="192000">固定 192 kHz</option>
+            </select>
+          </div>
+          <p className="setting-hint">自动：按开始播放的歌曲打开设备，无缝衔接的下一首采样率不同时重采样。始终跟随：采样率变化时重新打开设备（切换时有短暂停顿）。固定：所有音源重采样到所选采样率，设备不支持时使用最接近的采样率。</p>
+
+          <div className="setting-line setting-line-divider">
+            <span>防削波限幅器</span>
+            <button
+              type="button"
+              className={`switch ${limiterEnabled ? "on" : ""}`}
+              onClick={() => {
+                const v = !limiterEnabled;
+                setLimiterEnabled(v);
+                localStorage.setItem("audio_limiter_enabled", String(v));
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">均衡器或人声消除提升音量时柔和压低峰值，避免爆音；响度均衡提升与夜间模式始终启用限幅。</p>
+
+          <div className="setting-line setting-line-divider">
+            <span>抖动（TPDF）</span>
+            <button
+              type="button"
+              className={`switch ${ditherEnabled ? "on" : ""}`}
+              onClick={() => {
+                const v = !ditherEnabled;
+                setDitherEnabled(v);
+                localStorage.setItem("audio_dither_enabled", String(v));
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">输出设备位深低于音源（如 24 bit 音源输出到 16 bit 设备）时加入抖动，减少低音量段的量化失真。</p>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>DSD 输出</span>
+            <select
+              className="offline-bandwidth-input"
+              value={dsdOutput}
+              onChange={(event) => {
+                const output = event.target.value === "dop" ? "dop" : "pcm";
+                setDsdOutput(output);
+                localStorage.setItem("audio_dsd_output", output);
+              }}
+            >
+              <option value="pcm">转换为 PCM</option>
+              <option value="dop">DoP（需 DAC 支持）</option>
+            </select>
+          </div>
+          <p className="setting-hint">DoP 原样传输 DSD 数据，不经过均衡器与音量调节；设备不支持所需采样率时自动转换为 PCM。从下一首 DSD 歌曲开始生效。</p>
+
+          <div className="setting-line setting-line-divider">
+            <span>跳过静音</span>
+            <button
+              type="button"
+              className={`switch ${skipSilence ? "on" : ""}`}
+              onClick={() => {
+                const v = !skipSilence;
+                setSkipSilence(v);
+                localStorage.setItem("audio_skip_silence", String(v));
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">跳过歌曲开头的静音，结尾和曲中超过 2 秒的静音会被缩短。</p>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>结尾淡出</span>
+            <select
+              className="offline-bandwidth-input"
+              value={endFadeSecs}
+              onChange={(event) => {
+                const secs = Number(event.target.value);
+                setEndFadeSecs(secs);
+                localStorage.setItem("audio_end_fade_secs", String(secs));
+              }}
+            >
+              <option value={0}>关闭</option>
+              <option value={3}>3 秒</option>
+              <option value={5}>5 秒</option>
+              <option value={8}>8 秒</option>
+              <option value={10}>10 秒</option>
+              <option value={15}>15 秒</option>
+            </select>
+          </div>
+          <p className="setting-hint">每首歌的最后几秒逐渐减弱到静音，适合电台式连续收听；按解码得到的实际时长（或设置的结束点）计算。</p>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>音量曲线</span>
+            <select
+              className="offline-bandwidth-input"
+              value={volumeCurve}
+              onChange={(event) => {
+                const curve = event.target.value === "linear" ? "linear" : "logarithmic";
+                setVolumeCurve(curve);
+                localStorage.setItem("audio_volume_curve", curve);
+              }}
+            >
+              <option value="logarithmic">对数（按听感）</option>
+              <option value="linear">线性</option>
+            </select>
+          </div>
+          <p className="setting-hint">对数曲线让音量滑块每一段的响度变化更均匀；线性曲线按滑块比例直接缩放音量。</p>
+        </article>
+      ) : null}
+
+      {isTauriEnv ? (
+        <article className="settings-card padded">
+          <div className="setting-line">
+            <p className="block-title">输出链路</p>
+            <button type="button" className="ghost-btn" onClick={() => { void loadOutputInfo(); }}>刷新</button>
+          </div>
+          {outputInfo ? (
+            <>
+              <div className="setting-line setting-line-divider">
+                <span>输出设备</span>
+                <span>{outputInfo.deviceName || "未知设备"}</span>
+              </div>
+              <div className="setting-line setting-line-divider">
+                <span>音源格式</span>
+                <span>{outputInfo.sourceSampleRate} Hz · {outputInfo.sourceChannels} 声道</span>
+              </div>
+              <div className="setting-line setting-line-divider">
+                <span>输出格式</span>
+                <span>{outputInfo.sampleRate} Hz · {outputInfo.channels} 声道</span>
+              </div>
+              <div className="setting-line setting-line-divider">
+                <span>重采样</span>
+                <span>{outputInfo.resampling ? "是" : "否"}</span>
+              </div>
+              <div className="setting-line setting-line-divider">
+                <span>均衡器</span>
+                <span>{outputInfo.eqEnabled ? "在路径中" : "已旁路"}</span>
+              </div>
+              <div className="setting-line setting-line-divider">
+                <span>其他处理（人声消除 / 夜间模式 / 音量均衡）</span>
+                <span>{outputInfo.otherDsp ? "在路径中" : "无"}</span>
+              </div>
+              <p className="setting-hint">
+                {outputInfo.bitPerfect
+                  ? "比特完美：音频数据未经修改直接送往设备。"
+                  : "非比特完美：关闭重采样和音效处理，并将音量设为 100% 可实现比特完美输出。"}
+              </p>
+            </>
+          ) : (
+            <p className="setting-hint">{outputInfo === null ? "当前没有播放，开始播放后点击刷新。" : "暂无输出信息。"}</p>
+          )}
+        </article>
+      ) : null}
+
+      {isTauriEnv && jackPorts !== null && jackPorts !== undefined ? (
+        <article className="settings-card padded">
+          <p className="block-title">音频输出</p>
+          <div className="setting-line">
+            <span>JACK / PipeWire 输出</span>
+            <button
+              type="button"
+              className={`switch ${outputBackend.backend === "jack" ? "on" : ""}`}
+              onClick={() => {
+                saveOutputBackend({ ...outputBackend, backend: outputBackend.backend === "jack" ? "system" : "jack" });
+              }}
+            >
+              <span />
+            </button>
+          </div>
+
+          <div className="setting-line with-gap setting-line-divider">
+            <span>客户端名称</span>
+            <input
+              key={outputBackend.clientName}
+              type="text"
+              className="offline-bandwidth-input jack-client-input"
+              defaultValue={outputBackend.clientName}
+              disabled={outputBackend.backend !== "jack"}
+              onBlur={(event) => {
+                const clientName = event.target.value.trim() || DEFAULT_OUTPUT_BACKEND.clientName;
+                if (clientName !== outputBackend.clientName) {
+                  saveOutputBackend({ ...outputBackend, clientName });
+                }
+              }}
+            />
+          </div>
+
+          {["左声道", "右声道"].map((label, index) => (
+            <div key={label} className="setting-line with-gap setting-line-divider">
+              <span>{label}</span>
+              <select
+                className="offline-bandwidth-input jack-port-select"
+                value={outputBackend.ports[index] ?? ""}
+                disabled={outputBackend.backend !== "jack"}
+                onChange={(event) => {
+                  const ports = [0, 1].map((channel) => (channel === index ? event.target.value : outputBackend.ports[channel] ?? ""));
+                  saveOutputBackend({ ...outputBackend, ports: ports.some(Boolean) ? ports : [] });
+                }}
+              >
+                <option value="">自动连接（system:playback）</option>
+                {[...new Set([...jackPorts, outputBackend.ports[index] ?? ""])].filter(Boolean).map((port) => (
+                  <option key={port} value={port}>{port}</option>
+                ))}
+              </select>
+            </div>
+          ))}
+          <p className="setting-hint">
+            {jackPortsError
+              ? `无法连接 JACK 服务：${jackPortsError}`
+              : "PipeWire 用户需安装 pipewire-jack。指定端口后不再自动连接系统输出。"}
+          </p>
+        </article>
+      ) : null}
+
+      {isTauriEnv && shellIntegration?.supported ? (
+        <article className="settings-card padded">
+          <p className="block-title">系统集成</p>
+          <div className="setting-line">
+            <span>文件管理器右键菜单</span>
+            <button
+              type="button"
+              className={`switch ${shellIntegration.installed ? "on" : ""}`}
+              onClick={() => {
+                void toggleShellIntegration(!shellIntegration.installed);
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">在音频文件或文件夹上右键选择“添加到 BaYin 队列”，即可追加到当前播放队列。</p>
+        </article>
+      ) : null}
+
+      {isTauriEnv && telemetrySummary ? (
+        <article className="settings-card padded">
+          <p className="block-title">隐私</p>
+          <div className="setting-line">
+            <span>匿名使用统计</span>
+            <button
+              type="button"
+              className={`switch ${telemetrySummary.enabled ? "on" : ""}`}
+              onClick={() => {
+                void setTelemetryEnabled(!telemetrySummary.enabled);
+              }}
+            >
+              <span />
+            </button>
+          </div>
+          <p className="setting-hint">
+            仅统计功能使用次数和错误类型，不包含文件路径、歌曲信息或服务器地址。
+            {telemetrySummary.uploadAvailable ? "开启后定期批量上传，用于改进应用。" : "当前版本不会上传任何数据。"}
+          </p>
+          {telemetrySummary.enabled ? (
+            <div className="setting-line setting-line-divider">
+              <span>待上传 {telemetrySummary.pending.reduce((sum, item) => sum + item.count, 0)} 次记录</span>
+              <button
+                type="button"
+                className="ghost-btn"
+                disabled={telemetrySummary.pending.length === 0}
+                onClick={() => {
+                  void clearTelemetry();
+                }}
+              >
+                清除
+              </button>
+            </div>
+          ) : null}
+        </article>
+      ) : null}
     </section>
   );
 
   const renderSettingsLyricsPage = () => (
     <section className="settings-ui-page">
       {renderOnlineLyricSettingsCard()}
+      {isTauriEnv && providerList ? (
+        <article className="settings-card padded">
+          <p className="block-title">来源插件</p>
+          {providerList.providers.filter((provider) => !provider.builtin).map((provider) => (
+            <div key={provider.id} className="setting-line">
+              <span>{provider.name}</span>
+              <span>
+                {provider.kinds
+                  .map((kind) => (kind === "lyrics" ? "歌词" : kind === "cover" ? "封面" : "元数据"))
+                  .join(" / ")}
+              </span>
+            </div>
+          ))}
+          {providerList.errors.map((error) => (
+            <p key={error.path} className="setting-hint">加载失败：{error.path}（{error.message}）</p>
+          ))}
+          <p className="setting-hint">将 .rhai 脚本放入 {providerList.scriptDir} 后重新加载即可添加来源。</p>
+          <div className="setting-line setting-line-divider">
+            <span>已安装 {providerList.providers.filter((provider) => !provider.builtin).length} 个插件</span>
+            <button
+              type="button"
+              className="ghost-btn"
+              onClick={() => {
+                void reloadProviders();
+              }}
+            >
+              重新加载
+            </button>
+          </div>
+        </article>
+      ) : null}
+    </section>
+  );
+
+  const renderSettingsDownloadsPage = () => (
+    <section className="settings-ui-page">
+      <article className="settings-card padded">
+        <p className="block-title">下载限制</p>
+        <div className="setting-line">
+          <span>同时下载</span>
+          <span>{downloadSettings.maxConcurrent} 首</span>
+        </div>
+        <input
+          type="range"
+          min={1}
+          max={8}
+          value={downloadSettings.maxConcurrent}
+          onChange={(event) => {
+            void saveDownloadSettings({ ...downloadSettings, maxConcurrent: Number(event.target.value) });
+          }}
+        />
+
+        <div className="setting-line with-gap setting-line-divider">
+          <span>带宽上限（KB/s，0 为不限）</span>
+          <input
+            type="number"
+            className="offline-bandwidth-input"
+            min={0}
+            step={128}
+            value={downloadSettings.bandwidthLimitKbps}
+            onChange={(event) => {
+              const value = Math.max(0, Math.floor(Number(event.target.value) || 0));
+              void saveDownloadSettings({ ...downloadSettings, bandwidthLimitKbps: value });
+            }}
+          />
+        </div>
+      </article>
+
+      <article className="settings-card padded">
+        <p className="block-title">自动同步</p>
+        <div className="setting-line setting-line-divider">
+          <span>按规则自动保持离线</span>
+          <button
+            type="button"
+            className={`switch ${offlineSyncRules.enabled ? "on" : ""}`}
+            onClick={() => { void saveOfflineSyncRules({ ...offlineSyncRules, enabled: !offlineSyncRules.enabled }); }}
+          >
+            <span />
+          </button>
+        </div>
+
+        <div className="setting-line setting-line-divider">
+          <span>收藏的歌曲</span>
+          <button
+            type="button"
+            className={`switch ${offlineSyncRules.favorites ? "on" : ""}`}
+            onClick={() => { void saveOfflineSyncRules({ ...offlineSyncRules, favorites: !offlineSyncRules.favorites }); }}
+          >
+            <span />
+          </button>
+        </div>
+
+        <div className="setting-line">
+          <span>最近的歌单</span>
+          <span>{offlineSyncRules.recentPlaylists} 个</span>
+        </div>
+        <input
+          type="range"
+          min={0}
+          max={20}
+          value={offlineSyncRules.recentPlaylists}
+          onChange={(event) => {
+            void saveOfflineSyncRules({ ...offlineSyncRules, recentPlaylists: Number(event.target.value) });
+          }}
+        />
+
+        <div className="setting-line">
+          <span>最近播放</span>
+          <span>{offlineSyncRules.recentPlayed} 首</span>
+        </div>
+        <input
+          type="range"
+          min={0}
+          max={1000}
+          step={50}
+          value={offlineSyncRules.recentPlayed}
+          onChange={(event) => {
+            void saveOfflineSyncRules({ ...offlineSyncRules, recentPlayed: Number(event.target.value) });
+          }}
+        />
+
+        <div className="setting-line with-gap setting-line-divider">
+          <span>存储上限（MB，0 为不限）</span>
+          <input
+            type="number"
+            className="offline-bandwidth-input"
+            min={0}
+            step={1024}
+            value={offlineSyncRules.budgetMb}
+            onChange={(event) => {
+              const value = Math.max(0, Math.floor(Number(event.target.value) || 0));
+              void saveOfflineSyncRules({ ...offlineSyncRules, budgetMb: value });
+            }}
+          />
+        </div>
+
+        <button
+          type="button"
+          className="ghost-btn full"
+          disabled={offlineSyncing}
+          onClick={() => { void runOfflineSync(); }}
+        >
+          {offlineSyncing ? "同步中..." : "立即同步"}
+        </button>
+      </article>
+
+      <article className="settings-card padded">
+        <p className="block-title">下载队列</p>
+        {offlineDownloads.length ? (
+          <ul className="offline-download-list">
+            {offlineDownloads.map((item) => {
+              const percent = item.totalBytes > 0
+                ? Math.min(100, Math.round((item.downloadedBytes / item.totalBytes) * 100))
+                : 0;
+              return (
+                <li key={item.songId} className={`offline-download-item ${item.status}`}>
+                  <div className="offline-download-main">
+                    <strong>
+                      {item.title ?? item.songId}
+                      {item.auto ? <span className="offline-auto-tag">自动</span> : null}
+                    </strong>
+                    <small>
+                      {item.artist ? `${item.artist} · ` : ""}
+                      {OFFLINE_STATUS_LABEL[item.status]}
+                      {item.status === "downloading" || item.status === "paused" ? ` ${percent}%` : ""}
+                      {item.error ? `：${item.error}` : ""}
+                    </small>
+                    <div className="offline-download-progress">
+                      <span style={{ width: `${item.status === "done" ? 100 : percent}%` }} />
+                    </div>
+                  </div>
+                  <div className="offline-download-actions">
+                    {item.status === "queued" || item.status === "downloading" ? (
+                      <button type="button" className="text-btn" onClick={() => { void runOfflineAction("offline_pause", item.songId); }}>
+                        暂停
+                      </button>
+                    ) : null}
+                    {item.status === "paused" || item.status === "failed" ? (
+                      <button type="button" className="text-btn" onClick={() => { void runOfflineAction("offline_resume", item.songId); }}>
+                        继续
+                      </button>
+                    ) : null}
+                    <button type="button" className="text-btn danger" onClick={() => { void runOfflineAction("offline_remove", item.songId); }}>
+                      删除
+                    </button>
+                  </div>
+                </li>
+              );
+            })}
+          </ul>
+        ) : (
+          <p className="settings-meta-line">暂无离线歌曲，可在流媒体歌曲菜单中选择「离线下载」</p>
+        )}
+      </article>
     </section>
   );
 
@@ -5350,6 +7715,9 @@ export default function App() {
     if (page === "settings-lyrics") {
       return renderSettingsLyricsPage();
     }
+    if (page === "settings-downloads") {
+      return renderSettingsDownloadsPage();
+    }
 
     return renderSettingsPage();
   })();
@@ -5408,7 +7776,7 @@ export default function App() {
           {NAV_SYSTEM.map((item) => {
             const isActive =
               page === item.page
-              || (item.page === "settings" && (page === "settings-ui" || page === "settings-lyrics"))
+              || (item.page === "settings" && (page === "settings-ui" || page === "settings-lyrics" || page === "settings-downloads"))
               || (item.page === "scan" && page === "stream-config");
 
             return (
@@ -5664,7 +8032,7 @@ export default function App() {
           <div className="player-center-wrap">
             <div className="player-center">
               <button type="button" className="icon-btn subtle" aria-label="上一首" onClick={() => { void playPrevious(); }}><LineIcon name="prev" /></button>
-              <button type="button" className="play-main-btn" aria-label={isPlaying ? "暂停" : "播放"} onClick={() => { void togglePlayPause(); }} disabled={isResolvingSong}>{isPlaying ? <LineIcon name="pause" /> : <LineIcon name="play" />}</button>
+              <button type="button" className={`play-main-btn ${isBuffering ? "buffering" : ""}`} aria-label={isBuffering ? "缓冲中" : isPlaying ? "暂停" : "播放"} onClick={() => { void togglePlayPause(); }} disabled={isResolvingSong}>{isPlaying ? <LineIcon name="pause" /> : <LineIcon name="play" />}</button>
               <button type="button" className="icon-btn subtle" aria-label="下一首" onClick={() => { void playNext(); }}><LineIcon name="next" /></button>
             </div>
           </div>
@@ -5811,14 +8179,38 @@ export default function App() {
           onEqualizerGainChange={handleEqualizerGainChange}
           onEqualizerApplyPreset={handleEqualizerApplyPreset}
           onEqualizerReset={handleEqualizerReset}
+          userEqPresets={userEqPresets}
+          onSaveEqPreset={isTauriEnv ? (name) => void handleSaveEqPreset(name) : undefined}
+          onDeleteEqPreset={isTauriEnv ? (name) => void handleDeleteEqPreset(name) : undefined}
+          vocalReductionEnabled={vocalReductionEnabled}
+          vocalReductionStrength={vocalReductionStrength}
+          onVocalReductionChange={isTauriEnv ? handleVocalReductionChange : undefined}
+          nightModeEnabled={nightModeEnabled}
+          nightModePreset={nightModePreset}
+          onNightModeChange={isTauriEnv ? handleNightModeChange : undefined}
+          waveform={currentSong ? streamWaveforms[currentSong.id] : undefined}
+          onSetCuePoint={isTauriEnv ? (kind) => void handleSetCuePoint(kind) : undefined}
+          abLoopStart={abLoop.start}
+          abLoopActive={abLoop.active}
+          onSetAbLoop={isTauriEnv ? (kind) => void handleSetAbLoop(kind) : undefined}
         />
       )}
 
       {showQueuePanel ? (
         <section className="floating-panel queue-panel">
           <div className="floating-panel-head">
-            <h3>播放队列</h3>
+            <h3>播放队列{radioActive ? <span className="queue-radio-tag">电台</span> : null}</h3>
             <div>
+              {isTauriEnv ? (
+                <>
+                  <button type="button" className="text-btn" disabled={!currentSongId} onClick={() => { void copyNowPlaying(); }}>
+                    复制正在播放
+                  </button>
+                  <button type="button" className="text-btn" disabled={!queueSongs.length} onClick={() => { void exportQueue(); }}>
+                    导出
+                  </button>
+                </>
+              ) : null}
               <button type="button" className="text-btn" onClick={clearQueue}>清空</button>
               <button type="button" className="icon-btn subtle" onClick={() => setShowQueuePanel(false)}>×</button>
             </div>
@@ -5910,7 +8302,7 @@ export default function App() {
             </div>
 
             <div className="lyric-source-tabs">
-              {(DEFAULT_LYRIC_PROVIDER_ORDER).map((provider) => (
+              {[...DEFAULT_LYRIC_PROVIDER_ORDER, ...pluginLyricProviders].map((provider) => (
                 <button
                   key={provider}
                   type="button"
@@ -5918,7 +8310,7 @@ export default function App() {
                   onClick={() => setLyricSourceDialogProvider(provider)}
                 >
                   {resolveLyricProviderLabel(provider)}
-                  <span>{lyricSourceDialogProviderCounts[provider]}</span>
+                  <span>{lyricSourceDialogProviderCounts[provider] ?? 0}</span>
                 </button>
               ))}
             </div>
@@ -5991,6 +8383,81 @@ export default function App() {
         </div>
       ) : null}
 
+      {organizeDialogOpen ? (
+        <div className="overlay" data-no-drag="true" onClick={closeOrganizeDialog}>
+          <section className="lyric-source-dialog organize-dialog" data-no-drag="true" onClick={(event) => event.stopPropagation()}>
+            <div className="lyric-source-dialog-head">
+              <h3>整理文件</h3>
+              <button type="button" className="icon-btn subtle" onClick={closeOrganizeDialog}>×</button>
+            </div>
+
+            <div className="lyric-source-search">
+              <input
+                type="text"
+                value={organizeTemplate}
+                onChange={(event) => setOrganizeTemplate(event.target.value)}
+                onKeyDown={(event) => {
+                  if (event.key === "Enter") {
+                    void previewOrganize();
+                  }
+                }}
+                placeholder={DEFAULT_ORGANIZE_TEMPLATE}
+              />
+              <button
+                type="button"
+                className="primary-btn lyric-source-search-btn"
+                onClick={() => {
+                  void previewOrganize();
+                }}
+                disabled={organizeBusy}
+              >
+                预览
+              </button>
+            </div>
+            <p className="setting-hint">
+              可用占位符：{"{albumartist} {artist} {album} {title} {track} {disc} {year} {genre} {ext}"}，路径相对于所在的音乐文件夹。
+            </p>
+
+            {organizeError ? <p className="lyric-source-error">{organizeError}</p> : null}
+
+            <div className="lyric-source-list organize-list">
+              {organizeBusy && !organizePreview ? (
+                <p className="lyric-source-empty">正在计算目标路径...</p>
+              ) : organizePreview?.entries.length ? (
+                organizePreview.entries.map((entry) => (
+                  <article key={entry.songId} className={`organize-item ${entry.conflict ? "conflict" : ""}`}>
+                    <small>{entry.from}</small>
+                    {entry.to ? <strong>→ {entry.to}</strong> : null}
+                    {entry.conflict ? <span className="organize-conflict">{entry.conflict}</span> : null}
+                  </article>
+                ))
+              ) : organizePreview ? (
+                <p className="lyric-source-empty">所有文件都已符合模板</p>
+              ) : null}
+            </div>
+
+            {organizePreview ? (
+              <div className="organize-footer">
+                <span>
+                  待移动 {organizePreview.entries.filter((entry) => !entry.conflict).length} · 冲突{" "}
+                  {organizePreview.entries.filter((entry) => entry.conflict).length} · 无需变动 {organizePreview.unchanged}
+                </span>
+                <button
+                  type="button"
+                  className="primary-btn"
+                  onClick={() => {
+                    void applyOrganize();
+                  }}
+                  disabled={organizeBusy || !organizePreview.entries.some((entry) => !entry.conflict)}
+                >
+                  {organizeBusy ? "整理中..." : "开始整理"}
+                </button>
+              </div>
+            ) : null}
+          </section>
+        </div>
+      ) : null}
+
       {songsSortDialogOpen ? (
         <div className="overlay" onClick={closeSongsSortDialog}>
           <section className="songs-sort-dialog" data-no-drag="true" onClick={(event) => event.stopPropagation()}>
@@ -6098,6 +8565,12 @@ export default function App() {
                 <LineIcon name="next" />
                 <span>下一首播放</span>
               </button>
+              {isTauriEnv ? (
+                <button type="button" className="song-context-item" onClick={() => { void startRadio(songMenuSong.id); }}>
+                  <LineIcon name="radio" />
+                  <span>从这首歌开始电台</span>
+                </button>
+              ) : null}
               <button type="button" className="song-context-item" onClick={() => jumpToSongArtist(songMenuSong)}>
                 <LineIcon name="artists" />
                 <span>查看艺术家</span>
@@ -6106,6 +8579,47 @@ export default function App() {
                 <LineIcon name="albums" />
                 <span>查看专辑</span>
               </button>
+              {isTauriEnv ? (
+                <button
+                  type="button"
+                  className="song-context-item"
+                  onClick={() => {
+                    const next = !songMenuFavorite;
+                    void invoke("db_set_song_extra", { songId: songMenuSong.id, key: "favorite", value: next ? true : null })
+                      .catch((error) => setScanMessage(`收藏失败：${parseMessage(error)}`));
+                    closeSongMenu();
+                  }}
+                >
+                  <LineIcon name="heart" />
+                  <span>{songMenuFavorite ? "取消收藏" : "收藏"}</span>
+                </button>
+              ) : null}
+              {isTauriEnv && songMenuSong.sourceType === "stream" ? (
+                <button
+                  type="button"
+                  className="song-context-item"
+                  onClick={() => {
+                    void downloadSongForOffline(songMenuSong.id);
+                    closeSongMenu();
+                  }}
+                >
+                  <LineIcon name="download" />
+                  <span>离线下载</span>
+                </button>
+              ) : null}
+              {isTauriEnv ? (
+                <button
+                  type="button"
+                  className="song-context-item"
+                  onClick={() => {
+                    void exportSongAudio(songMenuSong);
+                    closeSongMenu();
+                  }}
+                >
+                  <LineIcon name="download" />
+                  <span>导出音频</span>
+                </button>
+              ) : null}
               <button type="button" className="song-context-item" onClick={() => openSongInfo(songMenuSong.id)}>
                 <LineIcon name="about" />
                 <span>歌曲信息</span>
@@ -19,6 +19,13 @@ import "./NowPlayingPage.css";
 
 type PlayMode = "sequence" | "shuffle" | "repeat-one";
 type FontWeightOption = "Normal" | "Medium" | "Bold";
+type NightModePreset = "light" | "medium" | "strong";
+
+const NIGHT_MODE_PRESETS: { value: NightModePreset; label: string }[] = [
+  { value: "light", label: "轻" },
+  { value: "medium", label: "中" },
+  { value: "strong", label: "强" },
+];
 type LyricAlign = "left" | "center" | "right";
 type LyricSourceMode = "local" | "online";
 type LyricProvider = "qq" | "kugou" | "netease";
@@ -27,6 +34,12 @@ interface EqualizerPreset {
   gains: number[];
 }
 
+/** 保存在数据库中的自定义均衡器预设 */
+export interface UserEqualizerPreset extends EqualizerPreset {
+  preamp: number;
+  enabled: boolean;
+}
+
 interface ParsedLrcLine {
   time: number;
   text: string;
@@ -113,8 +126,28 @@ export interface NowPlayingPageProps {
   onCloseWindow?: () => void;
   onEqualizerEnabledChange: (enabled: boolean) => void;
   onEqualizerGainChange: (index: number, gain: number) => void;
-  onEqualizerApplyPreset: (gains: number[]) => void;
+  onEqualizerApplyPreset: (gains: number[], enabled?: boolean) => void;
   onEqualizerReset: () => void;
+  /** 自定义预设，仅桌面端可保存 */
+  userEqPresets?: UserEqualizerPreset[];
+  onSaveEqPreset?: (name: string) => void;
+  onDeleteEqPreset?: (name: string) => void;
+  /** 人声消除（卡拉 OK），仅原生音频引擎支持 */
+  vocalReductionEnabled?: boolean;
+  vocalReductionStrength?: number;
+  onVocalReductionChange?: (enabled: boolean, strength: number) => void;
+  /** 夜间模式（动态压缩），仅原生音频引擎支持 */
+  nightModeEnabled?: boolean;
+  nightModePreset?: NightModePreset;
+  onNightModeChange?: (enabled: boolean, preset: NightModePreset) => void;
+  /** 流媒体歌曲的进度条波形（0-255 峰值），下载中逐步补全 */
+  waveform?: number[];
+  /** 将当前位置设为歌曲开始/结束点，或清除，仅原生音频引擎支持 */
+  onSetCuePoint?: (kind: "in" | "out" | "clear") => void;
+  /** A-B 循环：已设置的 A 点与是否在循环中 */
+  abLoopStart?: number | null;
+  abLoopActive?: boolean;
+  onSetAbLoop?: (kind: "a" | "b" | "clear") => void;
 }
 
 const FW: Record<FontWeightOption, number> = {
@@ -493,6 +526,20 @@ export default function NowPlayingPage({
   onEqualizerGainChange,
   onEqualizerApplyPreset,
   onEqualizerReset,
+  userEqPresets = [],
+  onSaveEqPreset,
+  onDeleteEqPreset,
+  vocalReductionEnabled = false,
+  vocalReductionStrength = 0.8,
+  onVocalReductionChange,
+  nightModeEnabled = false,
+  nightModePreset = "medium",
+  onNightModeChange,
+  waveform,
+  onSetCuePoint,
+  abLoopStart = null,
+  abLoopActive = false,
+  onSetAbLoop,
 }: NowPlayingPageProps) {
   const [bgColors, setBgColors] = useState<[string, string] | null>(null);
   const [showQueue, setShowQueue] = useState(false);
@@ -898,6 +945,16 @@ export default function NowPlayingPage({
     setMoreMenuOpen(false);
   };
 
+  const setCuePoint = (kind: "in" | "out" | "clear") => {
+    onSetCuePoint?.(kind);
+    setMoreMenuOpen(false);
+  };
+
+  const setAbLoop = (kind: "a" | "b" | "clear") => {
+    onSetAbLoop?.(kind);
+    setMoreMenuOpen(false);
+  };
+
   const currentProviderLabel = currentLyricProvider ? resolveLyricProviderLabel(currentLyricProvider) : "";
   const hasWindowControlButtons = Boolean(
     onToggleWindowFullscreen || onMinimizeWindow || onToggleWindowMaximize || onCloseWindow,
@@ -1251,16 +1308,29 @@ export default function NowPlayingPage({
 
         <div className="np-progress">
           <span className="np-time">{fmt(currentTime)}</span>
-          <input
-            type="range"
-            className="np-prog-slider"
-            min={0}
-            max={duration || 1}
-            step={0.1}
-            value={currentTime}
-            style={{ "--prog-pct": `${progressPercent}%` } as CSSProperties}
-            onChange={(event) => onSeek(Number(event.target.value))}
-          />
+          <div className={`np-prog-track${waveform?.length ? " has-waveform" : ""}`}>
+            {waveform?.length ? (
+              <div className="np-waveform" aria-hidden="true">
+                {waveform.map((peak, index) => (
+                  <span
+                    key={index}
+                    className={(index + 0.5) / waveform.length * 100 <= progressPercent ? "played" : undefined}
+                    style={{ height: `${Math.max(6, (peak / 255) * 100)}%` }}
+                  />
+                ))}
+              </div>
+            ) : null}
+            <input
+              type="range"
+              className="np-prog-slider"
+              min={0}
+              max={duration || 1}
+              step={0.1}
+              value={currentTime}
+              style={{ "--prog-pct": `${progressPercent}%` } as CSSProperties}
+              onChange={(event) => onSeek(Number(event.target.value))}
+            />
+          </div>
           <span className="np-time np-time-r">{fmt(duration)}</span>
         </div>
 
@@ -1356,6 +1426,46 @@ export default function NowPlayingPage({
                   <button type="button" className="np-more-item" onClick={openSettings}>
                     打开设置
                   </button>
+                  {onSetCuePoint ? (
+                    <>
+                      <div className="np-more-menu-sep" />
+                      <div className="np-more-menu-head">起止点</div>
+                      <button type="button" className="np-more-item" onClick={() => setCuePoint("in")} disabled={!hasSong}>
+                        将当前位置设为开始点
+                      </button>
+                      <button type="button" className="np-more-item" onClick={() => setCuePoint("out")} disabled={!hasSong}>
+                        将当前位置设为结束点
+                      </button>
+                      <button type="button" className="np-more-item" onClick={() => setCuePoint("clear")} disabled={!hasSong}>
+                        清除起止点
+                      </button>
+                    </>
+                  ) : null}
+                  {onSetAbLoop ? (
+                    <>
+                      <div className="np-more-menu-sep" />
+                      <div className="np-more-menu-head">A-B 循环</div>
+                      <button type="button" className="np-more-item" onClick={() => setAbLoop("a")} disabled={!hasSong}>
+                        将当前位置设为 A 点
+                      </button>
+                      <button
+                        type="button"
+                        className="np-more-item"
+                        onClick={() => setAbLoop("b")}
+                        disabled={!hasSong || abLoopStart === null}
+                      >
+                        将当前位置设为 B 点并循环
+                      </button>
+                      <button
+                        type="button"
+                        className="np-more-item"
+                        onClick={() => setAbLoop("clear")}
+                        disabled={abLoopStart === null && !abLoopActive}
+                      >
+                        取消 A-B 循环
+                      </button>
+                    </>
+                  ) : null}
                 </div>
               ) : null}
             </div>
@@ -1507,6 +1617,32 @@ export default function NowPlayingPage({
                     </button>
                   );
                 })}
+                {userEqPresets.map((preset) => {
+                  const isActive = preset.gains.every(
+                    (gain, index) => Math.abs(gain - normalizedEqGains[index]) < 0.05,
+                  );
+                  return (
+                    <span key={`user-${preset.name}`} className="np-eq-user-preset">
+                      <button
+                        type="button"
+                        className={`np-eq-preset${isActive ? " active" : ""}`}
+                        onClick={() => onEqualizerApplyPreset(preset.gains, preset.enabled)}
+                      >
+                        {preset.name}
+                      </button>
+                      {onDeleteEqPreset ? (
+                        <button
+                          type="button"
+                          className="np-eq-preset-delete"
+                          onClick={() => onDeleteEqPreset(preset.name)}
+                          aria-label={`删除预设 ${preset.name}`}
+                        >
+                          ×
+                        </button>
+                      ) : null}
+                    </span>
+                  );
+                })}
               </div>
 
               <div className={`np-eq-bands${equalizerEnabled ? "" : " off"}`}>
@@ -1533,7 +1669,68 @@ export default function NowPlayingPage({
                 ))}
               </div>
 
+              {onVocalReductionChange ? (
+                <div className={`np-eq-vocal${vocalReductionEnabled ? "" : " off"}`}>
+                  <button
+                    type="button"
+                    className={`np-eq-switch${vocalReductionEnabled ? " on" : ""}`}
+                    onClick={() => onVocalReductionChange(!vocalReductionEnabled, vocalReductionStrength)}
+                  >
+                    人声消除
+                  </button>
+                  <input
+                    type="range"
+                    className="np-eq-vocal-slider"
+                    min={0}
+                    max={1}
+                    step={0.05}
+                    value={vocalReductionStrength}
+                    disabled={!vocalReductionEnabled}
+                    onChange={(event) => onVocalReductionChange(true, Number(event.target.value))}
+                    aria-label="人声消除强度"
+                  />
+                  <span className="np-eq-gain">{Math.round(vocalReductionStrength * 100)}%</span>
+                </div>
+              ) : null}
+
+              {onNightModeChange ? (
+                <div className={`np-eq-vocal${nightModeEnabled ? "" : " off"}`}>
+                  <button
+                    type="button"
+                    className={`np-eq-switch${nightModeEnabled ? " on" : ""}`}
+                    onClick={() => onNightModeChange(!nightModeEnabled, nightModePreset)}
+                    title="压低响亮段落、提升安静段落，适合深夜收听"
+                  >
+                    夜间模式
+                  </button>
+                  {NIGHT_MODE_PRESETS.map((preset) => (
+                    <button
+                      key={preset.value}
+                      type="button"
+                      className={`np-eq-preset${nightModeEnabled && nightModePreset === preset.value ? " active" : ""}`}
+                      onClick={() => onNightModeChange(true, preset.value)}
+                    >
+                      {preset.label}
+                    </button>
+                  ))}
+                </div>
+              ) : null}
+
               <footer className="np-eq-foot">
+                {onSaveEqPreset ? (
+                  <button
+                    type="button"
+                    className="np-eq-reset"
+                    onClick={() => {
+                      const name = window.prompt("预设名称")?.trim();
+                      if (name) {
+                        onSaveEqPreset(name);
+                      }
+                    }}
+                  >
+                    保存为预设
+                  </button>
+                ) : null}
                 <button type="button" className="np-eq-reset" onClick={onEqualizerReset}>
                   重置
                 </button>